@@ -0,0 +1,374 @@
+# Messages shown by podcastctl. Translations live next to this file in
+# locales/<language>/podcastctl.ftl and only need the messages they change.
+
+## Errors
+
+error = Error: { $message }
+error-storage-rejected = Storage refused the upload: { $detail }
+error-storage-unavailable = Storage is unavailable: { $detail }
+error-mp3 = Error processing MP3 { $detail }
+error-audio = Error reading { $format } audio: { $detail }
+error-plugin = Plugin { $plugin } failed: { $detail }
+error-plugin-exited = Plugin { $plugin } exited with status { $code }
+error-wasm = WASM plugin error: { $detail }
+error-broken-links = { $count ->
+    [one] 1 link is broken
+   *[other] { $count } links are broken
+}
+error-http-status = { $url } returned HTTP { $status }
+error-feed-mismatch = { $count ->
+    [one] Feeds differ in 1 way that would affect subscribers
+   *[other] Feeds differ in { $count } ways that would affect subscribers
+}
+error-missing-env = Environment variable { $name } is not set
+error-summarize = Unable to summarize episode: { $detail }
+error-episode-not-found = No episode matches '{ $selector }'
+error-ambiguous-episode = '{ $selector }' matches more than one episode: { $episodes }
+error-clip = Unable to make clip: { $detail }
+error-promo = Unable to promote episode: { $detail }
+error-workspace-locked = Workspace { $workspace } is locked by another command (pid { $pid }); if it crashed, run `state reset --lock`
+error-channel-exists = { $path } already exists, pass --force to overwrite it
+error-trash = Unable to restore episode: { $detail }
+error-crypto = Encryption error: { $detail }
+error-validation-failed = { $count ->
+    [one] Validation found 1 problem
+   *[other] Validation found { $count } problems
+}
+error-archive-damaged = { $count ->
+    [one] 1 published object is missing or changed
+   *[other] { $count } published objects are missing or changed
+}
+error-remote-mismatch = { $count ->
+    [one] 1 published file is missing or doesn't match its episode
+   *[other] { $count } published files are missing or don't match their episodes
+}
+error-provider-checks-failed = { $count ->
+    [one] 1 storage provider check failed
+   *[other] { $count } storage provider checks failed
+}
+error-bulk-edit = Unable to edit episodes: { $detail }
+error-invalid-url = { $field } is not a usable URL ('{ $url }'): { $detail }
+error-cdn-not-serving = The CDN doesn't serve what the feed references: { $detail }
+error-not-draft = Episode { $episode } isn't a draft
+error-no-season-episodes = No episodes in season { $season }
+error-transcript = Unable to publish transcript: { $detail }
+error-chapters = Invalid chapters: { $detail }
+error-notes = Invalid show notes: { $detail }
+error-approval = Unable to approve episode: { $detail }
+error-not-allowed = Not allowed: { $detail }
+error-checklist = The pre-publish checklist isn't done: { $items }
+error-storage-config = Storage isn't set up: { $detail }
+error-art = Unable to generate art: { $detail }
+error-bundle = Invalid bundle: { $detail }
+error-editor-failed = The editor '{ $editor }' failed: { $detail }
+error-placeholder-text = Episodes still have placeholder text: { $detail }
+error-pipeline = Pipeline failed: { $detail }
+error-config-override = Unable to override channel.yaml: { $detail }
+error-invalid-date = '{ $date }' is not a release date: { $detail }
+error-unknown-timezone = Unknown timezone '{ $name }', use a name like America/New_York
+error-episode-number-taken = S{ $season }E{ $number } is already '{ $title }', pick another --episode
+error-name-template = Invalid nameTemplate: { $detail }
+error-import-failed = { $count } audio file(s) couldn't be imported; run again to retry them
+error-artwork-rejected = Artwork { $path } { $problems }
+error-audio-quality = Audio { $path } fails quality checks: { $problems }
+error-no-inbox = No inbox to watch: set `inbox.dir` in channel.yaml, or pass --dir
+error-email-rejected = Unable to import the email: { $detail }
+error-unknown-destination = Unknown destination { $name }; add it to `publishing.destinations` in channel.yaml
+error-no-podping = Podping isn't set up; add `publishing.notifications.podping` to channel.yaml
+error-hook-failed = Hook { $hook } failed: { $detail }
+error-not-private = The feed isn't private; add `publishing.private` to channel.yaml
+error-geoip = Unable to look up where listeners are: { $detail }
+error-backup = Unable to back up or restore the show: { $detail }
+error-csv-import = Unable to import the CSV: { $detail }
+error-unset-variables = channel.yaml uses environment variables that aren't set: { $names }; set them, or give defaults like `${"{"}NAME:-value{"}"}`
+error-workspace-file = Unable to use the workspace file: { $detail }
+error-channels-failed = These channels failed: { $names }
+error-index = Unable to use the episode index: { $detail }
+error-toml = Error in TOML: { $detail }
+error-convert-config = Unable to convert { $detail }
+error-guid-strategy = Unable to make the episode GUIDs: { $detail }
+error-tag = Unable to tag audio: { $detail }
+error-purge-analytics = Unable to purge the access logs: { $detail }
+error-offline = --offline was set, but { $detail }
+error-unknown = Unknown data store error
+
+## Status
+
+severity-error = error
+severity-warning = warning
+validate-summary = { $errors ->
+    [one] 1 error
+   *[other] { $errors } errors
+}, { $warnings ->
+    [one] 1 warning
+   *[other] { $warnings } warnings
+}
+already-encrypted = { $path } is already encrypted
+encrypted = Encrypted { $path }
+decrypted = Decrypted { $path }
+not-encrypted = { $path } is not encrypted
+trashed = Moved '{ $title }' to { $path }
+trashed-undo = Undo with: trash restore { $id }
+render-reminder = The published feed still lists it until you run: render-channel --upload
+approved = Approved '{ $title }' by { $approver }
+episode-published = Published '{ $title }', released { $date }
+pipeline-step = ==> Step { $step } of { $count }: { $description }
+pipeline-shell-skipped = Not running shell steps with --dry-run
+pipeline-done = Pipeline '{ $name }' done, { $count } step(s) ran
+renumber-unchanged = Every episode is already numbered in order
+renumber-count = { $count } episode(s) will be renumbered
+renumber-done = Renumbered { $count } episode(s)
+episode-edited = Saved { $path }
+edit-id-changed = error: the id can't be changed from { $id }
+edit-again = Edit it again?
+not-approved = warning: leaving out { $episode } until it is approved: { $reason }
+media-size-updated = { $episode }: media is { $new } bytes, not { $old }, updated the episode file
+media-size-unknown = warning: keeping the size of { $episode }'s media in its file: { $detail }
+truncated-field = { $field } was cut to { $length } characters
+checklist-header = Checklist for "{ $episode }":
+checklist-passed = { "  " }[x] { $item }
+checklist-failed = { "  " }[ ] { $item }
+checklist-confirm = { "  " }{ $item }? [y/N]
+restored = Restored { $path }
+wrote-draft-notes = Wrote draft notes to { $path }, review them before publishing
+wrote-file = Wrote { $path }
+image-ok = { $path } is already fine for podcast directories
+image-problem = { $path } { $problem }
+uploading = Uploading { $name }, { $size }
+uploads-total = all { $count } uploads
+uploads-summary = Uploaded { $count } object(s), { $size } in { $seconds }s, { $speed }/s
+upload-progress = { $name }: { $percent }% uploaded
+upload-resuming = Resuming the upload of { $key }, { $parts } part(s) are already uploaded
+upload-interrupted = The upload of { $key } was interrupted; run the command again to resume it
+uploaded-file = Uploaded file { $url }
+uploaded-clip = Share: { $url }
+uploaded-art = Episode image: { $url }
+uploaded-transcript = Transcript: { $url }
+uploaded-chapters = Chapters: { $url }
+uploaded-page = Podcast page: { $url }
+uploaded-json-feed = JSON Feed: { $url }
+uploaded-stylesheet = Stylesheet: { $url }
+uploaded-recent-feed = Recent episodes feed: { $url }
+uploaded-archive-feed = Archive feed: { $url }
+not-released-yet = leaving out { $episode } until it is released on { $date }
+draft-left-out = leaving out { $episode } until it is published with `publish`
+destination-left-out = leaving out { $episode }, it is published to { $destination }
+metrics-failed = warning: unable to send metrics to { $detail }
+hook-failed = warning: hook { $hook } failed: { $detail }
+hook-not-set = it needs either a `command` or a `webhook`
+hook-not-started = unable to run it: { $detail }
+hook-exited = exited with { $status }
+hook-timed-out = still running after { $seconds }s, stopped
+hook-http-status = HTTP { $status }
+private-feed-url = Subscribers use the private feed at { $url }
+podping-sent = Notified Podping about { $url }
+podping-failed = warning: { $url } was published, but Podping wasn't notified: { $detail }; run `notify podping` to try again
+websub-failed = warning: { $hub } wasn't told about { $url }: { $detail }
+error-reported = Reported as { $id }
+error-report-failed = warning: unable to send the error report: { $detail }
+snapshot-failed = warning: { $url } can't be restored if publishing fails, it couldn't be fetched: { $detail }
+feed-restored = Restored the previous { $url }
+feed-not-restored = Unable to restore the previous { $url }: { $detail }
+uploaded-season-feed = Season feed: { $url }
+cdn-waiting = { $count ->
+    [one] Waiting for the CDN to serve 1 new object
+   *[other] Waiting for the CDN to serve { $count } new objects
+}
+site-written = Wrote { $count } page(s) to { $path }
+uploaded-site-page = Site page: { $url }
+uploaded-feed = Podcast URL: { $url }
+dry-run-upload = Would upload { $key } ({ $size }, { $content_type }) to { $url }
+inbox-watching = Watching { $dir } for audio named like "2024-06-02 - Title.mp3", press Ctrl+C to stop
+inbox-created = Created draft episode "{ $title }" as { $path }
+inbox-problem = warning: { $path }: { $detail }
+import-file = ==> { $index } of { $count }: { $path }
+import-failed = { $path }: { $detail }
+import-summary = Imported { $imported } episode(s), skipped { $skipped } imported before, { $failed } failed
+audio-mismatch = warning: { $path } is { $actual } audio, not { $named }; publishing it as .{ $extension }
+tagged-file = Tagged { $path }
+artwork-warning = warning: artwork { $path } { $problem }
+uploaded-artwork = Channel image: { $url }
+audio-quality-warning = warning: audio { $path } { $problem }
+audio-quality-passed = { $path } passes the quality checks
+dry-run-episode = Would write { $path }
+dry-run-trailer = Would add the trailer to { $path }
+trailer-added = Added the trailer "{ $title }" to { $path }
+sync-added = Added { $key }
+sync-updated = Updated { $key }
+sync-summary = { $added } added, { $updated } updated, { $unchanged } unchanged
+daemon-listening = Serving JSON-RPC requests on { $address } to clients with the token in { $token }, press Ctrl+C to stop
+dashboard-listening = Serving the dashboard at { $url }
+serve-listening = Serving the feed at { $url }, press Ctrl+C to stop
+watch-waiting = Watching for changes, press Ctrl+C to stop
+prune-deleted = Deleted { $key } ({ $size })
+prune-would-delete = Would delete { $key } ({ $size })
+prune-summary = { $count } orphaned object(s), { $size }
+deleted-objects-summary = Deleted { $count } object(s), { $size }
+stats-summary = { $downloads } download(s) from { $requests } request(s) for episodes in { $files } log file(s); { $bots } request(s) from bots not counted
+purge-deleted = Deleted { $name }
+purge-deleted-dry-run = Would delete { $name }
+purge-anonymized = Anonymized { $name }
+purge-anonymized-dry-run = Would anonymize { $name }
+purge-summary = { $deleted } log(s) written before { $before } deleted, { $anonymized } anonymized
+bundle-exported = Wrote { $count } file(s), { $size }, to { $path }
+bundle-imported = Restored "{ $title }", { $episodes } episode(s) in { $count } file(s), to { $path }
+backed-up = Backed up { $key } ({ $size })
+backup-problem = warning: { $key } may be damaged: { $detail }
+backup-summary = Backed up { $count } object(s), { $size }, to { $path }; { $elsewhere } hosted elsewhere left out
+restored-object = Restored { $url }
+restore-summary = Restored { $count } object(s), { $size }
+csv-import-unchanged = No episodes would change
+csv-import-count = { $count } episode(s) will change
+csv-import-done = Updated { $count } episode(s)
+channel-heading = Channel { $name }: { $path }
+reindexed = Indexed { $count } episode(s)
+converted-file = Converted { $from } to { $to }
+convert-channel-file = Pass --channel-file { $path } from now on, and change it in podcasts.yaml if it is there
+deprecation = warning: { $message } ({ $id }). { $migration }, or silence this with --allow-deprecated { $id }
+deprecated-default-copyright = channel.yaml has no `copyright`, so the feed says "Copyright 2022"; this default will be removed
+deprecated-default-categories = channel.yaml has no `categories`, so the show is listed under Fiction; this default will be removed
+deprecation-run-migrate = Run `podcastctl migrate` to write it into channel.yaml
+migrate-nothing = Nothing in channel.yaml is deprecated
+migrated = Migrated { $ids }
+diff-not-published = { $url } isn't published yet, so every episode is new
+diff-unchanged = The local feed is the same as { $url }
+diff-channel = Channel
+diff-added = Added
+diff-removed = Removed
+diff-changed = Changed { $title }
+diff-summary = { $added } added, { $removed } removed, { $changed } changed, { $channel } channel field(s) changed
+guid-changed = warning: the GUID of "{ $title }" changes from { $published } to { $rendered }, so apps will list it as a new episode
+guid-changed-help = { $count } episode(s) change GUID since the feed was last published from here. Set `guid` in their episode files to the published GUID to keep them
+uploaded-uncompressed-feed = Uncompressed feed: { $url }
+feed-unchanged = Nothing in the feed changed since it was last published from here
+no-history-file = warning: { $path } has no git history before { $date }, using the current file
+no-history = warning: { $path } has no git history before { $date }, using the current files
+placeholder-used = warning: { $path } has no { $field }, using a placeholder
+transcripts-left-out = warning: leaving out transcripts of { $episode }: { $detail }
+notes-left-out = warning: leaving out show notes of { $episode }: { $detail }
+chapters-left-out = warning: leaving out chapters of { $episode }: { $detail }
+encrypted-left-out = warning: leaving out { $path }: it is encrypted and { $var } isn't set
+token-saved = Saved the new token as { $name } in { $path }; give subscribers the new feed URL
+token-revoked = The old token is no longer accepted
+presigned-still-valid = Presigned URLs given out before keep working until they expire, up to { $days } day(s), unless the access key that signed them is deactivated
+podcast-guid-kept = Kept podcast:guid { $guid } for { $key }; set `podcastGuid` in channel.yaml to keep it when publishing from another checkout
+yaml-rewritten = warning: unable to edit the YAML in place, so it was written again without its comments and formatting
+
+## Reports
+
+archive-missing = missing
+archive-size-changed = size is { $actual } bytes, published { $expected } bytes
+archive-etag-changed = ETag is { $actual }, published { $expected }
+archive-unreachable = unreachable: { $reason }
+archive-nothing-published = Nothing has been published from this workspace yet
+archive-ok = OK       { $key }
+archive-damaged = DAMAGED  { $key }: { $problem }
+archive-intact = All { $count } published object(s) are intact
+archive-webhook = podcast-ctl: { $damaged } of { $count } published object(s) are missing or changed
+link-channel-image = channel image
+link-channel-link = channel link
+link-episode-media = { $title } media
+link-episode-image = { $title } image
+link-episode-link = { $title } link
+link-unknown-type = unknown type
+link-ok = OK      { $label } ({ $url }): { $kind }
+link-ok-size = OK      { $label } ({ $url }): { $kind }, { $bytes } bytes
+link-broken = BROKEN  { $label } ({ $url }): { $detail }
+remote-ok = OK        { $label } ({ $url })
+remote-mismatch = MISMATCH  { $label } ({ $url }): { $bytes } bytes, the episode file says { $expected }
+remote-unknown = UNKNOWN   { $label } ({ $url }): no Content-Length
+remote-missing = MISSING   { $label } ({ $url }): { $detail }
+bulk-no-changes = No episodes would change
+bulk-will-change = { $count } episode(s) will change
+bulk-updated = Updated { $count } episode(s)
+workspace-root = Workspace: { $path }
+workspace-empty = (empty, created by the first command that stores state)
+workspace-version = layout version:
+workspace-lock = lock:
+workspace-lock-held = held by pid { $pid }
+workspace-lock-free = free
+workspace-journal = journal:
+workspace-journal-objects = { $count } published object(s)
+workspace-usage = { $count } file(s), { $size }
+fixtures-wrote = Wrote { $channel } with { $count } episode(s) to { $path }
+fixtures-wrote-audio = Wrote silent audio to { $path }
+provider-pass = PASS  { $check }
+provider-fail = FAIL  { $check }: { $detail }
+provider-upload = upload { $key }
+provider-public-url = public URL { $url }
+provider-content-type = content type
+provider-byte-ranges = byte ranges
+provider-list = list
+provider-delete = delete
+provider-contents-differ = the contents differ from what was uploaded
+provider-forbidden = HTTP 403; the object isn't public, check objectOptions.acl and the bucket policy
+provider-status = HTTP { $status }
+provider-served-as = served as '{ $served }', expected '{ $expected }'
+provider-wrong-range = the range has the wrong bytes
+provider-no-ranges = HTTP { $status } instead of 206; podcast apps won't be able to seek
+provider-listed-size = listed as { $bytes } bytes, not { $expected }
+provider-not-listed = { $key } isn't listed
+provider-still-listed = { $key } is still listed
+info-channel-file = Channel file:
+info-environment-file = Environment file:
+info-none = (none)
+info-language = Language:
+info-modes = Modes:
+info-mode-offline = offline
+info-mode-dry-run = dry run
+info-mode-screen-reader = screen reader
+info-overrides = Overrides:
+info-publishing = Publishing:
+info-type = type:
+info-bucket = bucket:
+info-endpoint = endpoint:
+info-region = region:
+info-region-default = (from AWS_REGION or the AWS profile)
+info-profile = profile:
+info-profile-default = (from AWS_PROFILE or the default chain)
+info-account = account:
+info-container = container:
+info-path = path:
+info-host = host:
+info-prefix = prefix:
+info-uploads = uploads:
+info-feed = feed:
+info-environment = Environment:
+info-not-set = (not set)
+info-from = (from { $setting })
+info-configuration = Configuration:
+
+## init
+
+init-describe = Describe the show. Press enter to accept the value in [brackets].
+init-title = Title
+init-description = Description
+init-subtitle = Subtitle
+init-summary = Summary
+init-explicit = Explicit content? (y/n)
+init-yes = y
+init-image = Artwork URL (square, 1400-3000px)
+init-link = Website (optional)
+init-owner-name = Owner name
+init-owner-email = Owner email
+init-keywords = Keywords, comma separated (optional)
+init-category = Apple Podcasts category, like Technology or Society & Culture > Documentary
+init-unknown-category = { $category } is not an Apple Podcasts category.
+init-language = Language code, like en-us or de
+init-copyright = Copyright notice
+init-episodic = Can episodes be listened to in any order? (y/n)
+init-publishing = Where episodes and the feed are published (any S3 compatible storage).
+init-bucket = Bucket
+init-region = Region
+init-endpoint = Endpoint
+init-prefix = Prefix inside the bucket
+init-next-step = Add an episode with: podcast-ctl -c { $path } create-episode <file.mp3> --date YYYY-MM-DD --title <title>
+confirm-write = Write these changes?
+confirm-choices = [y/N]
+nothing-written = Nothing written
+prompt-required = { $label } is required.
+prompt-no-answer = no answer for '{ $label }'
+create-description = Episode description
+create-summary = One-line summary
+create-link = Episode web page (optional)
+placeholders-written = warning: no { $fields } given, fill in the placeholders before publishing
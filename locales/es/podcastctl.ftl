@@ -0,0 +1,374 @@
+# Mensajes de podcastctl en español. Los mensajes que faltan aquí se
+# muestran en inglés.
+
+## Errores
+
+error = Error: { $message }
+error-storage-rejected = El almacenamiento rechazó la subida: { $detail }
+error-storage-unavailable = El almacenamiento no está disponible: { $detail }
+error-mp3 = Error al procesar el MP3 { $detail }
+error-audio = Error al leer el audio { $format }: { $detail }
+error-plugin = El plugin { $plugin } falló: { $detail }
+error-plugin-exited = El plugin { $plugin } terminó con el estado { $code }
+error-wasm = Error del plugin WASM: { $detail }
+error-broken-links = { $count ->
+    [one] Hay 1 enlace roto
+   *[other] Hay { $count } enlaces rotos
+}
+error-http-status = { $url } respondió HTTP { $status }
+error-feed-mismatch = { $count ->
+    [one] Los feeds difieren en 1 aspecto que afectaría a los suscriptores
+   *[other] Los feeds difieren en { $count } aspectos que afectarían a los suscriptores
+}
+error-missing-env = La variable de entorno { $name } no está definida
+error-summarize = No se pudo resumir el episodio: { $detail }
+error-episode-not-found = Ningún episodio coincide con '{ $selector }'
+error-ambiguous-episode = '{ $selector }' coincide con más de un episodio: { $episodes }
+error-clip = No se pudo crear el clip: { $detail }
+error-promo = No se pudo promocionar el episodio: { $detail }
+error-workspace-locked = Otro comando tiene bloqueado el espacio de trabajo { $workspace } (pid { $pid }); si se interrumpió, ejecuta `state reset --lock`
+error-channel-exists = { $path } ya existe, usa --force para sobrescribirlo
+error-trash = No se pudo restaurar el episodio: { $detail }
+error-crypto = Error de cifrado: { $detail }
+error-validation-failed = { $count ->
+    [one] La validación encontró 1 problema
+   *[other] La validación encontró { $count } problemas
+}
+error-archive-damaged = { $count ->
+    [one] Falta 1 objeto publicado o ha cambiado
+   *[other] Faltan { $count } objetos publicados o han cambiado
+}
+error-remote-mismatch = { $count ->
+    [one] Falta 1 archivo publicado o no coincide con su episodio
+   *[other] Faltan { $count } archivos publicados o no coinciden con sus episodios
+}
+error-provider-checks-failed = { $count ->
+    [one] Falló 1 comprobación del proveedor de almacenamiento
+   *[other] Fallaron { $count } comprobaciones del proveedor de almacenamiento
+}
+error-bulk-edit = No se pudieron editar los episodios: { $detail }
+error-invalid-url = { $field } no es una URL válida ('{ $url }'): { $detail }
+error-cdn-not-serving = La CDN no sirve lo que el feed referencia: { $detail }
+error-not-draft = El episodio { $episode } no es un borrador
+error-no-season-episodes = No hay episodios en la temporada { $season }
+error-transcript = No se pudo publicar la transcripción: { $detail }
+error-chapters = Capítulos no válidos: { $detail }
+error-notes = Notas del episodio no válidas: { $detail }
+error-approval = No se pudo aprobar el episodio: { $detail }
+error-not-allowed = No permitido: { $detail }
+error-checklist = La lista de verificación previa a la publicación no está completa: { $items }
+error-storage-config = El almacenamiento no está configurado: { $detail }
+error-art = No se pudo generar la imagen: { $detail }
+error-bundle = Paquete no válido: { $detail }
+error-editor-failed = El editor '{ $editor }' falló: { $detail }
+error-placeholder-text = Hay episodios con texto de relleno: { $detail }
+error-pipeline = El pipeline falló: { $detail }
+error-config-override = No se pudo sobrescribir channel.yaml: { $detail }
+error-invalid-date = '{ $date }' no es una fecha de publicación: { $detail }
+error-unknown-timezone = Zona horaria desconocida '{ $name }', usa un nombre como America/New_York
+error-episode-number-taken = S{ $season }E{ $number } ya es '{ $title }', elige otro --episode
+error-name-template = nameTemplate no válido: { $detail }
+error-import-failed = No se pudieron importar { $count } archivo(s) de audio; vuelve a ejecutarlo para reintentarlos
+error-artwork-rejected = La imagen { $path } no sirve: { $problems }
+error-audio-quality = El audio { $path } no pasa los controles de calidad: { $problems }
+error-no-inbox = No hay bandeja de entrada que vigilar: define `inbox.dir` en channel.yaml o pasa --dir
+error-email-rejected = No se pudo importar el correo: { $detail }
+error-unknown-destination = Destino desconocido { $name }; añádelo a `publishing.destinations` en channel.yaml
+error-no-podping = Podping no está configurado; añade `publishing.notifications.podping` a channel.yaml
+error-hook-failed = Falló el hook { $hook }: { $detail }
+error-not-private = El feed no es privado; añade `publishing.private` a channel.yaml
+error-geoip = No se pudo averiguar dónde están los oyentes: { $detail }
+error-backup = No se pudo respaldar o restaurar el programa: { $detail }
+error-csv-import = No se pudo importar el CSV: { $detail }
+error-unset-variables = channel.yaml usa variables de entorno que no están definidas: { $names }; defínelas, o da valores por defecto como `${"{"}NAME:-valor{"}"}`
+error-workspace-file = No se pudo usar el archivo del espacio de trabajo: { $detail }
+error-channels-failed = Fallaron estos canales: { $names }
+error-index = No se pudo usar el índice de episodios: { $detail }
+error-toml = Error en TOML: { $detail }
+error-convert-config = No se pudo convertir { $detail }
+error-guid-strategy = No se pudieron generar los GUID de los episodios: { $detail }
+error-tag = No se pudo etiquetar el audio: { $detail }
+error-purge-analytics = No se pudieron purgar los registros de acceso: { $detail }
+error-offline = Se indicó --offline, pero { $detail }
+error-unknown = Error desconocido del almacenamiento
+
+## Estado
+
+severity-error = error
+severity-warning = aviso
+validate-summary = { $errors ->
+    [one] 1 error
+   *[other] { $errors } errores
+}, { $warnings ->
+    [one] 1 aviso
+   *[other] { $warnings } avisos
+}
+already-encrypted = { $path } ya está cifrado
+encrypted = Cifrado { $path }
+decrypted = Descifrado { $path }
+not-encrypted = { $path } no está cifrado
+trashed = Se movió '{ $title }' a { $path }
+trashed-undo = Para deshacerlo: trash restore { $id }
+render-reminder = El feed publicado lo sigue mostrando hasta que ejecutes: render-channel --upload
+approved = '{ $title }' aprobado por { $approver }
+episode-published = '{ $title }' publicado, con fecha { $date }
+pipeline-step = ==> Paso { $step } de { $count }: { $description }
+pipeline-shell-skipped = Los pasos de shell no se ejecutan con --dry-run
+pipeline-done = Pipeline '{ $name }' terminado, { $count } paso(s) ejecutado(s)
+renumber-unchanged = Todos los episodios ya están numerados en orden
+renumber-count = Se renumerarán { $count } episodio(s)
+renumber-done = { $count } episodio(s) renumerado(s)
+episode-edited = { $path } guardado
+edit-id-changed = error: el id no se puede cambiar de { $id }
+edit-again = ¿Editarlo de nuevo?
+not-approved = aviso: se omite { $episode } hasta que se apruebe: { $reason }
+media-size-updated = { $episode }: el audio ocupa { $new } bytes, no { $old }; se actualizó el archivo del episodio
+media-size-unknown = aviso: se mantiene el tamaño del audio de { $episode } de su archivo: { $detail }
+truncated-field = { $field } se recortó a { $length } caracteres
+checklist-header = Lista de verificación de "{ $episode }":
+checklist-passed = { "  " }[x] { $item }
+checklist-failed = { "  " }[ ] { $item }
+checklist-confirm = { "  " }¿{ $item }? [s/N]
+restored = Restaurado { $path }
+wrote-draft-notes = Se escribió un borrador de las notas en { $path }, revísalo antes de publicar
+wrote-file = Escrito { $path }
+image-ok = { $path } ya es válida para los directorios de podcasts
+image-problem = { $path } { $problem }
+uploading = Subiendo { $name }, { $size }
+uploads-total = las { $count } subidas
+uploads-summary = { $count } objeto(s) subido(s), { $size } en { $seconds } s, { $speed }/s
+upload-progress = { $name }: { $percent }% subido
+upload-resuming = Reanudando la subida de { $key }, { $parts } parte(s) ya están subidas
+upload-interrupted = La subida de { $key } se interrumpió; ejecuta el comando de nuevo para reanudarla
+uploaded-file = Archivo subido { $url }
+uploaded-clip = Compartir: { $url }
+uploaded-art = Imagen del episodio: { $url }
+uploaded-transcript = Transcripción: { $url }
+uploaded-chapters = Capítulos: { $url }
+uploaded-page = Página del podcast: { $url }
+uploaded-json-feed = JSON Feed: { $url }
+uploaded-stylesheet = Hoja de estilos: { $url }
+uploaded-recent-feed = Feed de episodios recientes: { $url }
+uploaded-archive-feed = Feed de archivo: { $url }
+not-released-yet = se omite { $episode } hasta su publicación el { $date }
+draft-left-out = se omite { $episode } hasta que se publique con `publish`
+destination-left-out = se omite { $episode }, se publica en { $destination }
+metrics-failed = aviso: no se pudieron enviar las métricas a { $detail }
+hook-failed = aviso: falló el hook { $hook }: { $detail }
+hook-not-set = necesita un `command` o un `webhook`
+hook-not-started = no se pudo ejecutar: { $detail }
+hook-exited = terminó con { $status }
+hook-timed-out = seguía en ejecución tras { $seconds } s, se detuvo
+hook-http-status = HTTP { $status }
+private-feed-url = Los suscriptores usan el feed privado en { $url }
+podping-sent = Se avisó a Podping de { $url }
+podping-failed = aviso: { $url } se publicó, pero no se pudo avisar a Podping: { $detail }; ejecuta `notify podping` para reintentarlo
+websub-failed = aviso: no se pudo avisar a { $hub } de { $url }: { $detail }
+error-reported = Informe enviado como { $id }
+error-report-failed = aviso: no se pudo enviar el informe del error: { $detail }
+snapshot-failed = aviso: { $url } no se podrá restaurar si la publicación falla, no se pudo obtener: { $detail }
+feed-restored = Se restauró el { $url } anterior
+feed-not-restored = No se pudo restaurar el { $url } anterior: { $detail }
+uploaded-season-feed = Feed de temporada: { $url }
+cdn-waiting = { $count ->
+    [one] Esperando a que la CDN sirva 1 objeto nuevo
+   *[other] Esperando a que la CDN sirva { $count } objetos nuevos
+}
+site-written = Se escribieron { $count } página(s) en { $path }
+uploaded-site-page = Página del sitio: { $url }
+uploaded-feed = URL del podcast: { $url }
+dry-run-upload = Se subiría { $key } ({ $size }, { $content_type }) a { $url }
+inbox-watching = Vigilando { $dir } en busca de audio con nombres como "2024-06-02 - Título.mp3", pulsa Ctrl+C para detener
+inbox-created = Creado el episodio borrador "{ $title }" como { $path }
+inbox-problem = aviso: { $path }: { $detail }
+import-file = ==> { $index } de { $count }: { $path }
+import-failed = { $path }: { $detail }
+import-summary = { $imported } episodio(s) importado(s), { $skipped } omitido(s) por estar ya importados, { $failed } fallido(s)
+audio-mismatch = aviso: { $path } es audio { $actual }, no { $named }; se publica como .{ $extension }
+tagged-file = Se etiquetó { $path }
+artwork-warning = aviso: la imagen { $path } { $problem }
+uploaded-artwork = Imagen del canal: { $url }
+audio-quality-warning = aviso: el audio { $path } { $problem }
+audio-quality-passed = { $path } pasa los controles de calidad
+dry-run-episode = Se escribiría { $path }
+dry-run-trailer = Se añadiría el tráiler a { $path }
+trailer-added = Se añadió el tráiler "{ $title }" a { $path }
+sync-added = Añadido { $key }
+sync-updated = Actualizado { $key }
+sync-summary = { $added } añadidos, { $updated } actualizados, { $unchanged } sin cambios
+daemon-listening = Atendiendo peticiones JSON-RPC en { $address } a clientes con el token de { $token }, pulsa Ctrl+C para detener
+dashboard-listening = Sirviendo el panel en { $url }
+serve-listening = Sirviendo el feed en { $url }, pulsa Ctrl+C para detener
+watch-waiting = Esperando cambios, pulsa Ctrl+C para detener
+prune-deleted = Eliminado { $key } ({ $size })
+prune-would-delete = Se eliminaría { $key } ({ $size })
+prune-summary = { $count } objeto(s) huérfano(s), { $size }
+deleted-objects-summary = { $count } objeto(s) eliminado(s), { $size }
+stats-summary = { $downloads } descarga(s) de { $requests } petición(es) de episodios en { $files } archivo(s) de registro; { $bots } petición(es) de bots no contada(s)
+purge-deleted = Se borró { $name }
+purge-deleted-dry-run = Se borraría { $name }
+purge-anonymized = Se anonimizó { $name }
+purge-anonymized-dry-run = Se anonimizaría { $name }
+purge-summary = { $deleted } registro(s) escritos antes del { $before } borrados, { $anonymized } anonimizados
+bundle-exported = Se escribieron { $count } archivo(s), { $size }, en { $path }
+bundle-imported = Se restauró "{ $title }", { $episodes } episodio(s) en { $count } archivo(s), en { $path }
+backed-up = Se respaldó { $key } ({ $size })
+backup-problem = aviso: { $key } puede estar dañado: { $detail }
+backup-summary = Se respaldaron { $count } objeto(s), { $size }, en { $path }; { $elsewhere } alojado(s) en otro sitio no incluido(s)
+restored-object = Se restauró { $url }
+restore-summary = Se restauraron { $count } objeto(s), { $size }
+csv-import-unchanged = Ningún episodio cambiaría
+csv-import-count = { $count } episodio(s) cambiarán
+csv-import-done = Se actualizaron { $count } episodio(s)
+channel-heading = Canal { $name }: { $path }
+reindexed = Se indexaron { $count } episodio(s)
+converted-file = Se convirtió { $from } en { $to }
+convert-channel-file = Usa --channel-file { $path } de ahora en adelante, y cámbialo en podcasts.yaml si está ahí
+deprecation = aviso: { $message } ({ $id }). { $migration }, o silencia esto con --allow-deprecated { $id }
+deprecated-default-copyright = channel.yaml no tiene `copyright`, así que el feed dice "Copyright 2022"; este valor predeterminado se eliminará
+deprecated-default-categories = channel.yaml no tiene `categories`, así que el programa aparece en Fiction; este valor predeterminado se eliminará
+deprecation-run-migrate = Ejecuta `podcastctl migrate` para escribirlo en channel.yaml
+migrate-nothing = Nada en channel.yaml está obsoleto
+migrated = Migrado: { $ids }
+diff-not-published = { $url } aún no está publicado, así que todos los episodios son nuevos
+diff-unchanged = El feed local es igual a { $url }
+diff-channel = Canal
+diff-added = Añadidos
+diff-removed = Eliminados
+diff-changed = Cambiado { $title }
+diff-summary = { $added } añadido(s), { $removed } eliminado(s), { $changed } cambiado(s), { $channel } campo(s) del canal cambiado(s)
+guid-changed = aviso: el GUID de "{ $title }" cambia de { $published } a { $rendered }, así que las aplicaciones lo mostrarán como un episodio nuevo
+guid-changed-help = { $count } episodio(s) cambian de GUID desde la última publicación desde aquí. Pon `guid` en sus archivos de episodio con el GUID publicado para conservarlos
+uploaded-uncompressed-feed = Feed sin comprimir: { $url }
+feed-unchanged = Nada en el feed cambió desde la última publicación desde aquí
+no-history-file = aviso: { $path } no tiene historial de git antes de { $date }, se usa el archivo actual
+no-history = aviso: { $path } no tiene historial de git antes de { $date }, se usan los archivos actuales
+placeholder-used = aviso: { $path } no tiene { $field }, se usa un texto provisional
+transcripts-left-out = aviso: se omiten las transcripciones de { $episode }: { $detail }
+notes-left-out = aviso: se omiten las notas de { $episode }: { $detail }
+chapters-left-out = aviso: se omiten los capítulos de { $episode }: { $detail }
+encrypted-left-out = aviso: se omite { $path }: está cifrado y { $var } no está definida
+token-saved = Se guardó el nuevo token como { $name } en { $path }; da a los suscriptores la nueva URL del feed
+token-revoked = El token anterior ya no se acepta
+presigned-still-valid = Las URL prefirmadas entregadas antes siguen funcionando hasta que caduquen, hasta { $days } día(s), salvo que se desactive la clave de acceso que las firmó
+podcast-guid-kept = Se guarda podcast:guid { $guid } para { $key }; define `podcastGuid` en channel.yaml para conservarlo al publicar desde otra copia
+yaml-rewritten = aviso: no se pudo editar el YAML en su sitio, así que se reescribió sin sus comentarios ni su formato
+
+## Informes
+
+archive-missing = no está
+archive-size-changed = ocupa { $actual } bytes, se publicó con { $expected } bytes
+archive-etag-changed = el ETag es { $actual }, se publicó con { $expected }
+archive-unreachable = inaccesible: { $reason }
+archive-nothing-published = Todavía no se ha publicado nada desde este espacio de trabajo
+archive-ok = OK       { $key }
+archive-damaged = DAÑADO   { $key }: { $problem }
+archive-intact = Los { $count } objeto(s) publicados están intactos
+archive-webhook = podcast-ctl: faltan o han cambiado { $damaged } de { $count } objeto(s) publicados
+link-channel-image = imagen del canal
+link-channel-link = enlace del canal
+link-episode-media = audio de { $title }
+link-episode-image = imagen de { $title }
+link-episode-link = enlace de { $title }
+link-unknown-type = tipo desconocido
+link-ok = OK      { $label } ({ $url }): { $kind }
+link-ok-size = OK      { $label } ({ $url }): { $kind }, { $bytes } bytes
+link-broken = ROTO    { $label } ({ $url }): { $detail }
+remote-ok = OK        { $label } ({ $url })
+remote-mismatch = DISTINTO  { $label } ({ $url }): { $bytes } bytes, el archivo del episodio indica { $expected }
+remote-unknown = DESCONOC. { $label } ({ $url }): sin Content-Length
+remote-missing = FALTA     { $label } ({ $url }): { $detail }
+bulk-no-changes = Ningún episodio cambiaría
+bulk-will-change = Cambiarán { $count } episodio(s)
+bulk-updated = Se actualizaron { $count } episodio(s)
+workspace-root = Espacio de trabajo: { $path }
+workspace-empty = (vacío, lo crea el primer comando que guarda estado)
+workspace-version = versión:
+workspace-lock = bloqueo:
+workspace-lock-held = en manos del pid { $pid }
+workspace-lock-free = libre
+workspace-journal = registro:
+workspace-journal-objects = { $count } objeto(s) publicados
+workspace-usage = { $count } archivo(s), { $size }
+fixtures-wrote = Se escribió { $channel } con { $count } episodio(s) en { $path }
+fixtures-wrote-audio = Se escribió audio en silencio en { $path }
+provider-pass = BIEN  { $check }
+provider-fail = FALLO { $check }: { $detail }
+provider-upload = subir { $key }
+provider-public-url = URL pública { $url }
+provider-content-type = tipo de contenido
+provider-byte-ranges = rangos de bytes
+provider-list = listar
+provider-delete = borrar
+provider-contents-differ = el contenido no coincide con lo que se subió
+provider-forbidden = HTTP 403; el objeto no es público, revisa objectOptions.acl y la política del bucket
+provider-status = HTTP { $status }
+provider-served-as = se sirve como '{ $served }', se esperaba '{ $expected }'
+provider-wrong-range = el rango tiene bytes incorrectos
+provider-no-ranges = HTTP { $status } en lugar de 206; las apps de podcasts no podrán avanzar ni retroceder
+provider-listed-size = aparece con { $bytes } bytes, no { $expected }
+provider-not-listed = { $key } no aparece en la lista
+provider-still-listed = { $key } sigue apareciendo en la lista
+info-channel-file = Archivo del canal:
+info-environment-file = Archivo de entorno:
+info-none = (ninguno)
+info-language = Idioma:
+info-modes = Modos:
+info-mode-offline = sin conexión
+info-mode-dry-run = simulación
+info-mode-screen-reader = lector de pantalla
+info-overrides = Sustituciones:
+info-publishing = Publicación:
+info-type = tipo:
+info-bucket = bucket:
+info-endpoint = endpoint:
+info-region = región:
+info-region-default = (de AWS_REGION o del perfil de AWS)
+info-profile = perfil:
+info-profile-default = (de AWS_PROFILE o de la cadena predeterminada)
+info-account = cuenta:
+info-container = contenedor:
+info-path = ruta:
+info-host = host:
+info-prefix = prefijo:
+info-uploads = subidas:
+info-feed = feed:
+info-environment = Entorno:
+info-not-set = (sin definir)
+info-from = (de { $setting })
+info-configuration = Configuración:
+
+## init
+
+init-describe = Describe el programa. Pulsa Intro para aceptar el valor entre [corchetes].
+init-title = Título
+init-description = Descripción
+init-subtitle = Subtítulo
+init-summary = Resumen
+init-explicit = ¿Contenido explícito? (s/n)
+init-yes = s
+init-image = URL de la portada (cuadrada, 1400-3000px)
+init-link = Sitio web (opcional)
+init-owner-name = Nombre del propietario
+init-owner-email = Correo del propietario
+init-keywords = Palabras clave, separadas por comas (opcional)
+init-category = Categoría de Apple Podcasts, como Technology o Society & Culture > Documentary
+init-unknown-category = { $category } no es una categoría de Apple Podcasts.
+init-language = Código de idioma, como es o en-us
+init-copyright = Aviso de copyright
+init-episodic = ¿Se pueden escuchar los episodios en cualquier orden? (s/n)
+init-publishing = Dónde se publican los episodios y el feed (cualquier almacenamiento compatible con S3).
+init-bucket = Bucket
+init-region = Región
+init-endpoint = Endpoint
+init-prefix = Prefijo dentro del bucket
+init-next-step = Añade un episodio con: podcast-ctl -c { $path } create-episode <archivo.mp3> --date AAAA-MM-DD --title <título>
+confirm-write = ¿Escribir estos cambios?
+confirm-choices = [y/N]
+nothing-written = No se escribió nada
+prompt-required = { $label } es obligatorio.
+prompt-no-answer = sin respuesta para '{ $label }'
+create-description = Descripción del episodio
+create-summary = Resumen en una línea
+create-link = Página web del episodio (opcional)
+placeholders-written = aviso: sin { $fields }, completa el texto de relleno antes de publicar
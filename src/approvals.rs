@@ -0,0 +1,286 @@
+//! Editorial sign-off before an episode is published.
+//!
+//! Reviewers are listed in an episode's `approvedBy` as `Name <email>`, a
+//! name, or an email. With `approvals.required` set in `channel.yaml`,
+//! `render-channel --strict` leaves out episodes without enough approvals
+//! from `approvals.approvers`. With `approvals.requireSignoff`, an approval
+//! only counts when a git commit touching the episode file carries a
+//! matching `Signed-off-by:` trailer (`git commit -s`).
+
+use crate::config::{ApprovalConfig, ChannelConfig, Episode};
+use crate::i18n::t;
+use crate::output::outln;
+use crate::CliError;
+use log::debug;
+use std::path::Path;
+use std::process::Command;
+
+/// Why the episode can't be published yet, or `None` when it is approved.
+pub fn missing_approval(
+    config: &ApprovalConfig,
+    episode_path: &Path,
+    episode: &Episode,
+) -> Option<String> {
+    if config.required == 0 {
+        return None;
+    }
+
+    // The same person approving twice still counts once
+    let mut approvals: Vec<&str> = Vec::new();
+    for approval in &episode.approved_by {
+        let counted = approvals.iter().any(|other| same_person(other, approval));
+        if is_approver(config, approval) && !counted {
+            approvals.push(approval);
+        }
+    }
+    let allowed = approvals.len();
+
+    if config.require_signoff {
+        let signoffs = signoffs(episode_path);
+        approvals.retain(|approval| {
+            signoffs
+                .iter()
+                .any(|signoff| same_person(signoff, approval))
+        });
+    }
+
+    if approvals.len() >= config.required {
+        None
+    } else if config.require_signoff && allowed >= config.required {
+        Some(format!(
+            "{} of {} approval(s) are signed off in git",
+            approvals.len(),
+            config.required
+        ))
+    } else {
+        Some(format!(
+            "{} of {} approval(s)",
+            approvals.len(),
+            config.required
+        ))
+    }
+}
+
+/// Whether `approver` may approve episodes.
+pub fn is_approver(config: &ApprovalConfig, approver: &str) -> bool {
+    config.approvers.is_empty()
+        || config
+            .approvers
+            .iter()
+            .any(|allowed| same_person(allowed, approver))
+}
+
+/// `approve`: add `approver` to the `approvedBy` of the episode
+/// `selector` picks.
+pub fn approve(
+    episode_dir: &Path,
+    channel_config: &ChannelConfig,
+    selector: &str,
+    approver: &str,
+) -> Result<(), CliError> {
+    let (path, mut episode) =
+        crate::find_episode(episode_dir, selector, channel_config.channel.tz()?)?;
+    if !is_approver(&channel_config.approvals, approver) {
+        return Err(CliError::ApprovalError(format!(
+            "{} is not one of the approvers in channel.yaml",
+            approver
+        )));
+    }
+    if !episode.approved_by.iter().any(|by| by == approver) {
+        episode.approved_by.push(approver.to_owned());
+        crate::save_episode(&path, &episode, channel_config)?;
+    }
+    outln!(
+        "{}",
+        t!("approved", title = episode.title.as_str(), approver = approver)
+    );
+    Ok(())
+}
+
+/// `Name <email>`, `Name`, and `email` all match on either part.
+pub fn same_person(a: &str, b: &str) -> bool {
+    let (a_name, a_email) = identity(a);
+    let (b_name, b_email) = identity(b);
+    let matches = |x: Option<&str>, y: Option<&str>| match (x, y) {
+        (Some(x), Some(y)) => x.eq_ignore_ascii_case(y),
+        _ => false,
+    };
+    matches(a_name, b_name) || matches(a_email, b_email)
+}
+
+fn identity(text: &str) -> (Option<&str>, Option<&str>) {
+    let text = text.trim();
+    match text.split_once('<') {
+        Some((name, email)) => {
+            let name = name.trim();
+            (
+                Some(name).filter(|name| !name.is_empty()),
+                Some(email.trim_end_matches('>').trim()),
+            )
+        }
+        None if text.contains('@') => (None, Some(text)),
+        None => (Some(text), None),
+    }
+}
+
+/// The `Signed-off-by:` trailers of every commit that touched `path`.
+fn signoffs(path: &Path) -> Vec<String> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let name = path.file_name().unwrap_or_default();
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "log",
+            "--format=%(trailers:key=Signed-off-by,valueonly)",
+            "--",
+        ])
+        .arg(name)
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.trim().to_owned())
+            .filter(|line| !line.is_empty())
+            .collect(),
+        Ok(output) => {
+            debug!("git failed: {}", String::from_utf8_lossy(&output.stderr));
+            Vec::new()
+        }
+        Err(e) => {
+            debug!("Unable to run git: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn approvals(required: usize, approvers: &[&str], require_signoff: bool) -> ApprovalConfig {
+        ApprovalConfig {
+            required,
+            approvers: approvers
+                .iter()
+                .map(|approver| approver.to_string())
+                .collect(),
+            require_signoff,
+        }
+    }
+
+    fn approved_by(approvers: &[&str]) -> Episode {
+        let mut episode = Episode::make_test("episode");
+        episode.approved_by = approvers
+            .iter()
+            .map(|approver| approver.to_string())
+            .collect();
+        episode
+    }
+
+    /// An episode file committed to a new git repository with `message`.
+    fn committed(message: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("approvals-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("2022-09-01.yaml");
+        fs::write(&path, "title: Episode\n").unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(&dir)
+                .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?} failed", args);
+        };
+        git(&["init", "--quiet"]);
+        git(&["add", "2022-09-01.yaml"]);
+        git(&["commit", "--quiet", "-m", message]);
+        path
+    }
+
+    #[test]
+    fn same_person_matches_on_name_or_email() {
+        assert!(same_person("Ann Lee <ann@example.com>", "ann lee"));
+        assert!(same_person("Ann Lee <ann@example.com>", "ANN@example.com"));
+        assert!(same_person(
+            "Ann <ann@example.com>",
+            "A. Lee <ann@example.com>"
+        ));
+        assert!(!same_person("Ann Lee", "ann@example.com"));
+        assert!(!same_person("<ann@example.com>", "Ann Lee"));
+        assert!(!same_person(
+            "Ann Lee <ann@example.com>",
+            "Bob <bob@example.com>"
+        ));
+    }
+
+    #[test]
+    fn approvers_are_matched_by_name_only_or_email_only() {
+        let config = approvals(1, &["Ann Lee <ann@example.com>", "bob@example.com"], false);
+        assert!(is_approver(&config, "Ann Lee"));
+        assert!(is_approver(&config, "ann@example.com"));
+        assert!(is_approver(&config, "Robert <bob@example.com>"));
+        assert!(!is_approver(&config, "Bob"));
+        assert!(is_approver(&ApprovalConfig::default(), "anyone"));
+    }
+
+    #[test]
+    fn duplicate_approvers_count_once() {
+        let config = approvals(2, &[], false);
+        let path = Path::new("2022-09-01.yaml");
+        let twice = approved_by(&["Ann Lee <ann@example.com>", "ann@example.com", "ANN LEE"]);
+        assert_eq!(
+            missing_approval(&config, path, &twice).as_deref(),
+            Some("1 of 2 approval(s)")
+        );
+        let two = approved_by(&["Ann Lee <ann@example.com>", "Bob"]);
+        assert_eq!(missing_approval(&config, path, &two), None);
+    }
+
+    #[test]
+    fn only_approvers_count() {
+        let config = approvals(1, &["Ann Lee"], false);
+        let path = Path::new("2022-09-01.yaml");
+        assert!(missing_approval(&config, path, &approved_by(&["Bob"])).is_some());
+        assert_eq!(
+            missing_approval(&config, path, &approved_by(&["ann lee"])),
+            None
+        );
+        assert_eq!(
+            missing_approval(&approvals(0, &[], false), path, &approved_by(&[])),
+            None
+        );
+    }
+
+    #[test]
+    fn signoffs_must_match_an_approval() {
+        let config = approvals(1, &[], true);
+        let episode = approved_by(&["Ann Lee <ann@example.com>"]);
+
+        let signed = committed("Approve episode\n\nSigned-off-by: A. Lee <ann@example.com>");
+        assert_eq!(signoffs(&signed), vec!["A. Lee <ann@example.com>"]);
+        assert_eq!(missing_approval(&config, &signed, &episode), None);
+
+        let unsigned = committed("Approve episode");
+        assert_eq!(
+            missing_approval(&config, &unsigned, &episode).as_deref(),
+            Some("0 of 1 approval(s) are signed off in git")
+        );
+
+        let someone_else = committed("Approve episode\n\nSigned-off-by: Bob <bob@example.com>");
+        assert!(missing_approval(&config, &someone_else, &episode).is_some());
+
+        for path in [signed, unsigned, someone_else] {
+            fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use crate::config::*;
+use crate::http::HttpClient;
+use crate::i18n::t;
+use crate::links::check_url;
+use crate::output::{self, outln};
+use crate::state::{JournalEntry, Workspace};
+use crate::CliError;
+use futures::{stream, StreamExt};
+use log::info;
+use reqwest::{Method, StatusCode};
+use serde::Serialize;
+use serde_json::json;
+use std::fmt;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum ArchiveProblem {
+    Missing,
+    SizeChanged { expected: u64, actual: u64 },
+    EtagChanged { expected: String, actual: String },
+    Unreachable { reason: String },
+}
+
+impl fmt::Display for ArchiveProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveProblem::Missing => write!(f, "{}", t!("archive-missing")),
+            ArchiveProblem::SizeChanged { expected, actual } => write!(
+                f,
+                "{}",
+                t!(
+                    "archive-size-changed",
+                    actual = *actual,
+                    expected = *expected
+                )
+            ),
+            ArchiveProblem::EtagChanged { expected, actual } => write!(
+                f,
+                "{}",
+                t!(
+                    "archive-etag-changed",
+                    actual = actual.as_str(),
+                    expected = expected.as_str()
+                )
+            ),
+            ArchiveProblem::Unreachable { reason } => {
+                write!(f, "{}", t!("archive-unreachable", reason = reason.as_str()))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DamagedObject<'a> {
+    key: &'a str,
+    url: &'a str,
+    problem: ArchiveProblem,
+}
+
+/// Check that every object in the workspace's publish journal is still in
+/// storage with the size and ETag it was uploaded with. Meant to run from
+/// cron: with `quiet` only problems are printed, and problems are posted to
+/// the `archive.webhook` before failing.
+pub async fn verify_archive(
+    config: &ArchiveConfig,
+    workspace: &Workspace,
+    client: &HttpClient,
+    quiet: bool,
+) -> Result<(), CliError> {
+    let manifest = workspace.manifest()?;
+    if manifest.is_empty() {
+        outln!("{}", t!("archive-nothing-published"));
+        return Ok(());
+    }
+
+    let mut results: Vec<_> = stream::iter(manifest.values())
+        .map(|entry| async move { (entry, verify_object(client, entry).await) })
+        .buffer_unordered(client.concurrency())
+        .collect()
+        .await;
+    results.sort_by(|a, b| a.0.key.cmp(&b.0.key));
+
+    let mut damaged = Vec::new();
+    for (entry, problem) in results {
+        match problem {
+            None if !quiet => outln!("{}", t!("archive-ok", key = entry.key.as_str())),
+            None => {}
+            Some(problem) => {
+                outln!(
+                    "{}",
+                    t!(
+                        "archive-damaged",
+                        key = entry.key.as_str(),
+                        problem = problem.to_string()
+                    )
+                );
+                damaged.push(DamagedObject {
+                    key: &entry.key,
+                    url: &entry.url,
+                    problem,
+                });
+            }
+        }
+    }
+
+    output::record("checked", manifest.len());
+    output::record("damaged", &damaged);
+    if damaged.is_empty() {
+        if !quiet {
+            outln!("{}", t!("archive-intact", count = manifest.len()));
+        }
+        return Ok(());
+    }
+
+    if let Some(webhook) = &config.webhook {
+        info!("Reporting damaged objects to {}", webhook);
+        let body = json!({
+            "text": t!(
+                "archive-webhook",
+                damaged = damaged.len(),
+                count = manifest.len()
+            ),
+            "objects": damaged,
+        });
+        let response = client
+            .send(webhook, || {
+                client.request(Method::POST, webhook).json(&body)
+            })
+            .await?;
+        if !response.status.is_success() {
+            return Err(CliError::HttpStatus(
+                webhook.clone(),
+                response.status.as_u16(),
+            ));
+        }
+    }
+
+    Err(CliError::ArchiveDamaged(damaged.len()))
+}
+
+async fn verify_object(client: &HttpClient, entry: &JournalEntry) -> Option<ArchiveProblem> {
+    let response = match check_url(client, &entry.url).await {
+        Ok(response) => response,
+        Err(e) => {
+            return Some(ArchiveProblem::Unreachable {
+                reason: e.localized(),
+            })
+        }
+    };
+
+    if matches!(response.status, StatusCode::NOT_FOUND | StatusCode::GONE) {
+        return Some(ArchiveProblem::Missing);
+    }
+    if !response.status.is_success() {
+        return Some(ArchiveProblem::Unreachable {
+            reason: format!("HTTP {}", response.status),
+        });
+    }
+
+    if let Some(actual) = response.content_length {
+        if actual != entry.bytes {
+            return Some(ArchiveProblem::SizeChanged {
+                expected: entry.bytes,
+                actual,
+            });
+        }
+    }
+
+    match (&entry.etag, &response.etag) {
+        (Some(expected), Some(actual)) if normalize_etag(expected) != normalize_etag(actual) => {
+            Some(ArchiveProblem::EtagChanged {
+                expected: expected.clone(),
+                actual: actual.clone(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// `W/"abc"` and `"abc"` name the same content.
+fn normalize_etag(etag: &str) -> &str {
+    etag.trim_start_matches("W/").trim_matches('"')
+}
@@ -0,0 +1,231 @@
+//! Episode artwork drawn from a template, so every episode's image looks
+//! the same without opening a design tool.
+//!
+//! ```yaml
+//! art:
+//!   template: art/template.png
+//!   font: art/Inter-Bold.ttf
+//!   text:
+//!     - text: "Episode {episode_number}"
+//!       x: 120
+//!       y: 2300
+//!       size: 160
+//!     - text: "{title}"
+//!       x: 120
+//!       y: 2500
+//!       size: 200
+//!       color: "#f5d76e"
+//!       maxWidth: 2760
+//! ```
+
+use crate::config::{ArtConfig, ArtText, ChannelConfig, Episode};
+use crate::i18n::t;
+use crate::output::outln;
+use crate::upload;
+use crate::CliError;
+use ab_glyph::{point, Font, FontVec, GlyphId, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+use log::info;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Draw `episode`'s text on the template and save it to `output`, as a PNG
+/// or, by its extension, a JPEG. Transparency in the template is flattened
+/// onto white, since directories reject it.
+pub fn generate(
+    config: &ArtConfig,
+    channel_dir: &Path,
+    episode: &Episode,
+    output: &Path,
+) -> Result<(), CliError> {
+    let template = channel_dir.join(&config.template);
+    let mut image = image::open(&template)
+        .map_err(|e| art_error(&template, e))?
+        .to_rgba8();
+    let font_path = channel_dir.join(&config.font);
+    let font =
+        FontVec::try_from_vec(fs::read(&font_path)?).map_err(|e| art_error(&font_path, e))?;
+
+    let variables = [
+        ("title", episode.title.clone()),
+        ("season", episode.season.to_string()),
+        ("episode_number", episode.episode_number.to_string()),
+        ("id", episode.id.clone()),
+    ];
+    for text in &config.text {
+        let value = crate::template::expand(&text.text, &variables);
+        draw_text(&mut image, &font, text, &value)?;
+    }
+
+    info!("Writing {}", output.display());
+    crate::images::flatten(&image::DynamicImage::ImageRgba8(image))
+        .save(output)
+        .map_err(|e| art_error(output, e))
+}
+
+fn art_error(path: &Path, error: impl std::fmt::Display) -> CliError {
+    CliError::ArtError(format!("{}: {}", path.display(), error))
+}
+
+fn draw_text(
+    image: &mut RgbaImage,
+    font: &FontVec,
+    text: &ArtText,
+    value: &str,
+) -> Result<(), CliError> {
+    let color = parse_color(&text.color)?;
+    let scale = PxScale::from(text.size);
+    let scaled = font.as_scaled(scale);
+
+    let mut baseline = text.y as f32 + scaled.ascent();
+    for line in wrap(font, scale, value, text.max_width) {
+        let mut x = text.x as f32;
+        let mut previous: Option<GlyphId> = None;
+        for c in line.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                x += scaled.kern(previous, id);
+            }
+            previous = Some(id);
+            let glyph = id.with_scale_and_position(scale, point(x, baseline));
+            x += scaled.h_advance(id);
+
+            if let Some(outlined) = font.outline_glyph(glyph) {
+                let bounds = outlined.px_bounds();
+                outlined.draw(|glyph_x, glyph_y, coverage| {
+                    let px = bounds.min.x as i64 + i64::from(glyph_x);
+                    let py = bounds.min.y as i64 + i64::from(glyph_y);
+                    if px >= 0
+                        && py >= 0
+                        && px < i64::from(image.width())
+                        && py < i64::from(image.height())
+                    {
+                        blend(image.get_pixel_mut(px as u32, py as u32), color, coverage);
+                    }
+                });
+            }
+        }
+        baseline += scaled.height() + scaled.line_gap();
+    }
+    Ok(())
+}
+
+/// Split `value` into lines no wider than `max_width`, breaking between
+/// words. A word wider than the line gets a line of its own.
+fn wrap(font: &FontVec, scale: PxScale, value: &str, max_width: Option<u32>) -> Vec<String> {
+    let max_width = match max_width {
+        Some(max_width) => max_width as f32,
+        None => return value.lines().map(str::to_owned).collect(),
+    };
+    let scaled = font.as_scaled(scale);
+    let width = |text: &str| {
+        let mut width = 0.0;
+        let mut previous: Option<GlyphId> = None;
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                width += scaled.kern(previous, id);
+            }
+            width += scaled.h_advance(id);
+            previous = Some(id);
+        }
+        width
+    };
+
+    let mut lines = Vec::new();
+    for paragraph in value.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_owned()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if !line.is_empty() && width(&candidate) > max_width {
+                lines.push(std::mem::replace(&mut line, word.to_owned()));
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn blend(pixel: &mut Rgba<u8>, color: Rgba<u8>, coverage: f32) {
+    let alpha = coverage.clamp(0.0, 1.0) * f32::from(color[3]) / 255.0;
+    for channel in 0..3 {
+        let under = f32::from(pixel[channel]);
+        let over = f32::from(color[channel]);
+        pixel[channel] = (under + (over - under) * alpha).round() as u8;
+    }
+    let under = f32::from(pixel[3]) / 255.0;
+    pixel[3] = ((under + (1.0 - under) * alpha) * 255.0).round() as u8;
+}
+
+fn parse_color(color: &str) -> Result<Rgba<u8>, CliError> {
+    let invalid = || CliError::ArtError(format!("'{}' is not a color like #ffffff", color));
+    let hex = color.strip_prefix('#').ok_or_else(invalid)?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return Err(invalid());
+    }
+
+    let mut channels = [255; 4];
+    for (index, channel) in channels.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(Rgba(channels))
+}
+
+/// `generate-art`: draw the artwork of the episode `selector` picks to
+/// `output`, or a PNG next to its file, and with `upload`, upload it and
+/// make it the episode's image.
+pub async fn generate_episode_art(
+    episode_dir: &Path,
+    channel_config: &ChannelConfig,
+    selector: &str,
+    output: Option<PathBuf>,
+    upload: bool,
+) -> Result<(), CliError> {
+    let config = channel_config
+        .art
+        .as_ref()
+        .ok_or_else(|| CliError::ArtError("channel.yaml has no `art` section".to_owned()))?;
+    let (path, mut episode) =
+        crate::find_episode(episode_dir, selector, channel_config.channel.tz()?)?;
+
+    let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+    let output = output.unwrap_or_else(|| crate::crypto::plain_path(&path).with_extension("png"));
+    generate(config, channel_dir, &episode, &output)?;
+    outln!("{}", t!("wrote-file", path = output.display().to_string()));
+
+    if upload {
+        let publishing = &channel_config.publishing;
+        let workspace = crate::state::Workspace::new(channel_dir);
+        let lock = workspace.lock()?;
+        let file = tokio::fs::File::open(&output).await?;
+        let size = file.metadata().await?.len();
+        let extension = output
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "png".to_owned());
+        let key = upload::artifact_key(publishing, &episode, &format!(".{}", extension));
+        let uploaded = upload::upload_object(
+            file,
+            size,
+            publishing,
+            key.clone(),
+            upload::ObjectOptions::media(publishing),
+        )
+        .await?;
+        workspace.record_upload(&lock, &key, &uploaded, size)?;
+
+        episode.image = uploaded.url.clone();
+        if !upload::dry_run() {
+            crate::save_episode(&path, &episode, channel_config)?;
+        }
+        outln!("{}", t!("uploaded-art", url = uploaded.url.as_str()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+//! Channel artwork from a local file. When `image` in channel.yaml is a
+//! path, relative to channel.yaml, rather than a URL, the image is checked
+//! against what directories accept, uploaded with `render-channel
+//! --upload`, and the feed points at the uploaded copy:
+//!
+//! ```yaml
+//! image: art/cover.jpg
+//! ```
+//!
+//! The uploaded copy is named by its contents, so apps that cache artwork
+//! by URL see a new image as soon as it changes.
+
+use crate::config::{ChannelDetails, Episode, PublishingConfig};
+use crate::events;
+use crate::i18n::t;
+use crate::validate::{self, Severity};
+use crate::CliError;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Artwork larger than this is slow for apps to download on every refresh.
+const MAX_BYTES: u64 = 512 * 1024;
+
+/// A channel image read from a local file.
+pub struct LocalArtwork {
+    pub path: PathBuf,
+    pub bytes: Vec<u8>,
+    pub content_type: &'static str,
+    pub key: String,
+    pub url: String,
+}
+
+/// Whether `image` is a file rather than a URL.
+pub fn is_local(image: &str) -> bool {
+    !image.starts_with("https://") && !image.starts_with("http://")
+}
+
+/// The channel's image, when it is a local file.
+pub fn local(
+    channel_dir: &Path,
+    publishing: &PublishingConfig,
+    image: &str,
+) -> Result<Option<LocalArtwork>, CliError> {
+    if !is_local(image) {
+        return Ok(None);
+    }
+    let path = channel_dir.join(image);
+    let bytes = fs::read(&path).map_err(|e| {
+        CliError::ArtworkRejected(path.display().to_string(), format!("can't be read: {}", e))
+    })?;
+    let (extension, content_type) = crate::images::file_type(&bytes);
+    let hash = format!("{:x}", md5::compute(&bytes));
+    let key = format!("{}/artwork-{}.{}", publishing.prefix, &hash[..8], extension);
+    Ok(Some(LocalArtwork {
+        url: crate::upload::object_url(publishing, &key),
+        path,
+        bytes,
+        content_type,
+        key,
+    }))
+}
+
+impl LocalArtwork {
+    /// What directories would reject the image for, as errors, and what
+    /// makes it slow, as warnings.
+    pub fn problems(&self) -> Vec<(Severity, String)> {
+        let mut problems: Vec<(Severity, String)> = validate::artwork_problems(&self.bytes)
+            .into_iter()
+            .map(|problem| (Severity::Error, problem))
+            .collect();
+        let size = self.bytes.len() as u64;
+        if size > MAX_BYTES {
+            problems.push((
+                Severity::Warning,
+                format!(
+                    "is {}, apps download artwork over {} slowly",
+                    crate::preview::format_bytes(size),
+                    crate::preview::format_bytes(MAX_BYTES)
+                ),
+            ));
+        }
+        problems
+    }
+
+    /// Fail on what directories would reject, and warn about the rest.
+    pub fn check(&self) -> Result<(), CliError> {
+        let mut errors = Vec::new();
+        for (severity, problem) in self.problems() {
+            match severity {
+                Severity::Error => errors.push(problem),
+                Severity::Warning => events::warn(t!(
+                    "artwork-warning",
+                    path = self.path.display().to_string(),
+                    problem = problem
+                )),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(CliError::ArtworkRejected(
+                self.path.display().to_string(),
+                errors.join("; "),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Point the channel, and the episodes that use its image, at the
+    /// uploaded copy.
+    pub fn substitute<'a>(
+        &self,
+        channel: &mut ChannelDetails,
+        episodes: impl Iterator<Item = &'a mut Episode>,
+    ) {
+        for episode in episodes {
+            if episode.image == channel.image {
+                episode.image = self.url.clone();
+            }
+        }
+        channel.image = self.url.clone();
+    }
+}
@@ -0,0 +1,398 @@
+//! The audio formats episodes can be published in, and reading their
+//! duration. MP3s are read with `mp3-metadata`, the rest with symphonia, as
+//! is encrypted audio of any format so it can be decrypted as it is read.
+//! Tags are read with symphonia, and its ID3 readers for MP3s.
+
+use crate::i18n::t;
+use crate::CliError;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::{MediaSource, MediaSourceStream, ReadBytes};
+use symphonia::core::meta::{
+    MetadataBuilder, MetadataOptions, MetadataReader, MetadataRevision, StandardTagKey,
+};
+use symphonia::core::probe::Hint;
+use symphonia_metadata::{id3v1, id3v2};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    Mp3,
+    /// AAC in an MPEG-4 container
+    M4a,
+    /// Vorbis in Ogg
+    Ogg,
+    /// Opus in Ogg
+    Opus,
+    Flac,
+}
+
+impl AudioFormat {
+    /// The format of an audio file, from its first bytes, or from its
+    /// extension when they aren't recognized, so a file named `.mp3` that
+    /// holds M4A audio is published as M4A.
+    pub fn detect(path: &Path, head: &[u8]) -> Option<Self> {
+        Self::sniff(head).or_else(|| Self::from_extension(path))
+    }
+
+    /// The format a file's extension says it is.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let plain = crate::crypto::plain_path(path);
+        let extension = plain
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(|extension| extension.to_lowercase());
+        match extension.as_deref() {
+            Some("mp3") => Some(AudioFormat::Mp3),
+            Some("m4a") | Some("aac") => Some(AudioFormat::M4a),
+            Some("ogg") | Some("oga") => Some(AudioFormat::Ogg),
+            Some("opus") => Some(AudioFormat::Opus),
+            Some("flac") => Some(AudioFormat::Flac),
+            _ => None,
+        }
+    }
+
+    fn sniff(head: &[u8]) -> Option<Self> {
+        // MPEG audio frame sync, with a layer set; ADTS AAC has none
+        let mpeg_frame = head.len() > 1 && head[0] == 0xFF && head[1] & 0xE0 == 0xE0;
+        if head.starts_with(b"ID3") || (mpeg_frame && head[1] & 0x06 != 0) {
+            Some(AudioFormat::Mp3)
+        } else if head.len() > 8 && &head[4..8] == b"ftyp" {
+            Some(AudioFormat::M4a)
+        } else if head.starts_with(b"OggS") {
+            let opus = head.windows(8).any(|window| window == b"OpusHead");
+            Some(if opus {
+                AudioFormat::Opus
+            } else {
+                AudioFormat::Ogg
+            })
+        } else if head.starts_with(b"fLaC") {
+            Some(AudioFormat::Flac)
+        } else {
+            None
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::Opus => "opus",
+            AudioFormat::Flac => "flac",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "MP3",
+            AudioFormat::M4a => "M4A",
+            AudioFormat::Ogg => "Ogg Vorbis",
+            AudioFormat::Opus => "Opus",
+            AudioFormat::Flac => "FLAC",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "audio/mpeg",
+            AudioFormat::M4a => "audio/x-m4a",
+            AudioFormat::Ogg => "audio/ogg",
+            AudioFormat::Opus => "audio/opus",
+            AudioFormat::Flac => "audio/flac",
+        }
+    }
+}
+
+/// The kind of audio `head` starts, when it is audio podcasts can't use.
+pub fn unsupported_audio(head: &[u8]) -> Option<&'static str> {
+    let container =
+        |id: &[u8], kind: &[u8]| head.len() >= 12 && &head[..4] == id && &head[8..12] == kind;
+    if container(b"RIFF", b"WAVE") {
+        Some("WAV")
+    } else if container(b"FORM", b"AIFF") || container(b"FORM", b"AIFC") {
+        Some("AIFF")
+    } else {
+        None
+    }
+}
+
+/// Why the file at `path`, starting with `head`, can't be published.
+pub fn not_audio(path: &Path, head: &[u8]) -> String {
+    match unsupported_audio(head) {
+        Some(kind) => format!(
+            "{} is {} audio, which podcast apps don't play; convert it to MP3 or M4A",
+            path.display(),
+            kind
+        ),
+        None => format!(
+            "{} is not an MP3, M4A, Ogg, Opus, or FLAC file",
+            path.display()
+        ),
+    }
+}
+
+/// Warn when the audio at `path` isn't what its extension says.
+pub fn warn_mismatch(path: &Path, format: AudioFormat) {
+    if let Some(named) = AudioFormat::from_extension(path).filter(|named| *named != format) {
+        eprintln!(
+            "{}",
+            t!(
+                "audio-mismatch",
+                path = path.display().to_string(),
+                actual = format.name(),
+                named = named.name(),
+                extension = format.extension()
+            )
+        );
+    }
+}
+
+/// The enclosure type for a media URL, by its extension. Anything unknown
+/// is assumed to be an MP3, as feeds always were.
+pub fn mime_type_for_url(url: &str) -> &'static str {
+    let name = url.rsplit('/').next().unwrap_or_default();
+    let extension = Path::new(name)
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(|extension| extension.to_lowercase());
+    match extension.as_deref() {
+        Some("mp4") => "video/mp4",
+        Some("m4v") => "video/x-m4v",
+        Some("mov") => "video/quicktime",
+        _ => AudioFormat::detect(Path::new(name), &[])
+            .unwrap_or(AudioFormat::Mp3)
+            .mime_type(),
+    }
+}
+
+/// What `create-episode` records about the audio.
+#[derive(Debug, Clone)]
+pub struct AudioInfo {
+    pub duration: Duration,
+    /// kbit/s
+    pub bitrate: Option<u32>,
+    /// Hz
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub recorded_date: Option<String>,
+}
+
+/// Read an audio file that is on disk as-is.
+pub fn read_file(format: AudioFormat, path: &Path) -> Result<AudioInfo, CliError> {
+    match format {
+        AudioFormat::Mp3 => mp3_info(mp3_metadata::read_from_file(path)),
+        _ => {
+            let size = path.metadata()?.len();
+            probe(format, Box::new(File::open(path)?), size)
+        }
+    }
+}
+
+/// Read audio that was tagged in memory.
+pub fn read_slice(format: AudioFormat, audio: &[u8]) -> Result<AudioInfo, CliError> {
+    match format {
+        AudioFormat::Mp3 => mp3_info(mp3_metadata::read_from_slice(audio)),
+        _ => probe(
+            format,
+            Box::new(Cursor::new(audio.to_vec())),
+            audio.len() as u64,
+        ),
+    }
+}
+
+/// Read an `.age` file, decrypting it as it is read.
+pub fn read_encrypted(format: AudioFormat, path: &Path) -> Result<AudioInfo, CliError> {
+    let mut reader = crate::crypto::open(path)?;
+    let size = crate::crypto::plaintext_len(&mut reader)?;
+    probe(format, Box::new(Decrypted { reader, size }), size)
+}
+
+/// Encrypted audio for symphonia to read through.
+struct Decrypted {
+    reader: crate::crypto::Decrypted,
+    size: u64,
+}
+
+impl Read for Decrypted {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl Seek for Decrypted {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        self.reader.seek(position)
+    }
+}
+
+impl MediaSource for Decrypted {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        Some(self.size)
+    }
+}
+
+fn mp3_info(
+    metadata: Result<mp3_metadata::MP3Metadata, mp3_metadata::Error>,
+) -> Result<AudioInfo, CliError> {
+    let metadata = metadata.map_err(|e| CliError::Mp3Error(format!("{}", e)))?;
+    let first = metadata.frames.first();
+    Ok(AudioInfo {
+        duration: metadata.duration,
+        bitrate: first.map(|frame| u32::from(frame.bitrate)),
+        sample_rate: first.map(|frame| u32::from(frame.sampling_freq)),
+        channels: first.and_then(|frame| match frame.chan_type {
+            mp3_metadata::ChannelType::SingleChannel => Some(1),
+            mp3_metadata::ChannelType::Unknown => None,
+            _ => Some(2),
+        }),
+        recorded_date: metadata
+            .tag
+            .as_ref()
+            .filter(|tag| tag.year > 0)
+            .map(|tag| tag.year.to_string()),
+    })
+}
+
+/// The length of the default track, from the container, without decoding
+/// any audio.
+fn probe(
+    format: AudioFormat,
+    source: Box<dyn MediaSource>,
+    size: u64,
+) -> Result<AudioInfo, CliError> {
+    let audio_error = |detail: String| CliError::AudioError(format.extension(), detail);
+
+    let mut hint = Hint::new();
+    hint.with_extension(format.extension());
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            MediaSourceStream::new(source, Default::default()),
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| audio_error(e.to_string()))?;
+
+    let track = probed
+        .format
+        .default_track()
+        .ok_or_else(|| audio_error("there is no audio track".to_owned()))?;
+    let params = &track.codec_params;
+    let frames = params
+        .n_frames
+        .ok_or_else(|| audio_error("the length is unknown".to_owned()))?;
+    let duration = match (params.time_base, params.sample_rate) {
+        (Some(time_base), _) => {
+            let time = time_base.calc_time(frames);
+            Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac)
+        }
+        (None, Some(rate)) => Duration::from_secs_f64(frames as f64 / f64::from(rate)),
+        (None, None) => return Err(audio_error("the sample rate is unknown".to_owned())),
+    };
+
+    let bitrate = Some(duration.as_secs())
+        .filter(|seconds| *seconds > 0)
+        .map(|seconds| (size * 8 / seconds / 1000) as u32);
+    Ok(AudioInfo {
+        duration,
+        bitrate,
+        sample_rate: params.sample_rate,
+        channels: params.channels.map(|channels| channels.count() as u32),
+        recorded_date: None,
+    })
+}
+
+/// What an audio file's tags say about it, for `create-episodes`.
+#[derive(Debug, Default)]
+pub struct AudioTags {
+    pub title: Option<String>,
+    /// As tagged, like `2023-06-06` or `2023`
+    pub date: Option<String>,
+    pub comment: Option<String>,
+}
+
+/// The tags of an audio file on disk; none when they can't be read.
+pub fn read_tags(format: AudioFormat, path: &Path) -> AudioTags {
+    let mut tags = AudioTags::default();
+    let revisions = match format {
+        AudioFormat::Mp3 => id3_revisions(path),
+        _ => probe_revisions(format, path),
+    };
+    for revision in revisions {
+        for tag in revision.tags() {
+            let value = tag.value.to_string().trim().to_owned();
+            if value.is_empty() {
+                continue;
+            }
+            let field = match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => &mut tags.title,
+                Some(StandardTagKey::ReleaseDate) | Some(StandardTagKey::Date) => &mut tags.date,
+                Some(StandardTagKey::Comment) | Some(StandardTagKey::Description) => {
+                    &mut tags.comment
+                }
+                _ => continue,
+            };
+            // ID3v2 comes first, and is more complete than ID3v1
+            if field.is_none() {
+                *field = Some(value);
+            }
+        }
+    }
+    tags
+}
+
+/// The ID3v2 tag at the start of an MP3, then the ID3v1 tag at its end.
+fn id3_revisions(path: &Path) -> Vec<MetadataRevision> {
+    let stream = || -> Option<MediaSourceStream> {
+        let file = File::open(path).ok()?;
+        Some(MediaSourceStream::new(Box::new(file), Default::default()))
+    };
+    let mut revisions = Vec::new();
+    if let Some(mut source) = stream() {
+        let mut reader = id3v2::Id3v2Reader::new(&MetadataOptions::default());
+        revisions.extend(reader.read_all(&mut source).ok());
+    }
+    let size = path.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    if let (Some(mut source), true) = (stream(), size >= 128) {
+        let mut builder = MetadataBuilder::new();
+        if source.ignore_bytes(size - 128).is_ok()
+            && id3v1::read_id3v1(&mut source, &mut builder).is_ok()
+        {
+            revisions.push(builder.metadata());
+        }
+    }
+    revisions
+}
+
+/// Tags before the container, like ID3v2 on FLAC, then the container's.
+fn probe_revisions(format: AudioFormat, path: &Path) -> Vec<MetadataRevision> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Vec::new(),
+    };
+    let mut hint = Hint::new();
+    hint.with_extension(format.extension());
+    let mut probed = match symphonia::default::get_probe().format(
+        &hint,
+        MediaSourceStream::new(Box::new(file), Default::default()),
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    ) {
+        Ok(probed) => probed,
+        Err(_) => return Vec::new(),
+    };
+    let mut revisions = Vec::new();
+    if let Some(metadata) = probed.metadata.get() {
+        revisions.extend(metadata.current().cloned());
+    }
+    revisions.extend(probed.format.metadata().current().cloned());
+    revisions
+}
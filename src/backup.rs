@@ -0,0 +1,281 @@
+//! `backup <dir>` copies what the show has published from storage to a
+//! directory, so it can be put back with `restore <dir>` if the bucket is
+//! lost or the show moves hosts: every episode's media and image, the
+//! channel artwork, and the published podcast.xml. Transcripts, chapters,
+//! and the other feeds are made from the episode files, so rendering puts
+//! them back. Objects hosted elsewhere, like artwork on another site, are
+//! left out.
+//!
+//! The directory has `backup.json`, listing each object with its size and
+//! MD5, and the objects under `objects/`, or `destinations/<name>/` for
+//! episodes published to a destination. Downloads are checked against the
+//! size in the episode file and the MD5 recorded when they were uploaded
+//! from this workspace; `restore` checks the files against `backup.json`
+//! before uploading anything.
+
+use crate::config::{ChannelConfig, Episode};
+use crate::http::HttpClient;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::private::Signer;
+use crate::state::Workspace;
+use crate::upload::{self, ObjectOptions};
+use crate::CliError;
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+const MANIFEST: &str = "backup.json";
+const FORMAT: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    format: u32,
+    created_at: DateTime<Utc>,
+    title: String,
+    objects: Vec<BackupObject>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupObject {
+    key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    destination: Option<String>,
+    /// Where the object is in the backup directory
+    path: String,
+    bytes: u64,
+    md5: String,
+}
+
+/// An object to back up.
+struct Wanted {
+    key: String,
+    url: String,
+    /// The size the episode file says the media is
+    bytes: Option<u64>,
+}
+
+/// Copy every published object the show references into `dir`.
+pub async fn backup(
+    config: &ChannelConfig,
+    workspace: &Workspace,
+    episodes: &[Episode],
+    client: &HttpClient,
+    dir: &Path,
+) -> Result<(), CliError> {
+    let main = &config.publishing;
+    let mut wanted: BTreeMap<Option<String>, BTreeMap<String, Wanted>> = BTreeMap::new();
+    let mut elsewhere = 0;
+    let mut want = |destination: Option<&str>, url: &str, bytes: Option<u64>| {
+        let publishing = upload::destination(main, destination)?;
+        match upload::url_key(&publishing, url) {
+            Some(key) => {
+                let objects = wanted.entry(destination.map(str::to_owned)).or_default();
+                let object = objects.entry(key.clone()).or_insert_with(|| Wanted {
+                    url: upload::object_url(&publishing, &key),
+                    key,
+                    bytes: None,
+                });
+                object.bytes = object.bytes.or(bytes);
+            }
+            None if url.is_empty() => {}
+            None => {
+                info!("{} isn't in storage, leaving it out", url);
+                elsewhere += 1;
+            }
+        }
+        Ok::<_, CliError>(())
+    };
+    let feed_key = format!("{}/podcast.xml", main.prefix);
+    want(None, &upload::object_url(main, &feed_key), None)?;
+    want(None, &config.channel.image, None)?;
+    for episode in episodes {
+        let destination = episode.destination.as_deref();
+        want(destination, &episode.media.url, Some(episode.media.bytes.0))?;
+        want(destination, &episode.image, None)?;
+    }
+
+    let journal = workspace.manifest()?;
+    let mut objects = Vec::new();
+    let mut problems = 0;
+    let mut total = 0;
+    for (destination, wanted) in &wanted {
+        let publishing = upload::destination(main, destination.as_deref())?;
+        let signer = Signer::new(&publishing, None).await?;
+        for object in wanted.values() {
+            let url = match &signer {
+                Some(signer) => signer.url(&object.url).await?,
+                None => object.url.clone(),
+            };
+            info!("Backing up {}", object.url);
+            let path = match destination {
+                Some(name) => format!("destinations/{}/{}", name, object.key),
+                None => format!("objects/{}", object.key),
+            };
+            let file = dir.join(safe_path(&path)?);
+            if let Some(parent) = file.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // Episodes can be hundreds of MB, so they are streamed to a
+            // file next to where they go rather than held in memory
+            let partial = PathBuf::from(format!("{}.partial", file.display()));
+            let download = match client.download(&url, &partial).await {
+                Ok(download) if download.status.is_success() => download,
+                Ok(download) => {
+                    problem(&object.key, &format!("HTTP {}", download.status.as_u16()));
+                    problems += 1;
+                    continue;
+                }
+                Err(e) => {
+                    let _ = fs::remove_file(&partial);
+                    problem(&object.key, &e.localized());
+                    problems += 1;
+                    continue;
+                }
+            };
+            fs::rename(&partial, &file)?;
+            let bytes = download.bytes;
+            let md5 = download.md5;
+            let published = journal.get(&object.key);
+            let expected_md5 = published
+                .and_then(|entry| entry.md5.clone())
+                .or_else(|| download.etag.as_deref().and_then(etag_md5));
+            let mut sizes = object
+                .bytes
+                .into_iter()
+                .chain(published.map(|entry| entry.bytes));
+            if let Some(expected) = sizes.find(|expected| *expected != bytes) {
+                let detail = format!("size is {} bytes, published {} bytes", bytes, expected);
+                problem(&object.key, &detail);
+                problems += 1;
+            } else if let Some(expected) = expected_md5.filter(|expected| *expected != md5) {
+                let detail = format!("MD5 is {}, published {}", md5, expected);
+                problem(&object.key, &detail);
+                problems += 1;
+            }
+
+            let size = crate::preview::format_bytes(bytes);
+            outln!(
+                "{}",
+                t!("backed-up", key = object.key.as_str(), size = size)
+            );
+            total += bytes;
+            objects.push(BackupObject {
+                key: object.key.clone(),
+                destination: destination.clone(),
+                path,
+                bytes,
+                md5,
+            });
+        }
+    }
+
+    let manifest = Manifest {
+        format: FORMAT,
+        created_at: Utc::now(),
+        title: config.channel.title.clone(),
+        objects,
+    };
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(MANIFEST), serde_json::to_vec_pretty(&manifest)?)?;
+    outln!(
+        "{}",
+        t!(
+            "backup-summary",
+            count = manifest.objects.len(),
+            size = crate::preview::format_bytes(total),
+            path = dir.display().to_string(),
+            elsewhere = elsewhere
+        )
+    );
+    if problems > 0 {
+        return Err(CliError::BackupError(format!(
+            "{} object(s) couldn't be backed up intact",
+            problems
+        )));
+    }
+    Ok(())
+}
+
+/// Upload everything in the backup at `dir` back to storage, feeds last.
+pub async fn restore(
+    config: &ChannelConfig,
+    workspace: &Workspace,
+    dir: &Path,
+) -> Result<(), CliError> {
+    let manifest: Manifest = serde_json::from_slice(&fs::read(dir.join(MANIFEST))?)?;
+    if manifest.format != FORMAT {
+        return Err(CliError::BackupError(format!(
+            "{} is format {}, this version reads format {}",
+            MANIFEST, manifest.format, FORMAT
+        )));
+    }
+    let mut objects = manifest.objects;
+    objects.sort_by_key(|object| object.key.ends_with(".xml"));
+    for object in &objects {
+        let contents = fs::read(dir.join(safe_path(&object.path)?))?;
+        if format!("{:x}", md5::compute(&contents)) != object.md5 {
+            return Err(CliError::BackupError(format!(
+                "{} doesn't match {}",
+                object.path, MANIFEST
+            )));
+        }
+    }
+
+    let lock = workspace.lock()?;
+    let mut total = 0;
+    for object in &objects {
+        let publishing = upload::destination(&config.publishing, object.destination.as_deref())?;
+        let options = if object.key.ends_with(".xml") {
+            ObjectOptions::feed(&publishing)
+        } else {
+            ObjectOptions::media(&publishing)
+        };
+        let file = tokio::fs::File::open(dir.join(safe_path(&object.path)?)).await?;
+        let key = object.key.clone();
+        let uploaded = upload::upload_object(file, object.bytes, &publishing, key, options).await?;
+        workspace.record_upload(&lock, &object.key, &uploaded, object.bytes)?;
+        outln!("{}", t!("restored-object", url = uploaded.url.as_str()));
+        total += object.bytes;
+    }
+    outln!(
+        "{}",
+        t!(
+            "restore-summary",
+            count = objects.len(),
+            size = crate::preview::format_bytes(total)
+        )
+    );
+    Ok(())
+}
+
+fn problem(key: &str, detail: &str) {
+    eprintln!("{}", t!("backup-problem", key = key, detail = detail));
+}
+
+/// The MD5 in a plain S3 ETag; multipart uploads' aren't one.
+fn etag_md5(etag: &str) -> Option<String> {
+    let etag = etag.trim_matches('"');
+    (etag.len() == 32 && etag.chars().all(|c| c.is_ascii_hexdigit())).then(|| etag.to_lowercase())
+}
+
+/// `path`, if it stays inside the backup directory.
+fn safe_path(path: &str) -> Result<&Path, CliError> {
+    let relative = Path::new(path);
+    if relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        Ok(relative)
+    } else {
+        Err(CliError::BackupError(format!(
+            "{} is outside the backup",
+            path
+        )))
+    }
+}
@@ -0,0 +1,146 @@
+//! `create-episodes --from-dir`: an episode for each audio file in a
+//! directory, filled in from the file's tags. The title is the title tag,
+//! or the file name; the release date is the date tag, or a date like
+//! `2023-06-06` in the file name; and the comment tag is the description.
+//! Files already imported, as the workspace's `imports.jsonl` says, are
+//! skipped, so an import that stopped partway can be run again.
+//!
+//! `watch-inbox` imports from an inbox the same way, but only files named
+//! like `2024-06-02 - Title.mp3`, taking the date and title from the name.
+
+use crate::audio::{self, AudioFormat};
+use crate::state::ImportEntry;
+use crate::CliError;
+use chrono::NaiveDate;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// An audio file to make an episode from.
+pub struct Candidate {
+    /// Absolute path of the audio file
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub title: String,
+    /// As tagged or named, for `create-episode --date`
+    pub date: String,
+    pub description: Option<String>,
+}
+
+/// What was found in the directory.
+pub struct Scan {
+    pub candidates: Vec<Candidate>,
+    /// Audio files imported before
+    pub imported: usize,
+    /// Audio files that can't be imported, and why
+    pub problems: Vec<(PathBuf, String)>,
+}
+
+/// The audio files in `dir`, by file name, that aren't in `imports`.
+pub fn scan(dir: &Path, imports: &[ImportEntry]) -> Result<Scan, CliError> {
+    scan_with(dir, imports, |path, format, stem| {
+        let tags = audio::read_tags(format, path);
+        let date = tags
+            .date
+            .filter(|date| date.len() >= 10)
+            .or_else(|| date_in_name(stem))
+            .ok_or_else(|| "no release date in its tags or file name".to_owned())?;
+        Ok((
+            tags.title.unwrap_or_else(|| stem.to_owned()),
+            date,
+            tags.comment,
+        ))
+    })
+}
+
+/// The audio files in an inbox that aren't in `imports`. Files not named
+/// like `2024-06-02 - Title.mp3` are problems.
+pub fn scan_inbox(dir: &Path, imports: &[ImportEntry]) -> Result<Scan, CliError> {
+    scan_with(dir, imports, |_, _, stem| {
+        let (date, title) = inbox_name(stem).ok_or_else(|| {
+            "not named like `2024-06-02 - Title.mp3`, so it was left alone".to_owned()
+        })?;
+        Ok((title, date, None))
+    })
+}
+
+/// Audio files in `dir` that aren't in `imports`, with the title, date, and
+/// description `describe` finds for each, or why it can't be imported.
+fn scan_with<F>(dir: &Path, imports: &[ImportEntry], describe: F) -> Result<Scan, CliError>
+where
+    F: Fn(&Path, AudioFormat, &str) -> Result<(String, String, Option<String>), String>,
+{
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = fs::canonicalize(entry?.path())?;
+        if path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut scan = Scan {
+        candidates: Vec::new(),
+        imported: 0,
+        problems: Vec::new(),
+    };
+    for path in paths {
+        let mut head = Vec::new();
+        fs::File::open(&path)?.take(64).read_to_end(&mut head)?;
+        let format = match AudioFormat::detect(&path, &head) {
+            Some(format) => format,
+            // Audio that can't be published, like WAV, is worth saying so
+            None if audio::unsupported_audio(&head).is_some() => {
+                let problem = audio::not_audio(&path, &head);
+                scan.problems.push((path, problem));
+                continue;
+            }
+            None => continue,
+        };
+        let bytes = path.metadata()?.len();
+        if imports
+            .iter()
+            .any(|import| import.source == path && import.bytes == bytes)
+        {
+            scan.imported += 1;
+            continue;
+        }
+
+        let stem = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        match describe(&path, format, &stem) {
+            Ok((title, date, description)) => scan.candidates.push(Candidate {
+                title,
+                date,
+                description,
+                path,
+                bytes,
+            }),
+            Err(problem) => scan.problems.push((path, problem)),
+        }
+    }
+    Ok(scan)
+}
+
+/// The date and title of a name like `2024-06-02 - Title`.
+pub fn inbox_name(stem: &str) -> Option<(String, String)> {
+    let (date, title) = stem.split_once(" - ")?;
+    let date = date.trim();
+    let title = title.trim();
+    if title.is_empty() || NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+        return None;
+    }
+    Some((date.to_owned(), title.to_owned()))
+}
+
+/// The first date like `2023-06-06` in a file name.
+fn date_in_name(name: &str) -> Option<String> {
+    let chars: Vec<char> = name.chars().collect();
+    chars
+        .windows(10)
+        .map(|window| window.iter().collect::<String>())
+        .find(|text| NaiveDate::parse_from_str(text, "%Y-%m-%d").is_ok())
+}
@@ -0,0 +1,155 @@
+use crate::compare::scalar;
+use crate::config::*;
+use crate::output::outln;
+use crate::CliError;
+use crate::i18n::t;
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+use std::path::PathBuf;
+
+/// `--set media.url=https://...`: a dotted path into the episode YAML and
+/// the YAML value to put there.
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    path: Vec<String>,
+    value: Value,
+}
+
+/// `--filter season=2`: the value at the path must equal the expected
+/// text, or for lists, contain it.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    path: Vec<String>,
+    expected: String,
+}
+
+impl fmt::Display for Assignment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = serde_yaml::to_string(&self.value).map_err(|_| fmt::Error)?;
+        write!(f, "{}={}", self.path.join("."), value.trim_end())
+    }
+}
+
+pub fn parse_assignment(text: &str) -> Result<Assignment, String> {
+    let (path, value) = split_pair(text)?;
+    let value = serde_yaml::from_str(value).map_err(|e| format!("'{}': {}", value, e))?;
+    Ok(Assignment { path, value })
+}
+
+pub fn parse_filter(text: &str) -> Result<Filter, String> {
+    let (path, expected) = split_pair(text)?;
+    Ok(Filter {
+        path,
+        expected: expected.to_owned(),
+    })
+}
+
+fn split_pair(text: &str) -> Result<(Vec<String>, &str), String> {
+    match text.split_once('=') {
+        Some((path, value)) if !path.is_empty() => {
+            Ok((path.split('.').map(|part| part.to_owned()).collect(), value))
+        }
+        _ => Err(format!("'{}' is not in the form field=value", text)),
+    }
+}
+
+impl Filter {
+    fn matches(&self, episode: &Value) -> bool {
+        match lookup(episode, &self.path) {
+            Some(Value::Sequence(items)) => items.iter().any(|item| scalar(item) == self.expected),
+            Some(value) => scalar(value) == self.expected,
+            None => false,
+        }
+    }
+}
+
+/// Apply `assignments` to every episode matching all `filters`, showing a
+/// diff of each change and asking before writing unless `yes` is set.
+pub fn bulk_edit(
+    episodes: Vec<(PathBuf, Episode)>,
+    assignments: &[Assignment],
+    filters: &[Filter],
+    yes: bool,
+    channel_config: &ChannelConfig,
+) -> Result<(), CliError> {
+    let mut changes = Vec::new();
+    for (path, episode) in episodes {
+        let mut value = serde_yaml::to_value(&episode)?;
+        if !filters.iter().all(|filter| filter.matches(&value)) {
+            continue;
+        }
+
+        for assignment in assignments {
+            assign(&mut value, &assignment.path, assignment.value.clone())
+                .map_err(CliError::BulkEditError)?;
+        }
+        // Round-trip through Episode so typos and wrong types are rejected
+        let edited: Episode = serde_yaml::from_value(value)?;
+
+        // Preview the edit as it will be written, comments and all
+        let before = String::from_utf8_lossy(&crate::crypto::read(&path)?).into_owned();
+        let after = crate::formats::updated_text(&path, &edited)?;
+        if crate::diff::print_diff(&path, &before, &after) {
+            changes.push((path, edited));
+        }
+    }
+
+    if changes.is_empty() {
+        outln!("{}", t!("bulk-no-changes"));
+        return Ok(());
+    }
+
+    outln!("{}", t!("bulk-will-change", count = changes.len()));
+    if !crate::diff::confirm(&t!("confirm-write"), yes)? {
+        outln!("{}", t!("nothing-written"));
+        return Ok(());
+    }
+
+    for (path, episode) in &changes {
+        crate::save_episode(path, episode, channel_config)?;
+    }
+    outln!("{}", t!("bulk-updated", count = changes.len()));
+    Ok(())
+}
+
+/// Apply `--set` overrides to channel.yaml as it was read. The channel's
+/// details are at the top of the file, so `channel.explicit` is
+/// `explicit`.
+pub fn override_config(config: &mut Value, overrides: &[Assignment]) -> Result<(), CliError> {
+    for assignment in overrides {
+        let path = match assignment.path.split_first() {
+            Some((first, rest)) if first == "channel" && !rest.is_empty() => rest,
+            _ => &assignment.path[..],
+        };
+        assign(config, path, assignment.value.clone()).map_err(CliError::ConfigOverride)?;
+    }
+    Ok(())
+}
+
+fn lookup<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter()
+        .try_fold(value, |value, key| value.get(key.as_str()))
+}
+
+fn assign(value: &mut Value, path: &[String], new_value: Value) -> Result<(), String> {
+    let (last, parents) = match path.split_last() {
+        Some(split) => split,
+        None => return Ok(()),
+    };
+
+    let mut current = value;
+    for key in parents {
+        let mapping = current
+            .as_mapping_mut()
+            .ok_or_else(|| format!("{} is not a mapping", key))?;
+        current = mapping
+            .entry(Value::from(key.as_str()))
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+    }
+
+    current
+        .as_mapping_mut()
+        .ok_or_else(|| format!("cannot set {}", path.join(".")))?
+        .insert(Value::from(last.as_str()), new_value);
+    Ok(())
+}
@@ -0,0 +1,517 @@
+//! One-file backups of a whole show, for moving it to another machine or
+//! host. A bundle is a zstd-compressed tar (`.tar.zst`) of the channel
+//! directory's files:
+//!
+//! - `manifest.json`: what the bundle holds, with the MD5 of every file
+//! - `channel.yaml`, whatever the channel file is called
+//! - `episodes/`: episode files, notes, transcripts, and chapters
+//! - files `channel.yaml` and the episodes refer to elsewhere in the
+//!   channel directory, like the stylesheet and art template
+//! - `media/`: episode audio, only when exported with `--media`
+//!
+//! Paths are relative to the channel directory, so `import-bundle` puts
+//! everything back where it was.
+
+use crate::config::{ChannelConfig, Episode};
+use crate::i18n::t;
+use crate::output::outln;
+use crate::CliError;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+const MANIFEST: &str = "manifest.json";
+const CHANNEL_FILE: &str = "channel.yaml";
+const FORMAT: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    format: u32,
+    created_at: DateTime<Utc>,
+    title: String,
+    episodes: usize,
+    files: Vec<ManifestFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestFile {
+    path: String,
+    bytes: u64,
+    md5: String,
+}
+
+/// Write everything needed to restore the show to `output`.
+pub fn export(
+    channel_file: &Path,
+    config: &ChannelConfig,
+    episode_dir: &Path,
+    episodes: &[Episode],
+    media_dir: Option<&Path>,
+    output: &Path,
+) -> Result<(), CliError> {
+    let channel_dir = channel_file.parent().unwrap_or_else(|| Path::new(""));
+    let media_names: Vec<&str> = episodes
+        .iter()
+        .filter_map(|episode| episode.media.url.rsplit('/').next())
+        .collect();
+
+    // Bundle path to source file
+    let mut files = BTreeMap::new();
+    files.insert(CHANNEL_FILE.to_owned(), channel_file.to_path_buf());
+    for path in walk(episode_dir)? {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        // Audio only comes with --media
+        if media_names.contains(&&*name) {
+            continue;
+        }
+        if let Some(bundle_path) = bundle_path(channel_dir, &path) {
+            files.insert(bundle_path, path);
+        }
+    }
+
+    let mut referenced: Vec<PathBuf> = Vec::new();
+    referenced.extend(
+        config
+            .publishing
+            .stylesheet
+            .iter()
+            .map(|p| channel_dir.join(p)),
+    );
+    if let Some(art) = &config.art {
+        referenced.push(channel_dir.join(&art.template));
+        referenced.push(channel_dir.join(&art.font));
+    }
+    for episode in episodes {
+        referenced.extend(episode.notes.iter().map(|p| episode_dir.join(p)));
+        referenced.extend(
+            episode
+                .transcripts
+                .iter()
+                .map(|t| episode_dir.join(&t.path)),
+        );
+    }
+    for path in referenced.into_iter().filter(|path| path.is_file()) {
+        match bundle_path(channel_dir, &path) {
+            Some(bundle_path) => {
+                files.insert(bundle_path, path);
+            }
+            None => warn!(
+                "{} is outside the channel directory, leaving it out",
+                path.display()
+            ),
+        }
+    }
+
+    if let Some(media_dir) = media_dir {
+        for path in walk(media_dir)? {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if media_names.contains(&&*name) {
+                files.insert(format!("media/{}", name), path);
+            }
+        }
+    }
+
+    let mut manifest = Manifest {
+        format: FORMAT,
+        created_at: Utc::now(),
+        title: config.channel.title.clone(),
+        episodes: episodes.len(),
+        files: Vec::new(),
+    };
+    for (bundle_path, source) in &files {
+        manifest.files.push(ManifestFile {
+            path: bundle_path.clone(),
+            bytes: fs::metadata(source)?.len(),
+            md5: crate::sync::md5_file(source)?,
+        });
+    }
+
+    let mut tar = tar::Builder::new(zstd::Encoder::new(File::create(output)?, 0)?);
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    append(
+        &mut tar,
+        MANIFEST,
+        manifest_json.len() as u64,
+        &manifest_json[..],
+    )?;
+    let mut total = 0;
+    for (bundle_path, source) in &files {
+        info!("Adding {} as {}", source.display(), bundle_path);
+        let file = File::open(source)?;
+        let size = file.metadata()?.len();
+        append(&mut tar, bundle_path, size, file)?;
+        total += size;
+    }
+    tar.into_inner()?.finish()?;
+
+    outln!(
+        "{}",
+        t!(
+            "bundle-exported",
+            count = files.len(),
+            size = crate::preview::format_bytes(total),
+            path = output.display().to_string()
+        )
+    );
+    Ok(())
+}
+
+/// Restore a bundle into the channel directory of `channel_file`. Nothing
+/// is written when any file would be overwritten, unless `force` is set, or
+/// when any file in the bundle is corrupt: files are unpacked into a
+/// temporary directory, and only moved into place once all of them check
+/// out.
+pub fn import(channel_file: &Path, bundle: &Path, force: bool) -> Result<(), CliError> {
+    let channel_dir = channel_file.parent().unwrap_or_else(|| Path::new(""));
+    let mut archive = tar::Archive::new(zstd::Decoder::new(File::open(bundle)?)?);
+    let mut entries = archive.entries()?;
+
+    let manifest: Manifest = match entries.next() {
+        Some(entry) => {
+            let entry = entry?;
+            if entry_path(&entry) != MANIFEST {
+                return Err(bundle_error("it doesn't start with a manifest"));
+            }
+            serde_json::from_reader(entry)?
+        }
+        None => return Err(bundle_error("it doesn't start with a manifest")),
+    };
+    if manifest.format != FORMAT {
+        return Err(bundle_error(&format!(
+            "format {} isn't one this podcast-ctl understands",
+            manifest.format
+        )));
+    }
+
+    let mut destinations = BTreeMap::new();
+    for file in &manifest.files {
+        let destination = destination(channel_file, channel_dir, &file.path)?;
+        if destination.exists() && !force {
+            return Err(CliError::ChannelExists(destination.display().to_string()));
+        }
+        destinations.insert(file.path.as_str(), (destination, file));
+    }
+
+    let staging = channel_dir.join(format!(".import-bundle.{}", std::process::id()));
+    let unpacked = unpack(entries, &destinations, &staging).and_then(|staged| {
+        if staged.len() != manifest.files.len() {
+            return Err(bundle_error("it ends before every file in the manifest"));
+        }
+        for (staged, destination) in &staged {
+            if let Some(parent) = destination.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            info!("Writing {}", destination.display());
+            fs::rename(staged, destination)?;
+        }
+        Ok(staged.len())
+    });
+    fs::remove_dir_all(&staging).ok();
+    let imported = unpacked?;
+
+    outln!(
+        "{}",
+        t!(
+            "bundle-imported",
+            count = imported,
+            episodes = manifest.episodes,
+            title = manifest.title.as_str(),
+            path = channel_dir.display().to_string()
+        )
+    );
+    Ok(())
+}
+
+/// Unpack the entries after the manifest into `staging`, checking each
+/// against the manifest, and return where each was unpacked and where it
+/// goes.
+fn unpack<R: Read>(
+    entries: tar::Entries<R>,
+    destinations: &BTreeMap<&str, (PathBuf, &ManifestFile)>,
+    staging: &Path,
+) -> Result<Vec<(PathBuf, PathBuf)>, CliError> {
+    let mut staged = Vec::new();
+    for entry in entries {
+        let mut entry = entry?;
+        let name = entry_path(&entry);
+        let (destination, expected) = destinations
+            .get(name.as_str())
+            .ok_or_else(|| bundle_error(&format!("{} isn't in the manifest", name)))?;
+
+        // Paths in the manifest were checked to stay inside the directory
+        let unpacked = staging.join(&name);
+        if let Some(parent) = unpacked.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut output = Md5Writer {
+            inner: File::create(&unpacked)?,
+            context: md5::Context::new(),
+        };
+        io::copy(&mut entry, &mut output)?;
+        if format!("{:x}", output.context.compute()) != expected.md5 {
+            return Err(bundle_error(&format!("{} is corrupt", name)));
+        }
+        staged.push((unpacked, destination.clone()));
+    }
+    Ok(staged)
+}
+
+/// The full path of an entry, however long.
+fn entry_path<R: Read>(entry: &tar::Entry<R>) -> String {
+    String::from_utf8_lossy(&entry.path_bytes()).into_owned()
+}
+
+/// Hashes what is written through it.
+struct Md5Writer<W> {
+    inner: W,
+    context: md5::Context,
+}
+
+impl<W: Write> Write for Md5Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.context.consume(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn bundle_error(detail: &str) -> CliError {
+    CliError::BundleError(detail.to_owned())
+}
+
+/// Where a file from the bundle goes, refusing paths that would leave the
+/// channel directory.
+fn destination(channel_file: &Path, channel_dir: &Path, path: &str) -> Result<PathBuf, CliError> {
+    if path == CHANNEL_FILE {
+        return Ok(channel_file.to_path_buf());
+    }
+    let relative = Path::new(path);
+    let safe = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if !safe {
+        return Err(bundle_error(&format!(
+            "{} is outside the channel directory",
+            path
+        )));
+    }
+    Ok(channel_dir.join(relative))
+}
+
+/// `path` relative to the channel directory, with `/` separators.
+fn bundle_path(channel_dir: &Path, path: &Path) -> Option<String> {
+    let absolute = |path: &Path| {
+        fs::canonicalize(if path.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            path
+        })
+    };
+    let relative = absolute(path)
+        .ok()?
+        .strip_prefix(absolute(channel_dir).ok()?)
+        .ok()?
+        .to_path_buf();
+    let parts: Vec<_> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    Some(parts.join("/"))
+}
+
+/// Every file under `dir` that isn't hidden, like `.trash/`.
+fn walk(dir: &Path) -> Result<Vec<PathBuf>, CliError> {
+    let mut files = Vec::new();
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(walk(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Add `size` bytes of `contents` as the file `path`. Sizes of 8 GiB and
+/// more are written the GNU way, in binary.
+fn append<W: Write>(
+    tar: &mut tar::Builder<W>,
+    path: &str,
+    size: u64,
+    contents: impl Read,
+) -> Result<(), CliError> {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Regular);
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp().max(0) as u64);
+    let contents = Exactly {
+        inner: contents.take(size),
+    };
+    tar.append_data(&mut header, path, contents)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => {
+                bundle_error(&format!("{} changed while it was being added", path))
+            }
+            _ => e.into(),
+        })
+}
+
+/// Reads all of a `take`, failing when what it reads from ends first, so a
+/// file that shrinks can't leave its entry short.
+struct Exactly<R> {
+    inner: io::Take<R>,
+}
+
+impl<R: Read> Read for Exactly<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read == 0 && !buf.is_empty() && self.inner.limit() > 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILES: [(&str, &[u8]); 2] = [
+        ("channel.yaml", b"title: Show\n"),
+        ("episodes/first.yaml", b"title: First\n"),
+    ];
+
+    /// A bundle at `path` whose manifest lists `listed`, holding `entries`.
+    fn write_bundle(path: &Path, format: u32, listed: &[(&str, &[u8])], entries: &[(&str, &[u8])]) {
+        let manifest = Manifest {
+            format,
+            created_at: Utc::now(),
+            title: "Show".to_owned(),
+            episodes: 1,
+            files: listed
+                .iter()
+                .map(|(path, contents)| ManifestFile {
+                    path: path.to_string(),
+                    bytes: contents.len() as u64,
+                    md5: format!("{:x}", md5::compute(contents)),
+                })
+                .collect(),
+        };
+        let mut tar = tar::Builder::new(zstd::Encoder::new(File::create(path).unwrap(), 0).unwrap());
+        let json = serde_json::to_vec(&manifest).unwrap();
+        append(&mut tar, MANIFEST, json.len() as u64, &json[..]).unwrap();
+        for (name, contents) in entries {
+            append(&mut tar, name, contents.len() as u64, *contents).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    /// A temporary directory with a bundle and a channel directory to
+    /// import it into, and the channel file.
+    fn workspace() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = dir.path().join("show.tar.zst");
+        let channel_file = dir.path().join("show").join("podcast.yaml");
+        fs::create_dir_all(channel_file.parent().unwrap()).unwrap();
+        (dir, bundle, channel_file)
+    }
+
+    fn import_error(channel_file: &Path, bundle: &Path) -> String {
+        match import(channel_file, bundle, false) {
+            Err(CliError::BundleError(detail)) => detail,
+            other => panic!("imported with {:?}", other),
+        }
+    }
+
+    /// Whether anything but the channel file is left in the channel
+    /// directory, like the staging directory.
+    fn nothing_written(channel_file: &Path) -> bool {
+        let channel_dir = channel_file.parent().unwrap();
+        fs::read_dir(channel_dir)
+            .unwrap()
+            .all(|entry| entry.unwrap().path() == channel_file)
+    }
+
+    #[test]
+    fn puts_every_file_back_where_it_was() {
+        let (_dir, bundle, channel_file) = workspace();
+        write_bundle(&bundle, FORMAT, &FILES, &FILES);
+        import(&channel_file, &bundle, false).unwrap();
+
+        assert_eq!(fs::read(&channel_file).unwrap(), b"title: Show\n");
+        let episode = channel_file.with_file_name("episodes").join("first.yaml");
+        assert_eq!(fs::read(episode).unwrap(), b"title: First\n");
+        let channel_dir = channel_file.parent().unwrap();
+        assert_eq!(fs::read_dir(channel_dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn overwrites_files_only_when_forced() {
+        let (_dir, bundle, channel_file) = workspace();
+        write_bundle(&bundle, FORMAT, &FILES, &FILES);
+        fs::write(&channel_file, "title: Old\n").unwrap();
+
+        assert!(matches!(
+            import(&channel_file, &bundle, false),
+            Err(CliError::ChannelExists(_))
+        ));
+        assert_eq!(fs::read(&channel_file).unwrap(), b"title: Old\n");
+        import(&channel_file, &bundle, true).unwrap();
+        assert_eq!(fs::read(&channel_file).unwrap(), b"title: Show\n");
+    }
+
+    #[test]
+    fn writes_nothing_when_a_file_is_corrupt() {
+        let (_dir, bundle, channel_file) = workspace();
+        let changed = [FILES[0], ("episodes/first.yaml", &b"title: Changed\n"[..])];
+        write_bundle(&bundle, FORMAT, &FILES, &changed);
+
+        assert!(import_error(&channel_file, &bundle).contains("is corrupt"));
+        assert!(!channel_file.exists());
+        assert!(nothing_written(&channel_file));
+    }
+
+    #[test]
+    fn refuses_paths_outside_the_channel_directory() {
+        let (_dir, bundle, channel_file) = workspace();
+        for path in ["../outside.yaml", "/etc/outside.yaml", "episodes/../../outside.yaml"] {
+            write_bundle(&bundle, FORMAT, &[FILES[0], (path, b"")], &[FILES[0]]);
+            assert!(import_error(&channel_file, &bundle).contains("outside the channel directory"));
+            assert!(nothing_written(&channel_file));
+        }
+    }
+
+    #[test]
+    fn refuses_bundles_that_differ_from_their_manifest() {
+        let (_dir, bundle, channel_file) = workspace();
+        let extra = [FILES[0], ("episodes/extra.yaml", &b""[..])];
+        write_bundle(&bundle, FORMAT, &FILES[..1], &extra);
+        assert!(import_error(&channel_file, &bundle).contains("isn't in the manifest"));
+
+        write_bundle(&bundle, FORMAT, &FILES, &FILES[..1]);
+        assert!(import_error(&channel_file, &bundle).contains("ends before every file"));
+        assert!(nothing_written(&channel_file));
+
+        write_bundle(&bundle, FORMAT + 1, &FILES, &FILES);
+        assert!(import_error(&channel_file, &bundle).contains("isn't one this podcast-ctl"));
+    }
+}
@@ -0,0 +1,93 @@
+//! Waiting for a CDN in front of the bucket to serve what was just
+//! uploaded. Uploads go to the bucket, but feeds send apps to
+//! `publicBaseUrl`, and a CDN can answer with a 404 or an old copy for a
+//! while after an upload. With `publishing.verifyCdn`, `render-channel
+//! --upload` checks new objects at their public URLs and only publishes
+//! the feed once every one is served with the right size and type.
+
+use crate::config::VerifyCdnConfig;
+use crate::http::HttpClient;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::CliError;
+use futures::{stream, StreamExt};
+use log::debug;
+use reqwest::StatusCode;
+use std::time::{Duration, Instant};
+
+/// An object a feed references, as the CDN should serve it.
+pub struct CdnObject {
+    pub url: String,
+    pub bytes: u64,
+    pub content_type: String,
+}
+
+/// Check `objects` until the CDN serves all of them, or until the timeout
+/// in `config`, when it is an error listing the ones it doesn't.
+pub async fn wait_until_served(
+    client: &HttpClient,
+    config: &VerifyCdnConfig,
+    objects: Vec<CdnObject>,
+) -> Result<(), CliError> {
+    if objects.is_empty() {
+        return Ok(());
+    }
+    outln!("{}", t!("cdn-waiting", count = objects.len()));
+
+    let deadline = Instant::now() + Duration::from_secs(config.timeout_seconds);
+    let mut objects = objects;
+    loop {
+        let results: Vec<Result<(), String>> = stream::iter(&objects)
+            .map(|object| check(client, object))
+            .buffered(client.concurrency())
+            .collect()
+            .await;
+        let pending: Vec<(CdnObject, String)> = objects
+            .into_iter()
+            .zip(results)
+            .filter_map(|(object, result)| result.err().map(|detail| (object, detail)))
+            .collect();
+        if pending.is_empty() {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            let detail = pending
+                .iter()
+                .map(|(object, detail)| format!("{} ({})", object.url, detail))
+                .collect::<Vec<_>>()
+                .join(", ");
+            return Err(CliError::CdnNotServing(detail));
+        }
+
+        debug!("The CDN doesn't serve {} objects yet", pending.len());
+        objects = pending.into_iter().map(|(object, _)| object).collect();
+        tokio::time::sleep(Duration::from_secs(config.interval_seconds)).await;
+    }
+}
+
+/// What's wrong with how the CDN serves `object`, if anything.
+async fn check(client: &HttpClient, object: &CdnObject) -> Result<(), String> {
+    let response = crate::links::check_url(client, &object.url)
+        .await
+        .map_err(|e| e.localized())?;
+    if !response.status.is_success() {
+        return Err(format!("HTTP {}", response.status));
+    }
+    // A one-byte probe, for CDNs that refuse HEAD, has the range's length
+    if response.status == StatusCode::OK {
+        if let Some(length) = response
+            .content_length
+            .filter(|length| *length != object.bytes)
+        {
+            return Err(format!("{} bytes instead of {}", length, object.bytes));
+        }
+    }
+    let served = response.content_type.unwrap_or_default();
+    if !served.starts_with(&object.content_type) {
+        return Err(format!(
+            "served as '{}', not '{}'",
+            served, object.content_type
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! What each `render-channel --upload` changed in the feed, for everyone
+//! who didn't run it.
+//!
+//! The published feed is compared with the copy this workspace published
+//! last time, and the changes are appended to `.podcast-ctl/changelog.md`.
+//! With `changelog.webhook` set they are also posted as JSON (with a
+//! Slack-style `text` field), and with `changelog.messageFile` set they are
+//! written there as a commit message for `git commit -F`.
+
+use crate::compare::{diff_feeds, FeedDifference};
+use crate::config::ChangelogConfig;
+use crate::feed::{parse_feed, ParsedFeed};
+use crate::http::HttpClient;
+use crate::state::{Workspace, WorkspaceLock};
+use crate::CliError;
+use chrono::Utc;
+use log::{info, warn};
+use reqwest::Method;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+/// Describe how `feed` differs from what was published before it, and
+/// record it, returning the changes. Callers must hold the lock.
+pub async fn record(
+    config: &ChangelogConfig,
+    workspace: &Workspace,
+    lock: &WorkspaceLock,
+    channel_dir: &Path,
+    client: &HttpClient,
+    feed: &str,
+) -> Result<Vec<String>, CliError> {
+    let previous = match fs::read_to_string(workspace.published_feed_path()) {
+        Ok(previous) => parse_feed(&previous)?,
+        Err(e) if e.kind() == ErrorKind::NotFound => ParsedFeed::default(),
+        Err(e) => return Err(e.into()),
+    };
+    let changes = describe(&previous, &parse_feed(feed)?);
+    workspace.write_atomic(lock, &workspace.published_feed_path(), feed.as_bytes())?;
+
+    let mut entry = format!("## {}\n\n", Utc::now().format("%Y-%m-%d %H:%M:%S UTC"));
+    if changes.is_empty() {
+        entry.push_str("- No changes to the feed\n");
+    }
+    for change in &changes {
+        entry.push_str(&format!("- {}\n", change));
+    }
+    entry.push('\n');
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(workspace.changelog_path())?
+        .write_all(entry.as_bytes())?;
+
+    let summary = format!("Publish feed: {} change(s)", changes.len());
+    if let Some(message_file) = &config.message_file {
+        let mut message = format!("{}\n\n", summary);
+        for change in &changes {
+            message.push_str(&format!("- {}\n", change));
+        }
+        fs::write(channel_dir.join(message_file), message)?;
+    }
+
+    if let Some(webhook) = &config.webhook {
+        if changes.is_empty() {
+            return Ok(changes);
+        }
+        info!("Posting the changelog to {}", webhook);
+        let body = json!({
+            "text": format!("podcast-ctl: {}\n{}", summary, changes.join("\n")),
+            "changes": changes,
+        });
+        // The feed is already published, so a webhook that is down doesn't
+        // fail the publish
+        let response = client
+            .send(webhook, || {
+                client.request(Method::POST, webhook).json(&body)
+            })
+            .await;
+        match response {
+            Ok(response) if response.status.is_success() => {}
+            Ok(response) => warn!(
+                "Posting the changelog to {} failed with HTTP {}",
+                webhook,
+                response.status.as_u16()
+            ),
+            Err(e) => warn!("Posting the changelog to {} failed: {}", webhook, e),
+        }
+    }
+
+    Ok(changes)
+}
+
+/// One line per episode added, removed, or changed, then one for the
+/// channel.
+pub fn describe(previous: &ParsedFeed, current: &ParsedFeed) -> Vec<String> {
+    if previous.channel.is_empty() {
+        return vec![format!(
+            "First publish from this workspace, with {} episode(s)",
+            current.items.len()
+        )];
+    }
+
+    // Current titles win over the ones they replace
+    let titles: BTreeMap<&str, &str> = previous
+        .items
+        .iter()
+        .chain(&current.items)
+        .filter_map(|item| item.guid().map(|guid| (guid, item.title())))
+        .collect();
+    let title = |guid: &str| titles.get(guid).copied().unwrap_or(guid).to_owned();
+
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut changed: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut channel = Vec::new();
+    for difference in diff_feeds(current, previous, true) {
+        match difference {
+            FeedDifference::ExtraItem { title, .. } => added.push(title),
+            FeedDifference::MissingItem { title, .. } => removed.push(title),
+            FeedDifference::GuidMismatch { title, .. } => {
+                changed.entry(title).or_default().push("guid".to_owned())
+            }
+            FeedDifference::ChangedItemField { guid, field, .. }
+            | FeedDifference::MissingItemField { guid, field, .. }
+            | FeedDifference::ExtraItemField { guid, field } => {
+                changed.entry(title(&guid)).or_default().push(field)
+            }
+            FeedDifference::ChangedChannelField { field, .. }
+            | FeedDifference::MissingChannelField { field, .. }
+            | FeedDifference::ExtraChannelField { field } => channel.push(field),
+        }
+    }
+
+    let mut lines = Vec::new();
+    lines.extend(added.iter().map(|title| format!("Added {:?}", title)));
+    lines.extend(removed.iter().map(|title| format!("Removed {:?}", title)));
+    lines.extend(
+        changed
+            .iter()
+            .map(|(title, fields)| format!("Changed {:?}: {}", title, fields.join(", "))),
+    );
+    if !channel.is_empty() {
+        lines.push(format!("Changed the channel: {}", channel.join(", ")));
+    }
+    lines
+}
@@ -0,0 +1,67 @@
+//! Workspaces of several shows. A `podcasts.yaml` names each show's
+//! channel file, relative to it, with the episodes next to each channel
+//! file as usual:
+//!
+//! ```yaml
+//! channels:
+//!   dnd: dnd/channel.yaml
+//!   tech: tech/channel.yaml
+//! ```
+//!
+//! `--channel dnd` runs any command on that show, instead of
+//! `--channel-file dnd/channel.yaml`, and `render-channel --all` and
+//! `sync --all` publish every show's feed in one run.
+
+use crate::CliError;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where `--channel` and `--all` look for channels, unless `--workspace`
+/// says otherwise.
+pub const WORKSPACE_FILE: &str = "podcasts.yaml";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceFile {
+    /// Channel files by name, relative to the workspace file
+    channels: BTreeMap<String, PathBuf>,
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+impl WorkspaceFile {
+    pub fn load(path: &Path) -> Result<WorkspaceFile, CliError> {
+        let text = fs::read_to_string(path).map_err(|e| {
+            CliError::WorkspaceFile(format!("unable to read {}: {}", path.display(), e))
+        })?;
+        let mut workspace: WorkspaceFile = serde_yaml::from_str(&text)
+            .map_err(|e| CliError::WorkspaceFile(format!("{}: {}", path.display(), e)))?;
+        workspace.dir = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+        Ok(workspace)
+    }
+
+    /// The channel file of the channel `name`.
+    pub fn channel_file(&self, name: &str) -> Result<PathBuf, CliError> {
+        match self.channels.get(name) {
+            Some(file) => Ok(self.dir.join(file)),
+            None => {
+                let names: Vec<&str> = self.channels.keys().map(String::as_str).collect();
+                Err(CliError::WorkspaceFile(format!(
+                    "there is no channel {}; there are {}",
+                    name,
+                    names.join(", ")
+                )))
+            }
+        }
+    }
+
+    /// Every channel's name and channel file, by name.
+    pub fn all(&self) -> Vec<(String, PathBuf)> {
+        self.channels
+            .iter()
+            .map(|(name, file)| (name.clone(), self.dir.join(file)))
+            .collect()
+    }
+}
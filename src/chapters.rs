@@ -0,0 +1,318 @@
+//! Episode chapters, published in the Podcasting 2.0 JSON chapters format
+//! next to the audio.
+//!
+//! Chapters are listed in the episode YAML, or in a `.chapters.txt` file
+//! next to it with one chapter per line:
+//!
+//! ```text
+//! 00:00 Intro
+//! 12:34 Character creation | https://example.com/rules
+//! 1:02:00 The farm | https://example.com/farm | https://example.com/farm.jpg
+//! ```
+//!
+//! or, with neither, in a `## Chapters` section of the show notes, written
+//! the same way and optionally as a list. Chapters from the show notes
+//! are also published as `podcast:soundbite`s.
+
+use crate::config::{Chapter, Episode, PublishingConfig};
+use crate::transcript::parse_timestamp;
+use crate::CliError;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const MIME_TYPE: &str = "application/json+chapters";
+/// Seconds; apps skip soundbites shorter than this.
+const MIN_SOUNDBITE: u64 = 15;
+/// Seconds; apps take up to 2 minutes, but a minute from the start of a
+/// chapter is enough to give a taste of it.
+const MAX_SOUNDBITE: u64 = 60;
+
+/// `podcast:soundbite`, in seconds from the start of the episode.
+#[derive(Debug, Clone)]
+pub struct Soundbite {
+    pub start: u64,
+    pub duration: u64,
+    pub title: String,
+}
+
+#[derive(Serialize)]
+struct ChaptersJson<'a> {
+    version: &'static str,
+    title: &'a str,
+    chapters: Vec<ChapterJson<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChapterJson<'a> {
+    start_time: f64,
+    title: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    img: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<&'a str>,
+}
+
+/// The sidecar chapters file for an episode.
+pub fn sidecar_path(episode_path: &Path) -> PathBuf {
+    crate::crypto::plain_path(episode_path).with_extension("chapters.txt")
+}
+
+/// The episode's chapters, from its YAML, its sidecar file, or its show
+/// notes.
+pub fn load(episode_path: &Path, episode: &Episode) -> Result<Vec<Chapter>, CliError> {
+    if !episode.chapters.is_empty() {
+        return Ok(episode.chapters.clone());
+    }
+
+    let sidecar = sidecar_path(episode_path);
+    if sidecar.exists() {
+        return parse_chapters_file(&fs::read_to_string(&sidecar)?);
+    }
+    match crate::notes::load(episode_path, episode)? {
+        Some(notes) => from_notes(&notes),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// The chapters in the show notes' `## Chapters` section.
+pub fn from_notes(notes: &str) -> Result<Vec<Chapter>, CliError> {
+    parse_chapters_file(&notes_section(notes))
+}
+
+/// The lines of the show notes' `## Chapters` section that start with a
+/// time, without list markers.
+fn notes_section(notes: &str) -> String {
+    let mut section = String::new();
+    let mut in_section = false;
+    for line in notes.lines().map(|line| line.trim()) {
+        if line.starts_with('#') {
+            in_section = line
+                .trim_start_matches('#')
+                .trim()
+                .eq_ignore_ascii_case("chapters");
+            continue;
+        }
+        if in_section {
+            let line = line
+                .strip_prefix("- ")
+                .or_else(|| line.strip_prefix("* "))
+                .unwrap_or(line);
+            let timed = line
+                .split_once(char::is_whitespace)
+                .and_then(|(start, _)| parse_timestamp(start))
+                .is_some();
+            if timed {
+                section.push_str(line);
+                section.push('\n');
+            }
+        }
+    }
+    section
+}
+
+/// The show notes' chapters as soundbites, each from the start of its
+/// chapter and at most a minute long. Chapters shorter than a soundbite
+/// has to be are left out.
+pub fn soundbites(notes: &str, duration: u64) -> Vec<Soundbite> {
+    let chapters = parse_chapters_file(&notes_section(notes)).unwrap_or_default();
+    let starts: Vec<Option<u64>> = chapters
+        .iter()
+        .map(|chapter| parse_timestamp(&chapter.start).map(|start| start / 1000))
+        .collect();
+
+    let mut soundbites = Vec::new();
+    for (index, chapter) in chapters.iter().enumerate() {
+        let start = match starts[index] {
+            Some(start) => start,
+            None => continue,
+        };
+        let end = starts[index + 1..]
+            .iter()
+            .flatten()
+            .next()
+            .copied()
+            .unwrap_or(duration);
+        let length = end.saturating_sub(start).min(MAX_SOUNDBITE);
+        if length >= MIN_SOUNDBITE {
+            soundbites.push(Soundbite {
+                start,
+                duration: length,
+                title: chapter.title.clone(),
+            });
+        }
+    }
+    soundbites
+}
+
+/// Parse the `start title | url | image` lines of a chapters file.
+pub fn parse_chapters_file(text: &str) -> Result<Vec<Chapter>, CliError> {
+    let mut chapters = Vec::new();
+    for line in text.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (start, rest) = line.split_once(char::is_whitespace).ok_or_else(|| {
+            CliError::ChapterError(format!("'{}' is not in the form 'start title'", line))
+        })?;
+        let mut parts = rest.split('|').map(|part| part.trim().to_owned());
+        chapters.push(Chapter {
+            start: start.to_owned(),
+            title: parts.next().unwrap_or_default(),
+            url: parts.next().filter(|url| !url.is_empty()),
+            image: parts.next().filter(|image| !image.is_empty()),
+        });
+    }
+
+    Ok(chapters)
+}
+
+/// Render chapters as Podcasting 2.0 JSON chapters. Starts must parse and
+/// come in order.
+pub fn to_json(episode: &Episode, chapters: &[Chapter]) -> Result<String, CliError> {
+    let entries = chapters
+        .iter()
+        .zip(starts(&episode.id, chapters)?)
+        .map(|(chapter, start)| ChapterJson {
+            start_time: start as f64 / 1000.0,
+            title: &chapter.title,
+            img: chapter.image.as_deref(),
+            url: chapter.url.as_deref(),
+        })
+        .collect();
+
+    Ok(serde_json::to_string_pretty(&ChaptersJson {
+        version: "1.2.0",
+        title: &episode.title,
+        chapters: entries,
+    })?)
+}
+
+/// Each chapter's start, in milliseconds. Starts must parse and come in
+/// order; `episode` names the episode in errors.
+pub fn starts(episode: &str, chapters: &[Chapter]) -> Result<Vec<u64>, CliError> {
+    let mut previous: Option<u64> = None;
+    let mut starts = Vec::new();
+    for chapter in chapters {
+        let start = parse_timestamp(&chapter.start).ok_or_else(|| {
+            CliError::ChapterError(format!(
+                "'{}' in episode {} is not a time like 12:34",
+                chapter.start, episode
+            ))
+        })?;
+        if previous.map(|previous| start <= previous).unwrap_or(false) {
+            return Err(CliError::ChapterError(format!(
+                "'{}' in episode {} must start after the chapter above it",
+                chapter.title, episode
+            )));
+        }
+        previous = Some(start);
+        starts.push(start);
+    }
+    Ok(starts)
+}
+
+/// Where an episode's chapters are uploaded, next to the audio.
+pub fn object_key(publishing: &PublishingConfig, episode: &Episode) -> String {
+    crate::upload::artifact_key(publishing, episode, ".chapters.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTES: &str = "\
+# Episode 4
+
+We start at 00:00 with the news.
+
+## Chapters
+
+- 00:00 Intro
+* 12:34 Character creation | https://example.com/rules
+1:02:00 The farm | https://example.com/farm | https://example.com/farm.jpg
+Thanks to everyone who wrote in
+
+## Links
+
+- 01:00 Not a chapter
+";
+
+    fn chapter(start: &str, title: &str) -> Chapter {
+        Chapter {
+            start: start.to_owned(),
+            title: title.to_owned(),
+            url: None,
+            image: None,
+        }
+    }
+
+    #[test]
+    fn reads_chapters_from_the_chapters_section_of_show_notes() {
+        let chapters = from_notes(NOTES).unwrap();
+        assert_eq!(
+            chapters,
+            vec![
+                chapter("00:00", "Intro"),
+                Chapter {
+                    url: Some("https://example.com/rules".to_owned()),
+                    ..chapter("12:34", "Character creation")
+                },
+                Chapter {
+                    url: Some("https://example.com/farm".to_owned()),
+                    image: Some("https://example.com/farm.jpg".to_owned()),
+                    ..chapter("1:02:00", "The farm")
+                },
+            ]
+        );
+        assert!(from_notes("# Episode 4\n\n- 00:00 Intro\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn reads_chapters_files() {
+        let text = "# chapters\n\n00:00 Intro |  | https://example.com/intro.jpg\n05:00 Main\n";
+        assert_eq!(
+            parse_chapters_file(text).unwrap(),
+            vec![
+                Chapter {
+                    image: Some("https://example.com/intro.jpg".to_owned()),
+                    ..chapter("00:00", "Intro")
+                },
+                chapter("05:00", "Main"),
+            ]
+        );
+        assert!(matches!(
+            parse_chapters_file("00:00"),
+            Err(CliError::ChapterError(_))
+        ));
+    }
+
+    #[test]
+    fn starts_must_parse_and_come_in_order() {
+        let chapters = from_notes(NOTES).unwrap();
+        assert_eq!(starts("first", &chapters).unwrap(), vec![0, 754_000, 3_720_000]);
+
+        let backwards = [chapter("05:00", "Main"), chapter("05:00", "Again")];
+        assert!(matches!(starts("first", &backwards), Err(CliError::ChapterError(_))));
+        let unreadable = [chapter("five", "Main")];
+        assert!(matches!(starts("first", &unreadable), Err(CliError::ChapterError(_))));
+    }
+
+    #[test]
+    fn makes_soundbites_of_a_minute_at_most_from_long_enough_chapters() {
+        let soundbites: Vec<(u64, u64, String)> = soundbites(NOTES, 3730)
+            .into_iter()
+            .map(|soundbite| (soundbite.start, soundbite.duration, soundbite.title))
+            .collect();
+        assert_eq!(
+            soundbites,
+            vec![
+                (0, 60, "Intro".to_owned()),
+                (754, 60, "Character creation".to_owned()),
+            ]
+        );
+        assert_eq!(super::soundbites(NOTES, 3800).len(), 3);
+    }
+}
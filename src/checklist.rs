@@ -0,0 +1,160 @@
+//! The show's run-of-show, checked before `render-channel --upload`
+//! publishes new episodes.
+//!
+//! ```yaml
+//! checklist:
+//!   - item: Artwork updated
+//!     check: artwork
+//!   - item: Transcript attached
+//!     check: transcript
+//!   - item: Loudness normalized
+//!     command: ./scripts/check-loudness.sh
+//!   - item: Sponsor read included
+//! ```
+//!
+//! Items with a `check` or `command` are evaluated; the rest are asked
+//! about. Commands run from the channel directory with the episode in
+//! `PODCAST_CTL_EPISODE_ID` and `PODCAST_CTL_EPISODE_FILE`, and pass when
+//! they exit with 0.
+
+use crate::config::{ChannelConfig, ChecklistCheck, ChecklistItem, Episode};
+use crate::feed::parse_feed;
+use crate::i18n::t;
+use crate::output::{out, outln};
+use crate::state::Workspace;
+use crate::CliError;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Run the checklist for the episodes that weren't in the feed the last
+/// time it was published from this workspace, or for the latest episode
+/// when nothing was. With `assume_yes`, items checked by hand count as
+/// confirmed.
+pub fn run(
+    config: &ChannelConfig,
+    channel_dir: &Path,
+    paths: &[PathBuf],
+    episodes: &[Episode],
+    assume_yes: bool,
+) -> Result<(), CliError> {
+    if config.checklist.is_empty() {
+        return Ok(());
+    }
+
+    let mut failed = Vec::new();
+    let episodes: Vec<(&Path, &Episode)> = paths
+        .iter()
+        .map(|path| path.as_path())
+        .zip(episodes)
+        .collect();
+    for (path, episode) in new_episodes(channel_dir, &episodes) {
+        outln!(
+            "{}",
+            t!("checklist-header", episode = episode.title.as_str())
+        );
+        for item in &config.checklist {
+            let passed = match evaluate(config, channel_dir, path, episode, item)? {
+                Some(passed) => passed,
+                None if assume_yes => true,
+                None => confirm(&item.item)?,
+            };
+            if passed {
+                outln!("{}", t!("checklist-passed", item = item.item.as_str()));
+            } else {
+                outln!("{}", t!("checklist-failed", item = item.item.as_str()));
+                failed.push(format!("{}: {}", episode.id, item.item));
+            }
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::ChecklistFailed(failed.join(", ")))
+    }
+}
+
+fn new_episodes<'a>(
+    channel_dir: &Path,
+    episodes: &[(&'a Path, &'a Episode)],
+) -> Vec<(&'a Path, &'a Episode)> {
+    let published = fs::read_to_string(Workspace::new(channel_dir).published_feed_path())
+        .ok()
+        .and_then(|feed| parse_feed(&feed).ok());
+    match published {
+        Some(feed) => {
+            let guids: HashSet<&str> = feed.items.iter().filter_map(|item| item.guid()).collect();
+            episodes
+                .iter()
+                .filter(|(_, episode)| !guids.contains(episode.id.as_str()))
+                .copied()
+                .collect()
+        }
+        None => episodes
+            .iter()
+            .max_by_key(|(_, episode)| episode.released_at)
+            .copied()
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Whether the item holds, or `None` when it has to be confirmed by hand.
+fn evaluate(
+    config: &ChannelConfig,
+    channel_dir: &Path,
+    path: &Path,
+    episode: &Episode,
+    item: &ChecklistItem,
+) -> Result<Option<bool>, CliError> {
+    if let Some(command) = &item.command {
+        let dir = match channel_dir {
+            dir if dir.as_os_str().is_empty() => Path::new("."),
+            dir => dir,
+        };
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(dir)
+            .env("PODCAST_CTL_EPISODE_ID", &episode.id)
+            .env("PODCAST_CTL_EPISODE_FILE", path)
+            .stdout(crate::output::child_stdout())
+            .status()?;
+        return Ok(Some(status.success()));
+    }
+
+    let episode_dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let passed = match item.check {
+        None => return Ok(None),
+        Some(ChecklistCheck::Artwork) => episode.image != config.channel.image,
+        Some(ChecklistCheck::Transcript) => {
+            crate::transcript::published_transcripts(&config.publishing, episode_dir, episode)
+                .map(|transcripts| {
+                    !transcripts.is_empty()
+                        && transcripts
+                            .iter()
+                            .all(|transcript| transcript.source.exists())
+                })
+                .unwrap_or(false)
+        }
+        Some(ChecklistCheck::Chapters) => crate::chapters::load(path, episode)
+            .map(|chapters| !chapters.is_empty())
+            .unwrap_or(false),
+        Some(ChecklistCheck::Notes) => matches!(crate::notes::load(path, episode), Ok(Some(_))),
+        Some(ChecklistCheck::Approved) => {
+            crate::approvals::missing_approval(&config.approvals, path, episode).is_none()
+        }
+    };
+    Ok(Some(passed))
+}
+
+fn confirm(item: &str) -> Result<bool, CliError> {
+    out!("{} ", t!("checklist-confirm", item = item));
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(crate::init::is_yes(answer.trim()))
+}
@@ -0,0 +1,1918 @@
+//! The `podcastctl` command line, run by the binary.
+
+mod daemon;
+mod info;
+mod pipeline;
+
+use crate::config::*;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::*;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use log::info;
+use std::fs;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+#[derive(Parser)]
+#[clap(author, version, about, long_about = None)]
+struct Cli {
+    /// Directory that contains the channel.yaml file
+    #[clap(short, long, value_parser)]
+    channel_file: Option<PathBuf>,
+    /// Run on this channel of the workspace file, instead of giving its
+    /// --channel-file
+    #[clap(long, global = true, value_parser)]
+    channel: Option<String>,
+    /// With render-channel or sync, publish every channel in the workspace
+    /// file, one after another
+    #[clap(long, global = true, action)]
+    all: bool,
+    /// The file naming the channels --channel and --all pick from
+    #[clap(long, global = true, value_parser, default_value = channels::WORKSPACE_FILE)]
+    workspace: PathBuf,
+    #[clap(flatten)]
+    global: GlobalOptions,
+    #[clap(subcommand)]
+    command: Commands,
+}
+
+#[derive(Args, Clone)]
+pub(crate) struct GlobalOptions {
+    /// Forbid all network access; commands that need it fail immediately
+    #[clap(long, global = true, action)]
+    offline: bool,
+    /// Language for messages, like `es`; defaults to PODCAST_CTL_LANG or
+    /// LANG
+    #[clap(long, global = true, value_parser)]
+    lang: Option<String>,
+    /// Line-by-line output without progress bars, colors, or tables; also
+    /// set by PODCAST_CTL_SCREEN_READER=1
+    #[clap(long, global = true, action)]
+    screen_reader: bool,
+    /// Print the keys, sizes, content types, and URLs uploads would write,
+    /// and write nothing: no uploads, episode files, or publish history
+    #[clap(long, global = true, action)]
+    dry_run: bool,
+    /// Write progress as JSON lines on stderr, like
+    /// `{"event":"uploadStarted",...}`, for programs that show their own
+    #[clap(long, global = true, action)]
+    events: bool,
+    /// No progress bars or progress lines, only results, for logs and CI
+    #[clap(long, short, global = true, action)]
+    quiet: bool,
+    /// With `json`, print the results as one JSON document on stdout once
+    /// the command is done, with every message on stderr, for CI
+    #[clap(long, global = true, value_enum, default_value = "text")]
+    output: output::OutputFormat,
+    /// Override a channel.yaml value for this run, as `path=value`, like
+    /// `publishing.prefix=staging/show`. The value is parsed as YAML.
+    /// Repeat for several
+    #[clap(
+        long = "set",
+        global = true,
+        value_name = "PATH=VALUE",
+        value_parser = bulk::parse_assignment
+    )]
+    overrides: Vec<bulk::Assignment>,
+    /// Don't warn about this deprecation, by the id its warning gives, or
+    /// `all`. Repeat for several
+    #[clap(long, global = true, value_name = "ID", value_parser = deprecations::parse_id)]
+    allow_deprecated: Vec<String>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Interactively write a new channel.yaml and episodes directory
+    Init(InitOptions),
+    /// Write a synthetic channel with many episodes, for benchmarks and demos
+    GenerateFixtures(FixturesOptions),
+    /// Restore a show from a bundle made by export-bundle, next to the
+    /// channel file
+    ImportBundle(ImportBundleOptions),
+    /// Generate episode config
+    CreateEpisode(NewEpisode),
+    /// Create an episode for each audio file in a directory, from the
+    /// files' tags, resuming an import that stopped partway
+    CreateEpisodes(BatchOptions),
+    /// Watch an inbox folder, and make a draft episode from each audio file
+    /// named like `2024-06-02 - Title.mp3` that lands in it
+    WatchInbox(InboxOptions),
+    /// Make a draft episode from an email with audio attached, with the
+    /// subject as its title and the body as its description
+    ImportEmail(EmailOptions),
+    /// Change an episode's title, description, or numbering, or edit its
+    /// whole file in $EDITOR, checking the result before it is saved
+    EditEpisode(EditOptions),
+    /// Upload a trailer for the show or a season, published as
+    /// `podcast:trailer` instead of as an episode
+    AddTrailer(TrailerOptions),
+    /// Encrypt an unreleased episode's notes, and optionally its audio
+    Encrypt(CryptOptions),
+    /// Decrypt an episode's notes, and optionally its audio, for release
+    Decrypt(CryptOptions),
+    /// Change a field on every episode matching a filter
+    BulkEdit(BulkEditOptions),
+    /// Write every episode's fields and media details as CSV, one row per
+    /// episode, for spreadsheets
+    ExportCsv(ExportCsvOptions),
+    /// Apply the edits in a CSV `export-csv` wrote back to the episodes
+    ImportCsv(ImportCsvOptions),
+    /// Write the channel file and episode files again as YAML, TOML, or
+    /// JSON, replacing the originals. Comments are not kept
+    ConvertConfig(ConvertConfigOptions),
+    /// Change channel.yaml so it relies on nothing deprecated, keeping
+    /// what it means
+    Migrate(MigrateOptions),
+    /// Number episodes in release order, within each season or across them
+    /// as `seasons.numbering` says, fixing gaps and duplicates
+    Renumber(RenumberOptions),
+    /// Write an episode's title, number, show, artwork, and chapters into
+    /// the ID3 tags of its MP3
+    Tag(TagOptions),
+    /// Check an episode's audio against the `quality` thresholds in
+    /// channel.yaml, printing its bitrate, sample rate, channels, and with
+    /// ffmpeg, loudness, peak, and longest silence
+    CheckAudio(CheckAudioOptions),
+    /// Record a reviewer's approval of an episode for publishing
+    Approve(ApproveOptions),
+    /// Release a draft episode: clear `draft` and set `releasedAt` to now
+    Publish(PublishOptions),
+    /// Move an episode to episodes/.trash/, where it can be restored from,
+    /// and optionally delete its objects from storage
+    DeleteEpisode(DeleteOptions),
+    /// List or restore deleted episodes
+    #[clap(subcommand)]
+    Trash(TrashCommand),
+    /// Render XML that would be uploaded to S3 storage
+    RenderChannel(RenderOptions),
+    /// Show what publishing would change for listeners: episodes added and
+    /// removed, and fields changed, between the local feed and the published
+    /// one
+    Diff(DiffOptions),
+    /// Render an archive website with a page per episode, and optionally
+    /// upload it under the publishing prefix's `site/`
+    RenderSite(RenderSiteOptions),
+    /// Publish the feed and what it references, uploading only what changed
+    /// since the last upload from this workspace
+    Sync(SyncOptions),
+    /// Serve the feed and local media over HTTP, to preview the show in a
+    /// podcast app before publishing
+    Serve(ServeOptions),
+    /// Serve listing, creating, rendering, and publishing episodes over
+    /// JSON-RPC, with progress notifications, for desktop and web frontends
+    Daemon(DaemonOptions),
+    /// Check the channel and episodes against Apple Podcasts and Spotify requirements
+    Validate(ValidateOptions),
+    /// Check that every link, image, and media URL in the feed responds
+    CheckLinks,
+    /// Check that everything published from this workspace is still intact;
+    /// with --quiet, only damaged objects are printed, for cron
+    VerifyArchive,
+    /// Check that every episode's media and image are live, and that the
+    /// media is the size its episode file says
+    VerifyRemote,
+    /// Check that the storage provider serves uploads publicly, with the
+    /// right content types and byte ranges, and can list and delete them
+    VerifyProvider,
+    /// Delete objects under the publishing prefix's `artifacts/` that no
+    /// episode references; with --dry-run, only list them
+    ///
+    /// Nothing outside `artifacts/` is deleted: trailers, clips, replaced
+    /// channel artwork (`artwork-*.jpg` and `.png`), and feeds of old
+    /// seasons or archives stay in storage until they are removed by hand.
+    Prune,
+    /// Count downloads of each episode per day or week in the S3 or
+    /// CloudFront access logs `analytics` in channel.yaml points at
+    Stats(StatsOptions),
+    /// Reports on the downloads in the access logs
+    #[clap(subcommand)]
+    Analytics(AnalyticsCommand),
+    /// Delete access logs written before a day, or older than
+    /// `analytics.retentionDays`, and anonymize the addresses in the rest
+    /// as `analytics.anonymize` says; with --dry-run, only list them
+    PurgeAnalytics(PurgeAnalyticsOptions),
+    /// Write the channel file, episodes, and the files they use to one
+    /// `.tar.zst`, for backups and moving hosts
+    ExportBundle(ExportBundleOptions),
+    /// Download every published episode's media and image, the channel
+    /// artwork, and the feed to a directory, checking each one
+    Backup(BackupOptions),
+    /// Upload a directory `backup` wrote back to storage
+    Restore(RestoreBackupOptions),
+    /// Show the differences between two episodes
+    Compare(CompareOptions),
+    /// Compare a feed against one produced by another host for the same show
+    CompareFeeds(CompareFeedsOptions),
+    /// Draft an episode's summary, description, and chapters from its transcript
+    Summarize(SummarizeOptions),
+    /// Suggest keywords for an episode from its transcript
+    Keywords(KeywordOptions),
+    /// Find episodes by text, or with --transcripts, find where a term was spoken
+    Search(SearchOptions),
+    /// Build the episode index `index.sqlite` in channel.yaml turns on again
+    /// from every episode file
+    Reindex,
+    /// Cut a section of an episode into a shareable clip
+    Clip(ClipOptions),
+    /// Write social posts announcing an episode, and optionally post them
+    Promo(PromoOptions),
+    /// Tell a service the published feed changed, as `render-channel
+    /// --upload` does when `publishing.notifications` sets it up
+    #[clap(subcommand)]
+    Notify(NotifyCommand),
+    /// Publish a private feed again with fresh URLs: with tokens, a new
+    /// token that replaces the old one, for when the feed's link leaked;
+    /// with presigned URLs, fresh signatures, before they expire
+    #[clap(alias = "rotate-tokens")]
+    RotateUrls(RotateUrlsOptions),
+    /// Draw an episode's artwork from the channel's art template
+    GenerateArt(ArtOptions),
+    /// Convert an image that directories would reject to RGB, without
+    /// transparency or interlacing
+    FixImage(FixImageOptions),
+    /// Inspect or clear the local `.podcast-ctl/` workspace state
+    #[clap(subcommand)]
+    State(StateCommand),
+    /// Print the configuration commands run with: channel.yaml with `--set`
+    /// overrides and defaults applied, where it publishes, and the
+    /// environment it reads, with secrets masked
+    Info,
+    /// Run the steps of a pipeline from channel.yaml, or list the pipelines
+    Run(RunOptions),
+    /// Run a `podcast-ctl-<name>` plugin from the PATH
+    #[clap(external_subcommand)]
+    External(Vec<OsString>),
+}
+
+impl Commands {
+    /// Why the command needs the network, if it does.
+    fn network_use(&self) -> Option<&'static str> {
+        match self {
+            Commands::Init(_) => None,
+            Commands::GenerateFixtures(_) => None,
+            Commands::ImportBundle(_) | Commands::ExportBundle(_) => None,
+            Commands::Encrypt(_) | Commands::Decrypt(_) => None,
+            Commands::EditEpisode(_) | Commands::BulkEdit(_) | Commands::Renumber(_) => None,
+            Commands::ExportCsv(_) | Commands::ImportCsv(_) => None,
+            Commands::ConvertConfig(_) => None,
+            Commands::Migrate(_) => None,
+            Commands::Tag(options) if options.artwork.is_none() => {
+                Some("tag reads the episode image from its URL")
+            }
+            Commands::Tag(_) => None,
+            Commands::CheckAudio(_) => None,
+            Commands::Approve(_) => None,
+            Commands::Publish(_) => None,
+            Commands::DeleteEpisode(options) if options.render => {
+                Some("delete-episode --render publishes the feed")
+            }
+            Commands::DeleteEpisode(options) if options.remote => {
+                Some("delete-episode --remote deletes objects from storage")
+            }
+            Commands::DeleteEpisode(_) => None,
+            Commands::Trash(_) => None,
+            Commands::CreateEpisode(_) => Some("create-episode uploads the episode audio"),
+            Commands::CreateEpisodes(_) => Some("create-episodes uploads the episode audio"),
+            Commands::WatchInbox(_) => Some("watch-inbox uploads the episode audio"),
+            Commands::ImportEmail(_) => Some("import-email uploads the episode audio"),
+            Commands::AddTrailer(_) => Some("add-trailer uploads the trailer audio"),
+            Commands::RenderChannel(options) if options.upload => {
+                Some("render-channel --upload publishes the feed")
+            }
+            Commands::RenderChannel(options) if options.trust_bucket => {
+                Some("render-channel --trust-bucket requests every episode's media")
+            }
+            Commands::RenderChannel(_) => None,
+            Commands::Diff(options) if options.against.is_none() => {
+                Some("diff downloads the published feed")
+            }
+            Commands::Diff(options)
+                if matches!(&options.against, Some(against) if compare::is_url(against)) =>
+            {
+                Some("diff downloads the feed given as a URL")
+            }
+            Commands::Diff(_) => None,
+            Commands::RenderSite(options) if options.upload => {
+                Some("render-site --upload publishes the site")
+            }
+            Commands::RenderSite(_) => None,
+            Commands::Sync(_) => Some("sync uploads what changed"),
+            Commands::Serve(_) => None,
+            // Each request's command checks for itself
+            Commands::Daemon(_) => None,
+            Commands::Validate(_) => None,
+            Commands::VerifyArchive => Some("verify-archive requests every published object"),
+            Commands::CheckLinks => Some("check-links requests every URL in the feed"),
+            Commands::VerifyRemote => {
+                Some("verify-remote requests every episode's media and image")
+            }
+            Commands::VerifyProvider => {
+                Some("verify-provider uploads, fetches, and deletes test objects")
+            }
+            Commands::Prune => Some("prune lists and deletes objects in storage"),
+            Commands::Backup(_) => Some("backup downloads every published object"),
+            Commands::Restore(_) => Some("restore uploads the backed up objects"),
+            Commands::Stats(options) if options.logs.is_none() => {
+                Some("stats reads access logs from the log bucket")
+            }
+            Commands::Stats(_) => None,
+            Commands::Analytics(AnalyticsCommand::Compare(options)) if options.logs.is_none() => {
+                Some("analytics compare reads access logs from the log bucket")
+            }
+            Commands::Analytics(_) => None,
+            Commands::PurgeAnalytics(options) if options.logs.is_none() => {
+                Some("purge-analytics deletes and rewrites logs in the log bucket")
+            }
+            Commands::PurgeAnalytics(_) => None,
+            Commands::Compare(_) => None,
+            Commands::CompareFeeds(options)
+                if compare::is_url(&options.ours) || compare::is_url(&options.reference) =>
+            {
+                Some("compare-feeds downloads feeds given as URLs")
+            }
+            Commands::CompareFeeds(_) => None,
+            Commands::Summarize(_) => Some("summarize calls the configured language model"),
+            Commands::Keywords(_) => None,
+            Commands::Search(_) => None,
+            Commands::Reindex => None,
+            Commands::Clip(options) if options.upload => Some("clip --upload publishes the clip"),
+            Commands::Clip(options) if options.audio.is_none() => {
+                Some("clip reads the episode audio from its media URL")
+            }
+            Commands::Clip(options)
+                if (options.audiogram || options.captions) && options.artwork.is_none() =>
+            {
+                Some("clip --audiogram reads the episode image from its URL")
+            }
+            Commands::Clip(_) => None,
+            Commands::Promo(options) if options.post => Some("promo --post publishes the posts"),
+            Commands::Promo(_) => None,
+            Commands::Notify(_) => Some("notify sends the notification"),
+            Commands::RotateUrls(_) => Some("rotate-urls publishes the feed"),
+            Commands::GenerateArt(options) if options.upload => {
+                Some("generate-art --upload publishes the art")
+            }
+            Commands::GenerateArt(_) => None,
+            Commands::FixImage(_) => None,
+            Commands::State(_) => None,
+            Commands::Info => None,
+            // Each step checks for itself
+            Commands::Run(_) => None,
+            Commands::External(_) => None,
+        }
+    }
+
+    /// What the command does to the show, for checking `roles`. Read-only
+    /// commands return `None`.
+    fn operation(&self) -> Option<Operation> {
+        match self {
+            Commands::Init(_) | Commands::GenerateFixtures(_) => None,
+            Commands::ImportBundle(_) | Commands::ExportBundle(_) => None,
+            Commands::CreateEpisode(_) | Commands::CreateEpisodes(_) => Some(Operation::Create),
+            Commands::WatchInbox(_) => Some(Operation::Create),
+            Commands::ImportEmail(_) => Some(Operation::Create),
+            Commands::AddTrailer(_) => Some(Operation::Publish),
+            Commands::Encrypt(_) | Commands::Decrypt(_) => Some(Operation::Update),
+            Commands::EditEpisode(_) | Commands::BulkEdit(_) | Commands::Renumber(_) => {
+                Some(Operation::Update)
+            }
+            Commands::ExportCsv(_) => None,
+            Commands::ImportCsv(_) => Some(Operation::Update),
+            Commands::ConvertConfig(_) => Some(Operation::Update),
+            Commands::Migrate(_) => Some(Operation::Update),
+            Commands::Tag(_) => None,
+            Commands::CheckAudio(_) => None,
+            Commands::Approve(_) => Some(Operation::Approve),
+            Commands::Publish(_) => Some(Operation::Publish),
+            Commands::DeleteEpisode(_) => Some(Operation::Delete),
+            Commands::Trash(TrashCommand::Restore(_)) => Some(Operation::Update),
+            Commands::Trash(TrashCommand::List) => None,
+            Commands::RenderChannel(options) if options.upload => Some(Operation::Publish),
+            Commands::RenderChannel(options) if options.trust_bucket => Some(Operation::Update),
+            Commands::RenderChannel(_) => None,
+            Commands::Diff(_) => None,
+            Commands::RenderSite(options) if options.upload => Some(Operation::Publish),
+            Commands::RenderSite(_) => None,
+            Commands::Sync(_) => Some(Operation::Publish),
+            Commands::Serve(_) => None,
+            // Each request's command checks for itself
+            Commands::Daemon(_) => None,
+            Commands::Validate(_) | Commands::CheckLinks | Commands::VerifyArchive => None,
+            Commands::VerifyRemote => None,
+            Commands::VerifyProvider => Some(Operation::Publish),
+            Commands::Prune => Some(Operation::Delete),
+            Commands::Backup(_) => None,
+            Commands::Restore(_) => Some(Operation::Publish),
+            Commands::Stats(_) | Commands::Analytics(_) => None,
+            Commands::PurgeAnalytics(_) => Some(Operation::Delete),
+            Commands::Compare(_) | Commands::CompareFeeds(_) | Commands::Search(_) => None,
+            Commands::Reindex => None,
+            Commands::Summarize(_) => Some(Operation::Update),
+            Commands::Keywords(options) if options.write => Some(Operation::Update),
+            Commands::Keywords(_) => None,
+            Commands::Clip(options) if options.upload => Some(Operation::Publish),
+            Commands::Clip(_) => None,
+            Commands::Promo(options) if options.post => Some(Operation::Publish),
+            Commands::Promo(_) => None,
+            Commands::Notify(_) => Some(Operation::Publish),
+            Commands::RotateUrls(_) => Some(Operation::Publish),
+            Commands::GenerateArt(options) if options.upload => Some(Operation::Publish),
+            Commands::GenerateArt(_) => None,
+            Commands::FixImage(_) => None,
+            Commands::State(_) | Commands::Info => None,
+            // Steps and plugins check for themselves
+            Commands::Run(_) | Commands::External(_) => None,
+        }
+    }
+}
+
+#[derive(Parser, Clone)]
+struct RenderOptions {
+    /// When set, the xml file will be uploaded instead of written to stdout
+    #[clap(long, short, action)]
+    upload: bool,
+    /// How to print the feed to stdout. With --upload, `html` also uploads
+    /// podcast.html next to podcast.xml, and `jsonfeed` uploads feed.json
+    #[clap(long, value_enum, default_value = "xml")]
+    format: render::RenderFormat,
+    /// Fail if `validate` finds any problem, warnings included, for CI.
+    /// Episodes without the approvals `channel.yaml` asks for are left out
+    #[clap(long, action, conflicts_with = "lenient")]
+    strict: bool,
+    /// Render what can be rendered, for previews: missing optional fields
+    /// get placeholders, and broken chapters or transcripts are left out
+    #[clap(long, action)]
+    lenient: bool,
+    /// Render the feed as it was on a past date, like 2023-06-01: only
+    /// episodes released by then, with the channel and episode files from
+    /// the last git commit before it, when they are in a git repository
+    #[clap(long, value_parser = parse_as_of, conflicts_with = "upload")]
+    as_of: Option<DateTime<Utc>>,
+    /// Include episodes whose `releasedAt` is still to come. Without it
+    /// they are left out until then, so uploading on a schedule releases
+    /// them
+    #[clap(long, action, conflicts_with = "as-of")]
+    include_future: bool,
+    /// `lastBuildDate` of the feed instead of now, as an RFC 3339 time or
+    /// Unix seconds, so the same episodes render byte-identical feeds.
+    /// Defaults to `SOURCE_DATE_EPOCH` when it is set
+    #[clap(long, value_parser = dates::parse_build_date)]
+    build_date: Option<DateTime<Utc>>,
+    /// Render only this season's feed, with the channel details from
+    /// `seasons.feeds`. With --upload, only season-N/podcast.xml and what
+    /// it references are uploaded
+    #[clap(long, value_name = "N")]
+    season: Option<u64>,
+    /// Render the feed of this destination in `publishing.destinations`:
+    /// its episodes along with the main feed's, published to its storage
+    #[clap(long, value_name = "NAME")]
+    destination: Option<String>,
+    /// Count checklist items that are confirmed by hand as done, instead of
+    /// asking, for CI
+    #[clap(long, action, requires = "upload")]
+    yes: bool,
+    /// Take each episode's size from a HEAD of its media URL, and correct
+    /// `media.bytes` in episode files that disagree, for media that was
+    /// uploaded again without updating them
+    #[clap(long, action, conflicts_with = "as-of")]
+    trust_bucket: bool,
+    /// Cut subtitles and summaries longer than Apple allows at a word
+    /// boundary, with an ellipsis, instead of warning about them
+    #[clap(long, action)]
+    truncate: bool,
+    /// Render again whenever channel.yaml or a file in the episodes
+    /// directory changes, and with --upload, publish again. Problems are
+    /// printed without exiting
+    #[clap(long, action, conflicts_with = "as-of")]
+    watch: bool,
+    /// With --watch, how long files must stay unchanged before rendering
+    #[clap(long, value_name = "SECONDS", default_value_t = 2, requires = "watch")]
+    debounce: u64,
+}
+
+impl RenderOptions {
+    /// What the library renders for these arguments.
+    fn library(&self, global: &GlobalOptions) -> render::RenderOptions {
+        render::RenderOptions {
+            upload: self.upload,
+            format: self.format,
+            strict: self.strict,
+            lenient: self.lenient,
+            as_of: self.as_of,
+            include_future: self.include_future,
+            build_date: self.build_date,
+            season: self.season,
+            destination: self.destination.clone(),
+            yes: self.yes,
+            trust_bucket: self.trust_bucket,
+            truncate: self.truncate,
+            offline: global.offline,
+            token: None,
+        }
+    }
+}
+
+#[derive(Parser)]
+struct DiffOptions {
+    /// URL or path of the feed to compare with, instead of the published
+    /// podcast.xml
+    #[clap(long, value_name = "URL_OR_PATH")]
+    against: Option<String>,
+    /// Include episodes whose `releasedAt` is still to come, as an upload
+    /// after they are released would
+    #[clap(long, action)]
+    include_future: bool,
+}
+
+#[derive(Parser)]
+struct RenderSiteOptions {
+    /// Directory to write the site to
+    #[clap(long, short, value_parser, default_value = "site")]
+    out_dir: PathBuf,
+    /// Directory with `index.html`, `index-item.html`, or `episode.html`
+    /// templates to use instead of the bundled ones
+    #[clap(long, value_parser)]
+    templates: Option<PathBuf>,
+    /// Upload the site under the publishing prefix
+    #[clap(long, action)]
+    upload: bool,
+}
+
+#[derive(Parser, Clone)]
+struct SyncOptions {
+    /// Directory of episode audio, uploaded when a file named like an
+    /// episode's media changed
+    #[clap(long, value_parser)]
+    media: Option<PathBuf>,
+    /// Count checklist items that are confirmed by hand as done, instead of
+    /// asking, for CI
+    #[clap(long, action)]
+    yes: bool,
+}
+
+#[derive(Parser)]
+struct DaemonOptions {
+    /// Port to listen on
+    #[clap(long, short, default_value_t = 8766)]
+    port: u16,
+    /// Address to listen on. Anyone who can connect can publish, so only
+    /// listen beyond this machine on a network you trust
+    #[clap(long, default_value = "127.0.0.1")]
+    address: std::net::IpAddr,
+    /// Also serve a read-only web dashboard of the show's status, episodes,
+    /// validation, and publish history on this port, at the same address
+    #[clap(long)]
+    dashboard_port: Option<u16>,
+}
+
+#[derive(Parser)]
+struct ServeOptions {
+    /// Port to listen on
+    #[clap(long, short, default_value_t = 8080)]
+    port: u16,
+    /// Address to listen on; 0.0.0.0 lets phones on the same network
+    /// subscribe
+    #[clap(long, default_value = "127.0.0.1")]
+    address: std::net::IpAddr,
+    /// Directory of episode audio and images, served in place of the URLs
+    /// of files with the same names; defaults to the episodes directory
+    #[clap(long, value_parser)]
+    media: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+struct FixturesOptions {
+    /// Number of episodes to generate
+    #[clap(long, default_value = "100")]
+    episodes: usize,
+    /// Seed for the generated data; the same seed writes the same files
+    #[clap(long, default_value = "1")]
+    seed: u64,
+    /// Also write a silent MP3 per episode to audio/, and use its length
+    /// and size in the episode
+    #[clap(long, action)]
+    audio: bool,
+    /// Length of each silent MP3
+    #[clap(long, default_value = "10", requires = "audio")]
+    audio_seconds: u64,
+    /// Overwrite an existing channel file
+    #[clap(long, action)]
+    force: bool,
+}
+
+#[derive(Parser)]
+struct InitOptions {
+    /// Overwrite an existing channel file
+    #[clap(long, action)]
+    force: bool,
+}
+
+#[derive(Parser)]
+struct ImportBundleOptions {
+    /// Bundle written by export-bundle
+    #[clap(value_parser)]
+    bundle: PathBuf,
+    /// Overwrite files that already exist
+    #[clap(long, action)]
+    force: bool,
+}
+
+#[derive(Parser)]
+struct ExportBundleOptions {
+    /// Where to write the bundle, like `show.tar.zst`
+    #[clap(value_parser)]
+    bundle: PathBuf,
+    /// Directory of episode audio to include; without it, the bundle
+    /// only refers to the published media
+    #[clap(long, value_parser)]
+    media: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+struct NewEpisode {
+    /// Audio for the episode: MP3, M4A, Ogg, Opus, or FLAC
+    #[clap(value_parser)]
+    file: PathBuf,
+    /// Release date, like 2023-06-06, local time, like 2023-06-06T06:00, or
+    /// RFC 3339 time with an offset
+    #[clap(short, long)]
+    date: String,
+    /// Timezone of a --date without an offset, like America/New_York;
+    /// defaults to the channel's `timezone`, or UTC
+    #[clap(long)]
+    timezone: Option<String>,
+    /// Episode Name
+    #[clap(short, long)]
+    title: String,
+    /// Transcript used to pick the episode's keywords, instead of copying
+    /// the channel's. It is also published with the episode.
+    #[clap(long, value_parser)]
+    transcript: Option<PathBuf>,
+    /// The episode's own keywords, comma-separated, published after the
+    /// channel's instead of copying them or picking them from --transcript
+    #[clap(long, value_delimiter = ',')]
+    keywords: Vec<String>,
+    /// Markdown show notes, published as the episode's full description
+    #[clap(long, value_parser)]
+    notes: Option<PathBuf>,
+    /// Whether this is a regular episode, a trailer, or bonus content
+    #[clap(long, value_enum, default_value = "full")]
+    episode_type: EpisodeType,
+    /// Mark this episode explicit or clean, instead of following the channel
+    #[clap(long, value_parser)]
+    explicit: Option<bool>,
+    /// Upload the audio, but leave the episode out of the feed until it is
+    /// published with `publish`
+    #[clap(long, action)]
+    draft: bool,
+    /// Publish the episode to this destination in `publishing.destinations`,
+    /// like a members-only bucket, instead of the main feed's storage
+    #[clap(long)]
+    destination: Option<String>,
+    /// Episode description; asked for when not given
+    #[clap(long)]
+    description: Option<String>,
+    /// One-line summary, published as the subtitle; asked for when not given
+    #[clap(long)]
+    summary: Option<String>,
+    /// Web page for the episode; asked for when not given, unless
+    /// `publishing.site` links it to its page
+    #[clap(long)]
+    link: Option<String>,
+    /// Don't ask for what isn't given, for scripts. The description and
+    /// summary are left as placeholders to fill in before publishing
+    #[clap(long, action)]
+    no_interactive: bool,
+    /// Season number, instead of the latest season or the one `seasons`
+    /// in channel.yaml starts
+    #[clap(long)]
+    season: Option<u64>,
+    /// Episode number, instead of the one after the highest in its season,
+    /// or in the show with `seasons.numbering: global`
+    #[clap(long)]
+    episode: Option<u64>,
+    /// Artwork for this episode, a square JPEG or PNG, uploaded next to
+    /// its audio; the channel's image when not given
+    #[clap(long, value_parser)]
+    image: Option<PathBuf>,
+    /// Write the episode's title, number, show, artwork, and the chapters
+    /// in --notes into the MP3's ID3 tags before uploading it
+    #[clap(long, action)]
+    tag: bool,
+}
+
+impl From<NewEpisode> for create::NewEpisode {
+    fn from(data: NewEpisode) -> Self {
+        create::NewEpisode {
+            file: data.file,
+            date: data.date,
+            timezone: data.timezone,
+            title: data.title,
+            transcript: data.transcript,
+            keywords: data.keywords,
+            notes: data.notes,
+            episode_type: data.episode_type,
+            explicit: data.explicit,
+            draft: data.draft,
+            destination: data.destination,
+            description: data.description,
+            summary: data.summary,
+            link: data.link,
+            no_interactive: data.no_interactive,
+            season: data.season,
+            episode: data.episode,
+            image: data.image,
+            tag: data.tag,
+        }
+    }
+}
+
+#[derive(Parser)]
+struct BatchOptions {
+    /// Directory of audio files, one episode each
+    #[clap(long, value_parser)]
+    from_dir: PathBuf,
+    /// Timezone of dates without an offset, like America/New_York;
+    /// defaults to the channel's `timezone`, or UTC
+    #[clap(long)]
+    timezone: Option<String>,
+    /// Upload the audio, but leave the episodes out of the feed until they
+    /// are published with `publish`
+    #[clap(long, action)]
+    draft: bool,
+}
+
+impl From<BatchOptions> for create::BatchOptions {
+    fn from(data: BatchOptions) -> Self {
+        create::BatchOptions {
+            from_dir: data.from_dir,
+            timezone: data.timezone,
+            draft: data.draft,
+        }
+    }
+}
+
+#[derive(Parser)]
+struct InboxOptions {
+    /// Folder to watch; defaults to `inbox.dir` in channel.yaml
+    #[clap(long, value_parser)]
+    dir: Option<PathBuf>,
+    /// Timezone of the dates in file names, like America/New_York; defaults
+    /// to the channel's `timezone`, or UTC
+    #[clap(long)]
+    timezone: Option<String>,
+    /// Seconds a new file's size has to stay the same before it is
+    /// imported, so files still being copied in are left alone
+    #[clap(long, default_value_t = 5)]
+    settle: u64,
+}
+
+impl From<InboxOptions> for create::InboxOptions {
+    fn from(data: InboxOptions) -> Self {
+        create::InboxOptions {
+            dir: data.dir,
+            timezone: data.timezone,
+            settle: data.settle,
+        }
+    }
+}
+
+#[derive(Parser)]
+struct EmailOptions {
+    /// The message, as a `.eml` file, or `-` to read it from stdin
+    #[clap(value_parser)]
+    message: PathBuf,
+    /// Timezone of the release date, like America/New_York; defaults to
+    /// the channel's `timezone`, or UTC
+    #[clap(long)]
+    timezone: Option<String>,
+}
+
+impl From<EmailOptions> for create::EmailOptions {
+    fn from(data: EmailOptions) -> Self {
+        create::EmailOptions {
+            message: data.message,
+            timezone: data.timezone,
+        }
+    }
+}
+
+#[derive(Parser)]
+#[clap(group(clap::ArgGroup::new("changes").required(true).multiple(true)))]
+struct EditOptions {
+    /// Episode id or id prefix, file name, release date, or `latest`
+    #[clap(value_parser)]
+    episode: String,
+    /// New title
+    #[clap(long, short, group = "changes")]
+    title: Option<String>,
+    /// New description
+    #[clap(long, group = "changes")]
+    description: Option<String>,
+    /// New summary, published as the subtitle
+    #[clap(long, group = "changes")]
+    summary: Option<String>,
+    /// New link; an empty one removes it
+    #[clap(long, group = "changes")]
+    link: Option<String>,
+    /// New season number
+    #[clap(long, group = "changes")]
+    season: Option<u64>,
+    /// New episode number within the season
+    #[clap(long, group = "changes")]
+    episode_number: Option<u64>,
+    /// Edit the whole file in $VISUAL or $EDITOR instead, reopening it until
+    /// it is a valid episode
+    #[clap(
+        long,
+        action,
+        group = "changes",
+        conflicts_with_all = &[
+            "title", "description", "summary", "link", "season", "episode-number"
+        ]
+    )]
+    open: bool,
+    /// Write the changes without showing them and asking first
+    #[clap(long, short, action, conflicts_with = "open")]
+    yes: bool,
+}
+
+#[derive(Parser)]
+struct TrailerOptions {
+    /// Audio for the trailer: MP3, M4A, Ogg, Opus, or FLAC
+    #[clap(value_parser)]
+    file: PathBuf,
+    /// Title apps show for the trailer
+    #[clap(short, long)]
+    title: String,
+    /// Release date, like 2023-01-01, local time, like 2023-01-01T06:00, or
+    /// RFC 3339 time with an offset; defaults to now
+    #[clap(short, long)]
+    date: Option<String>,
+    /// Timezone of a --date without an offset, like America/New_York;
+    /// defaults to the channel's `timezone`, or UTC
+    #[clap(long, requires = "date")]
+    timezone: Option<String>,
+    /// The season this is the trailer for; without it, it is the show's
+    #[clap(long, value_parser)]
+    season: Option<u64>,
+}
+
+impl From<TrailerOptions> for create::TrailerOptions {
+    fn from(data: TrailerOptions) -> Self {
+        create::TrailerOptions {
+            file: data.file,
+            title: data.title,
+            date: data.date,
+            timezone: data.timezone,
+            season: data.season,
+        }
+    }
+}
+
+#[derive(Parser)]
+struct CryptOptions {
+    /// Episode id or id prefix, file name, release date, or `latest`
+    #[clap(value_parser)]
+    episode: String,
+    /// Media files for the episode to encrypt or decrypt as well
+    #[clap(long, value_parser)]
+    media: Vec<PathBuf>,
+}
+
+#[derive(Parser)]
+struct BulkEditOptions {
+    /// Field to change, as `path=value`, e.g. `image=https://...` or
+    /// `media.url=...`. The value is parsed as YAML. Repeat for several
+    #[clap(long, value_parser = bulk::parse_assignment, required = true)]
+    set: Vec<bulk::Assignment>,
+    /// Only change episodes where `path=value`, e.g. `season=2` or
+    /// `keywords=Interview`. Repeat to require several
+    #[clap(long, value_parser = bulk::parse_filter)]
+    filter: Vec<bulk::Filter>,
+    /// Write the changes without asking
+    #[clap(long, short, action)]
+    yes: bool,
+}
+
+#[derive(Parser)]
+struct ExportCsvOptions {
+    /// Where to write the CSV, instead of stdout
+    #[clap(long, short, value_parser)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+struct ImportCsvOptions {
+    /// CSV with an `id` column and the columns to change
+    #[clap(value_parser)]
+    input: PathBuf,
+    /// Write the changes without asking
+    #[clap(long, short, action)]
+    yes: bool,
+}
+
+#[derive(Parser)]
+struct ConvertConfigOptions {
+    /// The format to write
+    #[clap(long, value_enum)]
+    to: formats::Format,
+    /// Files to convert, instead of the channel file and every episode
+    #[clap(value_parser)]
+    files: Vec<PathBuf>,
+}
+
+#[derive(Parser)]
+struct MigrateOptions {
+    /// Write the changes without asking
+    #[clap(long, short, action)]
+    yes: bool,
+}
+
+#[derive(Parser)]
+struct RenumberOptions {
+    /// Work out seasons again from `seasons` in channel.yaml, instead of
+    /// keeping each episode's season
+    #[clap(long, action)]
+    by_date: bool,
+    /// Write the changes without asking
+    #[clap(long, short, action)]
+    yes: bool,
+}
+
+#[derive(Parser)]
+struct CheckAudioOptions {
+    /// Audio file, which may be encrypted
+    #[clap(value_parser)]
+    file: PathBuf,
+}
+
+#[derive(Parser)]
+struct TagOptions {
+    /// Episode id or id prefix, file name, release date, or `latest`
+    #[clap(value_parser)]
+    episode: String,
+    /// The episode's MP3, tagged in place
+    #[clap(value_parser)]
+    file: PathBuf,
+    /// Cover art, instead of reading the episode image from its URL
+    #[clap(long, value_parser)]
+    artwork: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+struct ApproveOptions {
+    /// Episode id or id prefix, file name, release date, or `latest`
+    #[clap(value_parser)]
+    episode: String,
+    /// Who approves it, as `Name <email>`
+    #[clap(long, value_parser)]
+    by: String,
+}
+
+#[derive(Parser)]
+struct PublishOptions {
+    /// Episode id or id prefix, file name, release date, or `latest`
+    #[clap(value_parser)]
+    episode: String,
+}
+
+#[derive(Parser)]
+struct DeleteOptions {
+    /// Episode id or id prefix, file name, release date, or `latest`
+    #[clap(value_parser)]
+    episode: String,
+    /// Also delete the episode's media, transcripts, and chapters from
+    /// storage. Restoring it from the trash won't bring them back
+    #[clap(long, action)]
+    remote: bool,
+    /// Render and upload the feed without the episode, before anything is
+    /// deleted from storage
+    #[clap(long, action)]
+    render: bool,
+    /// With --render, count checklist items that are confirmed by hand as
+    /// done, instead of asking
+    #[clap(long, action, requires = "render")]
+    yes: bool,
+}
+
+#[derive(Subcommand)]
+enum TrashCommand {
+    /// Show deleted episodes, most recent first
+    List,
+    /// Put the most recently deleted copy of an episode back
+    Restore(RestoreOptions),
+}
+
+#[derive(Parser)]
+struct RestoreOptions {
+    /// Episode id or id prefix, original file name, release date, or
+    /// `latest`
+    #[clap(value_parser)]
+    episode: String,
+}
+
+#[derive(Parser)]
+struct RunOptions {
+    /// Pipeline from `pipelines` in channel.yaml; lists them when left out
+    #[clap(value_parser)]
+    pipeline: Option<String>,
+}
+
+#[derive(Parser)]
+struct ValidateOptions {
+    /// Fail on warnings as well as errors
+    #[clap(long, action)]
+    strict: bool,
+}
+
+#[derive(Parser)]
+struct CompareOptions {
+    /// Episode id or id prefix, file name, release date, or `latest`
+    #[clap(value_parser)]
+    first: String,
+    /// Episode id or id prefix, file name, release date, or `latest`
+    #[clap(value_parser)]
+    second: String,
+    /// Also print fields that are the same
+    #[clap(long, action)]
+    all: bool,
+    /// Also diff the rendered <item> elements
+    #[clap(long, action)]
+    rendered: bool,
+}
+
+#[derive(Parser)]
+struct CompareFeedsOptions {
+    /// Path or URL of the feed rendered by podcast-ctl
+    #[clap(value_parser)]
+    ours: String,
+    /// Path or URL of the feed from the other host
+    #[clap(value_parser)]
+    reference: String,
+}
+
+#[derive(Parser)]
+struct SummarizeOptions {
+    /// Episode id or id prefix, file name, release date, or `latest`
+    #[clap(value_parser)]
+    episode: String,
+    /// Transcript file (plain text, SRT, or VTT)
+    #[clap(long, short, value_parser)]
+    transcript: PathBuf,
+    /// Write the drafts without showing the changes and asking first
+    #[clap(long, short, action)]
+    yes: bool,
+}
+
+#[derive(Parser)]
+struct KeywordOptions {
+    /// Episode id or id prefix, file name, release date, or `latest`
+    #[clap(value_parser)]
+    episode: String,
+    /// Transcript file (plain text, SRT, or VTT)
+    #[clap(long, short, value_parser)]
+    transcript: PathBuf,
+    /// Maximum number of keywords to suggest
+    #[clap(long, default_value_t = 10)]
+    limit: usize,
+    /// Replace the episode's keywords with the suggestions
+    #[clap(long, action)]
+    write: bool,
+    /// With --write, write without showing the change and asking first
+    #[clap(long, short, action, requires = "write")]
+    yes: bool,
+}
+
+#[derive(Parser)]
+struct SearchOptions {
+    /// Text to look for
+    #[clap(value_parser)]
+    query: String,
+    /// Search episode transcripts instead of episode metadata
+    #[clap(long, action)]
+    transcripts: bool,
+}
+
+#[derive(Parser)]
+struct ArtOptions {
+    /// Episode id or id prefix, file name, release date, or `latest`
+    #[clap(value_parser)]
+    episode: String,
+    /// PNG or JPEG to write; defaults to a PNG next to the episode file
+    #[clap(long, short, value_parser)]
+    out: Option<PathBuf>,
+    /// Upload the artwork and make it the episode's image
+    #[clap(long, action)]
+    upload: bool,
+}
+
+#[derive(Parser)]
+struct FixImageOptions {
+    /// JPEG or PNG
+    #[clap(value_parser)]
+    image: PathBuf,
+    /// Where to write the converted image; defaults to replacing it
+    #[clap(long, short, value_parser)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+struct ClipOptions {
+    /// Episode id or id prefix, file name, release date, or `latest`
+    #[clap(value_parser)]
+    episode: String,
+    /// Start of the clip, as MM:SS or HH:MM:SS
+    #[clap(long, value_parser = parse_clip_time)]
+    from: u64,
+    /// End of the clip, as MM:SS or HH:MM:SS
+    #[clap(long, value_parser = parse_clip_time)]
+    to: u64,
+    /// Local copy of the episode audio, instead of reading its media URL
+    #[clap(long, value_parser)]
+    audio: Option<PathBuf>,
+    /// Also render an audiogram video: artwork, an animated waveform, and
+    /// optionally captions
+    #[clap(long, action)]
+    audiogram: bool,
+    /// Shape of the audiogram: 1:1, 9:16, or 16:9. Repeat for several
+    #[clap(long, value_parser = clip::AspectRatio::parse, default_value = "1:1")]
+    aspect: Vec<clip::AspectRatio>,
+    /// Artwork for the audiogram, defaults to the episode image
+    #[clap(long, value_parser)]
+    artwork: Option<PathBuf>,
+    /// Burn the transcript into the audiogram as captions (implies --audiogram)
+    #[clap(long, action)]
+    captions: bool,
+    /// Transcript for --captions, when it isn't next to the episode file
+    #[clap(long, short, value_parser)]
+    transcript: Option<PathBuf>,
+    /// Directory to write clips to, defaults to `clips/` in the channel directory
+    #[clap(long, short, value_parser)]
+    out_dir: Option<PathBuf>,
+    /// Upload the clip and print its URL
+    #[clap(long, short, action)]
+    upload: bool,
+}
+
+impl From<&ClipOptions> for clip::ClipOptions {
+    fn from(data: &ClipOptions) -> Self {
+        clip::ClipOptions {
+            range: clip::ClipRange {
+                from: data.from,
+                to: data.to,
+            },
+            audio: data.audio.clone(),
+            audiogram: data.audiogram,
+            aspect: data.aspect.clone(),
+            artwork: data.artwork.clone(),
+            captions: data.captions,
+            transcript: data.transcript.clone(),
+            output: data.out_dir.clone(),
+            upload: data.upload,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum StateCommand {
+    /// Describe the workspace layout and what it holds
+    Show,
+    /// Delete workspace state. Clears the cache when no parts are named
+    Reset(ResetOptions),
+}
+
+#[derive(Parser)]
+struct ResetOptions {
+    /// Delete cached data, such as the transcript index
+    #[clap(long, action)]
+    cache: bool,
+    /// Delete the progress of interrupted uploads
+    #[clap(long, action)]
+    multipart: bool,
+    /// Delete the record of published objects
+    #[clap(long, action)]
+    journal: bool,
+    /// Remove a lock left behind by a command that crashed
+    #[clap(long, action)]
+    lock: bool,
+    /// Delete everything
+    #[clap(long, action)]
+    all: bool,
+}
+
+#[derive(Parser)]
+struct PromoOptions {
+    /// Episode id or id prefix, file name, release date, or `latest`
+    #[clap(value_parser)]
+    episode: String,
+    /// Only write posts for these networks, defaults to all of them
+    #[clap(long, value_enum)]
+    network: Vec<promo::Network>,
+    /// Post to every network with an account under `promo` in channel.yaml
+    #[clap(long, action)]
+    post: bool,
+}
+
+#[derive(Parser)]
+struct RotateUrlsOptions {
+    /// The new token, instead of a random one
+    #[clap(long, value_parser)]
+    token: Option<String>,
+    /// Count checklist items that are confirmed by hand as done, instead of
+    /// asking, for CI
+    #[clap(long, action)]
+    yes: bool,
+}
+
+#[derive(Parser)]
+struct StatsOptions {
+    /// Read logs from this directory, like one `aws s3 sync` copied them
+    /// to, instead of from the log bucket
+    #[clap(long, value_parser)]
+    logs: Option<PathBuf>,
+    /// Only count downloads on or after this day, as YYYY-MM-DD
+    #[clap(long, value_parser)]
+    since: Option<NaiveDate>,
+    /// Only count downloads on or before this day, as YYYY-MM-DD
+    #[clap(long, value_parser)]
+    until: Option<NaiveDate>,
+    /// Sum downloads by day or week
+    #[clap(long, value_enum, default_value = "day")]
+    by: stats::Period,
+    /// Count downloads of each episode by country and region instead,
+    /// looking addresses up offline in a MaxMind database
+    #[clap(long, action, conflicts_with = "by")]
+    geography: bool,
+    /// The MaxMind database, like GeoLite2-City.mmdb, instead of
+    /// `analytics.geoipDatabase`
+    #[clap(long, value_parser)]
+    geoip: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum AnalyticsCommand {
+    /// Compare the latest episodes' downloads in their first days, with
+    /// those still in the window projected to its end
+    Compare(AnalyticsCompareOptions),
+}
+
+#[derive(Parser)]
+struct AnalyticsCompareOptions {
+    /// Days after release to count, like 7d or 2w
+    #[clap(long, value_parser = stats::parse_window, default_value = "7d")]
+    window: u64,
+    /// How many of the latest released episodes to compare
+    #[clap(long, default_value_t = 10)]
+    episodes: usize,
+    /// Read logs from this directory instead of from the log bucket
+    #[clap(long, value_parser)]
+    logs: Option<PathBuf>,
+    /// Print CSV, for spreadsheets, instead of a table
+    #[clap(long, action)]
+    csv: bool,
+}
+
+#[derive(Parser)]
+struct BackupOptions {
+    /// Where to write the backup; objects already there are replaced
+    #[clap(value_parser)]
+    dir: PathBuf,
+}
+
+#[derive(Parser)]
+struct RestoreBackupOptions {
+    /// A directory `backup` wrote
+    #[clap(value_parser)]
+    dir: PathBuf,
+}
+
+#[derive(Parser)]
+struct PurgeAnalyticsOptions {
+    /// Delete logs written before this day, as YYYY-MM-DD, instead of
+    /// those older than `analytics.retentionDays`
+    #[clap(long, value_parser)]
+    before: Option<NaiveDate>,
+    /// Purge logs in this directory instead of in the log bucket
+    #[clap(long, value_parser)]
+    logs: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum NotifyCommand {
+    /// Send the feed's URL to Podping, for Podcasting 2.0 apps
+    Podping,
+}
+
+/// The end of the given day, or an exact RFC 3339 time.
+fn parse_as_of(text: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(23, 59, 59))
+        .map(|time| Utc.from_utc_datetime(&time))
+        .ok_or_else(|| format!("'{}' is not a date like 2023-06-01", text))
+}
+
+fn parse_clip_time(text: &str) -> Result<u64, String> {
+    transcript::parse_timestamp(text)
+        .ok_or_else(|| format!("'{}' is not a MM:SS or HH:MM:SS time", text))
+}
+
+/// human-panic's crash report, for release builds. Its `setup_panic!`
+/// names the hook's argument `PanicInfo`, which Rust 1.81 deprecated.
+fn setup_human_panic() {
+    if human_panic::PanicStyle::default() == human_panic::PanicStyle::Debug {
+        return;
+    }
+    let meta = human_panic::metadata!();
+    std::panic::set_hook(Box::new(move |info| {
+        let file_path = human_panic::handle_dump(&meta, info);
+        human_panic::print_msg(file_path, &meta)
+            .expect("human-panic: printing error message to console failed");
+    }));
+}
+
+/// Run the command line and exit with its status.
+pub fn main() {
+    if report::enabled() {
+        report::setup_panic();
+    } else {
+        setup_human_panic();
+    }
+    if let Err(error) = run() {
+        report::error(&error);
+        eprintln!("{}", t!("error", message = error.localized()));
+        std::process::exit(error.exit_code());
+    }
+}
+
+fn run() -> Result<(), CliError> {
+    dotenv::dotenv().ok();
+    // Colors have to be settled before the arguments are parsed, since clap
+    // colors its own errors and help
+    let screen_reader = output::select(
+        std::env::args_os().any(|arg| arg == OsStr::new("--screen-reader")),
+    );
+    let color = if screen_reader {
+        clap::ColorChoice::Never
+    } else {
+        clap::ColorChoice::Auto
+    };
+    let mut command = None;
+    let cli = Cli::command()
+        .color(color)
+        .try_get_matches()
+        .and_then(|matches| {
+            report::set_command(matches.subcommand_name());
+            command = matches.subcommand_name().map(str::to_owned);
+            Cli::from_arg_matches(&matches)
+        })
+        .unwrap_or_else(|e| e.exit());
+    let mut logger = env_logger::Builder::from_default_env();
+    if screen_reader {
+        logger.write_style(env_logger::WriteStyle::Never);
+    }
+    logger.init();
+    i18n::select(cli.global.lang.as_deref());
+
+    let mut context = events::Context::new()
+        .dry_run(cli.global.dry_run)
+        .screen_reader(screen_reader)
+        .quiet(cli.global.quiet)
+        .output_format(cli.global.output, command.as_deref())
+        .listen(events::print_warnings);
+    if cli.global.output == output::OutputFormat::Json {
+        context = context.listen(output::record_upload);
+    }
+    if cli.global.events {
+        context = context.listen(events::write_json);
+    }
+    let result = context.sync_scope(|| run_cli(cli, &context));
+    context.sync_scope(|| output::finish(result.as_ref().err().map(CliError::localized)));
+    result
+}
+
+/// Run the command line's command in `context`.
+fn run_cli(cli: Cli, context: &events::Context) -> Result<(), CliError> {
+    // Global arguments can't conflict with, or stand in for, the top
+    // level's in clap
+    let picked = [cli.channel_file.is_some(), cli.channel.is_some(), cli.all];
+    match picked.iter().filter(|picked| **picked).count() {
+        0 => Cli::command()
+            .error(
+                clap::ErrorKind::MissingRequiredArgument,
+                "give --channel-file, or --channel or --all with a workspace file",
+            )
+            .exit(),
+        1 => {}
+        _ => Cli::command()
+            .error(
+                clap::ErrorKind::ArgumentConflict,
+                "give only one of --channel-file, --channel, and --all",
+            )
+            .exit(),
+    }
+    if cli.all {
+        return run_all(&cli.workspace, cli.global, &cli.command, context);
+    }
+    let channel_file = match (cli.channel_file, &cli.channel) {
+        (Some(channel_file), _) => channel_file,
+        (None, Some(name)) => channels::WorkspaceFile::load(&cli.workspace)?.channel_file(name)?,
+        (None, None) => unreachable!("clap requires --channel-file, --channel, or --all"),
+    };
+
+    if let Commands::Init(options) = &cli.command {
+        return init::init_channel(&channel_file, options.force);
+    }
+
+    if let Commands::ImportBundle(options) = &cli.command {
+        return bundle::import(&channel_file, &options.bundle, options.force);
+    }
+
+    if let Commands::GenerateFixtures(options) = &cli.command {
+        return fixtures::generate_fixtures(
+            &channel_file,
+            options.force,
+            &fixtures::FixtureParams {
+                episodes: options.episodes,
+                seed: options.seed,
+                audio_seconds: Some(options.audio_seconds).filter(|_| options.audio),
+            },
+        );
+    }
+
+    if !channel_file.exists() {
+        panic!("'{:?}' doesn't exist.", channel_file);
+    }
+
+    setup(&cli.global, &cli.command)?;
+
+    if let Commands::External(args) = &cli.command {
+        return plugins::run_external(&channel_file, cli.global.offline, args);
+    }
+
+    run_channel(channel_file, cli.global, cli.command, context)
+}
+
+/// What every command needs set up before it runs, once per run.
+fn setup(global: &GlobalOptions, command: &Commands) -> Result<(), CliError> {
+    deprecations::allow(&global.allow_deprecated);
+    if global.offline {
+        if let Some(reason) = command.network_use() {
+            return Err(CliError::Offline(reason));
+        }
+    }
+    Ok(())
+}
+
+/// `render-channel --all` and `sync --all`: the command for each channel in
+/// the workspace file, going on to the next when one fails.
+fn run_all(
+    workspace: &Path,
+    global: GlobalOptions,
+    command: &Commands,
+    context: &events::Context,
+) -> Result<(), CliError> {
+    let workspace = channels::WorkspaceFile::load(workspace)?;
+    setup(&global, command)?;
+    let mut failed = Vec::new();
+    for (name, channel_file) in workspace.all() {
+        let command = match command {
+            Commands::RenderChannel(options) => Commands::RenderChannel(options.clone()),
+            Commands::Sync(options) => Commands::Sync(options.clone()),
+            _ => {
+                return Err(CliError::WorkspaceFile(
+                    "--all only works with render-channel and sync".to_owned(),
+                ))
+            }
+        };
+        let path = channel_file.display().to_string();
+        outln!("{}", t!("channel-heading", name = name.as_str(), path = path));
+        let ran = run_channel(channel_file, global.clone(), command, context);
+        if let Err(e) = ran {
+            eprintln!("{}", t!("error", message = e.localized()));
+            failed.push(name);
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::ChannelsFailed(failed.join(", ")))
+    }
+}
+
+/// Load `channel_file` and run `command` on it.
+fn run_channel(
+    channel_file: PathBuf,
+    global: GlobalOptions,
+    command: Commands,
+    context: &events::Context,
+) -> Result<(), CliError> {
+    let mut episode_dir = channel_file.clone();
+    episode_dir.pop();
+    episode_dir.push("episodes");
+
+    let channel_file_text = match &command {
+        Commands::RenderChannel(RenderOptions {
+            as_of: Some(as_of), ..
+        }) => match history::file_as_of(&channel_file, *as_of) {
+            Some(text) => String::from_utf8_lossy(&text).into_owned(),
+            None => {
+                eprintln!(
+                    "{}",
+                    t!(
+                        "no-history-file",
+                        path = channel_file.display().to_string(),
+                        date = as_of.to_string()
+                    )
+                );
+                fs::read_to_string(&channel_file)?
+            }
+        },
+        _ => fs::read_to_string(&channel_file)?,
+    };
+    let format = formats::Format::of(&channel_file);
+    let channel_config = parse_channel_config(format, &channel_file_text, &global.overrides)?;
+    formats::set_new_episodes(format);
+    // `migrate` is what the warnings say to run
+    if !matches!(command, Commands::Migrate(_)) {
+        if let Ok(document) = format.parse_value(channel_file_text.as_bytes()) {
+            deprecations::check_channel(&document);
+        }
+    }
+    report::set_channel(&channel_config);
+    storage::check(&channel_config.publishing)?;
+
+    info!("Channel Config: {:?}", channel_config);
+
+    if let Some(operation) = command.operation() {
+        let channel_dir = episode_dir.parent().unwrap_or(&episode_dir);
+        roles::check(&channel_config.roles, channel_dir, operation)?;
+    }
+
+    parsed_main(
+        channel_file,
+        episode_dir,
+        channel_config,
+        global,
+        command,
+        context,
+    )
+}
+
+#[tokio::main]
+async fn parsed_main(
+    channel_file: PathBuf,
+    episode_dir: PathBuf,
+    channel_config: ChannelConfig,
+    global: GlobalOptions,
+    commands: Commands,
+    context: &events::Context,
+) -> Result<(), CliError> {
+    match commands {
+        Commands::RenderChannel(data) if data.watch => {
+            let debounce = std::time::Duration::from_secs(data.debounce);
+            let options = data.library(&global);
+            render::watch(&channel_file, &episode_dir, &options, debounce, context).await
+        }
+        Commands::RenderChannel(data) => {
+            let options = data.library(&global);
+            render::render_channel(&episode_dir, channel_config, &options, context).await
+        }
+        Commands::Diff(data) => {
+            let (against, include_future) = (data.against, data.include_future);
+            let offline = global.offline;
+            render::diff(&episode_dir, channel_config, against, include_future, offline, context)
+                .await
+        }
+        Commands::RenderSite(data) => {
+            let options = render::SiteOptions {
+                output: data.out_dir,
+                templates: data.templates,
+                upload: data.upload,
+            };
+            render::render_site(&episode_dir, &channel_config, &options, context).await
+        }
+        Commands::Sync(data) => {
+            let media = data.media.as_deref();
+            render::sync(&episode_dir, channel_config, media, data.yes, context).await
+        }
+        Commands::Daemon(data) => {
+            let address = std::net::SocketAddr::new(data.address, data.port);
+            let dashboard = data.dashboard_port.map(|port| {
+                let address = std::net::SocketAddr::new(data.address, port);
+                dashboard::serve(address, episode_dir.clone(), channel_config)
+            });
+            let daemon = daemon::serve(address, &channel_file, &global, move || {
+                get_all_episode_files(&episode_dir)?
+                    .into_iter()
+                    .map(|(path, episode)| Ok((path, serde_json::to_value(&episode)?)))
+                    .collect()
+            });
+            match dashboard {
+                Some(dashboard) => tokio::try_join!(daemon, dashboard).map(|_| ()),
+                None => daemon.await,
+            }
+        }
+        Commands::Serve(data) => {
+            let media_dir = data.media.unwrap_or_else(|| episode_dir.clone());
+            let address = std::net::SocketAddr::new(data.address, data.port);
+            let render_media_dir = media_dir.clone();
+            serve::serve(address, &media_dir, move |base_url| {
+                render::render_preview(&episode_dir, &channel_config, &render_media_dir, base_url)
+            })
+            .await
+        }
+        Commands::CreateEpisode(data) => {
+            create::create_episode(&episode_dir, channel_config, data.into()).await.map(|_| ())
+        }
+        Commands::CreateEpisodes(data) => {
+            create::create_episodes(&episode_dir, channel_config, data.into()).await
+        }
+        Commands::WatchInbox(data) => {
+            create::watch_inbox(&episode_dir, channel_config, data.into()).await
+        }
+        Commands::ImportEmail(data) => {
+            create::import_email(&episode_dir, channel_config, data.into()).await
+        }
+        Commands::EditEpisode(data) => {
+            let changes = edit::EpisodeChanges {
+                title: data.title,
+                description: data.description,
+                summary: data.summary,
+                link: data.link,
+                season: data.season,
+                episode_number: data.episode_number,
+            };
+            let (open, yes) = (data.open, data.yes);
+            edit::edit_episode(&episode_dir, &channel_config, &data.episode, changes, open, yes)
+        }
+        Commands::Run(data) => pipeline::run(
+            &channel_file,
+            &global,
+            &channel_config.pipelines,
+            data.pipeline.as_deref(),
+            |args| {
+                Cli::command()
+                    .try_get_matches_from(args)
+                    .map(|_| ())
+                    .map_err(|e| {
+                        let message = e.to_string();
+                        let first_line = message.lines().next().unwrap_or_default();
+                        first_line.trim_start_matches("error: ").to_owned()
+                    })
+            },
+        ),
+        Commands::AddTrailer(data) => {
+            create::add_trailer(&channel_file, channel_config, data.into()).await
+        }
+        Commands::ExportBundle(data) => {
+            let episodes = get_all_episodes(&episode_dir)?;
+            bundle::export(
+                &channel_file,
+                &channel_config,
+                &episode_dir,
+                &episodes,
+                data.media.as_deref(),
+                &data.bundle,
+            )
+        }
+        Commands::Encrypt(data) => {
+            crypto::encrypt_episode(&episode_dir, &channel_config, &data.episode, &data.media)
+        }
+        Commands::Decrypt(data) => crypto::decrypt_episode(&episode_dir, &channel_config, &data.episode, &data.media),
+        Commands::BulkEdit(data) => bulk::bulk_edit(
+            get_all_episode_files(&episode_dir)?,
+            &data.set,
+            &data.filter,
+            data.yes,
+            &channel_config,
+        ),
+        Commands::ExportCsv(data) => spreadsheet::export_csv(
+            &episode_dir,
+            get_all_episode_files(&episode_dir)?,
+            data.out.as_deref(),
+        ),
+        Commands::ImportCsv(data) => spreadsheet::import_csv(
+            get_all_episode_files(&episode_dir)?,
+            &data.input,
+            data.yes,
+            &channel_config,
+        ),
+        Commands::ConvertConfig(data) => {
+            formats::convert_show(&channel_file, &episode_dir, data.files, data.to, &channel_config)
+        }
+        Commands::Migrate(data) => deprecations::migrate(&channel_file, data.yes),
+        Commands::Renumber(data) => {
+            crypto::require_readable(&episode_dir)?;
+            numbering::renumber(
+                get_all_episode_files(&episode_dir)?,
+                data.by_date,
+                data.yes,
+                &channel_config,
+            )
+        }
+        Commands::Tag(data) => {
+            let artwork = data.artwork.as_deref();
+            id3tags::tag_episode(&episode_dir, &channel_config, &data.episode, &data.file, artwork)
+                .await
+        }
+        Commands::CheckAudio(data) => quality::report(&channel_config.quality, &data.file),
+        Commands::Approve(data) => {
+            approvals::approve(&episode_dir, &channel_config, &data.episode, &data.by)
+        }
+        Commands::Publish(data) => edit::publish_draft(&episode_dir, &channel_config, &data.episode),
+        Commands::DeleteEpisode(data) => {
+            let render = data.render.then(|| render::RenderOptions {
+                offline: global.offline,
+                ..render::RenderOptions::publish(data.yes)
+            });
+            trash::delete_episode(&episode_dir, &channel_config, &data.episode, data.remote, render)
+                .await
+        }
+        Commands::Trash(TrashCommand::List) => trash::print_list(&episode_dir),
+        Commands::Trash(TrashCommand::Restore(data)) => {
+            let restored = trash::restore(&episode_dir, &channel_config, &data.episode)?;
+            outln!("{}", t!("restored", path = restored.display().to_string()));
+            output::record("restored", &restored);
+            Ok(())
+        }
+        Commands::Validate(data) => {
+            validate::run(&channel_config, &episode_dir, global.offline, data.strict).await
+        }
+        Commands::VerifyArchive => {
+            let mut channel_dir = episode_dir.clone();
+            channel_dir.pop();
+            let workspace = state::Workspace::new(&channel_dir);
+            let client = http::HttpClient::new(&channel_config.http)?;
+            let quiet = global.quiet;
+            archive::verify_archive(&channel_config.archive, &workspace, &client, quiet).await
+        }
+        Commands::Prune => {
+            let mut channel_dir = episode_dir.clone();
+            channel_dir.pop();
+            let workspace = state::Workspace::new(&channel_dir);
+            crypto::require_readable(&episode_dir)?;
+            let episodes = get_all_episodes(&episode_dir)?;
+            prune::prune(&channel_config.publishing, &workspace, &episode_dir, &episodes).await
+        }
+        Commands::Backup(options) => {
+            let mut channel_dir = episode_dir.clone();
+            channel_dir.pop();
+            let workspace = state::Workspace::new(&channel_dir);
+            let episodes = get_all_episodes(&episode_dir)?;
+            let client = http::HttpClient::new(&channel_config.http)?;
+            backup::backup(&channel_config, &workspace, &episodes, &client, &options.dir).await
+        }
+        Commands::Restore(options) => {
+            let mut channel_dir = episode_dir.clone();
+            channel_dir.pop();
+            let workspace = state::Workspace::new(&channel_dir);
+            backup::restore(&channel_config, &workspace, &options.dir).await
+        }
+        Commands::Stats(options) => {
+            let episodes = index::episodes(&channel_config, &episode_dir)?;
+            let analytics = &channel_config.analytics;
+            let geoip = options.geoip.as_ref().or(analytics.geoip_database.as_ref());
+            let geography = match (options.geography, geoip) {
+                (false, _) => None,
+                (true, Some(geoip)) => Some(geoip.as_path()),
+                (true, None) => {
+                    return Err(CliError::GeoipError(
+                        "set `analytics.geoipDatabase` in channel.yaml, or pass --geoip"
+                            .to_owned(),
+                    ))
+                }
+            };
+            let options = stats::StatsOptions {
+                logs: options.logs.as_deref(),
+                since: options.since,
+                until: options.until,
+                by: options.by,
+                geography,
+            };
+            stats::stats(analytics, &channel_config.publishing, &episodes, &options).await
+        }
+        Commands::Analytics(AnalyticsCommand::Compare(options)) => {
+            let count = options.episodes;
+            let recent = index::latest_released(&channel_config, &episode_dir, Utc::now(), count)?;
+            let options = stats::CompareOptions {
+                logs: options.logs.as_deref(),
+                window: options.window,
+                csv: options.csv,
+            };
+            let analytics = &channel_config.analytics;
+            stats::compare(analytics, &channel_config.publishing, &recent, &options).await
+        }
+        Commands::PurgeAnalytics(options) => {
+            let options = stats::PurgeOptions {
+                logs: options.logs.as_deref(),
+                before: options.before,
+            };
+            stats::purge(&channel_config.analytics, &channel_config.publishing, &options).await
+        }
+        Commands::CheckLinks => {
+            let episodes = get_all_episodes(&episode_dir)?;
+            let client = http::HttpClient::new(&channel_config.http)?;
+            links::check_links(&channel_config, &episodes, &client).await
+        }
+        Commands::VerifyRemote => {
+            let episodes = get_all_episodes(&episode_dir)?;
+            let client = http::HttpClient::new(&channel_config.http)?;
+            links::verify_remote(&episodes, &client).await
+        }
+        Commands::VerifyProvider => {
+            let client = http::HttpClient::new(&channel_config.http)?;
+            provider::verify_provider(&channel_config.publishing, &client).await
+        }
+        Commands::Compare(data) => {
+            let timezone = channel_config.channel.tz()?;
+            let (_, first) = find_episode(&episode_dir, &data.first, timezone)?;
+            let (_, second) = find_episode(&episode_dir, &data.second, timezone)?;
+            compare::compare_episodes(&first, &second, data.all, data.rendered)
+        }
+        Commands::CompareFeeds(data) => {
+            let client = http::HttpClient::new(&channel_config.http)?;
+            compare::compare_feeds(&data.ours, &data.reference, &client).await
+        }
+        Commands::Summarize(data) => {
+            let transcript = &data.transcript;
+            summarize::summarize(&episode_dir, &channel_config, &data.episode, transcript, data.yes)
+                .await
+        }
+        Commands::Search(data) => {
+            search::search(&channel_config, &episode_dir, &data.query, data.transcripts)
+        }
+        Commands::Reindex => {
+            let count = index::rebuild(&channel_config, &episode_dir)?;
+            outln!("{}", t!("reindexed", count = count));
+            output::record("indexed", count);
+            Ok(())
+        }
+        Commands::Clip(data) => {
+            let options = clip::ClipOptions::from(&data);
+            clip::clip_episode(&episode_dir, &channel_config, &data.episode, &options).await
+        }
+        Commands::GenerateArt(data) => {
+            art::generate_episode_art(
+                &episode_dir,
+                &channel_config,
+                &data.episode,
+                data.out,
+                data.upload,
+            )
+            .await
+        }
+        Commands::FixImage(data) => images::fix_image(&data.image, data.out.as_deref()),
+        Commands::Promo(data) => {
+            promo::promote(&channel_config, &episode_dir, &data.episode, &data.network, data.post)
+                .await
+        }
+        Commands::Notify(NotifyCommand::Podping) => podping::notify_feed(&channel_config).await,
+        Commands::RotateUrls(options) => {
+            let token = options.token.clone();
+            render::rotate_urls(&episode_dir, channel_config, token, options.yes, context).await
+        }
+        Commands::Info => {
+            info::print(&channel_file, &channel_config, &global.overrides, global.offline)
+        }
+        Commands::State(command) => {
+            let mut channel_dir = episode_dir.clone();
+            channel_dir.pop();
+            let workspace = state::Workspace::new(&channel_dir);
+            match command {
+                StateCommand::Show => workspace.show(),
+                StateCommand::Reset(options) => {
+                    let nothing_named =
+                        !(options.cache || options.multipart || options.journal || options.lock);
+                    workspace.reset(&state::ResetParts {
+                        cache: options.all || options.cache || nothing_named,
+                        multipart: options.all || options.multipart,
+                        journal: options.all || options.journal,
+                        lock: options.all || options.lock,
+                    })
+                }
+            }
+        }
+        Commands::Keywords(data) => keywords::suggest(
+            &episode_dir,
+            &channel_config,
+            &data.episode,
+            &data.transcript,
+            data.limit,
+            data.write,
+            data.yes,
+        ),
+        Commands::Init(_)
+        | Commands::GenerateFixtures(_)
+        | Commands::ImportBundle(_)
+        | Commands::External(_) => unreachable!(
+            "init, generate-fixtures, import-bundle, and external subcommands are dispatched before loading the channel"
+        ),
+    }
+}
@@ -0,0 +1,379 @@
+//! `daemon` serves the show's core operations over JSON-RPC 2.0, for
+//! desktop and web frontends that would rather not drive the command line
+//! themselves. Requests and responses are JSON objects, one per line, over
+//! a local TCP connection:
+//!
+//! ```json
+//! {"jsonrpc":"2.0","id":1,"token":"<token>","method":"create","params":{"file":"ep.mp3","title":"Pilot","date":"2024-01-05"}}
+//! {"jsonrpc":"2.0","method":"progress","params":{"event":"uploadStarted","key":"show/artifacts/2024-01-05.mp3","bytes":52428800}}
+//! {"jsonrpc":"2.0","id":1,"result":{"output":""}}
+//! ```
+//!
+//! Every request carries the `token` the daemon writes to
+//! `.podcast-ctl/daemon-token` when it starts, which only the owner can
+//! read, so neither other users nor web pages posting to the port can run
+//! anything. Connections that start like an HTTP request are closed
+//! without an answer.
+//!
+//! `list` returns the episodes, each with the `file` it is read from.
+//! `create`, `render`, `publish`, and `validate` run `create-episode`,
+//! `render-channel`, `render-channel --upload --yes`, and `validate` with
+//! `params` as their options, written as in pipelines (`episodeNumber: 3`
+//! is `--episode-number 3`), and `create` takes the audio as `file`. Only
+//! the options listed for each method in [`allowed_params`] are taken, so
+//! requests can't change global options like `--channel-file`. While they
+//! run, their progress events are sent as `progress` notifications, and
+//! their result holds what they printed.
+//!
+//! Each of those runs as its own podcast-ctl process, with the daemon's
+//! global options, so it checks `roles` and `--offline` for itself. The
+//! daemon listens on localhost unless told otherwise.
+//!
+//! With `--dashboard-port`, it also serves a read-only web page of the
+//! show's state, for co-hosts; see `dashboard`.
+
+use super::{pipeline, GlobalOptions};
+use crate::events;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::state::Workspace;
+use crate::CliError;
+use log::debug;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::process::Command;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The command ran, and failed
+const COMMAND_FAILED: i64 = -32000;
+/// The request's token is missing or wrong
+const UNAUTHORIZED: i64 = -32001;
+
+/// A JSON-RPC error response's code, message, and data.
+struct RpcError(i64, String, Option<Value>);
+
+struct Daemon<F> {
+    global_args: Vec<String>,
+    token: String,
+    list: F,
+}
+
+/// Serve requests until interrupted. `list` reads the episodes afresh for
+/// every `list` request, so edits to episode files show up.
+pub async fn serve<F>(
+    address: SocketAddr,
+    channel_file: &Path,
+    global: &GlobalOptions,
+    list: F,
+) -> Result<(), CliError>
+where
+    F: Fn() -> Result<Vec<(PathBuf, Value)>, CliError> + Send + Sync + 'static,
+{
+    let channel_dir = channel_file.parent().unwrap_or_else(|| Path::new("."));
+    let workspace = Workspace::new(channel_dir);
+    let token = workspace.new_daemon_token()?;
+    let listener = TcpListener::bind(address).await?;
+    outln!(
+        "{}",
+        t!(
+            "daemon-listening",
+            address = listener.local_addr()?.to_string(),
+            token = workspace.daemon_token_path().display().to_string()
+        )
+    );
+
+    let daemon = Arc::new(Daemon {
+        global_args: pipeline::global_args(channel_file, global),
+        token,
+        list,
+    });
+    let context = events::current();
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let daemon = daemon.clone();
+        let context = context.clone();
+        tokio::spawn(async move {
+            let handled = handle(stream, daemon.as_ref());
+            if let Err(e) = context.scope(handled).await {
+                debug!("Connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// Answer a connection's requests, in order, until it closes.
+async fn handle<F>(stream: TcpStream, daemon: &Daemon<F>) -> Result<(), CliError>
+where
+    F: Fn() -> Result<Vec<(PathBuf, Value)>, CliError>,
+{
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    let mut first = true;
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        // A browser's request, whose body could otherwise pass for one
+        if std::mem::take(&mut first) && is_http(&line) {
+            debug!("Closing a connection that sent HTTP");
+            return Ok(());
+        }
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let error = RpcError(PARSE_ERROR, e.to_string(), None);
+                send(&mut write, &error_response(Value::Null, error)).await?;
+                continue;
+            }
+        };
+        // Requests without an id are notifications, and get no response
+        let id = request.get("id").cloned();
+        if request.get("token").and_then(Value::as_str) != Some(daemon.token.as_str()) {
+            let error = RpcError(
+                UNAUTHORIZED,
+                "the token is missing or wrong".to_owned(),
+                None,
+            );
+            send(
+                &mut write,
+                &error_response(id.unwrap_or(Value::Null), error),
+            )
+            .await?;
+            return Ok(());
+        }
+        let result = call(&request, daemon, &mut write).await;
+        let id = match id {
+            Some(id) => id,
+            None => continue,
+        };
+        let response = match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err(error) => error_response(id, error),
+        };
+        send(&mut write, &response).await?;
+    }
+    Ok(())
+}
+
+async fn call<F>(
+    request: &Value,
+    daemon: &Daemon<F>,
+    write: &mut OwnedWriteHalf,
+) -> Result<Value, RpcError>
+where
+    F: Fn() -> Result<Vec<(PathBuf, Value)>, CliError>,
+{
+    let method = request
+        .get("method")
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError(INVALID_REQUEST, "a request needs a method".to_owned(), None))?;
+    debug!("{} {}", method, request);
+    let params = match request.get("params") {
+        None | Some(Value::Null) => BTreeMap::new(),
+        Some(params) => serde_json::from_value(params.clone()).map_err(|_| {
+            RpcError(
+                INVALID_PARAMS,
+                "params have to be an object of options".to_owned(),
+                None,
+            )
+        })?,
+    };
+
+    let (command, fixed): (&str, &[&str]) = match method {
+        "list" => {
+            let episodes = (daemon.list)()
+                .map_err(|e| RpcError(COMMAND_FAILED, e.localized(), None))?
+                .into_iter()
+                .map(|(path, mut episode)| {
+                    episode["file"] = json!(path.display().to_string());
+                    episode
+                })
+                .collect();
+            return Ok(Value::Array(episodes));
+        }
+        "create" => ("create-episode", &["noInteractive"]),
+        "render" => ("render-channel", &[]),
+        "publish" => ("render-channel", &["upload", "yes"]),
+        "validate" => ("validate", &[]),
+        _ => {
+            return Err(RpcError(
+                METHOD_NOT_FOUND,
+                format!("there is no method '{}'", method),
+                None,
+            ))
+        }
+    };
+    let mut options: BTreeMap<String, serde_yaml::Value> = params;
+    let allowed = allowed_params(method);
+    if let Some(param) = options
+        .keys()
+        .find(|param| !allowed.contains(&param.as_str()))
+    {
+        return Err(RpcError(
+            INVALID_PARAMS,
+            format!("'{}' is not an option of {}", param, method),
+            None,
+        ));
+    }
+    // The audio is create-episode's argument, after `--` so it can't be
+    // taken for an option
+    let file = match options.remove("file") {
+        Some(serde_yaml::Value::String(file)) => Some(file),
+        Some(_) => {
+            return Err(RpcError(
+                INVALID_PARAMS,
+                "file has to be a path".to_owned(),
+                None,
+            ))
+        }
+        None => None,
+    };
+    options.insert(
+        "step".to_owned(),
+        serde_yaml::Value::String(command.to_owned()),
+    );
+    for flag in fixed {
+        options.insert((*flag).to_owned(), serde_yaml::Value::Bool(true));
+    }
+    let mut args = pipeline::command_with_options(&options)
+        .map_err(|detail| RpcError(INVALID_PARAMS, detail, None))?;
+    if let Some(file) = file {
+        args.push("--".to_owned());
+        args.push(file);
+    }
+    run(&daemon.global_args, &args, write).await
+}
+
+/// The params each method takes. Anything else is refused rather than
+/// passed on, since it would become a command line option.
+fn allowed_params(method: &str) -> &'static [&'static str] {
+    match method {
+        "create" => &[
+            "file",
+            "title",
+            "date",
+            "timezone",
+            "transcript",
+            "keywords",
+            "notes",
+            "episodeType",
+            "explicit",
+            "draft",
+            "destination",
+            "description",
+            "summary",
+            "link",
+            "season",
+            "episode",
+            "image",
+            "tag",
+        ],
+        "render" | "publish" => &[
+            "format",
+            "strict",
+            "lenient",
+            "asOf",
+            "includeFuture",
+            "season",
+            "destination",
+        ],
+        "validate" => &["strict"],
+        _ => &[],
+    }
+}
+
+/// Whether `line` is an HTTP request line, like `POST / HTTP/1.1`.
+fn is_http(line: &str) -> bool {
+    line.split_whitespace()
+        .last()
+        .is_some_and(|version| version.starts_with("HTTP/"))
+}
+
+/// Run podcast-ctl, sending its progress events as notifications.
+async fn run(
+    global_args: &[String],
+    args: &[String],
+    write: &mut OwnedWriteHalf,
+) -> Result<Value, RpcError> {
+    let failed = |detail: String| RpcError(COMMAND_FAILED, detail, None);
+    let program = std::env::current_exe().map_err(|e| failed(e.to_string()))?;
+    let mut command = Command::new(program);
+    command.args(global_args);
+    if !global_args.iter().any(|arg| arg == "--events") {
+        command.arg("--events");
+    }
+    let mut child = command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| failed(e.to_string()))?;
+
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let read_output = tokio::spawn(async move {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).await.map(|_| output)
+    });
+
+    let mut messages = Vec::new();
+    if let Some(stderr) = child.stderr.take() {
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            match serde_json::from_str::<Value>(&line) {
+                Ok(event) if event.get("event").is_some() => {
+                    let notification =
+                        json!({"jsonrpc": "2.0", "method": "progress", "params": event});
+                    // A client that went away still lets the command finish
+                    let _ = send(write, &notification).await;
+                }
+                _ => messages.push(line),
+            }
+        }
+    }
+
+    let status = child.wait().await.map_err(|e| failed(e.to_string()))?;
+    let output = read_output
+        .await
+        .map_err(|e| failed(e.to_string()))?
+        .map_err(|e| failed(e.to_string()))?;
+    if !status.success() {
+        let detail = messages
+            .iter()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .cloned()
+            .unwrap_or_else(|| format!("exited with {}", status));
+        return Err(RpcError(
+            COMMAND_FAILED,
+            detail,
+            Some(json!({"output": output, "messages": messages})),
+        ));
+    }
+    Ok(json!({"output": output, "messages": messages}))
+}
+
+fn error_response(id: Value, RpcError(code, message, data): RpcError) -> Value {
+    let mut error = json!({"code": code, "message": message});
+    if let Some(data) = data {
+        error["data"] = data;
+    }
+    json!({"jsonrpc": "2.0", "id": id, "error": error})
+}
+
+async fn send(write: &mut OwnedWriteHalf, message: &Value) -> Result<(), CliError> {
+    let mut line = message.to_string();
+    line.push('\n');
+    write.write_all(line.as_bytes()).await?;
+    Ok(())
+}
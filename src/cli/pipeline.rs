@@ -0,0 +1,266 @@
+//! Named pipelines, for running a team's whole workflow with one command.
+//! `run release` runs the steps of `pipelines.release` in order, stopping
+//! at the first one that fails:
+//!
+//! ```yaml
+//! pipelines:
+//!   release:
+//!     - validate
+//!     - step: render
+//!       upload: true
+//!       yes: true
+//!     - shell: ./scripts/invalidate-cdn.sh
+//! ```
+//!
+//! A step is any podcast-ctl command or plugin, with its options as they are on the
+//! command line (`episodeNumber: 3` is `--episode-number 3`, `true` is a
+//! flag) and its arguments under `args`. `render` is `render-channel` and
+//! `upload` is `render-channel --upload`. `shell` steps run in the channel
+//! file's directory, with `PODCAST_CTL_CHANNEL_FILE` set. Each step runs as
+//! its own podcast-ctl process, so it checks `roles` and `--offline` for
+//! itself.
+
+use super::GlobalOptions;
+use crate::config::PipelineStep;
+use crate::i18n::t;
+use crate::output::{self, outln};
+use crate::CliError;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+
+/// How a step runs.
+enum Invocation {
+    /// podcast-ctl with these arguments, after the global ones
+    Command(Vec<String>),
+    Shell(String),
+}
+
+impl Invocation {
+    fn describe(&self) -> String {
+        match self {
+            Invocation::Command(args) => args.join(" "),
+            Invocation::Shell(command) => command.clone(),
+        }
+    }
+}
+
+/// Run the pipeline called `name`, or list the pipelines without one.
+/// `check` parses a full podcast-ctl command line, so every step is known
+/// to be valid before the first one runs.
+pub fn run<F>(
+    channel_file: &Path,
+    global: &GlobalOptions,
+    pipelines: &BTreeMap<String, Vec<PipelineStep>>,
+    name: Option<&str>,
+    check: F,
+) -> Result<(), CliError>
+where
+    F: Fn(&[String]) -> Result<(), String>,
+{
+    let name = match name {
+        Some(name) => name,
+        None => {
+            for (name, steps) in pipelines {
+                let steps: Vec<String> = steps
+                    .iter()
+                    .map(|step| resolve(step).map_or_else(|e| e, |step| step.describe()))
+                    .collect();
+                outln!("{}: {}", name, steps.join(", "));
+            }
+            return Ok(());
+        }
+    };
+    let steps = pipelines.get(name).ok_or_else(|| {
+        CliError::PipelineError(format!("channel.yaml has no pipeline named '{}'", name))
+    })?;
+
+    let global_args = global_args(channel_file, global);
+    let mut invocations = Vec::new();
+    for (index, step) in steps.iter().enumerate() {
+        let invalid = |detail: String| {
+            CliError::PipelineError(format!("step {} of '{}': {}", index + 1, name, detail))
+        };
+        let invocation = resolve(step).map_err(invalid)?;
+        if let Invocation::Command(args) = &invocation {
+            let mut line = vec!["podcast-ctl".to_owned()];
+            line.extend(global_args.iter().cloned());
+            line.extend(args.iter().cloned());
+            check(&line).map_err(invalid)?;
+        }
+        invocations.push(invocation);
+    }
+
+    let program = std::env::current_exe()?;
+    let channel_dir = match channel_file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    // Shell steps run in the channel's directory, not this one
+    let absolute_channel_file =
+        std::fs::canonicalize(channel_file).unwrap_or_else(|_| channel_file.to_path_buf());
+    let count = invocations.len();
+    for (index, invocation) in invocations.iter().enumerate() {
+        let description = invocation.describe();
+        outln!(
+            "{}",
+            t!(
+                "pipeline-step",
+                step = index + 1,
+                count = count,
+                description = description.as_str()
+            )
+        );
+        let status = match invocation {
+            Invocation::Command(args) => Command::new(&program)
+                .args(&global_args)
+                .args(args)
+                .stdout(output::child_stdout())
+                .status()?,
+            Invocation::Shell(_) if global.dry_run => {
+                outln!("{}", t!("pipeline-shell-skipped"));
+                continue;
+            }
+            Invocation::Shell(command) => Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .current_dir(channel_dir)
+                .env("PODCAST_CTL_CHANNEL_FILE", &absolute_channel_file)
+                .stdout(output::child_stdout())
+                .status()?,
+        };
+        if !status.success() {
+            return Err(CliError::PipelineError(format!(
+                "step {} of '{}' ({}) failed with {}",
+                index + 1,
+                name,
+                description,
+                status
+            )));
+        }
+    }
+    outln!("{}", t!("pipeline-done", name = name, count = count));
+    Ok(())
+}
+
+/// The options every step runs with: this channel file, and the global
+/// options `run` was given.
+pub fn global_args(channel_file: &Path, global: &GlobalOptions) -> Vec<String> {
+    let mut args = vec![
+        "--channel-file".to_owned(),
+        channel_file.display().to_string(),
+    ];
+    for (set, flag) in [
+        (global.offline, "--offline"),
+        (global.screen_reader, "--screen-reader"),
+        (global.dry_run, "--dry-run"),
+        (global.events, "--events"),
+        (global.quiet, "--quiet"),
+    ] {
+        if set {
+            args.push(flag.to_owned());
+        }
+    }
+    if let Some(lang) = &global.lang {
+        args.push("--lang".to_owned());
+        args.push(lang.clone());
+    }
+    for assignment in &global.overrides {
+        args.push("--set".to_owned());
+        args.push(assignment.to_string());
+    }
+    args
+}
+
+fn resolve(step: &PipelineStep) -> Result<Invocation, String> {
+    let args = match step {
+        PipelineStep::Name(name) => command(name),
+        PipelineStep::Options(options) => match options.get("shell") {
+            Some(Value::String(command)) if options.len() == 1 => {
+                return Ok(Invocation::Shell(command.clone()))
+            }
+            Some(Value::String(_)) => return Err("a shell step takes no options".to_owned()),
+            Some(_) => return Err("shell has to be a command line".to_owned()),
+            None => command_with_options(options)?,
+        },
+    };
+    if args.first().map(String::as_str) == Some("run") {
+        return Err("pipelines can't run other pipelines".to_owned());
+    }
+    Ok(Invocation::Command(args))
+}
+
+/// The command line for `{step: <name>, <option>: <value>...}`.
+pub fn command_with_options(options: &BTreeMap<String, Value>) -> Result<Vec<String>, String> {
+    let mut args = match options.get("step") {
+        Some(Value::String(name)) => command(name),
+        Some(_) => return Err("step has to be a command name".to_owned()),
+        None => return Err("a step needs a `step` or `shell`".to_owned()),
+    };
+    let mut positional = Vec::new();
+    for (option, value) in options {
+        match option.as_str() {
+            "step" => {}
+            "args" => positional = values(option, value)?,
+            _ => {
+                let flag = format!("--{}", kebab_case(option));
+                match value {
+                    Value::Bool(true) => args.push(flag),
+                    Value::Bool(false) | Value::Null => {}
+                    // Joined, so a value starting with `-` can't be taken
+                    // for another option
+                    value => {
+                        for value in values(option, value)? {
+                            args.push(format!("{}={}", flag, value));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    args.extend(positional);
+    Ok(args)
+}
+
+/// The command line for the step `name`.
+fn command(name: &str) -> Vec<String> {
+    match kebab_case(name).as_str() {
+        "render" => vec!["render-channel".to_owned()],
+        "upload" => vec!["render-channel".to_owned(), "--upload".to_owned()],
+        name => vec![name.to_owned()],
+    }
+}
+
+/// Scalars as they are written on the command line; lists give one each.
+fn values(option: &str, value: &Value) -> Result<Vec<String>, String> {
+    match value {
+        Value::Sequence(items) => items.iter().map(|item| scalar(option, item)).collect(),
+        value => scalar(option, value).map(|value| vec![value]),
+    }
+}
+
+fn scalar(option: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Number(value) => Ok(value.to_string()),
+        Value::Bool(value) => Ok(value.to_string()),
+        _ => Err(format!("{} has to be a value or a list of values", option)),
+    }
+}
+
+/// `episodeNumber` as the option `episode-number`.
+fn kebab_case(name: &str) -> String {
+    let mut kebab = String::new();
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            if !kebab.is_empty() {
+                kebab.push('-');
+            }
+            kebab.push(c.to_ascii_lowercase());
+        } else {
+            kebab.push(c);
+        }
+    }
+    kebab
+}
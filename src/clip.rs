@@ -0,0 +1,418 @@
+//! Cut short clips and audiograms out of an episode with ffmpeg, for social
+//! promotion.
+//!
+//! `ffmpeg` must be on the `PATH`, or set `FFMPEG` to its location.
+
+use crate::config::ChannelConfig;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::transcript::{parse_cues, Cue};
+use crate::upload;
+use crate::CliError;
+use log::{debug, info};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A range of an episode, in milliseconds from the start.
+#[derive(Debug, Clone, Copy)]
+pub struct ClipRange {
+    pub from: u64,
+    pub to: u64,
+}
+
+impl ClipRange {
+    pub fn duration(&self) -> u64 {
+        self.to - self.from
+    }
+}
+
+/// Copy `range` of `source` (a local file or a URL ffmpeg can read) into an
+/// mp3 at `output`.
+pub fn cut_audio(source: &str, range: ClipRange, output: &Path) -> Result<(), CliError> {
+    info!("Cutting {} into {}", source, output.display());
+    run_ffmpeg(
+        None,
+        &[
+            "-ss".to_owned(),
+            seconds(range.from),
+            "-t".to_owned(),
+            seconds(range.duration()),
+            "-i".to_owned(),
+            source.to_owned(),
+            "-vn".to_owned(),
+            "-codec:a".to_owned(),
+            "libmp3lame".to_owned(),
+            "-q:a".to_owned(),
+            "2".to_owned(),
+            path_arg(output),
+        ],
+    )
+}
+
+/// Shape of an audiogram video.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AspectRatio {
+    /// 1:1, for feeds
+    Square,
+    /// 9:16, for stories and reels
+    Vertical,
+    /// 16:9, for video sites
+    Landscape,
+}
+
+struct AudiogramLayout {
+    width: u32,
+    height: u32,
+    artwork_size: u32,
+    artwork_top: u32,
+    waveform_top: u32,
+    waveform_height: u32,
+}
+
+impl AspectRatio {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text {
+            "1:1" | "square" => Ok(AspectRatio::Square),
+            "9:16" | "vertical" => Ok(AspectRatio::Vertical),
+            "16:9" | "landscape" => Ok(AspectRatio::Landscape),
+            _ => Err(format!("'{}' is not one of 1:1, 9:16, or 16:9", text)),
+        }
+    }
+
+    /// Suffix for the video's file name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AspectRatio::Square => "square",
+            AspectRatio::Vertical => "vertical",
+            AspectRatio::Landscape => "landscape",
+        }
+    }
+
+    fn layout(&self) -> AudiogramLayout {
+        match self {
+            AspectRatio::Square => AudiogramLayout {
+                width: 1080,
+                height: 1080,
+                artwork_size: 560,
+                artwork_top: 120,
+                waveform_top: 720,
+                waveform_height: 160,
+            },
+            AspectRatio::Vertical => AudiogramLayout {
+                width: 1080,
+                height: 1920,
+                artwork_size: 880,
+                artwork_top: 280,
+                waveform_top: 1240,
+                waveform_height: 240,
+            },
+            AspectRatio::Landscape => AudiogramLayout {
+                width: 1920,
+                height: 1080,
+                artwork_size: 600,
+                artwork_top: 100,
+                waveform_top: 760,
+                waveform_height: 160,
+            },
+        }
+    }
+}
+
+/// Render `audio` as an audiogram: the artwork over a blurred copy of
+/// itself, an animated waveform, and optionally the transcript burned in as
+/// captions. `artwork` may be a local file or a URL.
+pub fn render_audiogram(
+    audio: &Path,
+    artwork: &str,
+    transcript: Option<&str>,
+    range: ClipRange,
+    aspect: AspectRatio,
+    output: &Path,
+) -> Result<(), CliError> {
+    let work_dir = fs::canonicalize(output.parent().unwrap_or_else(|| Path::new(".")))?;
+    let audio = fs::canonicalize(audio)?;
+    let layout = aspect.layout();
+
+    let mut filter = format!(
+        "[0:v]split=2[background][cover_art];\
+         [background]scale={w}:{h}:force_original_aspect_ratio=increase,crop={w}:{h},boxblur=30,eq=brightness=-0.25[bg];\
+         [cover_art]scale={art}:{art}[art];\
+         [1:a]showwaves=s={w}x{wave_h}:mode=cline:rate=25:colors=white[wave];\
+         [bg][art]overlay=(W-w)/2:{art_top}[cover];\
+         [cover][wave]overlay=0:{wave_top}:shortest=1",
+        w = layout.width,
+        h = layout.height,
+        art = layout.artwork_size,
+        art_top = layout.artwork_top,
+        wave_h = layout.waveform_height,
+        wave_top = layout.waveform_top,
+    );
+
+    let captions = output.with_extension("srt");
+    if let Some(transcript) = transcript {
+        fs::write(&captions, clip_captions(&parse_cues(transcript), range))?;
+        // The subtitles filter has its own escaping rules, so run ffmpeg next
+        // to the captions file and refer to it by a plain file name.
+        let captions_name = captions
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        write!(
+            filter,
+            "[waves];[waves]subtitles={}:force_style='Alignment=2,FontSize=14,MarginV=20'",
+            captions_name
+        )
+        .ok();
+    }
+    filter.push_str("[v]");
+
+    info!(
+        "Rendering {} audiogram {}",
+        aspect.as_str(),
+        output.display()
+    );
+    let result = run_ffmpeg(
+        Some(&work_dir),
+        &[
+            "-loop".to_owned(),
+            "1".to_owned(),
+            "-i".to_owned(),
+            artwork.to_owned(),
+            "-i".to_owned(),
+            path_arg(&audio),
+            "-filter_complex".to_owned(),
+            filter,
+            "-map".to_owned(),
+            "[v]".to_owned(),
+            "-map".to_owned(),
+            "1:a".to_owned(),
+            "-shortest".to_owned(),
+            "-codec:v".to_owned(),
+            "libx264".to_owned(),
+            "-pix_fmt".to_owned(),
+            "yuv420p".to_owned(),
+            "-codec:a".to_owned(),
+            "aac".to_owned(),
+            "-movflags".to_owned(),
+            "+faststart".to_owned(),
+            path_arg(&work_dir.join(output.file_name().unwrap_or_default())),
+        ],
+    );
+
+    if transcript.is_some() {
+        fs::remove_file(&captions).ok();
+    }
+    result
+}
+
+/// SRT captions for the cues inside `range`, re-timed to start at zero. A
+/// cue lasts until the next one starts, or until the end of the clip.
+pub fn clip_captions(cues: &[Cue], range: ClipRange) -> String {
+    let timed: Vec<(u64, &str)> = cues
+        .iter()
+        .filter_map(|cue| cue.start.map(|start| (start, cue.text.as_str())))
+        .collect();
+
+    let mut out = String::new();
+    let mut number = 1;
+    for (index, (start, text)) in timed.iter().enumerate() {
+        let end = timed
+            .get(index + 1)
+            .map(|(next, _)| *next)
+            .unwrap_or(range.to)
+            .min(range.to);
+        if end <= range.from || *start >= range.to {
+            continue;
+        }
+
+        let start = start.max(&range.from) - range.from;
+        let end = end - range.from;
+        writeln!(out, "{}", number).ok();
+        writeln!(out, "{} --> {}", srt_timestamp(start), srt_timestamp(end)).ok();
+        writeln!(out, "{}", text).ok();
+        writeln!(out).ok();
+        number += 1;
+    }
+
+    out
+}
+
+/// File name for a clip, e.g. `2022-09-01-session-12m34s-13m10s`.
+pub fn clip_name(episode_path: &Path, range: ClipRange) -> String {
+    let stem = episode_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "clip".to_owned());
+    format!(
+        "{}-{}-{}",
+        stem,
+        short_time(range.from),
+        short_time(range.to)
+    )
+}
+
+fn run_ffmpeg(work_dir: Option<&Path>, args: &[String]) -> Result<(), CliError> {
+    let program = std::env::var("FFMPEG").unwrap_or_else(|_| "ffmpeg".to_owned());
+    debug!("Running {} {:?}", program, args);
+
+    let mut command = Command::new(&program);
+    command
+        .args(["-hide_banner", "-loglevel", "error", "-y"])
+        .args(args)
+        .stdout(crate::output::child_stdout());
+    if let Some(dir) = work_dir {
+        command.current_dir(dir);
+    }
+
+    let status = command
+        .status()
+        .map_err(|e| CliError::ClipError(format!("unable to run {}: {}", program, e)))?;
+    if !status.success() {
+        return Err(CliError::ClipError(format!(
+            "{} exited with {}",
+            program, status
+        )));
+    }
+
+    Ok(())
+}
+
+fn path_arg(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn seconds(ms: u64) -> String {
+    format!("{}.{:03}", ms / 1000, ms % 1000)
+}
+
+fn srt_timestamp(ms: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+fn short_time(ms: u64) -> String {
+    let secs = ms / 1000;
+    format!("{}m{:02}s", secs / 60, secs % 60)
+}
+
+/// Where clips are written when `--out-dir` isn't given.
+pub fn default_output_dir(channel_dir: &Path) -> PathBuf {
+    channel_dir.join("clips")
+}
+
+/// What `clip` cuts, and what it makes from the clip.
+#[derive(Debug, Clone)]
+pub struct ClipOptions {
+    pub range: ClipRange,
+    /// Local copy of the episode audio, instead of its media URL
+    pub audio: Option<PathBuf>,
+    /// Also render an audiogram in each of `aspect`
+    pub audiogram: bool,
+    pub aspect: Vec<AspectRatio>,
+    /// Artwork for the audiogram, instead of the episode image
+    pub artwork: Option<PathBuf>,
+    /// Burn the transcript into the audiogram
+    pub captions: bool,
+    /// Transcript for `captions`, instead of the episode's
+    pub transcript: Option<PathBuf>,
+    /// Directory to write to, instead of [`default_output_dir`]
+    pub output: Option<PathBuf>,
+    /// Upload what was written under the publishing prefix's `clips/`
+    pub upload: bool,
+}
+
+/// `clip`: cut a clip of the episode `selector` picks, with audiograms
+/// when `options` asks for them, and upload them with `options.upload`.
+pub async fn clip_episode(
+    episode_dir: &Path,
+    channel_config: &ChannelConfig,
+    selector: &str,
+    options: &ClipOptions,
+) -> Result<(), CliError> {
+    let range = options.range;
+    if range.to <= range.from {
+        return Err(CliError::ClipError("--to must be after --from".to_owned()));
+    }
+
+    let (path, episode) = crate::find_episode(episode_dir, selector, channel_config.channel.tz()?)?;
+    let source = match &options.audio {
+        Some(audio) => audio.to_string_lossy().into_owned(),
+        None => episode.media.url.clone(),
+    };
+
+    let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+    let output_dir = options
+        .output
+        .clone()
+        .unwrap_or_else(|| default_output_dir(channel_dir));
+    fs::create_dir_all(&output_dir)?;
+
+    let name = clip_name(&path, range);
+    let audio = output_dir.join(format!("{}.mp3", name));
+    cut_audio(&source, range, &audio)?;
+    outln!("{}", t!("wrote-file", path = audio.display().to_string()));
+    let mut outputs = vec![audio.clone()];
+
+    if options.audiogram || options.captions {
+        let transcript = if options.captions {
+            let transcript_path = options
+                .transcript
+                .clone()
+                .or_else(|| crate::transcript::episode_transcript(&path, &episode))
+                .ok_or_else(|| {
+                    CliError::ClipError(format!(
+                        "--captions needs a transcript, pass --transcript or add one to {}",
+                        path.display()
+                    ))
+                })?;
+            Some(String::from_utf8_lossy(&crate::crypto::read(&transcript_path)?).into_owned())
+        } else {
+            None
+        };
+        let artwork = match &options.artwork {
+            Some(artwork) => artwork.to_string_lossy().into_owned(),
+            None => episode.image.clone(),
+        };
+
+        for aspect in &options.aspect {
+            let video = output_dir.join(format!("{}-{}.mp4", name, aspect.as_str()));
+            render_audiogram(&audio, &artwork, transcript.as_deref(), range, *aspect, &video)?;
+            outln!("{}", t!("wrote-file", path = video.display().to_string()));
+            outputs.push(video);
+        }
+    }
+
+    if options.upload {
+        let publishing = &channel_config.publishing;
+        let workspace = crate::state::Workspace::new(channel_dir);
+        let lock = workspace.lock()?;
+        for output in outputs {
+            let file = tokio::fs::File::open(&output).await?;
+            let size = file.metadata().await?.len();
+            let file_name = output.file_name().unwrap_or_default().to_string_lossy();
+            let key = format!("{}/clips/{}", publishing.prefix, file_name);
+            let uploaded = upload::upload_object(
+                file,
+                size,
+                publishing,
+                key.clone(),
+                upload::ObjectOptions {
+                    resume_dir: Some(workspace.multipart_dir()),
+                    ..upload::ObjectOptions::media(publishing)
+                },
+            )
+            .await?;
+            workspace.record_upload(&lock, &key, &uploaded, size)?;
+            outln!("{}", t!("uploaded-clip", url = uploaded.url.as_str()));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,418 @@
+use crate::config::*;
+use crate::feed::{parse_feed, ParsedFeed, ParsedItem};
+use crate::http::HttpClient;
+use crate::output::{out, outln};
+use crate::CliError;
+use serde_yaml::Value;
+use similar::TextDiff;
+use std::collections::BTreeMap;
+
+/// Print a field-by-field comparison of two episodes.
+pub fn compare_episodes(
+    left: &Episode,
+    right: &Episode,
+    show_all: bool,
+    rendered: bool,
+) -> Result<(), CliError> {
+    let left_fields = flatten(&serde_yaml::to_value(left)?);
+    let right_fields = flatten(&serde_yaml::to_value(right)?);
+
+    let mut keys: Vec<&String> = left_fields.keys().chain(right_fields.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    let mut differences = 0;
+    for key in keys {
+        let left_value = left_fields.get(key);
+        let right_value = right_fields.get(key);
+        if left_value == right_value {
+            if show_all {
+                outln!(
+                    "  {}: {}",
+                    key,
+                    left_value.map(|v| v.as_str()).unwrap_or("")
+                );
+            }
+            continue;
+        }
+
+        differences += 1;
+        outln!("~ {}", key);
+        outln!(
+            "    - {}",
+            left_value.map(|v| v.as_str()).unwrap_or("<missing>")
+        );
+        outln!(
+            "    + {}",
+            right_value.map(|v| v.as_str()).unwrap_or("<missing>")
+        );
+    }
+
+    outln!(
+        "{} field(s) differ between {} and {}",
+        differences, left.id, right.id
+    );
+
+    if rendered {
+        let left_xml = format!("{}\n", crate::xml::generate_item_xml(left)?);
+        let right_xml = format!("{}\n", crate::xml::generate_item_xml(right)?);
+        outln!();
+        out!(
+            "{}",
+            TextDiff::from_lines(&left_xml, &right_xml)
+                .unified_diff()
+                .header(&left.id, &right.id)
+        );
+    }
+
+    Ok(())
+}
+
+/// Flattens nested YAML into `path.to[0].field => value` pairs.
+fn flatten(value: &Value) -> BTreeMap<String, String> {
+    let mut fields = BTreeMap::new();
+    flatten_into(value, String::new(), &mut fields);
+    fields
+}
+
+fn flatten_into(value: &Value, path: String, fields: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                let key = match key {
+                    Value::String(key) => key.clone(),
+                    other => scalar(other),
+                };
+                let child = if path.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                flatten_into(value, child, fields);
+            }
+        }
+        Value::Sequence(items) => {
+            for (index, item) in items.iter().enumerate() {
+                flatten_into(item, format!("{}[{}]", path, index), fields);
+            }
+        }
+        other => {
+            fields.insert(path, scalar(other));
+        }
+    }
+}
+
+pub fn scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_owned(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        other => serde_yaml::to_string(other)
+            .unwrap_or_default()
+            .trim()
+            .to_owned(),
+    }
+}
+
+/// Channel fields that change on every render and are only checked for presence.
+const VOLATILE_CHANNEL_FIELDS: [&str; 2] = ["lastBuildDate", "pubDate"];
+
+#[derive(Debug)]
+pub enum FeedDifference {
+    MissingChannelField {
+        field: String,
+        reference: String,
+    },
+    ExtraChannelField {
+        field: String,
+    },
+    ChangedChannelField {
+        field: String,
+        ours: String,
+        reference: String,
+    },
+    MissingItem {
+        guid: String,
+        title: String,
+    },
+    ExtraItem {
+        guid: String,
+        title: String,
+    },
+    GuidMismatch {
+        title: String,
+        ours: String,
+        reference: String,
+    },
+    MissingItemField {
+        guid: String,
+        field: String,
+        reference: String,
+    },
+    ChangedItemField {
+        guid: String,
+        field: String,
+        ours: String,
+        reference: String,
+    },
+    ExtraItemField {
+        guid: String,
+        field: String,
+    },
+}
+
+impl FeedDifference {
+    /// Missing data and changed GUIDs break subscribers during a migration;
+    /// everything else is informational.
+    pub fn is_problem(&self) -> bool {
+        matches!(
+            self,
+            FeedDifference::MissingChannelField { .. }
+                | FeedDifference::MissingItem { .. }
+                | FeedDifference::GuidMismatch { .. }
+                | FeedDifference::MissingItemField { .. }
+        )
+    }
+}
+
+impl std::fmt::Display for FeedDifference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeedDifference::MissingChannelField { field, reference } => {
+                write!(f, "channel is missing {} (reference: {})", field, reference)
+            }
+            FeedDifference::ExtraChannelField { field } => {
+                write!(f, "channel adds {}", field)
+            }
+            FeedDifference::ChangedChannelField {
+                field,
+                ours,
+                reference,
+            } => write!(
+                f,
+                "channel {} differs: {:?} vs reference {:?}",
+                field, ours, reference
+            ),
+            FeedDifference::MissingItem { guid, title } => {
+                write!(f, "item {:?} ({}) is missing", title, guid)
+            }
+            FeedDifference::ExtraItem { guid, title } => {
+                write!(f, "item {:?} ({}) is not in the reference", title, guid)
+            }
+            FeedDifference::GuidMismatch {
+                title,
+                ours,
+                reference,
+            } => write!(
+                f,
+                "item {:?} has GUID {} but the reference uses {}",
+                title, ours, reference
+            ),
+            FeedDifference::MissingItemField {
+                guid,
+                field,
+                reference,
+            } => write!(
+                f,
+                "item {} is missing {} (reference: {})",
+                guid, field, reference
+            ),
+            FeedDifference::ChangedItemField {
+                guid,
+                field,
+                ours,
+                reference,
+            } => write!(
+                f,
+                "item {} {} differs: {:?} vs reference {:?}",
+                guid, field, ours, reference
+            ),
+            FeedDifference::ExtraItemField { guid, field } => {
+                write!(f, "item {} adds {}", guid, field)
+            }
+        }
+    }
+}
+
+/// Semantic comparison of two feeds. Items are matched by GUID, falling
+/// back to the title to detect GUIDs that changed. Fields only our items
+/// have are listed with `extra_item_fields`, for changelogs; `compare-feeds`
+/// leaves them out.
+pub fn diff_feeds(
+    ours: &ParsedFeed,
+    reference: &ParsedFeed,
+    extra_item_fields: bool,
+) -> Vec<FeedDifference> {
+    let mut differences = Vec::new();
+
+    for (field, reference_value) in &reference.channel {
+        match ours.channel.get(field) {
+            None => differences.push(FeedDifference::MissingChannelField {
+                field: field.clone(),
+                reference: reference_value.clone(),
+            }),
+            Some(_) if VOLATILE_CHANNEL_FIELDS.contains(&field.as_str()) => {}
+            Some(value) if value != reference_value => {
+                differences.push(FeedDifference::ChangedChannelField {
+                    field: field.clone(),
+                    ours: value.clone(),
+                    reference: reference_value.clone(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for field in ours.channel.keys() {
+        if !reference.channel.contains_key(field) {
+            differences.push(FeedDifference::ExtraChannelField {
+                field: field.clone(),
+            });
+        }
+    }
+
+    let mut matched = vec![false; ours.items.len()];
+    for reference_item in &reference.items {
+        let reference_guid = reference_item.guid().unwrap_or_default();
+        let by_guid = ours
+            .items
+            .iter()
+            .position(|item| !reference_guid.is_empty() && item.guid() == Some(reference_guid));
+
+        if let Some(index) = by_guid {
+            matched[index] = true;
+            diff_items(
+                &ours.items[index],
+                reference_item,
+                extra_item_fields,
+                &mut differences,
+            );
+            continue;
+        }
+
+        let by_title =
+            ours.items.iter().enumerate().position(|(index, item)| {
+                !matched[index] && item.title() == reference_item.title()
+            });
+        match by_title {
+            Some(index) => {
+                matched[index] = true;
+                differences.push(FeedDifference::GuidMismatch {
+                    title: reference_item.title().to_owned(),
+                    ours: ours.items[index].guid().unwrap_or_default().to_owned(),
+                    reference: reference_guid.to_owned(),
+                });
+            }
+            None => differences.push(FeedDifference::MissingItem {
+                guid: reference_guid.to_owned(),
+                title: reference_item.title().to_owned(),
+            }),
+        }
+    }
+
+    for (index, item) in ours.items.iter().enumerate() {
+        if !matched[index] {
+            differences.push(FeedDifference::ExtraItem {
+                guid: item.guid().unwrap_or_default().to_owned(),
+                title: item.title().to_owned(),
+            });
+        }
+    }
+
+    differences
+}
+
+fn diff_items(
+    ours: &ParsedItem,
+    reference: &ParsedItem,
+    extra_item_fields: bool,
+    differences: &mut Vec<FeedDifference>,
+) {
+    let guid = reference.guid().unwrap_or_default();
+    for (field, reference_value) in &reference.fields {
+        match ours.fields.get(field) {
+            None => differences.push(FeedDifference::MissingItemField {
+                guid: guid.to_owned(),
+                field: field.clone(),
+                reference: reference_value.clone(),
+            }),
+            Some(value) if value != reference_value => {
+                differences.push(FeedDifference::ChangedItemField {
+                    guid: guid.to_owned(),
+                    field: field.clone(),
+                    ours: value.clone(),
+                    reference: reference_value.clone(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    if !extra_item_fields {
+        return;
+    }
+    for field in ours.fields.keys() {
+        if !reference.fields.contains_key(field) {
+            differences.push(FeedDifference::ExtraItemField {
+                guid: guid.to_owned(),
+                field: field.clone(),
+            });
+        }
+    }
+}
+
+/// Load a feed from a local file or an http(s) URL.
+pub async fn load_feed(source: &str, client: &HttpClient) -> Result<ParsedFeed, CliError> {
+    let text = if is_url(source) {
+        let response = client.get(source).await?;
+        if !response.status.is_success() {
+            return Err(CliError::HttpStatus(
+                source.to_owned(),
+                response.status.as_u16(),
+            ));
+        }
+        response.text()
+    } else {
+        std::fs::read_to_string(source)?
+    };
+
+    parse_feed(&text)
+}
+
+pub fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+pub async fn compare_feeds(
+    ours: &str,
+    reference: &str,
+    client: &HttpClient,
+) -> Result<(), CliError> {
+    let ours_feed = load_feed(ours, client).await?;
+    let reference_feed = load_feed(reference, client).await?;
+
+    let differences = diff_feeds(&ours_feed, &reference_feed, false);
+    let problems = differences.iter().filter(|d| d.is_problem()).count();
+
+    for difference in &differences {
+        let marker = if difference.is_problem() {
+            "ERROR"
+        } else {
+            "note "
+        };
+        outln!("{} {}", marker, difference);
+    }
+
+    outln!(
+        "{} item(s) here, {} in the reference: {} problem(s), {} other difference(s)",
+        ours_feed.items.len(),
+        reference_feed.items.len(),
+        problems,
+        differences.len() - problems
+    );
+
+    if problems > 0 {
+        return Err(CliError::FeedMismatch(problems));
+    }
+
+    Ok(())
+}
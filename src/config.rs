@@ -1,5 +1,7 @@
-use chrono::{serde::ts_seconds, DateTime, Utc};
+use chrono::{serde::ts_seconds, DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -7,28 +9,1072 @@ pub struct ChannelConfig {
     #[serde(flatten)]
     pub channel: ChannelDetails,
     pub publishing: PublishingConfig,
+    #[serde(default)]
+    pub plugins: Vec<PluginConfig>,
+    #[serde(default)]
+    pub wasm_plugins: Vec<WasmPluginConfig>,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub summarize: Option<SummarizeConfig>,
+    #[serde(default)]
+    pub promo: PromoConfig,
+    /// Episode artwork drawn by `generate-art`
+    #[serde(default)]
+    pub art: Option<ArtConfig>,
+    #[serde(default)]
+    pub encryption: Option<EncryptionConfig>,
+    #[serde(default)]
+    pub archive: ArchiveConfig,
+    #[serde(default)]
+    pub approvals: ApprovalConfig,
+    #[serde(default)]
+    pub changelog: ChangelogConfig,
+    /// Checked for new episodes before they are published
+    #[serde(default)]
+    pub checklist: Vec<ChecklistItem>,
+    /// Words `validate` checks titles and show notes for, like a sponsor's
+    /// or network's content policy
+    #[serde(default)]
+    pub lint: LintConfig,
+    /// What new episodes' audio is checked against before it is uploaded
+    #[serde(default)]
+    pub quality: QualityConfig,
+    /// Where `watch-inbox` picks up audio handed off by an editor
+    #[serde(default)]
+    pub inbox: InboxConfig,
+    /// Who may run commands that change or publish the show, by role name.
+    /// Everyone may when empty
+    #[serde(default)]
+    pub roles: BTreeMap<String, RoleConfig>,
+    /// When `create-episode` starts a new season, and what season feeds say
+    #[serde(default)]
+    pub seasons: SeasonConfig,
+    /// Where `render-channel --upload` reports how each publish went
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    /// Where `stats` reads access logs from, and how long they are kept
+    #[serde(default)]
+    pub analytics: AnalyticsConfig,
+    /// A local index of the episode files, for big archives
+    #[serde(default)]
+    pub index: IndexConfig,
+    /// Commands and webhooks run after uploads, to announce episodes or
+    /// rebuild a site
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    /// Named lists of steps, run in order with `run <name>`
+    #[serde(default)]
+    pub pipelines: BTreeMap<String, Vec<PipelineStep>>,
+}
+
+/// Season rollover and episode numbering for `create-episode` and
+/// `renumber`. Without either rollover setting, a new episode is in the
+/// latest episode's season.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SeasonConfig {
+    /// Start a season every year, on `seasonStartsOn`'s month and day, or
+    /// on January 1st without it
+    pub new_season_every: Option<SeasonPeriod>,
+    /// When a season starts, like `2024-09-01`
+    pub season_starts_on: Option<NaiveDate>,
+    /// Channel details for a season's feed, by season number, in place of
+    /// the show's
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub feeds: BTreeMap<u64, SeasonFeedConfig>,
+    /// How episodes are numbered: from 1 in each season, by default, or
+    /// counting on across seasons, or not at all
+    pub numbering: Numbering,
+}
+
+/// How episodes are numbered.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum Numbering {
+    /// Each season starts again from 1
+    #[default]
+    PerSeason,
+    /// One count for the whole show, continuing across seasons
+    Global,
+    /// Episodes have no numbers, and the feed leaves out `itunes:episode`
+    None,
+}
+
+/// What a season's feed says instead of the show's channel details. Unset
+/// fields are the show's.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SeasonFeedConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum SeasonPeriod {
+    Year,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleConfig {
+    /// Names, emails, or `Name <email>`
+    pub members: Vec<String>,
+    pub allow: Vec<Operation>,
+}
+
+/// What a command does to the show, for `roles`.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum Operation {
+    /// Add episodes
+    Create,
+    /// Edit, encrypt, or restore episodes
+    Update,
+    /// Sign off on episodes for publishing
+    Approve,
+    /// Upload the feed, clips, or posts
+    Publish,
+    /// Delete episodes
+    Delete,
+}
+
+impl Operation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Create => "create",
+            Operation::Update => "update",
+            Operation::Approve => "approve",
+            Operation::Publish => "publish",
+            Operation::Delete => "delete",
+        }
+    }
+}
+
+/// Editorial review needed before `render-channel --strict` publishes an
+/// episode.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ApprovalConfig {
+    /// Approvals each episode needs; 0 turns approvals off
+    pub required: usize,
+    /// Who may approve, as names, emails, or `Name <email>`; anyone when
+    /// empty
+    pub approvers: Vec<String>,
+    /// Only count approvals backed by a `Signed-off-by:` trailer on a commit
+    /// of the episode file
+    pub require_signoff: bool,
+}
+
+/// Wordlists for `validate`, matched as whole words or phrases, ignoring
+/// case.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LintConfig {
+    /// Terms that may not appear at all; validation fails on them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub blocked: Vec<String>,
+    /// House style: terms to warn about, and what to say instead, like
+    /// `guys: folks`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub replace: BTreeMap<String, String>,
+}
+
+/// Thresholds for `check-audio` and `create-episode`. Checks without a
+/// threshold are skipped. Loudness, peaks, and silence are measured with
+/// ffmpeg, so it is only needed when one of those is set.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QualityConfig {
+    /// kbit/s
+    pub min_bitrate: Option<u32>,
+    /// Hz, like 44100
+    pub min_sample_rate: Option<u32>,
+    /// 2 to catch mono exports of a stereo show
+    pub min_channels: Option<u32>,
+    /// Integrated loudness to aim for, in LUFS, like -16
+    pub loudness: Option<f64>,
+    /// How far from `loudness` the audio may be, in LU
+    pub loudness_tolerance: f64,
+    /// Highest sample peak, in dBFS; louder peaks are likely clipped
+    pub max_peak: Option<f64>,
+    /// Longest stretch of silence, in seconds
+    pub max_silence_seconds: Option<f64>,
+    /// Quieter than this, in dBFS, counts as silence
+    pub silence_threshold: f64,
+    /// Warn about problems instead of failing
+    pub warn_only: bool,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            min_bitrate: None,
+            min_sample_rate: None,
+            min_channels: None,
+            loudness: None,
+            loudness_tolerance: 2.0,
+            max_peak: None,
+            max_silence_seconds: None,
+            silence_threshold: -50.0,
+            warn_only: false,
+        }
+    }
+}
+
+/// Settings for `verify-archive`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ArchiveConfig {
+    /// Receives a JSON POST (with a Slack-style `text` field) when published
+    /// objects are missing or changed
+    pub webhook: Option<String>,
+}
+
+/// Where the changes made by each publish are reported, besides
+/// `.podcast-ctl/changelog.md`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChangelogConfig {
+    /// Receives a JSON POST (with a Slack-style `text` field) listing the
+    /// changes, when there are any
+    pub webhook: Option<String>,
+    /// File, relative to `channel.yaml`, that the changes are written to as
+    /// a commit message, for `git commit -F`
+    pub message_file: Option<PathBuf>,
+}
+
+/// The folder `watch-inbox` makes draft episodes from, who may send them
+/// in with `import-email`, and who hears about them.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InboxConfig {
+    /// Folder, relative to `channel.yaml`, that audio named like
+    /// `2024-06-02 - Title.mp3` is dropped into
+    pub dir: Option<PathBuf>,
+    /// Receives a JSON POST (with a Slack-style `text` field) for each
+    /// episode created, or file that couldn't be
+    pub webhook: Option<String>,
+    /// Addresses `import-email` accepts episodes from; anyone when empty
+    pub senders: Vec<String>,
+}
+
+/// Metrics sent after each publish, for monitoring automated ones. Either
+/// or both of statsd and a Prometheus Pushgateway can be set.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MetricsConfig {
+    /// statsd server, as `host:port`, sent to over UDP
+    pub statsd: Option<String>,
+    /// Pushgateway URL, like `http://pushgateway:9091`
+    pub pushgateway: Option<String>,
+    /// Prefix of metric names, `podcastctl` by default
+    pub prefix: Option<String>,
+}
+
+/// The access logs S3 or CloudFront writes for the show's bucket or
+/// distribution, which `stats` counts downloads in.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AnalyticsConfig {
+    pub format: AccessLogFormat,
+    /// The bucket logs are written to, `publishing.bucket` when not set
+    pub bucket: Option<String>,
+    /// Where in the bucket logs are written, like `logs/`
+    pub prefix: String,
+    /// A MaxMind database, like GeoLite2-City.mmdb, for `stats --geography`
+    pub geoip_database: Option<PathBuf>,
+    /// How listeners' addresses are anonymized when counting, and in the
+    /// logs `purge-analytics` rewrites
+    pub anonymize: Option<Anonymize>,
+    /// Days logs are kept; `purge-analytics` deletes older ones
+    pub retention_days: Option<u64>,
+}
+
+/// Where commands that read every episode, like `search`, `stats`, and
+/// the dashboard, read them from.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IndexConfig {
+    /// Keep the episodes in a SQLite database in `.podcast-ctl/cache/`,
+    /// reading only the files that changed since. Needs the `sqlite`
+    /// feature
+    pub sqlite: bool,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLogFormat {
+    /// S3 server access logs
+    #[default]
+    S3,
+    /// CloudFront standard logs
+    Cloudfront,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Anonymize {
+    /// Keep the network: the first 24 bits of IPv4 addresses, and the
+    /// first 48 of IPv6 ones
+    Truncate,
+    /// Replace addresses with a hash of them, salted with
+    /// `PODCAST_CTL_ANALYTICS_SALT`
+    Hash,
+}
+
+/// Hooks run at each event, in order.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HooksConfig {
+    /// Once `create-episode` has uploaded an episode and saved its file
+    pub on_episode_uploaded: Vec<HookConfig>,
+    /// Once `render-channel --upload` has published podcast.xml
+    pub on_feed_published: Vec<HookConfig>,
+}
+
+/// A shell command to run or a webhook to POST to, with the event as JSON.
+/// A hook with both, or neither, is refused.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", try_from = "HookFields")]
+pub struct HookConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+    /// How long the command or request may take before it is stopped,
+    /// retries included
+    #[serde(default = "default_hook_timeout")]
+    pub timeout_seconds: u64,
+    #[serde(default)]
+    pub on_failure: HookFailure,
+}
+
+/// A hook as it is written, before it's checked.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HookFields {
+    #[serde(default)]
+    command: Option<String>,
+    #[serde(default)]
+    webhook: Option<String>,
+    #[serde(default = "default_hook_timeout")]
+    timeout_seconds: u64,
+    #[serde(default)]
+    on_failure: HookFailure,
+}
+
+impl TryFrom<HookFields> for HookConfig {
+    type Error = String;
+
+    fn try_from(fields: HookFields) -> Result<Self, Self::Error> {
+        match (&fields.command, &fields.webhook) {
+            (Some(command), Some(webhook)) => Err(format!(
+                "a hook runs a `command` or calls a `webhook`, not both: '{}' and '{}'",
+                command, webhook
+            )),
+            (None, None) => Err("a hook needs a `command` or a `webhook`".to_owned()),
+            _ => Ok(HookConfig {
+                command: fields.command,
+                webhook: fields.webhook,
+                timeout_seconds: fields.timeout_seconds,
+                on_failure: fields.on_failure,
+            }),
+        }
+    }
+}
+
+impl HookConfig {
+    /// The command or webhook, for messages.
+    pub fn name(&self) -> String {
+        self.command
+            .clone()
+            .or_else(|| self.webhook.clone())
+            .unwrap_or_default()
+    }
+}
+
+fn default_hook_timeout() -> u64 {
+    30
+}
+
+/// What a hook that fails does to the command that ran it.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum HookFailure {
+    /// Print a warning and carry on
+    #[default]
+    Warn,
+    /// Fail the command, after what it uploaded is up
+    Fail,
+}
+
+/// One item of the pre-publish checklist.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistItem {
+    /// What to check, as asked when it has to be confirmed by hand
+    pub item: String,
+    /// A check done by `podcast-ctl`
+    #[serde(default)]
+    pub check: Option<ChecklistCheck>,
+    /// A shell command that passes when it exits with 0
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ChecklistCheck {
+    /// The episode has its own image, not the channel's
+    Artwork,
+    /// Every transcript the episode lists exists, and it lists one
+    Transcript,
+    Chapters,
+    /// The episode has a show notes file
+    Notes,
+    /// The episode has the approvals `approvals` asks for
+    Approved,
+}
+
+/// A step of a pipeline: a command without options, like `validate`, or a
+/// mapping with the command under `step` and its options, like
+/// `{step: render, upload: true}`, or with a `shell` command to run.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum PipelineStep {
+    Name(String),
+    Options(BTreeMap<String, serde_yaml::Value>),
+}
+
+/// Who can read episodes encrypted with `encrypt`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionConfig {
+    /// age public keys (`age1...`)
+    pub recipients: Vec<String>,
+}
+
+/// OpenAI-compatible chat completions endpoint used by `summarize`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SummarizeConfig {
+    pub endpoint: String,
+    pub model: String,
+    /// Environment variable holding the API key, sent as a bearer token
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    /// Replaces the built-in system prompt; `{title}` is the channel title
+    #[serde(default)]
+    pub prompt: Option<String>,
+    #[serde(default = "default_summarize_max_characters")]
+    pub max_characters: usize,
+    #[serde(default = "default_summarize_timeout")]
+    pub timeout_seconds: u64,
+}
+
+fn default_summarize_max_characters() -> usize {
+    100_000
+}
+
+fn default_summarize_timeout() -> u64 {
+    300
+}
+
+/// A template image with text drawn on it for each episode.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtConfig {
+    /// PNG or JPEG, relative to the channel directory
+    pub template: PathBuf,
+    /// TrueType or OpenType font, relative to the channel directory
+    pub font: PathBuf,
+    pub text: Vec<ArtText>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ArtText {
+    /// Like `Episode {episode_number}`; `{title}`, `{season}`,
+    /// `{episode_number}`, and `{id}` are replaced
+    pub text: String,
+    /// Top left corner of the text, in pixels
+    pub x: u32,
+    pub y: u32,
+    /// Line height in pixels
+    pub size: f32,
+    /// `#rrggbb` or `#rrggbbaa`
+    #[serde(default = "default_art_color")]
+    pub color: String,
+    /// Wrap onto more lines instead of running wider than this
+    #[serde(default)]
+    pub max_width: Option<u32>,
+}
+
+fn default_art_color() -> String {
+    "#ffffff".to_owned()
+}
+
+/// Social posts written by `promo`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PromoConfig {
+    /// Always included, before hashtags made from the episode keywords
+    pub hashtags: Vec<String>,
+    /// `utm_campaign` for links, `{id}` and `{episode_number}` are replaced
+    pub utm_campaign: Option<String>,
+    /// Per-network templates, keyed by `x`, `mastodon`, or `linkedin`
+    pub templates: BTreeMap<String, String>,
+    pub mastodon: Option<MastodonConfig>,
+    pub x: Option<XConfig>,
+    pub linkedin: Option<LinkedInConfig>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MastodonConfig {
+    /// e.g. `https://mastodon.social`
+    pub instance: String,
+    pub access_token_env: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct XConfig {
+    /// Environment variable holding an OAuth 2.0 user access token
+    pub access_token_env: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedInConfig {
+    /// Person or organization URN to post as, e.g. `urn:li:person:abc123`
+    pub author: String,
+    pub access_token_env: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HttpConfig {
+    pub max_attempts: u32,
+    /// Zero disables rate limiting
+    pub requests_per_second: f64,
+    pub concurrency: usize,
+    pub timeout_seconds: u64,
+    pub user_agent: String,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            requests_per_second: 5.0,
+            concurrency: 4,
+            timeout_seconds: 30,
+            user_agent: format!("podcast-ctl/{}", env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginConfig {
+    pub name: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub stages: Vec<PluginStage>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum PluginStage {
+    Render,
+    Publish,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WasmPluginConfig {
+    /// Path to the `.wasm` module, relative to the channel directory
+    pub path: PathBuf,
+    #[serde(default = "default_wasm_fuel")]
+    pub fuel: u64,
+}
+
+fn default_wasm_fuel() -> u64 {
+    1_000_000_000
+}
+
+impl PluginStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PluginStage::Render => "render",
+            PluginStage::Publish => "publish",
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct PublishingConfig {
+    /// Where files are published: `s3` (the default, also for S3
+    /// compatible providers), `local`, `sftp`, `gcs`, or `azure`
+    #[serde(default, rename = "type")]
+    pub storage: StorageType,
+    /// Only used by S3
+    #[serde(default)]
     pub region: Region,
+    /// S3 or GCS bucket, or Azure container
+    #[serde(default)]
     pub bucket: String,
     pub prefix: String,
+    /// Where published files are served from instead of the storage's own
+    /// URL, like a CDN at `https://media.example.com`. Feed URLs, enclosure
+    /// URLs, and the printed podcast URL all use it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_base_url: Option<String>,
+    /// Address S3 buckets as `{endpoint}/{bucket}` instead of
+    /// `{bucket}.{endpoint}`, as MinIO and buckets with dots in their name
+    /// need
+    #[serde(default)]
+    pub path_style: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local: Option<LocalStorageConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sftp: Option<SftpStorageConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub azure: Option<AzureStorageConfig>,
+    /// XSL stylesheet, relative to the channel directory, uploaded next to
+    /// podcast.xml so browsers show a styled page instead of raw XML
+    #[serde(default)]
+    pub stylesheet: Option<PathBuf>,
+    /// Publish the bundled XSL stylesheet, a landing page with the show's
+    /// art and latest episodes, when `stylesheet` isn't set
+    #[serde(default)]
+    pub default_stylesheet: bool,
+    /// Also publish a small feed of only the latest episodes
+    #[serde(default)]
+    pub recent_feed: Option<RecentFeedConfig>,
+    /// Keep only this many of the latest episodes in podcast.xml, for
+    /// hosts and apps that cut off large feeds. Older episodes are
+    /// published in archive feeds of this many each, oldest first in
+    /// `podcast-archive-1.xml`, linked from podcast.xml as RFC 5005
+    /// `prev-archive`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<usize>,
+    /// Also publish a feed of each season's episodes, at
+    /// `{prefix}/season-N/podcast.xml`, with the channel details in
+    /// `seasons.feeds`
+    #[serde(default)]
+    pub per_season_feeds: bool,
+    /// The show's site is published with `render-site --upload`, so the
+    /// channel and episodes without a `link` link to their pages on it
+    #[serde(default)]
+    pub site: bool,
+    /// Before publishing a feed, wait until `publicBaseUrl` serves what it
+    /// references, for CDNs in front of the bucket
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify_cdn: Option<VerifyCdnConfig>,
+    /// WebSub hub named in the feeds and told about them once they are
+    /// published, so subscribed apps pick up new episodes right away
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub websub_hub: Option<String>,
+    /// Other places episodes can be published to, by name, like a
+    /// members-only bucket for bonus episodes; an episode's `destination`
+    /// picks one
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub destinations: BTreeMap<String, DestinationConfig>,
+    /// Services told about the feed once it is published
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    /// Upload without a public ACL and publish URLs only subscribers get,
+    /// for a subscriber-only feed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private: Option<PrivateConfig>,
+    /// Objects uploaded at the same time when a command publishes several,
+    /// like the transcripts, chapters, and feeds of `render-channel`
+    #[serde(default = "default_parallel_uploads")]
+    pub parallel_uploads: usize,
+    #[serde(default)]
+    pub multipart: MultipartConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub object_options: ObjectOptionsConfig,
+    /// How episode media and the files published next to it are keyed
+    /// under `{prefix}/artifacts/`
+    #[serde(default)]
+    pub key_layout: KeyLayout,
+    /// Name for new episodes' audio and episode files, like
+    /// `{date}-{slug}`, instead of the release date
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_template: Option<String>,
+    /// What each episode's `guid` is, unless the episode file sets one
+    #[serde(default)]
+    pub guid_strategy: GuidStrategy,
+    /// With `guidStrategy: template`, the GUID of each episode, like
+    /// `https://example.com/episodes/{id}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guid_template: Option<String>,
+    /// Upload podcast.xml and feed.json gzipped, with `Content-Encoding:
+    /// gzip`, for large feeds fetched often. Only S3, GCS, and Azure keep
+    /// the encoding
+    #[serde(default)]
+    pub gzip_feeds: bool,
+    /// With `gzipFeeds`, also upload each of them uncompressed, named with
+    /// this before the extension, like `-plain` for `podcast-plain.xml`,
+    /// for clients that don't decompress
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uncompressed_feed_suffix: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotificationsConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub podping: Option<PodpingConfig>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
+pub struct PodpingConfig {
+    /// Environment variable holding the podping.cloud auth token
+    pub token_env: String,
+    #[serde(default = "default_podping_url")]
+    pub url: String,
+}
+
+fn default_podping_url() -> String {
+    "https://podping.cloud/".to_owned()
+}
+
+/// How a private feed's URLs let subscribers in.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PrivateConfig {
+    pub urls: PrivateUrls,
+    /// How long presigned URLs last, at most 7 days
+    #[serde(default = "default_presigned_days")]
+    pub expires_days: u64,
+    /// Environment variable holding the token
+    #[serde(default = "default_token_env")]
+    pub token_env: String,
+    /// Query parameter the token is sent as
+    #[serde(default = "default_token_param")]
+    pub token_param: String,
+}
+
+fn default_presigned_days() -> u64 {
+    7
+}
+
+fn default_token_env() -> String {
+    "PODCAST_CTL_FEED_TOKEN".to_owned()
+}
+
+fn default_token_param() -> String {
+    "token".to_owned()
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum PrivateUrls {
+    /// S3 presigned URLs, which expire, for what the feed references; the
+    /// feed itself keeps a plain URL
+    Presigned,
+    /// URLs with a token, for a CDN or edge function to check
+    Token,
+}
+
+/// Where an episode with this `destination` is published instead, as
+/// changes to `publishing`; what isn't set is the same. Its episodes are
+/// left out of the main feed unless `mainFeed` is set, and published in the
+/// destination's own feed, along with the main feed's episodes, by
+/// `render-channel --upload --destination NAME`:
+///
+/// ```yaml
+/// publishing:
+///   destinations:
+///     members:
+///       bucket: show-members
+///       publicBaseUrl: https://members.example.com
+/// ```
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DestinationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bucket: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_base_url: Option<String>,
+    /// For `type: local`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local: Option<LocalStorageConfig>,
+    /// Also list the destination's episodes in the main feed, for public
+    /// episodes kept in another bucket. Their transcripts and chapters are
+    /// published with the feed they are rendered in
+    pub main_feed: bool,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum KeyLayout {
+    /// `artifacts/2022-09-01.mp3`
+    #[default]
+    Flat,
+    /// `artifacts/3f/a2/2022-09-01.mp3`, by the MD5 of the file name, so
+    /// large archives list quickly and don't load a single prefix. Public
+    /// URLs stay flat when `publicBaseUrl` is set, so the CDN must map
+    /// them to the sharded keys; without it, URLs point at the shards
+    Sharded,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum GuidStrategy {
+    /// The episode's `id`, the UUID it was created with
+    #[default]
+    Uuid,
+    /// The episode's media URL, which changes whenever the media moves
+    EnclosureUrl,
+    /// `guidTemplate`, with `{id}`, `{date}`, `{season}`, and `{episode}`
+    Template,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum StorageType {
+    #[default]
+    S3,
+    /// A directory a web server serves
+    Local,
+    /// A directory on a web server, uploaded to with `sftp`
+    Sftp,
+    /// Google Cloud Storage, with a token from `GOOGLE_OAUTH_ACCESS_TOKEN`
+    /// or `gcloud auth print-access-token`
+    Gcs,
+    /// Azure Blob Storage, with a SAS token from `AZURE_STORAGE_SAS_TOKEN`
+    Azure,
+}
+
+impl StorageType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StorageType::S3 => "s3",
+            StorageType::Local => "local",
+            StorageType::Sftp => "sftp",
+            StorageType::Gcs => "gcs",
+            StorageType::Azure => "azure",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalStorageConfig {
+    /// Directory files are copied into, relative to where podcast-ctl runs
+    pub path: PathBuf,
+    /// URL the directory is served at
+    pub base_url: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SftpStorageConfig {
+    /// Like `example.com` or `podcast@example.com`; anything `sftp`
+    /// accepts, including hosts from `~/.ssh/config`
+    pub host: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Directory on the server files are uploaded into
+    pub path: String,
+    /// URL the directory is served at
+    pub base_url: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AzureStorageConfig {
+    /// Storage account name, the `{account}` of
+    /// `{account}.blob.core.windows.net`
+    pub account: String,
+}
+
+/// How large files are uploaded: in parts, several at a time, so a dropped
+/// connection only costs the parts in flight.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MultipartConfig {
+    /// Files at least this large are uploaded in parts
+    pub threshold_mb: u64,
+    /// At least 5; raised when a file would need more than 10,000 parts
+    pub part_size_mb: u64,
+    /// Parts uploaded at the same time
+    pub concurrency: usize,
+}
+
+impl Default for MultipartConfig {
+    fn default() -> Self {
+        Self {
+            threshold_mb: 64,
+            part_size_mb: 16,
+            concurrency: 4,
+        }
+    }
+}
+
+/// How uploads recover from storage failing. Server errors, throttling,
+/// timeouts, and dropped connections are retried; refusals like a denied
+/// permission or a missing bucket are not.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryConfig {
+    /// Attempts at each request, including the first
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for each one after it
+    pub initial_backoff_ms: u64,
+    pub max_backoff_seconds: u64,
+    pub connect_timeout_seconds: u64,
+    /// Give up on an attempt when storage sends nothing back for this long
+    pub read_timeout_seconds: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff_ms: 500,
+            max_backoff_seconds: 30,
+            connect_timeout_seconds: 10,
+            read_timeout_seconds: 120,
+        }
+    }
+}
+
+/// What storage keeps with each uploaded object. Cache control applies to
+/// every backend that serves headers; the rest is S3 only.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ObjectOptionsConfig {
+    pub cache_control: CacheControlConfig,
+    /// Like `STANDARD_IA` or `INTELLIGENT_TIERING`
+    pub storage_class: Option<String>,
+    /// `AES256`, or `aws:kms` with `kmsKeyId`
+    pub server_side_encryption: Option<String>,
+    pub kms_key_id: Option<String>,
+    /// Canned ACL, like `bucket-owner-full-control`, or `none` for buckets
+    /// with ACLs disabled
+    pub acl: String,
+}
+
+impl Default for ObjectOptionsConfig {
+    fn default() -> Self {
+        Self {
+            cache_control: CacheControlConfig::default(),
+            storage_class: None,
+            server_side_encryption: None,
+            kms_key_id: None,
+            acl: "public-read".to_owned(),
+        }
+    }
+}
+
+/// `Cache-Control` values, like `max-age=300` for feeds so a CDN picks up
+/// new episodes within minutes, and `max-age=31536000, immutable` for
+/// media, which never changes under the same key.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CacheControlConfig {
+    /// Feeds and everything else rewritten on each render: the stylesheet,
+    /// the HTML page, transcripts, and chapters
+    pub feed: Option<String>,
+    /// Audio, artwork, and clips
+    pub media: Option<String>,
+}
+
+/// A feed of the latest episodes, published as `podcast-recent.xml` and
+/// linked from the main feed, for clients that poll often. It is uploaded
+/// with `Cache-Control: max-age`, and storage answers `If-Modified-Since`
+/// and `If-None-Match` with `304 Not Modified`, so polling it is cheap.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentFeedConfig {
+    #[serde(default = "default_recent_episodes")]
+    pub episodes: usize,
+    #[serde(default = "default_recent_max_age")]
+    pub max_age_seconds: u64,
+}
+
+fn default_recent_episodes() -> usize {
+    10
+}
+
+fn default_recent_max_age() -> u64 {
+    300
+}
+
+/// How long `render-channel --upload` waits for the CDN to serve newly
+/// uploaded objects before giving up without publishing the feed.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyCdnConfig {
+    #[serde(default = "default_cdn_timeout")]
+    pub timeout_seconds: u64,
+    /// Time between checks of objects the CDN doesn't serve yet
+    #[serde(default = "default_cdn_interval")]
+    pub interval_seconds: u64,
+}
+
+pub fn default_parallel_uploads() -> usize {
+    4
+}
+
+fn default_cdn_timeout() -> u64 {
+    300
+}
+
+fn default_cdn_interval() -> u64 {
+    10
+}
+
+/// Where the bucket is, and how to sign in to it.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct Region {
-    pub name: String,
+    /// Like `us-east-1`. When unset, the region comes from `AWS_REGION` or
+    /// the AWS profile
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Host objects are served from, like `s3.us-east-1.amazonaws.com`. Any
+    /// host outside of AWS is also where uploads are sent, for S3
+    /// compatible storage
     pub endpoint: String,
+    /// Named profile from `~/.aws/config` to take credentials from, instead
+    /// of `AWS_PROFILE` or the default chain
+    #[serde(default)]
+    pub profile: Option<String>,
 }
 
-impl From<Region> for rusoto_core::Region {
-    fn from(input: Region) -> Self {
-        rusoto_core::Region::Custom {
-            name: input.name,
-            endpoint: input.endpoint,
+impl Region {
+    /// The endpoint to send uploads to, when the bucket isn't on AWS.
+    pub fn custom_endpoint(&self) -> Option<String> {
+        if self.endpoint.ends_with(".amazonaws.com") {
+            None
+        } else {
+            Some(format!("https://{}", self.endpoint))
         }
     }
 }
@@ -46,24 +1092,196 @@ pub struct ChannelDetails {
     pub owner: OwnerDetails,
     #[serde(default)]
     pub keywords: Vec<String>,
+    /// Apple Podcasts categories, like `Technology` or
+    /// `Society & Culture > Documentary`
+    #[serde(default = "default_categories")]
+    pub categories: Vec<String>,
+    /// RSS language code, like `en-us` or `de`
+    #[serde(default = "default_language")]
+    pub language: String,
+    #[serde(default = "default_copyright")]
+    pub copyright: String,
+    /// Whether episodes are listened to in order
+    #[serde(default)]
+    pub podcast_type: PodcastType,
+    /// `podcast:medium`: what kind of feed this is. Music and audiobooks
+    /// are validated as tracks and chapters of a whole
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub medium: Option<Medium>,
+    /// `podcast:guid`. When not set, it is derived from the feed URL the
+    /// first time the feed is published and kept in the workspace, so
+    /// moving the feed later doesn't change it. Set this to keep the GUID
+    /// of a show that moved from another host, or to publish from
+    /// somewhere without the workspace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub podcast_guid: Option<String>,
+    /// `podcast:locked`: whether other hosts may import the feed without
+    /// the owner's email confirmation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked: Option<bool>,
+    /// `podcast:funding` links, like a Patreon or donation page
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub funding: Vec<FundingLink>,
+    /// Regular hosts, listed as `podcast:person` on the channel
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<Person>,
+    /// Recurring guests, listed as `podcast:person` on the channel
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guests: Vec<Person>,
+    /// `podcast:trailer`: trailers for the show or a season, added by
+    /// `add-trailer`. Unlike trailer episodes, they aren't feed items
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trailers: Vec<ChannelTrailer>,
+    /// IANA timezone, like `America/New_York`, for release dates given
+    /// without an offset and for the dates in the feed. UTC without it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// Words that make an episode explicit, for `validate` to flag in clean
+    /// channels. Replaces the built-in list when set
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub explicit_terms: Vec<String>,
+}
+
+/// A trailer published with the channel rather than as an episode.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelTrailer {
+    pub title: String,
+    pub url: String,
+    pub released_at: DateTime<Utc>,
+    pub bytes: ByteSize,
+    /// The season this is the trailer for, or the whole show when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub season: Option<u64>,
+    /// Content type, from the audio itself; guessed from the URL without it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+impl ChannelTrailer {
+    /// The trailer's `type`, as the feed gives it.
+    pub fn enclosure_type(&self) -> &str {
+        self.mime_type
+            .as_deref()
+            .unwrap_or_else(|| crate::audio::mime_type_for_url(&self.url))
+    }
+}
+
+/// Someone credited with `podcast:person`.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Person {
+    pub name: String,
+    /// Defaults to `host` or `guest`, depending on the list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    /// Website or profile page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub href: Option<String>,
+    /// Picture of the person
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub img: Option<String>,
+}
+
+pub(crate) fn default_categories() -> Vec<String> {
+    vec!["Fiction".to_owned()]
+}
+
+fn default_language() -> String {
+    "en-us".to_owned()
+}
+
+pub(crate) fn default_copyright() -> String {
+    "Copyright 2022".to_owned()
+}
+
+/// `itunes:type`
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum PodcastType {
+    /// Newest episodes first; each stands on its own
+    Episodic,
+    /// Oldest episodes first, like chapters of a story
+    #[default]
+    Serial,
+}
+
+impl PodcastType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PodcastType::Episodic => "Episodic",
+            PodcastType::Serial => "Serial",
+        }
+    }
+}
+
+/// `podcast:medium`
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum Medium {
+    #[default]
+    Podcast,
+    /// Episodes are the tracks of an album, numbered by `episodeNumber`
+    Music,
+    /// Episodes are chapters, numbered by `episodeNumber`
+    Audiobook,
+    /// Episodes are readings of a newsletter's issues
+    Newsletter,
+}
+
+impl Medium {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Medium::Podcast => "podcast",
+            Medium::Music => "music",
+            Medium::Audiobook => "audiobook",
+            Medium::Newsletter => "newsletter",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FundingLink {
+    pub url: String,
+    /// Shown by apps next to the link, e.g. "Support the show"
+    pub title: String,
 }
 
 impl ChannelDetails {
+    /// The channel's `timezone`, UTC without one.
+    pub fn tz(&self) -> Result<chrono_tz::Tz, crate::CliError> {
+        crate::dates::timezone(self.timezone.as_deref())
+    }
+
     #[cfg(test)]
     pub fn make_test() -> Self {
         Self {
             title: "title".to_owned(),
-            link: Some("link".to_owned()),
+            link: Some("https://example.com/".to_owned()),
             description: "description".to_owned(),
             subtitle: "subtitle".to_owned(),
             summary: "summary".to_owned(),
             explicit: true,
-            image: "image".to_owned(),
+            image: "https://example.com/image.jpg".to_owned(),
             owner: OwnerDetails {
                 name: "test".to_owned(),
                 email: "email".to_owned(),
             },
             keywords: vec!["keyword".to_owned()],
+            categories: default_categories(),
+            language: default_language(),
+            copyright: default_copyright(),
+            podcast_type: PodcastType::Serial,
+            medium: None,
+            podcast_guid: None,
+            locked: None,
+            funding: Vec::new(),
+            hosts: Vec::new(),
+            guests: Vec::new(),
+            trailers: Vec::new(),
+            timezone: None,
+            explicit_terms: Vec::new(),
         }
     }
 }
@@ -75,10 +1293,14 @@ pub struct OwnerDetails {
     pub email: String,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Episode {
     pub id: String,
+    /// The episode's `guid`, instead of what `publishing.guidStrategy`
+    /// makes it, like the GUID it had on the host it was imported from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guid: Option<String>,
     pub title: String,
     pub summary: String,
     pub description: String,
@@ -90,6 +1312,93 @@ pub struct Episode {
     pub released_at: DateTime<Utc>,
     pub media: EpisodeMedia,
     pub keywords: Vec<String>,
+    /// Publish the channel's keywords before these, so the episode only
+    /// lists its own
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub extend_channel_keywords: bool,
+    /// Transcripts uploaded next to the audio and listed as
+    /// `podcast:transcript`, one per format or language
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transcripts: Vec<EpisodeTranscript>,
+    /// Chapters, published as `podcast:chapters` JSON. When empty, they are
+    /// read from a `.chapters.txt` file next to the episode, if there is one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chapters: Vec<Chapter>,
+    /// Hosts of this episode, when they differ from the channel's
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<Person>,
+    /// Guests on this episode
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guests: Vec<Person>,
+    #[serde(default, skip_serializing_if = "EpisodeType::is_full")]
+    pub episode_type: EpisodeType,
+    /// Overrides the channel's `explicit` for this episode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explicit: Option<bool>,
+    /// Reviewers who approved the episode for publishing
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub approved_by: Vec<String>,
+    /// Markdown show notes, relative to the episodes directory. Published
+    /// as `content:encoded`, with a plain text `description`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<PathBuf>,
+    /// Uploaded, but left out of the feed and site until `publish` clears it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub draft: bool,
+    /// Name in `publishing.destinations` the episode is published to
+    /// instead of the main feed's storage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+}
+
+/// `itunes:episodeType`
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize, Clone, Copy, clap::ValueEnum)]
+#[serde(rename_all = "camelCase")]
+pub enum EpisodeType {
+    /// A regular episode
+    #[default]
+    Full,
+    /// A preview of the show or of a season
+    Trailer,
+    /// Extra content, like behind the scenes
+    Bonus,
+}
+
+impl EpisodeType {
+    pub fn is_full(&self) -> bool {
+        *self == EpisodeType::Full
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EpisodeType::Full => "full",
+            EpisodeType::Trailer => "trailer",
+            EpisodeType::Bonus => "bonus",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Chapter {
+    /// `HH:MM:SS` or `MM:SS` from the start of the episode
+    pub start: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EpisodeTranscript {
+    /// SRT, WebVTT, plain text, JSON, or HTML file, relative to the
+    /// episodes directory
+    pub path: PathBuf,
+    /// Language code, when it differs from the channel's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
 }
 
 impl Episode {
@@ -97,28 +1406,297 @@ impl Episode {
     pub fn make_test(title: &str) -> Self {
         Self {
             id: title.to_owned(),
+            guid: None,
             title: title.to_owned(),
             description: "description".to_owned(),
             summary: "summary".to_owned(),
-            link: Some("link".to_owned()),
-            image: "image".to_owned(),
+            link: Some(format!("https://example.com/{}", title)),
+            image: format!("https://example.com/{}.jpg", title),
             released_at: Utc::now(),
             season: 1,
             episode_number: 1,
             media: EpisodeMedia {
-                url: "url".to_owned(),
-                duration: 12,
-                bytes: 1000,
+                url: format!("https://example.com/{}.mp3", title),
+                duration: MediaDuration(12),
+                bytes: ByteSize(1000),
+                bitrate: None,
+                recorded_date: None,
+                mime_type: None,
             },
             keywords: vec!["keyword".to_owned()],
+            extend_channel_keywords: false,
+            transcripts: Vec::new(),
+            chapters: Vec::new(),
+            hosts: Vec::new(),
+            guests: Vec::new(),
+            episode_type: EpisodeType::Full,
+            explicit: None,
+            approved_by: Vec::new(),
+            notes: None,
+            draft: false,
+            destination: None,
         }
     }
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct EpisodeMedia {
     pub url: String,
-    pub duration: u64,
-    pub bytes: u64,
+    pub duration: MediaDuration,
+    pub bytes: ByteSize,
+    /// kbps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recorded_date: Option<String>,
+    /// Content type, from the audio itself; guessed from the URL without it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+impl EpisodeMedia {
+    /// The enclosure's `type`, as feeds give it.
+    pub fn enclosure_type(&self) -> &str {
+        self.mime_type
+            .as_deref()
+            .unwrap_or_else(|| crate::audio::mime_type_for_url(&self.url))
+    }
+}
+
+/// Length of audio. Read from a number of seconds, `1:02:03`, or
+/// `1h02m03s`, and written as a number of seconds, so saving an episode
+/// doesn't change how its file gives the duration. Units go from hours to
+/// seconds, and only the first may be 60 or more, so `62:03` and `90m` are
+/// read, but `1h90m` or `1h02`, which could mean minutes or seconds, are
+/// refused.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Copy)]
+#[serde(try_from = "NumberOrText", into = "u64")]
+pub struct MediaDuration(pub u64);
+
+/// Size of a file. Read from a number of bytes or a whole size like
+/// `88 MiB`, and written as a number of bytes, since feeds need the exact
+/// length. Fractions like `88.3 MiB` are refused for the same reason.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Clone, Copy)]
+#[serde(try_from = "NumberOrText", into = "u64")]
+pub struct ByteSize(pub u64);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrText {
+    Number(u64),
+    Text(String),
+}
+
+impl TryFrom<NumberOrText> for MediaDuration {
+    type Error = String;
+
+    fn try_from(value: NumberOrText) -> Result<Self, Self::Error> {
+        let text = match value {
+            NumberOrText::Number(seconds) => return Ok(MediaDuration(seconds)),
+            NumberOrText::Text(text) => text,
+        };
+        let invalid = || format!("'{}' is not a duration like 1h02m03s or 1:02:03", text);
+
+        if text.contains(':') {
+            let parts = text
+                .trim()
+                .split(':')
+                .map(|part| part.parse::<u64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| invalid())?;
+            if !(2..=3).contains(&parts.len()) || parts[1..].iter().any(|part| *part >= 60) {
+                return Err(invalid());
+            }
+            // Minutes and seconds, or hours, minutes and seconds
+            return parts
+                .iter()
+                .try_fold(0u64, |total, part| {
+                    total.checked_mul(60)?.checked_add(*part)
+                })
+                .map(MediaDuration)
+                .ok_or_else(invalid);
+        }
+        let mut seconds = 0u64;
+        let mut number = String::new();
+        // Each unit has to be smaller than the one before
+        let mut last_unit = None;
+        for c in text.chars().filter(|c| !c.is_whitespace()) {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let unit = match c.to_ascii_lowercase() {
+                'h' => 3600,
+                'm' => 60,
+                's' => 1,
+                _ => return Err(invalid()),
+            };
+            let value: u64 = number.parse().map_err(|_| invalid())?;
+            match last_unit {
+                Some(last) if unit >= last => return Err(invalid()),
+                Some(_) if value >= 60 => return Err(invalid()),
+                _ => {}
+            }
+            seconds = value
+                .checked_mul(unit)
+                .and_then(|value| value.checked_add(seconds))
+                .ok_or_else(invalid)?;
+            last_unit = Some(unit);
+            number.clear();
+        }
+        match (last_unit, number.is_empty()) {
+            (Some(_), true) => Ok(MediaDuration(seconds)),
+            // A bare number is seconds
+            (None, false) => number.parse().map(MediaDuration).map_err(|_| invalid()),
+            // Nothing, or a number after the last unit
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl From<MediaDuration> for u64 {
+    fn from(duration: MediaDuration) -> Self {
+        duration.0
+    }
+}
+
+impl TryFrom<NumberOrText> for ByteSize {
+    type Error = String;
+
+    fn try_from(value: NumberOrText) -> Result<Self, Self::Error> {
+        let text = match value {
+            NumberOrText::Number(bytes) => return Ok(ByteSize(bytes)),
+            NumberOrText::Text(text) => text,
+        };
+        let invalid = || format!("'{}' is not a size like 88 MiB or 92274688", text);
+
+        let trimmed = text.trim();
+        let split = trimmed
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(trimmed.len());
+        let (number, unit) = trimmed.split_at(split);
+        if unit.starts_with('.') {
+            return Err(format!(
+                "'{}' is rounded, give the exact number of bytes",
+                text
+            ));
+        }
+        let number: u64 = number.parse().map_err(|_| invalid())?;
+        let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "kb" => 1000,
+            "kib" => 1 << 10,
+            "mb" => 1000 * 1000,
+            "mib" => 1 << 20,
+            "gb" => 1000 * 1000 * 1000,
+            "gib" => 1 << 30,
+            _ => return Err(invalid()),
+        };
+        number
+            .checked_mul(multiplier)
+            .map(ByteSize)
+            .ok_or_else(invalid)
+    }
+}
+
+impl From<ByteSize> for u64 {
+    fn from(size: ByteSize) -> Self {
+        size.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn duration(text: &str) -> Result<u64, String> {
+        MediaDuration::try_from(NumberOrText::Text(text.to_owned())).map(u64::from)
+    }
+
+    fn size(text: &str) -> Result<u64, String> {
+        ByteSize::try_from(NumberOrText::Text(text.to_owned())).map(u64::from)
+    }
+
+    #[test]
+    fn reads_durations() {
+        assert_eq!(duration("3723"), Ok(3723));
+        assert_eq!(duration("1:02:03"), Ok(3723));
+        assert_eq!(duration("62:03"), Ok(3723));
+        assert_eq!(duration("1h02m03s"), Ok(3723));
+        assert_eq!(duration("1h 2m 3s"), Ok(3723));
+        assert_eq!(duration("45m"), Ok(2700));
+        assert_eq!(duration("90m"), Ok(5400));
+        assert_eq!(duration("2m 3s"), Ok(123));
+        assert_eq!(duration("0s"), Ok(0));
+    }
+
+    #[test]
+    fn refuses_durations() {
+        assert!(duration("").is_err());
+        assert!(duration("soon").is_err());
+        assert!(duration("1:2:3:4").is_err());
+        assert!(duration("1.5h").is_err());
+        assert!(duration(&format!("{}:00", u64::MAX)).is_err());
+        assert!(duration(&format!("{}h", u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn refuses_durations_with_unclear_units() {
+        // Minutes or seconds?
+        assert!(duration("1h02").is_err());
+        assert!(duration("h").is_err());
+        assert!(duration("1h1h").is_err());
+        assert!(duration("3s2m").is_err());
+        assert!(duration("1m1h").is_err());
+        assert!(duration("1h90m").is_err());
+        assert!(duration("1m60s").is_err());
+        assert!(duration("1:75:00").is_err());
+        assert!(duration("1:02:75").is_err());
+        assert!(duration("2:60").is_err());
+    }
+
+    #[test]
+    fn writes_durations_as_seconds() {
+        let media: EpisodeMedia =
+            serde_yaml::from_str("url: a.mp3\nduration: 1h02m03s\nbytes: 88 MiB\n").unwrap();
+        assert_eq!(media.duration, MediaDuration(3723));
+        let written = serde_yaml::to_string(&media).unwrap();
+        assert!(written.contains("duration: 3723\n"), "{}", written);
+        assert!(written.contains("bytes: 92274688\n"), "{}", written);
+        let read: EpisodeMedia = serde_yaml::from_str(&written).unwrap();
+        assert_eq!(read, media);
+    }
+
+    #[test]
+    fn refuses_hooks_with_both_or_neither_of_command_and_webhook() {
+        let hook = |yaml: &str| serde_yaml::from_str::<HookConfig>(yaml);
+        let command = hook("command: ./announce.sh").unwrap();
+        assert_eq!(command.command.as_deref(), Some("./announce.sh"));
+        assert_eq!(command.timeout_seconds, 30);
+        let webhook = hook("{webhook: 'https://example.com/build', onFailure: fail}").unwrap();
+        assert_eq!(webhook.on_failure, HookFailure::Fail);
+
+        let both = hook("{command: ./announce.sh, webhook: 'https://example.com/build'}");
+        assert!(both.unwrap_err().to_string().contains("not both"));
+        assert!(hook("timeoutSeconds: 10").is_err());
+    }
+
+    #[test]
+    fn reads_sizes() {
+        assert_eq!(size("92274688"), Ok(92274688));
+        assert_eq!(size("88 MiB"), Ok(92274688));
+        assert_eq!(size("88MB"), Ok(88_000_000));
+        assert_eq!(size("1 kib"), Ok(1024));
+        assert_eq!(size("12 b"), Ok(12));
+    }
+
+    #[test]
+    fn refuses_sizes() {
+        assert!(size("88.3 MiB").is_err());
+        assert!(size("88.0 MiB").is_err());
+        assert!(size("88 parsecs").is_err());
+        assert!(size("MiB").is_err());
+        assert!(size(&format!("{} GiB", u64::MAX)).is_err());
+    }
 }
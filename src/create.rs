@@ -0,0 +1,856 @@
+//! Creating episodes: uploading their audio and writing their episode
+//! files.
+//!
+//! [`create_episode`] is `create-episode`. [`create_episodes`],
+//! [`watch_inbox`], and [`import_email`] create draft or released
+//! episodes from audio files in a directory, an inbox folder, or an email,
+//! and [`add_trailer`] uploads a trailer into channel.yaml.
+
+use crate::config::*;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::{
+    audio, batch, chapters, crypto, dates, email, formats, get_all_episodes, hooks, http, id3tags,
+    images, init, keywords, naming, numbering, output, quality, save_episode, state, sync,
+    transcript, upload, validate, watch, CliError,
+};
+use chrono::Utc;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+use tokio::fs::File as TokioFile;
+use uuid::Uuid;
+
+/// What to make a new episode from, as `create-episode` takes it.
+#[derive(Debug, Clone)]
+pub struct NewEpisode {
+    /// Audio for the episode: MP3, M4A, Ogg, Opus, or FLAC
+    pub file: PathBuf,
+    /// Release date, local time, or RFC 3339 time, as
+    /// [`dates::parse_release`] reads it
+    pub date: String,
+    /// Timezone of a date without an offset, instead of the channel's
+    pub timezone: Option<String>,
+    pub title: String,
+    /// Transcript to pick keywords from, published with the episode
+    pub transcript: Option<PathBuf>,
+    /// The episode's own keywords, published after the channel's
+    pub keywords: Vec<String>,
+    /// Markdown show notes
+    pub notes: Option<PathBuf>,
+    pub episode_type: EpisodeType,
+    pub explicit: Option<bool>,
+    /// Leave the episode out of the feed until it is published
+    pub draft: bool,
+    /// `publishing.destinations` entry to publish the episode to
+    pub destination: Option<String>,
+    pub description: Option<String>,
+    pub summary: Option<String>,
+    pub link: Option<String>,
+    /// Don't ask for what isn't given; placeholders are written instead
+    pub no_interactive: bool,
+    pub season: Option<u64>,
+    pub episode: Option<u64>,
+    /// Episode artwork, uploaded next to its audio
+    pub image: Option<PathBuf>,
+    /// Write the episode's ID3 tags into the audio before uploading it
+    pub tag: bool,
+}
+
+impl NewEpisode {
+    /// A full episode of `file`, released on `date`, with everything else
+    /// left to the channel or asked for.
+    pub fn new(file: impl Into<PathBuf>, date: &str, title: &str) -> Self {
+        NewEpisode {
+            file: file.into(),
+            date: date.to_owned(),
+            timezone: None,
+            title: title.to_owned(),
+            transcript: None,
+            keywords: Vec::new(),
+            notes: None,
+            episode_type: EpisodeType::Full,
+            explicit: None,
+            draft: false,
+            destination: None,
+            description: None,
+            summary: None,
+            link: None,
+            no_interactive: false,
+            season: None,
+            episode: None,
+            image: None,
+            tag: false,
+        }
+    }
+}
+
+/// Where `create-episodes` imports from.
+#[derive(Debug, Clone)]
+pub struct BatchOptions {
+    /// Directory of audio files, one episode each
+    pub from_dir: PathBuf,
+    /// Timezone of dates without an offset, instead of the channel's
+    pub timezone: Option<String>,
+    /// Leave the episodes out of the feed until they are published
+    pub draft: bool,
+}
+
+/// What `watch-inbox` watches.
+#[derive(Debug, Clone)]
+pub struct InboxOptions {
+    /// Folder to watch, instead of `inbox.dir`
+    pub dir: Option<PathBuf>,
+    /// Timezone of the dates in file names, instead of the channel's
+    pub timezone: Option<String>,
+    /// Seconds a new file has to stay the same size before it is imported
+    pub settle: u64,
+}
+
+/// The email `import-email` imports.
+#[derive(Debug, Clone)]
+pub struct EmailOptions {
+    /// The message, as a `.eml` file, or `-` for stdin
+    pub message: PathBuf,
+    /// Timezone of the release date, instead of the channel's
+    pub timezone: Option<String>,
+}
+
+/// The trailer `add-trailer` uploads.
+#[derive(Debug, Clone)]
+pub struct TrailerOptions {
+    /// Audio for the trailer
+    pub file: PathBuf,
+    pub title: String,
+    /// Release date; now when not given
+    pub date: Option<String>,
+    /// Timezone of a date without an offset, instead of the channel's
+    pub timezone: Option<String>,
+    /// The season this is the trailer for; the show's without one
+    pub season: Option<u64>,
+}
+
+/// Create the episode `data` describes, returning its episode file.
+pub async fn create_episode(
+    episode_dir: &Path,
+    channel_config: ChannelConfig,
+    data: NewEpisode,
+) -> Result<PathBuf, CliError> {
+    // Everything the episode publishes goes to its destination
+    let mut channel_config = channel_config;
+    channel_config.publishing =
+        upload::destination(&channel_config.publishing, data.destination.as_deref())?;
+
+    let timezone = data.timezone.as_deref().or(channel_config.channel.timezone.as_deref());
+    let timezone = dates::timezone(timezone)?;
+    let publish_date = dates::parse_release(&data.date, timezone)?;
+    let publish_name = publish_date.with_timezone(&timezone).format("%Y-%m-%d").to_string();
+    let interactive = !data.no_interactive && atty::is(atty::Stream::Stdin);
+    let (description, summary, link) =
+        new_episode_text(&data, interactive, channel_config.publishing.site)?;
+    // Before uploading, so a taken --episode doesn't upload anything
+    crypto::require_readable(episode_dir)?;
+    let existing = get_all_episodes(episode_dir)?;
+    let (season, episode_number) = numbering::next(
+        &existing,
+        &channel_config.seasons,
+        channel_config.channel.tz()?,
+        publish_date,
+        data.season,
+        data.episode,
+    )?;
+    let image = match &data.image {
+        Some(path) => {
+            let image = fs::read(path)?;
+            let problems = validate::artwork_problems(&image);
+            if !problems.is_empty() {
+                let path = path.display().to_string();
+                return Err(CliError::ArtworkRejected(path, problems.join("; ")));
+            }
+            Some(image)
+        }
+        None => None,
+    };
+
+    // Unreleased audio is only ever decrypted as it is read
+    let encrypted = crypto::is_encrypted(&data.file);
+    let mut head = Vec::new();
+    if encrypted {
+        crypto::open(&data.file)?.take(64).read_to_end(&mut head)?;
+    } else {
+        fs::File::open(&data.file)?
+            .take(64)
+            .read_to_end(&mut head)?;
+    }
+    let format = audio::AudioFormat::detect(&data.file, &head)
+        .ok_or_else(|| CliError::AudioError("episode", audio::not_audio(&data.file, &head)))?;
+    audio::warn_mismatch(&data.file, format);
+    let source = if encrypted {
+        quality::Audio::Encrypted(&data.file)
+    } else {
+        quality::Audio::File(&data.file)
+    };
+    quality::check(&channel_config.quality, &data.file, format, source)?;
+
+    // Encrypted audio, and any with --dry-run, is only tagged in memory
+    let in_memory = if data.tag {
+        id3tags::check_format(&data.file, format)?;
+        let client = http::HttpClient::new(&channel_config.http)?;
+        let chapters = match &data.notes {
+            Some(path) => chapters::from_notes(&String::from_utf8_lossy(&crypto::read(path)?))?,
+            None => Vec::new(),
+        };
+        // Only needed for where the last chapter ends
+        let duration = match (encrypted, chapters.is_empty()) {
+            (_, true) => 0,
+            (true, false) => audio::read_encrypted(format, &data.file)?
+                .duration
+                .as_millis(),
+            (false, false) => audio::read_file(format, &data.file)?.duration.as_millis(),
+        };
+        let tags = id3tags::EpisodeTags {
+            title: data.title.clone(),
+            episode_number,
+            released_at: publish_date,
+            artwork: Some(match &image {
+                Some(image) => image.clone(),
+                None => {
+                    let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+                    id3tags::artwork(&channel_config.channel.image, channel_dir, &client).await?
+                }
+            }),
+            chapters,
+            duration: duration as u64,
+        };
+        let channel = &channel_config.channel;
+        if encrypted || upload::dry_run() {
+            Some(id3tags::write_bytes(
+                crypto::read(&data.file)?,
+                channel,
+                &tags,
+            )?)
+        } else {
+            id3tags::write_file(&data.file, channel, &tags)?;
+            None
+        }
+    } else {
+        None
+    };
+
+    let names = naming::episode_names(
+        &channel_config.publishing,
+        episode_dir,
+        &existing,
+        &publish_name,
+        (&data.title, season, episode_number),
+        format.extension(),
+    )?;
+    let object_key = names.object_key;
+
+    let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+    let workspace = state::Workspace::new(channel_dir);
+    let lock = workspace.lock()?;
+    let audio_options = upload::ObjectOptions {
+        resume_dir: Some(workspace.multipart_dir()),
+        content_type: Some(format.mime_type().to_owned()),
+        ..upload::ObjectOptions::media(&channel_config.publishing)
+    };
+
+    let (uploaded, size, info) = if let Some(audio) = in_memory {
+        let size = audio.len() as u64;
+        let info = audio::read_slice(format, &audio);
+        let uploaded = upload::upload_object(
+            Cursor::new(audio),
+            size,
+            &channel_config.publishing,
+            object_key.clone(),
+            audio_options,
+        )
+        .await?;
+        (uploaded, size, info)
+    } else if encrypted {
+        let mut decrypted = crypto::open(&data.file)?;
+        let size = crypto::plaintext_len(&mut decrypted)?;
+        let uploaded = upload::upload_object(
+            crypto::AsyncDecrypted::new(decrypted),
+            size,
+            &channel_config.publishing,
+            object_key.clone(),
+            audio_options,
+        )
+        .await?;
+        (uploaded, size, audio::read_encrypted(format, &data.file))
+    } else {
+        let file = TokioFile::open(&data.file).await?;
+        let file_metadata = file.metadata().await?;
+        let size = file_metadata.len();
+        let uploaded = upload::upload_object(
+            file,
+            size,
+            &channel_config.publishing,
+            object_key.clone(),
+            audio_options,
+        )
+        .await?;
+        (uploaded, size, audio::read_file(format, &data.file))
+    };
+    workspace.record_upload(&lock, &object_key, &uploaded, size)?;
+    if !upload::dry_run() {
+        outln!("{}", t!("uploaded-file", url = uploaded.url.as_str()));
+    }
+
+    let info = info?;
+
+    let keywords = match &data.transcript {
+        _ if !data.keywords.is_empty() => data.keywords.clone(),
+        Some(path) => {
+            let transcript = transcript::plain_text(&fs::read_to_string(path)?);
+            keywords::extract_keywords(&transcript, 10)
+        }
+        None => channel_config.channel.keywords.clone(),
+    };
+
+    // Transcripts and notes are referenced relative to the episodes directory
+    let relative = |path: &PathBuf| -> Result<PathBuf, CliError> {
+        let path = fs::canonicalize(path)?;
+        Ok(fs::canonicalize(episode_dir)
+            .ok()
+            .and_then(|dir| path.strip_prefix(dir).ok().map(|p| p.to_path_buf()))
+            .unwrap_or(path))
+    };
+    let transcripts = match &data.transcript {
+        Some(path) => vec![EpisodeTranscript {
+            path: relative(path)?,
+            language: None,
+        }],
+        None => Vec::new(),
+    };
+    let notes = data.notes.as_ref().map(relative).transpose()?;
+
+    let mut episode = Episode {
+        id: Uuid::new_v4().to_string(),
+        guid: None,
+        title: data.title.clone(),
+        description,
+        summary,
+        link,
+        released_at: publish_date,
+        season,
+        episode_number,
+        image: channel_config.channel.image.clone(),
+        media: EpisodeMedia {
+            url: uploaded.url,
+            duration: MediaDuration(info.duration.as_secs()),
+            bytes: ByteSize(size),
+            bitrate: info.bitrate,
+            recorded_date: info.recorded_date,
+            mime_type: Some(format.mime_type().to_owned()),
+        },
+        keywords,
+        extend_channel_keywords: !data.keywords.is_empty(),
+        transcripts,
+        chapters: Vec::new(),
+        hosts: Vec::new(),
+        guests: Vec::new(),
+        episode_type: data.episode_type,
+        explicit: data.explicit,
+        approved_by: Vec::new(),
+        notes,
+        draft: data.draft,
+        destination: data.destination.clone(),
+    };
+
+    if let Some(image) = image {
+        let (extension, content_type) = images::file_type(&image);
+        let key = upload::artifact_key(
+            &channel_config.publishing,
+            &episode,
+            &format!(".{}", extension),
+        );
+        let size = image.len() as u64;
+        let uploaded = upload::upload_object(
+            Cursor::new(image),
+            size,
+            &channel_config.publishing,
+            key.clone(),
+            upload::ObjectOptions {
+                content_type: Some(content_type.to_owned()),
+                ..upload::ObjectOptions::media(&channel_config.publishing)
+            },
+        )
+        .await?;
+        workspace.record_upload(&lock, &key, &uploaded, size)?;
+        if !upload::dry_run() {
+            outln!("{}", t!("uploaded-art", url = uploaded.url.as_str()));
+        }
+        episode.image = uploaded.url;
+    }
+
+    info!("episode {:?}", episode);
+
+    let mut episode_file = names.episode_file;
+    // Notes for encrypted audio are encrypted too
+    if encrypted {
+        episode_file = crypto::encrypted_path(&episode_file);
+    }
+
+    output::append(
+        "episodes",
+        serde_json::json!({
+            "path": episode_file,
+            "id": episode.id,
+            "season": episode.season,
+            "episodeNumber": episode.episode_number,
+            "url": episode.media.url,
+        }),
+    );
+    if upload::dry_run() {
+        outln!(
+            "{}",
+            t!("dry-run-episode", path = episode_file.display().to_string())
+        );
+        return Ok(episode_file);
+    }
+    save_episode(&episode_file, &episode, &channel_config)?;
+
+    if !channel_config.hooks.on_episode_uploaded.is_empty() {
+        let client = http::HttpClient::new(&channel_config.http)?;
+        let payload = hooks::HookPayload {
+            event: hooks::HookEvent::EpisodeUploaded,
+            channel: channel_config.channel.title.clone(),
+            feed_url: None,
+            changes: Vec::new(),
+            episodes: vec![hooks::HookEpisode::from(&episode)],
+        };
+        hooks::run(&channel_config.hooks.on_episode_uploaded, &client, &payload).await?;
+    }
+    Ok(episode_file)
+}
+
+/// Create an episode from each audio file in `--from-dir` that hasn't been
+/// imported yet, oldest first so they are numbered in order. A file that
+/// fails doesn't stop the rest; running again retries it.
+pub async fn create_episodes(
+    episode_dir: &Path,
+    channel_config: ChannelConfig,
+    data: BatchOptions,
+) -> Result<(), CliError> {
+    let timezone = data.timezone.as_deref().or(channel_config.channel.timezone.as_deref());
+    let timezone = dates::timezone(timezone)?;
+    let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+    let workspace = state::Workspace::new(channel_dir);
+    let scan = batch::scan(&data.from_dir, &workspace.imports()?)?;
+
+    let mut failed = scan.problems;
+    let mut candidates = Vec::new();
+    for candidate in scan.candidates {
+        match dates::parse_release(&candidate.date, timezone) {
+            Ok(released_at) => candidates.push((released_at, candidate)),
+            Err(e) => failed.push((candidate.path, e.localized())),
+        }
+    }
+    candidates.sort_by_key(|(released_at, _)| *released_at);
+
+    let count = candidates.len();
+    let mut imported = 0;
+    for (index, (_, candidate)) in candidates.into_iter().enumerate() {
+        let path = candidate.path.display().to_string();
+        outln!("{}", t!("import-file", index = index + 1, count = count, path = path));
+        let new_episode = imported_episode(&candidate, timezone, data.draft);
+        match create_episode(episode_dir, channel_config.clone(), new_episode).await {
+            Ok(_) if upload::dry_run() => imported += 1,
+            Ok(episode_file) => {
+                let lock = workspace.lock()?;
+                workspace.record_import(&lock, &candidate.path, candidate.bytes, &episode_file)?;
+                imported += 1;
+            }
+            Err(e) => failed.push((candidate.path, e.localized())),
+        }
+    }
+
+    outln!(
+        "{}",
+        t!(
+            "import-summary",
+            imported = imported,
+            skipped = scan.imported,
+            failed = failed.len()
+        )
+    );
+    for (path, detail) in &failed {
+        let path = path.display().to_string();
+        outln!("{}", t!("import-failed", path = path, detail = detail.as_str()));
+    }
+    if !failed.is_empty() {
+        return Err(CliError::ImportFailed(failed.len()));
+    }
+    Ok(())
+}
+
+/// The `create-episode` options for an audio file being imported.
+fn imported_episode(
+    candidate: &batch::Candidate,
+    timezone: chrono_tz::Tz,
+    draft: bool,
+) -> NewEpisode {
+    let summary = candidate.description.as_ref().map(|description| {
+        validate::truncate(description, validate::MAX_SUBTITLE)
+            .unwrap_or_else(|| description.clone())
+    });
+    NewEpisode {
+        timezone: Some(timezone.name().to_owned()),
+        draft,
+        description: candidate.description.clone(),
+        summary,
+        no_interactive: true,
+        // Tagging the source would change its size, and with it whether
+        // it was imported before
+        tag: false,
+        ..NewEpisode::new(candidate.path.clone(), &candidate.date, &candidate.title)
+    }
+}
+
+/// Make a draft episode from each audio file that lands in the inbox,
+/// until interrupted, telling `inbox.webhook` about each one. Files that
+/// were there before it started are imported first, and files that fail
+/// are retried once they change.
+pub async fn watch_inbox(
+    episode_dir: &Path,
+    channel_config: ChannelConfig,
+    data: InboxOptions,
+) -> Result<(), CliError> {
+    let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+    let inbox = data
+        .dir
+        .or_else(|| channel_config.inbox.dir.as_ref().map(|dir| channel_dir.join(dir)))
+        .ok_or(CliError::NoInbox)?;
+    fs::create_dir_all(&inbox)?;
+    let timezone = data.timezone.as_deref().or(channel_config.channel.timezone.as_deref());
+    let timezone = dates::timezone(timezone)?;
+    let workspace = state::Workspace::new(channel_dir);
+    let client = http::HttpClient::new(&channel_config.http)?;
+    let webhook = channel_config.inbox.webhook.as_deref();
+    outln!("{}", t!("inbox-watching", dir = inbox.display().to_string()));
+
+    // Files handled this run, and their size then, so each is only tried
+    // again once it changes
+    let mut handled: HashMap<PathBuf, u64> = HashMap::new();
+    let watched = vec![inbox.clone()];
+    let settle = std::time::Duration::from_secs(data.settle);
+    let mut watcher = watch::Watcher::new(&watched)?;
+    loop {
+        let scan = batch::scan_inbox(&inbox, &workspace.imports()?)?;
+        for (path, detail) in scan.problems {
+            let bytes = fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or_default();
+            if handled.insert(path.clone(), bytes) != Some(bytes) {
+                let path = path.display().to_string();
+                outln!("{}", t!("inbox-problem", path = path, detail = detail.as_str()));
+            }
+        }
+        for candidate in scan.candidates {
+            if handled.insert(candidate.path.clone(), candidate.bytes) == Some(candidate.bytes) {
+                continue;
+            }
+            let source = candidate.path.display().to_string();
+            let new_episode = imported_episode(&candidate, timezone, true);
+            let created = create_episode(episode_dir, channel_config.clone(), new_episode).await;
+            let body = match created {
+                Ok(episode_file) => {
+                    if !upload::dry_run() {
+                        let lock = workspace.lock()?;
+                        workspace.record_import(
+                            &lock,
+                            &candidate.path,
+                            candidate.bytes,
+                            &episode_file,
+                        )?;
+                    }
+                    let path = episode_file.display().to_string();
+                    let title = candidate.title.as_str();
+                    outln!("{}", t!("inbox-created", title = title, path = path.as_str()));
+                    serde_json::json!({
+                        "text": format!(
+                            "podcast-ctl: draft episode \"{}\" is ready for review in {}",
+                            title, path
+                        ),
+                        "source": source,
+                        "episode": path,
+                        "title": title,
+                    })
+                }
+                Err(e) => {
+                    let detail = e.localized();
+                    outln!(
+                        "{}",
+                        t!("inbox-problem", path = source.as_str(), detail = detail.as_str())
+                    );
+                    serde_json::json!({
+                        "text": format!("podcast-ctl: {} couldn't be imported: {}", source, detail),
+                        "source": source,
+                        "error": detail,
+                    })
+                }
+            };
+            if let Some(webhook) = webhook {
+                notify_inbox(&client, webhook, &body).await;
+            }
+        }
+        watcher.changed(settle).await;
+    }
+}
+
+/// Make a draft episode from an emailed submission, saving the audio in
+/// `email/` so it is imported only once, and tell `inbox.webhook`.
+pub async fn import_email(
+    episode_dir: &Path,
+    channel_config: ChannelConfig,
+    data: EmailOptions,
+) -> Result<(), CliError> {
+    let mut raw = Vec::new();
+    if data.message == Path::new("-") {
+        io::stdin().read_to_end(&mut raw)?;
+    } else {
+        raw = fs::read(&data.message)?;
+    }
+    let timezone = data.timezone.as_deref().or(channel_config.channel.timezone.as_deref());
+    let timezone = dates::timezone(timezone)?;
+    let submission = email::parse(&raw, timezone)?;
+    if !email::allowed(&channel_config.inbox.senders, &submission.from) {
+        let detail = format!("{} isn't in `inbox.senders`", submission.from);
+        return Err(CliError::EmailRejected(detail));
+    }
+
+    // Named by the audio, so the same audio sent twice is only imported once
+    let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+    let workspace = state::Workspace::new(channel_dir);
+    let dir = channel_dir.join(email::EMAIL_DIR);
+    fs::create_dir_all(&dir)?;
+    let name = format!(
+        "{}-{}.{}",
+        submission.date,
+        &sync::md5_hex(&submission.audio)[..8],
+        submission.format.extension()
+    );
+    let path = fs::canonicalize(&dir)?.join(name);
+    let bytes = submission.audio.len() as u64;
+    if let Some(import) = workspace
+        .imports()?
+        .into_iter()
+        .find(|import| import.source == path && import.bytes == bytes)
+    {
+        let detail = format!(
+            "its audio was imported before, as {}",
+            import.episode_file.display()
+        );
+        return Err(CliError::EmailRejected(detail));
+    }
+    fs::write(&path, &submission.audio)?;
+
+    let candidate = batch::Candidate {
+        path,
+        bytes,
+        title: submission.title,
+        date: submission.date,
+        description: submission.description,
+    };
+    let new_episode = imported_episode(&candidate, timezone, true);
+    let created = create_episode(episode_dir, channel_config.clone(), new_episode).await;
+    let title = candidate.title.as_str();
+    let (result, body) = match created {
+        Ok(episode_file) => {
+            if !upload::dry_run() {
+                let lock = workspace.lock()?;
+                workspace.record_import(&lock, &candidate.path, bytes, &episode_file)?;
+            }
+            let path = episode_file.display().to_string();
+            outln!("{}", t!("inbox-created", title = title, path = path.as_str()));
+            let text = format!(
+                "podcast-ctl: draft episode \"{}\" from {} is ready for review in {}",
+                title, submission.from, path
+            );
+            (
+                Ok(()),
+                serde_json::json!({
+                    "text": text,
+                    "from": submission.from,
+                    "episode": path,
+                    "title": title,
+                }),
+            )
+        }
+        Err(e) => {
+            let detail = e.localized();
+            let text = format!(
+                "podcast-ctl: \"{}\" from {} couldn't be imported: {}",
+                title, submission.from, detail
+            );
+            (
+                Err(e),
+                serde_json::json!({
+                    "text": text,
+                    "from": submission.from,
+                    "title": title,
+                    "error": detail,
+                }),
+            )
+        }
+    };
+    if let Some(webhook) = &channel_config.inbox.webhook {
+        let client = http::HttpClient::new(&channel_config.http)?;
+        notify_inbox(&client, webhook, &body).await;
+    }
+    result
+}
+
+/// Post what happened to an inbox file to `inbox.webhook`. Audio keeps
+/// arriving while the webhook is down, so that only logs a warning.
+async fn notify_inbox(client: &http::HttpClient, webhook: &str, body: &serde_json::Value) {
+    let response = client
+        .send(webhook, || client.request(reqwest::Method::POST, webhook).json(body))
+        .await;
+    match response {
+        Ok(response) if response.status.is_success() => {}
+        Ok(response) => warn!(
+            "Posting to {} failed with HTTP {}",
+            webhook,
+            response.status.as_u16()
+        ),
+        Err(e) => warn!("Posting to {} failed: {}", webhook, e),
+    }
+}
+
+/// Upload a trailer and add it to `trailers` in the channel file, leaving
+/// the rest of the file as it was written.
+pub async fn add_trailer(
+    channel_file: &Path,
+    channel_config: ChannelConfig,
+    data: TrailerOptions,
+) -> Result<(), CliError> {
+    let released_at = match &data.date {
+        Some(date) => {
+            let timezone = data.timezone.as_deref().or(channel_config.channel.timezone.as_deref());
+            dates::parse_release(date, dates::timezone(timezone)?)?
+        }
+        None => Utc::now(),
+    };
+    let mut head = Vec::new();
+    fs::File::open(&data.file)?.take(64).read_to_end(&mut head)?;
+    let format = audio::AudioFormat::detect(&data.file, &head)
+        .ok_or_else(|| CliError::AudioError("trailer", audio::not_audio(&data.file, &head)))?;
+    audio::warn_mismatch(&data.file, format);
+
+    let file_name = data.file.file_name().unwrap_or_default().to_string_lossy();
+    let object_key = format!("{}/trailers/{}", channel_config.publishing.prefix, file_name);
+    let channel_dir = channel_file.parent().unwrap_or_else(|| Path::new(""));
+    let workspace = state::Workspace::new(channel_dir);
+    let lock = workspace.lock()?;
+    let file = TokioFile::open(&data.file).await?;
+    let size = file.metadata().await?.len();
+    let options = upload::ObjectOptions {
+        resume_dir: Some(workspace.multipart_dir()),
+        content_type: Some(format.mime_type().to_owned()),
+        ..upload::ObjectOptions::media(&channel_config.publishing)
+    };
+    let uploaded = upload::upload_object(
+        file,
+        size,
+        &channel_config.publishing,
+        object_key.clone(),
+        options,
+    )
+    .await?;
+    workspace.record_upload(&lock, &object_key, &uploaded, size)?;
+
+    let trailer = ChannelTrailer {
+        title: data.title,
+        url: uploaded.url,
+        released_at,
+        bytes: ByteSize(size),
+        season: data.season,
+        mime_type: Some(format.mime_type().to_owned()),
+    };
+    if upload::dry_run() {
+        outln!(
+            "{}",
+            t!("dry-run-trailer", path = channel_file.display().to_string())
+        );
+        return Ok(());
+    }
+    outln!("{}", t!("uploaded-file", url = trailer.url.as_str()));
+
+    // Only the trailers change, so the rest of channel.yaml is left as it
+    // was written rather than serialized with every default filled in
+    let format = formats::Format::of(channel_file);
+    let original = fs::read_to_string(channel_file)?;
+    let mut document = format.parse_value(original.as_bytes())?;
+    // channel.yaml already parsed, so it is a mapping and `trailers` is a
+    // list when it is set at all
+    if let Some(channel) = document.as_mapping_mut() {
+        let trailers = channel
+            .entry("trailers".into())
+            .or_insert(serde_yaml::Value::Null);
+        if !trailers.is_sequence() {
+            *trailers = serde_yaml::Value::Sequence(Vec::new());
+        }
+        if let Some(trailers) = trailers.as_sequence_mut() {
+            trailers.push(serde_yaml::to_value(&trailer)?);
+        }
+    }
+    fs::write(channel_file, formats::update(format, &original, &document)?)?;
+    outln!(
+        "{}",
+        t!(
+            "trailer-added",
+            title = trailer.title.as_str(),
+            path = channel_file.display().to_string()
+        )
+    );
+    Ok(())
+}
+
+/// The description, summary, and link for a new episode: as given, asked
+/// for when `interactive`, or else placeholders that `render-channel
+/// --upload` refuses to publish. The link isn't asked for with `site`, as
+/// the episode's page is its link.
+fn new_episode_text(
+    data: &NewEpisode,
+    interactive: bool,
+    site: bool,
+) -> Result<(String, String, Option<String>), CliError> {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut ask = |given: &Option<String>, label: &str, default: Option<&str>| match given {
+        Some(given) => Ok(Some(given.clone())),
+        None if interactive => init::prompt(&mut input, &t!(label), default).map(Some),
+        None => Ok(None),
+    };
+
+    let description = ask(&data.description, "create-description", None)?;
+    let default_summary = description.as_deref().map(|description| {
+        validate::truncate(description, validate::MAX_SUBTITLE)
+            .unwrap_or_else(|| description.to_owned())
+    });
+    let summary = ask(&data.summary, "create-summary", default_summary.as_deref())?;
+    let link = if site {
+        data.link.clone()
+    } else {
+        ask(&data.link, "create-link", Some(""))?
+    };
+    let link = link.filter(|link| !link.is_empty());
+
+    let mut placeholders = Vec::new();
+    let mut or_placeholder = |value: Option<String>, field: &'static str| {
+        value.unwrap_or_else(|| {
+            placeholders.push(field);
+            validate::PLACEHOLDER.to_owned()
+        })
+    };
+    let description = or_placeholder(description, "description");
+    let summary = or_placeholder(summary, "summary");
+    if !placeholders.is_empty() {
+        eprintln!("{}", t!("placeholders-written", fields = placeholders.join(", ")));
+    }
+    Ok((description, summary, link))
+}
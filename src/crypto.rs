@@ -0,0 +1,311 @@
+//! age encryption for unreleased episodes.
+//!
+//! `encrypt` replaces an episode's YAML (and optionally its media) with an
+//! `.age` file encrypted to the `encryption.recipients` in `channel.yaml`.
+//! Encrypted episodes are decrypted whenever they are loaded, with the age
+//! identity file named by `PODCAST_CTL_IDENTITY`, so rendering and uploading
+//! work as usual for anyone holding a key. Without one they are left out
+//! with a warning, except by commands that delete or number episodes, which
+//! refuse to run rather than treat them as gone. Encrypted audio is
+//! decrypted as it is read, never all at once.
+
+use crate::config::*;
+use crate::events;
+use crate::i18n::t;
+use crate::output::{self, outln};
+use crate::CliError;
+use log::debug;
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+
+pub const IDENTITY_ENV: &str = "PODCAST_CTL_IDENTITY";
+const EXTENSION: &str = "age";
+
+/// Encrypted files already warned about, so each is only mentioned once.
+static LEFT_OUT: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+/// An `.age` file, decrypted as it is read.
+pub type Decrypted = age::stream::StreamReader<BufReader<fs::File>>;
+
+pub fn is_encrypted(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new(EXTENSION))
+}
+
+/// `episode.yaml.age` => `episode.yaml`; other paths are returned as-is.
+pub fn plain_path(path: &Path) -> PathBuf {
+    if is_encrypted(path) {
+        path.with_extension("")
+    } else {
+        path.to_owned()
+    }
+}
+
+pub fn encrypted_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Read a file, decrypting it first when it is an `.age` file.
+pub fn read(path: &Path) -> Result<Vec<u8>, CliError> {
+    let bytes = fs::read(path)?;
+    if is_encrypted(path) {
+        debug!("Decrypting {}", path.display());
+        decrypt(&bytes)
+    } else {
+        Ok(bytes)
+    }
+}
+
+/// Whether `path` can be read: plain files always can, encrypted ones only
+/// when `PODCAST_CTL_IDENTITY` is set. Otherwise warn that it is left out,
+/// and list it under `leftOut` with `--output json`.
+pub fn readable(path: &Path) -> bool {
+    if !is_encrypted(path) || std::env::var_os(IDENTITY_ENV).is_some() {
+        return true;
+    }
+    let mut left_out = LEFT_OUT.lock().unwrap_or_else(|e| e.into_inner());
+    if left_out.insert(path.to_owned()) {
+        let path = path.display().to_string();
+        events::warn(t!(
+            "encrypted-left-out",
+            path = path.as_str(),
+            var = IDENTITY_ENV
+        ));
+        output::append("leftOut", path);
+    }
+    false
+}
+
+/// Fail when an episode in `episode_dir` is encrypted and can't be
+/// decrypted. Leaving it out would have `prune` delete its objects, or
+/// `create-episode` reuse its number.
+pub fn require_readable(episode_dir: &Path) -> Result<(), CliError> {
+    if std::env::var_os(IDENTITY_ENV).is_some() {
+        return Ok(());
+    }
+    let paths = crate::get_episode_paths(episode_dir)?;
+    match paths.iter().find(|path| is_encrypted(path)) {
+        Some(path) => Err(CliError::CryptoError(format!(
+            "{} is encrypted and {} isn't set, so this can't account for it",
+            path.display(),
+            IDENTITY_ENV
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Open an `.age` file to decrypt it as it is read.
+pub fn open(path: &Path) -> Result<Decrypted, CliError> {
+    debug!("Decrypting {} as it is read", path.display());
+    decryptor(BufReader::new(fs::File::open(path)?))
+}
+
+/// How long `reader` is once decrypted, leaving it at the start.
+pub fn plaintext_len(reader: &mut Decrypted) -> Result<u64, CliError> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    reader.rewind()?;
+    Ok(len)
+}
+
+/// Write `contents` to `path`, encrypting them when it is an `.age` file.
+pub fn write(path: &Path, contents: &[u8], config: &ChannelConfig) -> Result<(), CliError> {
+    if is_encrypted(path) {
+        fs::write(path, encrypt(config, contents)?)?;
+    } else {
+        fs::write(path, contents)?;
+    }
+    Ok(())
+}
+
+/// Replace `path` with an encrypted copy, returning the new path.
+pub fn encrypt_file(path: &Path, config: &ChannelConfig) -> Result<PathBuf, CliError> {
+    let encrypted = encrypted_path(path);
+    fs::write(&encrypted, encrypt(config, &fs::read(path)?)?)?;
+    fs::remove_file(path)?;
+    Ok(encrypted)
+}
+
+/// Replace an `.age` file with its decrypted contents, returning the new
+/// path.
+pub fn decrypt_file(path: &Path) -> Result<PathBuf, CliError> {
+    if !is_encrypted(path) {
+        return Err(CliError::CryptoError(format!(
+            "{} is not an .age file",
+            path.display()
+        )));
+    }
+    let decrypted = plain_path(path);
+    fs::write(&decrypted, read(path)?)?;
+    fs::remove_file(path)?;
+    Ok(decrypted)
+}
+
+fn encrypt(config: &ChannelConfig, plaintext: &[u8]) -> Result<Vec<u8>, CliError> {
+    let keys = match &config.encryption {
+        Some(encryption) if !encryption.recipients.is_empty() => &encryption.recipients,
+        _ => {
+            return Err(CliError::CryptoError(
+                "channel.yaml has no `encryption.recipients`".to_owned(),
+            ))
+        }
+    };
+
+    let mut recipients: Vec<Box<dyn age::Recipient + Send>> = Vec::new();
+    for key in keys {
+        let recipient: age::x25519::Recipient = key
+            .parse()
+            .map_err(|e| CliError::CryptoError(format!("invalid recipient {}: {}", key, e)))?;
+        recipients.push(Box::new(recipient));
+    }
+
+    let encryptor = age::Encryptor::with_recipients(recipients)
+        .ok_or_else(|| CliError::CryptoError("no recipients".to_owned()))?;
+    let mut ciphertext = Vec::new();
+    let mut writer = encryptor
+        .wrap_output(&mut ciphertext)
+        .map_err(|e| CliError::CryptoError(e.to_string()))?;
+    writer.write_all(plaintext)?;
+    writer.finish()?;
+    Ok(ciphertext)
+}
+
+/// Decrypt `ciphertext` with the identity in `PODCAST_CTL_IDENTITY`.
+pub fn decrypt(ciphertext: &[u8]) -> Result<Vec<u8>, CliError> {
+    let mut plaintext = Vec::new();
+    decryptor(ciphertext)?.read_to_end(&mut plaintext)?;
+    Ok(plaintext)
+}
+
+fn decryptor<R: Read>(input: R) -> Result<age::stream::StreamReader<R>, CliError> {
+    let identities = identities()?;
+    let decryptor = match age::Decryptor::new(input).map_err(crypto_error)? {
+        age::Decryptor::Recipients(decryptor) => decryptor,
+        _ => {
+            return Err(CliError::CryptoError(
+                "passphrase encrypted files are not supported".to_owned(),
+            ))
+        }
+    };
+
+    decryptor
+        .decrypt(
+            identities
+                .iter()
+                .map(|identity| identity as &dyn age::Identity),
+        )
+        .map_err(crypto_error)
+}
+
+/// [`Decrypted`] for uploads. Decrypting a chunk is quick enough to do
+/// in place rather than on a blocking thread.
+pub struct AsyncDecrypted {
+    reader: Decrypted,
+    seeked: Option<io::Result<u64>>,
+}
+
+impl AsyncDecrypted {
+    pub fn new(reader: Decrypted) -> Self {
+        AsyncDecrypted {
+            reader,
+            seeked: None,
+        }
+    }
+}
+
+impl AsyncRead for AsyncDecrypted {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let read = self.reader.read(buf.initialize_unfilled())?;
+        buf.advance(read);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncSeek for AsyncDecrypted {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let seeked = self.reader.seek(position);
+        self.seeked = Some(seeked);
+        Ok(())
+    }
+
+    fn poll_complete(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        match self.seeked.take() {
+            Some(seeked) => Poll::Ready(seeked),
+            None => Poll::Ready(self.reader.stream_position()),
+        }
+    }
+}
+
+/// The `AGE-SECRET-KEY-...` lines of the identity file.
+fn identities() -> Result<Vec<age::x25519::Identity>, CliError> {
+    let path = std::env::var(IDENTITY_ENV)
+        .map_err(|_| CliError::MissingEnvVar(IDENTITY_ENV.to_owned()))?;
+    let identities: Vec<age::x25519::Identity> = fs::read_to_string(&path)?
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| line.starts_with("AGE-SECRET-KEY-"))
+        .map(|line| line.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|e| CliError::CryptoError(format!("invalid identity in {}: {}", path, e)))?;
+
+    if identities.is_empty() {
+        return Err(CliError::CryptoError(format!("{} has no identities", path)));
+    }
+    Ok(identities)
+}
+
+fn crypto_error(error: age::DecryptError) -> CliError {
+    CliError::CryptoError(error.to_string())
+}
+
+/// `encrypt`: encrypt the file of the episode `selector` picks, and
+/// `media`, leaving files that already are alone.
+pub fn encrypt_episode(
+    episode_dir: &Path,
+    config: &ChannelConfig,
+    selector: &str,
+    media: &[PathBuf],
+) -> Result<(), CliError> {
+    let (path, _) = crate::find_episode(episode_dir, selector, config.channel.tz()?)?;
+    for file in std::iter::once(&path).chain(media) {
+        if is_encrypted(file) {
+            outln!("{}", t!("already-encrypted", path = file.display().to_string()));
+        } else {
+            let encrypted = encrypt_file(file, config)?;
+            outln!("{}", t!("encrypted", path = encrypted.display().to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// `decrypt`: decrypt the file of the episode `selector` picks, and
+/// `media`, leaving files that aren't encrypted alone.
+pub fn decrypt_episode(
+    episode_dir: &Path,
+    config: &ChannelConfig,
+    selector: &str,
+    media: &[PathBuf],
+) -> Result<(), CliError> {
+    let (path, _) = crate::find_episode(episode_dir, selector, config.channel.tz()?)?;
+    for file in std::iter::once(&path).chain(media) {
+        if is_encrypted(file) {
+            let decrypted = decrypt_file(file)?;
+            outln!("{}", t!("decrypted", path = decrypted.display().to_string()));
+        } else {
+            outln!("{}", t!("not-encrypted", path = file.display().to_string()));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,292 @@
+//! The daemon's dashboard: a read-only web page of the show's state, for
+//! co-hosts who would rather not use the terminal. It shows where the show
+//! publishes, every episode and whether it is published, what `validate`
+//! finds, and what recent publishes changed, read afresh on every request.
+//!
+//! Validation runs offline, so artwork hosted elsewhere isn't downloaded
+//! and checked. Nothing on the page changes the show; that is left to the
+//! daemon's JSON-RPC methods and the command line.
+
+use crate::approvals;
+use crate::config::{ChannelConfig, Episode};
+use crate::events;
+use crate::feed;
+use crate::html::{escape, fill};
+use crate::i18n::t;
+use crate::output::outln;
+use crate::serve::{read_request, respond};
+use crate::state::Workspace;
+use crate::upload;
+use crate::validate::{self, Issue, Severity};
+use crate::CliError;
+use chrono::Utc;
+use log::{debug, warn};
+use std::collections::HashSet;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+
+const PAGE_TEMPLATE: &str = include_str!("../templates/dashboard.html");
+
+/// Publishes listed in the history, most recent first
+const HISTORY_LENGTH: usize = 10;
+
+struct Dashboard {
+    episode_dir: PathBuf,
+    config: ChannelConfig,
+}
+
+/// Serve the dashboard at `/` until interrupted.
+pub async fn serve(
+    address: SocketAddr,
+    episode_dir: PathBuf,
+    config: ChannelConfig,
+) -> Result<(), CliError> {
+    let listener = TcpListener::bind(address).await?;
+    outln!(
+        "{}",
+        t!(
+            "dashboard-listening",
+            url = format!("http://{}/", listener.local_addr()?)
+        )
+    );
+
+    let dashboard = Arc::new(Dashboard {
+        episode_dir,
+        config,
+    });
+    let context = events::current();
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let dashboard = dashboard.clone();
+        let context = context.clone();
+        tokio::spawn(async move {
+            let handled = handle(stream, dashboard.as_ref());
+            if let Err(e) = context.scope(handled).await {
+                debug!("Dashboard request from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, dashboard: &Dashboard) -> Result<(), CliError> {
+    let request = match read_request(&mut stream).await? {
+        Some(request) => request,
+        None => return Ok(()),
+    };
+    debug!("{} {}", request.method, request.path);
+    let head_only = match request.method.as_str() {
+        "GET" => false,
+        "HEAD" => true,
+        _ => return respond(&mut stream, "405 Method Not Allowed", "text/plain", b"").await,
+    };
+    if request.path.split('?').next() != Some("/") {
+        return respond(&mut stream, "404 Not Found", "text/plain", b"Not found").await;
+    }
+
+    match render(dashboard).await {
+        Ok(page) => {
+            let body = if head_only { &[][..] } else { page.as_bytes() };
+            respond(&mut stream, "200 OK", "text/html; charset=utf-8", body).await
+        }
+        Err(e) => {
+            let detail = e.localized();
+            warn!("Unable to render the dashboard: {}", detail);
+            respond(
+                &mut stream,
+                "500 Internal Server Error",
+                "text/plain",
+                detail.as_bytes(),
+            )
+            .await
+        }
+    }
+}
+
+async fn render(dashboard: &Dashboard) -> Result<String, CliError> {
+    let config = &dashboard.config;
+    let episode_dir = &dashboard.episode_dir;
+    let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+    let workspace = Workspace::new(channel_dir);
+
+    let mut episode_files = crate::index::episode_files(config, episode_dir)?;
+    let issues = validate::validate_show(config, channel_dir, episode_files.clone(), None).await?;
+    let published: HashSet<String> = match fs::read_to_string(workspace.published_feed_path()) {
+        Ok(feed) => feed::parse_feed(&feed)?
+            .items
+            .iter()
+            .filter_map(|item| item.guid().map(str::to_owned))
+            .collect(),
+        Err(_) => HashSet::new(),
+    };
+
+    episode_files.sort_by_key(|(_, episode)| std::cmp::Reverse(episode.released_at));
+    let episodes: Vec<String> = episode_files
+        .iter()
+        .map(|(path, episode)| episode_row(config, path, episode, &published, &issues))
+        .collect();
+
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+    let issue_items: Vec<String> = issues
+        .iter()
+        .map(|issue| {
+            let class = match issue.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            format!(
+                r#"            <li class="{}">{}</li>"#,
+                class,
+                escape(&issue.to_string())
+            )
+        })
+        .collect();
+
+    Ok(fill(
+        PAGE_TEMPLATE,
+        &[
+            ("language", escape(&config.channel.language)),
+            ("title", escape(&config.channel.title)),
+            ("image", escape(&config.channel.image)),
+            (
+                "updated",
+                Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+            ),
+            ("status", status(config, &workspace)?),
+            ("episode_count", episode_files.len().to_string()),
+            ("episodes", episodes.join("\n")),
+            (
+                "validation_summary",
+                escape(&t!(
+                    "validate-summary",
+                    errors = errors,
+                    warnings = issues.len() - errors
+                )),
+            ),
+            ("issues", issue_items.join("\n")),
+            ("history", history(&workspace)),
+        ],
+    ))
+}
+
+/// Where the show publishes, and what this workspace last did.
+fn status(config: &ChannelConfig, workspace: &Workspace) -> Result<String, CliError> {
+    let publishing = &config.publishing;
+    let feed_url = upload::object_url(publishing, &format!("{}/podcast.xml", publishing.prefix));
+    let journal = workspace.journal()?;
+    let last_upload = match journal.last() {
+        Some(entry) => format!(
+            "{} &middot; {}",
+            entry.at.format("%Y-%m-%d %H:%M:%S UTC"),
+            escape(&entry.key)
+        ),
+        None => "none".to_owned(),
+    };
+    let lock = match workspace.lock_holder() {
+        Some(holder) => format!("held by pid {}, a command is running", escape(&holder)),
+        None => "free".to_owned(),
+    };
+    let rows = [
+        (
+            "Feed",
+            format!(r#"<a href="{0}">{0}</a>"#, escape(&feed_url)),
+        ),
+        (
+            "Storage",
+            format!(
+                "{} &middot; {}",
+                publishing.storage.as_str(),
+                escape(&publishing.prefix)
+            ),
+        ),
+        ("Last upload", last_upload),
+        ("Workspace lock", lock),
+    ];
+    Ok(rows
+        .iter()
+        .map(|(name, value)| format!("            <dt>{}</dt><dd>{}</dd>", name, value))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn episode_row(
+    config: &ChannelConfig,
+    path: &Path,
+    episode: &Episode,
+    published: &HashSet<String>,
+    issues: &[Issue],
+) -> String {
+    let (class, status) = if published.contains(&episode.id) {
+        ("published", "Published".to_owned())
+    } else if episode.draft {
+        ("draft", "Draft".to_owned())
+    } else if let Some(missing) = approvals::missing_approval(&config.approvals, path, episode) {
+        ("blocked", format!("Needs approval: {}", missing))
+    } else if episode.released_at > Utc::now() {
+        ("scheduled", "Scheduled".to_owned())
+    } else {
+        ("ready", "Ready to publish".to_owned())
+    };
+    let (errors, warnings) = issues
+        .iter()
+        .filter(|issue| issue.episodes.contains(&episode.id))
+        .fold((0, 0), |(errors, warnings), issue| match issue.severity {
+            Severity::Error => (errors + 1, warnings),
+            Severity::Warning => (errors, warnings + 1),
+        });
+    let problems = match (errors, warnings) {
+        (0, 0) => String::new(),
+        (0, warnings) => format!(r#"<span class="warning">{} warning(s)</span>"#, warnings),
+        (errors, _) => format!(r#"<span class="error">{} error(s)</span>"#, errors),
+    };
+    format!(
+        r#"                <tr><td>{}</td><td>S{}E{}</td><td>{}</td><td class="status {}">{}</td><td>{}</td></tr>"#,
+        episode.released_at.format("%Y-%m-%d"),
+        episode.season,
+        episode.episode_number,
+        escape(&episode.title),
+        class,
+        escape(&status),
+        problems
+    )
+}
+
+/// The latest publishes from `.podcast-ctl/changelog.md`, newest first.
+fn history(workspace: &Workspace) -> String {
+    let changelog = fs::read_to_string(workspace.changelog_path()).unwrap_or_default();
+    let mut publishes: Vec<(&str, Vec<&str>)> = Vec::new();
+    for line in changelog.lines() {
+        if let Some(heading) = line.strip_prefix("## ") {
+            publishes.push((heading, Vec::new()));
+        } else if let (Some(change), Some((_, changes))) =
+            (line.strip_prefix("- "), publishes.last_mut())
+        {
+            changes.push(change);
+        }
+    }
+    if publishes.is_empty() {
+        return "        <p>Nothing has been published from this workspace yet.</p>".to_owned();
+    }
+    publishes
+        .iter()
+        .rev()
+        .take(HISTORY_LENGTH)
+        .map(|(heading, changes)| {
+            let items: Vec<String> = changes
+                .iter()
+                .map(|change| format!("<li>{}</li>", escape(change)))
+                .collect();
+            format!(
+                "        <h3>{}</h3>\n        <ul>{}</ul>",
+                escape(heading),
+                items.join("")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
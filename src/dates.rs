@@ -0,0 +1,135 @@
+//! Release dates and the timezone they are in. Dates can be given as
+//! `2023-06-06`, a local time like `2023-06-06T06:00`, or an RFC 3339 time
+//! with its offset. Dates without an offset are in the `--timezone`
+//! given, or the channel's `timezone`, or UTC. Episode files store the
+//! instant, and feeds show it in the channel's timezone.
+
+use crate::CliError;
+use chrono::{DateTime, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// The RFC 2822 format of `pubDate` and `lastBuildDate`.
+const FEED_FORMAT: &str = "%a, %d %b %Y %T %z";
+
+/// The timezone named `name`, like `America/New_York`; UTC without one.
+pub fn timezone(name: Option<&str>) -> Result<Tz, CliError> {
+    match name {
+        Some(name) => name
+            .parse()
+            .map_err(|_| CliError::UnknownTimezone(name.to_owned())),
+        None => Ok(Tz::UTC),
+    }
+}
+
+/// The instant `text` means, with dates and local times in `timezone`.
+pub fn parse_release(text: &str, timezone: Tz) -> Result<DateTime<Utc>, CliError> {
+    let invalid = |detail: &str| CliError::InvalidDate(text.to_owned(), detail.to_owned());
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let local = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(|| {
+            invalid("use a date like 2023-06-06, a time like 2023-06-06T06:00, or RFC 3339")
+        })?;
+    match timezone.from_local_datetime(&local) {
+        LocalResult::Single(time) => Ok(time.with_timezone(&Utc)),
+        // When clocks go back, the first of the two
+        LocalResult::Ambiguous(earliest, _) => Ok(earliest.with_timezone(&Utc)),
+        LocalResult::None => Err(invalid(&format!(
+            "that time is skipped in {} when clocks go forward",
+            timezone.name()
+        ))),
+    }
+}
+
+/// `time` as feeds write dates, in `timezone`.
+pub fn feed_date(time: &DateTime<Utc>, timezone: Tz) -> String {
+    time.with_timezone(&timezone)
+        .format(FEED_FORMAT)
+        .to_string()
+}
+
+/// A `lastBuildDate` given as an RFC 3339 time or Unix seconds, like
+/// `--build-date` and `SOURCE_DATE_EPOCH`.
+pub fn parse_build_date(text: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    text.trim()
+        .parse::<i64>()
+        .ok()
+        .and_then(|seconds| Utc.timestamp_opt(seconds, 0).single())
+        .ok_or_else(|| format!("'{}' is not an RFC 3339 time or Unix seconds", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn reads_dates_and_local_times_in_the_timezone() {
+        let new_york: Tz = "America/New_York".parse().unwrap();
+        assert_eq!(
+            parse_release("2023-06-06", new_york).unwrap(),
+            utc("2023-06-06T04:00:00Z")
+        );
+        assert_eq!(
+            parse_release("2023-06-06T06:00", new_york).unwrap(),
+            utc("2023-06-06T10:00:00Z")
+        );
+        assert_eq!(
+            parse_release("2023-12-06 06:00", new_york).unwrap(),
+            utc("2023-12-06T11:00:00Z")
+        );
+        assert_eq!(
+            parse_release("2023-06-06T06:00:30", Tz::UTC).unwrap(),
+            utc("2023-06-06T06:00:30Z")
+        );
+    }
+
+    #[test]
+    fn keeps_the_offset_of_rfc_3339_times() {
+        let tokyo: Tz = "Asia/Tokyo".parse().unwrap();
+        assert_eq!(
+            parse_release("2023-06-06T06:00:00-07:00", tokyo).unwrap(),
+            utc("2023-06-06T13:00:00Z")
+        );
+    }
+
+    #[test]
+    fn takes_the_first_of_repeated_times_and_refuses_skipped_ones() {
+        let new_york: Tz = "America/New_York".parse().unwrap();
+        // 1:30 happens twice on 2023-11-05, first in EDT
+        assert_eq!(
+            parse_release("2023-11-05T01:30", new_york).unwrap(),
+            utc("2023-11-05T05:30:00Z")
+        );
+        // and not at all on 2023-03-12
+        assert!(matches!(
+            parse_release("2023-03-12T02:30", new_york),
+            Err(CliError::InvalidDate(..))
+        ));
+    }
+
+    #[test]
+    fn refuses_what_is_not_a_date() {
+        for text in ["", "June 6, 2023", "2023-13-01", "2023-06-06T25:00", "06/06/2023"] {
+            assert!(
+                matches!(parse_release(text, Tz::UTC), Err(CliError::InvalidDate(..))),
+                "{}",
+                text
+            );
+        }
+    }
+}
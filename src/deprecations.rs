@@ -0,0 +1,188 @@
+//! Warnings about things a later version will stop doing: a field that
+//! was renamed, a value read a legacy way, a built-in default that is
+//! going away. Each deprecation has an id, and is warned about once a run
+//! with how to move off it:
+//!
+//! ```text
+//! warning: channel.yaml has no `copyright`, so the feed says "Copyright 2022"; this default will be removed (default-copyright). Run `podcastctl migrate` to write it into channel.yaml, or silence this with --allow-deprecated default-copyright
+//! ```
+//!
+//! `--allow-deprecated <id>`, or `all`, silences them. With `--output
+//! json` they are listed under `deprecations`, and with `--events` each is
+//! a `deprecated` event. `migrate` fixes the ones it can in the channel
+//! file, keeping what it means today.
+
+use crate::events::{self, Event};
+use crate::formats::Format;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::CliError;
+use serde_yaml::Value;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Deprecation {
+    /// The feed's copyright when channel.yaml has none, which names a year
+    DefaultCopyright,
+    /// The Fiction category when channel.yaml has none
+    DefaultCategories,
+}
+
+/// What `--allow-deprecated` takes to silence every deprecation.
+const ALL_IDS: &str = "all";
+
+static ALLOWED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+static WARNED: Mutex<Vec<Deprecation>> = Mutex::new(Vec::new());
+
+impl Deprecation {
+    pub const ALL: [Deprecation; 2] = [
+        Deprecation::DefaultCopyright,
+        Deprecation::DefaultCategories,
+    ];
+
+    /// What `--allow-deprecated` takes to silence it.
+    pub fn id(self) -> &'static str {
+        match self {
+            Deprecation::DefaultCopyright => "default-copyright",
+            Deprecation::DefaultCategories => "default-categories",
+        }
+    }
+
+    fn message(self) -> String {
+        match self {
+            Deprecation::DefaultCopyright => t!("deprecated-default-copyright"),
+            Deprecation::DefaultCategories => t!("deprecated-default-categories"),
+        }
+    }
+
+    /// How to move off it.
+    fn migration(self) -> String {
+        match self {
+            Deprecation::DefaultCopyright | Deprecation::DefaultCategories => {
+                t!("deprecation-run-migrate")
+            }
+        }
+    }
+
+    /// Whether channel.yaml, as read, relies on it.
+    fn in_channel(self, channel: &Value) -> bool {
+        match self {
+            Deprecation::DefaultCopyright => channel.get("copyright").is_none(),
+            Deprecation::DefaultCategories => channel.get("categories").is_none(),
+        }
+    }
+
+    /// Change channel.yaml, as read, so it no longer relies on it, without
+    /// changing what it means. Returns whether there was anything to do.
+    fn migrate(self, channel: &mut Value) -> bool {
+        if !self.in_channel(channel) {
+            return false;
+        }
+        let mapping = match channel.as_mapping_mut() {
+            Some(mapping) => mapping,
+            None => return false,
+        };
+        match self {
+            Deprecation::DefaultCopyright => {
+                let copyright = crate::config::default_copyright();
+                mapping.insert(Value::from("copyright"), Value::from(copyright));
+            }
+            Deprecation::DefaultCategories => {
+                let categories = crate::config::default_categories();
+                let categories = categories.into_iter().map(Value::from).collect();
+                mapping.insert(Value::from("categories"), Value::Sequence(categories));
+            }
+        }
+        true
+    }
+}
+
+/// Parse an `--allow-deprecated` id.
+pub fn parse_id(id: &str) -> Result<String, String> {
+    if id == ALL_IDS
+        || Deprecation::ALL
+            .iter()
+            .any(|deprecation| deprecation.id() == id)
+    {
+        return Ok(id.to_owned());
+    }
+    let ids: Vec<&str> = Deprecation::ALL
+        .iter()
+        .map(|deprecation| deprecation.id())
+        .collect();
+    Err(format!("ids are {}, and {}", ids.join(", "), ALL_IDS))
+}
+
+/// Silence the deprecations `ids` names for the rest of the run.
+pub fn allow(ids: &[String]) {
+    if let Ok(mut allowed) = ALLOWED.lock() {
+        allowed.extend(ids.iter().cloned());
+    }
+}
+
+/// Warn that `deprecation` was relied on, unless it was already this run
+/// or it is allowed.
+pub fn warn(deprecation: Deprecation) {
+    let allowed = match ALLOWED.lock() {
+        Ok(allowed) => allowed
+            .iter()
+            .any(|id| id == ALL_IDS || id == deprecation.id()),
+        Err(_) => false,
+    };
+    if allowed {
+        return;
+    }
+    match WARNED.lock() {
+        Ok(mut warned) if !warned.contains(&deprecation) => warned.push(deprecation),
+        _ => return,
+    }
+
+    events::warn(t!(
+        "deprecation",
+        message = deprecation.message(),
+        id = deprecation.id(),
+        migration = deprecation.migration()
+    ));
+    crate::output::append("deprecations", deprecation.id());
+    events::emit(Event::Deprecated {
+        id: deprecation.id().to_owned(),
+    });
+}
+
+/// Warn about each deprecation channel.yaml, as read, relies on.
+pub fn check_channel(channel: &Value) {
+    for deprecation in Deprecation::ALL {
+        if deprecation.in_channel(channel) {
+            warn(deprecation);
+        }
+    }
+}
+
+/// `migrate`: change the channel file so it relies on no deprecations,
+/// showing the diff and asking before writing unless `yes` is set.
+pub fn migrate(channel_file: &Path, yes: bool) -> Result<(), CliError> {
+    let format = Format::of(channel_file);
+    let original = fs::read_to_string(channel_file)?;
+    let mut document = format.parse_value(original.as_bytes())?;
+    let migrated: Vec<&str> = Deprecation::ALL
+        .iter()
+        .filter(|deprecation| deprecation.migrate(&mut document))
+        .map(|deprecation| deprecation.id())
+        .collect();
+    if migrated.is_empty() {
+        outln!("{}", t!("migrate-nothing"));
+        return Ok(());
+    }
+
+    let after = crate::formats::update(format, &original, &document)?;
+    crate::diff::print_diff(channel_file, &original, &after);
+    if !crate::diff::confirm(&t!("confirm-write"), yes)? {
+        outln!("{}", t!("nothing-written"));
+        return Ok(());
+    }
+    fs::write(channel_file, after)?;
+    outln!("{}", t!("migrated", ids = migrated.join(", ")));
+    Ok(())
+}
@@ -0,0 +1,69 @@
+//! Previews for commands that rewrite YAML files: a unified diff of each
+//! file as it will be written, comments and all, then a question before
+//! anything is written unless `--yes` was given. Diffs are colored in
+//! terminals, but not with `--screen-reader` or `NO_COLOR`.
+
+use crate::i18n::t;
+use crate::output::{out, outln};
+use crate::CliError;
+use similar::TextDiff;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Print how `path` changes from `before` to `after`, returning whether it
+/// changes at all.
+pub fn print_diff(path: &Path, before: &str, after: &str) -> bool {
+    if before == after {
+        return false;
+    }
+
+    let name = path.display().to_string();
+    let diff = TextDiff::from_lines(before, after)
+        .unified_diff()
+        .header(&name, &name)
+        .to_string();
+    if !use_color() {
+        out!("{}", diff);
+        return true;
+    }
+    for line in diff.lines() {
+        let color = if line.starts_with("+++") || line.starts_with("---") {
+            BOLD
+        } else if line.starts_with('+') {
+            GREEN
+        } else if line.starts_with('-') {
+            RED
+        } else if line.starts_with("@@") {
+            CYAN
+        } else {
+            outln!("{}", line);
+            continue;
+        };
+        outln!("{}{}{}", color, line, RESET);
+    }
+    true
+}
+
+/// Whether to go ahead with writing: `yes`, or a yes from the user.
+pub fn confirm(question: &str, yes: bool) -> Result<bool, CliError> {
+    if yes {
+        return Ok(true);
+    }
+    out!("{} {}: ", question, t!("confirm-choices"));
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes"))
+}
+
+fn use_color() -> bool {
+    !crate::output::screen_reader()
+        && std::env::var_os("NO_COLOR").is_none()
+        && atty::is(atty::Stream::Stdout)
+}
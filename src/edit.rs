@@ -0,0 +1,217 @@
+//! `edit-episode` and `publish`: changing an episode's fields, or editing
+//! its file in the user's editor. With `--open`, the file is edited as a
+//! copy under `.podcast-ctl/`, decrypted when the episode is encrypted, and
+//! only written back once it is an episode with the same id and no new
+//! validation errors.
+
+use crate::config::{ChannelConfig, Episode};
+use crate::crypto;
+use crate::diff;
+use crate::guids;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::state::Workspace;
+use crate::validate::{self, Issue, Severity};
+use crate::CliError;
+use chrono::{Timelike, Utc};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// The fields `edit-episode` changes; `None` leaves a field as it is.
+#[derive(Debug, Clone, Default)]
+pub struct EpisodeChanges {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub summary: Option<String>,
+    /// An empty link removes it
+    pub link: Option<String>,
+    pub season: Option<u64>,
+    pub episode_number: Option<u64>,
+}
+
+/// `edit-episode`: apply `changes` to the episode `selector` picks, or with
+/// `open`, edit its file in the user's editor, saving what passes
+/// validation. Without `yes`, the change is shown and confirmed first.
+pub fn edit_episode(
+    episode_dir: &Path,
+    channel_config: &ChannelConfig,
+    selector: &str,
+    changes: EpisodeChanges,
+    open: bool,
+    yes: bool,
+) -> Result<(), CliError> {
+    let timezone = channel_config.channel.tz()?;
+    let (path, episode) = crate::find_episode(episode_dir, selector, timezone)?;
+    let publishing = &channel_config.publishing;
+    let mut others: Vec<Episode> = crate::get_all_episodes(episode_dir)?
+        .into_iter()
+        .filter(|other| other.id != episode.id)
+        .collect();
+    for other in &mut others {
+        guids::fill(publishing, timezone, other)?;
+    }
+    let medium = channel_config.channel.medium.unwrap_or_default();
+    let numbering = channel_config.seasons.numbering;
+    let check = |edited: &Episode| {
+        validate::check_edit(medium, numbering, publishing, timezone, &episode, edited, &others)
+    };
+    let edited_message = || t!("episode-edited", path = path.display().to_string());
+
+    if open {
+        let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+        let workspace = Workspace::new(channel_dir);
+        match self::open(&workspace, &path, &episode, check)? {
+            Some(text) => {
+                crypto::write(&path, text.as_bytes(), channel_config)?;
+                outln!("{}", edited_message());
+            }
+            None => outln!("{}", t!("nothing-written")),
+        }
+        return Ok(());
+    }
+
+    let mut edited = episode.clone();
+    if let Some(title) = changes.title {
+        edited.title = title;
+    }
+    if let Some(description) = changes.description {
+        edited.description = description;
+    }
+    if let Some(summary) = changes.summary {
+        edited.summary = summary;
+    }
+    if let Some(link) = changes.link {
+        edited.link = Some(link).filter(|link| !link.is_empty());
+    }
+    if let Some(season) = changes.season {
+        edited.season = season;
+    }
+    if let Some(episode_number) = changes.episode_number {
+        edited.episode_number = episode_number;
+    }
+
+    let issues = check(&edited);
+    for issue in &issues {
+        eprintln!("{}", issue);
+    }
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(CliError::ValidationFailed(errors));
+    }
+    if crate::save_episode_confirmed(&path, &edited, channel_config, yes)? {
+        outln!("{}", edited_message());
+    }
+    Ok(())
+}
+
+/// `publish`: release the draft `selector` picks now.
+pub fn publish_draft(
+    episode_dir: &Path,
+    channel_config: &ChannelConfig,
+    selector: &str,
+) -> Result<(), CliError> {
+    let (path, mut episode) =
+        crate::find_episode(episode_dir, selector, channel_config.channel.tz()?)?;
+    if !episode.draft {
+        return Err(CliError::NotDraft(episode.id));
+    }
+    episode.draft = false;
+    episode.released_at = Utc::now().with_nanosecond(0).unwrap_or_else(Utc::now);
+    crate::save_episode(&path, &episode, channel_config)?;
+    outln!(
+        "{}",
+        t!(
+            "episode-published",
+            title = episode.title.as_str(),
+            date = episode.released_at.to_rfc3339()
+        )
+    );
+    Ok(())
+}
+
+/// Open the episode file at `path` in `$VISUAL` or `$EDITOR` until what is
+/// saved passes `check`, or the user gives up. `check` returns the problems
+/// with an edited episode. Returns the edited text, or `None` when it
+/// wasn't changed or the user gave up.
+pub fn open<F>(
+    workspace: &Workspace,
+    path: &Path,
+    episode: &Episode,
+    check: F,
+) -> Result<Option<String>, CliError>
+where
+    F: Fn(&Episode) -> Vec<Issue>,
+{
+    let original = String::from_utf8_lossy(&crypto::read(path)?).into_owned();
+    let format = crate::formats::Format::of(path);
+    let copy = workspace.cache_file(&format!("edit-{}.{}", episode.id, format.extension()));
+    if let Some(parent) = copy.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&copy, &original)?;
+
+    let edited = loop {
+        run_editor(&copy)?;
+        let text = fs::read_to_string(&copy)?;
+        if text == original {
+            break None;
+        }
+
+        let problems = match format.parse::<Episode>(text.as_bytes()) {
+            Ok(edited) if edited.id != episode.id => {
+                vec![t!("edit-id-changed", id = episode.id.as_str())]
+            }
+            Ok(edited) => {
+                let issues = check(&edited);
+                for issue in issues
+                    .iter()
+                    .filter(|issue| issue.severity == Severity::Warning)
+                {
+                    eprintln!("{}", issue);
+                }
+                issues
+                    .iter()
+                    .filter(|issue| issue.severity == Severity::Error)
+                    .map(|issue| issue.to_string())
+                    .collect()
+            }
+            Err(e) => vec![e.to_string()],
+        };
+        if problems.is_empty() {
+            break Some(text);
+        }
+        for problem in problems {
+            eprintln!("{}", problem);
+        }
+        if !diff::confirm(&t!("edit-again"), false)? {
+            break None;
+        }
+    };
+    fs::remove_file(&copy)?;
+    Ok(edited)
+}
+
+/// Run the user's editor on `path` through the shell, so editors given with
+/// arguments, like `code --wait`, work.
+fn run_editor(path: &Path) -> Result<(), CliError> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_owned());
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path)
+        .stdout(crate::output::child_stdout())
+        .status()?;
+    if !status.success() {
+        return Err(CliError::EditorFailed(editor, status.to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,119 @@
+//! `import-email`: a draft episode from an email, so remote contributors
+//! can submit episodes without access to the show's storage. The audio is
+//! the first audio attachment, the subject is the title, and the plain text
+//! body is the description. A subject like `2024-06-02 - Title` sets the
+//! release date; otherwise it is the day the email was sent.
+//!
+//! Messages are read from a file or stdin, so a mail server or fetchmail
+//! can hand each one over as it arrives, e.g. in `~/.fetchmailrc`:
+//!
+//! ```text
+//! mda "podcastctl -c /srv/show import-email -"
+//! ```
+
+use crate::audio::{self, AudioFormat};
+use crate::CliError;
+use chrono::{TimeZone, Utc};
+use chrono_tz::Tz;
+use mail_parser::{MessageParser, MimeHeaders};
+use std::path::Path;
+
+/// Where attachments are saved, next to `channel.yaml`.
+pub const EMAIL_DIR: &str = "email";
+
+/// What an email submits.
+pub struct Submission {
+    /// The sender's address
+    pub from: String,
+    pub title: String,
+    /// For `create-episode --date`
+    pub date: String,
+    pub description: Option<String>,
+    pub audio: Vec<u8>,
+    pub format: AudioFormat,
+}
+
+/// The episode `raw`, a whole RFC 822 message, submits, with the day it
+/// was sent in `timezone`.
+pub fn parse(raw: &[u8], timezone: Tz) -> Result<Submission, CliError> {
+    let rejected = |detail: &str| CliError::EmailRejected(detail.to_owned());
+    let message = MessageParser::default()
+        .parse(raw)
+        .ok_or_else(|| rejected("it isn't an email message"))?;
+
+    let from = message
+        .from()
+        .and_then(|from| from.first())
+        .and_then(|from| from.address.as_deref())
+        .map(str::to_lowercase)
+        .ok_or_else(|| rejected("it has no sender"))?;
+    let subject = message.subject().map(str::trim).unwrap_or_default();
+    let (date, title) = match crate::batch::inbox_name(subject) {
+        Some(named) => named,
+        None => {
+            let sent = message
+                .date()
+                .and_then(|date| Utc.timestamp_opt(date.to_timestamp(), 0).single())
+                .unwrap_or_else(Utc::now);
+            (
+                sent.with_timezone(&timezone).format("%Y-%m-%d").to_string(),
+                subject.to_owned(),
+            )
+        }
+    };
+    if title.is_empty() {
+        return Err(rejected("its subject, the episode's title, is empty"));
+    }
+
+    let (audio, format) = message
+        .attachments()
+        .find_map(|part| {
+            let name = part.attachment_name().unwrap_or_default();
+            let contents = part.contents();
+            let head = &contents[..contents.len().min(64)];
+            AudioFormat::detect(Path::new(name), head).map(|format| (contents.to_vec(), format))
+        })
+        .ok_or_else(|| {
+            let unsupported = message.attachments().find_map(|part| {
+                let contents = part.contents();
+                audio::unsupported_audio(&contents[..contents.len().min(64)])?;
+                let name = part.attachment_name().unwrap_or("attachment");
+                Some(audio::not_audio(Path::new(name), contents))
+            });
+            rejected(&unsupported.unwrap_or_else(|| "it has no audio attached".to_owned()))
+        })?;
+
+    Ok(Submission {
+        from,
+        title,
+        date,
+        description: message.body_text(0).as_deref().and_then(description),
+        audio,
+        format,
+    })
+}
+
+/// Whether `from` may submit episodes: anyone when `senders` is empty.
+/// Sender addresses are easily forged, so episodes are only ever drafts.
+pub fn allowed(senders: &[String], from: &str) -> bool {
+    senders.is_empty()
+        || senders
+            .iter()
+            .any(|sender| sender.eq_ignore_ascii_case(from))
+}
+
+/// The body without its signature or quoted replies, when anything is left.
+fn description(body: &str) -> Option<String> {
+    let lines: Vec<&str> = body
+        .lines()
+        .take_while(|line| *line != "-- ")
+        .filter(|line| !line.starts_with('>'))
+        .map(str::trim_end)
+        .collect();
+    let description = lines.join("\n").trim().to_owned();
+    if description.is_empty() {
+        None
+    } else {
+        Some(description)
+    }
+}
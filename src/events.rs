@@ -0,0 +1,185 @@
+//! Typed progress events, for programs that drive podcast-ctl and show
+//! their own progress instead of its bars and messages.
+//!
+//! Library calls take a [`Context`]: whether uploads are only printed,
+//! how output is shaped, and the listeners to tell about events, warnings
+//! among them. Nothing
+//! outlives the call it was passed to, so two calls can run side by side
+//! with their own. Other programs, like a GUI wrapper, run podcast-ctl
+//! with `--events` and read the events as JSON lines on stderr, one object
+//! each, named by its `event` field:
+//!
+//! ```json
+//! {"event":"uploadStarted","key":"show/artifacts/2022-09-08.mp3","bytes":52428800}
+//! {"event":"bytesTransferred","key":"show/artifacts/2022-09-08.mp3","done":16777216,"total":52428800}
+//! ```
+
+use crate::output::{Output, OutputFormat};
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum Event {
+    /// An object started uploading to storage.
+    UploadStarted { key: String, bytes: u64 },
+    /// More of an object was uploaded; `done` of `total` bytes so far.
+    BytesTransferred { key: String, done: u64, total: u64 },
+    /// An object finished uploading and is served at `url`.
+    UploadFinished { key: String, url: String },
+    /// The feeds were rendered, before any of them are uploaded.
+    RenderComplete {
+        feeds: usize,
+        episodes: usize,
+        bytes: u64,
+    },
+    /// Something deprecated was relied on; `id` is what
+    /// `--allow-deprecated` takes to silence it.
+    Deprecated { id: String },
+    /// Something was left out or couldn't be done, but the command goes on.
+    Warning { message: String },
+}
+
+type Listener = Arc<dyn Fn(&Event) + Send + Sync>;
+
+/// What a library call runs with, in place of the command line's global
+/// options.
+#[derive(Clone, Default)]
+pub struct Context {
+    dry_run: bool,
+    output: Output,
+    listeners: Vec<Listener>,
+}
+
+tokio::task_local! {
+    /// The context of the library call being made, for the code under it.
+    static CURRENT: Context;
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Context::default()
+    }
+
+    /// Print what uploads would write instead, and write nothing.
+    pub fn dry_run(mut self, enabled: bool) -> Self {
+        self.dry_run = enabled;
+        self
+    }
+
+    /// Print short lines that read well aloud instead of bars and tables.
+    pub fn screen_reader(mut self, enabled: bool) -> Self {
+        self.output.screen_reader = enabled;
+        self
+    }
+
+    /// Show no progress, only results.
+    pub fn quiet(mut self, enabled: bool) -> Self {
+        self.output.quiet = enabled;
+        self
+    }
+
+    /// Print results as `format`. With JSON, the document is started for
+    /// `command`, and what is printed goes to stderr.
+    pub fn output_format(mut self, format: OutputFormat, command: Option<&str>) -> Self {
+        self.output.json = format == OutputFormat::Json;
+        self.output.results = Default::default();
+        if let Ok(mut results) = self.output.results.lock() {
+            results.push(("command".to_owned(), serde_json::json!(command)));
+        }
+        self
+    }
+
+    /// Call `listener` with every event of the calls given this context.
+    pub fn listen(mut self, listener: impl Fn(&Event) + Send + Sync + 'static) -> Self {
+        self.listeners.push(Arc::new(listener));
+        self
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Tell every listener about `event`.
+    pub fn emit(&self, event: Event) {
+        for listener in &self.listeners {
+            listener(&event);
+        }
+    }
+
+    /// Tell every listener about a warning.
+    pub fn warn(&self, message: impl Into<String>) {
+        self.emit(Event::Warning {
+            message: message.into(),
+        });
+    }
+
+    /// Run `future` with this as the context of everything under it.
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        CURRENT.scope(self.clone(), future).await
+    }
+
+    /// Run `f` with this as the context of everything under it.
+    pub fn sync_scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        CURRENT.sync_scope(self.clone(), f)
+    }
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Context")
+            .field("dry_run", &self.dry_run)
+            .field("output", &self.output)
+            .field("listeners", &self.listeners.len())
+            .finish()
+    }
+}
+
+/// The context of the call being made, or outside of one, the default.
+pub fn current() -> Context {
+    CURRENT.try_with(Context::clone).unwrap_or_default()
+}
+
+/// How the output of the call being made is shaped, or outside of one,
+/// the default.
+pub fn output() -> Output {
+    CURRENT
+        .try_with(|context| context.output.clone())
+        .unwrap_or_default()
+}
+
+/// Whether the call being made is a dry run. Outside of one, it isn't.
+pub fn dry_run() -> bool {
+    CURRENT.try_with(Context::is_dry_run).unwrap_or(false)
+}
+
+/// Tell the listeners of the call being made about `event`.
+pub fn emit(event: Event) {
+    let _ = CURRENT.try_with(|context| context.emit(event));
+}
+
+/// Warn the listeners of the call being made, or outside of one, print
+/// the warning to stderr.
+pub fn warn(message: impl Into<String>) {
+    let message = message.into();
+    let warned = CURRENT.try_with(|context| context.warn(message.clone()));
+    if warned.is_err() {
+        eprintln!("{}", message);
+    }
+}
+
+/// Print warnings to stderr, for the command line.
+pub fn print_warnings(event: &Event) {
+    if let Event::Warning { message } = event {
+        eprintln!("{}", message);
+    }
+}
+
+/// Write `event` to stderr as a line of JSON, for `--events`.
+pub fn write_json(event: &Event) {
+    if let Ok(line) = serde_json::to_string(event) {
+        eprintln!("{}", line);
+    }
+}
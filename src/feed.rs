@@ -0,0 +1,136 @@
+use crate::CliError;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader;
+use std::collections::BTreeMap;
+
+/// A published RSS feed reduced to `path => value` pairs, so feeds from
+/// different generators can be compared without caring about formatting.
+/// Nested elements are joined with `/` and attributes with `@`, e.g.
+/// `itunes:owner/itunes:email` or `enclosure@url`.
+#[derive(Debug, Default)]
+pub struct ParsedFeed {
+    pub channel: BTreeMap<String, String>,
+    pub items: Vec<ParsedItem>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ParsedItem {
+    pub fields: BTreeMap<String, String>,
+}
+
+impl ParsedItem {
+    pub fn guid(&self) -> Option<&str> {
+        self.fields.get("guid").map(|guid| guid.as_str())
+    }
+
+    pub fn title(&self) -> &str {
+        self.fields
+            .get("title")
+            .map(|title| title.as_str())
+            .unwrap_or("<untitled>")
+    }
+}
+
+pub fn parse_feed(text: &str) -> Result<ParsedFeed, CliError> {
+    let mut reader = Reader::from_str(text);
+    reader.trim_text(true);
+
+    let mut feed = ParsedFeed::default();
+    let mut current_item: Option<ParsedItem> = None;
+    // Element names below <channel> or the current <item>
+    let mut path: Vec<String> = Vec::new();
+    let mut in_channel = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => {
+                let name = element_name(&element);
+                if !in_channel {
+                    in_channel = name == "channel";
+                    continue;
+                }
+                if name == "item" && current_item.is_none() && path.is_empty() {
+                    current_item = Some(ParsedItem::default());
+                    continue;
+                }
+                path.push(name);
+                let fields = target(&mut feed, &mut current_item);
+                add_attributes(fields, &path, &element)?;
+            }
+            Event::Empty(element) => {
+                if !in_channel {
+                    continue;
+                }
+                path.push(element_name(&element));
+                let fields = target(&mut feed, &mut current_item);
+                add_attributes(fields, &path, &element)?;
+                fields.entry(path.join("/")).or_default();
+                path.pop();
+            }
+            Event::Text(text) => {
+                if in_channel && !path.is_empty() {
+                    let value = text.unescape()?.into_owned();
+                    append(target(&mut feed, &mut current_item), path.join("/"), value);
+                }
+            }
+            Event::CData(data) => {
+                if in_channel && !path.is_empty() {
+                    let value = String::from_utf8_lossy(&data.into_inner()).into_owned();
+                    append(target(&mut feed, &mut current_item), path.join("/"), value);
+                }
+            }
+            Event::End(_) => {
+                if path.pop().is_none() {
+                    if let Some(item) = current_item.take() {
+                        feed.items.push(item);
+                    } else {
+                        in_channel = false;
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(feed)
+}
+
+fn target<'a>(
+    feed: &'a mut ParsedFeed,
+    current_item: &'a mut Option<ParsedItem>,
+) -> &'a mut BTreeMap<String, String> {
+    match current_item {
+        Some(item) => &mut item.fields,
+        None => &mut feed.channel,
+    }
+}
+
+fn element_name(element: &BytesStart) -> String {
+    String::from_utf8_lossy(element.name().as_ref()).into_owned()
+}
+
+fn add_attributes(
+    fields: &mut BTreeMap<String, String>,
+    path: &[String],
+    element: &BytesStart,
+) -> Result<(), CliError> {
+    for attribute in element.attributes().flatten() {
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+        if key.starts_with("xmlns") {
+            continue;
+        }
+        let value = attribute.unescape_value()?.into_owned();
+        append(fields, format!("{}@{}", path.join("/"), key), value);
+    }
+    Ok(())
+}
+
+/// Repeated elements (categories, keywords) are kept as one ` | ` joined value.
+fn append(fields: &mut BTreeMap<String, String>, key: String, value: String) {
+    let entry = fields.entry(key).or_default();
+    if !entry.is_empty() {
+        entry.push_str(" | ");
+    }
+    entry.push_str(value.trim());
+}
@@ -0,0 +1,231 @@
+//! What publishing would change for listeners: `diff` renders the feed
+//! from the local files and compares it with the published podcast.xml,
+//! or another feed, element by element:
+//!
+//! ```text
+//! Channel
+//!   ~ itunes:keywords: "Dungeons and Dragons" → "Dungeons and Dragons,dnd"
+//! Added
+//!   + "Session 12" (4a9f6ff2-73ae-47e1-a4ec-12d4d5fbbe9a)
+//! Changed "Session 11"
+//!   ~ enclosure@length: "100896867" → "100896901"
+//! 1 added, 0 removed, 1 changed, 1 channel field(s) changed
+//! ```
+//!
+//! Episodes are matched by GUID, and `lastBuildDate` and `pubDate` of the
+//! channel, which change on every render, are left out.
+
+use crate::compare::{self, diff_feeds, FeedDifference};
+use crate::feed::{parse_feed, ParsedFeed};
+use crate::http::HttpClient;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::CliError;
+use reqwest::{Method, StatusCode};
+use serde_json::json;
+
+/// Values longer than this, like descriptions, are cut short.
+const MAX_VALUE_LENGTH: usize = 80;
+
+/// How the feed changed, a line per field or episode.
+#[derive(Debug)]
+struct Changes {
+    channel: Vec<String>,
+    added: Vec<String>,
+    removed: Vec<String>,
+    /// Episodes by title, with a line per field
+    changed: Vec<(String, Vec<String>)>,
+}
+
+/// The feed at `published`, a URL or path, or no episodes when the URL
+/// isn't published yet.
+pub async fn load_published(client: &HttpClient, published: &str) -> Result<ParsedFeed, CliError> {
+    if !compare::is_url(published) {
+        return compare::load_feed(published, client).await;
+    }
+    // Straight from the bucket, never from the HTTP cache
+    let response = client
+        .send(published, || client.request(Method::GET, published))
+        .await?;
+    if response.status == StatusCode::NOT_FOUND {
+        outln!("{}", t!("diff-not-published", url = published));
+        return Ok(ParsedFeed::default());
+    }
+    if !response.status.is_success() {
+        return Err(CliError::HttpStatus(
+            published.to_owned(),
+            response.status.as_u16(),
+        ));
+    }
+    parse_feed(&response.text())
+}
+
+/// Print how `rendered`, the feed as the local files render it, differs
+/// from `published`, the feed at `source`.
+pub fn print(published: &ParsedFeed, rendered: &str, source: &str) -> Result<(), CliError> {
+    let local = parse_feed(rendered)?;
+    let changes = changes(published, &local);
+    crate::output::record(
+        "diff",
+        json!({
+            "channel": changes.channel,
+            "added": changes.added,
+            "removed": changes.removed,
+            "changed": changes
+                .changed
+                .iter()
+                .map(|(title, fields)| json!({ "title": title, "fields": fields }))
+                .collect::<Vec<_>>(),
+        }),
+    );
+    if changes.channel.is_empty()
+        && changes.added.is_empty()
+        && changes.removed.is_empty()
+        && changes.changed.is_empty()
+    {
+        outln!("{}", t!("diff-unchanged", url = source));
+        return Ok(());
+    }
+
+    if !changes.channel.is_empty() {
+        outln!("{}", t!("diff-channel"));
+        for line in &changes.channel {
+            outln!("  {}", line);
+        }
+    }
+    if !changes.added.is_empty() {
+        outln!("{}", t!("diff-added"));
+        for line in &changes.added {
+            outln!("  + {}", line);
+        }
+    }
+    if !changes.removed.is_empty() {
+        outln!("{}", t!("diff-removed"));
+        for line in &changes.removed {
+            outln!("  - {}", line);
+        }
+    }
+    for (title, fields) in &changes.changed {
+        outln!("{}", t!("diff-changed", title = format!("{:?}", title)));
+        for line in fields {
+            outln!("  {}", line);
+        }
+    }
+    outln!(
+        "{}",
+        t!(
+            "diff-summary",
+            added = changes.added.len(),
+            removed = changes.removed.len(),
+            changed = changes.changed.len(),
+            channel = changes.channel.len()
+        )
+    );
+    Ok(())
+}
+
+fn changes(published: &ParsedFeed, local: &ParsedFeed) -> Changes {
+    // Local titles win over the ones they replace
+    let title = |guid: &str| {
+        let item = local
+            .items
+            .iter()
+            .chain(&published.items)
+            .find(|item| item.guid() == Some(guid));
+        item.map_or(guid, |item| item.title()).to_owned()
+    };
+    let local_value = |guid: Option<&str>, field: &str| {
+        let fields = match guid {
+            Some(guid) => local
+                .items
+                .iter()
+                .find(|item| item.guid() == Some(guid))
+                .map(|item| &item.fields),
+            None => Some(&local.channel),
+        };
+        fields
+            .and_then(|fields| fields.get(field))
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    let mut changed: Vec<(String, Vec<String>)> = Vec::new();
+    let mut change = |title: String, line: String| match changed
+        .iter_mut()
+        .find(|(episode, _)| *episode == title)
+    {
+        Some((_, lines)) => lines.push(line),
+        None => changed.push((title, vec![line])),
+    };
+    let mut channel = Vec::new();
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    for difference in diff_feeds(local, published, true) {
+        match difference {
+            FeedDifference::ChangedChannelField {
+                field,
+                ours,
+                reference,
+            } => channel.push(changed_line(&field, &reference, &ours)),
+            FeedDifference::MissingChannelField { field, reference } => {
+                channel.push(removed_line(&field, &reference))
+            }
+            FeedDifference::ExtraChannelField { field } => {
+                channel.push(added_line(&field, &local_value(None, &field)))
+            }
+            FeedDifference::ExtraItem { guid, title } => {
+                added.push(format!("{:?} ({})", title, guid))
+            }
+            FeedDifference::MissingItem { guid, title } => {
+                removed.push(format!("{:?} ({})", title, guid))
+            }
+            FeedDifference::GuidMismatch {
+                title,
+                ours,
+                reference,
+            } => change(title, changed_line("guid", &reference, &ours)),
+            FeedDifference::ChangedItemField {
+                guid,
+                field,
+                ours,
+                reference,
+            } => change(title(&guid), changed_line(&field, &reference, &ours)),
+            FeedDifference::MissingItemField {
+                guid,
+                field,
+                reference,
+            } => change(title(&guid), removed_line(&field, &reference)),
+            FeedDifference::ExtraItemField { guid, field } => {
+                let value = local_value(Some(&guid), &field);
+                change(title(&guid), added_line(&field, &value))
+            }
+        }
+    }
+    Changes {
+        channel,
+        added,
+        removed,
+        changed,
+    }
+}
+
+fn changed_line(field: &str, before: &str, after: &str) -> String {
+    format!("~ {}: {} → {}", field, shorten(before), shorten(after))
+}
+
+fn added_line(field: &str, value: &str) -> String {
+    format!("+ {}: {}", field, shorten(value))
+}
+
+fn removed_line(field: &str, value: &str) -> String {
+    format!("- {}: {}", field, shorten(value))
+}
+
+/// `value` quoted, cut short at a character boundary when it is long.
+fn shorten(value: &str) -> String {
+    if value.chars().count() <= MAX_VALUE_LENGTH {
+        return format!("{:?}", value);
+    }
+    let short: String = value.chars().take(MAX_VALUE_LENGTH).collect();
+    format!("{:?}…", short)
+}
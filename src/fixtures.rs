@@ -0,0 +1,295 @@
+//! Synthetic channels for benchmarks, demos, and integration tests.
+//!
+//! Everything is generated from a seed, so the same options always write
+//! the same files.
+
+use crate::config::*;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::CliError;
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+const ADJECTIVES: [&str; 16] = [
+    "Lost",
+    "Haunted",
+    "Golden",
+    "Forgotten",
+    "Burning",
+    "Silent",
+    "Crooked",
+    "Sunken",
+    "Frozen",
+    "Wandering",
+    "Hidden",
+    "Broken",
+    "Verdant",
+    "Restless",
+    "Shattered",
+    "Ancient",
+];
+const NOUNS: [&str; 16] = [
+    "Tavern", "Farm", "Dragon", "Crypt", "Market", "Tower", "Harvest", "Caravan", "Bargain",
+    "Festival", "Ruins", "Heist", "Orchard", "Wizard", "Bridge", "Mine",
+];
+const SENTENCES: [&str; 12] = [
+    "The party argues about whether the map is upside down.",
+    "A suspicious goat follows everyone into town.",
+    "Someone finally reads the fine print on the quest board.",
+    "The bard insists the song counts as a persuasion check.",
+    "A shortcut through the swamp goes exactly as well as expected.",
+    "The innkeeper has opinions about the last group of adventurers.",
+    "Nobody remembers who was supposed to be keeping watch.",
+    "A treasure chest turns out to contain mostly turnips.",
+    "The rogue tries to sell the same sword back to the blacksmith.",
+    "An old rival shows up with a suspiciously good offer.",
+    "The cleric rolls a natural one at the worst possible moment.",
+    "Plans for the farm get more ambitious by the minute.",
+];
+const KEYWORDS: [&str; 10] = [
+    "Dungeons and Dragons",
+    "Actual Play",
+    "Comedy",
+    "Fantasy",
+    "Tabletop",
+    "Role Playing",
+    "Farming",
+    "Improv",
+    "Storytelling",
+    "Adventure",
+];
+
+/// Episodes per season
+const SEASON_LENGTH: usize = 50;
+/// A silent MPEG-1 Layer III frame header: 128 kbit/s, 44.1 kHz, mono.
+const SILENT_FRAME_HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0xC4];
+const SILENT_FRAME_LENGTH: usize = 417;
+const SAMPLES_PER_FRAME: u64 = 1152;
+const SAMPLE_RATE: u64 = 44100;
+
+pub struct FixtureParams {
+    pub episodes: usize,
+    pub seed: u64,
+    /// Length of the silent MP3 written for each episode, if any
+    pub audio_seconds: Option<u64>,
+}
+
+/// Write a synthetic channel to `channel_file`, its episodes next to it,
+/// and optionally a silent MP3 per episode in `audio/`.
+pub fn generate_fixtures(
+    channel_file: &Path,
+    force: bool,
+    params: &FixtureParams,
+) -> Result<(), CliError> {
+    if channel_file.exists() && !force {
+        return Err(CliError::ChannelExists(channel_file.display().to_string()));
+    }
+
+    let mut rng = SplitMix64(params.seed);
+    let channel = ChannelDetails {
+        title: "Fixture Farm".to_owned(),
+        link: Some("https://example.com/fixture-farm".to_owned()),
+        description: "A synthetic show generated for benchmarks and demos.".to_owned(),
+        subtitle: "Will they start a farm?".to_owned(),
+        summary: "Adventurers keep almost starting a farm.".to_owned(),
+        explicit: false,
+        image: "https://example.com/fixture-farm/art.jpg".to_owned(),
+        owner: OwnerDetails {
+            name: "Fixture Owner".to_owned(),
+            email: "owner@example.com".to_owned(),
+        },
+        keywords: KEYWORDS[..3].iter().map(|k| k.to_string()).collect(),
+        categories: vec!["Leisure > Games".to_owned()],
+        language: "en-us".to_owned(),
+        copyright: "Copyright 2022 Fixture Owner".to_owned(),
+        podcast_type: PodcastType::Serial,
+        medium: None,
+        podcast_guid: None,
+        locked: None,
+        funding: Vec::new(),
+        hosts: Vec::new(),
+        guests: Vec::new(),
+        trailers: Vec::new(),
+        timezone: None,
+        explicit_terms: Vec::new(),
+    };
+    let publishing = PublishingConfig {
+        storage: StorageType::S3,
+        region: Region {
+            name: Some("us-east-1".to_owned()),
+            endpoint: "s3.us-east-1.amazonaws.com".to_owned(),
+            profile: None,
+        },
+        bucket: "fixtures".to_owned(),
+        prefix: "fixture-farm".to_owned(),
+        stylesheet: None,
+        default_stylesheet: false,
+        recent_feed: None,
+        max_items: None,
+        per_season_feeds: false,
+        site: false,
+        verify_cdn: None,
+        websub_hub: None,
+        destinations: BTreeMap::new(),
+        notifications: NotificationsConfig::default(),
+        private: None,
+        parallel_uploads: default_parallel_uploads(),
+        multipart: MultipartConfig::default(),
+        retry: RetryConfig::default(),
+        object_options: ObjectOptionsConfig::default(),
+        key_layout: KeyLayout::default(),
+        name_template: None,
+        guid_strategy: GuidStrategy::default(),
+        guid_template: None,
+        gzip_feeds: false,
+        uncompressed_feed_suffix: None,
+        public_base_url: None,
+        path_style: false,
+        local: None,
+        sftp: None,
+        azure: None,
+    };
+    crate::init::write_channel(channel_file, &channel, &publishing)?;
+
+    let channel_dir = match channel_file.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+        _ => Path::new(".").to_owned(),
+    };
+    let episode_dir = channel_dir.join("episodes");
+    let audio_dir = channel_dir.join("audio");
+    let silent_audio = params.audio_seconds.map(silent_mp3);
+    if silent_audio.is_some() {
+        fs::create_dir_all(&audio_dir)?;
+    }
+
+    // Spread the episodes over about ten years, at most one a day and at
+    // least one a week, ending on a fixed date
+    let interval = (3650 / params.episodes.max(1)).clamp(1, 7) as i64;
+    let last_release: DateTime<Utc> = Utc.from_utc_datetime(
+        &NaiveDate::from_ymd_opt(2022, 9, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .expect("2022-09-01 is a date"),
+    );
+
+    for index in 0..params.episodes {
+        let released_at =
+            last_release - Duration::days(interval * (params.episodes - 1 - index) as i64);
+        let name = released_at.format("%Y-%m-%d").to_string();
+        let object_key = format!("{}/artifacts/{}.mp3", publishing.prefix, name);
+
+        let (duration, bytes) = match &silent_audio {
+            Some(audio) => {
+                fs::write(audio_dir.join(format!("{}.mp3", name)), audio)?;
+                (params.audio_seconds.unwrap_or_default(), audio.len() as u64)
+            }
+            None => {
+                let duration = 20 * 60 + rng.below(100 * 60);
+                (duration, duration * 128_000 / 8)
+            }
+        };
+
+        let title = format!(
+            "The {} {}",
+            ADJECTIVES[rng.below(ADJECTIVES.len() as u64) as usize],
+            NOUNS[rng.below(NOUNS.len() as u64) as usize]
+        );
+        let paragraphs: Vec<String> = (0..2 + rng.below(3))
+            .map(|_| {
+                (0..2 + rng.below(3))
+                    .map(|_| SENTENCES[rng.below(SENTENCES.len() as u64) as usize])
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        let mut keywords: Vec<String> = (0..3)
+            .map(|_| KEYWORDS[rng.below(KEYWORDS.len() as u64) as usize].to_owned())
+            .collect();
+        keywords.sort();
+        keywords.dedup();
+
+        let episode = Episode {
+            id: Uuid::from_u64_pair(rng.next(), rng.next()).to_string(),
+            guid: None,
+            title,
+            summary: paragraphs[0].clone(),
+            description: paragraphs.join("\n\n"),
+            link: None,
+            image: channel.image.clone(),
+            season: (index / SEASON_LENGTH + 1) as u64,
+            episode_number: (index + 1) as u64,
+            released_at,
+            media: EpisodeMedia {
+                url: crate::upload::object_url(&publishing, &object_key),
+                duration: MediaDuration(duration),
+                bytes: ByteSize(bytes),
+                bitrate: Some(128),
+                recorded_date: None,
+                mime_type: None,
+            },
+            keywords,
+            extend_channel_keywords: false,
+            transcripts: Vec::new(),
+            chapters: Vec::new(),
+            hosts: Vec::new(),
+            guests: Vec::new(),
+            episode_type: EpisodeType::Full,
+            explicit: None,
+            approved_by: Vec::new(),
+            notes: None,
+            draft: false,
+            destination: None,
+        };
+        fs::write(
+            episode_dir.join(format!("{}-session.yaml", name)),
+            serde_yaml::to_string(&episode)?,
+        )?;
+    }
+
+    outln!(
+        "{}",
+        t!(
+            "fixtures-wrote",
+            channel = channel_file.display().to_string(),
+            count = params.episodes,
+            path = episode_dir.display().to_string()
+        )
+    );
+    if silent_audio.is_some() {
+        outln!(
+            "{}",
+            t!(
+                "fixtures-wrote-audio",
+                path = audio_dir.display().to_string()
+            )
+        );
+    }
+    Ok(())
+}
+
+/// An MP3 of `seconds` of silence, made of empty frames.
+fn silent_mp3(seconds: u64) -> Vec<u8> {
+    let frames = (seconds * SAMPLE_RATE + SAMPLES_PER_FRAME - 1) / SAMPLES_PER_FRAME;
+    let mut frame = vec![0; SILENT_FRAME_LENGTH];
+    frame[..SILENT_FRAME_HEADER.len()].copy_from_slice(&SILENT_FRAME_HEADER);
+    frame.repeat(frames as usize)
+}
+
+/// Small, seedable random numbers; the fixtures don't need more.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
@@ -0,0 +1,262 @@
+//! Channel and episode files in TOML or JSON as well as YAML, picked by
+//! the file's extension, under `.age` for encrypted episodes:
+//! `channel.toml`, `2022-09-01-session.json`, and `.yaml` for anything
+//! else. Every format is read into the same document as YAML, so
+//! environment variables, `--set`, and validation work the same in each.
+//!
+//! Only YAML is edited in place. TOML and JSON files are written again
+//! whole when a command changes them, so comments in a TOML file are lost
+//! then. New episodes are written in the channel file's format.
+//!
+//! JSON transcripts are often kept in `episodes/` too, so a `.json` file
+//! there is only an episode when no other episode file has its name, like
+//! `2022-09-01.json` next to `2022-09-01.yaml`, and its name has no other
+//! extension, like `2022-09-01.es.json`.
+
+use crate::config::ChannelConfig;
+use crate::crypto;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::CliError;
+use clap::ValueEnum;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Yaml,
+    Toml,
+    Json,
+}
+
+const FORMATS: [Format; 3] = [Format::Yaml, Format::Toml, Format::Json];
+
+static NEW_EPISODES: AtomicU8 = AtomicU8::new(Format::Yaml as u8);
+
+/// Write new episode files in `format` for the rest of the run.
+pub fn set_new_episodes(format: Format) {
+    NEW_EPISODES.store(format as u8, Ordering::Relaxed);
+}
+
+/// The format new episode files are written in.
+pub fn new_episodes() -> Format {
+    FORMATS[NEW_EPISODES.load(Ordering::Relaxed) as usize]
+}
+
+impl Format {
+    /// The format of the file at `path`, when its extension names one.
+    pub fn detect(path: &Path) -> Option<Format> {
+        match crypto::plain_path(path).extension().and_then(OsStr::to_str) {
+            Some("yaml") => Some(Format::Yaml),
+            Some("toml") => Some(Format::Toml),
+            Some("json") => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    /// The format of the file at `path`, YAML unless its extension names
+    /// another.
+    pub fn of(path: &Path) -> Format {
+        Format::detect(path).unwrap_or(Format::Yaml)
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Yaml => "yaml",
+            Format::Toml => "toml",
+            Format::Json => "json",
+        }
+    }
+
+    /// Read `contents` as a document.
+    pub fn parse_value(self, contents: &[u8]) -> Result<Value, CliError> {
+        match self {
+            Format::Yaml => Ok(serde_yaml::from_slice(contents)?),
+            Format::Toml => Ok(from_toml(toml::from_slice(contents).map_err(toml_error)?)),
+            Format::Json => Ok(serde_json::from_slice(contents)?),
+        }
+    }
+
+    /// Read `contents` as a `T`.
+    pub fn parse<T: DeserializeOwned>(self, contents: &[u8]) -> Result<T, CliError> {
+        match self {
+            // Straight from the text, so errors say where in the file they are
+            Format::Yaml => Ok(serde_yaml::from_slice(contents)?),
+            Format::Json => Ok(serde_json::from_slice(contents)?),
+            // TOML dates and times aren't strings until they are in a document
+            Format::Toml => Ok(serde_yaml::from_value(self.parse_value(contents)?)?),
+        }
+    }
+
+    /// `value` written out in this format, without empty optional fields.
+    pub fn to_string<T: Serialize>(self, value: &T) -> Result<String, CliError> {
+        let value = crate::yaml::without_nulls(serde_yaml::to_value(value)?);
+        match self {
+            Format::Yaml => Ok(serde_yaml::to_string(&value)?),
+            Format::Toml => {
+                let value = toml::Value::try_from(&value).map_err(toml_error)?;
+                toml::to_string_pretty(&value).map_err(toml_error)
+            }
+            Format::Json => Ok(serde_json::to_string_pretty(&value)? + "\n"),
+        }
+    }
+}
+
+/// The text the file at `path` should have once it holds `value`. YAML is
+/// edited in place; other formats are written again when they change.
+pub fn updated_text<T: Serialize>(path: &Path, value: &T) -> Result<String, CliError> {
+    let format = Format::of(path);
+    if format == Format::Yaml {
+        return crate::yaml::updated_text(path, value);
+    }
+    if path.exists() {
+        let original = String::from_utf8_lossy(&crypto::read(path)?).into_owned();
+        return update(format, &original, &serde_yaml::to_value(value)?);
+    }
+    format.to_string(value)
+}
+
+/// Change `original`, in `format`, so it reads as `new`.
+pub fn update(format: Format, original: &str, new: &Value) -> Result<String, CliError> {
+    if format == Format::Yaml {
+        return crate::yaml::update(original, new);
+    }
+    let old = format.parse_value(original.as_bytes()).ok();
+    if old.map(crate::yaml::without_nulls) == Some(crate::yaml::without_nulls(new.clone())) {
+        return Ok(original.to_owned());
+    }
+    format.to_string(new)
+}
+
+/// The episode files among `paths`, the files of an episode directory.
+pub fn episode_files(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let name = |path: &Path| {
+        let plain = crypto::plain_path(path);
+        plain
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    };
+    let others: HashSet<String> = paths
+        .iter()
+        .filter(|path| matches!(Format::detect(path), Some(Format::Yaml | Format::Toml)))
+        .map(|path| name(path))
+        .collect();
+    paths
+        .into_iter()
+        .filter(|path| match Format::detect(path) {
+            Some(Format::Json) => {
+                let name = name(path);
+                !name.contains('.') && !others.contains(&name)
+            }
+            Some(_) => true,
+            None => false,
+        })
+        .collect()
+}
+
+/// Write each of `files` again in `format` next to it, named for the
+/// format, and remove the original. Returns the new files.
+pub fn convert(
+    files: &[PathBuf],
+    format: Format,
+    channel_config: &ChannelConfig,
+) -> Result<Vec<PathBuf>, CliError> {
+    let mut converted = Vec::new();
+    for path in files {
+        let from = Format::of(path);
+        if from == format {
+            converted.push(path.clone());
+            continue;
+        }
+        let mut target = crypto::plain_path(path).with_extension(format.extension());
+        if crypto::is_encrypted(path) {
+            target = crypto::encrypted_path(&target);
+        }
+        if target.exists() {
+            return Err(convert_error(
+                path,
+                &format!("{} already exists", target.display()),
+            ));
+        }
+
+        let value = from.parse_value(&crypto::read(path)?)?;
+        let text = format.to_string(&value)?;
+        // Anything the format can't hold, like a `null` in a list, would
+        // be lost
+        let reread = format.parse_value(text.as_bytes())?;
+        if crate::yaml::without_nulls(reread) != crate::yaml::without_nulls(value) {
+            let detail = format!("it doesn't read the same as {}", format.extension());
+            return Err(convert_error(path, &detail));
+        }
+        crypto::write(&target, text.as_bytes(), channel_config)?;
+        std::fs::remove_file(path)?;
+        outln!(
+            "{}",
+            t!(
+                "converted-file",
+                from = path.display().to_string(),
+                to = target.display().to_string()
+            )
+        );
+        converted.push(target);
+    }
+    Ok(converted)
+}
+
+/// `convert-config`: convert `files`, or the channel file and every
+/// episode, to `format`, saying what the channel file is now called.
+pub fn convert_show(
+    channel_file: &Path,
+    episode_dir: &Path,
+    files: Vec<PathBuf>,
+    format: Format,
+    channel_config: &ChannelConfig,
+) -> Result<(), CliError> {
+    let files = if files.is_empty() {
+        let mut files = vec![channel_file.to_path_buf()];
+        files.extend(crate::get_episode_paths(episode_dir)?);
+        files
+    } else {
+        files
+    };
+    let converted = convert(&files, format, channel_config)?;
+    let channel = files.iter().zip(&converted).find(|(file, _)| *file == channel_file);
+    if let Some((_, converted)) = channel.filter(|(file, converted)| file != converted) {
+        let path = converted.display().to_string();
+        outln!("{}", t!("convert-channel-file", path = path));
+    }
+    Ok(())
+}
+
+/// A document from TOML, with its dates and times as strings.
+fn from_toml(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(text) => Value::String(text),
+        toml::Value::Integer(number) => Value::from(number),
+        toml::Value::Float(number) => Value::from(number),
+        toml::Value::Boolean(value) => Value::Bool(value),
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(items) => Value::Sequence(items.into_iter().map(from_toml).collect()),
+        toml::Value::Table(table) => Value::Mapping(
+            table
+                .into_iter()
+                .map(|(key, value)| (Value::String(key), from_toml(value)))
+                .collect::<Mapping>(),
+        ),
+    }
+}
+
+fn toml_error<E: std::fmt::Display>(e: E) -> CliError {
+    CliError::TomlError(e.to_string())
+}
+
+fn convert_error(path: &Path, detail: &str) -> CliError {
+    CliError::ConvertConfig(format!("{}: {}", path.display(), detail))
+}
@@ -0,0 +1,138 @@
+//! Episode `guid`s. Apps tell episodes apart by them, so an episode whose
+//! GUID changes shows up again as a new episode. By default it is the
+//! episode's `id`, the UUID it was created with; `publishing.guidStrategy`
+//! can make it the media URL, or a template:
+//!
+//! ```yaml
+//! publishing:
+//!   guidStrategy: template
+//!   guidTemplate: "https://example.com/episodes/{id}"
+//! ```
+//!
+//! An episode file's own `guid` wins over the strategy, so episodes
+//! imported from another host keep the GUIDs apps know them by. GUIDs that
+//! are URLs are published with `isPermaLink="true"`, and the rest with
+//! `isPermaLink="false"`.
+//!
+//! Every render compares the GUIDs with the feed last published from this
+//! workspace, and warns about each episode whose GUID would change.
+
+use crate::config::{Episode, GuidStrategy, PublishingConfig};
+use crate::events;
+use crate::feed::{parse_feed, ParsedItem};
+use crate::i18n::t;
+use crate::template;
+use crate::CliError;
+use chrono_tz::Tz;
+
+/// An episode whose GUID changed since the feed was last published.
+pub struct ChangedGuid {
+    pub title: String,
+    pub published: String,
+    pub rendered: String,
+}
+
+/// Set the `guid` of `episode`, unless its file does, as `publishing`
+/// says, with `{date}` in `timezone`.
+pub fn fill(
+    publishing: &PublishingConfig,
+    timezone: Tz,
+    episode: &mut Episode,
+) -> Result<(), CliError> {
+    if episode.guid.is_some() {
+        return Ok(());
+    }
+    let guid = match publishing.guid_strategy {
+        GuidStrategy::Uuid => episode.id.clone(),
+        GuidStrategy::EnclosureUrl => episode.media.url.clone(),
+        GuidStrategy::Template => {
+            let guid_template = publishing.guid_template.as_ref().ok_or_else(|| {
+                CliError::GuidStrategy(
+                    "`guidStrategy` is `template`, but there is no `guidTemplate`".to_owned(),
+                )
+            })?;
+            let date = episode.released_at.with_timezone(&timezone);
+            let variables = [
+                ("id", episode.id.clone()),
+                ("date", date.format("%Y-%m-%d").to_string()),
+                ("season", episode.season.to_string()),
+                ("episode", episode.episode_number.to_string()),
+            ];
+            template::expand(guid_template, &variables)
+        }
+    };
+    if guid.trim().is_empty() {
+        return Err(CliError::GuidStrategy(format!(
+            "the GUID of {} is empty",
+            episode.id
+        )));
+    }
+    episode.guid = Some(guid);
+    Ok(())
+}
+
+/// The GUID `episode` is published with.
+pub fn guid(episode: &Episode) -> &str {
+    episode.guid.as_deref().unwrap_or(&episode.id)
+}
+
+/// Whether `guid` is published as a permalink: a URL.
+pub fn is_permalink(guid: &str) -> bool {
+    guid.starts_with("http://") || guid.starts_with("https://")
+}
+
+/// Episodes of `published` that are in `rendered` with another GUID,
+/// matched by their media URL, or else their title.
+pub fn changed(published: &str, rendered: &str) -> Result<Vec<ChangedGuid>, CliError> {
+    let published = parse_feed(published)?;
+    let rendered = parse_feed(rendered)?;
+    let media = |item: &ParsedItem| item.fields.get("enclosure@url").cloned();
+
+    let mut changed = Vec::new();
+    for item in &published.items {
+        let guid = item.guid().unwrap_or_default();
+        if rendered
+            .items
+            .iter()
+            .any(|other| other.guid() == Some(guid))
+        {
+            continue;
+        }
+        let same = rendered
+            .items
+            .iter()
+            .find(|other| media(other).is_some() && media(other) == media(item))
+            .or_else(|| {
+                rendered
+                    .items
+                    .iter()
+                    .find(|other| other.title() == item.title())
+            });
+        if let Some(other) = same {
+            changed.push(ChangedGuid {
+                title: other.title().to_owned(),
+                published: guid.to_owned(),
+                rendered: other.guid().unwrap_or_default().to_owned(),
+            });
+        }
+    }
+    Ok(changed)
+}
+
+/// Warn about each episode whose GUID changed since `published`, the feed
+/// last published from here, so apps would list it again.
+pub fn warn_changed(published: &str, rendered: &str) -> Result<(), CliError> {
+    let changed = changed(published, rendered)?;
+    for change in &changed {
+        events::warn(t!(
+            "guid-changed",
+            title = change.title.as_str(),
+            published = change.published.as_str(),
+            rendered = change.rendered.as_str()
+        ));
+    }
+    if !changed.is_empty() {
+        events::warn(t!("guid-changed-help", count = changed.len()));
+    }
+    Ok(())
+}
@@ -0,0 +1,91 @@
+//! Read the channel's files as they were at a past date, from the git
+//! repository they are kept in.
+
+use crate::crypto;
+use crate::CliError;
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The file at `path` as of the last commit before `as_of`, or `None` when
+/// it isn't in a git repository or wasn't committed by then.
+pub fn file_as_of(path: &Path, as_of: DateTime<Utc>) -> Option<Vec<u8>> {
+    let dir = parent_dir(path);
+    let name = path.file_name()?.to_string_lossy().into_owned();
+    let commit = commit_before(dir, &name, as_of)?;
+    git(dir, &["show", &format!("{}:./{}", commit, name)])
+}
+
+/// Episode files and their decrypted contents.
+pub type EpisodeFiles = Vec<(PathBuf, Vec<u8>)>;
+
+/// The episode files, decrypted, as they were in the last commit before
+/// `as_of`. `None` when the directory has no history from back then.
+pub fn episode_files_as_of(
+    episode_dir: &Path,
+    as_of: DateTime<Utc>,
+) -> Result<Option<EpisodeFiles>, CliError> {
+    let commit = match commit_before(episode_dir, ".", as_of) {
+        Some(commit) => commit,
+        None => return Ok(None),
+    };
+    let listing = match git(episode_dir, &["ls-tree", "--name-only", &commit, "--", "."]) {
+        Some(listing) => String::from_utf8_lossy(&listing).into_owned(),
+        None => return Ok(None),
+    };
+
+    let mut files = Vec::new();
+    let paths = listing.lines().map(|name| episode_dir.join(name)).collect();
+    for path in crate::formats::episode_files(paths) {
+        if !crypto::readable(&path) {
+            continue;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let contents = match git(episode_dir, &["show", &format!("{}:./{}", commit, name)]) {
+            Some(contents) => contents,
+            None => continue,
+        };
+        let contents = if crypto::is_encrypted(&path) {
+            crypto::decrypt(&contents)?
+        } else {
+            contents
+        };
+        files.push((path, contents));
+    }
+
+    Ok(Some(files))
+}
+
+fn commit_before(dir: &Path, path: &str, as_of: DateTime<Utc>) -> Option<String> {
+    let before = format!("--before={}", as_of.to_rfc3339());
+    let output = git(dir, &["log", "-1", "--format=%H", &before, "--", path])?;
+    let commit = String::from_utf8_lossy(&output).trim().to_owned();
+    if commit.is_empty() {
+        None
+    } else {
+        Some(commit)
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> Option<Vec<u8>> {
+    debug!("Running git {:?} in {}", args, dir.display());
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        debug!("git failed: {}", String::from_utf8_lossy(&output.stderr));
+        return None;
+    }
+    Some(output.stdout)
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
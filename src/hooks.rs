@@ -0,0 +1,181 @@
+//! Hooks: shell commands and webhooks run once an episode's audio is
+//! uploaded and once the feed is published, to announce episodes or
+//! rebuild a site.
+//!
+//! ```yaml
+//! hooks:
+//!   onEpisodeUploaded:
+//!     - command: ./scripts/announce.sh
+//!   onFeedPublished:
+//!     - webhook: https://api.netlify.com/build_hooks/abc123
+//!       timeoutSeconds: 10
+//!       onFailure: fail
+//! ```
+//!
+//! Commands run with `sh -c`, get the payload as JSON on stdin, and see
+//! `PODCAST_CTL_EVENT`; webhooks are POSTed the same JSON:
+//!
+//! ```json
+//! {"event":"feedPublished","channel":"My Show","feedUrl":"https://…/podcast.xml","changes":["Added S1E4 \"Title\""],"episodes":[{"id":"…","title":"Title","season":1,"episodeNumber":4,"url":"https://…/2022-09-08.mp3"}]}
+//! ```
+//!
+//! A hook that fails or runs out of time only warns, unless its
+//! `onFailure` is `fail`, which fails the command after the upload.
+
+use crate::config::{Episode, HookConfig, HookFailure};
+use crate::events;
+use crate::http::HttpClient;
+use crate::i18n::t;
+use crate::CliError;
+use chrono::{DateTime, Utc};
+use log::info;
+use reqwest::Method;
+use serde::Serialize;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HookEvent {
+    EpisodeUploaded,
+    FeedPublished,
+}
+
+impl HookEvent {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::EpisodeUploaded => "episodeUploaded",
+            HookEvent::FeedPublished => "feedPublished",
+        }
+    }
+}
+
+/// What hooks are told.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookPayload {
+    pub event: HookEvent,
+    /// The channel's title
+    pub channel: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed_url: Option<String>,
+    /// What publishing changed in the feed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<String>,
+    /// The uploaded episode, or the episodes new to the feed
+    pub episodes: Vec<HookEpisode>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookEpisode {
+    pub id: String,
+    pub title: String,
+    pub season: u64,
+    pub episode_number: u64,
+    pub released_at: DateTime<Utc>,
+    /// The episode's audio
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    pub draft: bool,
+}
+
+impl From<&Episode> for HookEpisode {
+    fn from(episode: &Episode) -> Self {
+        HookEpisode {
+            id: episode.id.clone(),
+            title: episode.title.clone(),
+            season: episode.season,
+            episode_number: episode.episode_number,
+            released_at: episode.released_at,
+            url: episode.media.url.clone(),
+            link: episode.link.clone(),
+            draft: episode.draft,
+        }
+    }
+}
+
+/// Run `hooks` in order, warning about those that fail, or failing with
+/// the first that fails with `onFailure: fail`.
+pub async fn run(
+    hooks: &[HookConfig],
+    client: &HttpClient,
+    payload: &HookPayload,
+) -> Result<(), CliError> {
+    let body = serde_json::to_vec(payload)?;
+    for hook in hooks {
+        let name = hook.name();
+        info!("Running {} hook {}", payload.event.as_str(), name);
+        let timeout = Duration::from_secs(hook.timeout_seconds);
+        let ran = match (&hook.command, &hook.webhook) {
+            (Some(command), None) => run_command(command, payload.event, &body, timeout).await,
+            (None, Some(webhook)) => post(client, webhook, &body, timeout).await,
+            _ => Err(t!("hook-not-set")),
+        };
+        match (ran, hook.on_failure) {
+            (Ok(()), _) => {}
+            (Err(detail), HookFailure::Fail) => return Err(CliError::HookFailed(name, detail)),
+            (Err(detail), HookFailure::Warn) => {
+                events::warn(t!("hook-failed", hook = name.as_str(), detail = detail))
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run_command(
+    command: &str,
+    event: HookEvent,
+    body: &[u8],
+    timeout: Duration,
+) -> Result<(), String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("PODCAST_CTL_EVENT", event.as_str())
+        .stdin(Stdio::piped())
+        .stdout(crate::output::child_stdout())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| t!("hook-not-started", detail = e.to_string()))?;
+    let finished = async {
+        if let Some(mut stdin) = child.stdin.take() {
+            // A command that doesn't read its stdin is fine
+            let _ = stdin.write_all(body).await;
+        }
+        child.wait().await
+    };
+    match tokio::time::timeout(timeout, finished).await {
+        Ok(Ok(status)) if status.success() => Ok(()),
+        Ok(Ok(status)) => Err(t!("hook-exited", status = status.to_string())),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(t!("hook-timed-out", seconds = timeout.as_secs())),
+    }
+}
+
+/// POST `body` to `webhook`, giving up once `timeout` has passed, however
+/// many attempts the client has made by then.
+async fn post(
+    client: &HttpClient,
+    webhook: &str,
+    body: &[u8],
+    timeout: Duration,
+) -> Result<(), String> {
+    let sent = client.send(webhook, || {
+        client
+            .request(Method::POST, webhook)
+            .header("Content-Type", "application/json")
+            .body(body.to_vec())
+    });
+    let response = tokio::time::timeout(timeout, sent)
+        .await
+        .map_err(|_| t!("hook-timed-out", seconds = timeout.as_secs()))?
+        .map_err(|e| e.localized())?;
+    if !response.status.is_success() {
+        return Err(t!("hook-http-status", status = response.status.as_u16()));
+    }
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use crate::config::*;
+use crate::preview::format_duration;
+use reqwest::Url;
+
+const FEED_TEMPLATE: &str = include_str!("../templates/feed.html");
+const ITEM_TEMPLATE: &str = include_str!("../templates/feed-item.html");
+
+/// Browsable HTML view of the feed, built from the bundled templates.
+pub fn render_html(channel: &ChannelDetails, episodes: &[Episode]) -> String {
+    let items: Vec<String> = episodes
+        .iter()
+        .map(|episode| {
+            fill(
+                ITEM_TEMPLATE,
+                &[
+                    ("title", escape(&episode.title)),
+                    ("season", episode.season.to_string()),
+                    ("episode", episode.episode_number.to_string()),
+                    (
+                        "released",
+                        episode.released_at.format("%B %-d, %Y").to_string(),
+                    ),
+                    ("duration", format_duration(episode.media.duration.0)),
+                    ("url", href(&episode.media.url).unwrap_or_default()),
+                    (
+                        "description",
+                        comrak::markdown_to_html(&episode.description, &Default::default()),
+                    ),
+                ],
+            )
+        })
+        .collect();
+
+    let link = match channel.link.as_deref().and_then(href) {
+        Some(link) => format!(r#"<p><a href="{0}">{0}</a></p>"#, link),
+        None => String::new(),
+    };
+
+    fill(
+        FEED_TEMPLATE,
+        &[
+            ("language", escape(&channel.language)),
+            ("title", escape(&channel.title)),
+            ("subtitle", escape(&channel.subtitle)),
+            ("image", href(&channel.image).unwrap_or_default()),
+            ("owner", escape(&channel.owner.name)),
+            ("episode_count", episodes.len().to_string()),
+            ("link", link),
+            (
+                "summary",
+                comrak::markdown_to_html(&channel.summary, &Default::default()),
+            ),
+            ("items", items.join("")),
+        ],
+    )
+}
+
+/// `template` with each `{{key}}` in it replaced by its value, in one
+/// pass, so a value that has `{{key}}` in it is left as it is.
+pub fn fill(template: &str, values: &[(&str, String)]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        filled.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let value = after.find("}}").and_then(|end| {
+            let key = &after[..end];
+            let (_, value) = values.iter().find(|(name, _)| *name == key)?;
+            Some((value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                filled.push_str(value);
+                rest = &after[end + 2..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// `text` for HTML, in elements and quoted attributes.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// `url` escaped for an `href` or `src`, when it is an http or https URL.
+/// Anything else, like a `javascript:` link in channel.yaml, is left out.
+pub fn href(url: &str) -> Option<String> {
+    let parsed = Url::parse(url).ok()?;
+    matches!(parsed.scheme(), "http" | "https").then(|| escape(url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> Vec<(&'static str, String)> {
+        vec![
+            ("title", "{{items}} & more".to_owned()),
+            ("items", "<li>one</li>".to_owned()),
+        ]
+    }
+
+    #[test]
+    fn fills_placeholders_in_one_pass() {
+        assert_eq!(
+            fill("<h1>{{title}}</h1><ul>{{items}}</ul>", &values()),
+            "<h1>{{items}} & more</h1><ul><li>one</li></ul>"
+        );
+        assert_eq!(fill("{{unknown}} {{title", &values()), "{{unknown}} {{title");
+        assert_eq!(fill("{{{title}}}", &values()), "{{{items}} & more}");
+    }
+
+    #[test]
+    fn escapes_both_quotes() {
+        assert_eq!(
+            escape(r#"<a href="x" title='y'>&</a>"#),
+            "&lt;a href=&quot;x&quot; title=&#39;y&#39;&gt;&amp;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn only_links_web_urls() {
+        assert_eq!(
+            href("https://example.com/?a=1&b=2").as_deref(),
+            Some("https://example.com/?a=1&amp;b=2")
+        );
+        assert!(href("HTTP://example.com/").is_some());
+        assert_eq!(href("javascript:alert(1)"), None);
+        assert_eq!(href(" JavaScript:alert(1)"), None);
+        assert_eq!(href("data:text/html,<script>"), None);
+        assert_eq!(href("episode.mp3"), None);
+    }
+
+    #[test]
+    fn leaves_out_links_that_are_not_web_urls() {
+        let mut channel = ChannelDetails::make_test();
+        channel.link = Some("javascript:alert(document.cookie)".to_owned());
+        channel.title = "Tom's {{items}}".to_owned();
+        let mut episode = Episode::make_test("first");
+        episode.media.url = "javascript:alert(1)".to_owned();
+        let html = render_html(&channel, &[episode]);
+        assert!(!html.contains("javascript:"));
+        assert!(html.contains("Tom&#39;s {{items}}"));
+    }
+}
@@ -0,0 +1,295 @@
+use crate::config::HttpConfig;
+use crate::CliError;
+use flate2::read::MultiGzDecoder;
+use log::{debug, warn};
+use reqwest::header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, RANGE};
+use reqwest::{Method, RequestBuilder, StatusCode};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, Semaphore};
+
+/// Shared client for everything that talks to the network outside of the
+/// storage provider. Requests are retried with exponential backoff, spaced
+/// out to `requestsPerSecond`, limited to `concurrency` in flight, and
+/// successful GET/HEAD responses are cached for the life of the process.
+pub struct HttpClient {
+    client: reqwest::Client,
+    /// For `download`: only connecting is limited to `timeoutSeconds`,
+    /// since large media can take longer than that to transfer
+    streaming: reqwest::Client,
+    max_attempts: u32,
+    min_interval: Duration,
+    last_request: Mutex<Option<Instant>>,
+    concurrency: usize,
+    permits: Arc<Semaphore>,
+    cache: Mutex<HashMap<(Method, String), HttpResponse>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub content_length: Option<u64>,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    pub content_encoding: Option<String>,
+    /// As sent, still compressed when `content_encoding` says so
+    pub body: Vec<u8>,
+}
+
+/// A response whose body was written to a file instead of kept in memory.
+#[derive(Debug)]
+pub struct Download {
+    pub status: StatusCode,
+    pub etag: Option<String>,
+    /// How much of the body was written; nothing is written unless the
+    /// request succeeded
+    pub bytes: u64,
+    pub md5: String,
+}
+
+impl HttpResponse {
+    /// The body, decompressed when it was sent with `Content-Encoding:
+    /// gzip`.
+    pub fn decoded_body(&self) -> Vec<u8> {
+        let gzipped = matches!(&self.content_encoding, Some(encoding) if encoding.eq_ignore_ascii_case("gzip"));
+        if !gzipped {
+            return self.body.clone();
+        }
+        let mut decoded = Vec::new();
+        match MultiGzDecoder::new(self.body.as_slice()).read_to_end(&mut decoded) {
+            Ok(_) => decoded,
+            Err(e) => {
+                warn!("Unable to decompress a gzipped response: {}", e);
+                self.body.clone()
+            }
+        }
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.decoded_body()).into_owned()
+    }
+}
+
+impl HttpClient {
+    pub fn new(config: &HttpConfig) -> Result<Self, CliError> {
+        let client = reqwest::Client::builder()
+            .user_agent(config.user_agent.clone())
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()?;
+        let streaming = reqwest::Client::builder()
+            .user_agent(config.user_agent.clone())
+            .connect_timeout(Duration::from_secs(config.timeout_seconds))
+            .build()?;
+
+        let min_interval = if config.requests_per_second > 0.0 {
+            Duration::from_secs_f64(1.0 / config.requests_per_second)
+        } else {
+            Duration::ZERO
+        };
+
+        let concurrency = config.concurrency.max(1);
+
+        Ok(Self {
+            client,
+            streaming,
+            max_attempts: config.max_attempts.max(1),
+            min_interval,
+            last_request: Mutex::new(None),
+            concurrency,
+            permits: Arc::new(Semaphore::new(concurrency)),
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub async fn get(&self, url: &str) -> Result<HttpResponse, CliError> {
+        self.cached(Method::GET, url, |client| client.get(url))
+            .await
+    }
+
+    pub async fn head(&self, url: &str) -> Result<HttpResponse, CliError> {
+        self.cached(Method::HEAD, url, |client| client.head(url))
+            .await
+    }
+
+    /// Fetch only the first byte of `url`, for servers that refuse HEAD.
+    pub async fn probe(&self, url: &str) -> Result<HttpResponse, CliError> {
+        self.send(url, || self.client.get(url).header(RANGE, "bytes=0-0"))
+            .await
+    }
+
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        self.client.request(method, url)
+    }
+
+    /// Send a request built by `build` with the client's retry and rate
+    /// limiting policy.
+    pub async fn send<F>(&self, url: &str, build: F) -> Result<HttpResponse, CliError>
+    where
+        F: Fn() -> RequestBuilder,
+    {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|_| CliError::Unknown)?;
+
+        let mut attempt = 1;
+        loop {
+            self.throttle().await;
+            debug!("HTTP request to {} (attempt {})", url, attempt);
+
+            let result = build().send().await;
+            let retryable = match &result {
+                Ok(response) => is_retryable_status(response.status()),
+                Err(e) => e.is_timeout() || e.is_connect(),
+            };
+
+            if !retryable || attempt >= self.max_attempts {
+                return read_response(result?).await;
+            }
+
+            let backoff = Duration::from_millis(250 * 2u64.pow(attempt - 1));
+            warn!(
+                "Request to {} failed, retrying in {:?} ({}/{})",
+                url, backoff, attempt, self.max_attempts
+            );
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+
+    /// GET `url` into the file at `path`, hashing it as it is written, with
+    /// the same retry and rate limiting policy as `send`.
+    pub async fn download(&self, url: &str, path: &Path) -> Result<Download, CliError> {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|_| CliError::Unknown)?;
+
+        let mut attempt = 1;
+        let response = loop {
+            self.throttle().await;
+            debug!("HTTP download of {} (attempt {})", url, attempt);
+
+            let result = self.streaming.get(url).send().await;
+            let retryable = match &result {
+                Ok(response) => is_retryable_status(response.status()),
+                Err(e) => e.is_timeout() || e.is_connect(),
+            };
+
+            if !retryable || attempt >= self.max_attempts {
+                break result?;
+            }
+
+            let backoff = Duration::from_millis(250 * 2u64.pow(attempt - 1));
+            warn!(
+                "Download of {} failed, retrying in {:?} ({}/{})",
+                url, backoff, attempt, self.max_attempts
+            );
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        };
+
+        let status = response.status();
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_owned());
+        let mut download = Download {
+            status,
+            etag,
+            bytes: 0,
+            md5: String::new(),
+        };
+        if !status.is_success() {
+            return Ok(download);
+        }
+
+        let mut response = response;
+        let mut file = tokio::fs::File::create(path).await?;
+        let mut md5 = md5::Context::new();
+        while let Some(chunk) = response.chunk().await? {
+            md5.consume(&chunk);
+            file.write_all(&chunk).await?;
+            download.bytes += chunk.len() as u64;
+        }
+        file.flush().await?;
+        download.md5 = format!("{:x}", md5.compute());
+        Ok(download)
+    }
+
+    async fn cached<F>(&self, method: Method, url: &str, build: F) -> Result<HttpResponse, CliError>
+    where
+        F: Fn(&reqwest::Client) -> RequestBuilder,
+    {
+        let key = (method, url.to_owned());
+        if let Some(response) = self.cache.lock().await.get(&key) {
+            debug!("Using cached response for {}", url);
+            return Ok(response.clone());
+        }
+
+        let response = self.send(url, || build(&self.client)).await?;
+        if response.status.is_success() {
+            self.cache.lock().await.insert(key, response.clone());
+        }
+
+        Ok(response)
+    }
+
+    async fn throttle(&self) {
+        if self.min_interval.is_zero() {
+            return;
+        }
+
+        let mut last = self.last_request.lock().await;
+        if let Some(previous) = *last {
+            let elapsed = previous.elapsed();
+            if elapsed < self.min_interval {
+                tokio::time::sleep(self.min_interval - elapsed).await;
+            }
+        }
+        *last = Some(Instant::now());
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+async fn read_response(response: reqwest::Response) -> Result<HttpResponse, CliError> {
+    let status = response.status();
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+    // A ranged probe reports the full size in `Content-Range: bytes 0-0/1234`
+    let content_length = header(CONTENT_RANGE)
+        .and_then(|value| value.rsplit('/').next())
+        .or_else(|| header(CONTENT_LENGTH))
+        .and_then(|value| value.parse().ok());
+    let content_type = header(CONTENT_TYPE).map(|value| value.to_owned());
+    let etag = header(ETAG).map(|value| value.to_owned());
+    let content_encoding = header(CONTENT_ENCODING).map(|value| value.to_owned());
+    let body = response.bytes().await?.to_vec();
+
+    Ok(HttpResponse {
+        status,
+        content_length,
+        content_type,
+        etag,
+        content_encoding,
+        body,
+    })
+}
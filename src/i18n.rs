@@ -0,0 +1,121 @@
+//! Translated messages, for hosts who would rather not use the CLI in
+//! English.
+//!
+//! Messages are Fluent files in `locales/<language>/podcastctl.ftl`,
+//! compiled into the binary. The language comes from `--lang`, then
+//! `PODCAST_CTL_LANG`, then the usual `LC_ALL`/`LC_MESSAGES`/`LANG`, and is
+//! English otherwise. A message missing from a translation is shown in
+//! English.
+//!
+//! ```ignore
+//! outln!("{}", t!("wrote-file", path = path.display().to_string()));
+//! ```
+
+use fluent_bundle::{FluentArgs, FluentBundle, FluentResource, FluentValue};
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use unic_langid::LanguageIdentifier;
+
+pub const LANG_ENV: &str = "PODCAST_CTL_LANG";
+
+/// The first locale is the fallback for every other one.
+const LOCALES: [(&str, &str); 2] = [
+    ("en-US", include_str!("../locales/en-US/podcastctl.ftl")),
+    ("es", include_str!("../locales/es/podcastctl.ftl")),
+];
+
+static SELECTED: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static BUNDLES: Vec<FluentBundle<FluentResource>> = LOCALES
+        .iter()
+        .map(|(id, source)| bundle(id, source))
+        .collect();
+}
+
+/// `t!("message-id")` or `t!("message-id", name = value, ...)`, in the
+/// selected language.
+macro_rules! t {
+    ($id:expr) => {
+        $crate::i18n::message($id, &[])
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::message($id, &[$((stringify!($name), $value.into())),+])
+    };
+}
+pub(crate) use t;
+
+/// Pick the language for the rest of the run. Unknown languages leave it
+/// in English.
+pub fn select(requested: Option<&str>) {
+    let requested = requested.map(|requested| requested.to_owned()).or_else(|| {
+        [LANG_ENV, "LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+    });
+
+    if let Some(index) = requested.as_deref().and_then(locale_index) {
+        SELECTED.store(index, Ordering::Relaxed);
+    }
+}
+
+/// The selected locale, like `en-US`.
+pub fn selected() -> &'static str {
+    LOCALES[SELECTED.load(Ordering::Relaxed)].0
+}
+
+/// The locale matching a language tag like `es`, `es-MX` or `es_MX.UTF-8`.
+fn locale_index(requested: &str) -> Option<usize> {
+    let tag = requested
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-");
+    let requested: LanguageIdentifier = tag.parse().ok()?;
+    LOCALES.iter().position(|(id, _)| {
+        id.parse::<LanguageIdentifier>()
+            .map(|id| id.language == requested.language)
+            .unwrap_or(false)
+    })
+}
+
+/// The message `id` with `args` filled in. Prefer `t!`.
+pub fn message(id: &str, args: &[(&str, FluentValue)]) -> String {
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, value.clone());
+    }
+
+    BUNDLES.with(|bundles| {
+        let selected = SELECTED.load(Ordering::Relaxed);
+        [selected, 0]
+            .iter()
+            .filter_map(|index| {
+                let bundle = &bundles[*index];
+                let pattern = bundle.get_message(id)?.value()?;
+                let mut errors = Vec::new();
+                Some(
+                    bundle
+                        .format_pattern(pattern, Some(&fluent_args), &mut errors)
+                        .into_owned(),
+                )
+            })
+            .next()
+            .unwrap_or_else(|| id.to_owned())
+    })
+}
+
+fn bundle(id: &str, source: &str) -> FluentBundle<FluentResource> {
+    let language: LanguageIdentifier = id.parse().expect("locale ids are valid");
+    let resource =
+        FluentResource::try_new(source.to_owned()).unwrap_or_else(|(resource, _)| resource);
+    let mut bundle = FluentBundle::new(vec![language]);
+    // The Unicode isolation marks around arguments show up as garbage in
+    // most terminals
+    bundle.set_use_isolating(false);
+    bundle
+        .add_resource(resource)
+        .expect("message ids are unique");
+    bundle
+}
@@ -0,0 +1,238 @@
+//! ID3 tags for episode MP3s, written from channel.yaml and the episode
+//! file so players that show tags, rather than the feed, show the episode:
+//! the title, the owner as artist, the show as album, the episode number as
+//! track, the release year, the genre `Podcast`, and the artwork as cover.
+//! Chapters are written as CHAP frames, listed in order by a CTOC frame,
+//! for apps that read chapters from the audio rather than the JSON
+//! chapters in the feed.
+//!
+//! Frames the episode doesn't set, like comments, or chapters when it has
+//! none, are kept.
+
+use crate::audio::AudioFormat;
+use crate::chapters;
+use crate::config::{ChannelConfig, ChannelDetails, Chapter, Episode};
+use crate::http::HttpClient;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::CliError;
+use chrono::{DateTime, Datelike, Utc};
+use id3::frame::{self, ExtendedLink, Picture, PictureType, TableOfContents};
+use id3::{Tag, TagLike, Version};
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+/// What an episode's tag is written from.
+pub struct EpisodeTags {
+    pub title: String,
+    pub episode_number: u64,
+    pub released_at: DateTime<Utc>,
+    /// Cover art, as PNG or JPEG
+    pub artwork: Option<Vec<u8>>,
+    pub chapters: Vec<Chapter>,
+    /// Milliseconds, where the last chapter ends
+    pub duration: u64,
+}
+
+impl EpisodeTags {
+    pub fn new(episode: &Episode, artwork: Option<Vec<u8>>, chapters: Vec<Chapter>) -> Self {
+        Self {
+            title: episode.title.clone(),
+            episode_number: episode.episode_number,
+            released_at: episode.released_at,
+            artwork,
+            chapters,
+            duration: episode.media.duration.0 * 1000,
+        }
+    }
+}
+
+/// Only MP3s have ID3 tags.
+pub fn check_format(path: &Path, format: AudioFormat) -> Result<(), CliError> {
+    if format != AudioFormat::Mp3 {
+        return Err(CliError::TagError(format!(
+            "{} is {}, only MP3s have ID3 tags",
+            path.display(),
+            format.name()
+        )));
+    }
+    Ok(())
+}
+
+/// Write the episode's tags into the MP3 at `path`.
+pub fn write_file(
+    path: &Path,
+    channel: &ChannelDetails,
+    tags: &EpisodeTags,
+) -> Result<(), CliError> {
+    let existing = match Tag::read_from_path(path) {
+        Ok(tag) => Some(tag),
+        Err(id3::Error {
+            kind: id3::ErrorKind::NoTag,
+            ..
+        }) => None,
+        Err(e) => return Err(tag_error(path, e)),
+    };
+    episode_tag(existing, channel, tags)?
+        .write_to_path(path, Version::Id3v23)
+        .map_err(|e| tag_error(path, e))
+}
+
+/// The MP3 in `audio` with the episode's tags written into it, for audio
+/// that is only ever decrypted in memory.
+pub fn write_bytes(
+    audio: Vec<u8>,
+    channel: &ChannelDetails,
+    tags: &EpisodeTags,
+) -> Result<Vec<u8>, CliError> {
+    let existing = Tag::read_from2(Cursor::new(&audio)).ok();
+    let mut file = Cursor::new(audio);
+    episode_tag(existing, channel, tags)?
+        .write_to_file(&mut file, Version::Id3v23)
+        .map_err(|e| CliError::TagError(e.to_string()))?;
+    Ok(file.into_inner())
+}
+
+/// Artwork from `source`, a URL or a file relative to `channel_dir`.
+pub async fn artwork(
+    source: &str,
+    channel_dir: &Path,
+    client: &HttpClient,
+) -> Result<Vec<u8>, CliError> {
+    if crate::artwork::is_local(source) {
+        return Ok(std::fs::read(channel_dir.join(source))?);
+    }
+    let response = client.get(source).await?;
+    if !response.status.is_success() {
+        return Err(CliError::HttpStatus(
+            source.to_owned(),
+            response.status.as_u16(),
+        ));
+    }
+    Ok(response.body)
+}
+
+/// `tag`: write the tags of the episode `selector` picks into `file`, with
+/// `artwork`, or the episode's image.
+pub async fn tag_episode(
+    episode_dir: &Path,
+    channel_config: &ChannelConfig,
+    selector: &str,
+    file: &Path,
+    artwork: Option<&Path>,
+) -> Result<(), CliError> {
+    let (episode_path, episode) =
+        crate::find_episode(episode_dir, selector, channel_config.channel.tz()?)?;
+    if crate::crypto::is_encrypted(file) {
+        return Err(CliError::TagError(format!(
+            "{} is encrypted, decrypt it first",
+            file.display()
+        )));
+    }
+    let mut head = Vec::new();
+    std::fs::File::open(file)?.take(64).read_to_end(&mut head)?;
+    let format = AudioFormat::detect(file, &head)
+        .ok_or_else(|| CliError::AudioError("episode", crate::audio::not_audio(file, &head)))?;
+    check_format(file, format)?;
+
+    let artwork = match artwork {
+        Some(artwork) => std::fs::read(artwork)?,
+        None => {
+            let client = HttpClient::new(&channel_config.http)?;
+            let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+            self::artwork(&episode.image, channel_dir, &client).await?
+        }
+    };
+    let path = file.display().to_string();
+    if crate::upload::dry_run() {
+        outln!("{}", t!("dry-run-episode", path = path));
+        return Ok(());
+    }
+    let chapters = chapters::load(&episode_path, &episode)?;
+    let tags = EpisodeTags::new(&episode, Some(artwork), chapters);
+    write_file(file, &channel_config.channel, &tags)?;
+    outln!("{}", t!("tagged-file", path = path));
+    Ok(())
+}
+
+fn episode_tag(
+    existing: Option<Tag>,
+    channel: &ChannelDetails,
+    tags: &EpisodeTags,
+) -> Result<Tag, CliError> {
+    let mut tag = existing.unwrap_or_default();
+    tag.set_title(tags.title.as_str());
+    tag.set_artist(channel.owner.name.as_str());
+    tag.set_album(channel.title.as_str());
+    tag.set_track(tags.episode_number as u32);
+    tag.set_year(tags.released_at.year());
+    tag.set_genre("Podcast");
+    if let Some(artwork) = &tags.artwork {
+        let mime_type = if artwork.starts_with(b"\x89PNG") {
+            "image/png"
+        } else if artwork.starts_with(&[0xFF, 0xD8]) {
+            "image/jpeg"
+        } else {
+            return Err(CliError::TagError(
+                "the artwork is not a PNG or JPEG image".to_owned(),
+            ));
+        };
+        tag.remove_picture_by_type(PictureType::CoverFront);
+        tag.add_frame(Picture {
+            mime_type: mime_type.to_owned(),
+            picture_type: PictureType::CoverFront,
+            description: String::new(),
+            data: artwork.clone(),
+        });
+    }
+    if !tags.chapters.is_empty() {
+        add_chapters(&mut tag, tags)?;
+    }
+    Ok(tag)
+}
+
+/// Replace the tag's chapters with the episode's, each ending where the
+/// next starts and the last at the end of the episode.
+fn add_chapters(tag: &mut Tag, tags: &EpisodeTags) -> Result<(), CliError> {
+    let starts = chapters::starts(&tags.title, &tags.chapters)?;
+    let ends = starts.iter().skip(1).copied().chain([tags.duration]);
+    let mut elements = Vec::new();
+    tag.remove_all_chapters();
+    tag.remove_all_tables_of_contents();
+    for (index, ((chapter, start), end)) in tags.chapters.iter().zip(&starts).zip(ends).enumerate()
+    {
+        let element_id = format!("chp{}", index);
+        let mut frames = vec![frame::Frame::text("TIT2", chapter.title.as_str())];
+        if let Some(url) = &chapter.url {
+            frames.push(
+                ExtendedLink {
+                    description: String::new(),
+                    link: url.clone(),
+                }
+                .into(),
+            );
+        }
+        tag.add_frame(frame::Chapter {
+            element_id: element_id.clone(),
+            start_time: *start as u32,
+            end_time: end.max(*start) as u32,
+            // Unused, chapters are placed by time
+            start_offset: u32::MAX,
+            end_offset: u32::MAX,
+            frames,
+        });
+        elements.push(element_id);
+    }
+    tag.add_frame(TableOfContents {
+        element_id: "toc".to_owned(),
+        top_level: true,
+        ordered: true,
+        elements,
+        frames: Vec::new(),
+    });
+    Ok(())
+}
+
+fn tag_error(path: &Path, error: id3::Error) -> CliError {
+    CliError::TagError(format!("{}: {}", path.display(), error))
+}
@@ -0,0 +1,206 @@
+//! What podcast directories accept in artwork: RGB JPEGs and PNGs, without
+//! transparency or interlacing. Apple takes other images without
+//! complaint and drops them days later, so they are caught here instead.
+
+use crate::i18n::t;
+use crate::output::outln;
+use crate::CliError;
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat, RgbImage};
+use std::fmt;
+use std::io::Cursor;
+use std::path::Path;
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_QUALITY: u8 = 92;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageProblem {
+    NotJpegOrPng,
+    Cmyk,
+    Interlaced,
+    Transparent,
+}
+
+impl fmt::Display for ImageProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let problem = match self {
+            ImageProblem::NotJpegOrPng => "is not a readable JPEG or PNG",
+            ImageProblem::Cmyk => "is a CMYK JPEG, it must be RGB",
+            ImageProblem::Interlaced => "is an interlaced PNG",
+            ImageProblem::Transparent => "has transparency",
+        };
+        f.write_str(problem)
+    }
+}
+
+/// Width and height from a PNG or JPEG header.
+pub fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    if bytes.starts_with(PNG_SIGNATURE) && bytes.len() >= 24 {
+        let width = u32::from_be_bytes(bytes[16..20].try_into().ok()?);
+        let height = u32::from_be_bytes(bytes[20..24].try_into().ok()?);
+        return Some((width, height));
+    }
+
+    let frame = jpeg_frame(bytes)?;
+    let height = u16::from_be_bytes([frame[1], frame[2]]);
+    let width = u16::from_be_bytes([frame[3], frame[4]]);
+    Some((width.into(), height.into()))
+}
+
+/// The extension and MIME type of a JPEG or PNG.
+pub fn file_type(bytes: &[u8]) -> (&'static str, &'static str) {
+    if bytes.starts_with(PNG_SIGNATURE) {
+        ("png", "image/png")
+    } else {
+        ("jpg", "image/jpeg")
+    }
+}
+
+/// What directories would object to, from the image's headers, and for
+/// PNGs that could be transparent, its pixels.
+pub fn problems(bytes: &[u8]) -> Vec<ImageProblem> {
+    let mut problems = Vec::new();
+    if bytes.starts_with(PNG_SIGNATURE) && bytes.len() >= 29 {
+        // IHDR: color type 4 and 6 carry alpha, and interlace method 1 is
+        // Adam7. Artwork is often exported with an alpha channel that is
+        // opaque everywhere, so only pixels that aren't count.
+        if matches!(bytes[25], 4 | 6) || png_has_chunk(bytes, b"tRNS") {
+            match png_is_transparent(bytes) {
+                Some(true) => problems.push(ImageProblem::Transparent),
+                Some(false) => {}
+                None => problems.push(ImageProblem::NotJpegOrPng),
+            }
+        }
+        if bytes[28] == 1 {
+            problems.push(ImageProblem::Interlaced);
+        }
+        return problems;
+    }
+
+    match jpeg_frame(bytes) {
+        Some(frame) if frame[5] == 4 => problems.push(ImageProblem::Cmyk),
+        Some(_) => {}
+        None => problems.push(ImageProblem::NotJpegOrPng),
+    }
+    problems
+}
+
+/// Re-encode an image without the problems `problems` finds: flattened
+/// onto white, in RGB, and not interlaced. JPEGs stay JPEGs and PNGs stay
+/// PNGs.
+pub fn convert(bytes: &[u8]) -> Result<Vec<u8>, CliError> {
+    let format = image::guess_format(bytes).map_err(image_error)?;
+    let decoded = image::load_from_memory_with_format(bytes, format).map_err(image_error)?;
+    let flattened = flatten(&decoded);
+
+    let mut converted = Vec::new();
+    match format {
+        ImageFormat::Jpeg => JpegEncoder::new_with_quality(&mut converted, JPEG_QUALITY)
+            .encode_image(&flattened)
+            .map_err(image_error)?,
+        _ => flattened
+            .write_to(&mut Cursor::new(&mut converted), ImageFormat::Png)
+            .map_err(image_error)?,
+    }
+    Ok(converted)
+}
+
+/// The image in RGB, with anything transparent blended onto white.
+pub fn flatten(image: &DynamicImage) -> RgbImage {
+    let rgba = image.to_rgba8();
+    let mut rgb = RgbImage::new(rgba.width(), rgba.height());
+    for (x, y, pixel) in rgba.enumerate_pixels() {
+        let alpha = u32::from(pixel[3]);
+        let channel = |value: u8| ((u32::from(value) * alpha + 255 * (255 - alpha)) / 255) as u8;
+        rgb.put_pixel(
+            x,
+            y,
+            image::Rgb([channel(pixel[0]), channel(pixel[1]), channel(pixel[2])]),
+        );
+    }
+    rgb
+}
+
+/// Whether any pixel of the PNG is less than opaque, with `tRNS` applied;
+/// none when it can't be decoded.
+fn png_is_transparent(bytes: &[u8]) -> Option<bool> {
+    let decoded = image::load_from_memory_with_format(bytes, ImageFormat::Png).ok()?;
+    if !decoded.color().has_alpha() {
+        return Some(false);
+    }
+    Some(
+        decoded
+            .to_rgba16()
+            .pixels()
+            .any(|pixel| pixel[3] < u16::MAX),
+    )
+}
+
+fn image_error(error: image::ImageError) -> CliError {
+    CliError::ArtError(error.to_string())
+}
+
+/// The body of the JPEG's start-of-frame segment: precision, height,
+/// width, and number of components.
+fn jpeg_frame(bytes: &[u8]) -> Option<&[u8]> {
+    if !bytes.starts_with(&[0xff, 0xd8]) {
+        return None;
+    }
+    // Walk the segments until a start-of-frame marker
+    let mut offset = 2;
+    while offset + 9 < bytes.len() {
+        if bytes[offset] != 0xff {
+            return None;
+        }
+        let marker = bytes[offset + 1];
+        let length = u16::from_be_bytes([bytes[offset + 2], bytes[offset + 3]]) as usize;
+        let is_frame = matches!(marker, 0xc0..=0xcf) && !matches!(marker, 0xc4 | 0xc8 | 0xcc);
+        if is_frame {
+            return Some(&bytes[offset + 4..]);
+        }
+        offset += 2 + length;
+    }
+    None
+}
+
+/// Whether a chunk of this type comes before the image data.
+fn png_has_chunk(bytes: &[u8], chunk_type: &[u8; 4]) -> bool {
+    let mut offset = PNG_SIGNATURE.len();
+    while offset + 8 <= bytes.len() {
+        let length = u32::from_be_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ]) as usize;
+        let found = &bytes[offset + 4..offset + 8];
+        if found == chunk_type {
+            return true;
+        }
+        if found == b"IDAT" {
+            return false;
+        }
+        offset += 12 + length;
+    }
+    false
+}
+
+/// `fix-image`: print the problems `problems` finds in `image`, and write
+/// it converted to `output`, or over itself.
+pub fn fix_image(image: &Path, output: Option<&Path>) -> Result<(), CliError> {
+    let bytes = std::fs::read(image)?;
+    let problems = problems(&bytes);
+    let path = image.display().to_string();
+    if problems.is_empty() {
+        outln!("{}", t!("image-ok", path = path));
+        return Ok(());
+    }
+    for problem in &problems {
+        outln!("{}", t!("image-problem", path = path.as_str(), problem = problem.to_string()));
+    }
+    let output = output.unwrap_or(image);
+    std::fs::write(output, convert(&bytes)?)?;
+    outln!("{}", t!("wrote-file", path = output.display().to_string()));
+    Ok(())
+}
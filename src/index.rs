@@ -0,0 +1,476 @@
+//! An index of the episode files, so commands that read every episode
+//! don't parse every file each time on a show with years of episodes.
+//!
+//! With `index.sqlite: true` in channel.yaml, and the crate built with the
+//! `sqlite` feature, episodes are kept in a SQLite database in
+//! `.podcast-ctl/cache/`. Each run only parses the files whose size or
+//! modification time changed since the last one, and drops the episodes
+//! whose files are gone; `reindex` builds it again from scratch. Encrypted
+//! episode files are never indexed, so their notes are only ever on disk
+//! encrypted; they are read each time.
+//!
+//! `search` and `analytics compare` query it: search with a full-text
+//! index of titles, summaries, descriptions, and keywords, and compare for
+//! the latest released episodes. `stats` and the daemon's dashboard read
+//! every episode from it. Commands that change episodes read the files.
+
+use crate::config::{ChannelConfig, Episode};
+use crate::state::Workspace;
+use crate::CliError;
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "sqlite")]
+const INDEX_FILE: &str = "index.sqlite";
+
+/// Every episode in `episode_dir`, with the file it was read from, from
+/// the index when channel.yaml turns it on.
+pub fn episode_files(
+    config: &ChannelConfig,
+    episode_dir: &Path,
+) -> Result<Vec<(PathBuf, Episode)>, CliError> {
+    if !config.index.sqlite {
+        return crate::get_all_episode_files(episode_dir);
+    }
+    indexed(&workspace(episode_dir), episode_dir)
+}
+
+/// Every episode in `episode_dir`, as [`episode_files`] reads them.
+pub fn episodes(config: &ChannelConfig, episode_dir: &Path) -> Result<Vec<Episode>, CliError> {
+    Ok(episode_files(config, episode_dir)?
+        .into_iter()
+        .map(|(_, episode)| episode)
+        .collect())
+}
+
+/// Episodes whose title, summary, description, or keywords contain
+/// `query`, ignoring case, newest first.
+pub fn search(
+    config: &ChannelConfig,
+    episode_dir: &Path,
+    query: &str,
+) -> Result<Vec<Episode>, CliError> {
+    let needle = query.to_lowercase();
+    let mut found = if config.index.sqlite {
+        searched(&workspace(episode_dir), episode_dir, query)?
+    } else {
+        crate::get_all_episodes(episode_dir)?
+            .into_iter()
+            .filter(|episode| matches(episode, &needle))
+            .collect()
+    };
+    found.sort_by_key(|episode| std::cmp::Reverse(episode.released_at));
+    Ok(found)
+}
+
+/// The `count` latest episodes released by `now` that aren't drafts,
+/// newest first.
+pub fn latest_released(
+    config: &ChannelConfig,
+    episode_dir: &Path,
+    now: DateTime<Utc>,
+    count: usize,
+) -> Result<Vec<Episode>, CliError> {
+    let mut latest: Vec<Episode> = if config.index.sqlite {
+        released(&workspace(episode_dir), episode_dir, now, count)?
+    } else {
+        crate::get_all_episodes(episode_dir)?
+            .into_iter()
+            .filter(|episode| !episode.draft && episode.released_at <= now)
+            .collect()
+    };
+    latest.sort_by_key(|episode| std::cmp::Reverse(episode.released_at));
+    latest.truncate(count);
+    Ok(latest)
+}
+
+/// Whether `episode` contains `needle`, which is lowercase.
+pub fn matches(episode: &Episode, needle: &str) -> bool {
+    [
+        &episode.title,
+        &episode.summary,
+        &episode.description,
+        &episode.keywords.join(" "),
+    ]
+    .iter()
+    .any(|text| text.to_lowercase().contains(needle))
+}
+
+/// Build the index again from every episode file, returning how many
+/// episodes are in it.
+pub fn rebuild(config: &ChannelConfig, episode_dir: &Path) -> Result<usize, CliError> {
+    if !config.index.sqlite {
+        return Err(CliError::IndexError(
+            "there is no index to rebuild; set `index.sqlite: true` in channel.yaml".to_owned(),
+        ));
+    }
+    let workspace = workspace(episode_dir);
+    clear(&workspace)?;
+    Ok(indexed(&workspace, episode_dir)?.len())
+}
+
+fn workspace(episode_dir: &Path) -> Workspace {
+    Workspace::new(episode_dir.parent().unwrap_or(episode_dir))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn unavailable() -> CliError {
+    CliError::IndexError(
+        "`index.sqlite` is set, but podcast-ctl was built without the `sqlite` feature".to_owned(),
+    )
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn indexed(
+    _workspace: &Workspace,
+    _episode_dir: &Path,
+) -> Result<Vec<(PathBuf, Episode)>, CliError> {
+    Err(unavailable())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn searched(
+    _workspace: &Workspace,
+    _episode_dir: &Path,
+    _query: &str,
+) -> Result<Vec<Episode>, CliError> {
+    Err(unavailable())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn released(
+    _workspace: &Workspace,
+    _episode_dir: &Path,
+    _now: DateTime<Utc>,
+    _count: usize,
+) -> Result<Vec<Episode>, CliError> {
+    Err(unavailable())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn clear(_workspace: &Workspace) -> Result<(), CliError> {
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+fn clear(workspace: &Workspace) -> Result<(), CliError> {
+    match std::fs::remove_file(workspace.cache_file(INDEX_FILE)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// The index, brought up to date with the episode files, and the files.
+#[cfg(feature = "sqlite")]
+fn refreshed(
+    workspace: &Workspace,
+    episode_dir: &Path,
+) -> Result<(sqlite::Index, Vec<PathBuf>), CliError> {
+    let path = workspace.cache_file(INDEX_FILE);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut index = sqlite::Index::open(&path)?;
+    let paths: Vec<PathBuf> = crate::get_episode_paths(episode_dir)?
+        .into_iter()
+        .filter(|path| crate::crypto::readable(path))
+        .collect();
+    index.refresh(&paths)?;
+    Ok((index, paths))
+}
+
+/// The encrypted episodes in `paths`, which are never indexed.
+#[cfg(feature = "sqlite")]
+fn encrypted(paths: &[PathBuf]) -> Result<Vec<Episode>, CliError> {
+    paths
+        .iter()
+        .filter(|path| crate::crypto::is_encrypted(path))
+        .map(|path| crate::parse_episode(path, &crate::crypto::read(path)?, None))
+        .collect()
+}
+
+#[cfg(feature = "sqlite")]
+fn searched(
+    workspace: &Workspace,
+    episode_dir: &Path,
+    query: &str,
+) -> Result<Vec<Episode>, CliError> {
+    let (index, paths) = refreshed(workspace, episode_dir)?;
+    let mut found = index.search(query)?;
+    let needle = query.to_lowercase();
+    found.extend(
+        encrypted(&paths)?
+            .into_iter()
+            .filter(|episode| matches(episode, &needle)),
+    );
+    Ok(found)
+}
+
+#[cfg(feature = "sqlite")]
+fn released(
+    workspace: &Workspace,
+    episode_dir: &Path,
+    now: DateTime<Utc>,
+    count: usize,
+) -> Result<Vec<Episode>, CliError> {
+    let (index, paths) = refreshed(workspace, episode_dir)?;
+    let mut latest = index.latest_released(now, count)?;
+    latest.extend(
+        encrypted(&paths)?
+            .into_iter()
+            .filter(|episode| !episode.draft && episode.released_at <= now),
+    );
+    Ok(latest)
+}
+
+#[cfg(feature = "sqlite")]
+fn indexed(
+    workspace: &Workspace,
+    episode_dir: &Path,
+) -> Result<Vec<(PathBuf, Episode)>, CliError> {
+    let (index, paths) = refreshed(workspace, episode_dir)?;
+    let mut stored = index.episodes()?;
+
+    let mut episodes = Vec::new();
+    for path in paths {
+        let episode = match stored.remove(&path.display().to_string()) {
+            Some(episode) => episode,
+            None => crate::parse_episode(&path, &crate::crypto::read(&path)?, None)?,
+        };
+        episodes.push((path, episode));
+    }
+    Ok(episodes)
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use crate::config::Episode;
+    use crate::CliError;
+    use chrono::{DateTime, Utc};
+    use log::{debug, info};
+    use rusqlite::{params, Connection, Params};
+    use std::collections::{HashMap, HashSet};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::UNIX_EPOCH;
+
+    /// Bumped when the tables change, which rebuilds the index.
+    const SCHEMA_VERSION: i64 = 2;
+
+    // Trigram tokens match any part of a word, as search always has
+    const SCHEMA: &str = "
+        DROP TABLE IF EXISTS episodes;
+        DROP TABLE IF EXISTS episode_text;
+        CREATE TABLE episodes (
+            path TEXT PRIMARY KEY,
+            modified INTEGER NOT NULL,
+            size INTEGER NOT NULL,
+            id TEXT NOT NULL,
+            released_at INTEGER NOT NULL,
+            draft INTEGER NOT NULL,
+            episode TEXT NOT NULL
+        );
+        CREATE INDEX episodes_released_at ON episodes (released_at);
+        CREATE VIRTUAL TABLE episode_text USING fts5(
+            path UNINDEXED, title, summary, description, keywords,
+            tokenize = 'trigram'
+        );
+    ";
+
+    pub struct Index {
+        connection: Connection,
+    }
+
+    impl Index {
+        pub fn open(path: &Path) -> Result<Index, CliError> {
+            let connection = Connection::open(path).map_err(index_error)?;
+            let version: i64 = connection
+                .query_row("PRAGMA user_version", [], |row| row.get(0))
+                .map_err(index_error)?;
+            if version != SCHEMA_VERSION {
+                debug!("Creating the episode index in {}", path.display());
+                connection
+                    .execute_batch(SCHEMA)
+                    .and_then(|_| connection.pragma_update(None, "user_version", SCHEMA_VERSION))
+                    .map_err(index_error)?;
+            }
+            Ok(Index { connection })
+        }
+
+        /// Parse the files in `paths` that changed since they were indexed,
+        /// and drop the episodes whose files aren't in `paths`.
+        pub fn refresh(&mut self, paths: &[PathBuf]) -> Result<(), CliError> {
+            let known = self.stamps()?;
+            let transaction = self.connection.transaction().map_err(index_error)?;
+            let mut seen = HashSet::new();
+            for path in paths {
+                let key = path.display().to_string();
+                seen.insert(key.clone());
+                if crate::crypto::is_encrypted(path) {
+                    continue;
+                }
+                let stamp = stamp(path)?;
+                if known.get(&key) == Some(&stamp) {
+                    continue;
+                }
+                info!("Indexing {}", path.display());
+                let episode = crate::parse_episode(path, &fs::read(path)?, None)?;
+                transaction
+                    .execute(
+                        "INSERT OR REPLACE INTO episodes
+                            (path, modified, size, id, released_at, draft, episode)
+                            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                        params![
+                            key,
+                            stamp.0,
+                            stamp.1,
+                            episode.id,
+                            episode.released_at.timestamp(),
+                            episode.draft,
+                            serde_json::to_string(&episode)?,
+                        ],
+                    )
+                    .and_then(|_| {
+                        transaction
+                            .execute("DELETE FROM episode_text WHERE path = ?1", params![key])
+                    })
+                    .and_then(|_| {
+                        transaction.execute(
+                            "INSERT INTO episode_text
+                                (path, title, summary, description, keywords)
+                                VALUES (?1, ?2, ?3, ?4, ?5)",
+                            params![
+                                key,
+                                episode.title,
+                                episode.summary,
+                                episode.description,
+                                episode.keywords.join(" "),
+                            ],
+                        )
+                    })
+                    .map_err(index_error)?;
+            }
+            for key in known.keys().filter(|key| !seen.contains(*key)) {
+                transaction
+                    .execute("DELETE FROM episodes WHERE path = ?1", params![key])
+                    .and_then(|_| {
+                        transaction
+                            .execute("DELETE FROM episode_text WHERE path = ?1", params![key])
+                    })
+                    .map_err(index_error)?;
+            }
+            transaction.commit().map_err(index_error)
+        }
+
+        /// The indexed episodes whose text contains `query`, ignoring case.
+        /// Queries of three characters or more use the full-text index;
+        /// shorter ones, which trigrams can't match, scan the text.
+        pub fn search(&self, query: &str) -> Result<Vec<Episode>, CliError> {
+            if query.chars().count() >= 3 {
+                let phrase = format!("\"{}\"", query.replace('"', "\"\""));
+                return self.query(
+                    "SELECT episodes.episode FROM episode_text
+                        JOIN episodes ON episodes.path = episode_text.path
+                        WHERE episode_text MATCH ?1",
+                    params![phrase],
+                );
+            }
+            let pattern = format!(
+                "%{}%",
+                query
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_")
+            );
+            self.query(
+                "SELECT episodes.episode FROM episode_text
+                    JOIN episodes ON episodes.path = episode_text.path
+                    WHERE episode_text.title LIKE ?1 ESCAPE '\\'
+                        OR episode_text.summary LIKE ?1 ESCAPE '\\'
+                        OR episode_text.description LIKE ?1 ESCAPE '\\'
+                        OR episode_text.keywords LIKE ?1 ESCAPE '\\'",
+                params![pattern],
+            )
+        }
+
+        /// The `count` latest indexed episodes released by `now` that
+        /// aren't drafts, newest first.
+        pub fn latest_released(
+            &self,
+            now: DateTime<Utc>,
+            count: usize,
+        ) -> Result<Vec<Episode>, CliError> {
+            self.query(
+                "SELECT episode FROM episodes
+                    WHERE draft = 0 AND released_at <= ?1
+                    ORDER BY released_at DESC
+                    LIMIT ?2",
+                params![now.timestamp(), count as i64],
+            )
+        }
+
+        /// The episodes in the first column of what `sql` selects.
+        fn query<P: Params>(&self, sql: &str, params: P) -> Result<Vec<Episode>, CliError> {
+            let mut statement = self.connection.prepare(sql).map_err(index_error)?;
+            let rows = statement
+                .query_map(params, |row| row.get::<_, String>(0))
+                .map_err(index_error)?;
+            let mut episodes = Vec::new();
+            for row in rows {
+                episodes.push(serde_json::from_str(&row.map_err(index_error)?)?);
+            }
+            Ok(episodes)
+        }
+
+        /// The indexed episodes by file.
+        pub fn episodes(&self) -> Result<HashMap<String, Episode>, CliError> {
+            let mut statement = self
+                .connection
+                .prepare("SELECT path, episode FROM episodes")
+                .map_err(index_error)?;
+            let rows = statement
+                .query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })
+                .map_err(index_error)?;
+            let mut episodes = HashMap::new();
+            for row in rows {
+                let (path, json) = row.map_err(index_error)?;
+                episodes.insert(path, serde_json::from_str(&json)?);
+            }
+            Ok(episodes)
+        }
+
+        /// When each indexed file was last modified, and its size.
+        fn stamps(&self) -> Result<HashMap<String, (i64, i64)>, CliError> {
+            let mut statement = self
+                .connection
+                .prepare("SELECT path, modified, size FROM episodes")
+                .map_err(index_error)?;
+            let rows = statement
+                .query_map([], |row| {
+                    let stamp = (row.get::<_, i64>(1)?, row.get::<_, i64>(2)?);
+                    Ok((row.get::<_, String>(0)?, stamp))
+                })
+                .map_err(index_error)?;
+            let stamps = rows.collect::<Result<_, _>>().map_err(index_error)?;
+            Ok(stamps)
+        }
+    }
+
+    /// When `path` was modified, in nanoseconds, and its size: edits
+    /// within a second that keep the size still change it.
+    fn stamp(path: &Path) -> Result<(i64, i64), CliError> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_nanos() as i64)
+            .unwrap_or_default();
+        Ok((modified, metadata.len() as i64))
+    }
+
+    fn index_error(e: rusqlite::Error) -> CliError {
+        CliError::IndexError(e.to_string())
+    }
+}
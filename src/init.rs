@@ -0,0 +1,205 @@
+use crate::config::*;
+use crate::i18n::t;
+use crate::output::{out, outln};
+use crate::CliError;
+use chrono::{Datelike, Utc};
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+/// Ask for the details of a new show and write `channel_file` and an empty
+/// `episodes/` directory next to it.
+pub fn init_channel(channel_file: &Path, force: bool) -> Result<(), CliError> {
+    if channel_file.exists() && !force {
+        return Err(CliError::ChannelExists(channel_file.display().to_string()));
+    }
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut ask = |label: &str, default: Option<&str>| prompt(&mut input, &t!(label), default);
+
+    outln!("{}", t!("init-describe"));
+    let title = ask("init-title", None)?;
+    let description = ask("init-description", Some(&title))?;
+    let subtitle = ask("init-subtitle", Some(&description))?;
+    let summary = ask("init-summary", Some(&description))?;
+    let explicit = ask("init-explicit", Some("n"))?;
+    let image = ask("init-image", None)?;
+    let link = ask("init-link", Some(""))?;
+    let owner_name = ask("init-owner-name", None)?;
+    let owner_email = ask("init-owner-email", None)?;
+    let keywords = ask("init-keywords", Some(""))?;
+    let category = loop {
+        let category = ask("init-category", None)?;
+        if crate::categories::is_known(&category) {
+            break category;
+        }
+        outln!("{}", t!("init-unknown-category", category = category));
+    };
+    let language = ask("init-language", Some("en-us"))?;
+    let default_copyright = format!("Copyright {} {}", Utc::now().year(), owner_name);
+    let copyright = ask("init-copyright", Some(&default_copyright))?;
+    let episodic = ask("init-episodic", Some("n"))?;
+
+    outln!();
+    outln!("{}", t!("init-publishing"));
+    let bucket = ask("init-bucket", None)?;
+    let region = ask("init-region", Some("us-east-1"))?;
+    let default_endpoint = format!("s3.{}.amazonaws.com", region);
+    let endpoint = ask("init-endpoint", Some(&default_endpoint))?;
+    let prefix = ask("init-prefix", Some(&slug(&title)))?;
+
+    let channel = ChannelDetails {
+        title,
+        link: Some(link).filter(|link| !link.is_empty()),
+        description,
+        subtitle,
+        summary,
+        explicit: is_yes(&explicit),
+        image,
+        owner: OwnerDetails {
+            name: owner_name,
+            email: owner_email,
+        },
+        keywords: keywords
+            .split(',')
+            .map(|keyword| keyword.trim().to_owned())
+            .filter(|keyword| !keyword.is_empty())
+            .collect(),
+        categories: vec![category],
+        language,
+        copyright,
+        podcast_type: if is_yes(&episodic) {
+            PodcastType::Episodic
+        } else {
+            PodcastType::Serial
+        },
+        medium: None,
+        podcast_guid: None,
+        locked: None,
+        funding: Vec::new(),
+        hosts: Vec::new(),
+        guests: Vec::new(),
+        trailers: Vec::new(),
+        timezone: None,
+        explicit_terms: Vec::new(),
+    };
+    let publishing = PublishingConfig {
+        storage: StorageType::S3,
+        region: Region {
+            name: Some(region),
+            endpoint,
+            profile: None,
+        },
+        bucket,
+        prefix,
+        stylesheet: None,
+        default_stylesheet: true,
+        recent_feed: None,
+        max_items: None,
+        per_season_feeds: false,
+        site: false,
+        verify_cdn: None,
+        websub_hub: None,
+        destinations: BTreeMap::new(),
+        notifications: NotificationsConfig::default(),
+        private: None,
+        parallel_uploads: default_parallel_uploads(),
+        multipart: MultipartConfig::default(),
+        retry: RetryConfig::default(),
+        object_options: ObjectOptionsConfig::default(),
+        key_layout: KeyLayout::default(),
+        name_template: None,
+        guid_strategy: GuidStrategy::default(),
+        guid_template: None,
+        gzip_feeds: false,
+        uncompressed_feed_suffix: None,
+        public_base_url: None,
+        path_style: false,
+        local: None,
+        sftp: None,
+        azure: None,
+    };
+
+    write_channel(channel_file, &channel, &publishing)?;
+
+    outln!();
+    outln!(
+        "{}",
+        t!("wrote-file", path = channel_file.display().to_string())
+    );
+    outln!(
+        "{}",
+        t!("init-next-step", path = channel_file.display().to_string())
+    );
+    Ok(())
+}
+
+/// Write `channel_file` and an `episodes/` directory next to it. Only the
+/// sections a new channel needs are written, without empty optional fields,
+/// and the result is checked to load.
+pub fn write_channel(
+    channel_file: &Path,
+    channel: &ChannelDetails,
+    publishing: &PublishingConfig,
+) -> Result<(), CliError> {
+    let mut document = crate::yaml::without_nulls(serde_yaml::to_value(channel)?);
+    if let Value::Mapping(mapping) = &mut document {
+        mapping.insert(
+            Value::from("publishing"),
+            crate::yaml::without_nulls(serde_yaml::to_value(publishing)?),
+        );
+    }
+    let format = crate::formats::Format::of(channel_file);
+    let text = format.to_string(&document)?;
+    format.parse::<ChannelConfig>(text.as_bytes())?;
+
+    if let Some(parent) = channel_file.parent() {
+        fs::create_dir_all(parent.join("episodes"))?;
+    }
+    fs::write(channel_file, text)?;
+    Ok(())
+}
+
+/// Ask for `label` until there is an answer, or the `default` is taken.
+pub fn prompt<R: BufRead>(input: &mut R, label: &str, default: Option<&str>) -> io::Result<String> {
+    loop {
+        match default {
+            Some(default) if !default.is_empty() => out!("{} [{}]: ", label, default),
+            _ => out!("{}: ", label),
+        }
+
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                t!("prompt-no-answer", label = label),
+            ));
+        }
+
+        let answer = line.trim();
+        match (answer.is_empty(), default) {
+            (false, _) => return Ok(answer.to_owned()),
+            (true, Some(default)) => return Ok(default.to_owned()),
+            (true, None) => outln!("{}", t!("prompt-required", label = label)),
+        }
+    }
+}
+
+/// `y`, or the translated equivalent
+pub fn is_yes(answer: &str) -> bool {
+    let answer = answer.to_lowercase();
+    answer.starts_with('y') || answer.starts_with(&t!("init-yes"))
+}
+
+/// `title` in lowercase words joined by `-`, for keys and file names.
+pub fn slug(title: &str) -> String {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
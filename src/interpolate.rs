@@ -0,0 +1,165 @@
+//! Environment variables in channel.yaml, so one file can publish to
+//! staging and production, and keep account names out of git:
+//!
+//! ```yaml
+//! publishing:
+//!   bucket: ${PODCAST_BUCKET}
+//!   region:
+//!     endpoint: ${S3_ENDPOINT:-https://sfo2.digitaloceanspaces.com}
+//! ```
+//!
+//! `${NAME}` is replaced with the variable, and `${NAME:-default}` with the
+//! default when it is unset or empty; `$${` is a literal `${`. Variables
+//! are read from the environment and `.env`. Only the channel's details
+//! and `publishing` are interpolated: hooks and pipelines run shell
+//! commands, where `${NAME}` is the shell's to expand.
+
+use crate::CliError;
+use serde_yaml::Value;
+use std::env;
+
+/// Sections of channel.yaml besides `publishing`; the rest of the top
+/// level is the channel's details.
+const NOT_INTERPOLATED: [&str; 21] = [
+    "plugins",
+    "wasmPlugins",
+    "http",
+    "summarize",
+    "promo",
+    "art",
+    "encryption",
+    "archive",
+    "approvals",
+    "changelog",
+    "checklist",
+    "lint",
+    "quality",
+    "inbox",
+    "roles",
+    "seasons",
+    "metrics",
+    "analytics",
+    "index",
+    "hooks",
+    "pipelines",
+];
+
+/// Replace the variables in `config`, channel.yaml as read, failing with
+/// every one that is unset and has no default.
+pub fn interpolate(config: &mut Value) -> Result<(), CliError> {
+    let mut unset = Vec::new();
+    if let Some(mapping) = config.as_mapping_mut() {
+        for (key, value) in mapping.iter_mut() {
+            if !matches!(key.as_str(), Some(key) if NOT_INTERPOLATED.contains(&key)) {
+                interpolate_value(value, &mut unset);
+            }
+        }
+    }
+    if unset.is_empty() {
+        return Ok(());
+    }
+    unset.sort();
+    unset.dedup();
+    Err(CliError::UnsetVariables(unset.join(", ")))
+}
+
+/// The variables `config`, channel.yaml as read, uses.
+pub fn variables(config: &Value) -> Vec<String> {
+    let mut variables = Vec::new();
+    if let Some(mapping) = config.as_mapping() {
+        for (key, value) in mapping {
+            if !matches!(key.as_str(), Some(key) if NOT_INTERPOLATED.contains(&key)) {
+                collect(value, &mut variables);
+            }
+        }
+    }
+    variables.sort();
+    variables.dedup();
+    variables
+}
+
+fn interpolate_value(value: &mut Value, unset: &mut Vec<String>) {
+    match value {
+        Value::String(text) if text.contains("${") => {
+            *text = replace(text, &mut |name, default| {
+                let value = env::var(name).ok().filter(|value| !value.is_empty());
+                let value = value.or_else(|| default.map(str::to_owned));
+                if value.is_none() {
+                    unset.push(name.to_owned());
+                }
+                value
+            })
+        }
+        Value::Sequence(items) => {
+            for item in items {
+                interpolate_value(item, unset);
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (_, item) in mapping.iter_mut() {
+                interpolate_value(item, unset);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn collect(value: &Value, variables: &mut Vec<String>) {
+    match value {
+        Value::String(text) => {
+            replace(text, &mut |name, _| {
+                variables.push(name.to_owned());
+                None
+            });
+        }
+        Value::Sequence(items) => items.iter().for_each(|item| collect(item, variables)),
+        Value::Mapping(mapping) => mapping.values().for_each(|item| collect(item, variables)),
+        _ => {}
+    }
+}
+
+/// `text` with each `${NAME}` or `${NAME:-default}` replaced with what
+/// `resolve` gives for its name and default, or nothing.
+fn replace<F>(text: &str, resolve: &mut F) -> String
+where
+    F: FnMut(&str, Option<&str>) -> Option<String>,
+{
+    let mut replaced = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        replaced.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("$${") {
+            replaced.push_str("${");
+            rest = after;
+            continue;
+        }
+        match reference(rest) {
+            Some((name, default, length)) => {
+                replaced.push_str(&resolve(name, default).unwrap_or_default());
+                rest = &rest[length..];
+            }
+            None => {
+                replaced.push('$');
+                rest = &rest[1..];
+            }
+        }
+    }
+    replaced.push_str(rest);
+    replaced
+}
+
+/// The `${NAME}` or `${NAME:-default}` at the start of `text`: the name,
+/// the default, and how long it is.
+fn reference(text: &str) -> Option<(&str, Option<&str>, usize)> {
+    let inner = text.strip_prefix("${")?;
+    let end = inner.find('}')?;
+    let (name, default) = match inner[..end].split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (&inner[..end], None),
+    };
+    let valid = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then(|| (name, default, end + 3))
+}
@@ -0,0 +1,167 @@
+//! JSON Feed 1.1 (https://www.jsonfeed.org/version/1.1/), for clients and
+//! websites that read JSON instead of RSS. Episode audio is an item
+//! attachment, and items use the same ids as RSS `guid`s, so an episode is
+//! the same item in both feeds.
+
+use crate::config::{ChannelDetails, Episode};
+use crate::urls;
+use crate::xml::ItemAssets;
+use crate::CliError;
+use serde::Serialize;
+use std::collections::HashMap;
+
+const VERSION: &str = "https://jsonfeed.org/version/1.1";
+
+#[derive(Serialize)]
+struct JsonFeed<'a> {
+    version: &'static str,
+    title: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    home_page_url: Option<&'a str>,
+    feed_url: &'a str,
+    description: &'a str,
+    icon: &'a str,
+    authors: Vec<Author<'a>>,
+    language: &'a str,
+    items: Vec<Item<'a>>,
+}
+
+#[derive(Serialize)]
+struct Author<'a> {
+    name: &'a str,
+}
+
+#[derive(Serialize)]
+struct Item<'a> {
+    id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<&'a str>,
+    title: &'a str,
+    content_html: String,
+    summary: &'a str,
+    image: &'a str,
+    date_published: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    attachments: Vec<Attachment<'a>>,
+}
+
+#[derive(Serialize)]
+struct Attachment<'a> {
+    url: &'a str,
+    mime_type: &'a str,
+    size_in_bytes: u64,
+    duration_in_seconds: u64,
+}
+
+/// The feed published at `feed_url`, newest episode first. Show notes in
+/// `item_assets` are used as the content of episodes that have them. URLs
+/// are written in their normal form, as in the RSS feed.
+pub fn render_json_feed(
+    channel: &ChannelDetails,
+    episodes: &[Episode],
+    feed_url: &str,
+    item_assets: &HashMap<String, ItemAssets>,
+) -> Result<String, CliError> {
+    let mut channel = channel.clone();
+    urls::normalize_channel(&mut channel)?;
+    let channel = &channel;
+    let mut episodes = episodes.to_vec();
+    for episode in episodes.iter_mut() {
+        urls::normalize_episode(episode)?;
+    }
+    episodes.sort_by_key(|episode| std::cmp::Reverse(episode.released_at));
+
+    let items = episodes
+        .iter()
+        .map(|episode| Item {
+            id: crate::guids::guid(episode),
+            url: episode.link.as_deref(),
+            title: &episode.title,
+            content_html: item_assets
+                .get(&episode.id)
+                .and_then(|assets| assets.show_notes.clone())
+                .unwrap_or_else(|| crate::notes::to_html(&episode.description)),
+            summary: &episode.summary,
+            image: &episode.image,
+            date_published: episode.released_at.to_rfc3339(),
+            tags: crate::keywords::episode_keywords(&channel.keywords, episode),
+            attachments: vec![Attachment {
+                url: &episode.media.url,
+                mime_type: episode.media.enclosure_type(),
+                size_in_bytes: episode.media.bytes.0,
+                duration_in_seconds: episode.media.duration.0,
+            }],
+        })
+        .collect();
+
+    let feed = JsonFeed {
+        version: VERSION,
+        title: &channel.title,
+        home_page_url: channel.link.as_deref(),
+        feed_url,
+        description: &channel.description,
+        icon: &channel.image,
+        authors: vec![Author {
+            name: &channel.owner.name,
+        }],
+        language: &channel.language,
+        items,
+    };
+    Ok(serde_json::to_string_pretty(&feed)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_urls_in_their_normal_form() {
+        let mut channel = ChannelDetails::make_test();
+        channel.link = Some("https://Example.com/my show".to_owned());
+        channel.image = "https://example.com/art/cover art.png".to_owned();
+        let mut episode = Episode::make_test("first");
+        episode.link = Some("https://example.com/episodes/café".to_owned());
+        episode.image = "https://EXAMPLE.com/first.png".to_owned();
+        episode.media.url = "https://example.com/first episode.mp3".to_owned();
+
+        let json = render_json_feed(
+            &channel,
+            &[episode],
+            "https://example.com/feed.json",
+            &HashMap::new(),
+        )
+        .unwrap();
+        let feed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(feed["home_page_url"], "https://example.com/my%20show");
+        assert_eq!(feed["icon"], "https://example.com/art/cover%20art.png");
+        let item = &feed["items"][0];
+        assert_eq!(item["url"], "https://example.com/episodes/caf%C3%A9");
+        assert_eq!(item["image"], "https://example.com/first.png");
+        assert_eq!(
+            item["attachments"][0]["url"],
+            "https://example.com/first%20episode.mp3"
+        );
+    }
+
+    #[test]
+    fn refuses_urls_podcast_apps_cannot_fetch() {
+        let mut channel = ChannelDetails::make_test();
+        channel.link = None;
+        channel.image = "https://example.com/cover.png".to_owned();
+        let mut episode = Episode::make_test("first");
+        episode.link = None;
+        episode.image = "https://example.com/first.png".to_owned();
+        episode.media.url = "ftp://example.com/first.mp3".to_owned();
+        let rendered = render_json_feed(
+            &channel,
+            &[episode],
+            "https://example.com/feed.json",
+            &HashMap::new(),
+        );
+        assert!(matches!(
+            rendered,
+            Err(CliError::InvalidUrl(field, ..)) if field == "media.url in episode first"
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::config::{ChannelConfig, Episode};
+use crate::output::{self, outln};
+use crate::CliError;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Common English words plus conversational filler that never make useful
+/// keywords.
+const STOPWORDS: &str = "\
+    a about above actually after again against all also am an and any anything are aren't \
+    around as at back be because been before being below between both but by can can't come \
+    could couldn't did didn't do does doesn't doing don't down during each even every few for \
+    from further get gets getting go goes going gonna good got great had hadn't has hasn't \
+    have haven't having he he'd he'll he's her here here's hers herself him himself his how \
+    how's i i'd i'll i'm i've if in into is isn't it it's its itself just kind know let let's \
+    like little lot make me mean might more most much must my myself need no nor not now of \
+    off oh okay on once one only or other ought our ours ourselves out over own pretty \
+    probably really right said same say see she she'd she'll she's should shouldn't so some \
+    something sort still such sure take than that that's the their theirs them themselves then \
+    there there's these they they'd they'll they're they've thing things think this those \
+    through to too uh um under until up very want was wasn't way we we'd we'll we're we've \
+    well were weren't what what's when when's where where's which while who who's whom why \
+    why's will with won't would wouldn't yeah yes you you'd you'll you're you've your yours \
+    yourself yourselves";
+
+const MAX_PHRASE_WORDS: usize = 3;
+
+/// The longest `itunes:keywords` directories are known to read; keywords
+/// past it are left out of the feed.
+pub const MAX_FEED_LENGTH: usize = 255;
+
+/// Rank candidate keywords in `text` with RAKE (Rapid Automatic Keyword
+/// Extraction): phrases are runs of non-stopwords, and each word scores
+/// its co-occurrence degree over its frequency.
+pub fn extract_keywords(text: &str, limit: usize) -> Vec<String> {
+    let phrases = candidate_phrases(text);
+
+    let mut frequency: HashMap<&str, f64> = HashMap::new();
+    let mut degree: HashMap<&str, f64> = HashMap::new();
+    for phrase in &phrases {
+        for word in phrase {
+            *frequency.entry(word.as_str()).or_default() += 1.0;
+            *degree.entry(word.as_str()).or_default() += phrase.len() as f64;
+        }
+    }
+
+    let mut scores: HashMap<String, (f64, usize)> = HashMap::new();
+    for phrase in &phrases {
+        let score: f64 = phrase
+            .iter()
+            .map(|word| degree[word.as_str()] / frequency[word.as_str()])
+            .sum();
+        let entry = scores.entry(phrase.join(" ")).or_insert((score, 0));
+        entry.1 += 1;
+    }
+
+    // Phrases that only appear once are usually noise in a long transcript
+    let repeated = scores.values().filter(|(_, count)| *count > 1).count();
+    let mut ranked: Vec<(String, f64, usize)> = scores
+        .into_iter()
+        .filter(|(_, (_, count))| repeated < limit || *count > 1)
+        .map(|(phrase, (score, count))| (phrase, score, count))
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.2.cmp(&a.2))
+            .then(a.0.cmp(&b.0))
+    });
+
+    ranked
+        .into_iter()
+        .take(limit)
+        .map(|(phrase, _, _)| phrase)
+        .collect()
+}
+
+/// The keywords `episode` is published with: its own, after the
+/// channel's when it extends them, each once.
+pub fn episode_keywords(channel: &[String], episode: &Episode) -> Vec<String> {
+    let inherited = channel.iter().filter(|_| episode.extend_channel_keywords);
+    let mut keywords: Vec<String> = Vec::new();
+    for keyword in inherited.chain(&episode.keywords) {
+        let keyword = keyword.trim();
+        let repeated = keywords
+            .iter()
+            .any(|existing| existing.eq_ignore_ascii_case(keyword));
+        if !keyword.is_empty() && !repeated {
+            keywords.push(keyword.to_owned());
+        }
+    }
+    keywords
+}
+
+/// `itunes:keywords` for `keywords`, comma-separated, and the keywords
+/// left out to keep it within `MAX_FEED_LENGTH`.
+pub fn feed_keywords(keywords: &[String]) -> (String, Vec<&str>) {
+    let mut joined = String::new();
+    let mut left_out = Vec::new();
+    for keyword in keywords.iter().map(|keyword| keyword.trim()) {
+        let separator = if joined.is_empty() { "" } else { "," };
+        let length = joined.chars().count() + separator.len() + keyword.chars().count();
+        if keyword.is_empty() {
+            continue;
+        } else if length > MAX_FEED_LENGTH {
+            left_out.push(keyword);
+        } else {
+            joined.push_str(separator);
+            joined.push_str(keyword);
+        }
+    }
+    (joined, left_out)
+}
+
+fn candidate_phrases(text: &str) -> Vec<Vec<String>> {
+    let mut phrases = Vec::new();
+
+    for fragment in text.split(|c: char| ".,!?;:()[]\"\n\u{2014}\u{2013}".contains(c)) {
+        let mut current: Vec<String> = Vec::new();
+        for raw in fragment.split_whitespace() {
+            let word: String = raw
+                .trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase();
+            let boundary = word.chars().count() < 3
+                || word.chars().all(|c| c.is_numeric())
+                || is_stopword(&word.replace('\u{2019}', "'"));
+
+            if boundary {
+                push_phrase(&mut phrases, &mut current);
+            } else {
+                current.push(word);
+            }
+        }
+        push_phrase(&mut phrases, &mut current);
+    }
+
+    phrases
+}
+
+fn push_phrase(phrases: &mut Vec<Vec<String>>, current: &mut Vec<String>) {
+    if !current.is_empty() && current.len() <= MAX_PHRASE_WORDS {
+        phrases.push(std::mem::take(current));
+    } else {
+        current.clear();
+    }
+}
+
+fn is_stopword(word: &str) -> bool {
+    STOPWORDS
+        .split_whitespace()
+        .any(|stopword| stopword == word)
+}
+
+/// `keywords`: print keywords for the episode `selector` picks from
+/// `transcript`, and with `write`, make them its keywords once confirmed,
+/// or with `yes`.
+pub fn suggest(
+    episode_dir: &Path,
+    channel_config: &ChannelConfig,
+    selector: &str,
+    transcript: &Path,
+    limit: usize,
+    write: bool,
+    yes: bool,
+) -> Result<(), CliError> {
+    let (path, mut episode) =
+        crate::find_episode(episode_dir, selector, channel_config.channel.tz()?)?;
+    let transcript = crate::transcript::plain_text(&std::fs::read_to_string(transcript)?);
+    let suggestions = extract_keywords(&transcript, limit);
+    for keyword in &suggestions {
+        outln!("{}", keyword);
+    }
+    output::record("keywords", &suggestions);
+    if write {
+        episode.keywords = suggestions;
+        crate::save_episode_confirmed(&path, &episode, channel_config, yes)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_phrases_at_stopwords_and_punctuation() {
+        assert_eq!(
+            candidate_phrases("We talked about Rust async runtimes, and then the borrow checker."),
+            vec![
+                vec!["talked"],
+                vec!["rust", "async", "runtimes"],
+                vec!["borrow", "checker"],
+            ]
+        );
+    }
+
+    #[test]
+    fn leaves_out_short_words_numbers_and_long_phrases() {
+        assert_eq!(
+            candidate_phrases("AI in 2024: podcasting. Distributed systems consensus protocols."),
+            vec![vec!["podcasting"]]
+        );
+        assert_eq!(
+            candidate_phrases("I don\u{2019}t think microphones matter"),
+            vec![vec!["microphones", "matter"]]
+        );
+    }
+
+    #[test]
+    fn ranks_phrases_by_word_degree_over_frequency() {
+        let text = "Rust async runtimes. Rust async runtimes. Borrow checker. Borrow checker. Cats.";
+        assert_eq!(
+            extract_keywords(text, 2),
+            vec!["rust async runtimes", "borrow checker"]
+        );
+        // With room to spare, phrases said once are kept too
+        assert_eq!(
+            extract_keywords(text, 3),
+            vec!["rust async runtimes", "borrow checker", "cats"]
+        );
+        assert!(extract_keywords("", 5).is_empty());
+    }
+
+    #[test]
+    fn publishes_each_keyword_once() {
+        let channel = vec!["Tech".to_owned(), "Rust".to_owned()];
+        let mut episode = Episode::make_test("first");
+        episode.keywords = vec!["rust".to_owned(), " async ".to_owned(), String::new()];
+        assert_eq!(episode_keywords(&channel, &episode), vec!["rust", "async"]);
+        episode.extend_channel_keywords = true;
+        assert_eq!(
+            episode_keywords(&channel, &episode),
+            vec!["Tech", "Rust", "async"]
+        );
+    }
+
+    #[test]
+    fn leaves_out_keywords_past_the_feed_length() {
+        let keywords: Vec<String> = ["a", "b", "c", "d"]
+            .iter()
+            .zip([100, 100, 60, 50])
+            .map(|(letter, length)| letter.repeat(length))
+            .collect();
+        let (joined, left_out) = feed_keywords(&keywords);
+        assert_eq!(joined.len(), 252);
+        assert_eq!(joined, keywords[0].clone() + "," + &keywords[1] + "," + &keywords[3]);
+        assert_eq!(left_out, vec![keywords[2].as_str()]);
+    }
+}
@@ -0,0 +1,618 @@
+//! podcast-ctl's library: channel.yaml and episode files, creating
+//! episodes, rendering the feed, and publishing it to storage, for
+//! automation that would rather call them than run the `podcastctl`
+//! binary.
+//!
+//! ```no_run
+//! use podcastctl::{get_all_episodes, render_feed, ChannelConfig, FeedOptions};
+//!
+//! # fn main() -> Result<(), podcastctl::Error> {
+//! let text = std::fs::read_to_string("show/channel.yaml")?;
+//! let config: ChannelConfig = serde_yaml::from_str(&text)?;
+//! let episodes = get_all_episodes("show/episodes".as_ref())?;
+//! let feed = render_feed(&config.channel, &episodes, &FeedOptions::default())?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The supported API is what is here: the channel and episodes of
+//! [`config`], [`render_feed`] and its [`FeedOptions`], storage through
+//! [`backend`] and [`StorageBackend`], and [`Error`], which everything
+//! fails with. The rest of the crate is the `podcastctl` binary's.
+
+pub(crate) mod approvals;
+pub(crate) mod archive;
+pub(crate) mod art;
+pub(crate) mod artwork;
+pub(crate) mod audio;
+pub(crate) mod backup;
+pub(crate) mod batch;
+pub(crate) mod bulk;
+pub(crate) mod bundle;
+pub(crate) mod categories;
+pub(crate) mod cdn;
+pub(crate) mod changelog;
+pub(crate) mod channels;
+pub(crate) mod chapters;
+pub(crate) mod checklist;
+#[doc(hidden)]
+pub mod cli;
+pub(crate) mod clip;
+pub(crate) mod compare;
+pub mod config;
+pub(crate) mod create;
+pub(crate) mod crypto;
+pub(crate) mod dashboard;
+pub(crate) mod dates;
+pub(crate) mod deprecations;
+pub(crate) mod diff;
+pub(crate) mod edit;
+pub(crate) mod email;
+pub(crate) mod events;
+pub(crate) mod feed;
+pub(crate) mod feeddiff;
+pub(crate) mod fixtures;
+pub(crate) mod formats;
+pub(crate) mod guids;
+pub(crate) mod history;
+pub(crate) mod hooks;
+pub(crate) mod html;
+pub(crate) mod http;
+pub(crate) mod i18n;
+pub(crate) mod id3tags;
+pub(crate) mod images;
+pub(crate) mod index;
+pub(crate) mod init;
+pub(crate) mod interpolate;
+pub(crate) mod jsonfeed;
+pub(crate) mod keywords;
+pub(crate) mod links;
+pub(crate) mod lint;
+pub(crate) mod metrics;
+pub(crate) mod multipart;
+pub(crate) mod naming;
+pub(crate) mod notes;
+pub(crate) mod numbering;
+pub(crate) mod output;
+pub(crate) mod plugins;
+pub(crate) mod podping;
+pub(crate) mod preview;
+pub(crate) mod private;
+pub(crate) mod promo;
+pub(crate) mod provider;
+pub(crate) mod prune;
+pub(crate) mod quality;
+pub(crate) mod render;
+pub(crate) mod report;
+pub(crate) mod roles;
+pub(crate) mod rollback;
+pub(crate) mod search;
+pub(crate) mod seasons;
+pub(crate) mod selector;
+pub(crate) mod serve;
+pub(crate) mod site;
+pub(crate) mod spreadsheet;
+pub(crate) mod state;
+pub(crate) mod stats;
+pub(crate) mod storage;
+pub(crate) mod summarize;
+pub(crate) mod sync;
+pub(crate) mod template;
+pub(crate) mod transcript;
+pub(crate) mod trash;
+pub(crate) mod upload;
+pub(crate) mod urls;
+pub(crate) mod validate;
+pub(crate) mod wasm;
+pub(crate) mod watch;
+pub(crate) mod websub;
+pub(crate) mod xml;
+pub(crate) mod yaml;
+
+pub use chapters::Soundbite;
+pub use config::{ChannelConfig, ChannelDetails, Episode};
+pub use output::Progress;
+pub use storage::{backend, Object, ObjectReader, ObjectSource, StorageBackend, StoredObject};
+pub use upload::ObjectOptions;
+pub use xml::{
+    ArchiveLink, ExtraElement, FeedOptions, ItemAssets, RelatedFeed, RenderExtras, TranscriptLink,
+    WebSub,
+};
+pub use CliError as Error;
+
+use crate::i18n::t;
+use crate::output::outln;
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::error::{DisplayErrorContext, ProvideErrorMetadata, SdkError};
+use chrono_tz::Tz;
+use log::debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// The RSS feed for `channel` and `episodes`.
+pub fn render_feed(
+    channel: &ChannelDetails,
+    episodes: &[Episode],
+    options: &FeedOptions,
+) -> Result<String, CliError> {
+    xml::generate_podcast_xml(channel, episodes, options)
+}
+
+/// Everything the library and the binary fail with. Its message is in the
+/// selected language, see [`CliError::localized`].
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    YamlError(#[from] serde_yaml::Error),
+    #[error(transparent)]
+    XmlError(#[from] std::string::FromUtf8Error),
+    #[error("{}", self.localized())]
+    StorageRejected(String),
+    #[error("{}", self.localized())]
+    StorageUnavailable(String),
+    #[error("{}", self.localized())]
+    Mp3Error(String),
+    #[error("{}", self.localized())]
+    AudioError(&'static str, String),
+    #[error(transparent)]
+    ChronoError(#[from] chrono::ParseError),
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+    #[error("{}", self.localized())]
+    PluginError(String, String),
+    #[error("{}", self.localized())]
+    PluginExited(String, i32),
+    #[error("{}", self.localized())]
+    WasmError(String),
+    #[error(transparent)]
+    HttpError(#[from] reqwest::Error),
+    #[error("{}", self.localized())]
+    BrokenLinks(usize),
+    #[error(transparent)]
+    XmlParseError(#[from] quick_xml::Error),
+    #[error("{}", self.localized())]
+    HttpStatus(String, u16),
+    #[error("{}", self.localized())]
+    FeedMismatch(usize),
+    #[error("{}", self.localized())]
+    MissingEnvVar(String),
+    #[error("{}", self.localized())]
+    SummarizeError(String),
+    #[error("{}", self.localized())]
+    EpisodeNotFound(String),
+    #[error("{}", self.localized())]
+    AmbiguousEpisode(String, String),
+    #[error("{}", self.localized())]
+    ClipError(String),
+    #[error("{}", self.localized())]
+    PromoError(String),
+    #[error("{}", self.localized())]
+    WorkspaceLocked(String, String),
+    #[error("{}", self.localized())]
+    ChannelExists(String),
+    #[error("{}", self.localized())]
+    TrashError(String),
+    #[error("{}", self.localized())]
+    CryptoError(String),
+    #[error("{}", self.localized())]
+    ValidationFailed(usize),
+    #[error("{}", self.localized())]
+    ArchiveDamaged(usize),
+    #[error("{}", self.localized())]
+    RemoteMismatch(usize),
+    #[error("{}", self.localized())]
+    ProviderChecksFailed(usize),
+    #[error("{}", self.localized())]
+    BulkEditError(String),
+    #[error("{}", self.localized())]
+    TranscriptError(String),
+    #[error("{}", self.localized())]
+    ChapterError(String),
+    #[error("{}", self.localized())]
+    NotesError(String),
+    #[error("{}", self.localized())]
+    ApprovalError(String),
+    #[error("{}", self.localized())]
+    NotAllowed(String),
+    #[error("{}", self.localized())]
+    ChecklistFailed(String),
+    #[error("{}", self.localized())]
+    StorageConfig(String),
+    #[error("{}", self.localized())]
+    ArtError(String),
+    #[error("{}", self.localized())]
+    BundleError(String),
+    #[error("{}", self.localized())]
+    InvalidUrl(String, String, String),
+    #[error("{}", self.localized())]
+    CdnNotServing(String),
+    #[error("{}", self.localized())]
+    NotDraft(String),
+    #[error("{}", self.localized())]
+    NoSeasonEpisodes(u64),
+    #[error("{}", self.localized())]
+    EditorFailed(String, String),
+    #[error("{}", self.localized())]
+    PlaceholderText(String),
+    #[error("{}", self.localized())]
+    PipelineError(String),
+    #[error("{}", self.localized())]
+    ConfigOverride(String),
+    #[error("{}", self.localized())]
+    InvalidDate(String, String),
+    #[error("{}", self.localized())]
+    UnknownTimezone(String),
+    #[error("{}", self.localized())]
+    EpisodeNumberTaken(u64, u64, String),
+    #[error("{}", self.localized())]
+    NameTemplate(String),
+    #[error("{}", self.localized())]
+    ImportFailed(usize),
+    #[error("{}", self.localized())]
+    ArtworkRejected(String, String),
+    #[error("{}", self.localized())]
+    AudioQuality(String, String),
+    #[error("{}", self.localized())]
+    NoInbox,
+    #[error("{}", self.localized())]
+    EmailRejected(String),
+    #[error("{}", self.localized())]
+    UnknownDestination(String),
+    #[error("{}", self.localized())]
+    NoPodping,
+    #[error("{}", self.localized())]
+    HookFailed(String, String),
+    #[error("{}", self.localized())]
+    NotPrivate,
+    #[error("{}", self.localized())]
+    GeoipError(String),
+    #[error("{}", self.localized())]
+    BackupError(String),
+    #[error("{}", self.localized())]
+    CsvImport(String),
+    #[error("{}", self.localized())]
+    UnsetVariables(String),
+    #[error("{}", self.localized())]
+    WorkspaceFile(String),
+    #[error("{}", self.localized())]
+    ChannelsFailed(String),
+    #[error("{}", self.localized())]
+    IndexError(String),
+    #[error("{}", self.localized())]
+    TomlError(String),
+    #[error("{}", self.localized())]
+    ConvertConfig(String),
+    #[error("{}", self.localized())]
+    GuidStrategy(String),
+    #[error("{}", self.localized())]
+    TagError(String),
+    #[error("{}", self.localized())]
+    PurgeAnalytics(String),
+    #[error("{}", self.localized())]
+    Offline(&'static str),
+    #[error("{}", self.localized())]
+    Unknown,
+}
+
+impl<E> From<SdkError<E, HttpResponse>> for CliError
+where
+    E: ProvideErrorMetadata + std::error::Error + 'static,
+{
+    fn from(error: SdkError<E, HttpResponse>) -> Self {
+        let retryable = match &error {
+            SdkError::TimeoutError(_)
+            | SdkError::DispatchFailure(_)
+            | SdkError::ResponseError(_) => true,
+            SdkError::ServiceError(service) => {
+                let status = service.raw().status().as_u16();
+                status == 429
+                    || status >= 500
+                    || matches!(
+                        service.err().code(),
+                        Some("RequestTimeout") | Some("SlowDown") | Some("InternalError")
+                    )
+            }
+            _ => false,
+        };
+        let detail = DisplayErrorContext(&error).to_string();
+        if retryable {
+            CliError::StorageUnavailable(detail)
+        } else {
+            CliError::StorageRejected(detail)
+        }
+    }
+}
+
+impl CliError {
+    /// Whether trying again later might succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, CliError::StorageUnavailable(_))
+    }
+
+    /// The status the process exits with: an external subcommand's own,
+    /// or 1.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CliError::PluginExited(_, code) => *code,
+            _ => 1,
+        }
+    }
+
+    /// The error in the selected language. Errors from libraries stay in
+    /// English.
+    pub fn localized(&self) -> String {
+        match self {
+            CliError::StorageRejected(detail) => {
+                t!("error-storage-rejected", detail = detail.as_str())
+            }
+            CliError::StorageUnavailable(detail) => {
+                t!("error-storage-unavailable", detail = detail.as_str())
+            }
+            CliError::Mp3Error(detail) => t!("error-mp3", detail = detail.as_str()),
+            CliError::AudioError(format, detail) => {
+                t!("error-audio", format = *format, detail = detail.as_str())
+            }
+            CliError::PluginError(plugin, detail) => {
+                t!("error-plugin", plugin = plugin.as_str(), detail = detail.as_str())
+            }
+            CliError::PluginExited(plugin, code) => {
+                t!("error-plugin-exited", plugin = plugin.as_str(), code = *code)
+            }
+            CliError::WasmError(detail) => t!("error-wasm", detail = detail.as_str()),
+            CliError::BrokenLinks(count) => t!("error-broken-links", count = *count),
+            CliError::HttpStatus(url, status) => {
+                t!("error-http-status", url = url.as_str(), status = *status)
+            }
+            CliError::FeedMismatch(count) => t!("error-feed-mismatch", count = *count),
+            CliError::MissingEnvVar(name) => t!("error-missing-env", name = name.as_str()),
+            CliError::SummarizeError(detail) => t!("error-summarize", detail = detail.as_str()),
+            CliError::EpisodeNotFound(selector) => {
+                t!("error-episode-not-found", selector = selector.as_str())
+            }
+            CliError::AmbiguousEpisode(selector, episodes) => t!(
+                "error-ambiguous-episode",
+                selector = selector.as_str(),
+                episodes = episodes.as_str()
+            ),
+            CliError::ClipError(detail) => t!("error-clip", detail = detail.as_str()),
+            CliError::PromoError(detail) => t!("error-promo", detail = detail.as_str()),
+            CliError::WorkspaceLocked(workspace, pid) => t!(
+                "error-workspace-locked",
+                workspace = workspace.as_str(),
+                pid = pid.as_str()
+            ),
+            CliError::ChannelExists(path) => t!("error-channel-exists", path = path.as_str()),
+            CliError::TrashError(detail) => t!("error-trash", detail = detail.as_str()),
+            CliError::CryptoError(detail) => t!("error-crypto", detail = detail.as_str()),
+            CliError::ValidationFailed(count) => t!("error-validation-failed", count = *count),
+            CliError::ArchiveDamaged(count) => t!("error-archive-damaged", count = *count),
+            CliError::RemoteMismatch(count) => t!("error-remote-mismatch", count = *count),
+            CliError::ProviderChecksFailed(count) => {
+                t!("error-provider-checks-failed", count = *count)
+            }
+            CliError::BulkEditError(detail) => t!("error-bulk-edit", detail = detail.as_str()),
+            CliError::TranscriptError(detail) => t!("error-transcript", detail = detail.as_str()),
+            CliError::ChapterError(detail) => t!("error-chapters", detail = detail.as_str()),
+            CliError::NotesError(detail) => t!("error-notes", detail = detail.as_str()),
+            CliError::ApprovalError(detail) => t!("error-approval", detail = detail.as_str()),
+            CliError::NotAllowed(detail) => t!("error-not-allowed", detail = detail.as_str()),
+            CliError::ChecklistFailed(items) => t!("error-checklist", items = items.as_str()),
+            CliError::StorageConfig(detail) => {
+                t!("error-storage-config", detail = detail.as_str())
+            }
+            CliError::ArtError(detail) => t!("error-art", detail = detail.as_str()),
+            CliError::BundleError(detail) => t!("error-bundle", detail = detail.as_str()),
+            CliError::InvalidUrl(field, url, detail) => t!(
+                "error-invalid-url",
+                field = field.as_str(),
+                url = url.as_str(),
+                detail = detail.as_str()
+            ),
+            CliError::CdnNotServing(detail) => {
+                t!("error-cdn-not-serving", detail = detail.as_str())
+            }
+            CliError::NotDraft(id) => t!("error-not-draft", episode = id.as_str()),
+            CliError::NoSeasonEpisodes(season) => t!("error-no-season-episodes", season = *season),
+            CliError::EditorFailed(editor, detail) => {
+                t!("error-editor-failed", editor = editor.as_str(), detail = detail.as_str())
+            }
+            CliError::PlaceholderText(detail) => {
+                t!("error-placeholder-text", detail = detail.as_str())
+            }
+            CliError::PipelineError(detail) => t!("error-pipeline", detail = detail.as_str()),
+            CliError::ConfigOverride(detail) => {
+                t!("error-config-override", detail = detail.as_str())
+            }
+            CliError::InvalidDate(date, detail) => {
+                t!("error-invalid-date", date = date.as_str(), detail = detail.as_str())
+            }
+            CliError::UnknownTimezone(name) => t!("error-unknown-timezone", name = name.as_str()),
+            CliError::EpisodeNumberTaken(season, number, title) => t!(
+                "error-episode-number-taken",
+                season = *season,
+                number = *number,
+                title = title.as_str()
+            ),
+            CliError::NameTemplate(detail) => t!("error-name-template", detail = detail.as_str()),
+            CliError::ImportFailed(count) => t!("error-import-failed", count = *count),
+            CliError::ArtworkRejected(path, problems) => {
+                t!("error-artwork-rejected", path = path.as_str(), problems = problems.as_str())
+            }
+            CliError::AudioQuality(path, problems) => t!(
+                "error-audio-quality",
+                path = path.as_str(),
+                problems = problems.as_str()
+            ),
+            CliError::NoInbox => t!("error-no-inbox"),
+            CliError::EmailRejected(detail) => t!("error-email-rejected", detail = detail.as_str()),
+            CliError::UnknownDestination(name) => {
+                t!("error-unknown-destination", name = name.as_str())
+            }
+            CliError::NoPodping => t!("error-no-podping"),
+            CliError::HookFailed(hook, detail) => {
+                t!("error-hook-failed", hook = hook.as_str(), detail = detail.as_str())
+            }
+            CliError::NotPrivate => t!("error-not-private"),
+            CliError::GeoipError(detail) => t!("error-geoip", detail = detail.as_str()),
+            CliError::BackupError(detail) => t!("error-backup", detail = detail.as_str()),
+            CliError::CsvImport(detail) => t!("error-csv-import", detail = detail.as_str()),
+            CliError::UnsetVariables(names) => t!("error-unset-variables", names = names.as_str()),
+            CliError::WorkspaceFile(detail) => t!("error-workspace-file", detail = detail.as_str()),
+            CliError::ChannelsFailed(names) => t!("error-channels-failed", names = names.as_str()),
+            CliError::IndexError(detail) => t!("error-index", detail = detail.as_str()),
+            CliError::TomlError(detail) => t!("error-toml", detail = detail.as_str()),
+            CliError::ConvertConfig(detail) => t!("error-convert-config", detail = detail.as_str()),
+            CliError::GuidStrategy(detail) => t!("error-guid-strategy", detail = detail.as_str()),
+            CliError::TagError(detail) => t!("error-tag", detail = detail.as_str()),
+            CliError::PurgeAnalytics(detail) => {
+                t!("error-purge-analytics", detail = detail.as_str())
+            }
+            CliError::Offline(detail) => t!("error-offline", detail = *detail),
+            CliError::Unknown => t!("error-unknown"),
+            CliError::IoError(e) => e.to_string(),
+            CliError::YamlError(e) => e.to_string(),
+            CliError::XmlError(e) => e.to_string(),
+            CliError::ChronoError(e) => e.to_string(),
+            CliError::JsonError(e) => e.to_string(),
+            CliError::HttpError(e) => e.to_string(),
+            CliError::XmlParseError(e) => e.to_string(),
+        }
+    }
+}
+
+/// channel.yaml's `text`, in `format`, with its environment variables
+/// filled in and `overrides` applied.
+pub(crate) fn parse_channel_config(
+    format: formats::Format,
+    text: &str,
+    overrides: &[bulk::Assignment],
+) -> Result<ChannelConfig, CliError> {
+    if overrides.is_empty() && !text.contains("${") {
+        return format.parse(text.as_bytes());
+    }
+    let mut value = format.parse_value(text.as_bytes())?;
+    interpolate::interpolate(&mut value)?;
+    if overrides.is_empty() {
+        return Ok(serde_yaml::from_value(value)?);
+    }
+    bulk::override_config(&mut value, overrides)?;
+    serde_yaml::from_value(value).map_err(|e| CliError::ConfigOverride(e.to_string()))
+}
+
+/// Write `episode` back to its file, keeping a YAML file's comments and
+/// layout, encrypted again when it was.
+pub(crate) fn save_episode(
+    path: &Path,
+    episode: &Episode,
+    channel_config: &ChannelConfig,
+) -> Result<(), CliError> {
+    let text = formats::updated_text(path, episode)?;
+    crypto::write(path, text.as_bytes(), channel_config)
+}
+
+/// Show how saving `episode` would change its file, and save it when
+/// `yes` is set or the user agrees. Returns whether it was saved.
+pub(crate) fn save_episode_confirmed(
+    path: &Path,
+    episode: &Episode,
+    channel_config: &ChannelConfig,
+    yes: bool,
+) -> Result<bool, CliError> {
+    let before = String::from_utf8_lossy(&crypto::read(path)?).into_owned();
+    let after = formats::updated_text(path, episode)?;
+    if !diff::print_diff(path, &before, &after) {
+        return Ok(false);
+    }
+    if !diff::confirm(&t!("confirm-write"), yes)? {
+        outln!("{}", t!("nothing-written"));
+        return Ok(false);
+    }
+    crypto::write(path, after.as_bytes(), channel_config)?;
+    Ok(true)
+}
+
+/// Every episode in `episode_dir`.
+pub fn get_all_episodes(episode_dir: &Path) -> Result<Vec<Episode>, CliError> {
+    Ok(get_all_episode_files(episode_dir)?
+        .into_iter()
+        .map(|(_, episode)| episode)
+        .collect())
+}
+
+/// Every episode in `episode_dir`, with the file it was read from.
+pub(crate) fn get_all_episode_files(episode_dir: &Path) -> Result<Vec<(PathBuf, Episode)>, CliError> {
+    let mut episodes: Vec<(PathBuf, Episode)> = Vec::new();
+
+    for path in get_episode_paths(episode_dir)? {
+        if !crypto::readable(&path) {
+            continue;
+        }
+        let episode = parse_episode(&path, &crypto::read(&path)?, None)?;
+        episodes.push((path, episode));
+    }
+
+    Ok(episodes)
+}
+
+/// Parse an episode file. With `placeholders`, missing optional fields are
+/// filled from the channel instead of failing.
+pub(crate) fn parse_episode(
+    path: &Path,
+    contents: &[u8],
+    placeholders: Option<&ChannelDetails>,
+) -> Result<Episode, CliError> {
+    let format = formats::Format::of(path);
+    let channel = match placeholders {
+        Some(channel) => channel,
+        None => return format.parse(contents),
+    };
+
+    let mut value = format.parse_value(contents)?;
+    for field in validate::fill_placeholders(&mut value, channel) {
+        events::warn(t!(
+            "placeholder-used",
+            path = path.display().to_string(),
+            field = field
+        ));
+    }
+    Ok(serde_yaml::from_value(value)?)
+}
+
+/// The episode files in `episode_dir`, encrypted or not.
+pub(crate) fn get_episode_paths(episode_dir: &Path) -> Result<Vec<PathBuf>, CliError> {
+    let mut paths = Vec::new();
+
+    for path in fs::read_dir(episode_dir)? {
+        paths.push(path?.path());
+    }
+
+    let paths = formats::episode_files(paths);
+    for path in &paths {
+        debug!("Found episode {:?}", path);
+    }
+    Ok(paths)
+}
+
+/// Find the episode `selector` picks, as described in `selector`.
+/// Release dates are in `timezone`, the channel's.
+pub(crate) fn find_episode(
+    episode_dir: &Path,
+    selector: &str,
+    timezone: Tz,
+) -> Result<(PathBuf, Episode), CliError> {
+    selector::select(
+        get_all_episode_files(episode_dir)?,
+        selector,
+        timezone,
+        |(path, episode)| {
+            let name = crypto::plain_path(path)
+                .file_stem()
+                .unwrap_or_default()
+                .to_owned();
+            (episode, name.to_string_lossy().into_owned())
+        },
+    )
+}
@@ -0,0 +1,294 @@
+use crate::config::*;
+use crate::http::{HttpClient, HttpResponse};
+use crate::i18n::t;
+use crate::output::{self, outln};
+use crate::CliError;
+use futures::{stream, StreamExt};
+use reqwest::StatusCode;
+use serde::Serialize;
+
+/// What `check-links` found for one URL, for `--output json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckedLink {
+    label: String,
+    url: String,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl CheckedLink {
+    fn new(label: String, url: String) -> Self {
+        CheckedLink {
+            label,
+            url,
+            ok: false,
+            status: None,
+            content_type: None,
+            bytes: None,
+            error: None,
+        }
+    }
+}
+
+/// HEAD every URL the feed references and report the ones that don't
+/// resolve to a successful response.
+pub async fn check_links(
+    channel_config: &ChannelConfig,
+    episodes: &[Episode],
+    client: &HttpClient,
+) -> Result<(), CliError> {
+    let mut targets = vec![(
+        t!("link-channel-image"),
+        channel_config.channel.image.clone(),
+    )];
+    if let Some(link) = &channel_config.channel.link {
+        targets.push((t!("link-channel-link"), link.clone()));
+    }
+
+    for episode in episodes {
+        let title = episode.title.as_str();
+        targets.push((
+            t!("link-episode-media", title = title),
+            episode.media.url.clone(),
+        ));
+        targets.push((
+            t!("link-episode-image", title = title),
+            episode.image.clone(),
+        ));
+        if let Some(link) = &episode.link {
+            targets.push((t!("link-episode-link", title = title), link.clone()));
+        }
+    }
+
+    let mut results: Vec<_> = stream::iter(targets)
+        .map(|(label, url)| async move {
+            let result = check_url(client, &url).await;
+            (label, url, result)
+        })
+        .buffer_unordered(client.concurrency())
+        .collect()
+        .await;
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut broken = 0;
+    let mut links = Vec::new();
+    for (label, url, result) in results {
+        match result {
+            Ok(response) if response.status.is_success() => {
+                let content_type = match &response.content_type {
+                    Some(content_type) => content_type.clone(),
+                    None => t!("link-unknown-type"),
+                };
+                let message = match response.content_length {
+                    Some(length) => t!(
+                        "link-ok-size",
+                        label = label.as_str(),
+                        url = url.as_str(),
+                        kind = content_type,
+                        bytes = length
+                    ),
+                    None => t!(
+                        "link-ok",
+                        label = label.as_str(),
+                        url = url.as_str(),
+                        kind = content_type
+                    ),
+                };
+                outln!("{}", message);
+                links.push(CheckedLink {
+                    ok: true,
+                    status: Some(response.status.as_u16()),
+                    content_type: response.content_type,
+                    bytes: response.content_length,
+                    ..CheckedLink::new(label, url)
+                });
+            }
+            Ok(response) => {
+                broken += 1;
+                let status = response.status.to_string();
+                outln!(
+                    "{}",
+                    t!(
+                        "link-broken",
+                        label = label.as_str(),
+                        url = url.as_str(),
+                        detail = status
+                    )
+                );
+                links.push(CheckedLink {
+                    status: Some(response.status.as_u16()),
+                    ..CheckedLink::new(label, url)
+                });
+            }
+            Err(e) => {
+                broken += 1;
+                let detail = e.localized();
+                outln!(
+                    "{}",
+                    t!(
+                        "link-broken",
+                        label = label.as_str(),
+                        url = url.as_str(),
+                        detail = detail
+                    )
+                );
+                links.push(CheckedLink {
+                    error: Some(e.localized()),
+                    ..CheckedLink::new(label, url)
+                });
+            }
+        }
+    }
+    output::record("links", &links);
+
+    if broken > 0 {
+        return Err(CliError::BrokenLinks(broken));
+    }
+
+    Ok(())
+}
+
+/// What `verify-remote` found for one object, for `--output json`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RemoteObject<'a> {
+    episode: &'a str,
+    object: &'a str,
+    url: &'a str,
+    /// `ok`, `mismatch`, `unknown`, or `missing`
+    state: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<u64>,
+    /// The size the episode file gives
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expected: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// HEAD every episode's media and image, and check that storage still has
+/// the media at the size the episode file gives. Unlike `check_links`, this
+/// is about what was uploaded, so links to other sites are left out.
+pub async fn verify_remote(episodes: &[Episode], client: &HttpClient) -> Result<(), CliError> {
+    let mut targets = Vec::new();
+    for episode in episodes {
+        targets.push((episode, "media", &episode.media.url, Some(episode.media.bytes.0)));
+        targets.push((episode, "image", &episode.image, None));
+    }
+
+    let results: Vec<_> = stream::iter(targets)
+        .map(|(episode, what, url, bytes)| async move {
+            let result = check_url(client, url).await;
+            (episode, what, url, bytes, result)
+        })
+        .buffered(client.concurrency())
+        .collect()
+        .await;
+
+    let mut problems = 0;
+    let mut objects = Vec::new();
+    for (episode, what, url, bytes, result) in results {
+        let label = format!("{} {}", episode.id, what);
+        let mut object = RemoteObject {
+            episode: &episode.id,
+            object: what,
+            url,
+            state: "ok",
+            bytes: None,
+            expected: bytes,
+            status: None,
+            error: None,
+        };
+        match result {
+            Ok(response) if response.status.is_success() => {
+                object.bytes = response.content_length;
+                match (bytes, response.content_length) {
+                    (Some(expected), Some(length)) if expected != length => {
+                        problems += 1;
+                        object.state = "mismatch";
+                        outln!(
+                            "{}",
+                            t!(
+                                "remote-mismatch",
+                                label = label.as_str(),
+                                url = url.as_str(),
+                                bytes = length,
+                                expected = expected
+                            )
+                        );
+                    }
+                    (Some(_), None) => {
+                        object.state = "unknown";
+                        outln!(
+                            "{}",
+                            t!("remote-unknown", label = label.as_str(), url = url.as_str())
+                        )
+                    }
+                    _ => outln!(
+                        "{}",
+                        t!("remote-ok", label = label.as_str(), url = url.as_str())
+                    ),
+                }
+            }
+            Ok(response) => {
+                problems += 1;
+                object.state = "missing";
+                object.status = Some(response.status.as_u16());
+                let status = response.status.to_string();
+                outln!(
+                    "{}",
+                    t!(
+                        "remote-missing",
+                        label = label.as_str(),
+                        url = url.as_str(),
+                        detail = status
+                    )
+                );
+            }
+            Err(e) => {
+                problems += 1;
+                object.state = "missing";
+                object.error = Some(e.localized());
+                let detail = e.localized();
+                outln!(
+                    "{}",
+                    t!(
+                        "remote-missing",
+                        label = label.as_str(),
+                        url = url.as_str(),
+                        detail = detail
+                    )
+                );
+            }
+        }
+        objects.push(object);
+    }
+    output::record("objects", &objects);
+
+    if problems > 0 {
+        return Err(CliError::RemoteMismatch(problems));
+    }
+
+    Ok(())
+}
+
+/// HEAD `url`, falling back to fetching one byte for servers that refuse HEAD.
+pub async fn check_url(client: &HttpClient, url: &str) -> Result<HttpResponse, CliError> {
+    let response = client.head(url).await?;
+    match response.status {
+        StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED | StatusCode::FORBIDDEN => {
+            client.probe(url).await
+        }
+        _ => Ok(response),
+    }
+}
@@ -0,0 +1,95 @@
+//! Content lint for `validate`: the channel's and episodes' titles and
+//! show notes checked against wordlists in channel.yaml, for shows with a
+//! sponsor's or network's content policy or a house style:
+//!
+//! ```yaml
+//! lint:
+//!   blocked: [competitor, "rival network"]
+//!   replace:
+//!     guys: folks
+//!     e-mail: email
+//! ```
+//!
+//! Blocked terms are errors, and terms with a replacement are warnings.
+
+use crate::config::{ChannelDetails, Episode, LintConfig};
+use crate::validate::{words, Issue, Severity};
+use crate::CliError;
+use std::path::PathBuf;
+
+/// Issues with the channel's and episodes' text, and their show notes.
+pub fn check(
+    config: &LintConfig,
+    channel: &ChannelDetails,
+    episodes: &[(PathBuf, Episode)],
+) -> Result<Vec<Issue>, CliError> {
+    let mut issues = Vec::new();
+    if config.blocked.is_empty() && config.replace.is_empty() {
+        return Ok(issues);
+    }
+
+    for (field, value) in [
+        ("title", &channel.title),
+        ("description", &channel.description),
+        ("summary", &channel.summary),
+    ] {
+        for (severity, message) in lint(config, &format!("channel {}", field), value) {
+            issues.push(Issue {
+                severity,
+                episodes: Vec::new(),
+                message,
+            });
+        }
+    }
+
+    for (path, episode) in episodes {
+        let notes = crate::notes::load(path, episode)?;
+        let mut fields = vec![
+            ("title", episode.title.as_str()),
+            ("description", episode.description.as_str()),
+            ("summary", episode.summary.as_str()),
+        ];
+        if let Some(notes) = &notes {
+            fields.push(("show notes", notes.as_str()));
+        }
+        for (field, value) in fields {
+            for (severity, message) in lint(config, field, value) {
+                issues.push(Issue {
+                    severity,
+                    episodes: vec![episode.id.clone()],
+                    message,
+                });
+            }
+        }
+    }
+    Ok(issues)
+}
+
+/// What the wordlists say about `value`.
+fn lint(config: &LintConfig, field: &str, value: &str) -> Vec<(Severity, String)> {
+    let text = words(value);
+    let found = |term: &str| {
+        let term = words(term);
+        !term.trim().is_empty() && text.contains(&term)
+    };
+
+    let mut problems = Vec::new();
+    for term in config.blocked.iter().filter(|term| found(term)) {
+        problems.push((
+            Severity::Error,
+            format!("{} contains '{}', which is blocked", field, term.trim()),
+        ));
+    }
+    for (term, replacement) in config.replace.iter().filter(|(term, _)| found(term)) {
+        problems.push((
+            Severity::Warning,
+            format!(
+                "{} says '{}', house style is '{}'",
+                field,
+                term.trim(),
+                replacement
+            ),
+        ));
+    }
+    problems
+}
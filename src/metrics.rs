@@ -0,0 +1,120 @@
+//! Metrics about each `render-channel --upload`, for monitoring publishes
+//! run from automation. They are sent to statsd, a Prometheus Pushgateway,
+//! or both, as `metrics` in channel.yaml says:
+//!
+//! ```yaml
+//! metrics:
+//!   statsd: localhost:8125
+//!   pushgateway: http://pushgateway:9091
+//!   prefix: podcastctl
+//! ```
+//!
+//! Sending them is best effort: a publish that worked isn't failed because
+//! its metrics couldn't be sent.
+
+use crate::config::MetricsConfig;
+use crate::events;
+use crate::http::HttpClient;
+use crate::i18n::t;
+use reqwest::Method;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+const DEFAULT_PREFIX: &str = "podcastctl";
+
+/// How a publish went.
+pub struct PublishMetrics {
+    pub duration: Duration,
+    pub objects_uploaded: usize,
+    pub bytes_uploaded: u64,
+    /// Episodes in the feed that weren't in the one published before
+    pub episodes_added: usize,
+    pub validation_warnings: usize,
+}
+
+impl PublishMetrics {
+    /// Everything but the duration, by name without the prefix.
+    fn counts(&self) -> [(&'static str, u64); 4] {
+        [
+            ("publish_objects_uploaded", self.objects_uploaded as u64),
+            ("publish_bytes_uploaded", self.bytes_uploaded),
+            ("publish_episodes_added", self.episodes_added as u64),
+            (
+                "publish_validation_warnings",
+                self.validation_warnings as u64,
+            ),
+        ]
+    }
+}
+
+/// Send `metrics` everywhere `config` says, warning about what fails.
+pub async fn emit(config: &MetricsConfig, client: &HttpClient, metrics: &PublishMetrics) {
+    let prefix = config.prefix.as_deref().unwrap_or(DEFAULT_PREFIX);
+    if let Some(address) = &config.statsd {
+        if let Err(e) = send_statsd(address, prefix, metrics) {
+            let detail = format!("statsd {}: {}", address, e);
+            events::warn(t!("metrics-failed", detail = detail));
+        }
+    }
+    if let Some(url) = &config.pushgateway {
+        if let Err(e) = push(client, url, prefix, metrics).await {
+            let detail = format!("Pushgateway {}: {}", url, e);
+            events::warn(t!("metrics-failed", detail = detail));
+        }
+    }
+}
+
+/// statsd gauges, with the duration as a timer in milliseconds.
+fn send_statsd(address: &str, prefix: &str, metrics: &PublishMetrics) -> std::io::Result<()> {
+    let mut lines = vec![format!(
+        "{}.publish_duration:{}|ms",
+        prefix,
+        metrics.duration.as_millis()
+    )];
+    lines.extend(
+        metrics
+            .counts()
+            .iter()
+            .map(|(name, value)| format!("{}.{}:{}|g", prefix, name, value)),
+    );
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.send_to(lines.join("\n").as_bytes(), address)?;
+    Ok(())
+}
+
+/// The metrics in the Prometheus text format, replacing the last ones
+/// pushed for the `prefix` job.
+async fn push(
+    client: &HttpClient,
+    url: &str,
+    prefix: &str,
+    metrics: &PublishMetrics,
+) -> Result<(), String> {
+    let mut values = vec![(
+        "publish_duration_seconds",
+        metrics.duration.as_secs_f64().to_string(),
+    )];
+    values.extend(
+        metrics
+            .counts()
+            .iter()
+            .map(|(name, value)| (*name, value.to_string())),
+    );
+    let mut body = String::new();
+    for (name, value) in values {
+        body.push_str(&format!("# TYPE {}_{} gauge\n", prefix, name));
+        body.push_str(&format!("{}_{} {}\n", prefix, name, value));
+    }
+
+    let url = format!("{}/metrics/job/{}", url.trim_end_matches('/'), prefix);
+    let response = client
+        .send(&url, || {
+            client.request(Method::PUT, &url).body(body.clone())
+        })
+        .await
+        .map_err(|e| e.localized())?;
+    if !response.status.is_success() {
+        return Err(format!("HTTP {}", response.status));
+    }
+    Ok(())
+}
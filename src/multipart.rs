@@ -0,0 +1,330 @@
+//! S3 multipart uploads for large files, with parts sent in parallel.
+//!
+//! When the caller gives a directory to keep progress in, an interrupted
+//! upload is left in storage and the next upload of the same key picks it
+//! back up: the parts storage already has are listed, and those whose MD5
+//! matches the local data are skipped instead of being sent again.
+
+use crate::config::{MultipartConfig, ObjectOptionsConfig, RetryConfig};
+use crate::events;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::output::Progress;
+use crate::upload::{encryption, retry, storage_class};
+use crate::CliError;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart, ObjectCannedAcl};
+use aws_sdk_s3::Client;
+use bytes::Bytes;
+use futures::{stream, StreamExt, TryStreamExt};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// S3 refuses parts smaller than this, except the last.
+const MIN_PART_SIZE: u64 = 5 * 1024 * 1024;
+const MAX_PARTS: u64 = 10_000;
+
+/// An upload in progress, saved so it can resume.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SavedUpload {
+    upload_id: String,
+    bucket: String,
+    key: String,
+    size: u64,
+    part_size: u64,
+}
+
+pub struct Target<'a> {
+    pub client: &'a Client,
+    pub bucket: &'a str,
+    pub key: &'a str,
+    pub content_type: &'a str,
+    pub cache_control: Option<&'a str>,
+    pub content_encoding: Option<&'a str>,
+    pub options: &'a ObjectOptionsConfig,
+    pub acl: Option<ObjectCannedAcl>,
+    pub retry: &'a RetryConfig,
+}
+
+/// Upload `read` in parts, returning the ETag of the whole object.
+pub async fn upload<R>(
+    target: Target<'_>,
+    mut read: R,
+    size: u64,
+    config: &MultipartConfig,
+    resume_dir: Option<&Path>,
+    progress: &mut Progress,
+) -> Result<Option<String>, CliError>
+where
+    R: AsyncRead + Unpin,
+{
+    let part_size = part_size(config, size);
+    let saved_path = resume_dir.map(|dir| dir.join(saved_name(target.key)));
+    let resumed = match &saved_path {
+        Some(path) => resume(&target, path, size, part_size).await,
+        None => None,
+    };
+    let (upload_id, done) = match resumed {
+        Some(resumed) => resumed,
+        None => {
+            let upload_id = create(&target).await?;
+            if let Some(path) = &saved_path {
+                save(path, &target, &upload_id, size, part_size)?;
+            }
+            (upload_id, HashMap::new())
+        }
+    };
+
+    let mut results =
+        match send_parts(&target, &upload_id, &mut read, size, config, done, progress).await {
+            Ok(results) => results,
+            Err(e) => {
+                // Without a place to resume from, the parts already sent only
+                // take up space
+                if saved_path.is_some() {
+                    events::warn(t!("upload-interrupted", key = target.key));
+                } else {
+                    abort(&target, &upload_id).await;
+                }
+                return Err(e);
+            }
+        };
+
+    results.sort_by_key(|part| part.part_number());
+    let completed = CompletedMultipartUpload::builder()
+        .set_parts(Some(results))
+        .build();
+    let output = retry(target.retry, target.key, || {
+        target
+            .client
+            .complete_multipart_upload()
+            .bucket(target.bucket)
+            .key(target.key)
+            .upload_id(&upload_id)
+            .multipart_upload(completed.clone())
+            .send()
+    })
+    .await?;
+
+    if let Some(path) = &saved_path {
+        if let Err(e) = fs::remove_file(path) {
+            warn!("Unable to remove {}: {}", path.display(), e);
+        }
+    }
+    Ok(output.e_tag)
+}
+
+/// Read the parts in order and upload those storage doesn't have yet,
+/// `concurrency` at a time.
+async fn send_parts<R>(
+    target: &Target<'_>,
+    upload_id: &str,
+    read: &mut R,
+    size: u64,
+    config: &MultipartConfig,
+    mut done: HashMap<i32, String>,
+    progress: &mut Progress,
+) -> Result<Vec<CompletedPart>, CliError>
+where
+    R: AsyncRead + Unpin,
+{
+    let part_size = part_size(config, size);
+    let parts = (size + part_size - 1) / part_size;
+    info!(
+        "Uploading {} in {} part(s) of up to {} bytes",
+        target.key, parts, part_size
+    );
+
+    let concurrency = config.concurrency.max(1);
+    let mut buffers = Vec::new();
+    let mut results = Vec::new();
+    for part_number in 1..=parts {
+        let length = part_size.min(size - (part_number - 1) * part_size);
+        let mut buffer = vec![0; length as usize];
+        read.read_exact(&mut buffer).await?;
+
+        let md5 = format!("{:x}", md5::compute(&buffer));
+        match done.remove(&(part_number as i32)) {
+            Some(etag) if etag.trim_matches('"') == md5 => {
+                debug!("Part {} of {} is already uploaded", part_number, target.key);
+                progress.add(length);
+                results.push(completed(part_number as i32, etag));
+            }
+            _ => buffers.push((part_number as i32, Bytes::from(buffer))),
+        }
+
+        if buffers.len() >= concurrency || part_number == parts {
+            let sent: Vec<CompletedPart> = stream::iter(buffers.drain(..))
+                .map(|(part_number, buffer)| upload_part(target, upload_id, part_number, buffer))
+                .buffer_unordered(concurrency)
+                .inspect_ok(|(_, length)| progress.add(*length))
+                .map_ok(|(part, _)| part)
+                .try_collect()
+                .await?;
+            results.extend(sent);
+        }
+    }
+    Ok(results)
+}
+
+/// At least the configured size, but large enough to stay under the part
+/// limit.
+fn part_size(config: &MultipartConfig, size: u64) -> u64 {
+    let configured = (config.part_size_mb * 1024 * 1024).max(MIN_PART_SIZE);
+    configured.max((size + MAX_PARTS - 1) / MAX_PARTS)
+}
+
+fn saved_name(key: &str) -> String {
+    format!("{}.json", key.replace('/', "_"))
+}
+
+async fn create(target: &Target<'_>) -> Result<String, CliError> {
+    let output = retry(target.retry, target.key, || {
+        target
+            .client
+            .create_multipart_upload()
+            .bucket(target.bucket)
+            .key(target.key)
+            .set_acl(target.acl.clone())
+            .content_type(target.content_type)
+            .set_cache_control(target.cache_control.map(str::to_owned))
+            .set_content_encoding(target.content_encoding.map(str::to_owned))
+            .set_storage_class(storage_class(target.options))
+            .set_server_side_encryption(encryption(target.options))
+            .set_ssekms_key_id(target.options.kms_key_id.clone())
+            .send()
+    })
+    .await?;
+    output
+        .upload_id
+        .ok_or_else(|| CliError::StorageRejected("storage did not return an upload ID".to_owned()))
+}
+
+/// The saved upload of this key and the ETags of the parts storage already
+/// has, when the saved upload is for the same data and still exists.
+async fn resume(
+    target: &Target<'_>,
+    path: &Path,
+    size: u64,
+    part_size: u64,
+) -> Option<(String, HashMap<i32, String>)> {
+    let saved: SavedUpload = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+    if saved.bucket != target.bucket || saved.key != target.key {
+        return None;
+    }
+    if saved.size != size || saved.part_size != part_size {
+        info!(
+            "{} changed since its upload was interrupted, starting over",
+            target.key
+        );
+        abort(target, &saved.upload_id).await;
+        return None;
+    }
+
+    let mut done = HashMap::new();
+    let mut pages = target
+        .client
+        .list_parts()
+        .bucket(target.bucket)
+        .key(target.key)
+        .upload_id(&saved.upload_id)
+        .into_paginator()
+        .send();
+    while let Some(page) = pages.next().await {
+        match page {
+            Ok(page) => {
+                for part in page.parts() {
+                    if let (Some(number), Some(etag)) = (part.part_number(), part.e_tag()) {
+                        done.insert(number, etag.to_owned());
+                    }
+                }
+            }
+            Err(e) => {
+                info!(
+                    "Unable to resume the upload of {}, starting over: {}",
+                    target.key,
+                    aws_sdk_s3::error::DisplayErrorContext(&e)
+                );
+                return None;
+            }
+        }
+    }
+
+    outln!(
+        "{}",
+        t!("upload-resuming", key = target.key, parts = done.len())
+    );
+    Some((saved.upload_id, done))
+}
+
+fn save(
+    path: &Path,
+    target: &Target<'_>,
+    upload_id: &str,
+    size: u64,
+    part_size: u64,
+) -> Result<(), CliError> {
+    let saved = SavedUpload {
+        upload_id: upload_id.to_owned(),
+        bucket: target.bucket.to_owned(),
+        key: target.key.to_owned(),
+        size,
+        part_size,
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_vec_pretty(&saved)?)?;
+    Ok(())
+}
+
+async fn upload_part(
+    target: &Target<'_>,
+    upload_id: &str,
+    part_number: i32,
+    buffer: Bytes,
+) -> Result<(CompletedPart, u64), CliError> {
+    let length = buffer.len() as u64;
+    let what = format!("part {} of {}", part_number, target.key);
+    let output = retry(target.retry, &what, || {
+        target
+            .client
+            .upload_part()
+            .bucket(target.bucket)
+            .key(target.key)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .content_length(length as i64)
+            .body(ByteStream::from(buffer.clone()))
+            .send()
+    })
+    .await?;
+    let etag = output.e_tag.unwrap_or_default();
+    Ok((completed(part_number, etag), length))
+}
+
+fn completed(part_number: i32, etag: String) -> CompletedPart {
+    CompletedPart::builder()
+        .part_number(part_number)
+        .e_tag(etag)
+        .build()
+}
+
+async fn abort(target: &Target<'_>, upload_id: &str) {
+    let aborted = target
+        .client
+        .abort_multipart_upload()
+        .bucket(target.bucket)
+        .key(target.key)
+        .upload_id(upload_id)
+        .send()
+        .await;
+    if let Err(e) = aborted {
+        debug!("Unable to abort the upload of {}: {}", target.key, e);
+    }
+}
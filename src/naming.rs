@@ -0,0 +1,107 @@
+//! What `create-episode` names a new episode's audio and episode file. By
+//! default both are named by the release date, as
+//! `artifacts/2022-09-01.mp3` and `2022-09-01-session.yaml`. With
+//! `nameTemplate`, both are named from the template instead:
+//!
+//! ```yaml
+//! publishing:
+//!   nameTemplate: "{date}-{slug}"
+//! ```
+//!
+//! names an episode called "The Lost Mine" `artifacts/2022-09-01-the-lost-mine.mp3`
+//! and `2022-09-01-the-lost-mine.yaml`. The template has `{date}`, the
+//! release date in the channel's timezone, `{slug}`, from the title, and
+//! `{season}` and `{episode}`; the extension is added to it.
+//!
+//! A name that is already another episode's file or media gets `-2`, `-3`,
+//! and so on, so two episodes released on the same day don't replace each
+//! other.
+
+use crate::config::{Episode, PublishingConfig};
+use crate::crypto;
+use crate::template;
+use crate::CliError;
+use std::path::{Path, PathBuf};
+
+/// Names of a new episode's audio and file.
+pub struct EpisodeNames {
+    pub object_key: String,
+    pub episode_file: PathBuf,
+}
+
+/// What the episode to be released on `date` is named.
+pub fn episode_names(
+    publishing: &PublishingConfig,
+    episode_dir: &Path,
+    episodes: &[Episode],
+    date: &str,
+    episode: (&str, u64, u64),
+    extension: &str,
+) -> Result<EpisodeNames, CliError> {
+    let (title, season, number) = episode;
+    let base = match &publishing.name_template {
+        Some(name_template) => {
+            // Titles with nothing to slug, like "???", are still named
+            let slug = Some(crate::init::slug(title))
+                .filter(|slug| !slug.is_empty())
+                .unwrap_or_else(|| "episode".to_owned());
+            let variables = [
+                ("date", date.to_owned()),
+                ("slug", slug),
+                ("season", season.to_string()),
+                ("episode", number.to_string()),
+            ];
+            let name = template::expand(name_template, &variables);
+            check_name(name_template, &name)?;
+            name
+        }
+        None => date.to_owned(),
+    };
+
+    let taken = |file_name: &str, media_name: &str| {
+        let episode_file = episode_dir.join(file_name);
+        episode_file.exists()
+            || crypto::encrypted_path(&episode_file).exists()
+            || episodes
+                .iter()
+                .any(|episode| episode.media.url.rsplit('/').next() == Some(media_name))
+    };
+    let mut attempt = 1;
+    loop {
+        let name = match attempt {
+            1 => base.clone(),
+            attempt => format!("{}-{}", base, attempt),
+        };
+        let format = crate::formats::new_episodes().extension();
+        let file_name = match publishing.name_template {
+            Some(_) => format!("{}.{}", name, format),
+            None => format!("{}-session.{}", name, format),
+        };
+        let media_name = format!("{}.{}", name, extension);
+        if !taken(&file_name, &media_name) {
+            return Ok(EpisodeNames {
+                object_key: format!("{}/artifacts/{}", publishing.prefix, media_name),
+                episode_file: episode_dir.join(file_name),
+            });
+        }
+        attempt += 1;
+    }
+}
+
+/// A name from `name_template` has to be a file name, with every
+/// placeholder filled in.
+fn check_name(name_template: &str, name: &str) -> Result<(), CliError> {
+    if name.contains('{') || name.contains('}') {
+        return Err(CliError::NameTemplate(format!(
+            "'{}' has a placeholder other than {{date}}, {{slug}}, {{season}}, or {{episode}}",
+            name_template
+        )));
+    }
+    if name.is_empty() || name.contains('/') || name.contains('\\') || name.starts_with('.') {
+        return Err(CliError::NameTemplate(format!(
+            "'{}' doesn't make a file name",
+            name_template
+        )));
+    }
+    Ok(())
+}
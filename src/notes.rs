@@ -0,0 +1,67 @@
+//! Long show notes, kept in a Markdown file next to the episode instead of
+//! its YAML, and published as `content:encoded` with a plain text
+//! `description` for apps that don't render HTML.
+
+use crate::config::Episode;
+use crate::CliError;
+use std::path::Path;
+
+/// Closing tags that end a line of text.
+const BLOCK_ENDS: [&str; 8] = [
+    "</p>",
+    "</li>",
+    "</h1>",
+    "</h2>",
+    "</h3>",
+    "</h4>",
+    "</blockquote>",
+    "</pre>",
+];
+
+/// The episode's show notes Markdown, when its YAML references a notes file.
+pub fn load(episode_path: &Path, episode: &Episode) -> Result<Option<String>, CliError> {
+    let notes = match &episode.notes {
+        Some(notes) => notes,
+        None => return Ok(None),
+    };
+
+    let episode_dir = episode_path.parent().unwrap_or_else(|| Path::new("."));
+    let path = episode_dir.join(notes);
+    let contents = crate::crypto::read(&path)
+        .map_err(|e| CliError::NotesError(format!("unable to read {}: {}", path.display(), e)))?;
+    Ok(Some(String::from_utf8_lossy(&contents).into_owned()))
+}
+
+pub fn to_html(markdown: &str) -> String {
+    comrak::markdown_to_html(markdown, &Default::default())
+}
+
+/// Markdown on one line of plain text, for fields like `itunes:subtitle`
+/// that apps show as typed: formatting is dropped and links keep their
+/// text.
+pub fn markdown_to_plain_text(markdown: &str) -> String {
+    plain_text(&to_html(markdown))
+        .lines()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Rendered notes as plain text, one line per paragraph or list item.
+pub fn plain_text(html: &str) -> String {
+    let mut text = html.replace("<br />", "\n");
+    for end in BLOCK_ENDS {
+        text = text.replace(end, &format!("{}\n", end));
+    }
+
+    let text = crate::transcript::strip_tags(&text)
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
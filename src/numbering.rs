@@ -0,0 +1,195 @@
+//! Season and episode numbers: what `create-episode` gives a new episode,
+//! and `renumber` for fixing up the numbers of existing episode files.
+//!
+//! A new episode is in the latest season, after its highest-numbered
+//! episode, unless a season starts by its release date as `seasons` in
+//! channel.yaml says. The first episode of a show is season 1, episode 1.
+//! With `seasons.numbering: global`, episodes are numbered after the
+//! highest-numbered episode of any season instead, and with `none`, they
+//! are numbered 0, which the feed leaves out.
+
+use crate::config::{ChannelConfig, Episode, Numbering, SeasonConfig};
+use crate::i18n::t;
+use crate::output::outln;
+use crate::seasons;
+use crate::CliError;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use std::path::PathBuf;
+
+/// The season and episode number for an episode released at
+/// `released_at`, after `episodes`, with seasons starting on days in
+/// `timezone`. `season` and `number` are used as given, and a number is
+/// taken after the highest one in its season, or in the show with global
+/// numbering.
+pub fn next(
+    episodes: &[Episode],
+    config: &SeasonConfig,
+    timezone: Tz,
+    released_at: DateTime<Utc>,
+    season: Option<u64>,
+    number: Option<u64>,
+) -> Result<(u64, u64), CliError> {
+    let season = match season {
+        Some(season) => season,
+        None => {
+            let latest_season = episodes.iter().map(|episode| episode.season).max();
+            let latest_release = episodes.iter().map(|episode| episode.released_at).max();
+            match (latest_season, latest_release) {
+                (Some(latest), Some(previous))
+                    if seasons::starts_between(config, timezone, previous, released_at) =>
+                {
+                    latest + 1
+                }
+                (Some(latest), _) => latest,
+                _ => 1,
+            }
+        }
+    };
+
+    let mut counted = episodes.iter().filter(|episode| match config.numbering {
+        Numbering::PerSeason => episode.season == season,
+        Numbering::Global => true,
+        Numbering::None => false,
+    });
+    let number = match number {
+        Some(number) => {
+            if let Some(taken) = counted.find(|episode| episode.episode_number == number) {
+                return Err(CliError::EpisodeNumberTaken(
+                    taken.season,
+                    number,
+                    taken.title.clone(),
+                ));
+            }
+            number
+        }
+        None if config.numbering == Numbering::None => 0,
+        None => {
+            counted
+                .map(|episode| episode.episode_number)
+                .max()
+                .unwrap_or(0)
+                + 1
+        }
+    };
+    Ok((season, number))
+}
+
+/// Number the episodes of each season one after another in release order,
+/// from the lowest number in the season, so shows that started counting
+/// before their first episode file keep doing so. With global numbering the
+/// whole show is counted that way instead, and with none every number is
+/// cleared to 0. With `by_date`, seasons are worked out again from
+/// `seasons` in channel.yaml, starting at 1, and numbers start from 1;
+/// otherwise episodes keep their season, and shows numbered from season 0
+/// start at season 1 instead. Shows a diff of each change and asks before
+/// writing unless `yes` is set.
+pub fn renumber(
+    mut episodes: Vec<(PathBuf, Episode)>,
+    by_date: bool,
+    yes: bool,
+    channel_config: &ChannelConfig,
+) -> Result<(), CliError> {
+    episodes
+        .sort_by_key(|(_, episode)| (episode.released_at, episode.season, episode.episode_number));
+
+    let seasons: Vec<u64> = if by_date {
+        let timezone = channel_config.channel.tz()?;
+        let mut season = 1;
+        let mut previous: Option<DateTime<Utc>> = None;
+        episodes
+            .iter()
+            .map(|(_, episode)| {
+                if let Some(previous) = previous {
+                    if seasons::starts_between(
+                        &channel_config.seasons,
+                        timezone,
+                        previous,
+                        episode.released_at,
+                    ) {
+                        season += 1;
+                    }
+                }
+                previous = Some(episode.released_at);
+                season
+            })
+            .collect()
+    } else {
+        let shift = episodes.iter().any(|(_, episode)| episode.season == 0) as u64;
+        episodes
+            .iter()
+            .map(|(_, episode)| episode.season + shift)
+            .collect()
+    };
+
+    // Episodes are counted along with the others in their season, or with
+    // every other episode when numbering is global
+    let numbering = channel_config.seasons.numbering;
+    let counted_with = |season: u64| match numbering {
+        Numbering::PerSeason => season,
+        Numbering::Global | Numbering::None => 0,
+    };
+    let first_number = |count: u64| -> u64 {
+        if by_date {
+            return 1;
+        }
+        episodes
+            .iter()
+            .zip(&seasons)
+            .filter(|(_, season)| counted_with(**season) == count)
+            .map(|((_, episode), _)| episode.episode_number.max(1))
+            .min()
+            .unwrap_or(1)
+    };
+    let first_numbers: Vec<u64> = seasons
+        .iter()
+        .map(|season| first_number(counted_with(*season)))
+        .collect();
+
+    let mut changes = Vec::new();
+    let mut numbered: Vec<(u64, u64)> = Vec::new();
+    for (((path, episode), season), first) in episodes.into_iter().zip(seasons).zip(first_numbers) {
+        let count = counted_with(season);
+        let number = match numbering {
+            Numbering::None => 0,
+            _ => numbered
+                .iter()
+                .filter(|(numbered_count, _)| *numbered_count == count)
+                .map(|(_, number)| *number + 1)
+                .max()
+                .unwrap_or(first),
+        };
+        numbered.push((count, number));
+        if (episode.season, episode.episode_number) == (season, number) {
+            continue;
+        }
+
+        let renumbered = Episode {
+            season,
+            episode_number: number,
+            ..episode
+        };
+        let before = String::from_utf8_lossy(&crate::crypto::read(&path)?).into_owned();
+        let after = crate::formats::updated_text(&path, &renumbered)?;
+        if crate::diff::print_diff(&path, &before, &after) {
+            changes.push((path, renumbered));
+        }
+    }
+
+    if changes.is_empty() {
+        outln!("{}", t!("renumber-unchanged"));
+        return Ok(());
+    }
+
+    outln!("{}", t!("renumber-count", count = changes.len()));
+    if !crate::diff::confirm(&t!("confirm-write"), yes)? {
+        outln!("{}", t!("nothing-written"));
+        return Ok(());
+    }
+
+    for (path, episode) in &changes {
+        crate::save_episode(path, episode, channel_config)?;
+    }
+    outln!("{}", t!("renumber-done", count = changes.len()));
+    Ok(())
+}
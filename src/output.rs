@@ -0,0 +1,416 @@
+//! How status output is shaped.
+//!
+//! With `--screen-reader` (or `PODCAST_CTL_SCREEN_READER=1`) there are no
+//! progress bars, colors, or column-aligned tables; progress and listings
+//! are short lines that read well aloud instead. With `--quiet` there is
+//! no progress at all, for logs and CI, only results. Each library call
+//! gets these from its [`events::Context`].
+//!
+//! With `--output json`, messages and progress go to stderr, and stdout is
+//! left for one JSON document written once the command is done, for CI.
+//! Everything printed goes through [`outln!`] and [`out!`], and tools and
+//! plugins get [`child_stdout`], so nothing else reaches it:
+//!
+//! ```json
+//! {"command":"create-episode","episodes":[{"path":"episodes/2022-09-08.yaml","season":1,"episodeNumber":4}],"ok":true,"uploads":[{"key":"show/artifacts/2022-09-08.mp3","url":"https://…"}]}
+//! ```
+
+use crate::events::{self, Event};
+use crate::i18n::t;
+use pbr::{MultiBar, Pipe, ProgressBar, Units};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::env;
+use std::fmt;
+use std::io::{self, Write};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Instant;
+
+pub const SCREEN_READER_ENV: &str = "PODCAST_CTL_SCREEN_READER";
+
+/// Whether screen reader output is asked for, by flag or environment.
+/// Plugins inherit it through the environment.
+pub fn select(flag: bool) -> bool {
+    let enabled = flag
+        || env::var(SCREEN_READER_ENV)
+            .map(|value| !value.is_empty() && value != "0")
+            .unwrap_or(false);
+    if enabled {
+        env::set_var(SCREEN_READER_ENV, "1");
+    }
+    enabled
+}
+
+/// How a call's output is shaped, kept in its [`events::Context`].
+#[derive(Debug, Clone, Default)]
+pub struct Output {
+    pub(crate) screen_reader: bool,
+    pub(crate) quiet: bool,
+    pub(crate) json: bool,
+    /// The JSON document's fields so far
+    pub(crate) results: Arc<Mutex<Vec<(String, Value)>>>,
+}
+
+pub fn screen_reader() -> bool {
+    events::output().screen_reader
+}
+
+pub fn quiet() -> bool {
+    events::output().quiet
+}
+
+/// How a command's results are printed, set with `--output`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Messages for people
+    #[default]
+    Text,
+    /// One JSON document on stdout, with messages on stderr
+    Json,
+}
+
+/// `println!`, to stdout, or to stderr with `--output json`.
+macro_rules! outln {
+    () => {
+        $crate::output::print(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::print(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+pub(crate) use outln;
+
+/// `print!`, to stdout, or to stderr with `--output json`, flushed so
+/// prompts show before the answer is read.
+macro_rules! out {
+    ($($arg:tt)*) => {
+        $crate::output::print(format_args!($($arg)*))
+    };
+}
+pub(crate) use out;
+
+/// Where messages, listings, and progress are written: stdout, or stderr
+/// when stdout is kept for the JSON document.
+pub fn writer() -> Box<dyn Write + Send> {
+    if json() {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    }
+}
+
+#[doc(hidden)]
+pub fn print(args: fmt::Arguments) {
+    let mut writer = writer();
+    // Like `println!`, but a closed pipe isn't worth a panic
+    let _ = writer.write_fmt(args).and_then(|_| writer.flush());
+}
+
+/// Stdout for tools, steps, and plugins run along the way, so what they
+/// print doesn't end up in the JSON document.
+pub fn child_stdout() -> Stdio {
+    if json() {
+        if let Some(stderr) = stderr_handle() {
+            return stderr;
+        }
+    }
+    Stdio::inherit()
+}
+
+#[cfg(unix)]
+fn stderr_handle() -> Option<Stdio> {
+    use std::os::unix::io::AsFd;
+    let stderr = io::stderr().as_fd().try_clone_to_owned().ok()?;
+    Some(Stdio::from(stderr))
+}
+
+#[cfg(windows)]
+fn stderr_handle() -> Option<Stdio> {
+    use std::os::windows::io::AsHandle;
+    let stderr = io::stderr().as_handle().try_clone_to_owned().ok()?;
+    Some(Stdio::from(stderr))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn stderr_handle() -> Option<Stdio> {
+    None
+}
+
+/// Add finished uploads to the JSON document, as a listener.
+pub fn record_upload(event: &Event) {
+    if let Event::UploadFinished { key, url } = event {
+        append("uploads", serde_json::json!({ "key": key, "url": url }));
+    }
+}
+
+pub fn json() -> bool {
+    events::output().json
+}
+
+/// Set `name` in the JSON document.
+pub fn record(name: &str, value: impl Serialize) {
+    let output = events::output();
+    if !output.json {
+        return;
+    }
+    let value = serde_json::to_value(value).unwrap_or(Value::Null);
+    let mut results = match output.results.lock() {
+        Ok(results) => results,
+        Err(_) => return,
+    };
+    match results.iter_mut().find(|(field, _)| field == name) {
+        Some((_, existing)) => *existing = value,
+        None => results.push((name.to_owned(), value)),
+    }
+}
+
+/// Add `value` to the list `name` in the JSON document.
+pub fn append(name: &str, value: impl Serialize) {
+    let output = events::output();
+    if !output.json {
+        return;
+    }
+    let value = serde_json::to_value(value).unwrap_or(Value::Null);
+    let mut results = match output.results.lock() {
+        Ok(results) => results,
+        Err(_) => return,
+    };
+    match results.iter_mut().find(|(field, _)| field == name) {
+        Some((_, Value::Array(items))) => items.push(value),
+        Some((_, existing)) => *existing = Value::Array(vec![existing.take(), value]),
+        None => results.push((name.to_owned(), Value::Array(vec![value]))),
+    }
+}
+
+/// Write the JSON document, saying whether the command succeeded and, if
+/// not, why.
+pub fn finish(error: Option<String>) {
+    let output = events::output();
+    if !output.json {
+        return;
+    }
+    let mut document = Map::new();
+    if let Ok(results) = output.results.lock() {
+        for (name, value) in results.iter() {
+            document.insert(name.clone(), value.clone());
+        }
+    }
+    document.insert("ok".to_owned(), Value::Bool(error.is_none()));
+    if let Some(error) = error {
+        document.insert("error".to_owned(), Value::String(error));
+    }
+    println!("{}", Value::Object(document));
+}
+
+/// Progress of an upload: a bar, or a line every quarter of the way. Each
+/// step is also sent as an event.
+pub struct Progress {
+    key: String,
+    total: u64,
+    done: u64,
+    display: Display,
+}
+
+enum Display {
+    Bar(Box<ProgressBar<Box<dyn Write + Send>>>),
+    /// One of a group's bars, and the group's bar for all of its uploads
+    Grouped {
+        bar: ProgressBar<Pipe>,
+        all: Arc<Mutex<ProgressBar<Pipe>>>,
+    },
+    Lines {
+        name: String,
+        quarters: u64,
+    },
+    Hidden,
+}
+
+impl Progress {
+    pub fn new(key: &str, name: &str, total: u64) -> Self {
+        let display = if quiet() {
+            Display::Hidden
+        } else if screen_reader() {
+            outln!(
+                "{}",
+                t!(
+                    "uploading",
+                    name = name,
+                    size = crate::preview::format_bytes(total)
+                )
+            );
+            Display::Lines {
+                name: name.to_owned(),
+                quarters: 0,
+            }
+        } else {
+            let mut bar = ProgressBar::on(writer(), total);
+            bar.set_units(Units::Bytes);
+            bar.show_speed = true;
+            bar.message(&format!("{} ", name));
+            Display::Bar(Box::new(bar))
+        };
+        Progress::with_display(key, total, display)
+    }
+
+    fn with_display(key: &str, total: u64, display: Display) -> Self {
+        events::emit(Event::UploadStarted {
+            key: key.to_owned(),
+            bytes: total,
+        });
+        Progress {
+            key: key.to_owned(),
+            total,
+            done: 0,
+            display,
+        }
+    }
+
+    pub fn add(&mut self, amount: u64) {
+        self.done += amount;
+        events::emit(Event::BytesTransferred {
+            key: self.key.clone(),
+            done: self.done,
+            total: self.total,
+        });
+        match &mut self.display {
+            Display::Bar(bar) => {
+                bar.add(amount);
+            }
+            Display::Grouped { bar, all } => {
+                bar.add(amount);
+                if let Ok(mut all) = all.lock() {
+                    all.add(amount);
+                }
+            }
+            Display::Lines { name, quarters } => {
+                let reached = (self.done * 4)
+                    .checked_div(self.total)
+                    .map_or(4, |reached| reached.min(4));
+                if reached > *quarters {
+                    *quarters = reached;
+                    outln!(
+                        "{}",
+                        t!(
+                            "upload-progress",
+                            name = name.as_str(),
+                            percent = reached * 25
+                        )
+                    );
+                }
+            }
+            Display::Hidden => {}
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        // The group's bars are drawn until every one of them is finished,
+        // whether its upload succeeded or not
+        if let Display::Grouped { bar, .. } = &mut self.display {
+            bar.finish();
+        }
+    }
+}
+
+/// Progress of uploads running at the same time: a bar for each, under
+/// them a bar for all of them with the time left, and once they are done,
+/// a summary of the throughput.
+pub struct ProgressGroup {
+    bars: Mutex<Vec<Option<ProgressBar<Pipe>>>>,
+    all: Option<Arc<Mutex<ProgressBar<Pipe>>>>,
+    drawing: Option<JoinHandle<()>>,
+    started: Instant,
+}
+
+impl ProgressGroup {
+    /// For uploading `objects`, as their names and sizes, in order.
+    pub fn new(objects: &[(String, u64)]) -> Self {
+        let mut group = ProgressGroup {
+            bars: Mutex::new(Vec::new()),
+            all: None,
+            drawing: None,
+            started: Instant::now(),
+        };
+        // Without bars, each upload shows its own lines, or nothing
+        if quiet() || screen_reader() {
+            return group;
+        }
+
+        let multi = Arc::new(MultiBar::on(writer()));
+        let bars = objects
+            .iter()
+            .map(|(name, size)| {
+                let mut bar = multi.create_bar(*size);
+                bar.set_units(Units::Bytes);
+                bar.show_speed = true;
+                bar.message(&format!("{} ", name));
+                Some(bar)
+            })
+            .collect();
+        let total = objects.iter().map(|(_, size)| size).sum();
+        let mut all = multi.create_bar(total);
+        all.set_units(Units::Bytes);
+        all.show_speed = true;
+        all.show_time_left = true;
+        all.message(&format!("{} ", t!("uploads-total", count = objects.len())));
+        group.bars = Mutex::new(bars);
+        group.all = Some(Arc::new(Mutex::new(all)));
+        // Bars are only drawn while something listens for them
+        group.drawing = Some(std::thread::spawn(move || multi.listen()));
+        group
+    }
+
+    /// Progress of the object at `index`, as it starts uploading.
+    pub fn start(&self, index: usize, key: &str, name: &str, total: u64) -> Progress {
+        let bar = self
+            .bars
+            .lock()
+            .ok()
+            .and_then(|mut bars| bars.get_mut(index).and_then(Option::take));
+        match (bar, &self.all) {
+            (Some(bar), Some(all)) => {
+                let all = all.clone();
+                Progress::with_display(key, total, Display::Grouped { bar, all })
+            }
+            _ => Progress::new(key, name, total),
+        }
+    }
+
+    /// Finish drawing, including the bars of uploads that never started,
+    /// and print how many objects and bytes went up how fast.
+    pub fn finish(mut self, objects: usize, bytes: u64) {
+        if let Ok(mut bars) = self.bars.lock() {
+            for bar in bars.iter_mut().filter_map(Option::as_mut) {
+                bar.finish();
+            }
+        }
+        if let Some(all) = self.all.take() {
+            if let Ok(mut all) = all.lock() {
+                all.finish();
+            }
+        }
+        if let Some(drawing) = self.drawing.take() {
+            let _ = drawing.join();
+        }
+
+        let seconds = self.started.elapsed().as_secs_f64();
+        let speed = if seconds > 0.0 {
+            (bytes as f64 / seconds) as u64
+        } else {
+            bytes
+        };
+        outln!(
+            "{}",
+            t!(
+                "uploads-summary",
+                count = objects,
+                size = crate::preview::format_bytes(bytes),
+                seconds = format!("{:.1}", seconds),
+                speed = crate::preview::format_bytes(speed)
+            )
+        );
+    }
+}
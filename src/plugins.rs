@@ -0,0 +1,119 @@
+//! External executable plugins.
+//!
+//! Any executable named `podcast-ctl-<name>` on the `PATH` can be run as
+//! `podcast-ctl <name> [args...]`. Plugins listed under `plugins` in
+//! `channel.yaml` are also called at the stages they register for, with a
+//! JSON document describing the channel on stdin:
+//!
+//! ```json
+//! { "stage": "render", "offline": false, "channel": { ... }, "episodes": [ ... ], "feed": "<?xml ..." }
+//! ```
+//!
+//! A plugin that exits non-zero fails the command that called it. When
+//! `--offline` is set, plugins also see `PODCAST_CTL_OFFLINE=1` and are
+//! expected not to use the network. With `--screen-reader` they see
+//! `PODCAST_CTL_SCREEN_READER=1`, and should print plain lines.
+
+use crate::config::*;
+use crate::CliError;
+use log::{debug, info};
+use serde::Serialize;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+pub const PLUGIN_PREFIX: &str = "podcast-ctl-";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StagePayload<'a> {
+    pub stage: PluginStage,
+    pub offline: bool,
+    pub channel: &'a ChannelDetails,
+    pub episodes: &'a [Episode],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed_url: Option<&'a str>,
+}
+
+/// Run `podcast-ctl-<name>` as if it were a built-in subcommand, telling
+/// it not to use the network when `offline`. When it fails, the error
+/// carries its exit status.
+pub fn run_external(
+    channel_file: &Path,
+    offline: bool,
+    args: &[OsString],
+) -> Result<(), CliError> {
+    let (name, rest) = match args.split_first() {
+        Some(split) => split,
+        None => return Err(CliError::Unknown),
+    };
+    let program = executable_name(&name.to_string_lossy());
+    debug!("Running external subcommand {:?} {:?}", program, rest);
+
+    let mut command = Command::new(&program);
+    command
+        .args(rest)
+        .env("PODCAST_CTL_CHANNEL_FILE", channel_file)
+        .stdout(crate::output::child_stdout());
+    if offline {
+        command.env("PODCAST_CTL_OFFLINE", "1");
+    }
+
+    let status = command.status().map_err(|e| {
+        CliError::PluginError(program.clone(), format!("unable to run plugin: {}", e))
+    })?;
+
+    if !status.success() {
+        return Err(CliError::PluginExited(program, status.code().unwrap_or(1)));
+    }
+
+    Ok(())
+}
+
+/// Call every plugin registered for `payload.stage`, in config order.
+pub fn run_stage(plugins: &[PluginConfig], payload: &StagePayload) -> Result<(), CliError> {
+    let registered = plugins
+        .iter()
+        .filter(|plugin| plugin.stages.contains(&payload.stage));
+
+    for plugin in registered {
+        let program = executable_name(&plugin.name);
+        info!("Calling plugin {} for {:?}", program, payload.stage);
+
+        let body = serde_json::to_vec(payload)?;
+        let mut command = Command::new(&program);
+        command
+            .args(&plugin.args)
+            .env("PODCAST_CTL_STAGE", payload.stage.as_str())
+            .stdin(Stdio::piped())
+            .stdout(crate::output::child_stdout());
+        if payload.offline {
+            command.env("PODCAST_CTL_OFFLINE", "1");
+        }
+
+        let mut child = command.spawn().map_err(|e| {
+            CliError::PluginError(program.clone(), format!("unable to run plugin: {}", e))
+        })?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&body)?;
+        }
+
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(CliError::PluginError(
+                program,
+                format!("exited with {}", status),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn executable_name(name: &str) -> String {
+    format!("{}{}", PLUGIN_PREFIX, name)
+}
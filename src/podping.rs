@@ -0,0 +1,67 @@
+//! Podping, which Podcasting 2.0 apps listen to so they pick up a feed as
+//! soon as it changes. With `publishing.notifications.podping` set,
+//! `render-channel --upload` sends the feed's URL to podping.cloud once the
+//! feed is up, and `notify podping` sends it again:
+//!
+//! ```yaml
+//! publishing:
+//!   notifications:
+//!     podping:
+//!       tokenEnv: PODPING_TOKEN
+//! ```
+
+use crate::config::{ChannelConfig, PodpingConfig};
+use crate::http::HttpClient;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::CliError;
+use log::info;
+use reqwest::Method;
+
+/// Tell Podping the feed at `feed_url` was updated.
+pub async fn notify(
+    config: &PodpingConfig,
+    client: &HttpClient,
+    feed_url: &str,
+) -> Result<(), CliError> {
+    let token = std::env::var(&config.token_env)
+        .map_err(|_| CliError::MissingEnvVar(config.token_env.clone()))?;
+    let query = [
+        ("url", feed_url),
+        ("reason", "update"),
+        ("medium", "podcast"),
+    ];
+
+    info!("Sending {} to Podping at {}", feed_url, config.url);
+    let response = client
+        .send(&config.url, || {
+            client
+                .request(Method::GET, &config.url)
+                .header("Authorization", &token)
+                .header(
+                    "User-Agent",
+                    concat!("podcastctl/", env!("CARGO_PKG_VERSION")),
+                )
+                .query(&query)
+        })
+        .await?;
+    if !response.status.is_success() {
+        return Err(CliError::HttpStatus(
+            config.url.clone(),
+            response.status.as_u16(),
+        ));
+    }
+    Ok(())
+}
+
+/// `notify podping`: tell Podping the published feed changed.
+pub async fn notify_feed(channel_config: &ChannelConfig) -> Result<(), CliError> {
+    let publishing = &channel_config.publishing;
+    let podping = publishing.notifications.podping.as_ref().ok_or(CliError::NoPodping)?;
+    let feed_key = format!("{}/podcast.xml", publishing.prefix);
+    let feed_url = crate::upload::object_url(publishing, &feed_key);
+    let client = HttpClient::new(&channel_config.http)?;
+    notify(podping, &client, &feed_url).await?;
+    outln!("{}", t!("podping-sent", url = feed_url));
+    Ok(())
+}
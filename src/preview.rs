@@ -0,0 +1,115 @@
+use crate::config::*;
+use std::fmt::Write;
+
+/// Human readable summary of what the rendered feed contains.
+pub fn render_preview(channel: &ChannelDetails, episodes: &[Episode]) -> String {
+    let mut out = String::new();
+
+    let screen_reader = crate::output::screen_reader();
+
+    writeln!(out, "{}", channel.title).ok();
+    if !screen_reader {
+        writeln!(out, "{}", "=".repeat(channel.title.chars().count())).ok();
+    }
+    writeln!(out, "{}", channel.subtitle).ok();
+    writeln!(out).ok();
+    writeln!(
+        out,
+        "Owner:    {} <{}>",
+        channel.owner.name, channel.owner.email
+    )
+    .ok();
+    writeln!(
+        out,
+        "Explicit: {}",
+        if channel.explicit { "yes" } else { "no" }
+    )
+    .ok();
+    writeln!(out, "Image:    {}", channel.image).ok();
+    if let Some(link) = &channel.link {
+        writeln!(out, "Link:     {}", link).ok();
+    }
+    if !channel.keywords.is_empty() {
+        writeln!(out, "Keywords: {}", channel.keywords.join(", ")).ok();
+    }
+    writeln!(out).ok();
+
+    let total_bytes: u64 = episodes.iter().map(|e| e.media.bytes.0).sum();
+    let total_duration: u64 = episodes.iter().map(|e| e.media.duration.0).sum();
+    writeln!(
+        out,
+        "{} episode(s), {} total, {}",
+        episodes.len(),
+        format_duration(total_duration),
+        format_bytes(total_bytes)
+    )
+    .ok();
+    writeln!(out).ok();
+
+    if screen_reader {
+        for episode in episodes {
+            writeln!(
+                out,
+                "Season {}, episode {}: {}. Released {}, {} long, {}.",
+                episode.season,
+                episode.episode_number,
+                episode.title,
+                episode.released_at.format("%Y-%m-%d"),
+                format_duration(episode.media.duration.0),
+                format_bytes(episode.media.bytes.0)
+            )
+            .ok();
+        }
+        return out;
+    }
+
+    writeln!(
+        out,
+        "{:>3} {:>4}  {:<10}  {:>8}  {:>10}  Title",
+        "S", "E", "Released", "Duration", "Size"
+    )
+    .ok();
+    for episode in episodes {
+        writeln!(
+            out,
+            "{:>3} {:>4}  {:<10}  {:>8}  {:>10}  {}",
+            episode.season,
+            episode.episode_number,
+            episode.released_at.format("%Y-%m-%d"),
+            format_duration(episode.media.duration.0),
+            format_bytes(episode.media.bytes.0),
+            episode.title
+        )
+        .ok();
+    }
+
+    out
+}
+
+/// Formats seconds as `H:MM:SS`.
+pub fn format_duration(seconds: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds / 60) % 60,
+        seconds % 60
+    )
+}
+
+/// Formats a byte count with binary units, e.g. `96.2 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
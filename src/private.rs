@@ -0,0 +1,287 @@
+//! Private feeds, for subscriber-only shows. With `publishing.private`,
+//! objects are uploaded without a public ACL, and the URLs in the feed
+//! only work for those given them:
+//!
+//! ```yaml
+//! publishing:
+//!   private:
+//!     urls: token                # or presigned
+//!     tokenEnv: FEED_TOKEN       # PODCAST_CTL_FEED_TOKEN by default
+//!     tokenParam: token
+//! ```
+//!
+//! With `token`, the feed's URL and the URLs in it get the token from
+//! `tokenEnv` as a query parameter, for a CDN or edge function in front of
+//! the bucket to check. With `presigned`, the URLs in the feed are S3
+//! presigned URLs, which expire after `expiresDays`, at most 7 days. Apps
+//! keep a feed's URL for good, so the feeds themselves aren't presigned:
+//! they keep `objectOptions.acl` and are fetched at their plain URL, and
+//! anyone given it can listen. To keep the feed itself private, use
+//! `token`.
+//!
+//! `rotate-urls` publishes the feed again with fresh URLs, for when a
+//! private feed's link leaks, and before presigned URLs expire, e.g. from
+//! cron. With `token`, it makes a new token, saves it as `tokenEnv` in
+//! `.env`, and publishes `tokens.json` under the prefix with the SHA-256
+//! of each token that is still valid, for the CDN or edge function to
+//! check:
+//!
+//! ```json
+//! { "sha256": ["9f86d081884c7d65..."], "updatedAt": "2022-09-01T12:00:00Z" }
+//! ```
+//!
+//! While the feed is published again both tokens are listed; once it is,
+//! only the new one is. Presigned URLs can't be revoked: those already
+//! given out work until they expire, unless the access key that signed
+//! them is deactivated. Episode files keep plain URLs, so nothing else
+//! changes. Private feeds aren't announced to WebSub hubs or Podping.
+
+use crate::config::{PrivateConfig, PrivateUrls, PublishingConfig, StorageType};
+use crate::upload::{self, ObjectOptions};
+use crate::CliError;
+use aws_sdk_s3::presigning::PresigningConfig;
+use rand::Rng;
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{Cursor, Write as _};
+use std::path::Path;
+use std::time::Duration;
+
+/// Where the hashes of the valid tokens are published, under the prefix.
+pub const TOKENS_FILE: &str = "tokens.json";
+
+/// Presigned URLs can't last longer than this.
+const MAX_PRESIGNED_DAYS: u64 = 7;
+
+/// Makes the URLs of a private feed.
+pub struct Signer<'a> {
+    publishing: &'a PublishingConfig,
+    config: &'a PrivateConfig,
+    token: Option<String>,
+    client: Option<aws_sdk_s3::Client>,
+}
+
+impl<'a> Signer<'a> {
+    /// The signer for `publishing`, if its feed is private. With tokens,
+    /// `token` is used, or else the one in `tokenEnv`.
+    pub async fn new(
+        publishing: &'a PublishingConfig,
+        token: Option<&str>,
+    ) -> Result<Option<Signer<'a>>, CliError> {
+        let config = match &publishing.private {
+            Some(config) => config,
+            None => return Ok(None),
+        };
+        let mut signer = Signer {
+            publishing,
+            config,
+            token: None,
+            client: None,
+        };
+        match config.urls {
+            PrivateUrls::Token => {
+                let token = match token {
+                    Some(token) => token.to_owned(),
+                    None => std::env::var(&config.token_env)
+                        .map_err(|_| CliError::MissingEnvVar(config.token_env.clone()))?,
+                };
+                signer.token = Some(token);
+            }
+            PrivateUrls::Presigned if publishing.storage != StorageType::S3 => {
+                return Err(CliError::StorageConfig(
+                    "`private.urls: presigned` needs S3 storage, use `token`".to_owned(),
+                ));
+            }
+            PrivateUrls::Presigned if config.expires_days > MAX_PRESIGNED_DAYS => {
+                return Err(CliError::StorageConfig(format!(
+                    "presigned URLs last at most {} days, not `expiresDays: {}`",
+                    MAX_PRESIGNED_DAYS, config.expires_days
+                )));
+            }
+            PrivateUrls::Presigned => {
+                signer.client = Some(upload::client(publishing).await);
+            }
+        }
+        Ok(Some(signer))
+    }
+
+    /// What subscribers are given for `url`. URLs outside this storage,
+    /// like artwork hosted elsewhere, are left alone.
+    pub async fn url(&self, url: &str) -> Result<String, CliError> {
+        let key = match upload::url_key(self.publishing, url) {
+            Some(key) => upload::storage_key(self.publishing, &key),
+            None => return Ok(url.to_owned()),
+        };
+        if let Some(token) = &self.token {
+            return self.with_token(url, token);
+        }
+        let client = match &self.client {
+            Some(client) => client,
+            None => return Ok(url.to_owned()),
+        };
+        let expires = Duration::from_secs(self.config.expires_days * 24 * 60 * 60);
+        let presigning = PresigningConfig::expires_in(expires)
+            .map_err(|e| CliError::StorageConfig(e.to_string()))?;
+        let request = client
+            .get_object()
+            .bucket(&self.publishing.bucket)
+            .key(key)
+            .presigned(presigning)
+            .await?;
+        Ok(request.uri().to_owned())
+    }
+
+    /// What subscribers are given for the feed at `url`: with the token,
+    /// or with presigned URLs, the plain URL.
+    pub fn feed_url(&self, url: &str) -> Result<String, CliError> {
+        match &self.token {
+            Some(token) => self.with_token(url, token),
+            None => Ok(url.to_owned()),
+        }
+    }
+
+    fn with_token(&self, url: &str, token: &str) -> Result<String, CliError> {
+        let mut signed = Url::parse(url).map_err(|e| {
+            CliError::InvalidUrl("A private URL".to_owned(), url.to_owned(), e.to_string())
+        })?;
+        signed
+            .query_pairs_mut()
+            .append_pair(&self.config.token_param, token);
+        Ok(signed.to_string())
+    }
+}
+
+/// A new random token, 32 bytes as hex.
+pub fn new_token() -> String {
+    let bytes: [u8; 32] = rand::thread_rng().gen();
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+/// The token `name` is set to in the `.env` file at `path`, if it's there.
+pub fn read_token(path: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()?
+        .lines()
+        .filter_map(parse_assignment)
+        .filter(|(key, _)| *key == name)
+        .last()
+        .map(|(_, value)| value.trim_matches(|c| c == '"' || c == '\'').to_owned())
+}
+
+/// The name and value a `.env` line like `export NAME=value` sets.
+fn parse_assignment(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim_start();
+    if line.starts_with('#') {
+        return None;
+    }
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let (key, value) = line.split_once('=')?;
+    Some((key.trim(), value.trim()))
+}
+
+/// Set `name` to `token` in the `.env` file at `path`, replacing the line
+/// that sets it, or adding one. A new `.env` is only readable by its owner.
+pub fn save_token(path: &Path, name: &str, token: &str) -> Result<(), CliError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let assignment = format!("{}={}", name, token);
+    let sets_name = |line: &str| matches!(parse_assignment(line), Some((key, _)) if key == name);
+    let mut lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            if sets_name(line) {
+                assignment.clone()
+            } else {
+                line.to_owned()
+            }
+        })
+        .collect();
+    if !text.lines().any(sets_name) {
+        lines.push(assignment);
+    }
+    let mut file = private_file(path)?;
+    file.write_all((lines.join("\n") + "\n").as_bytes())?;
+    Ok(())
+}
+
+/// `path` opened for writing from the start, created with mode 0600.
+#[cfg(unix)]
+fn private_file(path: &Path) -> std::io::Result<fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn private_file(path: &Path) -> std::io::Result<fs::File> {
+    fs::File::create(path)
+}
+
+/// Publish [`TOKENS_FILE`] with the hashes of `tokens`, so only they are
+/// accepted.
+pub async fn publish_tokens(
+    publishing: &PublishingConfig,
+    tokens: &[&str],
+) -> Result<(), CliError> {
+    let hashes: Vec<String> = tokens
+        .iter()
+        .map(|token| format!("{:x}", Sha256::digest(token.as_bytes())))
+        .collect();
+    let body = serde_json::to_vec_pretty(&serde_json::json!({
+        "sha256": hashes,
+        "updatedAt": chrono::Utc::now(),
+    }))?;
+    let size = body.len() as u64;
+    upload::upload_object(
+        Cursor::new(body),
+        size,
+        publishing,
+        format!("{}/{}", publishing.prefix, TOKENS_FILE),
+        ObjectOptions::feed(publishing),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_tokens_to_env_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".env");
+        save_token(&path, "FEED_TOKEN", "first").unwrap();
+        assert_eq!(read_token(&path, "FEED_TOKEN").as_deref(), Some("first"));
+
+        fs::write(&path, "# tokens\nexport FEED_TOKEN=\"first\"\nOTHER=1\n").unwrap();
+        save_token(&path, "FEED_TOKEN", "second").unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# tokens\nFEED_TOKEN=second\nOTHER=1\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn new_env_files_are_only_readable_by_their_owner() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".env");
+        save_token(&path, "FEED_TOKEN", "secret").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
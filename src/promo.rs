@@ -0,0 +1,318 @@
+//! Release-day social posts. Each network gets its own copy of the post,
+//! fitted to its length limit, with UTM parameters on the episode link so
+//! analytics can tell where listeners came from.
+
+use crate::config::*;
+use crate::http::HttpClient;
+use crate::output::outln;
+use crate::template::expand;
+use crate::CliError;
+use clap::ValueEnum;
+use log::info;
+use reqwest::{Method, Url};
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+const DEFAULT_TEMPLATE: &str = "{title}\n\n{summary}\n\n{link}\n\n{hashtags}";
+const ELLIPSIS: &str = "…";
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Network {
+    X,
+    Mastodon,
+    #[clap(name = "linkedin")]
+    LinkedIn,
+}
+
+impl Network {
+    pub fn all() -> Vec<Network> {
+        vec![Network::X, Network::Mastodon, Network::LinkedIn]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Network::X => "x",
+            Network::Mastodon => "mastodon",
+            Network::LinkedIn => "linkedin",
+        }
+    }
+
+    fn max_characters(&self) -> usize {
+        match self {
+            Network::X => 280,
+            Network::Mastodon => 500,
+            Network::LinkedIn => 3000,
+        }
+    }
+
+    /// X and Mastodon count every link as 23 characters, however long it is.
+    fn link_length(&self, link: &str) -> usize {
+        match self {
+            Network::X | Network::Mastodon => 23,
+            Network::LinkedIn => link.chars().count(),
+        }
+    }
+
+    fn max_hashtags(&self) -> usize {
+        match self {
+            Network::X => 2,
+            Network::Mastodon => 4,
+            Network::LinkedIn => 5,
+        }
+    }
+
+    pub fn is_configured(&self, config: &PromoConfig) -> bool {
+        match self {
+            Network::X => config.x.is_some(),
+            Network::Mastodon => config.mastodon.is_some(),
+            Network::LinkedIn => config.linkedin.is_some(),
+        }
+    }
+}
+
+/// Write the post for `network`, shortening the summary and then dropping
+/// hashtags until it fits.
+pub fn render_post(
+    config: &PromoConfig,
+    channel: &ChannelDetails,
+    episode: &Episode,
+    network: Network,
+) -> String {
+    let template = config
+        .templates
+        .get(network.as_str())
+        .map(|template| template.as_str())
+        .unwrap_or(DEFAULT_TEMPLATE);
+    let link = tracked_link(config, episode, network);
+    let mut hashtags = hashtags(config, episode);
+    hashtags.truncate(network.max_hashtags());
+    let mut summary = episode.summary.trim().to_owned();
+
+    loop {
+        let variables = vec![
+            ("title", episode.title.clone()),
+            ("summary", summary.clone()),
+            ("link", link.clone()),
+            ("hashtags", hashtags.join(" ")),
+            ("channel", channel.title.clone()),
+            ("season", episode.season.to_string()),
+            ("episode_number", episode.episode_number.to_string()),
+        ];
+        let post = expand(template, &variables).trim().to_owned();
+
+        let links = post.matches(&link).count();
+        let length = post.chars().count() - links * link.chars().count()
+            + links * network.link_length(&link);
+        let over = match length.checked_sub(network.max_characters()) {
+            None | Some(0) => return post,
+            Some(over) => over,
+        };
+
+        if !summary.is_empty() {
+            summary = shorten(&summary, over);
+        } else if !hashtags.is_empty() {
+            hashtags.pop();
+        } else {
+            let keep = post.chars().count().saturating_sub(over + 1);
+            return format!(
+                "{}{}",
+                post.chars().take(keep).collect::<String>(),
+                ELLIPSIS
+            );
+        }
+    }
+}
+
+/// Publish `post` to `network` with its configured account.
+pub async fn post(
+    config: &PromoConfig,
+    network: Network,
+    post: &str,
+    client: &HttpClient,
+) -> Result<(), CliError> {
+    let (url, token_env, body) = match network {
+        Network::X => {
+            let x = config.x.as_ref().ok_or_else(|| not_configured(network))?;
+            (
+                "https://api.twitter.com/2/tweets".to_owned(),
+                &x.access_token_env,
+                json!({ "text": post }),
+            )
+        }
+        Network::Mastodon => {
+            let mastodon = config
+                .mastodon
+                .as_ref()
+                .ok_or_else(|| not_configured(network))?;
+            (
+                format!(
+                    "{}/api/v1/statuses",
+                    mastodon.instance.trim_end_matches('/')
+                ),
+                &mastodon.access_token_env,
+                json!({ "status": post }),
+            )
+        }
+        Network::LinkedIn => {
+            let linkedin = config
+                .linkedin
+                .as_ref()
+                .ok_or_else(|| not_configured(network))?;
+            (
+                "https://api.linkedin.com/v2/ugcPosts".to_owned(),
+                &linkedin.access_token_env,
+                json!({
+                    "author": linkedin.author,
+                    "lifecycleState": "PUBLISHED",
+                    "specificContent": {
+                        "com.linkedin.ugc.ShareContent": {
+                            "shareCommentary": { "text": post },
+                            "shareMediaCategory": "NONE",
+                        }
+                    },
+                    "visibility": { "com.linkedin.ugc.MemberNetworkVisibility": "PUBLIC" },
+                }),
+            )
+        }
+    };
+    let token = std::env::var(token_env).map_err(|_| CliError::MissingEnvVar(token_env.clone()))?;
+
+    info!("Posting to {}", network.as_str());
+    let response = client
+        .send(&url, || {
+            client
+                .request(Method::POST, &url)
+                .bearer_auth(&token)
+                .json(&body)
+        })
+        .await?;
+    if !response.status.is_success() {
+        return Err(CliError::HttpStatus(url, response.status.as_u16()));
+    }
+
+    let reply: Value = serde_json::from_slice(&response.body).unwrap_or(Value::Null);
+    let posted = match network {
+        Network::X => reply["data"]["id"]
+            .as_str()
+            .map(|id| format!("https://x.com/i/web/status/{}", id)),
+        Network::Mastodon => reply["url"].as_str().map(|url| url.to_owned()),
+        Network::LinkedIn => reply["id"].as_str().map(|id| id.to_owned()),
+    };
+    outln!(
+        "Posted to {}: {}",
+        network.as_str(),
+        posted.unwrap_or_else(|| "ok".to_owned())
+    );
+
+    Ok(())
+}
+
+/// The episode page (or the audio, when there is no page) with
+/// `utm_source`, `utm_medium`, and `utm_campaign` added.
+fn tracked_link(config: &PromoConfig, episode: &Episode, network: Network) -> String {
+    let link = episode.link.as_deref().unwrap_or(&episode.media.url);
+    let campaign = expand(
+        config
+            .utm_campaign
+            .as_deref()
+            .unwrap_or("episode-{episode_number}"),
+        &[
+            ("id", episode.id.clone()),
+            ("episode_number", episode.episode_number.to_string()),
+        ],
+    );
+
+    match Url::parse(link) {
+        Ok(mut url) => {
+            url.query_pairs_mut()
+                .append_pair("utm_source", network.as_str())
+                .append_pair("utm_medium", "social")
+                .append_pair("utm_campaign", &campaign);
+            url.to_string()
+        }
+        Err(_) => link.to_owned(),
+    }
+}
+
+/// Configured hashtags, then the episode keywords, as `#CamelCase` with
+/// duplicates removed.
+fn hashtags(config: &PromoConfig, episode: &Episode) -> Vec<String> {
+    let mut seen = BTreeSet::new();
+    config
+        .hashtags
+        .iter()
+        .chain(episode.keywords.iter())
+        .filter_map(|keyword| {
+            let tag: String = keyword
+                .trim_start_matches('#')
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .map(|word| {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) => first.to_uppercase().chain(chars).collect(),
+                        None => String::new(),
+                    }
+                })
+                .collect();
+            if tag.is_empty() || !seen.insert(tag.to_lowercase()) {
+                None
+            } else {
+                Some(format!("#{}", tag))
+            }
+        })
+        .collect()
+}
+
+/// Drop at least `over` characters from the end of `text`, at a word
+/// boundary, and mark the cut with an ellipsis.
+fn shorten(text: &str, over: usize) -> String {
+    let text = text.trim_end_matches(ELLIPSIS);
+    let keep = text.chars().count().saturating_sub(over + 1);
+    let cut: String = text.chars().take(keep).collect();
+    match cut.rfind(char::is_whitespace) {
+        Some(index) if index > 0 => format!("{}{}", cut[..index].trim_end(), ELLIPSIS),
+        _ => String::new(),
+    }
+}
+
+fn not_configured(network: Network) -> CliError {
+    CliError::PromoError(format!(
+        "channel.yaml has no `promo.{}` section",
+        network.as_str()
+    ))
+}
+
+/// `promo`: print the posts announcing the episode `selector` picks, for
+/// `networks` or all of them, and with `publish`, post them. Networks
+/// named in `networks` must be configured; the rest are only posted to
+/// when they are.
+pub async fn promote(
+    channel_config: &ChannelConfig,
+    episode_dir: &Path,
+    selector: &str,
+    networks: &[Network],
+    publish: bool,
+) -> Result<(), CliError> {
+    let (_, mut episode) =
+        crate::find_episode(episode_dir, selector, channel_config.channel.tz()?)?;
+    crate::template::expand_episode(&mut episode);
+    let picked = if networks.is_empty() {
+        Network::all()
+    } else {
+        networks.to_vec()
+    };
+    let client = HttpClient::new(&channel_config.http)?;
+
+    for network in picked {
+        let text = render_post(&channel_config.promo, &channel_config.channel, &episode, network);
+        outln!("--- {}", network.as_str());
+        outln!("{}\n", text);
+
+        if publish && (!networks.is_empty() || network.is_configured(&channel_config.promo)) {
+            post(&channel_config.promo, network, &text, &client).await?;
+        }
+    }
+    Ok(())
+}
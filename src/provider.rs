@@ -0,0 +1,183 @@
+//! `verify-provider` checks that the configured storage provider does what
+//! publishing relies on, before a show is trusted to it. S3-compatible
+//! providers like MinIO, R2, and B2 differ in the details: some ignore
+//! ACLs, some serve everything as `application/octet-stream`, and some
+//! public URLs don't support the byte ranges podcast apps seek with.
+//!
+//! It uploads a small audio file under `{prefix}/artifacts/` and a small
+//! feed under `{prefix}/`, the same way episodes and feeds are published,
+//! then fetches them anonymously from their public URLs, lists them, and
+//! deletes them.
+
+use crate::config::PublishingConfig;
+use crate::http::HttpClient;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::upload::{self, ObjectOptions};
+use crate::CliError;
+use reqwest::header::RANGE;
+use reqwest::{Method, StatusCode};
+use std::io::Cursor;
+use uuid::Uuid;
+
+/// Size of the audio uploaded, big enough that a range is a small part of it.
+const AUDIO_BYTES: usize = 64 * 1024;
+
+struct TestObject {
+    key: String,
+    contents: Vec<u8>,
+    options: ObjectOptions,
+    /// Whether players need to fetch parts of it
+    ranges: bool,
+}
+
+pub async fn verify_provider(
+    publishing: &PublishingConfig,
+    client: &HttpClient,
+) -> Result<(), CliError> {
+    let id = Uuid::new_v4().simple().to_string();
+    let objects = vec![
+        TestObject {
+            key: format!("{}/artifacts/verify-provider-{}.mp3", publishing.prefix, id),
+            contents: (0..AUDIO_BYTES).map(|i| (i % 251) as u8).collect(),
+            options: ObjectOptions::media(publishing),
+            ranges: true,
+        },
+        TestObject {
+            key: format!("{}/verify-provider-{}.xml", publishing.prefix, id),
+            contents: b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\"/>\n"
+                .to_vec(),
+            options: ObjectOptions::feed(publishing),
+            ranges: false,
+        },
+    ];
+
+    let backend = crate::storage::backend(publishing).await?;
+    let mut failures = 0;
+    let mut report = |check: String, result: Result<(), String>| match result {
+        Ok(()) => outln!("{}", t!("provider-pass", check = check)),
+        Err(detail) => {
+            failures += 1;
+            outln!("{}", t!("provider-fail", check = check, detail = detail));
+        }
+    };
+
+    for object in objects {
+        let size = object.contents.len() as u64;
+        let uploaded = upload::upload_object(
+            Cursor::new(object.contents.clone()),
+            size,
+            publishing,
+            object.key.clone(),
+            object.options,
+        )
+        .await;
+        let url = match uploaded {
+            Ok(uploaded) => {
+                report(t!("provider-upload", key = object.key.as_str()), Ok(()));
+                uploaded.url
+            }
+            Err(e) => {
+                report(
+                    t!("provider-upload", key = object.key.as_str()),
+                    Err(e.localized()),
+                );
+                continue;
+            }
+        };
+        if upload::dry_run() {
+            continue;
+        }
+
+        let response = client
+            .send(&url, || client.request(Method::GET, &url))
+            .await;
+        let readable = match &response {
+            Ok(response) if response.status.is_success() && response.body == object.contents => {
+                Ok(())
+            }
+            Ok(response) if response.status.is_success() => Err(t!("provider-contents-differ")),
+            Ok(response) if response.status == StatusCode::FORBIDDEN => {
+                Err(t!("provider-forbidden"))
+            }
+            Ok(response) => Err(t!("provider-status", status = response.status.to_string())),
+            Err(e) => Err(e.localized()),
+        };
+        report(t!("provider-public-url", url = url.as_str()), readable);
+
+        let served = response
+            .ok()
+            .filter(|response| response.status.is_success());
+        if let Some(response) = served {
+            let expected = upload::mime_type(&object.key);
+            let served = response.content_type.clone().unwrap_or_default();
+            let content_type = if served.starts_with(&expected) {
+                Ok(())
+            } else {
+                Err(t!(
+                    "provider-served-as",
+                    served = served,
+                    expected = expected.as_str()
+                ))
+            };
+            report(t!("provider-content-type"), content_type);
+        }
+
+        if object.ranges {
+            let response = client
+                .send(&url, || {
+                    client
+                        .request(Method::GET, &url)
+                        .header(RANGE, "bytes=100-199")
+                })
+                .await;
+            let ranges = match response {
+                Ok(response) if response.status == StatusCode::PARTIAL_CONTENT => {
+                    if response.body == object.contents[100..200] {
+                        Ok(())
+                    } else {
+                        Err(t!("provider-wrong-range"))
+                    }
+                }
+                Ok(response) => Err(t!(
+                    "provider-no-ranges",
+                    status = response.status.to_string()
+                )),
+                Err(e) => Err(e.localized()),
+            };
+            report(t!("provider-byte-ranges"), ranges);
+        }
+
+        let key = upload::storage_key(publishing, &object.key);
+        let listed = match backend.list(&key).await {
+            Ok(listed) => match listed.iter().find(|stored| stored.key == key) {
+                Some(stored) if stored.size == size => Ok(()),
+                Some(stored) => Err(t!(
+                    "provider-listed-size",
+                    bytes = stored.size,
+                    expected = size
+                )),
+                None => Err(t!("provider-not-listed", key = key.as_str())),
+            },
+            Err(e) => Err(e.localized()),
+        };
+        report(t!("provider-list"), listed);
+
+        let deleted = match backend.delete(&key).await {
+            Ok(()) => match backend.list(&key).await {
+                Ok(listed) if listed.iter().any(|stored| stored.key == key) => {
+                    Err(t!("provider-still-listed", key = key.as_str()))
+                }
+                Ok(_) => Ok(()),
+                Err(e) => Err(e.localized()),
+            },
+            Err(e) => Err(e.localized()),
+        };
+        report(t!("provider-delete"), deleted);
+    }
+
+    if failures > 0 {
+        return Err(CliError::ProviderChecksFailed(failures));
+    }
+    Ok(())
+}
@@ -0,0 +1,154 @@
+//! `prune` deletes what renamed and deleted episodes left behind in
+//! storage. Only `{prefix}/artifacts/` is looked at, since the feeds, their
+//! pages, trailers, clips, and replaced channel artwork aren't named after
+//! episodes; `prune --help` says so. An object there is kept
+//! when it is an episode's media or image, or a file published next to the
+//! media, like `2022-09-01.srt`. Episodes in the trash keep theirs, so they
+//! can still be restored.
+//!
+//! `delete-episode --remote` deletes one episode's objects the same way,
+//! keeping any that other episodes use too, like shared artwork.
+
+use crate::config::{Episode, PublishingConfig};
+use crate::i18n::t;
+use crate::output::outln;
+use crate::state::Workspace;
+use crate::storage::{StorageBackend, StoredObject};
+use crate::upload;
+use crate::CliError;
+use log::debug;
+use std::path::Path;
+
+pub async fn prune(
+    publishing: &PublishingConfig,
+    workspace: &Workspace,
+    episode_dir: &Path,
+    episodes: &[Episode],
+) -> Result<(), CliError> {
+    let mut episodes = episodes.to_vec();
+    episodes.extend(
+        crate::trash::list(episode_dir)?
+            .into_iter()
+            .map(|trashed| trashed.episode),
+    );
+
+    let backend = crate::storage::backend(publishing).await?;
+    let prefix = format!("{}/artifacts/", publishing.prefix);
+    let mut orphans: Vec<_> = backend
+        .list(&prefix)
+        .await?
+        .into_iter()
+        .filter(|object| {
+            let name = object.key.rsplit('/').next().unwrap_or_default();
+            let referenced = episodes
+                .iter()
+                .any(|episode| is_referenced(publishing, episode, name));
+            if referenced {
+                debug!("{} is referenced, keeping it", object.key);
+            }
+            !referenced
+        })
+        .collect();
+    orphans.sort_by(|a, b| a.key.cmp(&b.key));
+    delete_objects(
+        publishing,
+        workspace,
+        backend.as_ref(),
+        &orphans,
+        "prune-summary",
+    )
+    .await
+}
+
+/// Delete what belongs to `episode` in storage and to none of `others`.
+pub async fn delete_episode_objects(
+    publishing: &PublishingConfig,
+    workspace: &Workspace,
+    episode: &Episode,
+    others: &[Episode],
+) -> Result<(), CliError> {
+    let backend = crate::storage::backend(publishing).await?;
+    let prefix = format!("{}/artifacts/", publishing.prefix);
+    let mut objects: Vec<_> = backend
+        .list(&prefix)
+        .await?
+        .into_iter()
+        .filter(|object| {
+            let name = object.key.rsplit('/').next().unwrap_or_default();
+            is_referenced(publishing, episode, name)
+                && !others
+                    .iter()
+                    .any(|other| is_referenced(publishing, other, name))
+        })
+        .collect();
+    objects.sort_by(|a, b| a.key.cmp(&b.key));
+    delete_objects(
+        publishing,
+        workspace,
+        backend.as_ref(),
+        &objects,
+        "deleted-objects-summary",
+    )
+    .await
+}
+
+/// Delete `objects`, or only say what would be deleted with `--dry-run`,
+/// then print the `summary` message.
+async fn delete_objects(
+    publishing: &PublishingConfig,
+    workspace: &Workspace,
+    backend: &dyn StorageBackend,
+    objects: &[StoredObject],
+    summary: &str,
+) -> Result<(), CliError> {
+    let dry_run = upload::dry_run();
+    let lock = if dry_run {
+        None
+    } else {
+        Some(workspace.lock()?)
+    };
+    let mut bytes = 0;
+    for object in objects {
+        let size = crate::preview::format_bytes(object.size);
+        match &lock {
+            None => outln!(
+                "{}",
+                t!("prune-would-delete", key = object.key.as_str(), size = size)
+            ),
+            Some(lock) => {
+                backend.delete(&object.key).await?;
+                let key = upload::public_key(publishing, &object.key);
+                workspace.record_delete(lock, &key)?;
+                outln!(
+                    "{}",
+                    t!("prune-deleted", key = object.key.as_str(), size = size)
+                );
+            }
+        }
+        bytes += object.size;
+    }
+    outln!(
+        "{}",
+        t!(
+            summary,
+            count = objects.len(),
+            size = crate::preview::format_bytes(bytes)
+        )
+    );
+    Ok(())
+}
+
+/// Whether the object named `name` under `artifacts/` belongs to `episode`.
+fn is_referenced(publishing: &PublishingConfig, episode: &Episode, name: &str) -> bool {
+    let file_name = |url: &str| url.rsplit('/').next().map(str::to_owned);
+    if file_name(&episode.media.url).as_deref() == Some(name)
+        || file_name(&episode.image).as_deref() == Some(name)
+    {
+        return true;
+    }
+    // Sidecars are named after the media, the same way `artifact_key` names
+    // them
+    let sidecar = upload::artifact_key(publishing, episode, ".");
+    let sidecar = sidecar.rsplit('/').next().unwrap_or_default();
+    name.starts_with(sidecar)
+}
@@ -0,0 +1,360 @@
+//! Quality checks for episode audio, run by `check-audio` and before
+//! `create-episode` uploads anything, against the thresholds in
+//! channel.yaml:
+//!
+//! ```yaml
+//! quality:
+//!   minBitrate: 96
+//!   minSampleRate: 44100
+//!   minChannels: 2
+//!   loudness: -16
+//!   maxPeak: -1
+//!   maxSilenceSeconds: 10
+//! ```
+//!
+//! The bitrate, sample rate, and channels come from the file's headers.
+//! Loudness, peaks, and silence are measured by decoding the audio with
+//! ffmpeg, which must be on the `PATH`, or set `FFMPEG` to its location.
+
+use crate::audio::{self, AudioFormat};
+use crate::config::QualityConfig;
+use crate::crypto;
+use crate::i18n::t;
+use crate::output::{self, outln};
+use crate::validate::Severity;
+use crate::CliError;
+use log::debug;
+use serde::Serialize;
+use serde_json::json;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// What was measured about the audio; `None` when it wasn't.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Measurements {
+    /// kbit/s
+    pub bitrate: Option<u32>,
+    /// Hz
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    /// Integrated loudness, in LUFS
+    pub loudness: Option<f64>,
+    /// Sample peak, in dBFS
+    pub peak: Option<f64>,
+    /// Longest stretch of silence, in seconds
+    pub longest_silence: Option<f64>,
+}
+
+/// Where the audio being checked is.
+#[derive(Clone, Copy)]
+pub enum Audio<'a> {
+    File(&'a Path),
+    /// An `.age` file, decrypted as it is read and only ever passed to
+    /// ffmpeg on its stdin
+    Encrypted(&'a Path),
+}
+
+/// Measure what `config` has thresholds for.
+pub fn measure(
+    config: &QualityConfig,
+    format: AudioFormat,
+    audio: Audio,
+) -> Result<Measurements, CliError> {
+    let info = match audio {
+        Audio::File(path) => audio::read_file(format, path)?,
+        Audio::Encrypted(path) => audio::read_encrypted(format, path)?,
+    };
+    let mut measurements = Measurements {
+        bitrate: info.bitrate,
+        sample_rate: info.sample_rate,
+        channels: info.channels,
+        ..Measurements::default()
+    };
+    if decodes(config) {
+        let log = run_ffmpeg(config, audio)?;
+        measurements.loudness = last_value(&log, "I:");
+        measurements.peak = last_value(&log, "Peak:");
+        measurements.longest_silence = log
+            .lines()
+            .filter_map(|line| line.split("silence_duration:").nth(1))
+            .filter_map(|duration| duration.trim().parse::<f64>().ok())
+            .fold(None, |longest: Option<f64>, duration| {
+                Some(longest.map_or(duration, |longest| longest.max(duration)))
+            })
+            .or(Some(0.0));
+    }
+    Ok(measurements)
+}
+
+/// Whether any check is configured.
+pub fn enabled(config: &QualityConfig) -> bool {
+    config.min_bitrate.is_some()
+        || config.min_sample_rate.is_some()
+        || config.min_channels.is_some()
+        || decodes(config)
+}
+
+/// Whether a check needs the audio decoded by ffmpeg.
+fn decodes(config: &QualityConfig) -> bool {
+    config.loudness.is_some() || config.max_peak.is_some() || config.max_silence_seconds.is_some()
+}
+
+/// What falls short of the thresholds, as errors, or as warnings with
+/// `warnOnly`.
+pub fn problems(config: &QualityConfig, measurements: &Measurements) -> Vec<(Severity, String)> {
+    let mut problems = Vec::new();
+    let mut below = |minimum: Option<u32>, value: Option<u32>, what: &str, unit: &str| {
+        if let (Some(minimum), Some(value)) = (minimum, value) {
+            if value < minimum {
+                problems.push(format!(
+                    "{} is {}{}, at least {}{} is needed",
+                    what, value, unit, minimum, unit
+                ));
+            }
+        }
+    };
+    below(
+        config.min_bitrate,
+        measurements.bitrate,
+        "bitrate",
+        " kbit/s",
+    );
+    below(
+        config.min_sample_rate,
+        measurements.sample_rate,
+        "sample rate",
+        " Hz",
+    );
+    below(
+        config.min_channels,
+        measurements.channels,
+        "channel count",
+        "",
+    );
+
+    if let (Some(target), Some(loudness)) = (config.loudness, measurements.loudness) {
+        if (loudness - target).abs() > config.loudness_tolerance {
+            problems.push(format!(
+                "loudness is {:.1} LUFS, {:.1} ± {:.1} is expected",
+                loudness, target, config.loudness_tolerance
+            ));
+        }
+    }
+    if let (Some(maximum), Some(peak)) = (config.max_peak, measurements.peak) {
+        if peak > maximum {
+            problems.push(format!(
+                "peaks at {:.1} dBFS, over {:.1}, so it is likely clipped",
+                peak, maximum
+            ));
+        }
+    }
+    if let (Some(maximum), Some(silence)) =
+        (config.max_silence_seconds, measurements.longest_silence)
+    {
+        if silence > maximum {
+            problems.push(format!(
+                "has {:.1} seconds of silence, more than {:.1}",
+                silence, maximum
+            ));
+        }
+    }
+
+    let severity = if config.warn_only {
+        Severity::Warning
+    } else {
+        Severity::Error
+    };
+    problems
+        .into_iter()
+        .map(|problem| (severity, problem))
+        .collect()
+}
+
+/// Fail on problems, and print the ones that are only warnings.
+pub fn check(
+    config: &QualityConfig,
+    path: &Path,
+    format: AudioFormat,
+    audio: Audio,
+) -> Result<(), CliError> {
+    if !enabled(config) {
+        return Ok(());
+    }
+    let measurements = measure(config, format, audio)?;
+    fail_on_problems(config, path, &measurements)
+}
+
+/// `check-audio`: print what was measured about the file, and check it.
+pub fn report(config: &QualityConfig, path: &Path) -> Result<(), CliError> {
+    // Encrypted audio is only ever decrypted as it is read
+    let encrypted = crypto::is_encrypted(path);
+    let mut head = Vec::new();
+    if encrypted {
+        crypto::open(path)?.take(64).read_to_end(&mut head)?;
+    } else {
+        File::open(path)?.take(64).read_to_end(&mut head)?;
+    }
+    let format = AudioFormat::detect(path, &head)
+        .ok_or_else(|| CliError::AudioError("episode", audio::not_audio(path, &head)))?;
+    let audio = if encrypted {
+        Audio::Encrypted(path)
+    } else {
+        Audio::File(path)
+    };
+    let measurements = measure(config, format, audio)?;
+
+    outln!("{}: {}", path.display(), format.name());
+    output::record("file", path);
+    output::record("format", format.name());
+    output::record("measurements", &measurements);
+    let lines = [
+        (
+            "bitrate",
+            measurements.bitrate.map(|v| format!("{} kbit/s", v)),
+        ),
+        (
+            "sample rate",
+            measurements.sample_rate.map(|v| format!("{} Hz", v)),
+        ),
+        ("channels", measurements.channels.map(|v| v.to_string())),
+        (
+            "loudness",
+            measurements.loudness.map(|v| format!("{:.1} LUFS", v)),
+        ),
+        ("peak", measurements.peak.map(|v| format!("{:.1} dBFS", v))),
+        (
+            "longest silence",
+            measurements.longest_silence.map(|v| format!("{:.1} s", v)),
+        ),
+    ];
+    for (name, value) in lines {
+        if let Some(value) = value {
+            outln!("  {:<16} {}", format!("{}:", name), value);
+        }
+    }
+
+    fail_on_problems(config, path, &measurements)?;
+    outln!(
+        "{}",
+        t!("audio-quality-passed", path = path.display().to_string())
+    );
+    Ok(())
+}
+
+fn fail_on_problems(
+    config: &QualityConfig,
+    path: &Path,
+    measurements: &Measurements,
+) -> Result<(), CliError> {
+    let problems = problems(config, measurements);
+    output::record(
+        "problems",
+        problems
+            .iter()
+            .map(|(severity, problem)| json!({ "severity": severity, "problem": problem }))
+            .collect::<Vec<_>>(),
+    );
+    let mut errors = Vec::new();
+    for (severity, problem) in problems {
+        match severity {
+            Severity::Error => errors.push(problem),
+            Severity::Warning => eprintln!(
+                "{}",
+                t!(
+                    "audio-quality-warning",
+                    path = path.display().to_string(),
+                    problem = problem
+                )
+            ),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(CliError::AudioQuality(
+            path.display().to_string(),
+            errors.join("; "),
+        ));
+    }
+    Ok(())
+}
+
+/// Decode the audio with ffmpeg's loudness and silence filters, returning
+/// what they logged.
+fn run_ffmpeg(config: &QualityConfig, audio: Audio) -> Result<String, CliError> {
+    let program = std::env::var("FFMPEG").unwrap_or_else(|_| "ffmpeg".to_owned());
+    let input = match audio {
+        Audio::File(path) => path.to_string_lossy().into_owned(),
+        Audio::Encrypted(_) => "pipe:0".to_owned(),
+    };
+    let filters = format!(
+        "ebur128=peak=sample:framelog=verbose,silencedetect=noise={}dB:d={}",
+        config.silence_threshold,
+        config.max_silence_seconds.unwrap_or(2.0)
+    );
+    let args = [
+        "-hide_banner",
+        "-nostats",
+        "-i",
+        &input,
+        "-af",
+        &filters,
+        "-f",
+        "null",
+        "-",
+    ];
+    debug!("Running {} {:?}", program, args);
+
+    let ffmpeg_error = |detail: String| CliError::AudioError("episode", detail);
+    let (stdin, decrypted) = match audio {
+        Audio::File(_) => (Stdio::null(), None),
+        Audio::Encrypted(path) => (Stdio::piped(), Some(crypto::open(path)?)),
+    };
+    let mut child = Command::new(&program)
+        .args(args)
+        .stdin(stdin)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| ffmpeg_error(format!("unable to run {}: {}", program, e)))?;
+
+    let writer = child.stdin.take();
+    let output = std::thread::scope(|scope| {
+        // Written from another thread so ffmpeg's log can't fill up and
+        // stall it while it waits for more input. A write that fails
+        // because ffmpeg gave up shows in its exit status, but audio that
+        // fails to decrypt is an error of its own.
+        let copying = match (decrypted, writer) {
+            (Some(mut decrypted), Some(mut writer)) => {
+                Some(scope.spawn(move || io::copy(&mut decrypted, &mut writer)))
+            }
+            _ => None,
+        };
+        let output = child.wait_with_output()?;
+        if let Some(Ok(Err(e))) = copying.map(|copying| copying.join()) {
+            if e.kind() != io::ErrorKind::BrokenPipe {
+                return Err(e);
+            }
+        }
+        Ok(output)
+    })?;
+    let log = String::from_utf8_lossy(&output.stderr).into_owned();
+    if !output.status.success() {
+        let last_line = log.lines().last().unwrap_or_default();
+        return Err(ffmpeg_error(format!(
+            "{} exited with {}: {}",
+            program, output.status, last_line
+        )));
+    }
+    Ok(log)
+}
+
+/// The number after the last `label` in ffmpeg's log, like `-16.2` from
+/// `I:         -16.2 LUFS`.
+fn last_value(log: &str, label: &str) -> Option<f64> {
+    log.lines()
+        .filter_map(|line| line.trim().strip_prefix(label))
+        .filter_map(|rest| rest.split_whitespace().next()?.parse().ok())
+        .next_back()
+}
@@ -0,0 +1,1268 @@
+//! Rendering the show's feeds, and publishing them with what they
+//! reference.
+//!
+//! [`render_channel`] is `render-channel`: it reads the channel's episode
+//! files, leaves out drafts, future episodes, and those of other
+//! destinations, and either prints the feed or, with
+//! [`RenderOptions::upload`], uploads the artwork, transcripts, chapters,
+//! archive and season feeds, and then `podcast.xml`, rolling the feeds back
+//! when an upload fails part way. [`sync`], [`diff`], and
+//! [`rotate_urls`] publish or compare the same feed.
+
+use crate::config::*;
+use crate::i18n::t;
+use crate::output::{out, outln};
+use crate::{
+    approvals, artwork, cdn, changelog, chapters, checklist, crypto, dates, events, feed,
+    feeddiff, guids, history, hooks, html, http, jsonfeed, links, metrics, notes, output,
+    plugins, podping, preview, private, rollback, seasons, serve, site, state, sync, template,
+    transcript, upload, validate, wasm, watch, websub, xml, CliError,
+};
+use crate::{get_all_episode_files, get_episode_paths, parse_channel_config, parse_episode};
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use log::debug;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// How to print the feed, and with an upload, what to publish next to it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RenderFormat {
+    /// The RSS document
+    #[default]
+    Xml,
+    /// A readable summary of the channel and its episodes
+    Pretty,
+    /// A browsable web page
+    Html,
+    /// JSON Feed 1.1
+    Jsonfeed,
+}
+
+/// What [`render_channel`] renders, and whether it publishes it.
+#[derive(Debug, Default, Clone)]
+pub struct RenderOptions {
+    /// Upload the feeds and what they reference, instead of printing the
+    /// feed
+    pub upload: bool,
+    /// How to print the feed. With `upload`, `Html` also uploads
+    /// podcast.html, and `Jsonfeed` uploads feed.json
+    pub format: RenderFormat,
+    /// Fail on any `validate` problem, and leave out episodes without the
+    /// approvals channel.yaml asks for
+    pub strict: bool,
+    /// Fill in missing optional fields, and leave out broken chapters,
+    /// notes, and transcripts, for previews
+    pub lenient: bool,
+    /// Render the feed as it was then, from git history when there is some
+    pub as_of: Option<DateTime<Utc>>,
+    /// Include episodes whose `releasedAt` is still to come
+    pub include_future: bool,
+    /// `lastBuildDate` instead of now, or `SOURCE_DATE_EPOCH`
+    pub build_date: Option<DateTime<Utc>>,
+    /// Render only this season's feed
+    pub season: Option<u64>,
+    /// Render the feed of this `publishing.destinations` entry
+    pub destination: Option<String>,
+    /// Count checklist items that are confirmed by hand as done
+    pub yes: bool,
+    /// Take media sizes from storage, correcting episode files
+    pub trust_bucket: bool,
+    /// Cut fields longer than Apple allows instead of warning about them
+    pub truncate: bool,
+    /// Tell plugins not to use the network
+    pub offline: bool,
+    /// The token for a private feed's URLs, instead of the one in
+    /// `tokenEnv`
+    pub token: Option<String>,
+}
+
+impl RenderOptions {
+    /// Upload the feed as it is, for commands that publish what they change.
+    pub fn publish(yes: bool) -> Self {
+        RenderOptions {
+            upload: true,
+            yes,
+            ..Default::default()
+        }
+    }
+}
+
+/// What `render-site` writes.
+#[derive(Debug, Clone)]
+pub struct SiteOptions {
+    /// Directory to write the site to
+    pub output: PathBuf,
+    /// Directory of templates to use instead of the bundled ones
+    pub templates: Option<PathBuf>,
+    /// Upload the site under the publishing prefix
+    pub upload: bool,
+}
+
+/// Render the feed of the episodes in `episode_dir`, printing it, or with
+/// `options.upload`, publishing it.
+pub async fn render_channel(
+    episode_dir: &Path,
+    channel_config: ChannelConfig,
+    options: &RenderOptions,
+    context: &events::Context,
+) -> Result<(), CliError> {
+    let rendering = render(episode_dir, channel_config, options, None, None);
+    context.scope(rendering).await
+}
+
+/// Print what publishing would change for listeners, against the feed at
+/// `against`, a URL or a path, or the published podcast.xml. Plugins are
+/// told not to use the network when `offline`.
+pub async fn diff(
+    episode_dir: &Path,
+    channel_config: ChannelConfig,
+    against: Option<String>,
+    include_future: bool,
+    offline: bool,
+    context: &events::Context,
+) -> Result<(), CliError> {
+    let publishing = &channel_config.publishing;
+    let feed_key = format!("{}/podcast.xml", publishing.prefix);
+    let against = against.unwrap_or_else(|| upload::object_url(publishing, &feed_key));
+    let options = RenderOptions {
+        include_future,
+        offline,
+        ..Default::default()
+    };
+    let rendering = render(episode_dir, channel_config, &options, Some(&against), None);
+    context.scope(rendering).await
+}
+
+/// Publish the feed and what it references, uploading only objects that
+/// changed since the last upload from this workspace, and with `media`, the
+/// audio in it that changed.
+pub async fn sync(
+    episode_dir: &Path,
+    channel_config: ChannelConfig,
+    media: Option<&Path>,
+    yes: bool,
+    context: &events::Context,
+) -> Result<(), CliError> {
+    let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+    let workspace = state::Workspace::new(channel_dir);
+    let mut changes = sync::Changes::new(&workspace)?;
+    if let Some(media) = media {
+        let episodes = crate::get_all_episodes(episode_dir)?;
+        let publishing = &channel_config.publishing;
+        let synced = sync::sync_media(publishing, &workspace, media, &episodes, &mut changes);
+        context.scope(synced).await?;
+    }
+
+    let options = RenderOptions::publish(yes);
+    let only_changed = Some(&mut changes);
+    let rendering = render(episode_dir, channel_config, &options, None, only_changed);
+    context.scope(rendering).await?;
+    changes.print_summary();
+    Ok(())
+}
+
+/// Publish a private feed again with fresh URLs. With tokens, a new token
+/// is made, or `token` is used; once the feed is published it's saved to
+/// the channel directory's `.env` and the old one stops being accepted.
+/// With presigned URLs, they are signed again.
+pub async fn rotate_urls(
+    episode_dir: &Path,
+    channel_config: ChannelConfig,
+    token: Option<String>,
+    yes: bool,
+    context: &events::Context,
+) -> Result<(), CliError> {
+    let private = match &channel_config.publishing.private {
+        Some(private) => private.clone(),
+        None => return Err(CliError::NotPrivate),
+    };
+    let publishing = channel_config.publishing.clone();
+    if private.urls == PrivateUrls::Presigned {
+        let options = RenderOptions::publish(yes);
+        render_channel(episode_dir, channel_config, &options, context).await?;
+        outln!(
+            "{}",
+            t!("presigned-still-valid", days = private.expires_days)
+        );
+        return Ok(());
+    }
+
+    let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+    let env_file = channel_dir.join(".env");
+    let old = private::read_token(&env_file, &private.token_env)
+        .or_else(|| std::env::var(&private.token_env).ok());
+    let token = token.unwrap_or_else(private::new_token);
+    let options = RenderOptions {
+        token: Some(token.clone()),
+        ..RenderOptions::publish(yes)
+    };
+
+    // Accept both while the feed with the new token goes out
+    let both: Vec<&str> = old
+        .iter()
+        .map(String::as_str)
+        .chain([token.as_str()])
+        .collect();
+    context
+        .scope(private::publish_tokens(&publishing, &both))
+        .await?;
+    render_channel(episode_dir, channel_config, &options, context).await?;
+
+    // Only now that the feed with the new token is out; until then `.env`
+    // keeps the old one, which is still accepted
+    if !context.is_dry_run() {
+        private::save_token(&env_file, &private.token_env, &token)?;
+        outln!(
+            "{}",
+            t!(
+                "token-saved",
+                name = private.token_env.as_str(),
+                path = env_file.display().to_string()
+            )
+        );
+    }
+    context
+        .scope(private::publish_tokens(&publishing, &[&token]))
+        .await?;
+    if old.is_some() {
+        outln!("{}", t!("token-revoked"));
+    }
+    Ok(())
+}
+
+/// `render-channel --watch`: render, then render again after every change,
+/// reading channel.yaml again each time. Files must stay unchanged for
+/// `debounce` first.
+pub async fn watch(
+    channel_file: &Path,
+    episode_dir: &Path,
+    options: &RenderOptions,
+    debounce: std::time::Duration,
+    context: &events::Context,
+) -> Result<(), CliError> {
+    let watched = [channel_file.to_path_buf(), episode_dir.to_path_buf()];
+    let format = crate::formats::Format::of(channel_file);
+    let mut watcher = watch::Watcher::new(&watched)?;
+    loop {
+        let channel_config = fs::read_to_string(channel_file)
+            .map_err(CliError::from)
+            .and_then(|text| parse_channel_config(format, &text, &[]));
+        let rendered = match channel_config {
+            Ok(channel_config) => {
+                render_channel(episode_dir, channel_config, options, context).await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = rendered {
+            context.warn(t!("error", message = e.localized()));
+        }
+        eprintln!("{}", t!("watch-waiting"));
+        watcher.changed(debounce).await;
+    }
+}
+
+/// The feed as `serve` shows it: rendered from the files as they are now,
+/// with media and images found in `media_dir` served from `base_url`.
+/// Transcripts and chapters are left out, since they are only published
+/// by uploading.
+pub fn render_preview(
+    episode_dir: &Path,
+    channel_config: &ChannelConfig,
+    media_dir: &Path,
+    base_url: &str,
+) -> Result<String, CliError> {
+    let local = |url: &mut String| {
+        if let Some(local) = serve::local_url(media_dir, base_url, url) {
+            *url = local;
+        }
+    };
+    let mut channel = channel_config.channel.clone();
+    local(&mut channel.image);
+    let timezone = dates::timezone(channel.timezone.as_deref())?;
+
+    let mut episodes = Vec::new();
+    let mut item_assets = HashMap::new();
+    for (path, mut episode) in get_all_episode_files(episode_dir)? {
+        let media_name = episode.media.url.rsplit('/').next().unwrap_or_default();
+        let local_media = fs::metadata(media_dir.join(media_name)).ok();
+        if let Some(metadata) = local_media.filter(|metadata| metadata.is_file()) {
+            episode.media.bytes.0 = metadata.len();
+        }
+        guids::fill(&channel_config.publishing, timezone, &mut episode)?;
+        local(&mut episode.media.url);
+        local(&mut episode.image);
+        template::expand_episode(&mut episode);
+        if let Some(notes) = notes::load(&path, &episode)? {
+            let assets = xml::ItemAssets {
+                show_notes: Some(notes::to_html(&notes)),
+                soundbites: chapters::soundbites(&notes, episode.media.duration.0),
+                ..Default::default()
+            };
+            item_assets.insert(episode.id.clone(), assets);
+        }
+        episodes.push(episode);
+    }
+
+    let publishing = &channel_config.publishing;
+    let feed_key = format!("{}/podcast.xml", publishing.prefix);
+    let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+    let podcast_guids = state::Workspace::new(channel_dir).podcast_guids()?;
+    let feed_options = xml::FeedOptions {
+        // As published, so `podcast:guid` is the same
+        podcast_guid: Some(podcast_guid(&podcast_guids, publishing, &feed_key)),
+        item_assets,
+        ..Default::default()
+    };
+    xml::generate_podcast_xml(&channel, &episodes, &feed_options)
+}
+
+/// `podcast:guid` of the feed at `key`: as it was first published, or
+/// derived from its URL.
+fn podcast_guid(
+    podcast_guids: &BTreeMap<String, String>,
+    publishing: &PublishingConfig,
+    key: &str,
+) -> String {
+    podcast_guids
+        .get(key)
+        .cloned()
+        .unwrap_or_else(|| xml::podcast_guid(&upload::object_url(publishing, key)))
+}
+
+/// Write the archive website, a page per released episode, and with
+/// `options.upload`, upload it under the publishing prefix's `site/`.
+pub async fn render_site(
+    episode_dir: &Path,
+    channel_config: &ChannelConfig,
+    options: &SiteOptions,
+    context: &events::Context,
+) -> Result<(), CliError> {
+    let publishing = &channel_config.publishing;
+    let mut episodes = Vec::new();
+    let published = get_all_episode_files(episode_dir)?
+        .into_iter()
+        .filter(|(_, episode)| !episode.draft && upload::listed_in(publishing, None, episode));
+    for (path, mut episode) in published {
+        let media_publishing = upload::destination(publishing, episode.destination.as_deref())?;
+        episode.media.url = upload::canonical_url(&media_publishing, &episode.media.url);
+        template::expand_episode(&mut episode);
+        let notes = notes::load(&path, &episode)?.map(|notes| notes::to_html(&notes));
+        episodes.push(site::SiteEpisode {
+            page: site::page_name(&path),
+            episode,
+            notes,
+        });
+    }
+
+    let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+    let mut channel = channel_config.channel.clone();
+    if let Some(artwork) = artwork::local(channel_dir, publishing, &channel.image)? {
+        let site_episodes = episodes.iter_mut().map(|page| &mut page.episode);
+        artwork.substitute(&mut channel, site_episodes);
+    }
+
+    let templates = site::Templates::load(options.templates.as_deref())?;
+    let feed_url = upload::object_url(publishing, &format!("{}/podcast.xml", publishing.prefix));
+    let pages = site::render_site(
+        &templates,
+        &channel,
+        &mut episodes,
+        &feed_url,
+        |page| site::page_url(publishing, page),
+    );
+
+    fs::create_dir_all(&options.output)?;
+    for (page, html) in &pages {
+        fs::write(options.output.join(page), html)?;
+    }
+    outln!(
+        "{}",
+        t!(
+            "site-written",
+            count = pages.len(),
+            path = options.output.display().to_string()
+        )
+    );
+
+    if options.upload {
+        let workspace = state::Workspace::new(channel_dir);
+        let lock = workspace.lock()?;
+        let pending: Vec<upload::PendingObject> = pages
+            .into_iter()
+            .map(|(page, html)| upload::PendingObject {
+                key: site::page_key(publishing, &page),
+                contents: html.into_bytes(),
+                options: upload::ObjectOptions::feed(publishing),
+            })
+            .collect();
+        let keys: Vec<(String, u64)> =
+            pending.iter().map(|page| (page.key.clone(), page.contents.len() as u64)).collect();
+        let uploads = upload::upload_objects(publishing, pending);
+        let results = context.scope(uploads).await;
+        for ((key, size), result) in keys.into_iter().zip(results) {
+            let uploaded = result?;
+            workspace.record_upload(&lock, &key, &uploaded, size)?;
+            outln!("{}", t!("uploaded-site-page", url = uploaded.url.as_str()));
+        }
+    }
+    Ok(())
+}
+
+/// HEAD each episode's media and use the size storage reports, writing it
+/// back to episode files that had a different one. Media that can't be
+/// reached keeps the size in its file.
+async fn trust_bucket_sizes(
+    channel_config: &ChannelConfig,
+    paths: &[PathBuf],
+    episodes: &mut [Episode],
+) -> Result<(), CliError> {
+    let client = http::HttpClient::new(&channel_config.http)?;
+    let client = &client;
+    let sizes: Vec<Result<u64, String>> = stream::iter(episodes.iter())
+        .map(|episode| async move {
+            match links::check_url(client, &episode.media.url).await {
+                Ok(response) if response.status.is_success() => response
+                    .content_length
+                    .ok_or_else(|| "no Content-Length".to_owned()),
+                Ok(response) => Err(format!("HTTP {}", response.status)),
+                Err(e) => Err(e.localized()),
+            }
+        })
+        .buffered(client.concurrency())
+        .collect()
+        .await;
+
+    for ((path, episode), size) in paths.iter().zip(episodes.iter_mut()).zip(sizes) {
+        let size = match size {
+            Ok(size) if size != episode.media.bytes.0 => size,
+            Ok(_) => continue,
+            Err(detail) => {
+                events::warn(t!(
+                    "media-size-unknown",
+                    episode = episode.id.as_str(),
+                    detail = detail
+                ));
+                continue;
+            }
+        };
+        outln!(
+            "{}",
+            t!(
+                "media-size-updated",
+                episode = episode.id.as_str(),
+                old = episode.media.bytes.0,
+                new = size
+            )
+        );
+        episode.media.bytes.0 = size;
+
+        // Only the size changes in the file, not placeholders or rewritten
+        // URLs from rendering
+        let mut saved = parse_episode(path, &crypto::read(path)?, None)?;
+        saved.media.bytes.0 = size;
+        crate::save_episode(path, &saved, channel_config)?;
+    }
+    Ok(())
+}
+
+/// Cut the fields Apple limits down to size, for `--truncate`.
+fn truncate_long_fields(channel: &mut ChannelDetails, episodes: &mut [Episode]) {
+    let mut fields: Vec<(String, &mut String, usize)> = vec![
+        ("channel subtitle".to_owned(), &mut channel.subtitle, validate::MAX_SUBTITLE),
+        ("channel summary".to_owned(), &mut channel.summary, validate::MAX_DESCRIPTION),
+    ];
+    for episode in episodes.iter_mut() {
+        // The summary is published as the episode's subtitle
+        let summary = format!("{} summary", episode.id);
+        let description = format!("{} description", episode.id);
+        fields.push((summary, &mut episode.summary, validate::MAX_SUBTITLE));
+        fields.push((description, &mut episode.description, validate::MAX_DESCRIPTION));
+    }
+
+    for (field, value, max) in fields {
+        if let Some(truncated) = validate::truncate(value, max) {
+            events::warn(t!("truncated-field", field = field, length = max));
+            *value = truncated;
+        }
+    }
+}
+
+/// Render, then upload, print, or with `diff`, compare with the feed
+/// there. With `sync_changes`, only objects that changed are uploaded.
+async fn render(
+    episode_dir: &Path,
+    mut channel_config: ChannelConfig,
+    render_options: &RenderOptions,
+    diff: Option<&str>,
+    mut sync_changes: Option<&mut sync::Changes>,
+) -> Result<(), CliError> {
+    let started = Instant::now();
+    // Feeds for a destination are published to its storage
+    let main_publishing = channel_config.publishing.clone();
+    let destination = render_options.destination.as_deref();
+    channel_config.publishing = upload::destination(&main_publishing, destination)?;
+    let historical = match render_options.as_of {
+        Some(as_of) => {
+            let files = history::episode_files_as_of(episode_dir, as_of)?;
+            if files.is_none() {
+                events::warn(t!(
+                    "no-history",
+                    path = episode_dir.display().to_string(),
+                    date = as_of.to_string()
+                ));
+            }
+            files
+        }
+        None => None,
+    };
+    let sources = match historical {
+        Some(files) => files,
+        None => get_episode_paths(episode_dir)?
+            .into_iter()
+            .filter(|path| crypto::readable(path))
+            .map(|path| crypto::read(&path).map(|contents| (path, contents)))
+            .collect::<Result<_, _>>()?,
+    };
+
+    let placeholders = if render_options.lenient {
+        Some(&channel_config.channel)
+    } else {
+        None
+    };
+    let now = Utc::now();
+    let mut episode_files = Vec::new();
+    for (path, contents) in sources {
+        let episode = parse_episode(&path, &contents, placeholders)?;
+        if episode.draft {
+            events::warn(t!("draft-left-out", episode = episode.id.as_str()));
+            continue;
+        }
+        if !upload::listed_in(&main_publishing, destination, &episode) {
+            let elsewhere = episode.destination.as_deref().unwrap_or_default();
+            events::warn(t!(
+                "destination-left-out",
+                episode = episode.id.as_str(),
+                destination = elsewhere
+            ));
+            continue;
+        }
+        if let Some(as_of) = render_options.as_of {
+            if episode.released_at > as_of {
+                continue;
+            }
+        } else if !render_options.include_future && episode.released_at > now {
+            events::warn(t!(
+                "not-released-yet",
+                episode = episode.id.as_str(),
+                date = episode.released_at.to_rfc3339()
+            ));
+            continue;
+        }
+        if render_options.strict {
+            let missing = approvals::missing_approval(&channel_config.approvals, &path, &episode);
+            if let Some(reason) = missing {
+                events::warn(t!(
+                    "not-approved",
+                    episode = episode.id.as_str(),
+                    reason = reason
+                ));
+                continue;
+            }
+        }
+        episode_files.push((path, episode));
+    }
+    let episode_paths: Vec<PathBuf> = episode_files.iter().map(|(path, _)| path.clone()).collect();
+    let timezone = dates::timezone(channel_config.channel.timezone.as_deref())?;
+    let mut episodes: Vec<Episode> = Vec::new();
+    for (_, mut episode) in episode_files {
+        let publishing = upload::destination(&main_publishing, episode.destination.as_deref())?;
+        episode.media.url = upload::canonical_url(&publishing, &episode.media.url);
+        guids::fill(&main_publishing, timezone, &mut episode)?;
+        episodes.push(episode);
+    }
+    let channel_dir = episode_dir.parent().unwrap_or(episode_dir).to_path_buf();
+    // Local channel artwork is published, and referenced, as an upload
+    let local_artwork = artwork::local(
+        &channel_dir,
+        &channel_config.publishing,
+        &channel_config.channel.image,
+    )?;
+    if let Some(artwork) = &local_artwork {
+        artwork.check()?;
+        artwork.substitute(&mut channel_config.channel, episodes.iter_mut());
+    }
+    site::fill_links(
+        &channel_config.publishing,
+        &mut channel_config.channel,
+        episode_paths.iter().map(PathBuf::as_path).zip(episodes.iter_mut()),
+    );
+    if render_options.trust_bucket {
+        trust_bucket_sizes(&channel_config, &episode_paths, &mut episodes).await?;
+    }
+    if render_options.truncate {
+        truncate_long_fields(&mut channel_config.channel, &mut episodes);
+    }
+
+    debug!("List episodes {:?}", episodes);
+
+    let issues = if render_options.strict {
+        let numbering = channel_config.seasons.numbering;
+        validate::validate(&channel_config.channel, numbering, &episodes, None, None).await
+    } else {
+        validate::check_ordering(
+            channel_config.channel.medium.unwrap_or_default(),
+            channel_config.seasons.numbering,
+            &episodes,
+        )
+    };
+    // Ordering problems only warn, uploads included; `--strict` fails on
+    // them with everything else `validate` finds
+    for issue in &issues {
+        events::warn(issue.to_string());
+    }
+    if render_options.strict && !issues.is_empty() {
+        return Err(CliError::ValidationFailed(issues.len()));
+    }
+    if render_options.upload {
+        let unfinished: Vec<String> = episodes
+            .iter()
+            .filter_map(|episode| {
+                let fields = validate::placeholder_fields(episode);
+                (!fields.is_empty()).then(|| format!("{} ({})", episode.id, fields.join(", ")))
+            })
+            .collect();
+        if !unfinished.is_empty() {
+            return Err(CliError::PlaceholderText(unfinished.join(", ")));
+        }
+    }
+
+    for episode in episodes.iter_mut() {
+        template::expand_episode(episode);
+    }
+    // Private feeds get URLs only subscribers have, when they are published
+    let signer = match render_options.upload {
+        true => {
+            let token = render_options.token.as_deref();
+            private::Signer::new(&channel_config.publishing, token).await?
+        }
+        false => None,
+    };
+    if let Some(signer) = &signer {
+        channel_config.channel.image = signer.url(&channel_config.channel.image).await?;
+        for episode in episodes.iter_mut() {
+            episode.media.url = signer.url(&episode.media.url).await?;
+            episode.image = signer.url(&episode.image).await?;
+        }
+    }
+
+    if render_options.upload {
+        checklist::run(
+            &channel_config,
+            &channel_dir,
+            &episode_paths,
+            &episodes,
+            render_options.yes,
+        )?;
+    }
+
+    let publishing = &channel_config.publishing;
+    let feed_key = format!("{}/podcast.xml", publishing.prefix);
+    let recent_key = format!("{}/podcast-recent.xml", publishing.prefix);
+    let json_feed_key = format!("{}/feed.json", publishing.prefix);
+    // Feeds of the main destination keep the GUID they were first
+    // published with; feeds for others are derived from their URLs
+    let podcast_guids = match destination {
+        None => state::Workspace::new(&channel_dir).podcast_guids()?,
+        Some(_) => BTreeMap::new(),
+    };
+    let mut feed_guids = vec![(
+        feed_key.clone(),
+        podcast_guid(&podcast_guids, publishing, &feed_key),
+    )];
+
+    let mut published_transcripts = Vec::new();
+    let mut published_chapters = Vec::new();
+    let mut item_assets = HashMap::new();
+    for (path, episode) in episode_paths.iter().zip(&episodes) {
+        let transcripts = transcript::published_transcripts(publishing, episode_dir, episode);
+        let transcripts = match transcripts {
+            Err(e) if render_options.lenient => {
+                events::warn(t!(
+                    "transcripts-left-out",
+                    episode = episode.id.as_str(),
+                    detail = e.localized()
+                ));
+                Vec::new()
+            }
+            other => other?,
+        };
+        let mut assets = xml::ItemAssets {
+            transcripts: transcripts
+                .iter()
+                .map(|transcript| xml::TranscriptLink {
+                    url: upload::object_url(publishing, &transcript.key),
+                    mime_type: transcript.mime_type.to_owned(),
+                    language: transcript.language.clone(),
+                })
+                .collect(),
+            chapters_url: None,
+            show_notes: None,
+            soundbites: Vec::new(),
+        };
+        published_transcripts.extend(transcripts);
+
+        match notes::load(path, episode) {
+            Ok(Some(notes)) => {
+                assets.show_notes = Some(notes::to_html(&notes));
+                assets.soundbites = chapters::soundbites(&notes, episode.media.duration.0);
+            }
+            Ok(None) => {}
+            Err(e) if render_options.lenient => events::warn(t!(
+                "notes-left-out",
+                episode = episode.id.as_str(),
+                detail = e.localized()
+            )),
+            Err(e) => return Err(e),
+        }
+
+        let episode_chapters = chapters::load(path, episode).and_then(|chapters| {
+            if chapters.is_empty() {
+                Ok(None)
+            } else {
+                chapters::to_json(episode, &chapters).map(Some)
+            }
+        });
+        let episode_chapters = match episode_chapters {
+            Err(e) if render_options.lenient => {
+                events::warn(t!(
+                    "chapters-left-out",
+                    episode = episode.id.as_str(),
+                    detail = e.localized()
+                ));
+                None
+            }
+            other => other?,
+        };
+        if let Some(json) = episode_chapters {
+            let key = chapters::object_key(publishing, episode);
+            assets.chapters_url = Some(upload::object_url(publishing, &key));
+            published_chapters.push((key, json));
+        }
+        item_assets.insert(episode.id.clone(), assets);
+    }
+
+    if let Some(signer) = &signer {
+        for assets in item_assets.values_mut() {
+            for transcript in assets.transcripts.iter_mut() {
+                transcript.url = signer.url(&transcript.url).await?;
+            }
+            if let Some(chapters_url) = &assets.chapters_url {
+                assets.chapters_url = Some(signer.url(chapters_url).await?);
+            }
+        }
+    }
+
+    // Feeds apps subscribe to name the hub; archive pages never change, and
+    // private feeds aren't announced
+    let websub = |key: &str| {
+        publishing.websub_hub.as_ref().filter(|_| signer.is_none()).map(|hub| xml::WebSub {
+            hub: hub.clone(),
+            self_url: upload::object_url(publishing, key),
+        })
+    };
+    let mut feed_options = xml::FeedOptions {
+        extras: wasm::apply_plugins(&channel_dir, &channel_config, &mut episodes)?,
+        stylesheet_href: (publishing.stylesheet.is_some() || publishing.default_stylesheet)
+            .then(|| "podcast.xsl".to_owned()),
+        related_feeds: Vec::new(),
+        archive_links: Vec::new(),
+        archive: false,
+        podcast_guid: Some(feed_guids[0].1.clone()),
+        item_assets,
+        built_at: render_options
+            .build_date
+            .or(render_options.as_of)
+            .or_else(|| dates::parse_build_date(&std::env::var("SOURCE_DATE_EPOCH").ok()?).ok()),
+        websub: websub(&feed_key),
+    };
+
+    let rendered_recent = match &publishing.recent_feed {
+        Some(recent) => {
+            let mut latest = episodes.clone();
+            latest.sort_by(|a, b| b.released_at.cmp(&a.released_at));
+            latest.truncate(recent.episodes);
+            let recent_options = xml::FeedOptions {
+                extras: feed_options.extras.clone(),
+                stylesheet_href: feed_options.stylesheet_href.clone(),
+                related_feeds: vec![xml::RelatedFeed {
+                    href: upload::object_url(publishing, &feed_key),
+                    title: "All episodes".to_owned(),
+                }],
+                archive_links: Vec::new(),
+                archive: false,
+                podcast_guid: feed_options.podcast_guid.clone(),
+                item_assets: feed_options.item_assets.clone(),
+                built_at: feed_options.built_at,
+                websub: websub(&recent_key),
+            };
+            feed_options.related_feeds.push(xml::RelatedFeed {
+                href: upload::object_url(publishing, &recent_key),
+                title: "Latest episodes".to_owned(),
+            });
+            Some(xml::generate_podcast_xml(&channel_config.channel, &latest, &recent_options)?)
+        }
+        None => None,
+    };
+
+    // With `maxItems`, podcast.xml keeps the latest episodes and older ones
+    // fill archive pages from the oldest, so only the newest page changes
+    let mut chronological: Vec<&Episode> = episodes.iter().collect();
+    chronological.sort_by_key(|episode| episode.released_at);
+    let max_items = publishing.max_items.filter(|max| *max > 0);
+    let archived = max_items.map_or(0, |max| chronological.len().saturating_sub(max));
+    let archive_key = |page: usize| format!("{}/podcast-archive-{}.xml", publishing.prefix, page);
+    let mut rendered_archives = Vec::new();
+    if let Some(max) = max_items.filter(|_| archived > 0) {
+        let pages: Vec<Vec<Episode>> = chronological[..archived]
+            .chunks(max)
+            .map(|page| page.iter().map(|episode| (*episode).clone()).collect())
+            .collect();
+        for (index, page) in pages.iter().enumerate() {
+            let number = index + 1;
+            let mut archive_links = vec![xml::ArchiveLink {
+                rel: "current",
+                href: upload::object_url(publishing, &feed_key),
+            }];
+            if number > 1 {
+                archive_links.push(xml::ArchiveLink {
+                    rel: "prev-archive",
+                    href: upload::object_url(publishing, &archive_key(number - 1)),
+                });
+            }
+            if number < pages.len() {
+                archive_links.push(xml::ArchiveLink {
+                    rel: "next-archive",
+                    href: upload::object_url(publishing, &archive_key(number + 1)),
+                });
+            }
+            let archive_options = xml::FeedOptions {
+                extras: feed_options.extras.clone(),
+                stylesheet_href: feed_options.stylesheet_href.clone(),
+                related_feeds: Vec::new(),
+                archive_links,
+                archive: true,
+                podcast_guid: feed_options.podcast_guid.clone(),
+                item_assets: feed_options.item_assets.clone(),
+                built_at: feed_options.built_at,
+                websub: None,
+            };
+            let rendered =
+                xml::generate_podcast_xml(&channel_config.channel, page, &archive_options)?;
+            rendered_archives.push((archive_key(number), rendered));
+        }
+        feed_options.archive_links.push(xml::ArchiveLink {
+            rel: "prev-archive",
+            href: upload::object_url(publishing, &archive_key(pages.len())),
+        });
+    }
+    let archived_ids: HashSet<&str> =
+        chronological[..archived].iter().map(|episode| episode.id.as_str()).collect();
+    let current: Vec<Episode> = episodes
+        .iter()
+        .filter(|episode| !archived_ids.contains(episode.id.as_str()))
+        .cloned()
+        .collect();
+
+    // Season feeds sit a directory below podcast.xml, and link back to it
+    let season_numbers: Vec<u64> = match render_options.season {
+        Some(season) => vec![season],
+        None if publishing.per_season_feeds && render_options.upload => {
+            let numbers: BTreeSet<u64> = episodes.iter().map(|episode| episode.season).collect();
+            numbers.into_iter().collect()
+        }
+        None => Vec::new(),
+    };
+    let mut rendered_seasons = Vec::new();
+    for season in season_numbers {
+        let season_episodes: Vec<Episode> = episodes
+            .iter()
+            .filter(|episode| episode.season == season)
+            .cloned()
+            .collect();
+        if season_episodes.is_empty() {
+            return Err(CliError::NoSeasonEpisodes(season));
+        }
+        let key = seasons::feed_key(publishing, season);
+        let guid = podcast_guid(&podcast_guids, publishing, &key);
+        feed_guids.push((key.clone(), guid.clone()));
+        let season_options = xml::FeedOptions {
+            extras: feed_options.extras.clone(),
+            stylesheet_href: feed_options
+                .stylesheet_href
+                .as_ref()
+                .map(|href| format!("../{}", href)),
+            related_feeds: vec![xml::RelatedFeed {
+                href: upload::object_url(publishing, &feed_key),
+                title: "All episodes".to_owned(),
+            }],
+            archive_links: Vec::new(),
+            archive: false,
+            podcast_guid: Some(guid),
+            item_assets: feed_options.item_assets.clone(),
+            built_at: feed_options.built_at,
+            websub: websub(&key),
+        };
+        let channel =
+            seasons::feed_channel(&channel_config.seasons, &channel_config.channel, season);
+        let rendered = xml::generate_podcast_xml(&channel, &season_episodes, &season_options)?;
+        rendered_seasons.push((key, channel, season_episodes, rendered));
+    }
+
+    let rendered_podcast =
+        xml::generate_podcast_xml(&channel_config.channel, &current, &feed_options)?;
+    events::emit(events::Event::RenderComplete {
+        feeds: 1 + rendered_seasons.len(),
+        episodes: current.len(),
+        bytes: rendered_podcast.len() as u64,
+    });
+    // Apps would list episodes whose GUID changed as new ones
+    if render_options.as_of.is_none() && destination.is_none() {
+        let workspace = state::Workspace::new(&channel_dir);
+        if let Ok(published) = fs::read_to_string(workspace.published_feed_path()) {
+            guids::warn_changed(&published, &rendered_podcast)?;
+        }
+    }
+
+    plugins::run_stage(
+        &channel_config.plugins,
+        &plugins::StagePayload {
+            stage: PluginStage::Render,
+            offline: render_options.offline,
+            channel: &channel_config.channel,
+            episodes: &episodes,
+            feed: Some(&rendered_podcast),
+            feed_url: None,
+        },
+    )?;
+
+    if render_options.upload {
+        let workspace = state::Workspace::new(&channel_dir);
+        let lock = workspace.lock()?;
+
+        // Everything the feed references goes up before the feed itself
+        let mut objects: Vec<(String, Vec<u8>, upload::ObjectOptions, &str)> = Vec::new();
+        if let Some(artwork) = local_artwork {
+            let options = upload::ObjectOptions {
+                content_type: Some(artwork.content_type.to_owned()),
+                ..upload::ObjectOptions::media(publishing)
+            };
+            objects.push((artwork.key, artwork.bytes, options, "uploaded-artwork"));
+        }
+        for transcript in published_transcripts {
+            if !transcript.source.exists() {
+                return Err(CliError::TranscriptError(format!(
+                    "{} does not exist",
+                    transcript.source.display()
+                )));
+            }
+            let contents = crypto::read(&transcript.source)?;
+            let options = upload::ObjectOptions::feed(publishing);
+            objects.push((transcript.key, contents, options, "uploaded-transcript"));
+        }
+        for (key, json) in published_chapters {
+            let options = upload::ObjectOptions::feed(publishing);
+            objects.push((key, json.into_bytes(), options, "uploaded-chapters"));
+        }
+        // With --season, only the season's feed is published
+        let whole_show = render_options.season.is_none();
+        if whole_show && render_options.format == RenderFormat::Html {
+            let html = html::render_html(&channel_config.channel, &episodes);
+            let key = format!("{}/podcast.html", publishing.prefix);
+            let options = upload::ObjectOptions::feed(publishing);
+            objects.push((key, html.into_bytes(), options, "uploaded-page"));
+        }
+        if whole_show && render_options.format == RenderFormat::Jsonfeed {
+            let json = jsonfeed::render_json_feed(
+                &channel_config.channel,
+                &episodes,
+                &upload::object_url(publishing, &json_feed_key),
+                &feed_options.item_assets,
+            )?;
+            if let Some(key) = upload::uncompressed_key(publishing, &json_feed_key) {
+                let options = upload::ObjectOptions::subscribed(publishing);
+                let json = json.clone().into_bytes();
+                objects.push((key, json, options, "uploaded-uncompressed-feed"));
+            }
+            let json = json.into_bytes();
+            let (json, options) = upload::feed_object(publishing, &json_feed_key, json)?;
+            objects.push((json_feed_key, json, options, "uploaded-json-feed"));
+        }
+        let stylesheet = match &publishing.stylesheet {
+            Some(stylesheet) => Some(fs::read(channel_dir.join(stylesheet))?),
+            None if publishing.default_stylesheet => {
+                Some(xml::DEFAULT_STYLESHEET.as_bytes().to_vec())
+            }
+            None => None,
+        };
+        if let Some(contents) = stylesheet {
+            let key = format!("{}/podcast.xsl", publishing.prefix);
+            let options = upload::ObjectOptions::feed(publishing);
+            objects.push((key, contents, options, "uploaded-stylesheet"));
+        }
+        let rendered_recent = rendered_recent.filter(|_| whole_show);
+        if let (Some(recent), Some(recent_feed)) = (&publishing.recent_feed, rendered_recent) {
+            let options = upload::ObjectOptions {
+                cache_control: Some(format!("max-age={}", recent.max_age_seconds)),
+                subscribed: true,
+                ..Default::default()
+            };
+            let recent_feed = recent_feed.into_bytes();
+            objects.push((recent_key.clone(), recent_feed, options, "uploaded-recent-feed"));
+        }
+        // Feeds go up last, once the CDN serves what they reference
+        let first_feed = objects.len();
+        for (key, archive) in rendered_archives.into_iter().filter(|_| whole_show) {
+            let options = upload::ObjectOptions::subscribed(publishing);
+            objects.push((key, archive.into_bytes(), options, "uploaded-archive-feed"));
+        }
+        for (key, _, _, season_feed) in rendered_seasons {
+            let options = upload::ObjectOptions::subscribed(publishing);
+            objects.push((key, season_feed.into_bytes(), options, "uploaded-season-feed"));
+        }
+        if whole_show {
+            let feed = rendered_podcast.clone().into_bytes();
+            if let Some(key) = upload::uncompressed_key(publishing, &feed_key) {
+                let options = upload::ObjectOptions::subscribed(publishing);
+                objects.push((key, feed.clone(), options, "uploaded-uncompressed-feed"));
+            }
+            let (feed, options) = upload::feed_object(publishing, &feed_key, feed)?;
+            objects.push((feed_key.clone(), feed, options, "uploaded-feed"));
+        }
+
+        // Uploaded several at a time: first what the feeds reference, then
+        // the archive and season feeds, then podcast.xml, which links to them
+        let (main_feed, feeds): (Vec<_>, Vec<_>) =
+            objects.split_off(first_feed).into_iter().partition(|(key, ..)| *key == feed_key);
+        let feed_keys: Vec<(String, bool)> = feeds
+            .iter()
+            .chain(&main_feed)
+            .map(|(key, _, options, _)| (key.clone(), options.content_encoding.is_some()))
+            .collect();
+        let mut snapshots = Vec::new();
+        let mut replaced = Vec::new();
+        let mut feed_uploaded = false;
+        let mut objects_uploaded = 0;
+        let mut bytes_uploaded = 0;
+        let mut cdn_objects = Vec::new();
+        let mut hub_topics = Vec::new();
+        for (batch, batch_objects) in [objects, feeds, main_feed].into_iter().enumerate() {
+            let are_feeds = batch > 0;
+            if batch == 1 && !feed_keys.is_empty() && !upload::dry_run() {
+                let client = http::HttpClient::new(&channel_config.http)?;
+                if let Some(verify_cdn) = &publishing.verify_cdn {
+                    let published = fs::read_to_string(workspace.published_feed_path())
+                        .unwrap_or_default();
+                    let new_media = episodes
+                        .iter()
+                        .filter(|episode| !published.contains(&episode.media.url))
+                        .map(|episode| cdn::CdnObject {
+                            url: episode.media.url.clone(),
+                            bytes: episode.media.bytes.0,
+                            content_type: episode.media.enclosure_type().to_owned(),
+                        });
+                    cdn_objects.extend(new_media);
+                    let objects = std::mem::take(&mut cdn_objects);
+                    cdn::wait_until_served(&client, verify_cdn, objects).await?;
+                }
+                let signer = signer.as_ref();
+                snapshots = rollback::take(&client, publishing, signer, &feed_keys).await;
+            }
+
+            let mut pending = Vec::new();
+            let mut messages = Vec::new();
+            for (key, contents, options, message) in batch_objects {
+                if let Some(changes) = sync_changes.as_mut() {
+                    if !changes.needs_upload(&key, &sync::md5_hex(&contents)) {
+                        continue;
+                    }
+                }
+                if are_feeds {
+                    replaced.push(key.clone());
+                }
+                messages.push((key.clone(), contents.len() as u64, message));
+                pending.push(upload::PendingObject { key, contents, options });
+            }
+            let results = upload::upload_objects(publishing, pending).await;
+
+            let mut failure = None;
+            for ((key, size, message), result) in messages.into_iter().zip(results) {
+                let uploaded = match result {
+                    Ok(uploaded) => uploaded,
+                    Err(e) => {
+                        failure.get_or_insert(e);
+                        continue;
+                    }
+                };
+                workspace.record_upload(&lock, &key, &uploaded, size)?;
+                outln!("{}", t!(message, url = uploaded.url.as_str()));
+                objects_uploaded += 1;
+                bytes_uploaded += size;
+                if !are_feeds {
+                    cdn_objects.push(cdn::CdnObject {
+                        url: uploaded.url.clone(),
+                        bytes: size,
+                        content_type: upload::mime_type(&key),
+                    });
+                }
+                if key == feed_key {
+                    feed_uploaded = true;
+                }
+                // podcast.xml, the season feeds, and the recent feed
+                if key.ends_with("/podcast.xml") || key == recent_key {
+                    hub_topics.push(uploaded.url.clone());
+                }
+            }
+            if let Some(e) = failure {
+                // Feeds already replaced go back to what they were, so live
+                // feeds only reference objects that are up
+                rollback::restore(publishing, &snapshots, &replaced).await;
+                return Err(e);
+            }
+        }
+        if feed_uploaded {
+            let url = upload::object_url(publishing, &feed_key);
+            output::record("feed", serde_json::json!({ "key": feed_key, "url": url }));
+        }
+        if let (true, Some(signer)) = (feed_uploaded, &signer) {
+            let url = signer.feed_url(&upload::object_url(publishing, &feed_key))?;
+            outln!("{}", t!("private-feed-url", url = url));
+        }
+        let announce = !upload::dry_run() && signer.is_none();
+        if let Some(hub) = publishing.websub_hub.as_ref().filter(|_| announce) {
+            let client = http::HttpClient::new(&channel_config.http)?;
+            websub::publish(&client, hub, &hub_topics).await;
+        }
+        // The publish history and metrics are of the main feed
+        if upload::dry_run() || !feed_uploaded || destination.is_some() {
+            return Ok(());
+        }
+
+        for (key, guid) in workspace.record_podcast_guids(&lock, &feed_guids)? {
+            outln!(
+                "{}",
+                t!(
+                    "podcast-guid-kept",
+                    key = key.as_str(),
+                    guid = guid.as_str()
+                )
+            );
+        }
+
+        let previous_feed = fs::read_to_string(workspace.published_feed_path()).ok();
+        let previous_ids: HashSet<String> = match &previous_feed {
+            Some(previous) => feed::parse_feed(previous)?
+                .items
+                .iter()
+                .filter_map(|item| item.guid().map(str::to_owned))
+                .collect(),
+            None => HashSet::new(),
+        };
+        let client = http::HttpClient::new(&channel_config.http)?;
+        let changes = changelog::record(
+            &channel_config.changelog,
+            &workspace,
+            &lock,
+            &channel_dir,
+            &client,
+            &rendered_podcast,
+        )
+        .await?;
+        if changes.is_empty() {
+            outln!("{}", t!("feed-unchanged"));
+        }
+        for change in &changes {
+            outln!("  {}", change);
+        }
+
+        let publish_metrics = metrics::PublishMetrics {
+            duration: started.elapsed(),
+            objects_uploaded,
+            bytes_uploaded,
+            episodes_added: current
+                .iter()
+                .filter(|episode| !previous_ids.contains(&episode.id))
+                .count(),
+            validation_warnings: issues.len(),
+        };
+        metrics::emit(&channel_config.metrics, &client, &publish_metrics).await;
+
+        let feed_url = upload::object_url(publishing, &feed_key);
+        // The feed is up either way, so a failed notification only warns
+        if let Some(podping) = publishing.notifications.podping.as_ref().filter(|_| announce) {
+            match podping::notify(podping, &client, &feed_url).await {
+                Ok(()) => outln!("{}", t!("podping-sent", url = feed_url.as_str())),
+                Err(e) => events::warn(t!(
+                    "podping-failed",
+                    url = feed_url.as_str(),
+                    detail = e.localized()
+                )),
+            }
+        }
+        plugins::run_stage(
+            &channel_config.plugins,
+            &plugins::StagePayload {
+                stage: PluginStage::Publish,
+                offline: render_options.offline,
+                channel: &channel_config.channel,
+                episodes: &episodes,
+                feed: None,
+                feed_url: Some(&feed_url),
+            },
+        )?;
+
+        let payload = hooks::HookPayload {
+            event: hooks::HookEvent::FeedPublished,
+            channel: channel_config.channel.title.clone(),
+            feed_url: Some(feed_url),
+            changes,
+            episodes: current
+                .iter()
+                .filter(|episode| !previous_ids.contains(&episode.id))
+                .map(hooks::HookEpisode::from)
+                .collect(),
+        };
+        hooks::run(&channel_config.hooks.on_feed_published, &client, &payload).await?;
+    } else if let Some(source) = diff {
+        let client = http::HttpClient::new(&channel_config.http)?;
+        let published = feeddiff::load_published(&client, source).await?;
+        feeddiff::print(&published, &rendered_podcast, source)?;
+    } else {
+        let (channel, episodes, rendered_podcast) = match rendered_seasons.pop() {
+            Some((_, channel, season_episodes, season_feed)) => {
+                (channel, season_episodes, season_feed)
+            }
+            None => (channel_config.channel.clone(), episodes, rendered_podcast),
+        };
+        let rendered = match render_options.format {
+            RenderFormat::Xml => format!("{}\n", rendered_podcast),
+            RenderFormat::Pretty => preview::render_preview(&channel, &episodes),
+            RenderFormat::Html => format!("{}\n", html::render_html(&channel, &episodes)),
+            RenderFormat::Jsonfeed => format!(
+                "{}\n",
+                jsonfeed::render_json_feed(
+                    &channel,
+                    &episodes,
+                    &upload::object_url(publishing, &json_feed_key),
+                    &feed_options.item_assets,
+                )?
+            ),
+        };
+        out!("{}", rendered);
+        output::record("feed", serde_json::json!({ "content": rendered }));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,307 @@
+//! Opt-in error reports to Sentry, or anything that takes Sentry's event
+//! API. With `PODCAST_CTL_SENTRY_DSN` set, failed commands and panics are
+//! sent as events with the command, the show, and channel.yaml with
+//! anything that looks like a secret redacted. Without it, nothing is sent
+//! and panics are reported by `human_panic` as before.
+
+use crate::config::ChannelConfig;
+use crate::i18n::t;
+use crate::CliError;
+use chrono::Utc;
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use std::time::Duration;
+use uuid::Uuid;
+
+pub const DSN_ENV: &str = "PODCAST_CTL_SENTRY_DSN";
+
+/// Parts of channel.yaml whose names have one of these words are redacted,
+/// like `webhook` or `accessTokenEnv`.
+const SECRET_WORDS: &[&str] = &[
+    "auth",
+    "credential",
+    "credentials",
+    "dsn",
+    "password",
+    "sas",
+    "secret",
+    "token",
+    "webhook",
+];
+const REDACTED: &str = "[redacted]";
+
+#[derive(Clone, Default)]
+struct Context {
+    command: Option<String>,
+    channel: Option<String>,
+    config: Option<Value>,
+    /// The values redacted from `config`, masked in messages too, since
+    /// errors like a failed hook's name them.
+    secrets: Vec<String>,
+}
+
+static CONTEXT: Mutex<Context> = Mutex::new(Context {
+    command: None,
+    channel: None,
+    config: None,
+    secrets: Vec::new(),
+});
+
+/// Where events go, from the DSN `https://<key>@<host>/<project>`.
+struct Endpoint {
+    store_url: String,
+    key: String,
+}
+
+fn endpoint() -> Option<Endpoint> {
+    let dsn = std::env::var(DSN_ENV).ok().filter(|dsn| !dsn.is_empty())?;
+    let (scheme, rest) = dsn.split_once("://")?;
+    let (key, rest) = rest.split_once('@')?;
+    let (host, project) = rest.rsplit_once('/')?;
+    let key = key.split(':').next().unwrap_or(key);
+    Some(Endpoint {
+        store_url: format!("{}://{}/api/{}/store/", scheme, host, project),
+        key: key.to_owned(),
+    })
+}
+
+/// Whether reports are turned on.
+pub fn enabled() -> bool {
+    endpoint().is_some()
+}
+
+/// Report panics, instead of `human_panic`, when reports are turned on.
+pub fn setup_panic() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(message) => (*message).to_owned(),
+            None => info
+                .payload()
+                .downcast_ref::<String>()
+                .cloned()
+                .unwrap_or_default(),
+        };
+        let location = info
+            .location()
+            .map(|location| format!("{}:{}", location.file(), location.line()));
+        send("fatal", "panic", &message, location);
+    }));
+}
+
+pub fn set_command(command: Option<&str>) {
+    if let Ok(mut context) = CONTEXT.lock() {
+        context.command = command.map(str::to_owned);
+    }
+}
+
+pub fn set_channel(config: &ChannelConfig) {
+    if let Ok(mut context) = CONTEXT.lock() {
+        context.channel = Some(config.channel.title.clone());
+        let config = serde_json::to_value(config).ok();
+        context.secrets = config.as_ref().map(secrets).unwrap_or_default();
+        context.config = config.map(redact);
+    }
+}
+
+/// Report a command that failed with `error`.
+pub fn error(error: &CliError) {
+    let debug = format!("{:?}", error);
+    let kind = debug
+        .split(|c: char| !c.is_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    send("error", kind, &error.localized(), None);
+}
+
+fn send(level: &str, kind: &str, message: &str, location: Option<String>) {
+    let endpoint = match endpoint() {
+        Some(endpoint) => endpoint,
+        None => return,
+    };
+    let event_id = Uuid::new_v4().simple().to_string();
+    let context = match CONTEXT.lock() {
+        Ok(context) => context.clone(),
+        Err(_) => Context::default(),
+    };
+    let event = event(&event_id, level, kind, message, location, &context);
+
+    // Panics can happen inside the runtime, which can't be blocked on, so
+    // the report is sent from a thread of its own
+    let sent = std::thread::spawn(move || post(&endpoint, &event))
+        .join()
+        .unwrap_or_else(|_| Err("the report panicked".to_owned()));
+    match sent {
+        Ok(()) => eprintln!("{}", t!("error-reported", id = event_id)),
+        Err(detail) => eprintln!("{}", t!("error-report-failed", detail = detail)),
+    }
+}
+
+fn event(
+    event_id: &str,
+    level: &str,
+    kind: &str,
+    message: &str,
+    location: Option<String>,
+    context: &Context,
+) -> Value {
+    let message = redact_text(message, &context.secrets);
+    json!({
+        "event_id": event_id,
+        "timestamp": Utc::now().to_rfc3339(),
+        "platform": "other",
+        "level": level,
+        "logger": "podcast-ctl",
+        "release": format!("podcast-ctl@{}", env!("CARGO_PKG_VERSION")),
+        "culprit": location,
+        "exception": {"values": [{"type": kind, "value": message}]},
+        "tags": {"command": context.command},
+        "extra": {"channel": context.channel, "config": context.config},
+    })
+}
+
+fn post(endpoint: &Endpoint, event: &Value) -> Result<(), String> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| e.to_string())?;
+    runtime.block_on(async {
+        let auth = format!(
+            "Sentry sentry_version=7, sentry_client=podcast-ctl/{}, sentry_key={}",
+            env!("CARGO_PKG_VERSION"),
+            endpoint.key
+        );
+        let response = reqwest::Client::new()
+            .post(&endpoint.store_url)
+            .header("X-Sentry-Auth", auth)
+            .json(event)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+        Ok(())
+    })
+}
+
+/// `value` with whatever is under a secret-looking name replaced.
+fn redact(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(name, value)| {
+                    if is_secret(&name) {
+                        (name, Value::String(REDACTED.to_owned()))
+                    } else {
+                        (name, redact(value))
+                    }
+                })
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(redact).collect()),
+        other => other,
+    }
+}
+
+/// The strings `redact` would replace in `value`.
+fn secrets(value: &Value) -> Vec<String> {
+    fn strings(value: &Value, found: &mut Vec<String>) {
+        match value {
+            Value::String(text) if !text.is_empty() => found.push(text.clone()),
+            Value::Object(map) => map.values().for_each(|value| strings(value, found)),
+            Value::Array(values) => values.iter().for_each(|value| strings(value, found)),
+            _ => {}
+        }
+    }
+
+    let mut found = Vec::new();
+    match value {
+        Value::Object(map) => {
+            for (name, value) in map {
+                if is_secret(name) {
+                    strings(value, &mut found);
+                } else {
+                    found.extend(secrets(value));
+                }
+            }
+        }
+        Value::Array(values) => values.iter().for_each(|value| found.extend(secrets(value))),
+        _ => {}
+    }
+    // Longest first, so a secret containing another is masked whole
+    found.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+    found.dedup();
+    found
+}
+
+/// `message` with each of `secrets` replaced.
+fn redact_text(message: &str, secrets: &[String]) -> String {
+    secrets.iter().fold(message.to_owned(), |message, secret| {
+        message.replace(secret.as_str(), REDACTED)
+    })
+}
+
+/// Whether the camelCase `name` looks like it holds a secret. `key` counts
+/// after the first word, as in `apiKey`, so `keyLayout` and `keywords` are
+/// kept.
+fn is_secret(name: &str) -> bool {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(c.to_ascii_lowercase());
+    }
+    words.push(word);
+    name == "key"
+        || words.iter().skip(1).any(|word| word == "key")
+        || words
+            .iter()
+            .any(|word| SECRET_WORDS.contains(&word.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_urls_are_not_sent() {
+        let webhook = "https://api.netlify.com/build_hooks/abc123";
+        let config = json!({
+            "channel": {"title": "Show"},
+            "hooks": {"onFeedPublished": [{"webhook": webhook, "onFailure": "fail"}]},
+        });
+        let context = Context {
+            command: Some("render-channel".to_owned()),
+            channel: Some("Show".to_owned()),
+            secrets: secrets(&config),
+            config: Some(redact(config)),
+        };
+        let error = CliError::HookFailed(webhook.to_owned(), "HTTP 500".to_owned());
+
+        let event = event(
+            "id",
+            "error",
+            "HookFailed",
+            &error.localized(),
+            None,
+            &context,
+        );
+
+        let payload = event.to_string();
+        assert!(!payload.contains("abc123"), "{}", payload);
+        assert!(payload.contains(REDACTED));
+        assert!(payload.contains("HTTP 500"));
+    }
+
+    #[test]
+    fn keeps_values_that_are_not_secret() {
+        let config = json!({"channel": {"title": "Show", "keywords": ["key"]}});
+        assert!(secrets(&config).is_empty());
+        assert_eq!(redact_text("Show failed", &secrets(&config)), "Show failed");
+    }
+}
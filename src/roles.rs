@@ -0,0 +1,114 @@
+//! Who may run commands that change or publish the show, for channels
+//! edited by several people from a shared repository.
+//!
+//! ```yaml
+//! roles:
+//!   editor:
+//!     members: [bob@example.com]
+//!     allow: [create, update]
+//!   producer:
+//!     members: [Ana <ana@example.com>]
+//!     allow: [create, update, approve, publish, delete]
+//! ```
+//!
+//! You are `PODCAST_CTL_USER` when it is set, and otherwise the git
+//! `user.email` and `user.name` of the channel's repository. Read-only
+//! commands are always allowed, and so is everything when no roles are
+//! configured. This guards against mistakes, not against people with
+//! write access to the repository.
+
+use crate::config::{Operation, RoleConfig};
+use crate::CliError;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+
+pub const USER_ENV: &str = "PODCAST_CTL_USER";
+
+/// Fail unless one of the roles of whoever is running the command allows
+/// `operation`.
+pub fn check(
+    roles: &BTreeMap<String, RoleConfig>,
+    channel_dir: &Path,
+    operation: Operation,
+) -> Result<(), CliError> {
+    if roles.is_empty() {
+        return Ok(());
+    }
+
+    let identities = identities(channel_dir);
+    if identities.is_empty() {
+        return Err(CliError::NotAllowed(format!(
+            "unable to tell who you are to check your role, set {} or git config user.email",
+            USER_ENV
+        )));
+    }
+
+    let mine: Vec<&String> = roles
+        .iter()
+        .filter(|(_, role)| {
+            role.members.iter().any(|member| {
+                identities
+                    .iter()
+                    .any(|identity| crate::approvals::same_person(member, identity))
+            })
+        })
+        .map(|(name, _)| name)
+        .collect();
+    if roles
+        .iter()
+        .any(|(name, role)| mine.contains(&name) && role.allow.contains(&operation))
+    {
+        return Ok(());
+    }
+
+    let allowed: Vec<&str> = roles
+        .iter()
+        .filter(|(_, role)| role.allow.contains(&operation))
+        .map(|(name, _)| name.as_str())
+        .collect();
+    let who = identities.join(" / ");
+    let held = if mine.is_empty() {
+        "no role".to_owned()
+    } else {
+        mine.iter()
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let needed = if allowed.is_empty() {
+        "no role".to_owned()
+    } else {
+        allowed.join(", ")
+    };
+    Err(CliError::NotAllowed(format!(
+        "{} ({}) may not {}; only {} may",
+        who,
+        held,
+        operation.as_str(),
+        needed
+    )))
+}
+
+/// `PODCAST_CTL_USER`, or else the git user's email and name.
+fn identities(channel_dir: &Path) -> Vec<String> {
+    if let Ok(user) = std::env::var(USER_ENV) {
+        if !user.trim().is_empty() {
+            return vec![user.trim().to_owned()];
+        }
+    }
+
+    ["user.email", "user.name"]
+        .iter()
+        .filter_map(|key| {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(channel_dir)
+                .args(["config", key])
+                .output()
+                .ok()?;
+            let value = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+            Some(value).filter(|value| output.status.success() && !value.is_empty())
+        })
+        .collect()
+}
@@ -0,0 +1,125 @@
+//! Putting published feeds back when publishing fails part way through.
+//! `render-channel --upload` uploads what feeds reference first, then the
+//! feeds, podcast.xml last. Before the feeds go up, each one's current
+//! contents are fetched from its URL, with the token when the feed is
+//! private, and if an upload fails, the
+//! feeds already replaced are restored, so apps never see a mix of old and
+//! new feeds.
+
+use crate::config::PublishingConfig;
+use crate::events;
+use crate::http::HttpClient;
+use crate::i18n::t;
+use crate::private::Signer;
+use crate::upload::{self, ObjectOptions};
+use crate::CliError;
+use reqwest::{Method, StatusCode};
+use std::io::Cursor;
+
+/// A feed as it was before publishing.
+pub struct FeedSnapshot {
+    key: String,
+    /// Decompressed, `None` when the feed wasn't published yet
+    contents: Option<Vec<u8>>,
+    /// Whether the feed is published gzipped
+    gzipped: bool,
+}
+
+/// The feeds at `keys` as they are published now, each with whether it
+/// is published gzipped. Feeds that can't be fetched are left out with a
+/// warning, and won't be restored.
+pub async fn take(
+    client: &HttpClient,
+    publishing: &PublishingConfig,
+    signer: Option<&Signer<'_>>,
+    keys: &[(String, bool)],
+) -> Vec<FeedSnapshot> {
+    let mut snapshots = Vec::new();
+    for (key, gzipped) in keys {
+        let url = upload::object_url(publishing, key);
+        let fetched = match signer {
+            Some(signer) => signer.feed_url(&url),
+            None => Ok(url.clone()),
+        };
+        let response = match fetched {
+            Ok(fetched) => {
+                client
+                    .send(&fetched, || client.request(Method::GET, &fetched))
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        let contents = match response {
+            Ok(response) if response.status.is_success() => Ok(Some(response.decoded_body())),
+            Ok(response) if response.status == StatusCode::NOT_FOUND => Ok(None),
+            Ok(response) => Err(format!("HTTP {}", response.status)),
+            Err(e) => Err(e.localized()),
+        };
+        match contents {
+            Ok(contents) => snapshots.push(FeedSnapshot {
+                key: key.clone(),
+                contents,
+                gzipped: *gzipped,
+            }),
+            Err(detail) => events::warn(t!("snapshot-failed", url = url.as_str(), detail = detail)),
+        }
+    }
+    snapshots
+}
+
+/// Put back the feeds in `snapshots` whose keys are in `replaced`:
+/// uploaded again as they were, or deleted when they didn't exist. Feeds
+/// that can't be restored are reported, and the rest still are.
+pub async fn restore(
+    publishing: &PublishingConfig,
+    snapshots: &[FeedSnapshot],
+    replaced: &[String],
+) {
+    for snapshot in snapshots
+        .iter()
+        .filter(|snapshot| replaced.contains(&snapshot.key))
+    {
+        let url = upload::object_url(publishing, &snapshot.key);
+        let restored = match &snapshot.contents {
+            Some(contents) => reupload(publishing, snapshot, contents.clone()).await,
+            None => delete(publishing, &snapshot.key).await,
+        };
+        match restored {
+            Ok(()) => eprintln!("{}", t!("feed-restored", url = url.as_str())),
+            Err(e) => events::warn(t!(
+                "feed-not-restored",
+                url = url.as_str(),
+                detail = e.localized()
+            )),
+        }
+    }
+}
+
+/// Upload the decompressed `contents` of `snapshot` again, gzipped the
+/// way publishing gzips feeds when it was published gzipped.
+async fn reupload(
+    publishing: &PublishingConfig,
+    snapshot: &FeedSnapshot,
+    contents: Vec<u8>,
+) -> Result<(), CliError> {
+    let (contents, options) = if snapshot.gzipped {
+        upload::feed_object(publishing, &snapshot.key, contents)?
+    } else {
+        (contents, ObjectOptions::subscribed(publishing))
+    };
+    let size = contents.len() as u64;
+    upload::upload_object(
+        Cursor::new(contents),
+        size,
+        publishing,
+        snapshot.key.clone(),
+        options,
+    )
+    .await
+    .map(|_| ())
+}
+
+async fn delete(publishing: &PublishingConfig, key: &str) -> Result<(), CliError> {
+    let backend = crate::storage::backend(publishing).await?;
+    backend.delete(&upload::storage_key(publishing, key)).await
+}
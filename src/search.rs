@@ -0,0 +1,216 @@
+use crate::config::*;
+use crate::output::{self, outln};
+use crate::preview::format_duration;
+use crate::state::Workspace;
+use crate::transcript::{episode_transcript, parse_cues, Cue};
+use crate::CliError;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const INDEX_FILE: &str = "transcript-index.json";
+
+/// Print `episodes`, the ones whose title, summary, description, or
+/// keywords contain `query`.
+pub fn print_metadata_matches(episodes: &[Episode], query: &str) {
+    let mut found = Vec::new();
+    for episode in episodes {
+        outln!(
+            "{}  {}  {}",
+            episode.released_at.format("%Y-%m-%d"),
+            episode.id,
+            episode.title
+        );
+        found.push(serde_json::json!({
+            "id": episode.id,
+            "title": episode.title,
+            "releasedAt": episode.released_at,
+        }));
+    }
+
+    outln!("{} episode(s) match {:?}", found.len(), query);
+    output::record("episodes", &found);
+}
+
+/// Local full-text index over every episode transcript. Transcripts are
+/// files next to the episode YAML with the same name and a `.vtt`, `.srt`,
+/// or `.txt` extension. Only transcripts that changed since the last search
+/// are re-read.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TranscriptIndex {
+    documents: BTreeMap<String, IndexedTranscript>,
+    /// term => (episode id, cue index)
+    postings: BTreeMap<String, Vec<(String, usize)>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexedTranscript {
+    source: PathBuf,
+    modified: u64,
+    title: String,
+    cues: Vec<IndexedCue>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexedCue {
+    start: Option<u64>,
+    text: String,
+}
+
+impl TranscriptIndex {
+    pub fn load_and_refresh(
+        workspace: &Workspace,
+        episodes: &[(PathBuf, Episode)],
+    ) -> Result<Self, CliError> {
+        let index_path = workspace.cache_file(INDEX_FILE);
+        let mut index: TranscriptIndex = match fs::read(&index_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+            Err(_) => TranscriptIndex::default(),
+        };
+
+        let mut changed = false;
+        let mut seen = BTreeSet::new();
+        for (episode_path, episode) in episodes {
+            let source = match episode_transcript(episode_path, episode) {
+                Some(source) => source,
+                None => continue,
+            };
+            seen.insert(episode.id.clone());
+
+            let modified = fs::metadata(&source)?
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            let current = index.documents.get(&episode.id);
+            if current.map(|d| (&d.source, d.modified)) == Some((&source, modified)) {
+                continue;
+            }
+
+            info!("Indexing transcript {}", source.display());
+            let cues = parse_cues(&String::from_utf8_lossy(&crate::crypto::read(&source)?))
+                .into_iter()
+                .map(|Cue { start, text }| IndexedCue { start, text })
+                .collect();
+            index.documents.insert(
+                episode.id.clone(),
+                IndexedTranscript {
+                    source,
+                    modified,
+                    title: episode.title.clone(),
+                    cues,
+                },
+            );
+            changed = true;
+        }
+
+        let before = index.documents.len();
+        index.documents.retain(|id, _| seen.contains(id));
+        changed |= before != index.documents.len();
+
+        if changed || index.postings.is_empty() {
+            index.rebuild_postings();
+            let lock = workspace.lock()?;
+            workspace.write_atomic(&lock, &index_path, &serde_json::to_vec(&index)?)?;
+            debug!("Wrote transcript index to {}", index_path.display());
+        }
+
+        Ok(index)
+    }
+
+    fn rebuild_postings(&mut self) {
+        self.postings.clear();
+        for (id, document) in &self.documents {
+            for (cue_index, cue) in document.cues.iter().enumerate() {
+                let terms: BTreeSet<String> = tokenize(&cue.text).collect();
+                for term in terms {
+                    self.postings
+                        .entry(term)
+                        .or_default()
+                        .push((id.clone(), cue_index));
+                }
+            }
+        }
+    }
+
+    /// Print every cue containing all terms of `query`, with its timestamp.
+    pub fn search(&self, query: &str) {
+        let terms: Vec<String> = tokenize(query).collect();
+        let phrase = query.to_lowercase();
+
+        let mut hits: Option<BTreeSet<(String, usize)>> = None;
+        for term in &terms {
+            let matches: BTreeSet<(String, usize)> = self
+                .postings
+                .get(term)
+                .map(|postings| postings.iter().cloned().collect())
+                .unwrap_or_default();
+            hits = Some(match hits {
+                Some(existing) => existing.intersection(&matches).cloned().collect(),
+                None => matches,
+            });
+        }
+
+        let mut found = Vec::new();
+        for (id, cue_index) in hits.unwrap_or_default() {
+            let document = &self.documents[&id];
+            let cue = &document.cues[cue_index];
+            if terms.len() > 1 && !cue.text.to_lowercase().contains(&phrase) {
+                continue;
+            }
+
+            let at = cue
+                .start
+                .map(|ms| format_duration(ms / 1000))
+                .unwrap_or_else(|| "-".to_owned());
+            if output::screen_reader() {
+                outln!("{} at {}: {}", document.title, at, cue.text);
+            } else {
+                outln!("{}  {:>8}  {}", document.title, at, cue.text);
+            }
+            found.push(serde_json::json!({
+                "id": id,
+                "title": document.title,
+                "startMs": cue.start,
+                "text": cue.text,
+            }));
+        }
+
+        outln!(
+            "{} transcript match(es) for {:?} across {} indexed transcript(s)",
+            found.len(),
+            query,
+            self.documents.len()
+        );
+        output::record("matches", &found);
+    }
+}
+
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 2)
+        .map(|word| word.to_lowercase())
+}
+
+/// `search`: print episodes matching `query`, or with `transcripts`, where
+/// it was spoken.
+pub fn search(
+    config: &ChannelConfig,
+    episode_dir: &Path,
+    query: &str,
+    transcripts: bool,
+) -> Result<(), CliError> {
+    if transcripts {
+        let workspace = Workspace::new(episode_dir.parent().unwrap_or(episode_dir));
+        let episodes = crate::index::episode_files(config, episode_dir)?;
+        let index = TranscriptIndex::load_and_refresh(&workspace, &episodes)?;
+        index.search(query);
+    } else {
+        let episodes = crate::index::search(config, episode_dir, query)?;
+        print_metadata_matches(&episodes, query);
+    }
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! Season rollover for `create-episode`, so seasons follow the calendar
+//! instead of whatever season the previous episode was in.
+//!
+//! ```yaml
+//! seasons:
+//!   newSeasonEvery: year
+//!   seasonStartsOn: 2024-09-01
+//! ```
+//!
+//! With both, a season starts every September 1st from 2024 on. With only
+//! `newSeasonEvery: year`, seasons start on January 1st, and with only
+//! `seasonStartsOn`, one season starts on that date.
+//!
+//! Also what a season's own feed, from `render-channel --season` or
+//! `publishing.perSeasonFeeds`, says about the show:
+//!
+//! ```yaml
+//! seasons:
+//!   feeds:
+//!     2:
+//!       title: The Lost Mine, an anthology
+//!       image: https://example.com/season-2.jpg
+//! ```
+
+use crate::config::{ChannelDetails, PublishingConfig, SeasonConfig, SeasonPeriod};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use chrono_tz::Tz;
+
+/// Whether a season starts after the episode released at `previous` and
+/// by `next`, so an episode released at `next` begins it. Seasons start on
+/// days in `timezone`, the channel's.
+pub fn starts_between(
+    config: &SeasonConfig,
+    timezone: Tz,
+    previous: DateTime<Utc>,
+    next: DateTime<Utc>,
+) -> bool {
+    let previous = previous.with_timezone(&timezone).date_naive();
+    let next = next.with_timezone(&timezone).date_naive();
+    let starts_in_range = |start: NaiveDate| previous < start && start <= next;
+
+    match config.new_season_every {
+        None => config.season_starts_on.map_or(false, starts_in_range),
+        Some(SeasonPeriod::Year) => {
+            let (month, day) = config
+                .season_starts_on
+                .map(|first| (first.month(), first.day()))
+                .unwrap_or((1, 1));
+            (previous.year()..=next.year())
+                .map(|year| anniversary(year, month, day))
+                .filter(|start| {
+                    config
+                        .season_starts_on
+                        .map_or(true, |first| *start >= first)
+                })
+                .any(starts_in_range)
+        }
+    }
+}
+
+/// `month`/`day` in `year`, with February 29th falling on March 1st in
+/// other years.
+fn anniversary(year: i32, month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, day)
+        .or_else(|| NaiveDate::from_ymd_opt(year, 3, 1))
+        .unwrap_or(NaiveDate::MIN)
+}
+
+/// The channel details of `season`'s feed: the show's, with whatever
+/// `seasons.feeds` sets for the season.
+pub fn feed_channel(
+    config: &SeasonConfig,
+    channel: &ChannelDetails,
+    season: u64,
+) -> ChannelDetails {
+    let mut channel = channel.clone();
+    if let Some(feed) = config.feeds.get(&season) {
+        let replace = |field: &mut String, value: &Option<String>| {
+            if let Some(value) = value {
+                *field = value.clone();
+            }
+        };
+        replace(&mut channel.title, &feed.title);
+        replace(&mut channel.subtitle, &feed.subtitle);
+        replace(&mut channel.description, &feed.description);
+        replace(&mut channel.summary, &feed.summary);
+        replace(&mut channel.image, &feed.image);
+        if feed.link.is_some() {
+            channel.link = feed.link.clone();
+        }
+    }
+    channel
+}
+
+/// Object key of `season`'s feed.
+pub fn feed_key(publishing: &PublishingConfig, season: u64) -> String {
+    format!("{}/season-{}/podcast.xml", publishing.prefix, season)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn starts(config: &SeasonConfig, timezone: &str, previous: &str, next: &str) -> bool {
+        let timezone: Tz = timezone.parse().unwrap();
+        starts_between(config, timezone, previous.parse().unwrap(), next.parse().unwrap())
+    }
+
+    #[test]
+    fn seasons_start_on_the_channels_day() {
+        let config = SeasonConfig {
+            season_starts_on: NaiveDate::from_ymd_opt(2023, 1, 1),
+            ..Default::default()
+        };
+        let previous = "2022-12-30T12:00:00Z";
+        // 22:00 on New Year's Eve in New York
+        assert!(starts(&config, "UTC", previous, "2023-01-01T03:00:00Z"));
+        assert!(!starts(&config, "America/New_York", previous, "2023-01-01T03:00:00Z"));
+        assert!(starts(&config, "America/New_York", previous, "2023-01-01T05:00:00Z"));
+        // Already the 1st in Auckland
+        assert!(starts(&config, "Pacific/Auckland", previous, "2022-12-31T11:00:00Z"));
+        assert!(!starts(&config, "UTC", previous, "2022-12-31T11:00:00Z"));
+    }
+
+    #[test]
+    fn yearly_seasons_start_on_the_channels_day() {
+        let config = SeasonConfig {
+            new_season_every: Some(SeasonPeriod::Year),
+            ..Default::default()
+        };
+        let previous = "2023-12-20T12:00:00Z";
+        assert!(!starts(&config, "America/Los_Angeles", previous, "2024-01-01T07:00:00Z"));
+        assert!(starts(&config, "America/Los_Angeles", previous, "2024-01-01T08:00:00Z"));
+        // The episode before was already released in the new year there
+        let previous = "2023-12-31T20:00:00Z";
+        assert!(!starts(&config, "Asia/Tokyo", previous, "2024-01-02T00:00:00Z"));
+    }
+}
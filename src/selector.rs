@@ -0,0 +1,169 @@
+//! Picking an episode on the command line. Anywhere an episode is asked
+//! for, it can be given as:
+//!
+//! - its id, or enough of the start of it to match only one episode
+//! - its file name without `.yaml`, like `2022-09-01-session`
+//! - the date it was released in the channel's timezone, like `2022-09-01`
+//! - `latest`, for the most recently released episode, leaving out drafts
+//!   and episodes scheduled for later
+//!
+//! A selector that matches more than one episode is an error listing them,
+//! rather than a guess.
+
+use crate::config::Episode;
+use crate::CliError;
+use chrono::{NaiveDate, Utc};
+use chrono_tz::Tz;
+
+const LATEST: &str = "latest";
+
+/// Whether an episode, with its file name, matches.
+type Rule<'a> = &'a dyn Fn(&Episode, &str) -> bool;
+
+/// The item whose episode `selector` picks. `describe` gives an item's
+/// episode and file name. When several items hold the same episode, like
+/// copies in the trash, the first is picked. Dates are in `timezone`.
+pub fn select<T, F>(items: Vec<T>, selector: &str, timezone: Tz, describe: F) -> Result<T, CliError>
+where
+    F: Fn(&T) -> (&Episode, String),
+{
+    let selector = selector.trim();
+    if selector.eq_ignore_ascii_case(LATEST) {
+        let now = Utc::now();
+        return items
+            .into_iter()
+            .enumerate()
+            .filter(|(_, item)| {
+                let episode = describe(item).0;
+                !episode.draft && episode.released_at <= now
+            })
+            // The first of the latest, when several were released together
+            .max_by_key(|(index, item)| (describe(item).0.released_at, std::cmp::Reverse(*index)))
+            .map(|(_, item)| item)
+            .ok_or_else(|| CliError::EpisodeNotFound(selector.to_owned()));
+    }
+
+    let date = NaiveDate::parse_from_str(selector, "%Y-%m-%d").ok();
+    let lowercase = selector.to_ascii_lowercase();
+    // In order of preference, so an exact id or name wins over a prefix
+    let rules: [Rule; 3] = [
+        &|episode, name| episode.id == selector || name == selector,
+        &|episode, _| date == Some(episode.released_at.with_timezone(&timezone).date_naive()),
+        &|episode, _| episode.id.to_ascii_lowercase().starts_with(&lowercase),
+    ];
+    let mut items = items;
+    for rule in rules {
+        let (matches, rest): (Vec<T>, Vec<T>) = items.into_iter().partition(|item| {
+            let (episode, name) = describe(item);
+            rule(episode, &name)
+        });
+        items = rest;
+
+        let mut ids: Vec<String> = Vec::new();
+        let mut described = Vec::new();
+        for item in &matches {
+            let (episode, name) = describe(item);
+            if !ids.contains(&episode.id) {
+                ids.push(episode.id.clone());
+                described.push(format!("{} ({})", name, episode.id));
+            }
+        }
+        if ids.len() > 1 {
+            return Err(CliError::AmbiguousEpisode(
+                selector.to_owned(),
+                described.join(", "),
+            ));
+        }
+        if let Some(item) = matches.into_iter().next() {
+            return Ok(item);
+        }
+    }
+    Err(CliError::EpisodeNotFound(selector.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn episode(id: &str, released_at: &str) -> Episode {
+        let mut episode = Episode::make_test(id);
+        episode.released_at = released_at.parse().unwrap();
+        episode
+    }
+
+    fn pick(episodes: &[Episode], selector: &str, timezone: Tz) -> Result<String, CliError> {
+        select(episodes.iter().collect(), selector, timezone, |episode| {
+            (*episode, format!("{}-file", episode.id))
+        })
+        .map(|episode| episode.id.clone())
+    }
+
+    fn episodes() -> Vec<Episode> {
+        vec![
+            episode("a1b2", "2022-09-01T02:00:00Z"),
+            episode("a1c3", "2022-09-08T12:00:00Z"),
+            episode("d4e5", "2022-09-15T12:00:00Z"),
+        ]
+    }
+
+    #[test]
+    fn matches_unique_prefixes() {
+        assert_eq!(pick(&episodes(), "a1b", Tz::UTC).unwrap(), "a1b2");
+        assert_eq!(pick(&episodes(), "D4", Tz::UTC).unwrap(), "d4e5");
+        assert_eq!(pick(&episodes(), "d4e5-file", Tz::UTC).unwrap(), "d4e5");
+        assert!(matches!(
+            pick(&episodes(), "ff", Tz::UTC),
+            Err(CliError::EpisodeNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn ambiguous_prefixes_are_errors() {
+        match pick(&episodes(), "a1", Tz::UTC) {
+            Err(CliError::AmbiguousEpisode(selector, described)) => {
+                assert_eq!(selector, "a1");
+                assert!(described.contains("a1b2") && described.contains("a1c3"));
+            }
+            other => panic!("expected an ambiguous match, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn exact_ids_win_over_prefixes() {
+        let mut episodes = episodes();
+        episodes.push(episode("a1", "2022-09-22T12:00:00Z"));
+        assert_eq!(pick(&episodes, "a1", Tz::UTC).unwrap(), "a1");
+    }
+
+    #[test]
+    fn dates_are_in_the_channel_timezone() {
+        // 02:00 UTC on the 1st is still the 31st in New York
+        let new_york: Tz = "America/New_York".parse().unwrap();
+        assert_eq!(pick(&episodes(), "2022-08-31", new_york).unwrap(), "a1b2");
+        assert!(pick(&episodes(), "2022-09-01", new_york).is_err());
+        assert_eq!(pick(&episodes(), "2022-09-01", Tz::UTC).unwrap(), "a1b2");
+    }
+
+    #[test]
+    fn latest_skips_drafts_and_scheduled_episodes() {
+        let mut episodes = episodes();
+        assert_eq!(pick(&episodes, "latest", Tz::UTC).unwrap(), "d4e5");
+
+        episodes[2].draft = true;
+        let mut scheduled = episode("f6", "2022-09-01T00:00:00Z");
+        scheduled.released_at = Utc::now() + Duration::days(7);
+        episodes.push(scheduled);
+        assert_eq!(pick(&episodes, "LATEST", Tz::UTC).unwrap(), "a1c3");
+    }
+
+    #[test]
+    fn latest_picks_the_first_released_together() {
+        let at = Utc.with_ymd_and_hms(2022, 9, 1, 12, 0, 0).unwrap();
+        let mut episodes = vec![Episode::make_test("first"), Episode::make_test("second")];
+        for episode in &mut episodes {
+            episode.released_at = at;
+        }
+        assert_eq!(pick(&episodes, "latest", Tz::UTC).unwrap(), "first");
+    }
+}
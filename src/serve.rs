@@ -0,0 +1,383 @@
+//! `serve` previews the show in a podcast app before anything is published.
+//! The feed is rendered again for every request, so edits to episode files
+//! show up on the next refresh, and media and images found in the media
+//! directory are served from here instead of from storage. Links use the
+//! host the app asked for, so a phone on the same network can subscribe to
+//! `http://<this machine>:<port>/podcast.xml`.
+//!
+//! This is a preview server: one file per request, over HTTP/1.1 without
+//! keep-alive.
+
+use crate::events;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::CliError;
+use log::{debug, warn};
+use std::net::{Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs::File;
+use tokio::io::{
+    AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader,
+};
+use tokio::net::{TcpListener, TcpStream};
+
+const FEED_PATH: &str = "/podcast.xml";
+const MEDIA_PATH: &str = "/media/";
+/// How much of a request is read: its request line and headers.
+const MAX_REQUEST_BYTES: u64 = 16 * 1024;
+/// How many headers a request can have.
+const MAX_HEADERS: usize = 64;
+
+/// Serve the feed `render` makes, given the base URL requests came in on,
+/// and the files in `media_dir`, until interrupted.
+pub async fn serve<F>(address: SocketAddr, media_dir: &Path, render: F) -> Result<(), CliError>
+where
+    F: Fn(&str) -> Result<String, CliError> + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(address).await?;
+    outln!(
+        "{}",
+        t!(
+            "serve-listening",
+            url = format!("http://{}{}", listener.local_addr()?, FEED_PATH)
+        )
+    );
+
+    let render = Arc::new(render);
+    // Requests are handled in tasks of their own, in this call's context
+    let context = events::current();
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let media_dir = media_dir.to_path_buf();
+        let render = render.clone();
+        let context = context.clone();
+        tokio::spawn(async move {
+            let handled = handle(stream, &media_dir, render.as_ref());
+            if let Err(e) = context.scope(handled).await {
+                debug!("Request from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// The URL `serve` has `url`'s file at, when the media directory holds a
+/// file with its name.
+pub fn local_url(media_dir: &Path, base_url: &str, url: &str) -> Option<String> {
+    let name = url.rsplit('/').next().filter(|name| !name.is_empty())?;
+    media_file(media_dir, name).map(|_| format!("{}{}{}", base_url, MEDIA_PATH, name))
+}
+
+fn media_file(media_dir: &Path, name: &str) -> Option<PathBuf> {
+    if name.contains(['/', '\\']) || name.starts_with('.') {
+        return None;
+    }
+    let path = media_dir.join(name);
+    path.is_file().then_some(path)
+}
+
+pub(crate) struct Request {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    host: Option<String>,
+    range: Option<String>,
+}
+
+async fn handle<F>(mut stream: TcpStream, media_dir: &Path, render: &F) -> Result<(), CliError>
+where
+    F: Fn(&str) -> Result<String, CliError>,
+{
+    let request = match read_request(&mut stream).await? {
+        Some(request) => request,
+        None => return Ok(()),
+    };
+    debug!("{} {}", request.method, request.path);
+    let head_only = match request.method.as_str() {
+        "GET" => false,
+        "HEAD" => true,
+        _ => return respond(&mut stream, "405 Method Not Allowed", "text/plain", b"").await,
+    };
+
+    let path = request.path.split('?').next().unwrap_or_default();
+    if path == FEED_PATH {
+        let host = request.host.unwrap_or_else(|| "localhost".to_owned());
+        return match render(&format!("http://{}", host)) {
+            Ok(feed) => {
+                let body = if head_only { &[][..] } else { feed.as_bytes() };
+                respond(
+                    &mut stream,
+                    "200 OK",
+                    "application/rss+xml; charset=utf-8",
+                    body,
+                )
+                .await
+            }
+            Err(e) => {
+                let detail = e.localized();
+                warn!("Unable to render the feed: {}", detail);
+                respond(
+                    &mut stream,
+                    "500 Internal Server Error",
+                    "text/plain",
+                    detail.as_bytes(),
+                )
+                .await
+            }
+        };
+    }
+
+    let file = path
+        .strip_prefix(MEDIA_PATH)
+        .and_then(|name| media_file(media_dir, name));
+    match file {
+        Some(file) => send_file(&mut stream, &file, request.range.as_deref(), head_only).await,
+        None => respond(&mut stream, "404 Not Found", "text/plain", b"Not found").await,
+    }
+}
+
+/// The request on `stream`, or `None` for one that isn't HTTP, is cut off,
+/// or is larger than [`MAX_REQUEST_BYTES`] or [`MAX_HEADERS`] allow. A
+/// `Host` that isn't `host[:port]` is left out.
+pub(crate) async fn read_request<S>(stream: &mut S) -> Result<Option<Request>, CliError>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_BYTES));
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 || !line.ends_with('\n') {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (method.to_owned(), path.to_owned()),
+        _ => return Ok(None),
+    };
+
+    let mut request = Request {
+        method,
+        path,
+        host: None,
+        range: None,
+    };
+    for _ in 0..=MAX_HEADERS {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 || !line.ends_with('\n') {
+            return Ok(None);
+        }
+        if line.trim().is_empty() {
+            return Ok(Some(request));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = Some(value.trim().to_owned());
+            match name.trim().to_ascii_lowercase().as_str() {
+                "host" => request.host = value.filter(|host| is_host(host)),
+                "range" => request.range = value,
+                _ => {}
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Whether `host` is a `Host` header's `host[:port]`: a hostname, an IPv4
+/// address, or an IPv6 address in brackets, and a port.
+fn is_host(host: &str) -> bool {
+    let (name_ok, port) = match host.strip_prefix('[') {
+        Some(rest) => match rest.split_once(']') {
+            Some((ip, port)) => (ip.parse::<Ipv6Addr>().is_ok(), port),
+            None => return false,
+        },
+        None => {
+            let (name, port) = host.split_at(host.find(':').unwrap_or(host.len()));
+            let label_ok = |label: &str| {
+                !label.is_empty() && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+            };
+            (name.len() <= 253 && name.split('.').all(label_ok), port)
+        }
+    };
+    let port_ok = match port.strip_prefix(':') {
+        Some(port) => port.bytes().all(|b| b.is_ascii_digit()) && port.parse::<u16>().is_ok(),
+        None => port.is_empty(),
+    };
+    name_ok && port_ok
+}
+
+pub(crate) async fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> Result<(), CliError> {
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    Ok(())
+}
+
+/// Send a file, or the part of it a `Range: bytes=...` header asks for,
+/// which podcast apps use to seek.
+async fn send_file(
+    stream: &mut TcpStream,
+    path: &Path,
+    range: Option<&str>,
+    head_only: bool,
+) -> Result<(), CliError> {
+    let mut file = File::open(path).await?;
+    let size = file.metadata().await?.len();
+    // Images by their extension, and anything else as media
+    let content_type = mime_guess::from_path(path)
+        .first()
+        .filter(|mime| mime.type_() == mime::IMAGE)
+        .map(|mime| mime.to_string())
+        .unwrap_or_else(|| crate::audio::mime_type_for_url(&path.to_string_lossy()).to_owned());
+
+    let (partial, start, end) = match range.map(|range| parse_range(range, size)) {
+        None => (false, 0, size),
+        Some(Some((start, end))) => (true, start, end),
+        Some(None) => {
+            let head = format!(
+                "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                size
+            );
+            stream.write_all(head.as_bytes()).await?;
+            return Ok(());
+        }
+    };
+
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\nConnection: close\r\n",
+        if partial { "206 Partial Content" } else { "200 OK" },
+        content_type,
+        end - start
+    );
+    if partial {
+        head.push_str(&format!(
+            "Content-Range: bytes {}-{}/{}\r\n",
+            start,
+            end - 1,
+            size
+        ));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    if !head_only {
+        file.seek(std::io::SeekFrom::Start(start)).await?;
+        tokio::io::copy(&mut file.take(end - start), stream).await?;
+    }
+    Ok(())
+}
+
+/// The bytes `start..end` a single `bytes=` range asks for, or `None` when
+/// none of them are in the file.
+fn parse_range(range: &str, size: u64) -> Option<(u64, u64)> {
+    let spec = range.trim().strip_prefix("bytes=")?;
+    let (start, end) = spec.split(',').next()?.trim().split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (size.saturating_sub(suffix), size)
+        }
+        (start, "") => (start.parse().ok()?, size),
+        (start, end) => (
+            start.parse().ok()?,
+            end.parse::<u64>().ok()?.saturating_add(1),
+        ),
+    };
+    let end = end.min(size);
+    (start < end).then_some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read(request: &str) -> Option<Request> {
+        read_request(&mut request.as_bytes()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn reads_the_request_line_and_headers() {
+        let request = read("GET /podcast.xml HTTP/1.1\r\nHost: 192.168.1.2:8080\r\nRange: bytes=0-\r\n\r\n")
+            .await
+            .unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/podcast.xml");
+        assert_eq!(request.host.as_deref(), Some("192.168.1.2:8080"));
+        assert_eq!(request.range.as_deref(), Some("bytes=0-"));
+    }
+
+    #[tokio::test]
+    async fn refuses_requests_that_are_cut_off_or_too_large() {
+        assert!(read("GET / HTTP/1.1\r\nHost: localhost\r\n").await.is_none());
+        assert!(read("GET / HTTP/1.1").await.is_none());
+
+        let headers = "X-Header: 1\r\n".repeat(MAX_HEADERS + 1);
+        assert!(read(&format!("GET / HTTP/1.1\r\n{}\r\n", headers)).await.is_none());
+        let headers = "X-Header: 1\r\n".repeat(MAX_HEADERS - 1);
+        assert!(read(&format!("GET / HTTP/1.1\r\n{}\r\n", headers)).await.is_some());
+
+        let long = "a".repeat(MAX_REQUEST_BYTES as usize);
+        assert!(read(&format!("GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n", long)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn leaves_out_hosts_that_are_not_host_and_port() {
+        let host = |host: &str| format!("GET / HTTP/1.1\r\nHost: {}\r\n\r\n", host);
+        let request = read(&host("evil.example/\"><script>")).await.unwrap();
+        assert_eq!(request.host, None);
+        let request = read(&host("[::1]:8080")).await.unwrap();
+        assert_eq!(request.host.as_deref(), Some("[::1]:8080"));
+    }
+
+    #[test]
+    fn reads_byte_ranges() {
+        assert_eq!(parse_range("bytes=0-499", 1000), Some((0, 500)));
+        assert_eq!(parse_range("bytes=0-0", 1000), Some((0, 1)));
+        assert_eq!(parse_range("bytes=500-", 1000), Some((500, 1000)));
+        assert_eq!(parse_range("bytes=-200", 1000), Some((800, 1000)));
+        assert_eq!(parse_range(" bytes= 10 - 19 ", 1000), Some((10, 20)));
+        // Only the first of several ranges is sent
+        assert_eq!(parse_range("bytes=0-1, 5-6", 1000), Some((0, 2)));
+    }
+
+    #[test]
+    fn keeps_byte_ranges_within_the_file() {
+        assert_eq!(parse_range("bytes=-2000", 1000), Some((0, 1000)));
+        assert_eq!(parse_range("bytes=900-1999", 1000), Some((900, 1000)));
+        assert_eq!(parse_range("bytes=1000-", 1000), None);
+        assert_eq!(parse_range("bytes=-0", 1000), None);
+        assert_eq!(parse_range("bytes=0-", 0), None);
+        assert_eq!(parse_range("bytes=0-18446744073709551615", 10), Some((0, 10)));
+    }
+
+    #[test]
+    fn refuses_ranges_that_are_not_byte_ranges() {
+        assert_eq!(parse_range("items=0-1", 1000), None);
+        assert_eq!(parse_range("bytes=500-400", 1000), None);
+        assert_eq!(parse_range("bytes=abc-", 1000), None);
+        assert_eq!(parse_range("bytes=-", 1000), None);
+        assert_eq!(parse_range("bytes=5", 1000), None);
+    }
+
+    #[test]
+    fn checks_hosts() {
+        assert!(is_host("localhost"));
+        assert!(is_host("my-laptop.local:8080"));
+        assert!(is_host("10.0.0.2:80"));
+        assert!(is_host("[fe80::1]"));
+        assert!(!is_host(""));
+        assert!(!is_host("localhost:"));
+        assert!(!is_host("localhost:+80"));
+        assert!(!is_host("localhost:65536"));
+        assert!(!is_host("a..b"));
+        assert!(!is_host("user@host"));
+        assert!(!is_host("host/path"));
+        assert!(!is_host("[::1"));
+        assert!(!is_host("[not-ip]:80"));
+    }
+}
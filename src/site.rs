@@ -0,0 +1,182 @@
+//! `render-site` builds a small archive website for the show: an
+//! `index.html` listing every episode, and a page per episode with a
+//! player, the show notes, artwork, and OpenGraph tags for link previews.
+//!
+//! Pages are filled in from the bundled templates, or from `index.html`,
+//! `index-item.html`, and `episode.html` in `--templates`, using the same
+//! `{{name}}` placeholders:
+//!
+//! - index: `language`, `title`, `subtitle`, `summary`, `image`, `owner`,
+//!   `episode_count`, `feed_url`, `page_url`, and `items`
+//! - index item: `page`, `title`, `released`, and `duration`
+//! - episode: `language`, `channel_title`, `title`, `summary`, `image`,
+//!   `season`, `episode`, `released`, `duration`, `url`, `mime_type`,
+//!   `notes`, and `page_url`
+//!
+//! With `publishing.site` set, feeds link the channel and each episode
+//! without a `link` of its own to its page.
+
+use crate::config::{ChannelDetails, Episode, PublishingConfig};
+use crate::html::{escape, fill};
+use crate::preview::format_duration;
+use crate::CliError;
+use std::fs;
+use std::path::Path;
+
+const INDEX_TEMPLATE: &str = include_str!("../templates/site-index.html");
+const INDEX_ITEM_TEMPLATE: &str = include_str!("../templates/site-index-item.html");
+const EPISODE_TEMPLATE: &str = include_str!("../templates/site-episode.html");
+
+pub struct Templates {
+    index: String,
+    index_item: String,
+    episode: String,
+}
+
+impl Templates {
+    /// The bundled templates, with any found in `dir` used instead.
+    pub fn load(dir: Option<&Path>) -> Result<Templates, CliError> {
+        let load = |name: &str, bundled: &str| -> Result<String, CliError> {
+            match dir.map(|dir| dir.join(name)).filter(|path| path.is_file()) {
+                Some(path) => Ok(fs::read_to_string(path)?),
+                None => Ok(bundled.to_owned()),
+            }
+        };
+        Ok(Templates {
+            index: load("index.html", INDEX_TEMPLATE)?,
+            index_item: load("index-item.html", INDEX_ITEM_TEMPLATE)?,
+            episode: load("episode.html", EPISODE_TEMPLATE)?,
+        })
+    }
+}
+
+/// An episode and what its page is built from.
+pub struct SiteEpisode {
+    /// File name of the episode's page, like `2022-09-01-session.html`
+    pub page: String,
+    pub episode: Episode,
+    /// Rendered show notes, when the episode has a notes file
+    pub notes: Option<String>,
+}
+
+/// Every page of the site, as file names and HTML, newest episode first.
+/// `page_url` gives the public URL of a page from its file name.
+pub fn render_site(
+    templates: &Templates,
+    channel: &ChannelDetails,
+    episodes: &mut [SiteEpisode],
+    feed_url: &str,
+    page_url: impl Fn(&str) -> String,
+) -> Vec<(String, String)> {
+    episodes.sort_by_key(|site_episode| std::cmp::Reverse(site_episode.episode.released_at));
+
+    let mut pages = Vec::new();
+    let mut items = Vec::new();
+    for site_episode in episodes.iter() {
+        let episode = &site_episode.episode;
+        let released = episode.released_at.format("%B %-d, %Y").to_string();
+        let duration = format_duration(episode.media.duration.0);
+        items.push(fill(
+            &templates.index_item,
+            &[
+                ("page", escape(&site_episode.page)),
+                ("title", escape(&episode.title)),
+                ("released", released.clone()),
+                ("duration", duration.clone()),
+            ],
+        ));
+
+        let notes = site_episode
+            .notes
+            .clone()
+            .unwrap_or_else(|| crate::notes::to_html(&episode.description));
+        let html = fill(
+            &templates.episode,
+            &[
+                ("language", escape(&channel.language)),
+                ("channel_title", escape(&channel.title)),
+                ("title", escape(&episode.title)),
+                ("summary", escape(&episode.summary)),
+                ("image", escape(&episode.image)),
+                ("season", episode.season.to_string()),
+                ("episode", episode.episode_number.to_string()),
+                ("released", released),
+                ("duration", duration),
+                ("url", escape(&episode.media.url)),
+                (
+                    "mime_type",
+                    episode.media.enclosure_type().to_owned(),
+                ),
+                ("page_url", escape(&page_url(&site_episode.page))),
+                ("notes", notes),
+            ],
+        );
+        pages.push((site_episode.page.clone(), html));
+    }
+
+    let index = fill(
+        &templates.index,
+        &[
+            ("language", escape(&channel.language)),
+            ("title", escape(&channel.title)),
+            ("subtitle", escape(&channel.subtitle)),
+            ("image", escape(&channel.image)),
+            ("owner", escape(&channel.owner.name)),
+            ("episode_count", episodes.len().to_string()),
+            ("feed_url", escape(feed_url)),
+            ("page_url", escape(&page_url("index.html"))),
+            ("summary", crate::notes::to_html(&channel.summary)),
+            ("items", items.join("")),
+        ],
+    );
+    pages.insert(0, ("index.html".to_owned(), index));
+    pages
+}
+
+/// Key of the site page `page` under the publishing prefix.
+pub fn page_key(publishing: &PublishingConfig, page: &str) -> String {
+    format!("{}/site/{}", publishing.prefix, page)
+}
+
+/// Public URL of the site page `page`.
+pub fn page_url(publishing: &PublishingConfig, page: &str) -> String {
+    crate::upload::object_url(publishing, &page_key(publishing, page))
+}
+
+/// Link the channel and the episodes, from their episode files, that have
+/// no `link` to their pages, when the site is published.
+pub fn fill_links<'a>(
+    publishing: &PublishingConfig,
+    channel: &mut ChannelDetails,
+    episodes: impl IntoIterator<Item = (&'a Path, &'a mut Episode)>,
+) {
+    if !publishing.site {
+        return;
+    }
+    if channel.link.is_none() {
+        channel.link = Some(page_url(publishing, "index.html"));
+    }
+    for (path, episode) in episodes {
+        if episode.link.is_none() {
+            episode.link = Some(page_url(publishing, &page_name(path)));
+        }
+    }
+}
+
+/// File name for an episode's page, from the name of its episode file.
+pub fn page_name(episode_path: &Path) -> String {
+    let stem = crate::crypto::plain_path(episode_path)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    format!("{}.html", stem)
+}
@@ -0,0 +1,382 @@
+//! `export-csv` writes every episode as a row of a CSV file, and
+//! `import-csv` applies the edits made to it in a spreadsheet back to the
+//! episode files.
+//!
+//! Rows are matched to episodes by `id`. Columns a sheet leaves out are
+//! left alone, so a sheet of `id,title` only retitles episodes. `id`,
+//! `file`, `draft`, `destination`, and the media columns are for reading:
+//! they come from publishing and the audio, and are ignored on import.
+//! Keywords are separated by `;`, and an empty `link` or `explicit`
+//! clears it. `releasedAt` takes what `create-episode --date` does.
+//!
+//! Cells that start with `=`, `+`, `-`, or `@` are written with a `'` in
+//! front, so spreadsheets don't run them as formulas, and the `'` is
+//! taken off again on import.
+
+use crate::config::{ChannelConfig, Episode, EpisodeType};
+use crate::i18n::t;
+use crate::output::{out, outln};
+use crate::CliError;
+use chrono::SecondsFormat;
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Columns `export-csv` writes, in order.
+const COLUMNS: [&str; 21] = [
+    "id",
+    "file",
+    "title",
+    "summary",
+    "description",
+    "link",
+    "image",
+    "season",
+    "episodeNumber",
+    "releasedAt",
+    "episodeType",
+    "explicit",
+    "keywords",
+    "draft",
+    "destination",
+    "mediaUrl",
+    "duration",
+    "bytes",
+    "bitrate",
+    "mimeType",
+    "recordedDate",
+];
+
+/// Columns `import-csv` ignores.
+const READ_ONLY: [&str; 10] = [
+    "id",
+    "file",
+    "draft",
+    "destination",
+    "mediaUrl",
+    "duration",
+    "bytes",
+    "bitrate",
+    "mimeType",
+    "recordedDate",
+];
+
+/// Write every episode in `episodes`, in release order, as CSV to
+/// `output`, or to stdout without it.
+pub fn export_csv(
+    episode_dir: &Path,
+    mut episodes: Vec<(PathBuf, Episode)>,
+    output: Option<&Path>,
+) -> Result<(), CliError> {
+    episodes.sort_by_key(|(_, episode)| episode.released_at);
+    let mut csv = COLUMNS.join(",");
+    csv.push_str("\r\n");
+    for (path, episode) in &episodes {
+        let file = path.strip_prefix(episode_dir).unwrap_or(path);
+        let file = file.display().to_string();
+        let row: Vec<String> = COLUMNS
+            .iter()
+            .map(|column| field(&cell(episode, &file, column)))
+            .collect();
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+
+    match output {
+        Some(output) => {
+            fs::write(output, csv)?;
+            eprintln!("{}", t!("wrote-file", path = output.display().to_string()));
+        }
+        None => out!("{}", csv),
+    }
+    Ok(())
+}
+
+/// Apply the edits in the CSV at `input` to the episodes it names,
+/// showing a diff of each change and asking before writing unless `yes`
+/// is set.
+pub fn import_csv(
+    episodes: Vec<(PathBuf, Episode)>,
+    input: &Path,
+    yes: bool,
+    channel_config: &ChannelConfig,
+) -> Result<(), CliError> {
+    let timezone = crate::dates::timezone(channel_config.channel.timezone.as_deref())?;
+    let text = fs::read_to_string(input)?;
+    let mut rows = parse(&text)?.into_iter();
+    let header = rows
+        .next()
+        .ok_or_else(|| CliError::CsvImport("the file is empty".to_owned()))?;
+    for column in &header {
+        if !COLUMNS.contains(&column.as_str()) {
+            return Err(CliError::CsvImport(format!(
+                "there is no `{}` column; columns are {}",
+                column,
+                COLUMNS.join(", ")
+            )));
+        }
+    }
+    let id_column = header
+        .iter()
+        .position(|column| column == "id")
+        .ok_or_else(|| CliError::CsvImport("rows need an `id` column".to_owned()))?;
+
+    let mut by_id: HashMap<String, (PathBuf, Episode)> = episodes
+        .into_iter()
+        .map(|(path, episode)| (episode.id.clone(), (path, episode)))
+        .collect();
+    let mut changes = Vec::new();
+    for (index, row) in rows.enumerate() {
+        // The header is row 1
+        let line = index + 2;
+        if row.iter().all(|value| value.is_empty()) {
+            continue;
+        }
+        let id = row.get(id_column).map(String::as_str).unwrap_or_default();
+        let (path, original) = by_id.remove(id).ok_or_else(|| {
+            CliError::CsvImport(format!("row {}: no episode has the id `{}`", line, id))
+        })?;
+        let mut episode = original.clone();
+        for (column, value) in header.iter().zip(&row) {
+            if !READ_ONLY.contains(&column.as_str()) {
+                apply(&mut episode, column, unescape(value), timezone)
+                    .map_err(|e| CliError::CsvImport(format!("row {}: {}: {}", line, column, e)))?;
+            }
+        }
+
+        // Rewriting an episode nothing was changed in only tidies its YAML
+        if episode == original {
+            continue;
+        }
+        let before = String::from_utf8_lossy(&crate::crypto::read(&path)?).into_owned();
+        let after = crate::formats::updated_text(&path, &episode)?;
+        if crate::diff::print_diff(&path, &before, &after) {
+            changes.push((path, episode));
+        }
+    }
+
+    if changes.is_empty() {
+        outln!("{}", t!("csv-import-unchanged"));
+        return Ok(());
+    }
+    outln!("{}", t!("csv-import-count", count = changes.len()));
+    if !crate::diff::confirm(&t!("confirm-write"), yes)? {
+        outln!("{}", t!("nothing-written"));
+        return Ok(());
+    }
+    for (path, episode) in &changes {
+        crate::save_episode(path, episode, channel_config)?;
+    }
+    outln!("{}", t!("csv-import-done", count = changes.len()));
+    Ok(())
+}
+
+fn cell(episode: &Episode, file: &str, column: &str) -> String {
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    match column {
+        "id" => episode.id.clone(),
+        "file" => file.to_owned(),
+        "title" => episode.title.clone(),
+        "summary" => episode.summary.clone(),
+        "description" => episode.description.clone(),
+        "link" => optional(&episode.link),
+        "image" => episode.image.clone(),
+        "season" => episode.season.to_string(),
+        "episodeNumber" => episode.episode_number.to_string(),
+        "releasedAt" => episode
+            .released_at
+            .to_rfc3339_opts(SecondsFormat::Secs, true),
+        "episodeType" => episode.episode_type.as_str().to_owned(),
+        "explicit" => episode
+            .explicit
+            .map(|explicit| explicit.to_string())
+            .unwrap_or_default(),
+        "keywords" => episode.keywords.join("; "),
+        "draft" => episode.draft.to_string(),
+        "destination" => optional(&episode.destination),
+        "mediaUrl" => episode.media.url.clone(),
+        "duration" => episode.media.duration.0.to_string(),
+        "bytes" => episode.media.bytes.0.to_string(),
+        "bitrate" => episode
+            .media
+            .bitrate
+            .map(|bitrate| bitrate.to_string())
+            .unwrap_or_default(),
+        "mimeType" => episode.media.enclosure_type().to_owned(),
+        "recordedDate" => optional(&episode.media.recorded_date),
+        _ => String::new(),
+    }
+}
+
+fn apply(episode: &mut Episode, column: &str, value: &str, timezone: Tz) -> Result<(), String> {
+    let number = |value: &str| {
+        value
+            .trim()
+            .parse::<u64>()
+            .map_err(|_| format!("'{}' is not a whole number", value))
+    };
+    match column {
+        "title" => episode.title = value.to_owned(),
+        "summary" => episode.summary = value.to_owned(),
+        "description" => episode.description = value.to_owned(),
+        "link" if value.trim().is_empty() => episode.link = None,
+        "link" => episode.link = Some(value.trim().to_owned()),
+        "image" => episode.image = value.trim().to_owned(),
+        "season" => episode.season = number(value)?,
+        "episodeNumber" => episode.episode_number = number(value)?,
+        "releasedAt" => {
+            episode.released_at =
+                crate::dates::parse_release(value.trim(), timezone).map_err(|e| e.localized())?
+        }
+        "episodeType" => episode.episode_type = EpisodeType::from_str(value.trim(), true)?,
+        "explicit" => {
+            episode.explicit = match value.trim().to_lowercase().as_str() {
+                "" => None,
+                "true" | "yes" => Some(true),
+                "false" | "no" => Some(false),
+                _ => return Err(format!("'{}' is not true, false, or empty", value)),
+            }
+        }
+        "keywords" => {
+            episode.keywords = value
+                .split(';')
+                .map(str::trim)
+                .filter(|keyword| !keyword.is_empty())
+                .map(str::to_owned)
+                .collect()
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Characters a spreadsheet takes a cell starting with as a formula.
+const FORMULA_STARTS: [char; 4] = ['=', '+', '-', '@'];
+
+/// `value` quoted when it has to be, as RFC 4180 says, and escaped with a
+/// `'` when it would be taken for a formula.
+pub fn field(value: &str) -> String {
+    let value = if needs_escape(value) {
+        format!("'{}", value)
+    } else {
+        value.to_owned()
+    };
+    if value.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Whether `value` starts like a formula, or like one already escaped, so
+/// that `unescape` gives back exactly what was written.
+fn needs_escape(value: &str) -> bool {
+    let mut chars = value.chars();
+    match chars.next() {
+        Some(c) if FORMULA_STARTS.contains(&c) => true,
+        Some('\'') => matches!(chars.next(), Some(c) if c == '\'' || FORMULA_STARTS.contains(&c)),
+        _ => false,
+    }
+}
+
+/// `value` without the `'` `field` escaped it with.
+fn unescape(value: &str) -> &str {
+    match value.strip_prefix('\'') {
+        Some(rest) if needs_escape(rest) => rest,
+        _ => value,
+    }
+}
+
+/// The rows of CSV `text`, as RFC 4180 and spreadsheets write it, after
+/// the byte order mark some spreadsheets start with.
+fn parse(text: &str) -> Result<Vec<Vec<String>>, CliError> {
+    let text = text.trim_start_matches('\u{feff}');
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut value = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                value.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if value.is_empty() => quoted = true,
+            _ if quoted => value.push(c),
+            ',' => row.push(std::mem::take(&mut value)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' => {
+                row.push(std::mem::take(&mut value));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => value.push(c),
+        }
+    }
+    if quoted {
+        return Err(CliError::CsvImport(
+            "a quoted field is never closed".to_owned(),
+        ));
+    }
+    if !value.is_empty() || !row.is_empty() {
+        row.push(value);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(values: &[&str]) {
+        let line: Vec<String> = values.iter().map(|value| field(value)).collect();
+        let text = format!("{}\r\n", line.join(","));
+        let rows = parse(&text).unwrap();
+        let read: Vec<&str> = rows[0].iter().map(|value| unescape(value)).collect();
+        assert_eq!(rows.len(), 1, "{:?}", rows);
+        assert_eq!(read, values);
+    }
+
+    #[test]
+    fn round_trips_quotes_and_commas() {
+        round_trip(&["id", "The \"Big\" One", "one, two", "\"", ""]);
+    }
+
+    #[test]
+    fn round_trips_newlines_inside_fields() {
+        round_trip(&["first line\nsecond line", "crlf\r\ninside", "cr\ronly"]);
+    }
+
+    #[test]
+    fn round_trips_formulas() {
+        round_trip(&[
+            "=1+1", "+44 20", "-5", "@SUM(A1)", "'=quoted", "''", "'plain",
+        ]);
+        assert_eq!(field("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(field("'plain"), "'plain");
+    }
+
+    #[test]
+    fn reads_crlf_and_lf_rows() {
+        let rows = parse("id,title\r\na,One\nb,Two").unwrap();
+        assert_eq!(
+            rows,
+            vec![vec!["id", "title"], vec!["a", "One"], vec!["b", "Two"]]
+        );
+    }
+
+    #[test]
+    fn skips_a_byte_order_mark() {
+        let rows = parse("\u{feff}id,title\r\na,\u{e9}t\u{e9}\r\n").unwrap();
+        assert_eq!(rows, vec![vec!["id", "title"], vec!["a", "\u{e9}t\u{e9}"]]);
+    }
+
+    #[test]
+    fn unclosed_quotes_are_errors() {
+        assert!(matches!(parse("id,\"title\n"), Err(CliError::CsvImport(_))));
+    }
+}
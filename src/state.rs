@@ -0,0 +1,483 @@
+//! Local workspace state, kept in `.podcast-ctl/` next to `channel.yaml`.
+//!
+//! ```text
+//! .podcast-ctl/
+//!   VERSION        layout version, bumped when the layout changes
+//!   lock           exists while a command is writing state; holds its pid
+//!   journal.jsonl  one JSON line per object published to storage
+//!   feed.xml       the feed as it was last published, to describe changes
+//!   changelog.md   what each publish changed in the feed
+//!   podcast-guids.json
+//!                  the `podcast:guid` each feed was first published with
+//!   imports.jsonl  audio files `create-episodes` made episodes from, so an
+//!                  import that stopped partway resumes where it stopped
+//!   daemon-token   what `daemon` clients send with each request; only the
+//!                  owner can read it
+//!   cache/         derived data that is safe to delete at any time
+//!   multipart/     progress of interrupted uploads, so they can resume
+//! ```
+//!
+//! Only one command may write state at a time. Files are replaced by
+//! writing a temporary file and renaming it over the old one, so readers
+//! never see half a file. `state show` describes the directory and
+//! `state reset` clears parts of it.
+
+use crate::i18n::t;
+use crate::output::{self, outln};
+use crate::upload::Uploaded;
+use crate::CliError;
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+pub const STATE_DIR: &str = ".podcast-ctl";
+const LAYOUT_VERSION: u32 = 1;
+
+pub struct Workspace {
+    root: PathBuf,
+}
+
+/// Held while a command writes workspace state; releases the lock on drop.
+pub struct WorkspaceLock {
+    path: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub at: DateTime<Utc>,
+    /// Object key in the bucket
+    pub key: String,
+    pub url: String,
+    pub bytes: u64,
+    /// ETag storage returned for the upload, used to detect corruption
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// Hex MD5 of the uploaded contents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub md5: Option<String>,
+    /// The object was deleted from storage by `prune`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deleted: bool,
+}
+
+/// An audio file `create-episodes` made an episode from.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportEntry {
+    pub at: DateTime<Utc>,
+    /// The audio file, as an absolute path
+    pub source: PathBuf,
+    /// Its size, so a file replaced since is imported again
+    pub bytes: u64,
+    pub episode_file: PathBuf,
+}
+
+impl Workspace {
+    pub fn new(channel_dir: &Path) -> Self {
+        Self {
+            root: channel_dir.join(STATE_DIR),
+        }
+    }
+
+    /// A file under `cache/`, e.g. `transcript-index.json`.
+    pub fn cache_file(&self, name: &str) -> PathBuf {
+        self.root.join("cache").join(name)
+    }
+
+    pub fn multipart_dir(&self) -> PathBuf {
+        self.root.join("multipart")
+    }
+
+    /// The feed as this workspace last published it.
+    pub fn published_feed_path(&self) -> PathBuf {
+        self.root.join("feed.xml")
+    }
+
+    pub fn changelog_path(&self) -> PathBuf {
+        self.root.join("changelog.md")
+    }
+
+    pub fn daemon_token_path(&self) -> PathBuf {
+        self.root.join("daemon-token")
+    }
+
+    /// Write a new random token for `daemon`, readable only by the owner,
+    /// and return it.
+    pub fn new_daemon_token(&self) -> Result<String, CliError> {
+        let path = self.daemon_token_path();
+        fs::create_dir_all(&self.root)?;
+        // Created afresh, so a copy others could read doesn't keep its mode
+        if let Err(e) = fs::remove_file(&path) {
+            if e.kind() != ErrorKind::NotFound {
+                return Err(e.into());
+            }
+        }
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        options.open(&path)?.write_all(token.as_bytes())?;
+        Ok(token)
+    }
+
+    fn podcast_guids_path(&self) -> PathBuf {
+        self.root.join("podcast-guids.json")
+    }
+
+    /// The `podcast:guid` of each feed, by key, as first published.
+    pub fn podcast_guids(&self) -> Result<BTreeMap<String, String>, CliError> {
+        match fs::read(self.podcast_guids_path()) {
+            Ok(contents) => Ok(serde_json::from_slice(&contents)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Keep the `podcast:guid` of the `feeds` published for the first time,
+    /// returning those. Callers must hold the lock.
+    pub fn record_podcast_guids(
+        &self,
+        lock: &WorkspaceLock,
+        feeds: &[(String, String)],
+    ) -> Result<Vec<(String, String)>, CliError> {
+        let mut guids = self.podcast_guids()?;
+        let added: Vec<(String, String)> = feeds
+            .iter()
+            .filter(|(key, _)| !guids.contains_key(key))
+            .cloned()
+            .collect();
+        if added.is_empty() {
+            return Ok(added);
+        }
+        guids.extend(added.iter().cloned());
+        self.write_atomic(
+            lock,
+            &self.podcast_guids_path(),
+            &serde_json::to_vec_pretty(&guids)?,
+        )?;
+        Ok(added)
+    }
+
+    fn imports_path(&self) -> PathBuf {
+        self.root.join("imports.jsonl")
+    }
+
+    /// Record that `create-episodes` made `episode_file` from the audio
+    /// file `source`. Callers must hold the lock.
+    pub fn record_import(
+        &self,
+        _lock: &WorkspaceLock,
+        source: &Path,
+        bytes: u64,
+        episode_file: &Path,
+    ) -> Result<(), CliError> {
+        let entry = ImportEntry {
+            at: Utc::now(),
+            source: source.to_owned(),
+            bytes,
+            episode_file: episode_file.to_owned(),
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.imports_path())?
+            .write_all(&line)?;
+        Ok(())
+    }
+
+    /// Every audio file `create-episodes` made an episode from.
+    pub fn imports(&self) -> Result<Vec<ImportEntry>, CliError> {
+        let text = match fs::read_to_string(self.imports_path()) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    fn journal_path(&self) -> PathBuf {
+        self.root.join("journal.jsonl")
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        self.root.join("lock")
+    }
+
+    fn ensure_layout(&self) -> Result<(), CliError> {
+        fs::create_dir_all(self.root.join("cache"))?;
+        fs::create_dir_all(self.multipart_dir())?;
+
+        let version_path = self.root.join("VERSION");
+        match fs::read_to_string(&version_path) {
+            Ok(version) if version.trim() == LAYOUT_VERSION.to_string() => {}
+            Ok(version) => warn!(
+                "{} has layout version {}, expected {}",
+                self.root.display(),
+                version.trim(),
+                LAYOUT_VERSION
+            ),
+            Err(_) => fs::write(&version_path, format!("{}\n", LAYOUT_VERSION))?,
+        }
+        Ok(())
+    }
+
+    /// Take the workspace lock, failing if another command holds it.
+    pub fn lock(&self) -> Result<WorkspaceLock, CliError> {
+        self.ensure_layout()?;
+        let path = self.lock_path();
+
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                writeln!(file, "{} {}", std::process::id(), Utc::now().to_rfc3339())?;
+                debug!("Took workspace lock {}", path.display());
+                Ok(WorkspaceLock { path })
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => Err(CliError::WorkspaceLocked(
+                path.display().to_string(),
+                self.lock_holder().unwrap_or_default(),
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// `<pid> <time>` of the command holding the lock, if any.
+    pub fn lock_holder(&self) -> Option<String> {
+        fs::read_to_string(self.lock_path())
+            .ok()
+            .map(|holder| holder.trim().to_owned())
+    }
+
+    /// Record an object published to storage. Callers must hold the lock.
+    /// Nothing is recorded on a dry run, since nothing was published.
+    pub fn record_upload(
+        &self,
+        _lock: &WorkspaceLock,
+        key: &str,
+        uploaded: &Uploaded,
+        bytes: u64,
+    ) -> Result<(), CliError> {
+        if crate::upload::dry_run() {
+            return Ok(());
+        }
+        let entry = JournalEntry {
+            at: Utc::now(),
+            key: key.to_owned(),
+            url: uploaded.url.clone(),
+            bytes,
+            etag: uploaded.etag.clone(),
+            md5: uploaded.md5.clone(),
+            deleted: false,
+        };
+        self.append_journal(&entry)
+    }
+
+    /// Record an object deleted from storage, so it leaves the manifest.
+    /// Callers must hold the lock.
+    pub fn record_delete(&self, _lock: &WorkspaceLock, key: &str) -> Result<(), CliError> {
+        let entry = JournalEntry {
+            at: Utc::now(),
+            key: key.to_owned(),
+            url: String::new(),
+            bytes: 0,
+            etag: None,
+            md5: None,
+            deleted: true,
+        };
+        self.append_journal(&entry)
+    }
+
+    fn append_journal(&self, entry: &JournalEntry) -> Result<(), CliError> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let mut journal = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.journal_path())?;
+        journal.write_all(&line)?;
+        Ok(())
+    }
+
+    /// The latest journal entry for every published key, which is what
+    /// storage should currently hold.
+    pub fn manifest(&self) -> Result<BTreeMap<String, JournalEntry>, CliError> {
+        let mut manifest = BTreeMap::new();
+        for entry in self.journal()? {
+            if entry.deleted {
+                manifest.remove(&entry.key);
+            } else {
+                manifest.insert(entry.key.clone(), entry);
+            }
+        }
+        Ok(manifest)
+    }
+
+    pub fn journal(&self) -> Result<Vec<JournalEntry>, CliError> {
+        let text = match fs::read_to_string(self.journal_path()) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    /// Replace `path` with `contents` without readers ever seeing a partial
+    /// file. Callers must hold the lock.
+    pub fn write_atomic(
+        &self,
+        _lock: &WorkspaceLock,
+        path: &Path,
+        contents: &[u8],
+    ) -> Result<(), CliError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temporary = path.with_extension(format!("tmp.{}", std::process::id()));
+        fs::write(&temporary, contents)?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+
+    /// Print the layout and what each part currently holds.
+    pub fn show(&self) -> Result<(), CliError> {
+        outln!(
+            "{}",
+            t!("workspace-root", path = self.root.display().to_string())
+        );
+        output::record("workspace", &self.root);
+        if !self.root.exists() {
+            outln!("  {}", t!("workspace-empty"));
+            return Ok(());
+        }
+
+        let version = fs::read_to_string(self.root.join("VERSION")).unwrap_or_default();
+        outln!("  {:<15} {}", t!("workspace-version"), version.trim());
+        let lock = match self.lock_holder() {
+            Some(holder) => t!("workspace-lock-held", pid = holder),
+            None => t!("workspace-lock-free"),
+        };
+        outln!("  {:<15} {}", t!("workspace-lock"), lock);
+
+        let journal = self.journal()?;
+        outln!(
+            "  {:<15} {}",
+            t!("workspace-journal"),
+            t!("workspace-journal-objects", count = journal.len())
+        );
+        output::record("layoutVersion", version.trim());
+        output::record("lockHolder", self.lock_holder());
+        output::record("journal", journal.len());
+        for entry in journal.iter().rev().take(5) {
+            outln!(
+                "    {}  {}",
+                entry.at.format("%Y-%m-%d %H:%M:%S"),
+                entry.key
+            );
+        }
+
+        for (name, dir) in [
+            ("cache", self.root.join("cache")),
+            ("multipart", self.multipart_dir()),
+        ] {
+            let (files, bytes) = dir_usage(&dir);
+            output::record(name, serde_json::json!({ "files": files, "bytes": bytes }));
+            outln!(
+                "  {:<15} {}",
+                format!("{}:", name),
+                t!(
+                    "workspace-usage",
+                    count = files,
+                    size = crate::preview::format_bytes(bytes)
+                )
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Delete the selected parts of the workspace.
+    pub fn reset(&self, parts: &ResetParts) -> Result<(), CliError> {
+        if parts.lock {
+            remove(&self.lock_path())?;
+        }
+
+        let _lock = self.lock()?;
+        if parts.cache {
+            remove(&self.root.join("cache"))?;
+        }
+        if parts.multipart {
+            remove(&self.multipart_dir())?;
+        }
+        if parts.journal {
+            remove(&self.journal_path())?;
+        }
+        self.ensure_layout()
+    }
+}
+
+impl Drop for WorkspaceLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Unable to release {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Which parts of the workspace `state reset` deletes.
+pub struct ResetParts {
+    pub cache: bool,
+    pub multipart: bool,
+    pub journal: bool,
+    pub lock: bool,
+}
+
+fn remove(path: &Path) -> Result<(), CliError> {
+    let result = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    match result {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => {
+            debug!("Removed {}", path.display());
+            Ok(())
+        }
+    }
+}
+
+fn dir_usage(dir: &Path) -> (usize, u64) {
+    let mut files = 0;
+    let mut bytes = 0;
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                let (nested_files, nested_bytes) = dir_usage(&path);
+                files += nested_files;
+                bytes += nested_bytes;
+            } else if let Ok(metadata) = entry.metadata() {
+                files += 1;
+                bytes += metadata.len();
+            }
+        }
+    }
+    (files, bytes)
+}
@@ -0,0 +1,1263 @@
+//! `stats`: downloads of each episode, counted in the access logs S3 or
+//! CloudFront writes for the show. Logs are read from the bucket
+//! `analytics` in channel.yaml names, or from a directory they were copied
+//! to with `--logs`:
+//!
+//! ```yaml
+//! analytics:
+//!   format: cloudfront     # s3 by default
+//!   bucket: my-show-logs   # publishing.bucket by default
+//!   prefix: cloudfront/
+//! ```
+//!
+//! A download is a GET of an episode's media that was served, from a
+//! client that isn't a known bot, counted once per address, user agent,
+//! episode, and day. Apps fetching an episode in ranges count once, and
+//! requests that sent under 1 KiB, like the `bytes=0-1` probes apps make
+//! before playing, don't count on their own.
+//!
+//! `stats --geography` counts downloads of each episode by country and
+//! region instead, looking addresses up in a MaxMind database, like
+//! GeoLite2-City.mmdb, set with `analytics.geoipDatabase` or `--geoip`.
+//! Lookups are made locally: addresses aren't sent anywhere.
+//!
+//! `analytics compare` compares the latest episodes' downloads in their
+//! first days after release. Episodes still in that window are projected
+//! to its end from the days so far, and those a quarter or more above or
+//! below the median episode are marked.
+//!
+//! Access logs hold listeners' addresses. With `analytics.anonymize`,
+//! addresses are counted truncated to their network (`truncate`), or as
+//! their HMAC-SHA256 (`hash`, keyed with `PODCAST_CTL_ANALYTICS_SALT`),
+//! and `purge-analytics` rewrites the stored logs the same way. It also
+//! deletes the logs written before `--before`, or more than
+//! `analytics.retentionDays` ago:
+//!
+//! ```yaml
+//! analytics:
+//!   anonymize: truncate    # or hash
+//!   retentionDays: 90
+//! ```
+//!
+//! Hashed addresses can't be located, so `stats --geography` counts their
+//! downloads in an unknown place.
+
+use crate::config::{AccessLogFormat, AnalyticsConfig, Anonymize, Episode, PublishingConfig};
+use crate::i18n::t;
+use crate::output::{self, outln};
+use crate::spreadsheet;
+use crate::upload::retry;
+use crate::CliError;
+use aws_sdk_s3::primitives::ByteStream;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hmac::{Hmac, Mac};
+use log::{debug, info};
+use maxminddb::geoip2;
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+/// Salt for `analytics.anonymize: hash`, so hashes can't be reversed by
+/// hashing every address.
+pub const SALT_ENV: &str = "PODCAST_CTL_ANALYTICS_SALT";
+
+/// How far from the median an episode is marked as over or under
+/// performing.
+const OUTLIER: f64 = 0.25;
+
+/// Downloads that sent less than this are probes.
+const MIN_BYTES: u64 = 1024;
+
+/// User agents of crawlers and scripts, matched case insensitively.
+const BOTS: &[&str] = &[
+    "bot",
+    "crawl",
+    "spider",
+    "slurp",
+    "curl/",
+    "wget/",
+    "python-requests",
+    "go-http-client",
+    "headlesschrome",
+    "facebookexternalhit",
+];
+
+/// The columns of a CloudFront log without a `#Fields` line.
+const CLOUDFRONT_FIELDS: &[&str] = &[
+    "date",
+    "time",
+    "x-edge-location",
+    "sc-bytes",
+    "c-ip",
+    "cs-method",
+    "cs(Host)",
+    "cs-uri-stem",
+    "sc-status",
+    "cs(Referer)",
+    "cs(User-Agent)",
+];
+
+/// How downloads are summed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Period {
+    Day,
+    /// Weeks are named for their Monday
+    Week,
+}
+
+impl Period {
+    fn start(&self, day: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => day,
+            Period::Week => day - Duration::days(day.weekday().num_days_from_monday() as i64),
+        }
+    }
+}
+
+/// Which downloads to count.
+pub struct StatsOptions<'a> {
+    /// A directory of logs to read instead of the log bucket
+    pub logs: Option<&'a Path>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    pub by: Period,
+    /// Count by country and region, with this MaxMind database
+    pub geography: Option<&'a Path>,
+}
+
+/// Which episodes `analytics compare` compares.
+pub struct CompareOptions<'a> {
+    /// A directory of logs to read instead of the log bucket
+    pub logs: Option<&'a Path>,
+    /// Days after release that are counted
+    pub window: u64,
+    pub csv: bool,
+}
+
+/// Which stored logs `purge-analytics` deletes.
+pub struct PurgeOptions<'a> {
+    /// A directory of logs to purge instead of the log bucket
+    pub logs: Option<&'a Path>,
+    /// Delete logs written before this day, instead of those older than
+    /// `analytics.retentionDays`
+    pub before: Option<NaiveDate>,
+}
+
+/// Anonymizes addresses as `analytics.anonymize` says.
+struct Anonymizer {
+    anonymize: Option<Anonymize>,
+    salt: String,
+}
+
+impl Anonymizer {
+    fn new(config: &AnalyticsConfig) -> Result<Anonymizer, CliError> {
+        let salt = match config.anonymize {
+            Some(Anonymize::Hash) => {
+                std::env::var(SALT_ENV).map_err(|_| CliError::MissingEnvVar(SALT_ENV.to_owned()))?
+            }
+            _ => String::new(),
+        };
+        Ok(Anonymizer {
+            anonymize: config.anonymize,
+            salt,
+        })
+    }
+
+    /// `address` anonymized. Anything that isn't an address, like one
+    /// hashed already, is kept as it is.
+    fn address(&self, address: &str) -> String {
+        let ip = match (self.anonymize, address.parse::<IpAddr>()) {
+            (Some(anonymize), Ok(ip)) => (anonymize, ip),
+            _ => return address.to_owned(),
+        };
+        match ip {
+            (Anonymize::Truncate, IpAddr::V4(ip)) => {
+                let [a, b, c, _] = ip.octets();
+                Ipv4Addr::new(a, b, c, 0).to_string()
+            }
+            (Anonymize::Truncate, IpAddr::V6(ip)) => {
+                let segments = ip.segments();
+                Ipv6Addr::new(segments[0], segments[1], segments[2], 0, 0, 0, 0, 0).to_string()
+            }
+            (Anonymize::Hash, ip) => {
+                // HMAC takes keys of any length, so this can't fail
+                let mut mac = Hmac::<Sha256>::new_from_slice(self.salt.as_bytes())
+                    .expect("HMAC accepts any key");
+                mac.update(ip.to_string().as_bytes());
+                mac.finalize()
+                    .into_bytes()
+                    .iter()
+                    .fold(String::new(), |mut hex, byte| {
+                        let _ = write!(hex, "{:02x}", byte);
+                        hex
+                    })
+            }
+        }
+    }
+}
+
+/// One request in a log.
+#[derive(Debug)]
+struct Request {
+    at: DateTime<Utc>,
+    ip: String,
+    user_agent: String,
+    path: String,
+    bytes_sent: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Row<'a> {
+    period: String,
+    id: &'a str,
+    season: u64,
+    episode_number: u64,
+    title: &'a str,
+    downloads: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeoRow<'a> {
+    id: &'a str,
+    season: u64,
+    episode_number: u64,
+    title: &'a str,
+    /// ISO 3166 country code, unless the address isn't in the database
+    country: Option<String>,
+    region: Option<String>,
+    downloads: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompareRow<'a> {
+    id: &'a str,
+    season: u64,
+    episode_number: u64,
+    title: &'a str,
+    released: NaiveDate,
+    /// Days of the window that have passed
+    days: u64,
+    downloads: u64,
+    /// Downloads over the whole window, from the days so far
+    projected: f64,
+    /// `projected` as a percentage of the median episode's
+    percent_of_median: Option<u64>,
+    performance: Performance,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Performance {
+    Over,
+    Typical,
+    Under,
+}
+
+impl Performance {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Performance::Over => "over",
+            Performance::Typical => "typical",
+            Performance::Under => "under",
+        }
+    }
+}
+
+/// Downloads counted so far.
+#[derive(Default)]
+struct Tally {
+    /// Bytes sent per address, user agent, episode, and day
+    listens: HashMap<(String, String, usize, NaiveDate), u64>,
+    requests: usize,
+    bots: usize,
+}
+
+impl Tally {
+    fn add(&mut self, request: Request, episodes: &HashMap<&str, usize>, options: &StatsOptions) {
+        let day = request.at.naive_utc().date();
+        if matches!(options.since, Some(since) if day < since)
+            || matches!(options.until, Some(until) if day > until)
+        {
+            return;
+        }
+        let name = request.path.rsplit('/').next().unwrap_or_default();
+        let episode = match episodes.get(name) {
+            Some(episode) => *episode,
+            None => return,
+        };
+        self.requests += 1;
+        let agent = request.user_agent.to_lowercase();
+        if BOTS.iter().any(|bot| agent.contains(bot)) {
+            self.bots += 1;
+            return;
+        }
+        *self
+            .listens
+            .entry((request.ip, request.user_agent, episode, day))
+            .or_default() += request.bytes_sent;
+    }
+
+    /// Downloads per period and episode.
+    fn downloads(&self, by: Period) -> BTreeMap<(NaiveDate, usize), u64> {
+        let mut downloads = BTreeMap::new();
+        for ((_, _, episode, day), bytes) in &self.listens {
+            if *bytes >= MIN_BYTES {
+                *downloads.entry((by.start(*day), *episode)).or_default() += 1;
+            }
+        }
+        downloads
+    }
+
+    /// Downloads per episode, country, and region.
+    fn geography(
+        &self,
+        geoip: &maxminddb::Reader<Vec<u8>>,
+    ) -> BTreeMap<(usize, Option<String>, Option<String>), u64> {
+        let mut places: HashMap<&str, Place> = HashMap::new();
+        let mut downloads = BTreeMap::new();
+        for ((ip, _, episode, _), bytes) in &self.listens {
+            if *bytes < MIN_BYTES {
+                continue;
+            }
+            let (country, region) = places
+                .entry(ip.as_str())
+                .or_insert_with(|| locate(geoip, ip));
+            *downloads
+                .entry((*episode, country.clone(), region.clone()))
+                .or_default() += 1;
+        }
+        downloads
+    }
+}
+
+/// A country code and region, when they are known.
+type Place = (Option<String>, Option<String>);
+
+fn locate(geoip: &maxminddb::Reader<Vec<u8>>, ip: &str) -> Place {
+    let city = ip
+        .parse::<IpAddr>()
+        .ok()
+        .and_then(|ip| geoip.lookup::<geoip2::City>(ip).ok());
+    let country = city
+        .as_ref()
+        .and_then(|city| city.country.as_ref()?.iso_code);
+    let region = city.as_ref().and_then(|city| {
+        let region = city.subdivisions.as_ref()?.first()?;
+        region
+            .names
+            .as_ref()
+            .and_then(|names| names.get("en").copied())
+            .or(region.iso_code)
+    });
+    (country.map(str::to_owned), region.map(str::to_owned))
+}
+
+/// Count and print downloads of `episodes`.
+pub async fn stats(
+    config: &AnalyticsConfig,
+    publishing: &PublishingConfig,
+    episodes: &[Episode],
+    options: &StatsOptions<'_>,
+) -> Result<(), CliError> {
+    let (tally, files) = read_logs(config, publishing, episodes, options).await?;
+    if let Some(database) = options.geography {
+        let geoip = maxminddb::Reader::open_readfile(database)
+            .map_err(|e| CliError::GeoipError(format!("{}: {}", database.display(), e)))?;
+        let downloads = tally.geography(&geoip);
+        let rows: Vec<GeoRow> = downloads
+            .iter()
+            .map(|((index, country, region), downloads)| {
+                let episode = &episodes[*index];
+                GeoRow {
+                    id: &episode.id,
+                    season: episode.season,
+                    episode_number: episode.episode_number,
+                    title: &episode.title,
+                    country: country.clone(),
+                    region: region.clone(),
+                    downloads: *downloads,
+                }
+            })
+            .collect();
+        print_geo_rows(&rows);
+        outln!("{}", summary(downloads.values().sum(), &tally, files));
+        output::record("geography", &rows);
+        return Ok(());
+    }
+
+    let downloads = tally.downloads(options.by);
+    let rows: Vec<Row> = downloads
+        .iter()
+        .map(|((start, index), downloads)| {
+            let episode = &episodes[*index];
+            Row {
+                period: start.format("%Y-%m-%d").to_string(),
+                id: &episode.id,
+                season: episode.season,
+                episode_number: episode.episode_number,
+                title: &episode.title,
+                downloads: *downloads,
+            }
+        })
+        .collect();
+    print_rows(&rows);
+    outln!("{}", summary(downloads.values().sum(), &tally, files));
+    output::record("downloads", &rows);
+    Ok(())
+}
+
+/// Compare downloads of the latest episodes, `recent`, newest first, in
+/// the days after their release.
+pub async fn compare(
+    config: &AnalyticsConfig,
+    publishing: &PublishingConfig,
+    recent: &[Episode],
+    options: &CompareOptions<'_>,
+) -> Result<(), CliError> {
+    let now = Utc::now();
+    let released = |episode: &Episode| episode.released_at.naive_utc().date();
+
+    let stats_options = StatsOptions {
+        logs: options.logs,
+        since: recent.iter().map(released).min(),
+        until: None,
+        by: Period::Day,
+        geography: None,
+    };
+    let (tally, files) = read_logs(config, publishing, recent, &stats_options).await?;
+    let mut downloads = vec![0; recent.len()];
+    for ((_, _, episode, day), bytes) in &tally.listens {
+        let after = (*day - released(&recent[*episode])).num_days();
+        if *bytes >= MIN_BYTES && (0..options.window as i64).contains(&after) {
+            downloads[*episode] += 1;
+        }
+    }
+
+    let today = now.naive_utc().date();
+    let days: Vec<u64> = recent
+        .iter()
+        .map(|episode| {
+            let passed = (today - released(episode)).num_days() as u64 + 1;
+            passed.min(options.window)
+        })
+        .collect();
+    let projected: Vec<f64> = downloads
+        .iter()
+        .zip(&days)
+        .map(|(downloads, days)| *downloads as f64 * options.window as f64 / *days as f64)
+        .collect();
+    let median = median(&projected).filter(|median| *median > 0.0);
+    let rows: Vec<CompareRow> = recent
+        .iter()
+        .enumerate()
+        .map(|(index, episode)| {
+            let relative = median.map(|median| projected[index] / median);
+            CompareRow {
+                id: &episode.id,
+                season: episode.season,
+                episode_number: episode.episode_number,
+                title: &episode.title,
+                released: released(episode),
+                days: days[index],
+                downloads: downloads[index],
+                projected: (projected[index] * 10.0).round() / 10.0,
+                percent_of_median: relative.map(|relative| (relative * 100.0).round() as u64),
+                performance: match relative {
+                    Some(relative) if relative >= 1.0 + OUTLIER => Performance::Over,
+                    Some(relative) if relative <= 1.0 - OUTLIER => Performance::Under,
+                    _ => Performance::Typical,
+                },
+            }
+        })
+        .collect();
+
+    if options.csv {
+        print_csv(&rows);
+    } else {
+        print_compare_rows(&rows, options.window);
+    }
+    let summary = summary(downloads.iter().sum(), &tally, files);
+    // Keep CSV on stdout clean for spreadsheets
+    if options.csv {
+        eprintln!("{}", summary);
+    } else {
+        outln!("{}", summary);
+    }
+    output::record("window", options.window);
+    output::record("episodes", &rows);
+    Ok(())
+}
+
+fn median(values: &[f64]) -> Option<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let middle = sorted.len() / 2;
+    match sorted.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((sorted[middle - 1] + sorted[middle]) / 2.0),
+        _ => Some(sorted[middle]),
+    }
+}
+
+/// A window like `7d`, `2w`, or `7`, in days.
+pub fn parse_window(text: &str) -> Result<u64, String> {
+    let (number, unit) = match text.strip_suffix('w') {
+        Some(weeks) => (weeks, 7),
+        None => (text.strip_suffix('d').unwrap_or(text), 1),
+    };
+    match number.parse::<u64>() {
+        Ok(number) if number > 0 => Ok(number * unit),
+        _ => Err(format!(
+            "'{}' isn't a number of days or weeks, like 7d or 2w",
+            text
+        )),
+    }
+}
+
+/// Requests for `episodes` in the logs, and how many logs were read.
+async fn read_logs(
+    config: &AnalyticsConfig,
+    publishing: &PublishingConfig,
+    episodes: &[Episode],
+    options: &StatsOptions<'_>,
+) -> Result<(Tally, usize), CliError> {
+    let names: HashMap<&str, usize> = episodes
+        .iter()
+        .enumerate()
+        .filter_map(|(index, episode)| Some((episode.media.url.rsplit('/').next()?, index)))
+        .collect();
+
+    let anonymizer = Anonymizer::new(config)?;
+    let mut tally = Tally::default();
+    let mut files = 0;
+    let mut add_log = |name: &str, contents: Vec<u8>| -> Result<(), CliError> {
+        for mut request in parse_log(config.format, name, contents)? {
+            request.ip = anonymizer.address(&request.ip);
+            tally.add(request, &names, options);
+        }
+        files += 1;
+        Ok(())
+    };
+    match options.logs {
+        Some(dir) => {
+            let mut paths: Vec<_> = std::fs::read_dir(dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<_, _>>()?;
+            paths.sort();
+            for path in paths.iter().filter(|path| path.is_file()) {
+                let name = path.to_string_lossy();
+                if !skipped(&file_name(path), options.since) {
+                    add_log(&name, std::fs::read(path)?)?;
+                }
+            }
+        }
+        None => {
+            let bucket = config.bucket.as_deref().unwrap_or(&publishing.bucket);
+            let client = crate::upload::client(publishing).await;
+            for (key, _) in list_logs(&client, publishing, bucket, &config.prefix).await? {
+                if skipped(&key, options.since) {
+                    continue;
+                }
+                info!("Reading access log s3://{}/{}", bucket, key);
+                add_log(&key, read_log(&client, publishing, bucket, &key).await?)?;
+            }
+        }
+    }
+    Ok((tally, files))
+}
+
+fn summary(downloads: u64, tally: &Tally, files: usize) -> String {
+    t!(
+        "stats-summary",
+        downloads = downloads,
+        requests = tally.requests,
+        bots = tally.bots,
+        files = files
+    )
+}
+
+fn print_rows(rows: &[Row]) {
+    if output::screen_reader() {
+        for row in rows {
+            outln!(
+                "{}: {} download(s) of season {}, episode {}: {}.",
+                row.period, row.downloads, row.season, row.episode_number, row.title
+            );
+        }
+        return;
+    }
+    outln!(
+        "{:<10}  {:>3} {:>4}  {:>9}  Title",
+        "Period", "S", "E", "Downloads"
+    );
+    for row in rows {
+        outln!(
+            "{:<10}  {:>3} {:>4}  {:>9}  {}",
+            row.period, row.season, row.episode_number, row.downloads, row.title
+        );
+    }
+}
+
+fn print_geo_rows(rows: &[GeoRow]) {
+    if output::screen_reader() {
+        for row in rows {
+            let place = match (&row.region, &row.country) {
+                (Some(region), Some(country)) => format!("{}, {}", region, country),
+                (None, Some(place)) | (Some(place), None) => place.clone(),
+                (None, None) => "an unknown place".to_owned(),
+            };
+            outln!(
+                "Season {}, episode {}: {} download(s) from {}.",
+                row.season, row.episode_number, row.downloads, place
+            );
+        }
+        return;
+    }
+    let known = |place: &Option<String>| place.clone().unwrap_or_else(|| "-".to_owned());
+    outln!(
+        "{:>3} {:>4}  {:<7}  {:<24}  {:>9}  Title",
+        "S", "E", "Country", "Region", "Downloads"
+    );
+    for row in rows {
+        outln!(
+            "{:>3} {:>4}  {:<7}  {:<24}  {:>9}  {}",
+            row.season,
+            row.episode_number,
+            known(&row.country),
+            known(&row.region),
+            row.downloads,
+            row.title
+        );
+    }
+}
+
+fn print_compare_rows(rows: &[CompareRow], window: u64) {
+    if output::screen_reader() {
+        for row in rows {
+            outln!(
+                "Season {}, episode {}: {}, released {}. {} download(s) in {} of {} day(s), \
+                 {} projected, {}.",
+                row.season,
+                row.episode_number,
+                row.title,
+                row.released,
+                row.downloads,
+                row.days,
+                window,
+                row.projected,
+                row.performance.as_str()
+            );
+        }
+        return;
+    }
+    outln!(
+        "{:>3} {:>4}  {:<10}  {:>4}  {:>9}  {:>9}  {:>9}  {:<5}  Title",
+        "S", "E", "Released", "Days", "Downloads", "Projected", "Of median", ""
+    );
+    for row in rows {
+        let percent = row
+            .percent_of_median
+            .map(|percent| format!("{}%", percent))
+            .unwrap_or_else(|| "-".to_owned());
+        let mark = match row.performance {
+            Performance::Typical => "",
+            performance => performance.as_str(),
+        };
+        outln!(
+            "{:>3} {:>4}  {:<10}  {:>4}  {:>9}  {:>9.1}  {:>9}  {:<5}  {}",
+            row.season,
+            row.episode_number,
+            row.released,
+            row.days,
+            row.downloads,
+            row.projected,
+            percent,
+            mark,
+            row.title
+        );
+    }
+}
+
+fn print_csv(rows: &[CompareRow]) {
+    outln!(
+        "id,season,episode,title,released,days,downloads,projected,percentOfMedian,performance"
+    );
+    for row in rows {
+        outln!(
+            "{},{},{},{},{},{},{},{},{},{}",
+            spreadsheet::field(row.id),
+            row.season,
+            row.episode_number,
+            spreadsheet::field(row.title),
+            row.released,
+            row.days,
+            row.downloads,
+            row.projected,
+            row.percent_of_median
+                .map(|percent| percent.to_string())
+                .unwrap_or_default(),
+            row.performance.as_str()
+        );
+    }
+}
+
+/// Delete the stored logs written before `options.before`, or more than
+/// `analytics.retentionDays` ago, and anonymize the addresses in the rest
+/// when `analytics.anonymize` is set. With `--dry-run`, only say which.
+pub async fn purge(
+    config: &AnalyticsConfig,
+    publishing: &PublishingConfig,
+    options: &PurgeOptions<'_>,
+) -> Result<(), CliError> {
+    let before = match (options.before, config.retention_days) {
+        (Some(before), _) => before,
+        (None, Some(days)) => Utc::now().naive_utc().date() - Duration::days(days as i64),
+        (None, None) => {
+            return Err(CliError::PurgeAnalytics(
+                "pass --before, or set `analytics.retentionDays` in channel.yaml".to_owned(),
+            ))
+        }
+    };
+    let expired = |name: &str, modified: Option<NaiveDate>| {
+        written(name).or(modified).map_or(false, |day| day < before)
+    };
+    let anonymizer = Anonymizer::new(config)?;
+    let dry_run = crate::upload::dry_run();
+    let (mut deleted, mut anonymized) = (0, 0);
+
+    match options.logs {
+        Some(dir) => {
+            let mut paths: Vec<_> = std::fs::read_dir(dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<_, _>>()?;
+            paths.sort();
+            for path in paths.iter().filter(|path| path.is_file()) {
+                let name = path.to_string_lossy();
+                let modified = std::fs::metadata(path)?
+                    .modified()
+                    .map(|modified| DateTime::<Utc>::from(modified).naive_utc().date())
+                    .ok();
+                // Only the file's own name, since a directory above it
+                // may be named for a day too
+                if expired(&file_name(path), modified) {
+                    if !dry_run {
+                        std::fs::remove_file(path)?;
+                    }
+                    print_purged("purge-deleted", &name, dry_run);
+                    deleted += 1;
+                } else if anonymizer.anonymize.is_some() {
+                    let contents = std::fs::read(path)?;
+                    let contents = anonymized_log(config.format, &name, contents, &anonymizer)?;
+                    if let Some(contents) = contents {
+                        if !dry_run {
+                            std::fs::write(path, contents)?;
+                        }
+                        print_purged("purge-anonymized", &name, dry_run);
+                        anonymized += 1;
+                    }
+                }
+            }
+        }
+        None => {
+            let bucket = config.bucket.as_deref().unwrap_or(&publishing.bucket);
+            let client = crate::upload::client(publishing).await;
+            for (key, modified) in list_logs(&client, publishing, bucket, &config.prefix).await? {
+                if expired(&key, modified) {
+                    if !dry_run {
+                        retry(&publishing.retry, &key, || {
+                            client.delete_object().bucket(bucket).key(&key).send()
+                        })
+                        .await?;
+                    }
+                    print_purged("purge-deleted", &key, dry_run);
+                    deleted += 1;
+                } else if anonymizer.anonymize.is_some() {
+                    let contents = read_log(&client, publishing, bucket, &key).await?;
+                    let contents = match anonymized_log(config.format, &key, contents, &anonymizer)?
+                    {
+                        Some(contents) => bytes::Bytes::from(contents),
+                        None => continue,
+                    };
+                    if !dry_run {
+                        retry(&publishing.retry, &key, || {
+                            client
+                                .put_object()
+                                .bucket(bucket)
+                                .key(&key)
+                                .body(ByteStream::from(contents.clone()))
+                                .send()
+                        })
+                        .await?;
+                    }
+                    print_purged("purge-anonymized", &key, dry_run);
+                    anonymized += 1;
+                }
+            }
+        }
+    }
+
+    outln!(
+        "{}",
+        t!(
+            "purge-summary",
+            deleted = deleted,
+            anonymized = anonymized,
+            before = before.to_string()
+        )
+    );
+    output::record("before", before);
+    output::record("deleted", deleted);
+    output::record("anonymized", anonymized);
+    Ok(())
+}
+
+fn print_purged(id: &str, name: &str, dry_run: bool) {
+    let id = if dry_run {
+        format!("{}-dry-run", id)
+    } else {
+        id.to_owned()
+    };
+    outln!("{}", t!(&id, name = name));
+}
+
+/// Keys of the logs under `prefix` in `bucket`, with the day each was
+/// last modified.
+async fn list_logs(
+    client: &aws_sdk_s3::Client,
+    publishing: &PublishingConfig,
+    bucket: &str,
+    prefix: &str,
+) -> Result<Vec<(String, Option<NaiveDate>)>, CliError> {
+    let mut logs = Vec::new();
+    let mut continuation: Option<String> = None;
+    loop {
+        let output = retry(&publishing.retry, prefix, || {
+            client
+                .list_objects_v2()
+                .bucket(bucket)
+                .prefix(prefix)
+                .set_continuation_token(continuation.clone())
+                .send()
+        })
+        .await?;
+        logs.extend(output.contents().iter().filter_map(|object| {
+            let modified = object
+                .last_modified()
+                .and_then(|modified| DateTime::from_timestamp(modified.secs(), 0))
+                .map(|modified| modified.date_naive());
+            Some((object.key()?.to_owned(), modified))
+        }));
+        match output.next_continuation_token {
+            Some(next) if output.is_truncated == Some(true) => continuation = Some(next),
+            _ => break,
+        }
+    }
+    Ok(logs)
+}
+
+async fn read_log(
+    client: &aws_sdk_s3::Client,
+    publishing: &PublishingConfig,
+    bucket: &str,
+    key: &str,
+) -> Result<Vec<u8>, CliError> {
+    let object = retry(&publishing.retry, key, || {
+        client.get_object().bucket(bucket).key(key).send()
+    })
+    .await?;
+    let contents = object
+        .body
+        .collect()
+        .await
+        .map_err(|e| CliError::StorageUnavailable(e.to_string()))?;
+    Ok(contents.to_vec())
+}
+
+/// The log `name` with its addresses anonymized, compressed again when it
+/// was, or `None` when there was nothing to anonymize.
+fn anonymized_log(
+    format: AccessLogFormat,
+    name: &str,
+    contents: Vec<u8>,
+    anonymizer: &Anonymizer,
+) -> Result<Option<Vec<u8>>, CliError> {
+    let compressed = contents.starts_with(&[0x1f, 0x8b]);
+    let contents = decompress(name, contents)?;
+    let text = String::from_utf8_lossy(&contents);
+    let anonymized = match format {
+        AccessLogFormat::S3 => text
+            .split('\n')
+            .map(|line| anonymize_s3(line, anonymizer))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        AccessLogFormat::Cloudfront => anonymize_cloudfront(&text, anonymizer),
+    };
+    if anonymized == text {
+        return Ok(None);
+    }
+    if !compressed {
+        return Ok(Some(anonymized.into_bytes()));
+    }
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(anonymized.as_bytes())?;
+    Ok(Some(encoder.finish()?))
+}
+
+/// An S3 log line with its remote address, the field after the time in
+/// brackets, anonymized.
+fn anonymize_s3(line: &str, anonymizer: &Anonymizer) -> String {
+    let start = match line.find("] ") {
+        Some(end) => end + 2,
+        None => return line.to_owned(),
+    };
+    let end = line[start..]
+        .find(' ')
+        .map_or(line.len(), |length| start + length);
+    format!(
+        "{}{}{}",
+        &line[..start],
+        anonymizer.address(&line[start..end]),
+        &line[end..]
+    )
+}
+
+/// A CloudFront log with its `c-ip` and `x-forwarded-for` columns
+/// anonymized.
+fn anonymize_cloudfront(text: &str, anonymizer: &Anonymizer) -> String {
+    let mut columns: Vec<&str> = CLOUDFRONT_FIELDS.to_vec();
+    let mut lines = Vec::new();
+    for line in text.split('\n') {
+        if let Some(names) = line.strip_prefix("#Fields:") {
+            columns = names.split_whitespace().collect();
+        }
+        if line.starts_with('#') {
+            lines.push(line.to_owned());
+            continue;
+        }
+        let values: Vec<String> = line
+            .split('\t')
+            .enumerate()
+            .map(|(index, value)| match columns.get(index) {
+                Some(&"c-ip") => anonymizer.address(value),
+                Some(&"x-forwarded-for") => value
+                    .split(',')
+                    .map(|address| anonymizer.address(address.trim()))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                _ => value.to_owned(),
+            })
+            .collect();
+        lines.push(values.join("\t"));
+    }
+    lines.join("\n")
+}
+
+/// The day a log was written, from the date in its name, like S3's
+/// `2022-09-01-12-00-00-...` and CloudFront's `ID.2022-09-01-12.abc.gz`.
+fn written(name: &str) -> Option<NaiveDate> {
+    let name = name.rsplit('/').next().unwrap_or_default();
+    (0..name.len().saturating_sub(9)).find_map(|start| {
+        let date = name.get(start..start + 10)?;
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+    })
+}
+
+/// The name of the log at `path`, without its directory.
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Whether the log `name` is only of requests before `since`. Logs are
+/// named for when they were written, which is after their requests.
+fn skipped(name: &str, since: Option<NaiveDate>) -> bool {
+    let since = match since {
+        Some(since) => since,
+        None => return false,
+    };
+    let skipped = matches!(written(name), Some(written) if written < since);
+    if skipped {
+        debug!("Skipping {}, written before {}", name, since);
+    }
+    skipped
+}
+
+/// The log `name`, decompressed when it's gzipped.
+fn decompress(name: &str, contents: Vec<u8>) -> Result<Vec<u8>, CliError> {
+    if !contents.starts_with(&[0x1f, 0x8b]) {
+        return Ok(contents);
+    }
+    let mut text = Vec::new();
+    MultiGzDecoder::new(contents.as_slice())
+        .read_to_end(&mut text)
+        .map_err(|e| {
+            CliError::IoError(std::io::Error::new(e.kind(), format!("{}: {}", name, e)))
+        })?;
+    Ok(text)
+}
+
+fn parse_log(
+    format: AccessLogFormat,
+    name: &str,
+    contents: Vec<u8>,
+) -> Result<Vec<Request>, CliError> {
+    let contents = decompress(name, contents)?;
+    let text = String::from_utf8_lossy(&contents);
+    Ok(match format {
+        AccessLogFormat::S3 => text.lines().filter_map(parse_s3).collect(),
+        AccessLogFormat::Cloudfront => parse_cloudfront(&text),
+    })
+}
+
+/// A GET of an object in an S3 server access log line.
+fn parse_s3(line: &str) -> Option<Request> {
+    let fields = s3_fields(line);
+    let field = |index: usize| fields.get(index).copied().filter(|value| *value != "-");
+    if field(6)? != "REST.GET.OBJECT" || !matches!(field(9)?, "200" | "206") {
+        return None;
+    }
+    let at = DateTime::parse_from_str(field(2)?, "%d/%b/%Y:%H:%M:%S %z").ok()?;
+    Some(Request {
+        at: at.with_timezone(&Utc),
+        ip: field(3)?.to_owned(),
+        user_agent: field(16).unwrap_or_default().to_owned(),
+        path: field(7)?.to_owned(),
+        bytes_sent: field(11).and_then(|bytes| bytes.parse().ok()).unwrap_or(0),
+    })
+}
+
+/// Fields of an S3 log line: separated by spaces, except in `[...]` and
+/// `"..."`.
+fn s3_fields(line: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut rest = line.trim();
+    while !rest.is_empty() {
+        let (field, after) = match rest.chars().next() {
+            Some(open @ ('[' | '"')) => {
+                let close = if open == '[' { ']' } else { '"' };
+                match rest[1..].find(close) {
+                    Some(end) => (&rest[1..end + 1], &rest[end + 2..]),
+                    None => (&rest[1..], ""),
+                }
+            }
+            _ => match rest.find(' ') {
+                Some(end) => (&rest[..end], &rest[end..]),
+                None => (rest, ""),
+            },
+        };
+        fields.push(field);
+        rest = after.trim_start();
+    }
+    fields
+}
+
+/// GETs in a CloudFront standard log, whose columns its `#Fields` line
+/// names.
+fn parse_cloudfront(text: &str) -> Vec<Request> {
+    let mut columns: Vec<&str> = CLOUDFRONT_FIELDS.to_vec();
+    let mut requests = Vec::new();
+    for line in text.lines() {
+        if let Some(names) = line.strip_prefix("#Fields:") {
+            columns = names.split_whitespace().collect();
+            continue;
+        }
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let values: Vec<&str> = line.split('\t').collect();
+        let field = |name: &str| {
+            let index = columns.iter().position(|column| *column == name)?;
+            values.get(index).copied().filter(|value| *value != "-")
+        };
+        let request = (|| {
+            if field("cs-method")? != "GET" || !matches!(field("sc-status")?, "200" | "206") {
+                return None;
+            }
+            let at = format!("{} {}", field("date")?, field("time")?);
+            let at = NaiveDateTime::parse_from_str(&at, "%Y-%m-%d %H:%M:%S").ok()?;
+            Some(Request {
+                at: Utc.from_utc_datetime(&at),
+                ip: field("c-ip")?.to_owned(),
+                user_agent: field("cs(User-Agent)")
+                    .unwrap_or_default()
+                    .replace("%20", " "),
+                path: field("cs-uri-stem")?.to_owned(),
+                bytes_sent: field("sc-bytes")
+                    .and_then(|bytes| bytes.parse().ok())
+                    .unwrap_or(0),
+            })
+        })();
+        requests.extend(request);
+    }
+    requests
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const S3_LINE: &str = "79a59df900b949e55d96a1e698fbacedfd6e09d98eacf8f8d5218e7cd47ef2be my-show [01/Sep/2022:12:00:00 +0000] 192.0.2.3 - 3E57427F3EXAMPLE REST.GET.OBJECT podcast/artifacts/2022-09-01.mp3 \"GET /podcast/artifacts/2022-09-01.mp3 HTTP/1.1\" 206 - 524288 52428800 70 10 \"-\" \"AppleCoreMedia/1.0.0.19G82 (iPhone; U; CPU OS 15_6 like Mac OS X; en_us)\" - s9lzHYrFp76ZVxRcpX9+5cjAnEH2ROuNkd2BHfIa6Uk= SigV4 ECDHE-RSA-AES128-GCM-SHA256 AuthHeader my-show.s3.amazonaws.com TLSv1.2";
+
+    const CLOUDFRONT_LOG: &str = "#Version: 1.0
+#Fields: date time x-edge-location sc-bytes c-ip cs-method cs(Host) cs-uri-stem sc-status cs(Referer) cs(User-Agent)
+2022-09-02\t07:30:00\tLHR62-C2\t2048\t198.51.100.7\tGET\td111111abcdef8.cloudfront.net\t/podcast/artifacts/2022-09-01.mp3\t200\t-\tOvercast/1.0%20Podcast%20Sync
+2022-09-02\t07:31:00\tLHR62-C2\t512\t198.51.100.7\tHEAD\td111111abcdef8.cloudfront.net\t/podcast/artifacts/2022-09-01.mp3\t200\t-\tOvercast/1.0
+2022-09-02\t07:32:00\tLHR62-C2\t0\t198.51.100.8\tGET\td111111abcdef8.cloudfront.net\t/podcast/artifacts/2022-09-01.mp3\t404\t-\tOvercast/1.0
+";
+
+    fn request(ip: &str, user_agent: &str, at: &str, bytes_sent: u64) -> Request {
+        Request {
+            at: at.parse().unwrap(),
+            ip: ip.to_owned(),
+            user_agent: user_agent.to_owned(),
+            path: "/podcast/artifacts/2022-09-01.mp3".to_owned(),
+            bytes_sent,
+        }
+    }
+
+    fn tally(requests: Vec<Request>) -> Tally {
+        let episodes = HashMap::from([("2022-09-01.mp3", 0)]);
+        let options = StatsOptions {
+            logs: None,
+            since: None,
+            until: None,
+            by: Period::Day,
+            geography: None,
+        };
+        let mut tally = Tally::default();
+        for request in requests {
+            tally.add(request, &episodes, &options);
+        }
+        tally
+    }
+
+    #[test]
+    fn parses_s3_gets() {
+        let request = parse_s3(S3_LINE).unwrap();
+        assert_eq!(
+            request.at,
+            "2022-09-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(request.ip, "192.0.2.3");
+        assert_eq!(request.path, "podcast/artifacts/2022-09-01.mp3");
+        assert_eq!(request.bytes_sent, 524288);
+        assert!(request
+            .user_agent
+            .starts_with("AppleCoreMedia/1.0.0.19G82 (iPhone;"));
+    }
+
+    #[test]
+    fn skips_s3_lines_that_arent_served_gets() {
+        let head = S3_LINE.replace("REST.GET.OBJECT", "REST.HEAD.OBJECT");
+        let denied = S3_LINE.replace("HTTP/1.1\" 206", "HTTP/1.1\" 403");
+        assert!(parse_s3(&head).is_none());
+        assert!(parse_s3(&denied).is_none());
+        assert!(parse_s3("not a log line").is_none());
+    }
+
+    #[test]
+    fn parses_cloudfront_gets_by_their_fields_line() {
+        let requests = parse_cloudfront(CLOUDFRONT_LOG);
+        assert_eq!(requests.len(), 1, "{:?}", requests);
+        let request = &requests[0];
+        assert_eq!(
+            request.at,
+            "2022-09-02T07:30:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(request.ip, "198.51.100.7");
+        assert_eq!(request.user_agent, "Overcast/1.0 Podcast Sync");
+        assert_eq!(request.path, "/podcast/artifacts/2022-09-01.mp3");
+        assert_eq!(request.bytes_sent, 2048);
+    }
+
+    #[test]
+    fn reads_cloudfront_columns_in_any_order() {
+        let log = "#Fields: c-ip cs-method sc-status date time cs-uri-stem sc-bytes\n\
+                   203.0.113.9\tGET\t206\t2022-09-03\t01:02:03\t/a.mp3\t4096\n";
+        let requests = parse_cloudfront(log);
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].ip, "203.0.113.9");
+        assert_eq!(requests[0].user_agent, "");
+        assert_eq!(requests[0].bytes_sent, 4096);
+    }
+
+    #[test]
+    fn counts_ranged_requests_once_per_listener_and_day() {
+        let app = "AppleCoreMedia/1.0";
+        let tally = tally(vec![
+            request("192.0.2.3", app, "2022-09-01T12:00:00Z", 600),
+            request("192.0.2.3", app, "2022-09-01T12:00:05Z", 600),
+            request("192.0.2.3", app, "2022-09-02T08:00:00Z", 50_000),
+        ]);
+        let downloads = tally.downloads(Period::Day);
+        let day = |day| NaiveDate::from_ymd_opt(2022, 9, day).unwrap();
+        assert_eq!(downloads.get(&(day(1), 0)), Some(&1));
+        assert_eq!(downloads.get(&(day(2), 0)), Some(&1));
+        assert_eq!(tally.downloads(Period::Week).values().sum::<u64>(), 2);
+    }
+
+    #[test]
+    fn leaves_out_bots_and_probes() {
+        let tally = tally(vec![
+            request(
+                "192.0.2.3",
+                "Mozilla/5.0 (compatible; Googlebot/2.1)",
+                "2022-09-01T12:00:00Z",
+                50_000,
+            ),
+            request("192.0.2.4", "curl/8.1.2", "2022-09-01T12:00:00Z", 50_000),
+            request("192.0.2.5", "AppleCoreMedia/1.0", "2022-09-01T12:00:00Z", 2),
+        ]);
+        assert_eq!(tally.requests, 3);
+        assert_eq!(tally.bots, 2);
+        assert!(tally.downloads(Period::Day).is_empty());
+    }
+
+    #[test]
+    fn ignores_requests_for_other_files() {
+        let mut other = request(
+            "192.0.2.3",
+            "AppleCoreMedia/1.0",
+            "2022-09-01T12:00:00Z",
+            50_000,
+        );
+        other.path = "/podcast/podcast.xml".to_owned();
+        let tally = tally(vec![other]);
+        assert_eq!(tally.requests, 0);
+    }
+
+    #[test]
+    fn anonymizes_addresses() {
+        let truncate = Anonymizer {
+            anonymize: Some(Anonymize::Truncate),
+            salt: String::new(),
+        };
+        assert_eq!(truncate.address("192.0.2.3"), "192.0.2.0");
+        assert_eq!(truncate.address("2001:db8:1:2:3::4"), "2001:db8:1::");
+        assert_eq!(truncate.address("unknown"), "unknown");
+
+        let hash = Anonymizer {
+            anonymize: Some(Anonymize::Hash),
+            salt: "pepper".to_owned(),
+        };
+        assert_eq!(
+            hash.address("192.0.2.3"),
+            "21ff1f9ba7c455804f8f9a22b938df5a46d424f04370218abc55361229a7a6ca"
+        );
+        assert_eq!(
+            anonymize_s3(S3_LINE, &truncate),
+            S3_LINE.replace("192.0.2.3", "192.0.2.0")
+        );
+    }
+}
@@ -0,0 +1,1020 @@
+//! Where published files are stored, picked with `publishing.type`. S3 and
+//! S3 compatible providers are the default; the rest are:
+//!
+//! ```yaml
+//! publishing:
+//!   type: local            # a directory a web server serves
+//!   prefix: podcast
+//!   local:
+//!     path: /var/www/podcast
+//!     baseUrl: https://example.com/podcast
+//! ```
+//!
+//! `sftp` uploads with the `sftp` command, so keys and hosts come from
+//! `~/.ssh/config`, and takes `host`, `path`, and `baseUrl` under `sftp`.
+//! It lists with `ls -ln`, a session per directory.
+//! `gcs` uploads to `bucket` with a token from `GOOGLE_OAUTH_ACCESS_TOKEN`
+//! or `gcloud auth print-access-token`. `azure` uploads to the `bucket`
+//! container of `azure.account` with a SAS token from
+//! `AZURE_STORAGE_SAS_TOKEN`. Files have to be public to be listened to:
+//! for GCS and Azure that is set on the bucket or container. Both upload
+//! in chunks, GCS as a resumable upload and Azure as blocks, so a failed
+//! chunk is all that is sent again.
+
+use crate::config::{PublishingConfig, RetryConfig, SftpStorageConfig, StorageType};
+use crate::output::Progress;
+use crate::upload::{retry, storage_url, ObjectOptions, S3Storage};
+use crate::CliError;
+use bytes::Bytes;
+use futures::future::LocalBoxFuture;
+use futures::FutureExt;
+use log::info;
+use quick_xml::events::Event;
+use quick_xml::reader::Reader;
+use reqwest::header::{
+    HeaderMap, HeaderValue, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+    LOCATION, RANGE,
+};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::fs;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncWriteExt};
+use tokio::process::Command;
+
+/// Read in chunks this large when copying, to show progress.
+const CHUNK_SIZE: usize = 1024 * 1024;
+/// GCS and Azure objects are sent in chunks this large. GCS needs a
+/// multiple of 256 KiB.
+const UPLOAD_CHUNK_SIZE: usize = 8 * 1024 * 1024;
+const AZURE_VERSION: &str = "2021-08-06";
+
+/// What is being stored.
+pub struct Object<'a> {
+    pub key: &'a str,
+    pub size: u64,
+    pub content_type: &'a str,
+    pub options: &'a ObjectOptions,
+}
+
+/// What an object is read from: a file or a buffer, which an upload can
+/// seek back to the start of to send it again.
+pub trait ObjectSource: AsyncRead + AsyncSeek + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncSeek + Send + Unpin> ObjectSource for T {}
+
+pub type ObjectReader = Box<dyn ObjectSource>;
+
+/// An object already in storage.
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub key: String,
+    pub size: u64,
+}
+
+/// Somewhere files can be published to.
+pub trait StorageBackend {
+    /// Store `object` with the contents of `read`, returning the ETag when
+    /// the backend has one.
+    fn put<'a>(
+        &'a self,
+        object: Object<'a>,
+        read: ObjectReader,
+        progress: &'a mut Progress,
+    ) -> LocalBoxFuture<'a, Result<Option<String>, CliError>>;
+
+    /// Every object whose key starts with `prefix`.
+    fn list<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Vec<StoredObject>, CliError>>;
+
+    fn delete<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Result<(), CliError>>;
+}
+
+/// The backend `publishing.type` picks.
+pub async fn backend(
+    publishing: &PublishingConfig,
+) -> Result<Box<dyn StorageBackend + '_>, CliError> {
+    check(publishing)?;
+    let backend: Box<dyn StorageBackend + '_> = match publishing.storage {
+        StorageType::S3 => Box::new(S3Storage::new(publishing).await),
+        StorageType::Local => Box::new(LocalStorage { publishing }),
+        StorageType::Sftp => Box::new(SftpStorage { publishing }),
+        StorageType::Gcs => Box::new(GcsStorage {
+            publishing,
+            client: http_client(&publishing.retry)?,
+            token: gcs_token().await?,
+        }),
+        StorageType::Azure => Box::new(AzureStorage {
+            publishing,
+            client: http_client(&publishing.retry)?,
+            sas: std::env::var("AZURE_STORAGE_SAS_TOKEN")
+                .map_err(|_| CliError::MissingEnvVar("AZURE_STORAGE_SAS_TOKEN".to_owned()))?,
+        }),
+    };
+    Ok(backend)
+}
+
+/// Make sure the chosen backend has the settings it needs.
+pub fn check(publishing: &PublishingConfig) -> Result<(), CliError> {
+    let missing = |what: &str| {
+        Err(CliError::StorageConfig(format!(
+            "`type: {}` needs {}",
+            publishing.storage.as_str(),
+            what
+        )))
+    };
+    match publishing.storage {
+        StorageType::S3 if publishing.region.endpoint.is_empty() => missing("`region.endpoint`"),
+        StorageType::S3 | StorageType::Gcs if publishing.bucket.is_empty() => missing("`bucket`"),
+        StorageType::Local if publishing.local.is_none() => missing("a `local` section"),
+        StorageType::Sftp if publishing.sftp.is_none() => missing("an `sftp` section"),
+        StorageType::Azure if publishing.azure.is_none() => missing("an `azure` section"),
+        StorageType::Azure if publishing.bucket.is_empty() => {
+            missing("the container name in `bucket`")
+        }
+        StorageType::Local | StorageType::Sftp if publishing.gzip_feeds => {
+            Err(CliError::StorageConfig(format!(
+                "`gzipFeeds` needs storage that keeps `Content-Encoding`, not `type: {}`",
+                publishing.storage.as_str()
+            )))
+        }
+        StorageType::S3 => crate::upload::check_object_options(&publishing.object_options),
+        _ => Ok(()),
+    }
+}
+
+struct LocalStorage<'a> {
+    publishing: &'a PublishingConfig,
+}
+
+impl StorageBackend for LocalStorage<'_> {
+    fn put<'a>(
+        &'a self,
+        object: Object<'a>,
+        mut read: ObjectReader,
+        progress: &'a mut Progress,
+    ) -> LocalBoxFuture<'a, Result<Option<String>, CliError>> {
+        async move {
+            let path = self.root()?.join(object.key);
+            // Written next to its destination and renamed, so the web
+            // server never serves half a file
+            let partial = PathBuf::from(format!("{}.partial", path.display()));
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            copy(&mut read, &partial, progress).await?;
+            fs::rename(&partial, &path).await?;
+            info!("Copied {} to {}", object.key, path.display());
+            Ok(None)
+        }
+        .boxed_local()
+    }
+
+    fn list<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Vec<StoredObject>, CliError>> {
+        async move {
+            let root = self.root()?;
+            // Only the directory the prefix is in has to be walked
+            let start = match prefix.rfind('/') {
+                Some(end) => root.join(&prefix[..end]),
+                None => root.to_path_buf(),
+            };
+            let mut objects = Vec::new();
+            let mut dirs = vec![start];
+            while let Some(dir) = dirs.pop() {
+                let mut entries = match fs::read_dir(&dir).await {
+                    Ok(entries) => entries,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                while let Some(entry) = entries.next_entry().await? {
+                    let path = entry.path();
+                    let metadata = entry.metadata().await?;
+                    if metadata.is_dir() {
+                        dirs.push(path);
+                        continue;
+                    }
+                    let key = path
+                        .strip_prefix(root)
+                        .unwrap_or(&path)
+                        .components()
+                        .map(|part| part.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    if key.starts_with(prefix) && !key.ends_with(".partial") {
+                        objects.push(StoredObject {
+                            key,
+                            size: metadata.len(),
+                        });
+                    }
+                }
+            }
+            Ok(objects)
+        }
+        .boxed_local()
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Result<(), CliError>> {
+        async move {
+            fs::remove_file(self.root()?.join(key)).await?;
+            Ok(())
+        }
+        .boxed_local()
+    }
+}
+
+impl LocalStorage<'_> {
+    fn root(&self) -> Result<&Path, CliError> {
+        match &self.publishing.local {
+            Some(local) => Ok(&local.path),
+            None => Err(CliError::StorageConfig("no `local` section".to_owned())),
+        }
+    }
+}
+
+struct SftpStorage<'a> {
+    publishing: &'a PublishingConfig,
+}
+
+impl StorageBackend for SftpStorage<'_> {
+    fn put<'a>(
+        &'a self,
+        object: Object<'a>,
+        mut read: ObjectReader,
+        progress: &'a mut Progress,
+    ) -> LocalBoxFuture<'a, Result<Option<String>, CliError>> {
+        async move {
+            let sftp = self.config()?;
+            // Made with a random name and only readable by us, so nobody
+            // else on the machine can swap in what is uploaded. It is
+            // removed when dropped
+            let local = tempfile::Builder::new().prefix("podcast-ctl-").tempfile()?;
+            let remote = sftp_remote(sftp, object.key);
+            let batch = sftp_put_batch(local.path(), &remote)?;
+            copy(&mut read, local.path(), progress).await?;
+
+            retry(&self.publishing.retry, object.key, || {
+                sftp_batch(&sftp.host, sftp.port, &batch)
+            })
+            .await
+            .map(|_| None)
+        }
+        .boxed_local()
+    }
+
+    fn list<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Vec<StoredObject>, CliError>> {
+        async move {
+            let sftp = self.config()?;
+            // Only the directory the prefix is in, and those under it that
+            // could hold its keys, have to be listed
+            let start = match prefix.rfind('/') {
+                Some(end) => prefix[..end].to_owned(),
+                None => String::new(),
+            };
+            let mut objects = Vec::new();
+            let mut dirs = vec![start];
+            while let Some(dir) = dirs.pop() {
+                // A `-` so a directory that isn't there lists as empty
+                let remote = sftp_quote(&sftp_remote(sftp, &dir))?;
+                let batch = format!("-ls -ln {}\n", remote);
+                let listing = retry(&self.publishing.retry, &dir, || {
+                    sftp_batch(&sftp.host, sftp.port, &batch)
+                })
+                .await?;
+                for (kind, size, name) in listing.lines().filter_map(sftp_entry) {
+                    let key = match dir.as_str() {
+                        "" => name.to_owned(),
+                        dir => format!("{}/{}", dir, name),
+                    };
+                    match kind {
+                        'd' if key.starts_with(prefix) || prefix.starts_with(&key) => {
+                            dirs.push(key)
+                        }
+                        '-' if key.starts_with(prefix) => objects.push(StoredObject { key, size }),
+                        _ => {}
+                    }
+                }
+            }
+            Ok(objects)
+        }
+        .boxed_local()
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Result<(), CliError>> {
+        async move {
+            let sftp = self.config()?;
+            let batch = format!("rm {}\n", sftp_quote(&sftp_remote(sftp, key))?);
+            retry(&self.publishing.retry, key, || {
+                sftp_batch(&sftp.host, sftp.port, &batch)
+            })
+            .await
+            .map(|_| ())
+        }
+        .boxed_local()
+    }
+}
+
+impl SftpStorage<'_> {
+    fn config(&self) -> Result<&SftpStorageConfig, CliError> {
+        self.publishing
+            .sftp
+            .as_ref()
+            .ok_or_else(|| CliError::StorageConfig("no `sftp` section".to_owned()))
+    }
+}
+
+fn sftp_remote(sftp: &SftpStorageConfig, key: &str) -> String {
+    format!("{}/{}", sftp.path.trim_end_matches('/'), key)
+}
+
+/// `path` quoted for a batch file, with `\` and `"` escaped. Batch files
+/// are read a line at a time, so paths with line breaks are refused.
+fn sftp_quote(path: &str) -> Result<String, CliError> {
+    if path.contains(['\n', '\r']) {
+        return Err(CliError::StorageRejected(format!(
+            "sftp can't name {:?}, which has a line break",
+            path
+        )));
+    }
+    let escaped = path.replace('\\', "\\\\").replace('"', "\\\"");
+    Ok(format!("\"{}\"", escaped))
+}
+
+/// Commands that upload `local` to `remote`, creating the directories
+/// above it. A `-` lets a command fail without ending the batch, for
+/// directories that already exist.
+fn sftp_put_batch(local: &Path, remote: &str) -> Result<String, CliError> {
+    let mut batch = String::new();
+    let mut directory = String::new();
+    let parents: Vec<&str> = remote.split('/').collect();
+    for part in &parents[..parents.len() - 1] {
+        directory.push_str(part);
+        if !directory.is_empty() {
+            batch.push_str(&format!("-mkdir {}\n", sftp_quote(&directory)?));
+        }
+        directory.push('/');
+    }
+    let local = sftp_quote(&local.display().to_string())?;
+    batch.push_str(&format!("put {} {}\n", local, sftp_quote(remote)?));
+    Ok(batch)
+}
+
+/// The type, size, and name in a line of `ls -ln`, like
+/// `-rw-r--r--    1 1000     1000       5120 Jan  1 12:00 episode.mp3`.
+/// The name may come with the directory listed in front of it.
+fn sftp_entry(line: &str) -> Option<(char, u64, &str)> {
+    // Batch mode echoes each command
+    if line.starts_with("sftp>") {
+        return None;
+    }
+    let mut rest = line.trim_start();
+    let mut fields = Vec::new();
+    for _ in 0..8 {
+        let end = rest.find(char::is_whitespace)?;
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    let kind = fields[0].chars().next()?;
+    let size = fields[4].parse().ok()?;
+    let name = rest.rsplit('/').next()?;
+    match name {
+        "" | "." | ".." => None,
+        name => Some((kind, size, name)),
+    }
+}
+
+/// Run `batch` in one `sftp` session, returning what it printed.
+async fn sftp_batch(host: &str, port: Option<u16>, batch: &str) -> Result<String, CliError> {
+    let mut command = Command::new("sftp");
+    command.arg("-b").arg("-");
+    if let Some(port) = port {
+        command.arg("-P").arg(port.to_string());
+    }
+    let mut child = command
+        .arg(host)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(batch.as_bytes()).await?;
+    }
+    let output = child.wait_with_output().await?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+
+    // ssh exits with 255 when it can't connect, which is worth retrying
+    let detail = format!(
+        "sftp to {} failed: {}",
+        host,
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    if output.status.code() == Some(255) {
+        Err(CliError::StorageUnavailable(detail))
+    } else {
+        Err(CliError::StorageRejected(detail))
+    }
+}
+
+struct GcsStorage<'a> {
+    publishing: &'a PublishingConfig,
+    client: reqwest::Client,
+    token: String,
+}
+
+impl StorageBackend for GcsStorage<'_> {
+    fn put<'a>(
+        &'a self,
+        object: Object<'a>,
+        mut read: ObjectReader,
+        progress: &'a mut Progress,
+    ) -> LocalBoxFuture<'a, Result<Option<String>, CliError>> {
+        async move {
+            let url = storage_url(self.publishing, object.key);
+            let mut headers =
+                object_headers(&object, CONTENT_TYPE, CACHE_CONTROL, CONTENT_ENCODING)?;
+            headers.insert("x-goog-resumable", HeaderValue::from_static("start"));
+            let (started, _) = http_send(&self.publishing.retry, &url, || {
+                self.client
+                    .post(&url)
+                    .bearer_auth(&self.token)
+                    .headers(headers.clone())
+                    .body(Bytes::new())
+            })
+            .await?;
+            let session = started
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .ok_or_else(|| {
+                    CliError::StorageRejected(format!("GCS didn't start an upload of {}", url))
+                })?
+                .to_owned();
+
+            let mut buffer = vec![0; UPLOAD_CHUNK_SIZE];
+            let mut sent = 0;
+            loop {
+                let length = read_chunk(&mut read, &mut buffer).await?;
+                let end = sent + length as u64;
+                if length == 0 && end < object.size {
+                    return Err(ended_early(&object, sent));
+                }
+                let chunk = Bytes::copy_from_slice(&buffer[..length]);
+                if let Some(etag) = self.put_chunk(&session, chunk, sent, object.size).await? {
+                    progress.add(length as u64);
+                    return Ok(etag);
+                }
+                progress.add(length as u64);
+                sent = end;
+            }
+        }
+        .boxed_local()
+    }
+
+    fn list<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Vec<StoredObject>, CliError>> {
+        async move {
+            let mut objects = Vec::new();
+            let mut page_token: Option<String> = None;
+            loop {
+                let mut url = self.api_url(&[])?;
+                url.query_pairs_mut()
+                    .append_pair("prefix", prefix)
+                    .append_pair("fields", "items(name,size),nextPageToken");
+                if let Some(page_token) = &page_token {
+                    url.query_pairs_mut().append_pair("pageToken", page_token);
+                }
+                let body = http_send(&self.publishing.retry, url.as_str(), || {
+                    self.client.get(url.clone()).bearer_auth(&self.token)
+                })
+                .await?
+                .1;
+                let page: GcsPage = serde_json::from_slice(&body)?;
+                objects.extend(page.items.into_iter().map(|item| StoredObject {
+                    key: item.name,
+                    size: item.size.parse().unwrap_or_default(),
+                }));
+                match page.next_page_token {
+                    Some(next) => page_token = Some(next),
+                    None => break,
+                }
+            }
+            Ok(objects)
+        }
+        .boxed_local()
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Result<(), CliError>> {
+        async move {
+            let url = self.api_url(&[key])?;
+            http_send(&self.publishing.retry, url.as_str(), || {
+                self.client.delete(url.clone()).bearer_auth(&self.token)
+            })
+            .await?;
+            Ok(())
+        }
+        .boxed_local()
+    }
+}
+
+impl GcsStorage<'_> {
+    /// Send `chunk`, which starts `offset` bytes into an object of `size`,
+    /// to the resumable upload `session`, sending again whatever GCS
+    /// didn't keep. Returns the object's ETag once it is all uploaded.
+    async fn put_chunk(
+        &self,
+        session: &str,
+        chunk: Bytes,
+        offset: u64,
+        size: u64,
+    ) -> Result<Option<Option<String>>, CliError> {
+        let end = offset + chunk.len() as u64;
+        let mut kept = offset;
+        loop {
+            let range = if size == 0 {
+                "bytes */0".to_owned()
+            } else {
+                format!("bytes {}-{}/{}", kept, end - 1, size)
+            };
+            let rest = chunk.slice((kept - offset) as usize..);
+            let (status, headers, _) = http_send_accepting(
+                &self.publishing.retry,
+                session,
+                |status| status == StatusCode::PERMANENT_REDIRECT,
+                || {
+                    self.client
+                        .put(session)
+                        .header(CONTENT_RANGE, &range)
+                        .body(rest.clone())
+                },
+            )
+            .await?;
+            if status.is_success() {
+                return Ok(Some(etag(&headers)));
+            }
+
+            // 308 means GCS wants more, and its Range says how much it kept
+            let next = headers
+                .get(RANGE)
+                .and_then(|range| range.to_str().ok())
+                .and_then(|range| range.rsplit_once('-'))
+                .and_then(|(_, last)| last.parse::<u64>().ok())
+                .map_or(0, |last| last + 1);
+            if next >= end {
+                return Ok(None);
+            }
+            if next <= kept {
+                return Err(CliError::StorageRejected(format!(
+                    "GCS kept nothing of the upload after byte {}",
+                    kept
+                )));
+            }
+            kept = next;
+        }
+    }
+
+    /// The JSON API URL of the bucket's objects, or of one object.
+    fn api_url(&self, object: &[&str]) -> Result<reqwest::Url, CliError> {
+        let mut url = reqwest::Url::parse("https://storage.googleapis.com/storage/v1/b")
+            .map_err(|e| CliError::StorageConfig(e.to_string()))?;
+        url.path_segments_mut()
+            .map_err(|_| CliError::StorageConfig("invalid GCS URL".to_owned()))?
+            .push(&self.publishing.bucket)
+            .push("o")
+            .extend(object);
+        Ok(url)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GcsPage {
+    #[serde(default)]
+    items: Vec<GcsItem>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GcsItem {
+    name: String,
+    /// Sent as a string, since it may not fit in a JSON number
+    size: String,
+}
+
+/// A token from the environment, or from the signed in `gcloud`.
+async fn gcs_token() -> Result<String, CliError> {
+    if let Ok(token) = std::env::var("GOOGLE_OAUTH_ACCESS_TOKEN") {
+        return Ok(token);
+    }
+    let output = Command::new("gcloud")
+        .args(["auth", "print-access-token"])
+        .stderr(Stdio::inherit())
+        .output()
+        .await
+        .map_err(|_| CliError::MissingEnvVar("GOOGLE_OAUTH_ACCESS_TOKEN".to_owned()))?;
+    if !output.status.success() {
+        return Err(CliError::MissingEnvVar(
+            "GOOGLE_OAUTH_ACCESS_TOKEN".to_owned(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+struct AzureStorage<'a> {
+    publishing: &'a PublishingConfig,
+    client: reqwest::Client,
+    sas: String,
+}
+
+impl StorageBackend for AzureStorage<'_> {
+    fn put<'a>(
+        &'a self,
+        object: Object<'a>,
+        mut read: ObjectReader,
+        progress: &'a mut Progress,
+    ) -> LocalBoxFuture<'a, Result<Option<String>, CliError>> {
+        async move {
+            let url = storage_url(self.publishing, object.key);
+            let sas = self.sas.trim_start_matches('?');
+
+            // Each chunk is a block, put together with a block list once
+            // they are all uploaded
+            let mut blocks = Vec::new();
+            let mut buffer = vec![0; UPLOAD_CHUNK_SIZE];
+            let mut sent = 0;
+            loop {
+                let length = read_chunk(&mut read, &mut buffer).await?;
+                if length == 0 {
+                    break;
+                }
+                // Block ids are base64, all the same length: eight digits
+                // already are
+                let id = format!("{:08}", blocks.len());
+                let block_url = format!("{}?comp=block&blockid={}&{}", url, id, sas);
+                let chunk = Bytes::copy_from_slice(&buffer[..length]);
+                http_send(&self.publishing.retry, &block_url, || {
+                    self.client
+                        .put(&block_url)
+                        .header("x-ms-version", AZURE_VERSION)
+                        .body(chunk.clone())
+                })
+                .await?;
+                progress.add(length as u64);
+                sent += length as u64;
+                blocks.push(id);
+            }
+            if sent < object.size {
+                return Err(ended_early(&object, sent));
+            }
+
+            let list = blocks.iter().fold(String::new(), |mut list, id| {
+                let _ = write!(list, "<Latest>{}</Latest>", id);
+                list
+            });
+            let list = format!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?><BlockList>{}</BlockList>",
+                list
+            );
+            let mut headers = object_headers(
+                &object,
+                "x-ms-blob-content-type",
+                "x-ms-blob-cache-control",
+                "x-ms-blob-content-encoding",
+            )?;
+            headers.insert("x-ms-version", HeaderValue::from_static(AZURE_VERSION));
+            let list_url = format!("{}?comp=blocklist&{}", url, sas);
+            http_put(
+                &self.client,
+                &self.publishing.retry,
+                &list_url,
+                headers,
+                Bytes::from(list),
+            )
+            .await
+        }
+        .boxed_local()
+    }
+
+    fn list<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Vec<StoredObject>, CliError>> {
+        async move {
+            let account = match &self.publishing.azure {
+                Some(azure) => &azure.account,
+                None => return Err(CliError::StorageConfig("no `azure` section".to_owned())),
+            };
+            let container = format!(
+                "https://{}.blob.core.windows.net/{}?{}",
+                account,
+                self.publishing.bucket,
+                self.sas.trim_start_matches('?')
+            );
+            let mut objects = Vec::new();
+            let mut marker: Option<String> = None;
+            loop {
+                let mut url = reqwest::Url::parse(&container)
+                    .map_err(|e| CliError::StorageConfig(e.to_string()))?;
+                url.query_pairs_mut()
+                    .append_pair("restype", "container")
+                    .append_pair("comp", "list")
+                    .append_pair("prefix", prefix);
+                if let Some(marker) = &marker {
+                    url.query_pairs_mut().append_pair("marker", marker);
+                }
+                let body = http_send(&self.publishing.retry, url.as_str(), || {
+                    self.client
+                        .get(url.clone())
+                        .header("x-ms-version", AZURE_VERSION)
+                })
+                .await?
+                .1;
+                let (page, next) = parse_blob_list(&String::from_utf8_lossy(&body))?;
+                objects.extend(page);
+                match next {
+                    Some(next) => marker = Some(next),
+                    None => break,
+                }
+            }
+            Ok(objects)
+        }
+        .boxed_local()
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Result<(), CliError>> {
+        async move {
+            let url = format!(
+                "{}?{}",
+                storage_url(self.publishing, key),
+                self.sas.trim_start_matches('?')
+            );
+            http_send(&self.publishing.retry, &url, || {
+                self.client
+                    .delete(&url)
+                    .header("x-ms-version", AZURE_VERSION)
+            })
+            .await?;
+            Ok(())
+        }
+        .boxed_local()
+    }
+}
+
+/// The blobs in a List Blobs response, and the marker of the next page.
+fn parse_blob_list(xml: &str) -> Result<(Vec<StoredObject>, Option<String>), CliError> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut objects = Vec::new();
+    let mut next_marker = None;
+    let mut element = String::new();
+    let mut name = None;
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => {
+                element = String::from_utf8_lossy(start.name().as_ref()).into_owned();
+            }
+            Event::Text(text) => {
+                let text = text.unescape()?.into_owned();
+                match element.as_str() {
+                    "Name" => name = Some(text),
+                    "Content-Length" => {
+                        if let Some(key) = name.take() {
+                            objects.push(StoredObject {
+                                key,
+                                size: text.parse().unwrap_or_default(),
+                            });
+                        }
+                    }
+                    "NextMarker" if !text.is_empty() => next_marker = Some(text),
+                    _ => {}
+                }
+            }
+            Event::End(_) => element.clear(),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok((objects, next_marker))
+}
+
+fn http_client(config: &RetryConfig) -> Result<reqwest::Client, CliError> {
+    Ok(reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout_seconds))
+        .build()?)
+}
+
+fn object_headers<K>(
+    object: &Object<'_>,
+    content_type: K,
+    cache_control: K,
+    content_encoding: K,
+) -> Result<HeaderMap, CliError>
+where
+    K: reqwest::header::IntoHeaderName,
+{
+    let mut headers = HeaderMap::new();
+    headers.insert(content_type, header_value(object.content_type)?);
+    if let Some(value) = &object.options.cache_control {
+        headers.insert(cache_control, header_value(value)?);
+    }
+    if let Some(value) = &object.options.content_encoding {
+        headers.insert(content_encoding, header_value(value)?);
+    }
+    Ok(headers)
+}
+
+fn header_value(value: &str) -> Result<HeaderValue, CliError> {
+    HeaderValue::from_str(value)
+        .map_err(|_| CliError::StorageConfig(format!("'{}' can't be sent as a header", value)))
+}
+
+/// PUT `contents` and return the ETag.
+async fn http_put(
+    client: &reqwest::Client,
+    config: &RetryConfig,
+    url: &str,
+    headers: HeaderMap,
+    contents: Bytes,
+) -> Result<Option<String>, CliError> {
+    let (headers, _) = http_send(config, url, || {
+        client
+            .put(url)
+            .headers(headers.clone())
+            .body(contents.clone())
+    })
+    .await?;
+    Ok(etag(&headers))
+}
+
+fn etag(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(str::to_owned)
+}
+
+/// Send the request `build` makes, retrying throttling, server errors, and
+/// connections that fail, and return the response headers and body.
+async fn http_send<F>(
+    config: &RetryConfig,
+    url: &str,
+    build: F,
+) -> Result<(HeaderMap, Bytes), CliError>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let (_, headers, body) = http_send_accepting(config, url, |_| false, build).await?;
+    Ok((headers, body))
+}
+
+/// [`http_send`], also taking the statuses `also` accepts as answers, and
+/// returning the status too.
+async fn http_send_accepting<F>(
+    config: &RetryConfig,
+    url: &str,
+    also: fn(StatusCode) -> bool,
+    build: F,
+) -> Result<(StatusCode, HeaderMap, Bytes), CliError>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    // The URL may hold a token, so it isn't logged
+    let host = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_owned))
+        .unwrap_or_default();
+    retry(config, &host, || {
+        let request = build();
+        let host = host.clone();
+        async move {
+            let response = request
+                .send()
+                .await
+                .map_err(|e| CliError::StorageUnavailable(format!("{}: {}", host, e)))?;
+            let status = response.status();
+            if status.is_success() || also(status) {
+                let headers = response.headers().clone();
+                let body = response
+                    .bytes()
+                    .await
+                    .map_err(|e| CliError::StorageUnavailable(format!("{}: {}", host, e)))?;
+                return Ok((status, headers, body));
+            }
+            let detail = format!(
+                "{} returned HTTP {}: {}",
+                host,
+                status.as_u16(),
+                response.text().await.unwrap_or_default().trim()
+            );
+            if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+                Err(CliError::StorageUnavailable(detail))
+            } else {
+                Err(CliError::StorageRejected(detail))
+            }
+        }
+    })
+    .await
+}
+
+/// Fill `buffer` from `read`, stopping short only at the end.
+async fn read_chunk(read: &mut ObjectReader, buffer: &mut [u8]) -> Result<usize, CliError> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        let read = read.read(&mut buffer[filled..]).await?;
+        if read == 0 {
+            break;
+        }
+        filled += read;
+    }
+    Ok(filled)
+}
+
+fn ended_early(object: &Object<'_>, sent: u64) -> CliError {
+    CliError::StorageRejected(format!(
+        "{} ended after {} of {} bytes",
+        object.key, sent, object.size
+    ))
+}
+
+async fn copy(
+    read: &mut ObjectReader,
+    path: &Path,
+    progress: &mut Progress,
+) -> Result<(), CliError> {
+    let mut file = fs::File::create(path).await?;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let read = read.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        file.write_all(&buffer[..read]).await?;
+        progress.add(read as u64);
+    }
+    file.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_paths_for_batch_files() {
+        assert_eq!(sftp_quote("/srv/show/a b.mp3").unwrap(), r#""/srv/show/a b.mp3""#);
+        assert_eq!(
+            sftp_quote(r#"/srv/"quoted" \ name"#).unwrap(),
+            r#""/srv/\"quoted\" \\ name""#
+        );
+        assert!(sftp_quote("/srv/show/a\nrm -rf /").is_err());
+        assert!(sftp_quote("/srv/show/a\r.mp3").is_err());
+    }
+
+    #[test]
+    fn put_batches_make_the_directories_first() {
+        let batch = sftp_put_batch(Path::new("/tmp/upload"), "/srv/show/audio/ep 1.mp3").unwrap();
+        assert_eq!(
+            batch,
+            concat!(
+                "-mkdir \"/srv\"\n",
+                "-mkdir \"/srv/show\"\n",
+                "-mkdir \"/srv/show/audio\"\n",
+                "put \"/tmp/upload\" \"/srv/show/audio/ep 1.mp3\"\n",
+            )
+        );
+    }
+
+    #[test]
+    fn reads_ls_entries() {
+        let file = "-rw-r--r--    1 1000     1000       5120 Jan  1 12:00 episode.mp3";
+        assert_eq!(sftp_entry(file), Some(('-', 5120, "episode.mp3")));
+        let dir = "drwxr-xr-x    2 1000     1000       4096 Jan  1 12:00 /srv/show/audio";
+        assert_eq!(sftp_entry(dir), Some(('d', 4096, "audio")));
+        let spaced = "-rw-r--r--    1 1000     1000         12 Sep  1  2022 my episode.mp3";
+        assert_eq!(sftp_entry(spaced), Some(('-', 12, "my episode.mp3")));
+    }
+
+    #[test]
+    fn skips_other_ls_output() {
+        assert_eq!(sftp_entry("sftp> -ls -ln \"/srv/show\""), None);
+        assert_eq!(sftp_entry(""), None);
+        assert_eq!(sftp_entry("total 8"), None);
+        let dot = "drwxr-xr-x    2 1000     1000       4096 Jan  1 12:00 .";
+        assert_eq!(sftp_entry(dot), None);
+        let no_size = "-rw-r--r--    1 1000     1000       many Jan  1 12:00 episode.mp3";
+        assert_eq!(sftp_entry(no_size), None);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::config::*;
+use crate::http::HttpClient;
+use crate::i18n::t;
+use crate::output::outln;
+use crate::CliError;
+use log::{debug, info};
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::json;
+use std::path::Path;
+use std::time::Duration;
+
+const DEFAULT_PROMPT: &str = "You write show notes for the podcast \"{title}\". \
+Read the episode transcript and reply with only a JSON object with the keys \
+\"summary\" (one or two plain sentences), \"description\" (two or three Markdown \
+paragraphs), and \"chapters\" (an array of objects with \"start\" as MM:SS or \
+HH:MM:SS and \"title\").";
+
+#[derive(Debug, Deserialize)]
+struct Draft {
+    summary: String,
+    description: String,
+    #[serde(default)]
+    chapters: Vec<DraftChapter>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DraftChapter {
+    start: String,
+    title: String,
+}
+
+/// Ask the configured language model for a draft summary, description, and
+/// chapter list, and write them into the episode for a human to review.
+pub async fn summarize_episode(
+    config: &SummarizeConfig,
+    channel: &ChannelDetails,
+    episode: &mut Episode,
+    transcript: &str,
+    client: &HttpClient,
+) -> Result<(), CliError> {
+    let transcript: String = transcript.chars().take(config.max_characters).collect();
+    let prompt = config
+        .prompt
+        .as_deref()
+        .unwrap_or(DEFAULT_PROMPT)
+        .replace("{title}", &channel.title);
+
+    let body = json!({
+        "model": config.model,
+        "messages": [
+            { "role": "system", "content": prompt },
+            { "role": "user", "content": format!("Episode: {}\n\nTranscript:\n{}", episode.title, transcript) },
+        ],
+    });
+
+    let api_key = match &config.api_key_env {
+        Some(name) => Some(std::env::var(name).map_err(|_| CliError::MissingEnvVar(name.clone()))?),
+        None => None,
+    };
+
+    info!("Requesting a draft summary from {}", config.endpoint);
+    let response = client
+        .send(&config.endpoint, || {
+            let request = client
+                .request(Method::POST, &config.endpoint)
+                .timeout(Duration::from_secs(config.timeout_seconds))
+                .json(&body);
+            match &api_key {
+                Some(key) => request.bearer_auth(key),
+                None => request,
+            }
+        })
+        .await?;
+    if !response.status.is_success() {
+        return Err(CliError::HttpStatus(
+            config.endpoint.clone(),
+            response.status.as_u16(),
+        ));
+    }
+
+    let completion: serde_json::Value = serde_json::from_slice(&response.body)?;
+    let content = completion["choices"][0]["message"]["content"]
+        .as_str()
+        .ok_or_else(|| CliError::SummarizeError("response has no message content".to_owned()))?;
+    debug!("Model replied with {}", content);
+
+    let draft: Draft = serde_json::from_str(strip_code_fence(content))?;
+
+    episode.summary = draft.summary;
+    episode.description = draft.description;
+    if !draft.chapters.is_empty() {
+        episode.description.push_str("\n\n## Chapters\n\n");
+        for chapter in draft.chapters {
+            episode
+                .description
+                .push_str(&format!("{} {}\n", chapter.start, chapter.title));
+        }
+    }
+
+    Ok(())
+}
+
+/// Models like to wrap JSON in ```json fences even when asked not to.
+fn strip_code_fence(content: &str) -> &str {
+    let trimmed = content.trim();
+    match trimmed.strip_prefix("```") {
+        Some(rest) => rest
+            .trim_start_matches("json")
+            .trim_end_matches("```")
+            .trim(),
+        None => trimmed,
+    }
+}
+
+/// `summarize`: draft the summary, description, and chapters of the
+/// episode `selector` picks from `transcript`, and save them once
+/// confirmed, or with `yes`.
+pub async fn summarize(
+    episode_dir: &Path,
+    channel_config: &ChannelConfig,
+    selector: &str,
+    transcript: &Path,
+    yes: bool,
+) -> Result<(), CliError> {
+    let config = channel_config.summarize.as_ref().ok_or_else(|| {
+        CliError::SummarizeError("channel.yaml has no `summarize` section".to_owned())
+    })?;
+    let (path, mut episode) =
+        crate::find_episode(episode_dir, selector, channel_config.channel.tz()?)?;
+    let transcript = std::fs::read_to_string(transcript)?;
+    let client = HttpClient::new(&channel_config.http)?;
+    summarize_episode(config, &channel_config.channel, &mut episode, &transcript, &client)
+        .await?;
+    if crate::save_episode_confirmed(&path, &episode, channel_config, yes)? {
+        outln!("{}", t!("wrote-draft-notes", path = path.display().to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,137 @@
+//! `sync` publishes only what changed since the last upload from this
+//! workspace. Contents are compared by MD5 with the publish journal; older
+//! entries without one are compared with their ETag, which S3 sets to the
+//! MD5 of objects uploaded in one part.
+
+use crate::config::{Episode, PublishingConfig};
+use crate::i18n::t;
+use crate::output::outln;
+use crate::state::{JournalEntry, Workspace};
+use crate::upload::{self, ObjectOptions};
+use crate::CliError;
+use log::debug;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use tokio::fs::File as TokioFile;
+
+/// What a sync found, object by object.
+pub struct Changes {
+    manifest: BTreeMap<String, JournalEntry>,
+    added: Vec<String>,
+    updated: Vec<String>,
+    unchanged: Vec<String>,
+}
+
+impl Changes {
+    pub fn new(workspace: &Workspace) -> Result<Self, CliError> {
+        Ok(Self {
+            manifest: workspace.manifest()?,
+            added: Vec::new(),
+            updated: Vec::new(),
+            unchanged: Vec::new(),
+        })
+    }
+
+    /// Whether `key` has to be uploaded for storage to hold contents with
+    /// this MD5.
+    pub fn needs_upload(&mut self, key: &str, md5: &str) -> bool {
+        let entry = match self.manifest.get(key) {
+            Some(entry) => entry,
+            None => {
+                self.added.push(key.to_owned());
+                return true;
+            }
+        };
+        let published = entry
+            .md5
+            .as_deref()
+            .or_else(|| entry.etag.as_deref().map(|etag| etag.trim_matches('"')));
+        if published == Some(md5) {
+            self.unchanged.push(key.to_owned());
+            false
+        } else {
+            self.updated.push(key.to_owned());
+            true
+        }
+    }
+
+    pub fn print_summary(&self) {
+        for key in &self.added {
+            outln!("{}", t!("sync-added", key = key.as_str()));
+        }
+        for key in &self.updated {
+            outln!("{}", t!("sync-updated", key = key.as_str()));
+        }
+        outln!(
+            "{}",
+            t!(
+                "sync-summary",
+                added = self.added.len(),
+                updated = self.updated.len(),
+                unchanged = self.unchanged.len()
+            )
+        );
+    }
+}
+
+pub fn md5_hex(contents: &[u8]) -> String {
+    format!("{:x}", md5::compute(contents))
+}
+
+/// Upload the episodes' media that is in `dir`, under the key
+/// `create-episode` gave it in the episode's destination, when storage
+/// doesn't have it as it is.
+pub async fn sync_media(
+    publishing: &PublishingConfig,
+    workspace: &Workspace,
+    dir: &Path,
+    episodes: &[Episode],
+    changes: &mut Changes,
+) -> Result<(), CliError> {
+    let lock = workspace.lock()?;
+    for episode in episodes {
+        // Under the destination the episode is published to, as named when
+        // it was created
+        let publishing = upload::destination(publishing, episode.destination.as_deref())?;
+        let key = upload::media_key(&publishing, episode);
+        let path = dir.join(key.rsplit('/').next().unwrap_or_default());
+        if !path.is_file() {
+            debug!("{} is not in {}, skipping", key, dir.display());
+            continue;
+        }
+
+        if !changes.needs_upload(&key, &md5_file(&path)?) {
+            continue;
+        }
+        let file = TokioFile::open(&path).await?;
+        let size = file.metadata().await?.len();
+        let options = ObjectOptions {
+            resume_dir: Some(workspace.multipart_dir()),
+            content_type: episode.media.mime_type.clone(),
+            ..ObjectOptions::media(&publishing)
+        };
+        let uploaded = upload::upload_object(file, size, &publishing, key.clone(), options).await?;
+        workspace.record_upload(&lock, &key, &uploaded, size)?;
+        if !upload::dry_run() {
+            outln!("{}", t!("uploaded-file", url = uploaded.url.as_str()));
+        }
+    }
+    Ok(())
+}
+
+/// Hash a file without reading all of it into memory.
+pub fn md5_file(path: &Path) -> Result<String, CliError> {
+    let mut file = fs::File::open(path)?;
+    let mut context = md5::Context::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        context.consume(&buffer[..read]);
+    }
+    Ok(format!("{:x}", context.compute()))
+}
@@ -0,0 +1,53 @@
+use crate::config::*;
+use crate::preview::format_bytes;
+
+/// Expand `{variable}` placeholders in an episode's title, summary, and
+/// description from the probed audio facts, so show notes don't need to be
+/// rewritten when the audio changes. Unknown placeholders are left as-is.
+///
+/// - `{duration}`: `HH:MM`
+/// - `{duration_minutes}`: whole minutes
+/// - `{bitrate}`: kbps, when known
+/// - `{recorded_date}`: from the audio file's tags, when known
+/// - `{size}`: file size, e.g. `96.2 MiB`
+pub fn expand_episode(episode: &mut Episode) {
+    let variables = variables(&episode.media);
+    episode.title = expand(&episode.title, &variables);
+    episode.summary = expand(&episode.summary, &variables);
+    episode.description = expand(&episode.description, &variables);
+}
+
+fn variables(media: &EpisodeMedia) -> Vec<(&'static str, String)> {
+    let mut variables = vec![
+        (
+            "duration",
+            format!(
+                "{:02}:{:02}",
+                media.duration.0 / 3600,
+                (media.duration.0 / 60) % 60
+            ),
+        ),
+        ("duration_minutes", (media.duration.0 / 60).to_string()),
+        ("size", format_bytes(media.bytes.0)),
+    ];
+    if let Some(bitrate) = media.bitrate {
+        variables.push(("bitrate", format!("{} kbps", bitrate)));
+    }
+    if let Some(recorded_date) = &media.recorded_date {
+        variables.push(("recorded_date", recorded_date.clone()));
+    }
+    variables
+}
+
+/// Replace each `{name}` in `text` with its value.
+pub fn expand(text: &str, variables: &[(&str, String)]) -> String {
+    if !text.contains('{') {
+        return text.to_owned();
+    }
+
+    variables
+        .iter()
+        .fold(text.to_owned(), |text, (name, value)| {
+            text.replace(&format!("{{{}}}", name), value)
+        })
+}
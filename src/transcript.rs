@@ -0,0 +1,181 @@
+use crate::config::{Episode, EpisodeTranscript, PublishingConfig};
+use crate::CliError;
+use std::path::{Path, PathBuf};
+
+const SIDECAR_EXTENSIONS: [&str; 3] = ["vtt", "srt", "txt"];
+
+/// One cue of an SRT/VTT transcript, or one paragraph of a plain text one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    /// Milliseconds from the start of the episode, when the format has timings
+    pub start: Option<u64>,
+    pub text: String,
+}
+
+/// Parse SRT, WebVTT, or plain text transcripts into cues.
+pub fn parse_cues(text: &str) -> Vec<Cue> {
+    let normalized = text.replace("\r\n", "\n");
+    let mut cues = Vec::new();
+
+    for block in normalized.split("\n\n") {
+        let lines: Vec<&str> = block
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect();
+        if lines.is_empty() || lines[0].starts_with("WEBVTT") || lines[0].starts_with("NOTE") {
+            continue;
+        }
+
+        let timing = lines.iter().position(|line| line.contains("-->"));
+        let (start, body) = match timing {
+            Some(index) => (
+                lines[index]
+                    .split("-->")
+                    .next()
+                    .and_then(|start| parse_timestamp(start.trim())),
+                &lines[index + 1..],
+            ),
+            None => (None, &lines[..]),
+        };
+
+        let text = strip_tags(&body.join(" "));
+        if !text.is_empty() {
+            cues.push(Cue { start, text });
+        }
+    }
+
+    cues
+}
+
+/// The transcript without cue numbers, timings, or markup.
+pub fn plain_text(text: &str) -> String {
+    parse_cues(text)
+        .into_iter()
+        .map(|cue| cue.text)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The transcript stored next to an episode's YAML file, with the same name
+/// and a `.vtt`, `.srt`, or `.txt` extension.
+pub fn find_sidecar(episode_path: &Path) -> Option<PathBuf> {
+    let episode_path = crate::crypto::plain_path(episode_path);
+    SIDECAR_EXTENSIONS
+        .iter()
+        .map(|extension| episode_path.with_extension(extension))
+        .find(|path| path.exists())
+}
+
+/// The transcript to read for an episode: the first one its YAML references
+/// that exists, or else the sidecar next to it.
+pub fn episode_transcript(episode_path: &Path, episode: &Episode) -> Option<PathBuf> {
+    let episode_dir = episode_path.parent().unwrap_or_else(|| Path::new("."));
+    episode
+        .transcripts
+        .iter()
+        .map(|transcript| episode_dir.join(&transcript.path))
+        .find(|path| path.exists())
+        .or_else(|| find_sidecar(episode_path))
+}
+
+/// `type` of a `podcast:transcript`, from the file's extension.
+pub fn mime_type(path: &Path) -> Option<&'static str> {
+    let path = crate::crypto::plain_path(path);
+    match path.extension()?.to_str()? {
+        "vtt" => Some("text/vtt"),
+        "srt" => Some("application/x-subrip"),
+        "txt" => Some("text/plain"),
+        "json" => Some("application/json"),
+        "html" | "htm" => Some("text/html"),
+        _ => None,
+    }
+}
+
+/// A transcript of an episode, and where it is published.
+#[derive(Debug, Clone)]
+pub struct PublishedTranscript {
+    pub source: PathBuf,
+    pub key: String,
+    pub mime_type: &'static str,
+    pub language: Option<String>,
+}
+
+/// Where each of an episode's transcripts is uploaded: next to the audio,
+/// named after it, e.g. `artifacts/2022-09-01.srt` or
+/// `artifacts/2022-09-01.es.vtt`.
+pub fn published_transcripts(
+    publishing: &PublishingConfig,
+    episode_dir: &Path,
+    episode: &Episode,
+) -> Result<Vec<PublishedTranscript>, CliError> {
+    let mut published: Vec<PublishedTranscript> = Vec::new();
+    for EpisodeTranscript { path, language } in &episode.transcripts {
+        let source = episode_dir.join(path);
+        let mime_type = mime_type(&source).ok_or_else(|| {
+            CliError::TranscriptError(format!(
+                "{} is not an SRT, WebVTT, text, JSON, or HTML file",
+                path.display()
+            ))
+        })?;
+        let extension = crate::crypto::plain_path(&source)
+            .extension()
+            .map(|extension| extension.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let suffix = match language {
+            Some(language) => format!(".{}.{}", language, extension),
+            None => format!(".{}", extension),
+        };
+        let key = crate::upload::artifact_key(publishing, episode, &suffix);
+
+        if published.iter().any(|other| other.key == key) {
+            return Err(CliError::TranscriptError(format!(
+                "episode {} has more than one {} transcript{}",
+                episode.id,
+                extension,
+                language
+                    .as_ref()
+                    .map(|language| format!(" in {}", language))
+                    .unwrap_or_default()
+            )));
+        }
+
+        published.push(PublishedTranscript {
+            source,
+            key,
+            mime_type,
+            language: language.clone(),
+        });
+    }
+
+    Ok(published)
+}
+
+/// Parses `HH:MM:SS,mmm`, `HH:MM:SS.mmm`, `MM:SS.mmm`, or `MM:SS` into
+/// milliseconds.
+pub fn parse_timestamp(text: &str) -> Option<u64> {
+    let parts: Vec<&str> = text.split(':').collect();
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [hours, minutes, seconds] => (hours.parse::<u64>().ok()?, *minutes, *seconds),
+        [minutes, seconds] => (0, *minutes, *seconds),
+        _ => return None,
+    };
+    let minutes: u64 = minutes.parse().ok()?;
+    let seconds: f64 = seconds.replace(',', ".").parse().ok()?;
+
+    Some((hours * 3600 + minutes * 60) * 1000 + (seconds * 1000.0).round() as u64)
+}
+
+pub fn strip_tags(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out.trim().to_owned()
+}
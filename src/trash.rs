@@ -0,0 +1,206 @@
+//! Deleted episodes are moved to `episodes/.trash/` instead of being removed,
+//! named `<deleted at>--<original file name>`, so they can be restored.
+
+use crate::config::*;
+use crate::crypto;
+use crate::events;
+use crate::i18n::t;
+use crate::output::{self, outln};
+use crate::render::{self, RenderOptions};
+use crate::CliError;
+use chrono::{NaiveDateTime, Utc};
+use log::info;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const TRASH_DIR: &str = ".trash";
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+pub struct TrashedEpisode {
+    pub deleted_at: NaiveDateTime,
+    /// File name the episode had before it was deleted
+    pub original_name: String,
+    pub episode: Episode,
+}
+
+/// Move an episode's YAML, and any transcripts next to it, to the trash.
+pub fn trash_episode(episode_dir: &Path, episode_path: &Path) -> Result<PathBuf, CliError> {
+    let trash_dir = episode_dir.join(TRASH_DIR);
+    fs::create_dir_all(&trash_dir)?;
+    let deleted_at = Utc::now().format(TIMESTAMP_FORMAT).to_string();
+
+    let stem = crypto::plain_path(episode_path)
+        .file_stem()
+        .unwrap_or_default()
+        .to_owned();
+    let mut trashed = None;
+    for entry in fs::read_dir(episode_dir)? {
+        let path = entry?.path();
+        if !path.is_file() || crypto::plain_path(&path).file_stem() != Some(&stem) {
+            continue;
+        }
+
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let destination = trash_dir.join(format!("{}--{}", deleted_at, name));
+        info!("Moving {} to {}", path.display(), destination.display());
+        fs::rename(&path, &destination)?;
+        if path == episode_path {
+            trashed = Some(destination);
+        }
+    }
+
+    trashed.ok_or_else(|| CliError::EpisodeNotFound(episode_path.display().to_string()))
+}
+
+/// `delete-episode`: move the episode `selector` picks to the trash. With
+/// `render`, the feed is published without it first, and with `remote`,
+/// its objects no other episode uses are deleted from storage.
+pub async fn delete_episode(
+    episode_dir: &Path,
+    channel_config: &ChannelConfig,
+    selector: &str,
+    remote: bool,
+    render: Option<RenderOptions>,
+) -> Result<(), CliError> {
+    if remote {
+        crypto::require_readable(episode_dir)?;
+    }
+    let (path, episode) = crate::find_episode(episode_dir, selector, channel_config.channel.tz()?)?;
+    let trashed = trash_episode(episode_dir, &path)?;
+    outln!(
+        "{}",
+        t!("trashed", title = episode.title.as_str(), path = trashed.display().to_string())
+    );
+    let rendered = render.is_some();
+    if let Some(options) = render {
+        let context = events::current();
+        render::render_channel(episode_dir, channel_config.clone(), &options, &context).await?;
+    }
+    if remote {
+        // Other episodes, and other copies in the trash, may share
+        // objects, like the show's artwork
+        let mut others = crate::get_all_episodes(episode_dir)?;
+        others.extend(
+            list(episode_dir)?
+                .into_iter()
+                .map(|trashed| trashed.episode)
+                .filter(|other| other.id != episode.id),
+        );
+        let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+        let workspace = crate::state::Workspace::new(channel_dir);
+        crate::prune::delete_episode_objects(
+            &channel_config.publishing,
+            &workspace,
+            &episode,
+            &others,
+        )
+        .await?;
+    } else {
+        outln!("{}", t!("trashed-undo", id = episode.id.as_str()));
+    }
+    if !rendered {
+        outln!("{}", t!("render-reminder"));
+    }
+    Ok(())
+}
+
+/// `trash list`: print the episodes in the trash, most recent first.
+pub fn print_list(episode_dir: &Path) -> Result<(), CliError> {
+    let mut listed = Vec::new();
+    for trashed in list(episode_dir)? {
+        outln!(
+            "{}  {}  {}  {}",
+            trashed.deleted_at.format("%Y-%m-%d %H:%M:%S"),
+            trashed.episode.id,
+            trashed.original_name,
+            trashed.episode.title
+        );
+        listed.push(serde_json::json!({
+            "deletedAt": trashed.deleted_at,
+            "id": trashed.episode.id,
+            "originalName": trashed.original_name,
+            "title": trashed.episode.title,
+        }));
+    }
+    output::record("episodes", &listed);
+    Ok(())
+}
+
+/// Every episode in the trash, most recently deleted first.
+pub fn list(episode_dir: &Path) -> Result<Vec<TrashedEpisode>, CliError> {
+    let trash_dir = episode_dir.join(TRASH_DIR);
+    if !trash_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(&trash_dir)? {
+        paths.push(entry?.path());
+    }
+    let mut trashed = Vec::new();
+    for path in crate::formats::episode_files(paths) {
+        let (deleted_at, original_name) = match parse_name(&path) {
+            Some(parsed) => parsed,
+            None => continue,
+        };
+        let format = crate::formats::Format::of(&path);
+        let episode: Episode = format.parse(&crypto::read(&path)?)?;
+        trashed.push(TrashedEpisode {
+            deleted_at,
+            original_name,
+            episode,
+        });
+    }
+
+    trashed.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+    Ok(trashed)
+}
+
+/// Move the most recently deleted copy of an episode, picked like any
+/// other episode but by its original file name, back into the episodes
+/// directory.
+pub fn restore(
+    episode_dir: &Path,
+    channel_config: &ChannelConfig,
+    selector: &str,
+) -> Result<PathBuf, CliError> {
+    let timezone = channel_config.channel.tz()?;
+    let trashed = crate::selector::select(list(episode_dir)?, selector, timezone, |trashed| {
+        let name = crypto::plain_path(Path::new(&trashed.original_name));
+        let name = name.file_stem().unwrap_or_default().to_string_lossy();
+        (&trashed.episode, name.into_owned())
+    })?;
+
+    let restored = episode_dir.join(&trashed.original_name);
+    if restored.exists() {
+        return Err(CliError::TrashError(format!(
+            "{} already exists, move it out of the way first",
+            restored.display()
+        )));
+    }
+
+    // Bring back the transcripts that were deleted with it
+    let prefix = format!("{}--", trashed.deleted_at.format(TIMESTAMP_FORMAT));
+    let stem = crypto::plain_path(Path::new(&trashed.original_name))
+        .file_stem()
+        .unwrap_or_default()
+        .to_owned();
+    for entry in fs::read_dir(episode_dir.join(TRASH_DIR))? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if let Some(original) = name.strip_prefix(&prefix) {
+            if crypto::plain_path(Path::new(original)).file_stem() == Some(&stem) {
+                fs::rename(&path, episode_dir.join(original))?;
+            }
+        }
+    }
+
+    Ok(restored)
+}
+
+fn parse_name(path: &Path) -> Option<(NaiveDateTime, String)> {
+    let name = path.file_name()?.to_str()?;
+    let (timestamp, original) = name.split_once("--")?;
+    let deleted_at = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+    Some((deleted_at, original.to_owned()))
+}
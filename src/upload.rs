@@ -1,47 +1,867 @@
-use futures::TryStreamExt;
-use log::info;
-use pbr::{ProgressBar, Units};
-use read_progress_stream::ReadProgressStream;
-use rusoto_core::ByteStream;
-use rusoto_s3::S3;
-use rusoto_s3::{PutObjectRequest, S3Client};
-use tokio::io::AsyncRead;
-use tokio_util::codec::{BytesCodec, FramedRead};
-
-pub async fn upload_contents<R>(
+use crate::config::{
+    Episode, KeyLayout, ObjectOptionsConfig, PrivateUrls, PublishingConfig, RetryConfig,
+    StorageType,
+};
+use crate::i18n::t;
+use crate::output::outln;
+use crate::output::{Progress, ProgressGroup};
+use crate::storage::{Object, ObjectReader, StorageBackend, StoredObject};
+use crate::{events, CliError};
+use aws_config::retry::RetryConfig as SdkRetryConfig;
+use aws_config::timeout::TimeoutConfig;
+use aws_config::BehaviorVersion;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{ObjectCannedAcl, ServerSideEncryption, StorageClass};
+use aws_smithy_types::body::SdkBody;
+use bytes::Bytes;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::future::LocalBoxFuture;
+use futures::{stream, FutureExt, StreamExt};
+use log::{info, warn};
+use rand::Rng;
+use std::future::Future;
+use std::io::{self, SeekFrom, Write};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+
+/// How often the progress of a streamed upload is shown.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// Streamed uploads are read in chunks this large.
+const BODY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// With `--dry-run`, or a dry run [`events::Context`], uploads print what
+/// they would write instead.
+pub fn dry_run() -> bool {
+    events::dry_run()
+}
+
+/// Where an object was published, and the ETag storage assigned it.
+#[derive(Debug, Clone)]
+pub struct Uploaded {
+    pub url: String,
+    pub etag: Option<String>,
+    /// Hex MD5 of what was sent, for `sync` to tell whether it changed
+    pub md5: Option<String>,
+}
+
+/// `Content-Encoding` of gzipped feeds.
+const GZIP: &str = "gzip";
+
+/// Extra headers stored with an object and served back to clients.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectOptions {
+    pub cache_control: Option<String>,
+    /// Where the progress of a multipart upload is kept, so an interrupted
+    /// upload resumes instead of starting over
+    pub resume_dir: Option<PathBuf>,
+    /// What the contents are, when known from the contents themselves;
+    /// otherwise guessed from the key
+    pub content_type: Option<String>,
+    /// `gzip` when the contents are gzipped, for clients to decompress
+    pub content_encoding: Option<String>,
+    /// A feed apps subscribe to, which keeps its ACL when the feed's URLs
+    /// are presigned
+    pub subscribed: bool,
+}
+
+impl ObjectOptions {
+    /// For audio, artwork, and clips.
+    pub fn media(publishing: &PublishingConfig) -> ObjectOptions {
+        ObjectOptions {
+            cache_control: publishing.object_options.cache_control.media.clone(),
+            resume_dir: None,
+            content_type: None,
+            content_encoding: None,
+            subscribed: false,
+        }
+    }
+
+    /// For feeds and everything else rewritten on each render.
+    pub fn feed(publishing: &PublishingConfig) -> ObjectOptions {
+        ObjectOptions {
+            cache_control: publishing.object_options.cache_control.feed.clone(),
+            resume_dir: None,
+            content_type: None,
+            content_encoding: None,
+            subscribed: false,
+        }
+    }
+
+    /// For podcast.xml and the other feeds apps subscribe to.
+    pub fn subscribed(publishing: &PublishingConfig) -> ObjectOptions {
+        ObjectOptions {
+            subscribed: true,
+            ..ObjectOptions::feed(publishing)
+        }
+    }
+
+    /// These options, for the feed at `key` gzipped.
+    pub fn gzipped(self, key: &str) -> ObjectOptions {
+        let content_type = if key.ends_with(".json") {
+            "application/feed+json; charset=utf-8"
+        } else {
+            "application/rss+xml; charset=utf-8"
+        };
+        ObjectOptions {
+            content_type: Some(content_type.to_owned()),
+            content_encoding: Some(GZIP.to_owned()),
+            ..self
+        }
+    }
+}
+
+/// The contents to upload the feed at `key` as, and the options to upload
+/// them with: gzipped when `publishing.gzipFeeds` says so.
+pub fn feed_object(
+    publishing: &PublishingConfig,
+    key: &str,
+    contents: Vec<u8>,
+) -> Result<(Vec<u8>, ObjectOptions), CliError> {
+    let options = ObjectOptions::subscribed(publishing);
+    if !publishing.gzip_feeds {
+        return Ok((contents, options));
+    }
+    // Without a timestamp in the header, the same feed always compresses
+    // to the same bytes, so `sync` can tell it didn't change
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&contents)?;
+    Ok((encoder.finish()?, options.gzipped(key)))
+}
+
+/// Where the uncompressed copy of the feed at `key` is uploaded, when
+/// `publishing.uncompressedFeedSuffix` asks for one.
+pub fn uncompressed_key(publishing: &PublishingConfig, key: &str) -> Option<String> {
+    let suffix = publishing
+        .uncompressed_feed_suffix
+        .as_ref()
+        .filter(|_| publishing.gzip_feeds)?;
+    let (stem, extension) = key.rsplit_once('.')?;
+    Some(format!("{}{}.{}", stem, suffix, extension))
+}
+
+/// The public URL an object gets once it is uploaded.
+pub fn object_url(publishing: &PublishingConfig, object_key: &str) -> String {
+    match &publishing.public_base_url {
+        Some(public_base_url) => format!(
+            "{}/{}",
+            public_base_url.trim_end_matches('/'),
+            public_key(publishing, object_key)
+        ),
+        None => storage_url(publishing, &storage_key(publishing, object_key)),
+    }
+}
+
+/// `publishing` changed as the destination `name` in
+/// `publishing.destinations` says, or as it is without one.
+pub fn destination(
+    publishing: &PublishingConfig,
+    name: Option<&str>,
+) -> Result<PublishingConfig, CliError> {
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(publishing.clone()),
+    };
+    let destination = publishing
+        .destinations
+        .get(name)
+        .ok_or_else(|| CliError::UnknownDestination(name.to_owned()))?;
+    let mut changed = publishing.clone();
+    if let Some(bucket) = &destination.bucket {
+        changed.bucket = bucket.clone();
+    }
+    if let Some(prefix) = &destination.prefix {
+        changed.prefix = prefix.clone();
+    }
+    if let Some(public_base_url) = &destination.public_base_url {
+        changed.public_base_url = Some(public_base_url.clone());
+    }
+    if let Some(local) = &destination.local {
+        changed.local = Some(local.clone());
+    }
+    Ok(changed)
+}
+
+/// Whether `episode` is listed in the feed published to `destination`, or
+/// the main feed without one: episodes published elsewhere are only listed
+/// in their destination's feed, unless that lists them in the main feed.
+pub fn listed_in(
+    publishing: &PublishingConfig,
+    destination: Option<&str>,
+    episode: &Episode,
+) -> bool {
+    match episode.destination.as_deref() {
+        None => true,
+        Some(name) if Some(name) == destination => true,
+        Some(name) => matches!(
+            publishing.destinations.get(name),
+            Some(destination) if destination.main_feed
+        ),
+    }
+}
+
+/// The key storage holds an object under. With `keyLayout: sharded`,
+/// artifacts go two hashed directories down; keys that already are are
+/// left alone.
+pub fn storage_key(publishing: &PublishingConfig, object_key: &str) -> String {
+    let artifacts = format!("{}/artifacts/", publishing.prefix);
+    let name = object_key
+        .strip_prefix(&artifacts)
+        .filter(|name| !name.contains('/'));
+    match (publishing.key_layout, name) {
+        (KeyLayout::Sharded, Some(name)) => format!("{}{}{}", artifacts, shard(name), name),
+        _ => object_key.to_owned(),
+    }
+}
+
+/// The key without shard directories, as public URLs have it.
+pub fn public_key(publishing: &PublishingConfig, object_key: &str) -> String {
+    let artifacts = format!("{}/artifacts/", publishing.prefix);
+    let name = object_key.strip_prefix(&artifacts).and_then(|sharded| {
+        let name = sharded.splitn(3, '/').nth(2)?;
+        (sharded == format!("{}{}", shard(name), name)).then_some(name)
+    });
+    match name {
+        Some(name) => format!("{}{}", artifacts, name),
+        None => object_key.to_owned(),
+    }
+}
+
+/// `3f/a2/` for a name whose MD5 starts with `3fa2`.
+fn shard(name: &str) -> String {
+    let hash = format!("{:x}", md5::compute(name));
+    format!("{}/{}/", &hash[..2], &hash[2..4])
+}
+
+/// `url` as `object_url` would make it now, when it was made for this
+/// storage before `publicBaseUrl` or `pathStyle` changed.
+pub fn canonical_url(publishing: &PublishingConfig, url: &str) -> String {
+    let mut forms = vec![storage_url(publishing, "")];
+    if publishing.storage == StorageType::S3 {
+        let other_style = PublishingConfig {
+            path_style: !publishing.path_style,
+            ..publishing.clone()
+        };
+        forms.push(storage_url(&other_style, ""));
+    }
+    forms
+        .iter()
+        .find_map(|form| url.strip_prefix(form.as_str()))
+        .map(|object_key| object_url(publishing, object_key))
+        .unwrap_or_else(|| url.to_owned())
+}
+
+/// The key of the object at `url`, without shard directories, when it is
+/// in this storage.
+pub fn url_key(publishing: &PublishingConfig, url: &str) -> Option<String> {
+    let url = canonical_url(publishing, url);
+    if let Some(base) = &publishing.public_base_url {
+        let base = format!("{}/", base.trim_end_matches('/'));
+        if let Some(key) = url.strip_prefix(&base) {
+            return Some(key.to_owned());
+        }
+    }
+    url.strip_prefix(&storage_url(publishing, ""))
+        .map(|key| public_key(publishing, key))
+}
+
+/// Where the storage itself serves an object.
+pub fn storage_url(publishing: &PublishingConfig, object_key: &str) -> String {
+    let base = |base_url: &str| format!("{}/{}", base_url.trim_end_matches('/'), object_key);
+    match publishing.storage {
+        StorageType::S3 if publishing.path_style => format!(
+            "https://{}/{}/{}",
+            publishing.region.endpoint, publishing.bucket, object_key
+        ),
+        StorageType::S3 => format!(
+            "https://{}.{}/{}",
+            publishing.bucket, publishing.region.endpoint, object_key
+        ),
+        StorageType::Local => base(
+            publishing
+                .local
+                .as_ref()
+                .map(|local| local.base_url.as_str())
+                .unwrap_or_default(),
+        ),
+        StorageType::Sftp => base(
+            publishing
+                .sftp
+                .as_ref()
+                .map(|sftp| sftp.base_url.as_str())
+                .unwrap_or_default(),
+        ),
+        StorageType::Gcs => format!(
+            "https://storage.googleapis.com/{}/{}",
+            publishing.bucket, object_key
+        ),
+        StorageType::Azure => format!(
+            "https://{}.blob.core.windows.net/{}/{}",
+            publishing
+                .azure
+                .as_ref()
+                .map(|azure| azure.account.as_str())
+                .unwrap_or_default(),
+            publishing.bucket,
+            object_key
+        ),
+    }
+}
+
+/// Key for a file published next to an episode's audio, named after it,
+/// e.g. `{prefix}/artifacts/2022-09-01{suffix}`.
+pub fn artifact_key(
+    publishing: &crate::config::PublishingConfig,
+    episode: &crate::config::Episode,
+    suffix: &str,
+) -> String {
+    let media_name = episode
+        .media
+        .url
+        .rsplit('/')
+        .next()
+        .and_then(|name| std::path::Path::new(name).file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .filter(|stem| !stem.is_empty())
+        .unwrap_or_else(|| episode.id.clone());
+    format!("{}/artifacts/{}{}", publishing.prefix, media_name, suffix)
+}
+
+/// Key of an episode's audio, e.g. `{prefix}/artifacts/2022-09-01.mp3`.
+pub fn media_key(
+    publishing: &crate::config::PublishingConfig,
+    episode: &crate::config::Episode,
+) -> String {
+    let extension = episode
+        .media
+        .url
+        .rsplit('/')
+        .next()
+        .and_then(|name| std::path::Path::new(name).extension())
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    artifact_key(publishing, episode, &extension)
+}
+
+/// An object for `upload_objects` to upload.
+pub struct PendingObject {
+    pub key: String,
+    pub contents: Vec<u8>,
+    pub options: ObjectOptions,
+}
+
+/// Upload `objects`, `publishing.parallelUploads` at a time, with their
+/// progress shown together. Every object is tried, even once one fails;
+/// the results are in the order of `objects`.
+pub async fn upload_objects(
+    publishing: &PublishingConfig,
+    objects: Vec<PendingObject>,
+) -> Vec<Result<Uploaded, CliError>> {
+    let sizes: Vec<u64> = objects
+        .iter()
+        .map(|object| object.contents.len() as u64)
+        .collect();
+    let group = if objects.len() > 1 && !dry_run() {
+        let names: Vec<(String, u64)> = objects
+            .iter()
+            .zip(&sizes)
+            .map(|(object, size)| (object_name(&object.key).to_owned(), *size))
+            .collect();
+        Some(ProgressGroup::new(&names))
+    } else {
+        None
+    };
+
+    let results: Vec<Result<Uploaded, CliError>> = stream::iter(objects.into_iter().enumerate())
+        .map(|(index, object)| {
+            let size = object.contents.len() as u64;
+            let group = group.as_ref().map(|group| (group, index));
+            upload_with(
+                std::io::Cursor::new(object.contents),
+                size,
+                publishing,
+                object.key,
+                object.options,
+                group,
+            )
+        })
+        .buffered(publishing.parallel_uploads.max(1))
+        .collect()
+        .await;
+
+    if let Some(group) = group {
+        let uploaded = results
+            .iter()
+            .zip(&sizes)
+            .filter(|(result, _)| result.is_ok());
+        let (count, bytes) = uploaded.fold((0, 0), |(count, bytes), (_, size)| {
+            (count + 1, bytes + size)
+        });
+        group.finish(count, bytes);
+    }
+    results
+}
+
+fn object_name(key: &str) -> &str {
+    key.split('/').next_back().unwrap_or_default()
+}
+
+/// Upload an object to the backend `publishing.type` picks.
+pub async fn upload_object<R>(
     read: R,
     size: u64,
-    region: crate::config::Region,
-    bucket: String,
+    publishing: &PublishingConfig,
     object_key: String,
-) -> Result<String, crate::CliError>
+    options: ObjectOptions,
+) -> Result<Uploaded, CliError>
 where
-    R: AsyncRead + Send + Sync + 'static,
+    R: AsyncRead + AsyncSeek + Send + Sync + Unpin + 'static,
 {
-    let reader = FramedRead::new(read, BytesCodec::new()).map_ok(|r| r.freeze());
-    let endpoint = region.endpoint.clone();
-    info!("file size: {}, region {:?}", size, &region);
+    upload_with(read, size, publishing, object_key, options, None).await
+}
 
-    let client = S3Client::new(region.into());
+/// Upload an object, showing its progress in `group`, at the index given,
+/// when it is one of several uploading at once.
+async fn upload_with<R>(
+    read: R,
+    size: u64,
+    publishing: &PublishingConfig,
+    object_key: String,
+    options: ObjectOptions,
+    group: Option<(&ProgressGroup, usize)>,
+) -> Result<Uploaded, CliError>
+where
+    R: AsyncRead + AsyncSeek + Send + Sync + Unpin + 'static,
+{
+    let url = object_url(publishing, &object_key);
+    let mime = options
+        .content_type
+        .clone()
+        .unwrap_or_else(|| mime_type(&object_key));
+    let object_key = storage_key(publishing, &object_key);
+    if dry_run() {
+        outln!(
+            "{}",
+            t!(
+                "dry-run-upload",
+                key = object_key.as_str(),
+                size = crate::preview::format_bytes(size),
+                content_type = mime.as_str(),
+                url = url.as_str()
+            )
+        );
+        crate::output::append(
+            "uploads",
+            serde_json::json!({ "key": object_key, "url": url, "bytes": size, "dryRun": true }),
+        );
+        return Ok(Uploaded {
+            url,
+            etag: None,
+            md5: None,
+        });
+    }
+    info!(
+        "file size: {}, storage {}",
+        size,
+        publishing.storage.as_str()
+    );
 
-    let mut pb = ProgressBar::new(size);
-    pb.set_units(Units::Bytes);
-    pb.show_speed = true;
+    let backend = crate::storage::backend(publishing).await?;
 
-    if let Some(name) = object_key.split("/").last() {
-        pb.message(&format!("{} ", &name));
-    }
+    let name = object_name(&object_key);
+    let mut progress = match group {
+        Some((group, index)) => group.start(index, &object_key, name, size),
+        None => Progress::new(&object_key, name, size),
+    };
 
-    // Progress handler to be called as bytes are read
-    let progress = Box::new(move |amount: u64, _| {
-        pb.add(amount);
+    let object = Object {
+        key: &object_key,
+        size,
+        content_type: &mime,
+        options: &options,
+    };
+    let context = Arc::new(Mutex::new(md5::Context::new()));
+    let read = Md5Reader {
+        inner: read,
+        context: context.clone(),
+    };
+    let etag = backend.put(object, Box::new(read), &mut progress).await?;
+    let md5 = context
+        .lock()
+        .map(|context| format!("{:x}", context.clone().compute()))
+        .ok();
+
+    events::emit(events::Event::UploadFinished {
+        key: object_key.clone(),
+        url: url.clone(),
     });
+    Ok(Uploaded { url, etag, md5 })
+}
+
+/// Hashes what storage reads from it, starting over when storage seeks
+/// back to the start to send it again.
+struct Md5Reader<R> {
+    inner: R,
+    context: Arc<Mutex<md5::Context>>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Md5Reader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = &poll {
+            if let Ok(mut context) = self.context.lock() {
+                context.consume(&buf.filled()[before..]);
+            }
+        }
+        poll
+    }
+}
+
+impl<R: AsyncSeek + Unpin> AsyncSeek for Md5Reader<R> {
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        if position == SeekFrom::Start(0) {
+            if let Ok(mut context) = self.context.lock() {
+                *context = md5::Context::new();
+            }
+        }
+        Pin::new(&mut self.inner).start_seek(position)
+    }
+
+    fn poll_complete(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.inner).poll_complete(cx)
+    }
+}
+
+/// S3 and S3 compatible storage. Objects from the multipart threshold up
+/// are uploaded in parts; smaller ones are streamed, and read again from
+/// the start when `publishing.retry` considers a failure transient.
+pub struct S3Storage<'a> {
+    publishing: &'a PublishingConfig,
+    client: aws_sdk_s3::Client,
+}
+
+impl<'a> S3Storage<'a> {
+    pub async fn new(publishing: &'a PublishingConfig) -> S3Storage<'a> {
+        S3Storage {
+            publishing,
+            client: client(publishing).await,
+        }
+    }
+}
+
+/// The body of a streamed upload: `source` from the start, counting what
+/// is read in `sent`. Each attempt gets a new one, which seeks back to the
+/// start before reading.
+struct SourceBody {
+    source: Arc<Mutex<ObjectReader>>,
+    sent: Arc<AtomicU64>,
+    size: u64,
+    seeking: bool,
+    rewound: bool,
+    buffer: Vec<u8>,
+}
+
+impl SourceBody {
+    fn new(source: Arc<Mutex<ObjectReader>>, sent: Arc<AtomicU64>, size: u64) -> SourceBody {
+        SourceBody {
+            source,
+            sent,
+            size,
+            seeking: false,
+            rewound: false,
+            buffer: vec![0; BODY_CHUNK_SIZE],
+        }
+    }
+}
+
+impl http_body::Body for SourceBody {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, io::Error>>> {
+        let this = &mut *self;
+        let mut source = this
+            .source
+            .lock()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "the upload source was lost"))?;
+        if !this.rewound {
+            if !this.seeking {
+                Pin::new(&mut **source).start_seek(SeekFrom::Start(0))?;
+                this.seeking = true;
+            }
+            futures::ready!(Pin::new(&mut **source).poll_complete(cx))?;
+            this.rewound = true;
+            this.sent.store(0, Ordering::Relaxed);
+        }
+
+        let mut buffer = ReadBuf::new(&mut this.buffer);
+        futures::ready!(Pin::new(&mut **source).poll_read(cx, &mut buffer))?;
+        let read = buffer.filled();
+        if read.is_empty() {
+            return Poll::Ready(None);
+        }
+        this.sent.fetch_add(read.len() as u64, Ordering::Relaxed);
+        Poll::Ready(Some(Ok(Bytes::copy_from_slice(read))))
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<reqwest::header::HeaderMap>, io::Error>> {
+        Poll::Ready(Ok(None))
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        http_body::SizeHint::with_exact(self.size)
+    }
+}
+
+impl StorageBackend for S3Storage<'_> {
+    fn put<'a>(
+        &'a self,
+        object: Object<'a>,
+        read: ObjectReader,
+        progress: &'a mut Progress,
+    ) -> LocalBoxFuture<'a, Result<Option<String>, CliError>> {
+        async move {
+            let publishing = self.publishing;
+            if object.size >= publishing.multipart.threshold_mb * 1024 * 1024 {
+                let target = crate::multipart::Target {
+                    client: &self.client,
+                    bucket: &publishing.bucket,
+                    key: object.key,
+                    content_type: object.content_type,
+                    cache_control: object.options.cache_control.as_deref(),
+                    content_encoding: object.options.content_encoding.as_deref(),
+                    options: &publishing.object_options,
+                    acl: acl(publishing, object.options),
+                    retry: &publishing.retry,
+                };
+                return crate::multipart::upload(
+                    target,
+                    read,
+                    object.size,
+                    &publishing.multipart,
+                    object.options.resume_dir.as_deref(),
+                    progress,
+                )
+                .await;
+            }
+
+            let source = Arc::new(Mutex::new(read));
+            let sent = Arc::new(AtomicU64::new(0));
+            let put = retry(&publishing.retry, object.key, || {
+                let body = SourceBody::new(source.clone(), sent.clone(), object.size);
+                self.client
+                    .put_object()
+                    .bucket(&publishing.bucket)
+                    .key(object.key)
+                    .body(ByteStream::new(SdkBody::from_body_0_4(body)))
+                    .content_length(object.size as i64)
+                    .set_acl(acl(publishing, object.options))
+                    .content_type(object.content_type)
+                    .set_cache_control(object.options.cache_control.clone())
+                    .set_content_encoding(object.options.content_encoding.clone())
+                    .set_storage_class(storage_class(&publishing.object_options))
+                    .set_server_side_encryption(encryption(&publishing.object_options))
+                    .set_ssekms_key_id(publishing.object_options.kms_key_id.clone())
+                    .send()
+            });
+            futures::pin_mut!(put);
+
+            // The bar moves as the body is read, but not back when a retry
+            // reads it from the start again
+            let mut shown = 0;
+            let output = loop {
+                tokio::select! {
+                    output = &mut put => break output?,
+                    _ = tokio::time::sleep(PROGRESS_INTERVAL) => {}
+                }
+                let read = sent.load(Ordering::Relaxed);
+                if read > shown {
+                    progress.add(read - shown);
+                    shown = read;
+                }
+            };
+            progress.add(object.size.saturating_sub(shown));
+            Ok(output.e_tag)
+        }
+        .boxed_local()
+    }
+
+    fn list<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> LocalBoxFuture<'a, Result<Vec<StoredObject>, CliError>> {
+        async move {
+            let mut objects = Vec::new();
+            let mut continuation: Option<String> = None;
+            loop {
+                let output = retry(&self.publishing.retry, prefix, || {
+                    self.client
+                        .list_objects_v2()
+                        .bucket(&self.publishing.bucket)
+                        .prefix(prefix)
+                        .set_continuation_token(continuation.clone())
+                        .send()
+                })
+                .await?;
+                objects.extend(output.contents().iter().filter_map(|object| {
+                    Some(StoredObject {
+                        key: object.key()?.to_owned(),
+                        size: object.size().unwrap_or_default().max(0) as u64,
+                    })
+                }));
+                match output.next_continuation_token {
+                    Some(next) if output.is_truncated == Some(true) => continuation = Some(next),
+                    _ => break,
+                }
+            }
+            Ok(objects)
+        }
+        .boxed_local()
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> LocalBoxFuture<'a, Result<(), CliError>> {
+        async move {
+            retry(&self.publishing.retry, key, || {
+                self.client
+                    .delete_object()
+                    .bucket(&self.publishing.bucket)
+                    .key(key)
+                    .send()
+            })
+            .await?;
+            Ok(())
+        }
+        .boxed_local()
+    }
+}
 
-    let stream = ReadProgressStream::new(reader, progress);
+/// Run a storage request until it succeeds, fails in a way retrying won't
+/// fix, or runs out of attempts. Attempts are spaced out exponentially,
+/// with up to half of each wait random so parallel uploads don't retry in
+/// lockstep.
+pub async fn retry<T, E, F, Fut>(
+    config: &RetryConfig,
+    what: &str,
+    mut send: F,
+) -> Result<T, CliError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Into<CliError>,
+{
+    let max_attempts = config.max_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        let error = match send().await {
+            Ok(output) => return Ok(output),
+            Err(e) => e.into(),
+        };
+        if !error.is_retryable() || attempt >= max_attempts {
+            return Err(error);
+        }
+
+        let backoff = backoff(config, attempt);
+        warn!(
+            "Uploading {} failed, retrying in {:?} ({}/{}): {}",
+            what, backoff, attempt, max_attempts, error
+        );
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
 
-    let body = ByteStream::new_with_size(stream, size as usize);
+fn backoff(config: &RetryConfig, attempt: u32) -> Duration {
+    let doubled = config
+        .initial_backoff_ms
+        .saturating_mul(2u64.saturating_pow(attempt - 1));
+    let base = doubled.min(config.max_backoff_seconds * 1000);
+    let jitter = rand::thread_rng().gen_range(0..=base / 2);
+    Duration::from_millis(base - base / 2 + jitter)
+}
 
-    let mime = mime_guess::from_path(&object_key)
+/// Reject `objectOptions` S3 wouldn't accept before anything is uploaded.
+pub fn check_object_options(options: &ObjectOptionsConfig) -> Result<(), CliError> {
+    let invalid = |field: &str, value: &str, allowed: &[&str]| {
+        Err(CliError::StorageConfig(format!(
+            "`objectOptions.{}` is {}, it must be one of {}",
+            field,
+            value,
+            allowed.join(", ")
+        )))
+    };
+    if options.acl != "none" && !ObjectCannedAcl::values().contains(&options.acl.as_str()) {
+        return invalid("acl", &options.acl, ObjectCannedAcl::values());
+    }
+    if let Some(class) = &options.storage_class {
+        if !StorageClass::values().contains(&class.as_str()) {
+            return invalid("storageClass", class, StorageClass::values());
+        }
+    }
+    if let Some(sse) = &options.server_side_encryption {
+        if !ServerSideEncryption::values().contains(&sse.as_str()) {
+            return invalid("serverSideEncryption", sse, ServerSideEncryption::values());
+        }
+    }
+    let kms = matches!(
+        options.server_side_encryption.as_deref(),
+        Some("aws:kms") | Some("aws:kms:dsse")
+    );
+    if options.kms_key_id.is_some() && !kms {
+        return Err(CliError::StorageConfig(
+            "`objectOptions.kmsKeyId` needs `serverSideEncryption: aws:kms`".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+/// The canned ACL objects get: none for private feeds, whose objects are
+/// only served through presigned URLs or a CDN. With presigned URLs, the
+/// feeds themselves keep theirs, so apps can fetch them at a stable URL.
+pub(crate) fn acl(
+    publishing: &PublishingConfig,
+    object: &ObjectOptions,
+) -> Option<ObjectCannedAcl> {
+    let options = &publishing.object_options;
+    let signed = match &publishing.private {
+        Some(private) => !(object.subscribed && private.urls == PrivateUrls::Presigned),
+        None => false,
+    };
+    if options.acl == "none" || signed {
+        None
+    } else {
+        Some(ObjectCannedAcl::from(options.acl.as_str()))
+    }
+}
+
+pub(crate) fn storage_class(options: &ObjectOptionsConfig) -> Option<StorageClass> {
+    options.storage_class.as_deref().map(StorageClass::from)
+}
+
+pub(crate) fn encryption(options: &ObjectOptionsConfig) -> Option<ServerSideEncryption> {
+    options
+        .server_side_encryption
+        .as_deref()
+        .map(ServerSideEncryption::from)
+}
+
+pub fn mime_type(object_key: &str) -> String {
+    mime_guess::from_path(object_key)
         .first()
         .map(|x| x.to_string())
         .unwrap_or_else(|| {
@@ -50,18 +870,42 @@ where
             } else {
                 mime::APPLICATION_OCTET_STREAM.to_string()
             }
-        });
-
-    let put_request = PutObjectRequest {
-        bucket: bucket.clone(),
-        key: object_key.clone(),
-        body: Some(body),
-        acl: Some("public-read".to_owned()),
-        content_type: Some(mime),
-        ..Default::default()
-    };
+        })
+}
 
-    client.put_object(put_request).await?;
+/// A client with credentials from the standard chain: the environment, the
+/// shared config and credentials files (including SSO), web identity
+/// tokens (IRSA), and the instance or container metadata service.
+pub async fn client(publishing: &PublishingConfig) -> aws_sdk_s3::Client {
+    let region = &publishing.region;
+    // Retries are left to `retry`, which knows which failures are worth it
+    let mut loader = aws_config::defaults(BehaviorVersion::latest())
+        .retry_config(SdkRetryConfig::disabled())
+        .timeout_config(
+            TimeoutConfig::builder()
+                .connect_timeout(Duration::from_secs(
+                    publishing.retry.connect_timeout_seconds,
+                ))
+                .read_timeout(Duration::from_secs(publishing.retry.read_timeout_seconds))
+                .build(),
+        );
+    if let Some(profile) = &region.profile {
+        loader = loader.profile_name(profile);
+    }
+    if let Some(name) = &region.name {
+        loader = loader.region(aws_config::Region::new(name.clone()));
+    }
+    let shared = loader.load().await;
 
-    Ok(format!("https://{}.{}/{}", &bucket, endpoint, &object_key))
+    let mut config =
+        aws_sdk_s3::config::Builder::from(&shared).force_path_style(publishing.path_style);
+    if let Some(endpoint) = region.custom_endpoint() {
+        // Most S3 compatible providers only support path-style requests, and
+        // don't care which region requests are signed for
+        config = config.endpoint_url(endpoint).force_path_style(true);
+        if shared.region().is_none() {
+            config = config.region(aws_config::Region::from_static("us-east-1"));
+        }
+    }
+    aws_sdk_s3::Client::from_conf(config.build())
 }
@@ -0,0 +1,135 @@
+//! URLs written into feeds. Files hold URLs as they were typed, with
+//! spaces, accented letters, or query strings, but podcast apps are strict
+//! about what they fetch. Every URL is parsed and written in its normal
+//! form: percent-encoded, with the host in lowercase punycode. Anything
+//! that isn't an `http` or `https` URL is an error naming where it came
+//! from. Empty values are left for `validate` to report.
+
+use crate::config::{ChannelDetails, Episode, Person};
+use crate::xml::ItemAssets;
+use crate::CliError;
+use reqwest::Url;
+
+/// `url` as it is written into a feed. `field` says where it came from,
+/// like `image in channel.yaml`.
+pub fn normalize(field: &str, url: &str) -> Result<String, CliError> {
+    let invalid = |detail: String| CliError::InvalidUrl(field.to_owned(), url.to_owned(), detail);
+    let parsed = Url::parse(url.trim()).map_err(|e| invalid(e.to_string()))?;
+    match parsed.scheme() {
+        "http" | "https" => Ok(parsed.to_string()),
+        scheme => Err(invalid(format!(
+            "podcast apps only fetch http and https URLs, not {}",
+            scheme
+        ))),
+    }
+}
+
+pub fn normalize_channel(channel: &mut ChannelDetails) -> Result<(), CliError> {
+    let field = |name: &str| format!("{} in channel.yaml", name);
+    normalize_optional(&field("link"), &mut channel.link)?;
+    normalize_field(&field("image"), &mut channel.image)?;
+    for funding in &mut channel.funding {
+        normalize_field(&field("funding url"), &mut funding.url)?;
+    }
+    for trailer in &mut channel.trailers {
+        normalize_field(&field("trailer url"), &mut trailer.url)?;
+    }
+    normalize_people(&field("hosts"), &mut channel.hosts)?;
+    normalize_people(&field("guests"), &mut channel.guests)
+}
+
+pub fn normalize_episode(episode: &mut Episode) -> Result<(), CliError> {
+    let id = episode.id.clone();
+    let field = |name: &str| format!("{} in episode {}", name, id);
+    normalize_optional(&field("link"), &mut episode.link)?;
+    normalize_field(&field("image"), &mut episode.image)?;
+    normalize_field(&field("media.url"), &mut episode.media.url)?;
+    normalize_people(&field("hosts"), &mut episode.hosts)?;
+    normalize_people(&field("guests"), &mut episode.guests)
+}
+
+/// Transcript and chapter URLs published with the episode `id`.
+pub fn normalize_assets(id: &str, assets: &mut ItemAssets) -> Result<(), CliError> {
+    for transcript in &mut assets.transcripts {
+        normalize_field(
+            &format!("transcript in episode {}", id),
+            &mut transcript.url,
+        )?;
+    }
+    normalize_optional(
+        &format!("chapters in episode {}", id),
+        &mut assets.chapters_url,
+    )
+}
+
+fn normalize_field(field: &str, url: &mut String) -> Result<(), CliError> {
+    if !url.is_empty() {
+        *url = normalize(field, url)?;
+    }
+    Ok(())
+}
+
+fn normalize_optional(field: &str, url: &mut Option<String>) -> Result<(), CliError> {
+    match url {
+        Some(url) => normalize_field(field, url),
+        None => Ok(()),
+    }
+}
+
+fn normalize_people(field: &str, people: &mut [Person]) -> Result<(), CliError> {
+    for person in people {
+        normalize_optional(field, &mut person.href)?;
+        normalize_optional(field, &mut person.img)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normal(url: &str) -> String {
+        normalize("link", url).unwrap()
+    }
+
+    #[test]
+    fn writes_urls_in_their_normal_form() {
+        assert_eq!(normal("https://example.com"), "https://example.com/");
+        assert_eq!(
+            normal(" https://Example.COM/My Show/ep 1.mp3 "),
+            "https://example.com/My%20Show/ep%201.mp3"
+        );
+        assert_eq!(
+            normal("https://bücher.example/café?q=a b"),
+            "https://xn--bcher-kva.example/caf%C3%A9?q=a%20b"
+        );
+        assert_eq!(normal("HTTP://example.com:80/a"), "http://example.com/a");
+        assert_eq!(
+            normal("https://example.com/a%20b.mp3"),
+            "https://example.com/a%20b.mp3"
+        );
+    }
+
+    #[test]
+    fn refuses_urls_podcast_apps_cannot_fetch() {
+        for url in ["ftp://example.com/a.mp3", "file:///tmp/a.mp3", "episode.mp3", "https://"] {
+            match normalize("media.url in episode first", url) {
+                Err(CliError::InvalidUrl(field, invalid, _)) => {
+                    assert_eq!(field, "media.url in episode first");
+                    assert_eq!(invalid, url);
+                }
+                other => panic!("{} gave {:?}", url, other),
+            }
+        }
+    }
+
+    #[test]
+    fn leaves_empty_urls_for_validate() {
+        let mut url = String::new();
+        normalize_field("image", &mut url).unwrap();
+        assert_eq!(url, "");
+        let mut link = Some("https://Example.com".to_owned());
+        normalize_optional("link", &mut link).unwrap();
+        assert_eq!(link.as_deref(), Some("https://example.com/"));
+    }
+}
@@ -0,0 +1,993 @@
+use crate::artwork::LocalArtwork;
+use crate::config::*;
+use crate::guids;
+use crate::http::HttpClient;
+use crate::i18n::t;
+use crate::images;
+use crate::output::outln;
+use crate::CliError;
+use chrono_tz::Tz;
+use log::debug;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Placeholder text written by `create-episode`.
+pub const PLACEHOLDER: &str = "Fill me in";
+/// Filler that means a field was never written, in lowercase.
+const FILLER: [&str; 2] = ["fill me in", "lorem ipsum"];
+/// Words that mark a field as unfinished, like `TODO`.
+const UNFINISHED: [&str; 3] = ["TODO", "TBD", "FIXME"];
+/// Hosts reserved for examples (RFC 2606), so never a real image or page.
+const EXAMPLE_HOSTS: [&str; 3] = ["example.com", "example.org", "example.net"];
+/// Apple Podcasts truncates or rejects longer descriptions.
+pub const MAX_DESCRIPTION: usize = 4000;
+/// Apple Podcasts cuts `itunes:subtitle` off after this many characters.
+pub const MAX_SUBTITLE: usize = 255;
+/// Words that make an episode explicit, unless the channel lists its own.
+const EXPLICIT_TERMS: [&str; 12] = [
+    "fuck",
+    "fucking",
+    "fucked",
+    "motherfucker",
+    "shit",
+    "shitty",
+    "bullshit",
+    "bitch",
+    "asshole",
+    "cunt",
+    "cock",
+    "porn",
+];
+const MIN_ARTWORK: u32 = 1400;
+const MAX_ARTWORK: u32 = 3000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    /// Directories will reject the feed or the episode
+    Error,
+    /// Allowed, but likely a mistake or a worse listing
+    Warning,
+}
+
+/// Something about the channel that podcast apps will get wrong.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Issue {
+    pub severity: Severity,
+    /// Episode ids involved, empty for channel issues
+    pub episodes: Vec<String>,
+    pub message: String,
+}
+
+impl Issue {
+    fn channel(severity: Severity, message: String) -> Self {
+        Self {
+            severity,
+            episodes: Vec::new(),
+            message,
+        }
+    }
+
+    fn episode(severity: Severity, episode: &Episode, message: String) -> Self {
+        Self {
+            severity,
+            episodes: vec![episode.id.clone()],
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => t!("severity-error"),
+            Severity::Warning => t!("severity-warning"),
+        };
+        if self.episodes.is_empty() {
+            write!(f, "{}: {}", severity, self.message)
+        } else {
+            write!(
+                f,
+                "{}: {} ({})",
+                severity,
+                self.message,
+                self.episodes.join(", ")
+            )
+        }
+    }
+}
+
+/// Check the channel and every episode against what Apple Podcasts and
+/// Spotify require. With a client, artwork is downloaded to check its size
+/// and format; channel artwork from a local file, `artwork`, is checked
+/// without one. Episode numbers are checked as `numbering` says to number
+/// them.
+pub async fn validate(
+    channel: &ChannelDetails,
+    numbering: Numbering,
+    episodes: &[Episode],
+    client: Option<&HttpClient>,
+    artwork: Option<&LocalArtwork>,
+) -> Vec<Issue> {
+    let mut issues = check_channel(channel);
+    if let Some(artwork) = artwork {
+        for (severity, problem) in artwork.problems() {
+            issues.push(Issue::channel(
+                severity,
+                format!("channel image {} {}", artwork.path.display(), problem),
+            ));
+        }
+    }
+    if let Some(client) = client {
+        if artwork.is_none() {
+            issues.extend(check_artwork(&channel.image, client).await);
+        }
+        issues.extend(check_episode_artwork(channel, episodes, client).await);
+    }
+    for episode in episodes {
+        issues.extend(check_episode(episode, numbering));
+        let published = crate::keywords::episode_keywords(&channel.keywords, episode);
+        let mut add = |severity, message| issues.push(Issue::episode(severity, episode, message));
+        check_keywords(&episode.keywords, &published, &mut add);
+    }
+    issues.extend(check_explicit(channel, episodes));
+    issues.extend(check_ordering(
+        channel.medium.unwrap_or_default(),
+        numbering,
+        episodes,
+    ));
+    issues
+}
+
+/// Everything `validate` reports: the show as it will be published, with
+/// local artwork and site links filled in, then its text linted.
+pub async fn validate_show(
+    config: &ChannelConfig,
+    channel_dir: &Path,
+    mut episode_files: Vec<(PathBuf, Episode)>,
+    client: Option<&HttpClient>,
+) -> Result<Vec<Issue>, CliError> {
+    let mut channel = config.channel.clone();
+    let local_artwork = crate::artwork::local(channel_dir, &config.publishing, &channel.image)?;
+    if let Some(artwork) = &local_artwork {
+        artwork.substitute(&mut channel, episode_files.iter_mut().map(|(_, e)| e));
+    }
+    crate::site::fill_links(
+        &config.publishing,
+        &mut channel,
+        episode_files
+            .iter_mut()
+            .map(|(path, episode)| (path.as_path(), episode)),
+    );
+    let timezone = channel.tz()?;
+    for (_, episode) in &mut episode_files {
+        guids::fill(&config.publishing, timezone, episode)?;
+    }
+    let episodes: Vec<Episode> = episode_files
+        .iter()
+        .map(|(_, episode)| episode.clone())
+        .collect();
+    let mut issues = validate(
+        &channel,
+        config.seasons.numbering,
+        &episodes,
+        client,
+        local_artwork.as_ref(),
+    )
+    .await;
+    issues.extend(crate::lint::check(&config.lint, &channel, &episode_files)?);
+    Ok(issues)
+}
+
+/// `validate`: print what [`validate_show`] finds and a summary, failing
+/// on errors, or with `strict`, on warnings too. Artwork is only
+/// downloaded to check unless `offline`.
+pub async fn run(
+    config: &ChannelConfig,
+    episode_dir: &Path,
+    offline: bool,
+    strict: bool,
+) -> Result<(), CliError> {
+    let episode_files = crate::get_all_episode_files(episode_dir)?;
+    let channel_dir = episode_dir.parent().unwrap_or(episode_dir);
+    // The artwork size can only be checked by downloading it
+    let client = if offline {
+        None
+    } else {
+        Some(HttpClient::new(&config.http)?)
+    };
+    let issues = validate_show(config, channel_dir, episode_files, client.as_ref()).await?;
+
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+    for issue in &issues {
+        outln!("{}", issue);
+    }
+    outln!(
+        "{}",
+        t!("validate-summary", errors = errors, warnings = issues.len() - errors)
+    );
+    crate::output::record("issues", &issues);
+
+    let failures = if strict { issues.len() } else { errors };
+    if failures > 0 {
+        return Err(CliError::ValidationFailed(failures));
+    }
+    Ok(())
+}
+
+fn check_channel(channel: &ChannelDetails) -> Vec<Issue> {
+    use Severity::*;
+    let mut issues = Vec::new();
+    let mut add = |severity, message: String| issues.push(Issue::channel(severity, message));
+
+    for (field, value) in [
+        ("title", &channel.title),
+        ("description", &channel.description),
+        ("summary", &channel.summary),
+    ] {
+        check_text(field, value, &mut add);
+    }
+    if channel.subtitle.trim().is_empty() {
+        add(Warning, "channel subtitle is empty".to_owned());
+    }
+    check_subtitle("channel subtitle", &channel.subtitle, &mut add);
+    check_plain_text("channel title", &channel.title, &mut add);
+
+    // Whether it is a JPEG or PNG is checked on the downloaded artwork, since
+    // image URLs often have no extension
+    check_url("channel image", &channel.image, &mut add);
+    match &channel.link {
+        Some(link) => check_url("channel link", link, &mut add),
+        None => add(Warning, "channel has no link to a website".to_owned()),
+    }
+
+    if channel.owner.name.trim().is_empty() {
+        add(Warning, "owner name is empty".to_owned());
+    }
+    if !is_email(&channel.owner.email) {
+        add(
+            Error,
+            format!(
+                "owner email '{}' is not a valid address",
+                channel.owner.email
+            ),
+        );
+    }
+    if channel.keywords.is_empty() {
+        add(Warning, "channel has no keywords".to_owned());
+    }
+    check_keywords(&channel.keywords, &channel.keywords, &mut add);
+    if channel.categories.is_empty() {
+        add(Error, "channel has no categories".to_owned());
+    }
+    if let Some(medium @ (Medium::Music | Medium::Audiobook)) = channel.medium {
+        if channel.podcast_type == PodcastType::Episodic {
+            add(
+                Warning,
+                format!(
+                    "{} feeds should be serial, so apps play them in order",
+                    medium.as_str()
+                ),
+            );
+        }
+    }
+    for category in &channel.categories {
+        if !crate::categories::is_known(category) {
+            add(
+                Error,
+                format!("'{}' is not an Apple Podcasts category", category),
+            );
+        }
+    }
+    if let Some(timezone) = &channel.timezone {
+        if crate::dates::timezone(Some(timezone)).is_err() {
+            add(
+                Error,
+                format!("'{}' is not a timezone like America/New_York", timezone),
+            );
+        }
+    }
+
+    issues
+}
+
+fn check_episode(episode: &Episode, numbering: Numbering) -> Vec<Issue> {
+    use Severity::*;
+    let mut issues = Vec::new();
+    let mut add =
+        |severity, message: String| issues.push(Issue::episode(severity, episode, message));
+
+    for (field, value) in [
+        ("title", &episode.title),
+        ("description", &episode.description),
+        ("summary", &episode.summary),
+    ] {
+        check_text(field, value, &mut add);
+    }
+    // The summary is published as the episode's subtitle
+    check_subtitle("summary", &episode.summary, &mut add);
+    check_plain_text("title", &episode.title, &mut add);
+    if let Some(link) = &episode.link {
+        check_url("link", link, &mut add);
+    }
+    check_url("image", &episode.image, &mut add);
+    check_url("media URL", &episode.media.url, &mut add);
+
+    if episode.media.duration.0 == 0 {
+        add(Error, "duration is 0".to_owned());
+    }
+    if episode.media.bytes.0 == 0 {
+        add(Error, "media size is 0 bytes".to_owned());
+    }
+    let media = episode.media.url.to_lowercase();
+    if ![".mp3", ".m4a", ".mp4", ".mov", ".m4v"]
+        .iter()
+        .any(|extension| media.ends_with(extension))
+    {
+        add(
+            Warning,
+            "media is not an mp3, m4a, mp4, mov, or m4v file".to_owned(),
+        );
+    }
+    if episode.episode_number == 0 && numbering != Numbering::None {
+        add(Warning, "episode number is 0".to_owned());
+    }
+    if episode.keywords.is_empty() && !episode.extend_channel_keywords {
+        add(Warning, "episode has no keywords".to_owned());
+    }
+
+    issues
+}
+
+fn check_text<F>(field: &str, value: &str, add: &mut F)
+where
+    F: FnMut(Severity, String),
+{
+    if value.trim().is_empty() {
+        add(Severity::Error, format!("{} is empty", field));
+    } else if let Some((severity, message)) = scaffolding(field, value) {
+        add(severity, message);
+    } else if value.chars().count() > MAX_DESCRIPTION {
+        add(
+            Severity::Error,
+            format!("{} is longer than {} characters", field, MAX_DESCRIPTION),
+        );
+    }
+}
+
+/// Keywords in `own` that read as two in `itunes:keywords`, and those of
+/// `published` it has no room for.
+fn check_keywords<F>(own: &[String], published: &[String], add: &mut F)
+where
+    F: FnMut(Severity, String),
+{
+    for keyword in own.iter().filter(|keyword| keyword.contains(',')) {
+        add(
+            Severity::Warning,
+            format!("keyword '{}' has a comma, so it reads as two", keyword),
+        );
+    }
+    let (_, left_out) = crate::keywords::feed_keywords(published);
+    if !left_out.is_empty() {
+        add(
+            Severity::Warning,
+            format!(
+                "itunes:keywords holds at most {} characters, so {} are left out",
+                crate::keywords::MAX_FEED_LENGTH,
+                left_out.join(", ")
+            ),
+        );
+    }
+}
+
+fn check_subtitle<F>(field: &str, value: &str, add: &mut F)
+where
+    F: FnMut(Severity, String),
+{
+    if value.chars().count() > MAX_SUBTITLE {
+        add(
+            Severity::Warning,
+            format!(
+                "{} is longer than {} characters, Apple Podcasts cuts it off",
+                field, MAX_SUBTITLE
+            ),
+        );
+    }
+    check_plain_text(field, value, add);
+}
+
+/// Fields published as plain text show HTML as literal tags.
+fn check_plain_text<F>(field: &str, value: &str, add: &mut F)
+where
+    F: FnMut(Severity, String),
+{
+    if contains_html(value) {
+        add(
+            Severity::Warning,
+            format!("{} contains HTML, which apps show as text", field),
+        );
+    }
+}
+
+fn contains_html(value: &str) -> bool {
+    value.match_indices('<').any(|(index, _)| {
+        let rest = &value[index + 1..];
+        let tag = rest.trim_start_matches('/');
+        tag.starts_with(|c: char| c.is_ascii_alphabetic())
+            && tag
+                .find('>')
+                .map(|end| !tag[..end].contains('<'))
+                .unwrap_or(false)
+    })
+}
+
+/// `value` cut at a word boundary to at most `max` characters, ellipsis
+/// included, or `None` when it already fits.
+pub fn truncate(value: &str, max: usize) -> Option<String> {
+    if value.chars().count() <= max {
+        return None;
+    }
+    let end = value
+        .char_indices()
+        .nth(max - 1)
+        .map(|(index, _)| index)
+        .unwrap_or(value.len());
+    let cut = &value[..end];
+    let cut = match cut.rfind(char::is_whitespace) {
+        Some(space) if space > 0 => &cut[..space],
+        _ => cut,
+    };
+    Some(format!(
+        "{}…",
+        cut.trim_end_matches(|c: char| c.is_whitespace() || c.is_ascii_punctuation())
+    ))
+}
+
+fn check_url<F>(field: &str, value: &str, add: &mut F)
+where
+    F: FnMut(Severity, String),
+{
+    if let Some((severity, message)) = scaffolding(field, value) {
+        add(severity, message);
+    } else if !(value.starts_with("https://") || value.starts_with("http://")) {
+        add(
+            Severity::Error,
+            format!("{} '{}' is not an http(s) URL", field, value),
+        );
+    } else if let Some(host) = example_host(value) {
+        add(
+            Severity::Warning,
+            format!("{} is on {}, which is only for examples", field, host),
+        );
+    }
+}
+
+/// A channel that isn't explicit can't have explicit episodes: Apple
+/// Podcasts rejects shows whose episodes or text say otherwise.
+fn check_explicit(channel: &ChannelDetails, episodes: &[Episode]) -> Vec<Issue> {
+    use Severity::*;
+    let mut issues = Vec::new();
+    if channel.explicit {
+        return issues;
+    }
+
+    let explicit: Vec<&Episode> = episodes
+        .iter()
+        .filter(|episode| episode.explicit == Some(true))
+        .collect();
+    if !explicit.is_empty() {
+        issues.push(Issue {
+            severity: Warning,
+            episodes: explicit.iter().map(|episode| episode.id.clone()).collect(),
+            message: format!(
+                "{} episodes are explicit but the channel isn't, set explicit on the channel",
+                explicit.len()
+            ),
+        });
+    }
+
+    let terms: Vec<String> = if channel.explicit_terms.is_empty() {
+        EXPLICIT_TERMS.iter().map(|term| words(term)).collect()
+    } else {
+        channel
+            .explicit_terms
+            .iter()
+            .map(|term| words(term))
+            .collect()
+    };
+    let flagged = |value: &str| {
+        let text = words(value);
+        terms
+            .iter()
+            .find(|term| !term.trim().is_empty() && text.contains(term.as_str()))
+            .map(|term| term.trim().to_owned())
+    };
+
+    for (field, value) in [
+        ("title", &channel.title),
+        ("description", &channel.description),
+        ("summary", &channel.summary),
+    ] {
+        if let Some(term) = flagged(value) {
+            issues.push(Issue::channel(
+                Warning,
+                format!(
+                    "channel {} contains '{}' but the channel isn't explicit",
+                    field, term
+                ),
+            ));
+        }
+    }
+    for episode in episodes {
+        if episode.explicit == Some(true) {
+            continue;
+        }
+        for (field, value) in [
+            ("title", &episode.title),
+            ("description", &episode.description),
+            ("summary", &episode.summary),
+        ] {
+            if let Some(term) = flagged(value) {
+                issues.push(Issue::episode(
+                    Warning,
+                    episode,
+                    format!(
+                        "{} contains '{}' but the episode isn't explicit",
+                        field, term
+                    ),
+                ));
+            }
+        }
+    }
+    issues
+}
+
+/// `value` as lowercase words between single spaces, with a space before
+/// and after, so `contains` finds whole words and phrases.
+pub fn words(value: &str) -> String {
+    let mut text = String::from(" ");
+    for word in value
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        text.push_str(word);
+        text.push(' ');
+    }
+    text
+}
+
+/// Text left over from scaffolding in `value`: placeholders and filler are
+/// errors, and words like `TODO` are warnings.
+fn scaffolding(field: &str, value: &str) -> Option<(Severity, String)> {
+    let lowercase = value.to_lowercase();
+    if let Some(filler) = FILLER.iter().find(|filler| lowercase.contains(*filler)) {
+        let found = &value[lowercase.find(filler).unwrap_or(0)..][..filler.len()];
+        return Some((
+            Severity::Error,
+            format!("{} still contains the '{}' placeholder", field, found),
+        ));
+    }
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .find(|word| UNFINISHED.contains(word))
+        .map(|word| {
+            (
+                Severity::Warning,
+                format!("{} contains '{}', it may not be finished", field, word),
+            )
+        })
+}
+
+/// The example host `url` is on, if it is on one.
+fn example_host(url: &str) -> Option<&'static str> {
+    let host = url
+        .split("://")
+        .nth(1)?
+        .split(['/', '?', '#', ':'])
+        .next()?;
+    let host = host.to_lowercase();
+    EXAMPLE_HOSTS
+        .iter()
+        .copied()
+        .find(|example| host == *example || host.ends_with(&format!(".{}", example)))
+}
+
+/// Apple requires square artwork between 1400 and 3000 pixels.
+async fn check_artwork(url: &str, client: &HttpClient) -> Vec<Issue> {
+    let response = match client.get(url).await {
+        Ok(response) if response.status.is_success() => response,
+        Ok(response) => {
+            return vec![Issue::channel(
+                Severity::Error,
+                format!("channel image returned HTTP {}", response.status.as_u16()),
+            )]
+        }
+        Err(e) => {
+            return vec![Issue::channel(
+                Severity::Error,
+                format!("unable to download channel image: {}", e),
+            )]
+        }
+    };
+
+    artwork_problems(&response.body)
+        .into_iter()
+        .map(|problem| Issue::channel(Severity::Error, format!("channel image {}", problem)))
+        .collect()
+}
+
+/// Why directories would reject `bytes` as show or episode artwork, like
+/// "is 800x600, it must be square...".
+pub fn artwork_problems(bytes: &[u8]) -> Vec<String> {
+    let mut problems: Vec<String> = images::problems(bytes)
+        .into_iter()
+        .map(|problem| problem.to_string())
+        .collect();
+    let (width, height) = match images::dimensions(bytes) {
+        Some(dimensions) => dimensions,
+        None => return problems,
+    };
+    debug!("Image is {}x{}", width, height);
+
+    if width != height || !(MIN_ARTWORK..=MAX_ARTWORK).contains(&width) {
+        problems.push(format!(
+            "is {}x{}, it must be square and between {} and {} pixels",
+            width, height, MIN_ARTWORK, MAX_ARTWORK
+        ));
+    }
+    problems
+}
+
+/// Check the format of each episode image that isn't the channel's.
+/// Images that can't be downloaded are left to `check-links`.
+async fn check_episode_artwork(
+    channel: &ChannelDetails,
+    episodes: &[Episode],
+    client: &HttpClient,
+) -> Vec<Issue> {
+    let mut by_image: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for episode in episodes {
+        if episode.image != channel.image {
+            by_image
+                .entry(episode.image.as_str())
+                .or_default()
+                .push(episode.id.clone());
+        }
+    }
+
+    let mut issues = Vec::new();
+    for (url, ids) in by_image {
+        let body = match client.get(url).await {
+            Ok(response) if response.status.is_success() => response.body,
+            _ => continue,
+        };
+        for problem in images::problems(&body) {
+            issues.push(Issue {
+                severity: Severity::Error,
+                episodes: ids.clone(),
+                message: format!("episode image {} {}", url, problem),
+            });
+        }
+    }
+    issues
+}
+
+fn is_email(value: &str) -> bool {
+    match value.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !value.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+/// Apps sort and de-duplicate episodes by different keys: some by pubDate,
+/// some by GUID or enclosure URL, some by season and episode number. Report
+/// episodes that share one of those keys, and numbering that contradicts
+/// the release dates.
+/// Fill the optional fields an episode's YAML is missing with placeholders,
+/// so a lenient render can still show it. Returns the fields it filled.
+pub fn fill_placeholders(
+    episode: &mut serde_yaml::Value,
+    channel: &ChannelDetails,
+) -> Vec<&'static str> {
+    let mapping = match episode.as_mapping_mut() {
+        Some(mapping) => mapping,
+        None => return Vec::new(),
+    };
+
+    let defaults = [
+        ("summary", serde_yaml::Value::from(PLACEHOLDER)),
+        ("description", serde_yaml::Value::from(PLACEHOLDER)),
+        ("image", serde_yaml::Value::from(channel.image.as_str())),
+        ("keywords", serde_yaml::Value::Sequence(Vec::new())),
+    ];
+    let mut filled = Vec::new();
+    for (field, default) in defaults {
+        let missing = mapping
+            .get(field)
+            .map(|value| value.is_null())
+            .unwrap_or(true);
+        if missing {
+            mapping.insert(field.into(), default);
+            filled.push(field);
+        }
+    }
+    filled
+}
+
+/// Fields of `episode` that still have placeholder text.
+pub fn placeholder_fields(episode: &Episode) -> Vec<&'static str> {
+    [
+        ("title", Some(&episode.title)),
+        ("description", Some(&episode.description)),
+        ("summary", Some(&episode.summary)),
+        ("link", episode.link.as_ref()),
+    ]
+    .into_iter()
+    .filter(|(field, value)| {
+        matches!(
+            value.and_then(|value| scaffolding(field, value)),
+            Some((Severity::Error, _))
+        )
+    })
+    .map(|(field, _)| field)
+    .collect()
+}
+
+/// Problems that editing `before` into `after` brings in: with the episode
+/// itself, or with how it is ordered among `others`, whose GUIDs are
+/// filled in. Problems that were already there are left out.
+pub fn check_edit(
+    medium: Medium,
+    numbering: Numbering,
+    publishing: &PublishingConfig,
+    timezone: Tz,
+    before: &Episode,
+    after: &Episode,
+    others: &[Episode],
+) -> Vec<Issue> {
+    let with = |episode: &Episode| {
+        let mut episode = episode.clone();
+        if let Err(e) = guids::fill(publishing, timezone, &mut episode) {
+            return vec![Issue::episode(Severity::Error, &episode, e.localized())];
+        }
+        let mut episodes = others.to_vec();
+        episodes.push(episode.clone());
+        let mut issues = check_episode(&episode, numbering);
+        issues.extend(check_ordering(medium, numbering, &episodes));
+        issues
+    };
+    let existing = with(before);
+    with(after)
+        .into_iter()
+        .filter(|issue| !existing.contains(issue))
+        .collect()
+}
+
+/// Problems with how episodes are told apart and ordered. Albums and
+/// audiobooks are often released all at once, so for those episodes may
+/// share a pubDate, but have to be numbered 1, 2, 3... in each season.
+/// Episode numbers are unique in each season, or in the show when
+/// `numbering` is global, and go up with the release date. GUIDs are
+/// compared as published, so `episodes` should have theirs filled in.
+pub fn check_ordering(medium: Medium, numbering: Numbering, episodes: &[Episode]) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    let numbered_parts = match medium {
+        Medium::Music => Some("tracks"),
+        Medium::Audiobook => Some("chapters"),
+        Medium::Podcast | Medium::Newsletter => None,
+    };
+    match numbered_parts {
+        Some(parts) => check_numbering(&mut issues, episodes, parts),
+        None => duplicates(&mut issues, episodes, "pubDate", |episode| {
+            episode.released_at.timestamp().to_string()
+        }),
+    }
+    duplicates(&mut issues, episodes, "GUID", |episode| {
+        guids::guid(episode).to_owned()
+    });
+    duplicates(&mut issues, episodes, "enclosure URL", |episode| {
+        episode.media.url.clone()
+    });
+    // The season only tells episodes apart when each starts from 1
+    let count = |episode: &Episode| match numbering {
+        Numbering::PerSeason => (episode.season, episode.episode_number),
+        Numbering::Global | Numbering::None => (0, episode.episode_number),
+    };
+    match numbering {
+        Numbering::PerSeason => duplicates(
+            &mut issues,
+            episodes,
+            "season and episode number",
+            |episode| format!("S{}E{}", episode.season, episode.episode_number),
+        ),
+        Numbering::Global => duplicates(&mut issues, episodes, "episode number", |episode| {
+            episode.episode_number.to_string()
+        }),
+        Numbering::None => {}
+    }
+
+    let mut numbered: Vec<&Episode> = match numbering {
+        Numbering::None => Vec::new(),
+        _ => episodes.iter().collect(),
+    };
+    numbered.sort_by_key(|episode| count(episode));
+    for pair in numbered.windows(2) {
+        let (earlier, later) = (pair[0], pair[1]);
+        if count(earlier) == count(later) {
+            continue;
+        }
+        if later.released_at < earlier.released_at {
+            issues.push(Issue {
+                severity: Severity::Error,
+                episodes: vec![earlier.id.clone(), later.id.clone()],
+                message: format!(
+                    "S{}E{} is released {} but S{}E{} is released later, on {}",
+                    later.season,
+                    later.episode_number,
+                    later.released_at.format("%Y-%m-%d %H:%M"),
+                    earlier.season,
+                    earlier.episode_number,
+                    earlier.released_at.format("%Y-%m-%d %H:%M"),
+                ),
+            });
+        }
+    }
+
+    issues
+}
+
+/// Warn about the numbers missing from each season, and about numbering
+/// from 0.
+fn check_numbering(issues: &mut Vec<Issue>, episodes: &[Episode], parts: &str) {
+    let mut seasons: BTreeMap<u64, BTreeSet<u64>> = BTreeMap::new();
+    for episode in episodes {
+        seasons
+            .entry(episode.season)
+            .or_default()
+            .insert(episode.episode_number);
+    }
+
+    for (season, numbers) in seasons {
+        let last = numbers.iter().next_back().copied().unwrap_or_default();
+        let mut missing: Vec<(u64, u64)> = Vec::new();
+        for number in (1..last).filter(|number| !numbers.contains(number)) {
+            match missing.last_mut() {
+                Some((_, end)) if *end + 1 == number => *end = number,
+                _ => missing.push((number, number)),
+            }
+        }
+        let ids = || {
+            episodes
+                .iter()
+                .filter(|episode| episode.season == season)
+                .map(|episode| episode.id.clone())
+                .collect()
+        };
+        if numbers.contains(&0) {
+            issues.push(Issue {
+                severity: Severity::Error,
+                episodes: ids(),
+                message: format!("{} of season {} are numbered from 0", parts, season),
+            });
+        }
+        if !missing.is_empty() {
+            issues.push(Issue {
+                severity: Severity::Warning,
+                episodes: ids(),
+                message: format!(
+                    "{} of season {} skip number(s) {}",
+                    parts,
+                    season,
+                    missing
+                        .iter()
+                        .map(|(start, end)| {
+                            if start == end {
+                                start.to_string()
+                            } else {
+                                format!("{}-{}", start, end)
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            });
+        }
+    }
+}
+
+fn duplicates<F>(issues: &mut Vec<Issue>, episodes: &[Episode], what: &str, key: F)
+where
+    F: Fn(&Episode) -> String,
+{
+    let mut by_key: HashMap<String, Vec<String>> = HashMap::new();
+    let mut order = Vec::new();
+    for episode in episodes {
+        let key = key(episode);
+        let ids = by_key.entry(key.clone()).or_default();
+        if ids.is_empty() {
+            order.push(key);
+        }
+        ids.push(episode.id.clone());
+    }
+
+    for key in order {
+        let ids = &by_key[&key];
+        if ids.len() > 1 {
+            issues.push(Issue {
+                severity: Severity::Error,
+                episodes: ids.clone(),
+                message: format!("{} episodes share the same {}", ids.len(), what),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episode(id: &str, number: u64, released_at: &str) -> Episode {
+        let mut episode = Episode::make_test(id);
+        episode.episode_number = number;
+        episode.released_at = released_at.parse().unwrap();
+        episode.media.url = format!("https://example.com/{}.mp3", id);
+        episode
+    }
+
+    fn shared_guids(issues: &[Issue]) -> Vec<Vec<String>> {
+        issues
+            .iter()
+            .filter(|issue| issue.message.ends_with("share the same GUID"))
+            .map(|issue| issue.episodes.clone())
+            .collect()
+    }
+
+    #[test]
+    fn episodes_are_told_apart_by_their_published_guid() {
+        let first = episode("first", 1, "2022-09-01T12:00:00Z");
+        let mut second = episode("second", 2, "2022-09-08T12:00:00Z");
+        let episodes = [first.clone(), second.clone()];
+        let issues = check_ordering(Medium::Podcast, Numbering::PerSeason, &episodes);
+        assert!(issues.is_empty(), "{:?}", issues);
+
+        // Imported from another host with the GUID apps know it by
+        second.guid = Some("first".to_owned());
+        let issues = check_ordering(Medium::Podcast, Numbering::PerSeason, &[first, second]);
+        assert_eq!(shared_guids(&issues), [["first", "second"]]);
+    }
+
+    #[test]
+    fn edits_that_make_guids_collide_are_found() {
+        let publishing: PublishingConfig = serde_yaml::from_str(
+            "prefix: show\nguidStrategy: template\nguidTemplate: https://example.com/{date}\n",
+        )
+        .unwrap();
+        let mut other = episode("other", 1, "2022-09-01T09:00:00Z");
+        guids::fill(&publishing, Tz::UTC, &mut other).unwrap();
+        let before = episode("edited", 2, "2022-09-08T12:00:00Z");
+        let mut after = before.clone();
+        after.released_at = "2022-09-01T18:00:00Z".parse().unwrap();
+
+        let others = [other];
+        let check = |after: &Episode| {
+            let (medium, numbering) = (Medium::Podcast, Numbering::PerSeason);
+            check_edit(medium, numbering, &publishing, Tz::UTC, &before, after, &others)
+        };
+        assert!(check(&before).is_empty());
+        assert_eq!(shared_guids(&check(&after)), [["other", "edited"]]);
+    }
+}
@@ -0,0 +1,291 @@
+//! Sandboxed WASM render plugins.
+//!
+//! Plugins listed under `wasmPlugins` in `channel.yaml` are loaded with
+//! wasmtime when the crate is built with the `wasm-plugins` feature. They
+//! get no WASI imports, so they cannot touch the filesystem or network.
+//! Each plugin runs with a fuel budget for the whole render, gets at most
+//! 64 MiB of memory, and is stopped when a call takes longer than 10 seconds.
+//!
+//! Host API. A plugin module must export:
+//!
+//! - `memory`: its linear memory.
+//! - `alloc(len: i32) -> i32`: returns a pointer to `len` writable bytes.
+//!
+//! and may export any of:
+//!
+//! - `transform_episode(ptr: i32, len: i32) -> i64`: receives an episode as
+//!   JSON and returns the (possibly modified) episode as JSON.
+//! - `channel_elements(ptr: i32, len: i32) -> i64`: receives the channel as
+//!   JSON and returns a JSON array of elements to add to `<channel>`.
+//! - `item_elements(ptr: i32, len: i32) -> i64`: receives an episode as JSON
+//!   and returns a JSON array of elements to add to its `<item>`.
+//!
+//! Results are packed as `(ptr << 32) | len`; returning `0` means "no
+//! change". Elements look like
+//! `{ "name": "network:id", "value": "1234", "attributes": { "k": "v" } }`;
+//! element and attribute names have to be XML names, like `network:id`.
+//!
+//! The host provides `env.log(ptr: i32, len: i32)` for debug logging.
+
+use crate::config::*;
+#[cfg(feature = "wasm-plugins")]
+use crate::xml::ExtraElement;
+use crate::xml::RenderExtras;
+use crate::CliError;
+use std::path::Path;
+
+#[cfg(feature = "wasm-plugins")]
+pub fn apply_plugins(
+    channel_dir: &Path,
+    channel_config: &ChannelConfig,
+    episodes: &mut [Episode],
+) -> Result<RenderExtras, CliError> {
+    let mut extras = RenderExtras::default();
+
+    for plugin_config in &channel_config.wasm_plugins {
+        let path = channel_dir.join(&plugin_config.path);
+        let mut plugin = sandbox::Plugin::load(&path, plugin_config.fuel)?;
+
+        for episode in episodes.iter_mut() {
+            if let Some(transformed) = plugin.call_json("transform_episode", &*episode)? {
+                *episode = transformed;
+            }
+        }
+
+        if let Some(elements) = plugin.call_json("channel_elements", &channel_config.channel)? {
+            check_names(&path, &elements)?;
+            extras.channel.extend::<Vec<_>>(elements);
+        }
+
+        for episode in episodes.iter() {
+            if let Some(elements) = plugin.call_json::<_, Vec<_>>("item_elements", episode)? {
+                check_names(&path, &elements)?;
+                extras
+                    .items
+                    .entry(episode.id.clone())
+                    .or_default()
+                    .extend(elements);
+            }
+        }
+    }
+
+    Ok(extras)
+}
+
+/// Fail on elements whose name, or an attribute's, isn't an XML name, so a
+/// plugin can't write malformed XML into the feed.
+#[cfg(feature = "wasm-plugins")]
+fn check_names(plugin: &Path, elements: &[ExtraElement]) -> Result<(), CliError> {
+    for element in elements {
+        let mut names = std::iter::once(&element.name).chain(element.attributes.keys());
+        if let Some(name) = names.find(|name| !is_qname(name)) {
+            return Err(CliError::WasmError(format!(
+                "{}: '{}' is not an XML name",
+                plugin.display(),
+                name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// A name with an optional prefix, like `network:id`.
+#[cfg(feature = "wasm-plugins")]
+fn is_qname(name: &str) -> bool {
+    match name.split_once(':') {
+        Some((prefix, local)) => is_ncname(prefix) && is_ncname(local),
+        None => is_ncname(name),
+    }
+}
+
+/// A name without a colon: a letter or `_`, then letters, digits, `_`,
+/// `-`, and `.`.
+#[cfg(feature = "wasm-plugins")]
+fn is_ncname(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_alphabetic() || first == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+#[cfg(not(feature = "wasm-plugins"))]
+pub fn apply_plugins(
+    _channel_dir: &Path,
+    channel_config: &ChannelConfig,
+    _episodes: &mut [Episode],
+) -> Result<RenderExtras, CliError> {
+    if !channel_config.wasm_plugins.is_empty() {
+        return Err(CliError::WasmError(
+            "wasmPlugins are configured, but podcast-ctl was built without the `wasm-plugins` feature"
+                .to_owned(),
+        ));
+    }
+
+    Ok(RenderExtras::default())
+}
+
+#[cfg(feature = "wasm-plugins")]
+mod sandbox {
+    use crate::CliError;
+    use log::{debug, info};
+    use serde::{de::DeserializeOwned, Serialize};
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use wasmtime::{
+        AsContext, Caller, Config, Engine, Instance, Linker, Memory, Module, Store, StoreLimits,
+        StoreLimitsBuilder,
+    };
+
+    const MAX_MEMORY: usize = 64 << 20;
+    /// How often the engine's epoch moves on, and how many epochs a call
+    /// gets before it is stopped.
+    const EPOCH: Duration = Duration::from_millis(100);
+    const CALL_EPOCHS: u64 = 100;
+
+    pub struct Plugin {
+        name: String,
+        store: Store<StoreLimits>,
+        instance: Instance,
+        memory: Memory,
+        /// Stops the thread moving the epoch on
+        stopped: Arc<AtomicBool>,
+    }
+
+    impl Plugin {
+        pub fn load(path: &Path, fuel: u64) -> Result<Self, CliError> {
+            let name = path.display().to_string();
+            info!("Loading WASM plugin {}", name);
+
+            let mut config = Config::new();
+            config.consume_fuel(true);
+            config.epoch_interruption(true);
+            let engine = Engine::new(&config).map_err(|e| wasm_error(&name, e))?;
+            let module = Module::from_file(&engine, path).map_err(|e| wasm_error(&name, e))?;
+
+            let mut linker = Linker::new(&engine);
+            linker
+                .func_wrap(
+                    "env",
+                    "log",
+                    |mut caller: Caller<'_, StoreLimits>, ptr: i32, len: i32| {
+                        if let Some(wasmtime::Extern::Memory(memory)) = caller.get_export("memory")
+                        {
+                            if let Some(buffer) = read(&caller, memory, ptr as u32, len as u32) {
+                                debug!("plugin: {}", String::from_utf8_lossy(&buffer));
+                            }
+                        }
+                    },
+                )
+                .map_err(|e| wasm_error(&name, e))?;
+
+            let limits = StoreLimitsBuilder::new()
+                .memory_size(MAX_MEMORY)
+                .instances(1)
+                .build();
+            let mut store = Store::new(&engine, limits);
+            store.limiter(|limits| limits);
+            store.add_fuel(fuel).map_err(|e| wasm_error(&name, e))?;
+            store.set_epoch_deadline(CALL_EPOCHS);
+
+            let stopped = Arc::new(AtomicBool::new(false));
+            let ticking = (engine.clone(), stopped.clone());
+            std::thread::spawn(move || {
+                let (engine, stopped) = ticking;
+                while !stopped.load(Ordering::Relaxed) {
+                    std::thread::sleep(EPOCH);
+                    engine.increment_epoch();
+                }
+            });
+
+            let instance = linker
+                .instantiate(&mut store, &module)
+                .map_err(|e| wasm_error(&name, e))?;
+            let memory = instance
+                .get_memory(&mut store, "memory")
+                .ok_or_else(|| wasm_error(&name, "module does not export `memory`"))?;
+
+            Ok(Self {
+                name,
+                store,
+                instance,
+                memory,
+                stopped,
+            })
+        }
+
+        /// Call `export` with `input` serialized as JSON. Returns `None` when
+        /// the plugin doesn't export the function or returns no result.
+        pub fn call_json<I, O>(&mut self, export: &str, input: &I) -> Result<Option<O>, CliError>
+        where
+            I: Serialize,
+            O: DeserializeOwned,
+        {
+            let function = match self
+                .instance
+                .get_typed_func::<(i32, i32), i64, _>(&mut self.store, export)
+            {
+                Ok(function) => function,
+                Err(_) => return Ok(None),
+            };
+
+            let body = serde_json::to_vec(input)?;
+            let ptr = self.write(&body)?;
+            self.store.set_epoch_deadline(CALL_EPOCHS);
+            let packed = function
+                .call(&mut self.store, (ptr, body.len() as i32))
+                .map_err(|e| wasm_error(&self.name, e))?;
+
+            if packed == 0 {
+                return Ok(None);
+            }
+
+            let packed = packed as u64;
+            let (out_ptr, out_len) = ((packed >> 32) as u32, packed as u32);
+            let buffer = read(&self.store, self.memory, out_ptr, out_len).ok_or_else(|| {
+                wasm_error(&self.name, "the result is outside of the plugin's memory")
+            })?;
+
+            Ok(Some(serde_json::from_slice(&buffer)?))
+        }
+
+        fn write(&mut self, bytes: &[u8]) -> Result<i32, CliError> {
+            let alloc = self
+                .instance
+                .get_typed_func::<i32, i32, _>(&mut self.store, "alloc")
+                .map_err(|e| wasm_error(&self.name, e))?;
+            let ptr = alloc
+                .call(&mut self.store, bytes.len() as i32)
+                .map_err(|e| wasm_error(&self.name, e))?;
+            self.memory
+                .write(&mut self.store, ptr as u32 as usize, bytes)
+                .map_err(|e| wasm_error(&self.name, e))?;
+            Ok(ptr)
+        }
+    }
+
+    impl Drop for Plugin {
+        fn drop(&mut self) {
+            self.stopped.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// `len` bytes of `memory` from `ptr`, checked to be inside it before
+    /// anything is allocated for them.
+    fn read(store: impl AsContext, memory: Memory, ptr: u32, len: u32) -> Option<Vec<u8>> {
+        let (ptr, len) = (ptr as usize, len as usize);
+        if ptr.checked_add(len)? > memory.data_size(&store) {
+            return None;
+        }
+        let mut buffer = vec![0; len];
+        memory.read(&store, ptr, &mut buffer).ok()?;
+        Some(buffer)
+    }
+
+    fn wasm_error<E: std::fmt::Display>(name: &str, error: E) -> CliError {
+        CliError::WasmError(format!("{}: {}", name, error))
+    }
+}
@@ -0,0 +1,104 @@
+//! Change detection for `render-channel --watch` and `watch-inbox`, with
+//! the platform's filesystem notifications. Hidden files, like editor
+//! swap files and `episodes/.trash/`, are ignored.
+
+use crate::CliError;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+/// Notifications of changes to files under the watched paths.
+pub struct Watcher {
+    // Stops watching when dropped
+    _watcher: RecommendedWatcher,
+    changes: UnboundedReceiver<()>,
+}
+
+/// A watched directory, or a file, which is watched through its
+/// directory so editors that save by replacing it are still noticed.
+enum Watched {
+    Dir(PathBuf),
+    File(PathBuf),
+}
+
+impl Watched {
+    fn matches(&self, path: &Path) -> bool {
+        match self {
+            Watched::Dir(dir) => match path.strip_prefix(dir) {
+                Ok(inside) => !inside
+                    .components()
+                    .any(|component| component.as_os_str().to_string_lossy().starts_with('.')),
+                Err(_) => false,
+            },
+            Watched::File(file) => path == file,
+        }
+    }
+}
+
+impl Watcher {
+    /// Watch `paths`, directories and everything under them, or files.
+    pub fn new(paths: &[PathBuf]) -> Result<Self, CliError> {
+        let watched: Vec<Watched> = paths
+            .iter()
+            .map(|path| {
+                let path = path.canonicalize()?;
+                Ok(if path.is_dir() {
+                    Watched::Dir(path)
+                } else {
+                    Watched::File(path)
+                })
+            })
+            .collect::<Result<_, io::Error>>()?;
+
+        let targets: Vec<(PathBuf, RecursiveMode)> = watched
+            .iter()
+            .map(|watched| match watched {
+                Watched::Dir(dir) => (dir.clone(), RecursiveMode::Recursive),
+                Watched::File(file) => (
+                    file.parent().unwrap_or(file).to_path_buf(),
+                    RecursiveMode::NonRecursive,
+                ),
+            })
+            .collect();
+
+        let (sender, changes) = mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(e) => return log::warn!("Unable to watch for changes: {}", e),
+                };
+                if matches!(event.kind, EventKind::Access(_)) {
+                    return;
+                }
+                if event
+                    .paths
+                    .iter()
+                    .any(|path| watched.iter().any(|watched| watched.matches(path)))
+                {
+                    let _ = sender.send(());
+                }
+            })
+            .map_err(io::Error::other)?;
+        for (target, mode) in &targets {
+            watcher.watch(target, *mode).map_err(io::Error::other)?;
+        }
+
+        Ok(Watcher {
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    /// Wait until something changes, then until nothing has for
+    /// `debounce`, so an editor saving several files, or a file being
+    /// copied in, triggers one render.
+    pub async fn changed(&mut self, debounce: Duration) {
+        if self.changes.recv().await.is_none() {
+            return;
+        }
+        while let Ok(Some(())) = tokio::time::timeout(debounce, self.changes.recv()).await {}
+    }
+}
@@ -0,0 +1,40 @@
+//! WebSub (formerly PubSubHubbub), so apps that subscribe through a hub
+//! hear about new episodes as soon as they are published instead of on
+//! their next poll. With `publishing.websubHub` set, feeds name the hub and
+//! their own URL, and `render-channel --upload` tells the hub about each
+//! feed it replaced:
+//!
+//! ```yaml
+//! publishing:
+//!   websubHub: https://pubsubhubbub.appspot.com/
+//! ```
+//!
+//! Like metrics, this is best effort: a publish that worked isn't failed
+//! because the hub couldn't be reached; apps still poll.
+
+use crate::events;
+use crate::http::HttpClient;
+use crate::i18n::t;
+use reqwest::Method;
+
+/// Tell `hub` that the feeds at `topics` changed, warning about each
+/// notification the hub doesn't accept.
+pub async fn publish(client: &HttpClient, hub: &str, topics: &[String]) {
+    for topic in topics {
+        let form = [("hub.mode", "publish"), ("hub.url", topic.as_str())];
+        let response = client
+            .send(hub, || client.request(Method::POST, hub).form(&form))
+            .await;
+        let problem = match response {
+            Ok(response) if response.status.is_success() => continue,
+            Ok(response) => format!("HTTP {}", response.status),
+            Err(e) => e.localized(),
+        };
+        events::warn(t!(
+            "websub-failed",
+            hub = hub,
+            url = topic.as_str(),
+            detail = problem
+        ));
+    }
+}
@@ -1,60 +1,225 @@
 use crate::config::*;
-use chrono::Utc;
-use quick_xml::events::{BytesDecl, BytesText, Event};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use quick_xml::events::{BytesCData, BytesDecl, BytesText, Event};
 use quick_xml::writer::Writer;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 use std::io::Cursor;
+use uuid::Uuid;
+
+/// The stylesheet published with `defaultStylesheet: true`.
+pub const DEFAULT_STYLESHEET: &str = include_str!("../templates/podcast.xsl");
+
+#[derive(Debug, Default)]
+pub struct FeedOptions {
+    /// Elements added by render plugins
+    pub extras: RenderExtras,
+    /// Emitted as an `<?xml-stylesheet?>` processing instruction
+    pub stylesheet_href: Option<String>,
+    /// Other versions of this feed, emitted as `<atom:link rel="alternate">`
+    pub related_feeds: Vec<RelatedFeed>,
+    /// Links between the feed and its archive pages (RFC 5005), emitted as
+    /// `<atom:link>` with the link's `rel`
+    pub archive_links: Vec<ArchiveLink>,
+    /// Marks an archive page with `<fh:archive/>`, so apps know its items
+    /// won't change
+    pub archive: bool,
+    /// `podcast:guid`, when channel.yaml doesn't set one
+    pub podcast_guid: Option<String>,
+    /// Files published alongside each episode, keyed by episode id
+    pub item_assets: HashMap<String, ItemAssets>,
+    /// `lastBuildDate` of the channel, when not now
+    pub built_at: Option<DateTime<Utc>>,
+    /// Emitted as `<atom:link rel="hub">` and `rel="self"`
+    pub websub: Option<WebSub>,
+}
+
+/// A WebSub hub apps can subscribe to for updates to a feed.
+#[derive(Debug, Clone)]
+pub struct WebSub {
+    pub hub: String,
+    /// The feed's own URL, which subscriptions are for
+    pub self_url: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ItemAssets {
+    /// Emitted as `podcast:transcript`
+    pub transcripts: Vec<TranscriptLink>,
+    /// Emitted as `podcast:chapters`
+    pub chapters_url: Option<String>,
+    /// HTML show notes, emitted as `content:encoded`
+    pub show_notes: Option<String>,
+    /// Emitted as `podcast:soundbite`
+    pub soundbites: Vec<crate::chapters::Soundbite>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TranscriptLink {
+    pub url: String,
+    pub mime_type: String,
+    pub language: Option<String>,
+}
+
+/// Namespace for podcast:guid, from the Podcasting 2.0 specification.
+const PODCAST_GUID_NAMESPACE: Uuid = uuid::uuid!("ead4c236-bf58-58c6-a2c6-a6b28d128cb6");
+
+/// UUIDv5 of the feed URL without its scheme and trailing slashes, as the
+/// Podcasting 2.0 specification requires.
+pub fn podcast_guid(feed_url: &str) -> String {
+    let normalized = feed_url
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(feed_url)
+        .trim_end_matches('/');
+    Uuid::new_v5(&PODCAST_GUID_NAMESPACE, normalized.as_bytes()).to_string()
+}
+
+#[derive(Debug, Clone)]
+pub struct RelatedFeed {
+    pub href: String,
+    pub title: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ArchiveLink {
+    /// `current`, `prev-archive`, or `next-archive`
+    pub rel: &'static str,
+    pub href: String,
+}
+
+/// Additional elements injected into the feed by render plugins.
+#[derive(Debug, Default, Clone)]
+pub struct RenderExtras {
+    pub channel: Vec<ExtraElement>,
+    /// Keyed by episode id
+    pub items: HashMap<String, Vec<ExtraElement>>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExtraElement {
+    pub name: String,
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+}
 
 pub fn generate_podcast_xml(
-    channel_details: ChannelDetails,
-    episodes: Vec<Episode>,
+    channel_details: &ChannelDetails,
+    episodes: &[Episode],
+    options: &FeedOptions,
 ) -> Result<String, crate::CliError> {
+    let extras = &options.extras;
+    let built_at = options.built_at.unwrap_or_else(Utc::now);
+    let timezone = crate::dates::timezone(channel_details.timezone.as_deref())?;
+
+    let mut channel = channel_details.clone();
+    crate::urls::normalize_channel(&mut channel)?;
+    let channel_details = &channel;
+    let mut episodes = episodes.to_vec();
+    let mut item_assets = HashMap::new();
+    for episode in episodes.iter_mut() {
+        crate::urls::normalize_episode(episode)?;
+        episode.keywords = crate::keywords::episode_keywords(&channel_details.keywords, episode);
+        if let Some(assets) = options.item_assets.get(&episode.id) {
+            let mut assets = assets.clone();
+            crate::urls::normalize_assets(&episode.id, &mut assets)?;
+            item_assets.insert(episode.id.clone(), assets);
+        }
+    }
+    let mut related_feeds = options.related_feeds.clone();
+    for related in related_feeds.iter_mut() {
+        related.href = crate::urls::normalize("related feed", &related.href)?;
+    }
+    let mut archive_links = options.archive_links.clone();
+    for link in archive_links.iter_mut() {
+        link.href = crate::urls::normalize("archive feed", &link.href)?;
+    }
+
+    // Newest first, whatever order the files were read in, so the same
+    // episodes always render the same feed
+    episodes.sort_by(|a, b| {
+        b.released_at
+            .cmp(&a.released_at)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    let published_at = episodes
+        .first()
+        .map(|episode| episode.released_at)
+        .unwrap_or(built_at);
     let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 4);
 
     writer
         .write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))
         .ok();
 
-    writer
+    if let Some(href) = &options.stylesheet_href {
+        writer
+            .write_event(Event::PI(BytesText::from_escaped(format!(
+                r#"xml-stylesheet type="text/xsl" href="{}""#,
+                quick_xml::escape::escape(href)
+            ))))
+            .ok();
+    }
+
+    let mut rss = writer
         .create_element("rss")
         .with_attribute(("xmlns:itunes", "http://www.itunes.com/dtds/podcast-1.0.dtd"))
         .with_attribute(("xmlns:content", "http://purl.org/rss/1.0/modules/content/"))
+        .with_attribute(("xmlns:podcast", "https://podcastindex.org/namespace/1.0"));
+    if !options.related_feeds.is_empty()
+        || !options.archive_links.is_empty()
+        || options.websub.is_some()
+    {
+        rss = rss.with_attribute(("xmlns:atom", "http://www.w3.org/2005/Atom"));
+    }
+    if options.archive {
+        rss = rss.with_attribute(("xmlns:fh", "http://purl.org/syndication/history/1.0"));
+    }
+    rss
         .with_attribute(("version", "2.0"))
         .write_inner_content(|writer| {
             writer
                 .create_element("channel")
                 .write_inner_content(|writer| {
                     add_text_element(writer, "title", &channel_details.title);
-                    add_text_element(
+                    add_cdata_element(
                         writer,
                         "description",
                         &comrak::markdown_to_html(&channel_details.title, &Default::default()),
                     );
                     if let Some(link) = &channel_details.link {
-                        add_text_element(writer, "link", &link);
+                        add_text_element(writer, "link", link);
                     }
-                    add_text_element(writer, "language", "en-us");
-                    add_text_element(writer, "copyright", "Copyright 2022");
+                    add_text_element(writer, "language", &channel_details.language);
+                    add_text_element(writer, "copyright", &channel_details.copyright);
                     add_text_element(
                         writer,
                         "lastBuildDate",
-                        &format!("{}", Utc::now().format("%a, %d %b %Y %T %z")),
+                        &crate::dates::feed_date(&built_at, timezone),
                     );
                     add_text_element(
                         writer,
                         "pubDate",
-                        &format!("{}", Utc::now().format("%a, %d %b %Y %T %z")),
+                        &crate::dates::feed_date(&published_at, timezone),
                     );
                     add_text_element(writer, "docs", "http://blogs.law.harvard.edu/tech/rss");
                     add_text_element(writer, "webMaster", &channel_details.owner.email);
-                    add_text_element(writer, "itunes:type", "Serial");
+                    add_text_element(
+                        writer,
+                        "itunes:type",
+                        channel_details.podcast_type.as_str(),
+                    );
 
                     add_text_element(writer, "itunes:author", &channel_details.owner.email);
                     add_text_element(
                         writer,
                         "itunes:subtitle",
-                        &channel_details.subtitle
+                        &crate::notes::markdown_to_plain_text(&channel_details.subtitle),
                     );
-                    add_text_element(
+                    add_cdata_element(
                         writer,
                         "itunes:summary",
                         &comrak::markdown_to_html(&channel_details.summary, &Default::default()),
@@ -82,11 +247,89 @@ pub fn generate_podcast_xml(
                     let image_url: &str = &channel_details.image;
                     writer
                         .create_element("itunes:image").with_attribute(("href", image_url)).write_empty().ok();
-                    writer
-                        .create_element("itunes:category").with_attribute(("text", "Fiction")).write_empty().ok();
+                    add_categories(writer, &channel_details.categories);
+                    add_keywords(writer, &channel_details.keywords);
+
+                    add_people(writer, &channel_details.hosts, &channel_details.guests);
+
+                    let guid = channel_details
+                        .podcast_guid
+                        .clone()
+                        .or_else(|| options.podcast_guid.clone());
+                    if let Some(guid) = guid {
+                        add_text_element(writer, "podcast:guid", &guid);
+                    }
+                    if let Some(medium) = channel_details.medium {
+                        add_text_element(writer, "podcast:medium", medium.as_str());
+                    }
+                    if let Some(locked) = channel_details.locked {
+                        writer
+                            .create_element("podcast:locked")
+                            .with_attribute(("owner", channel_details.owner.email.as_str()))
+                            .write_text_content(BytesText::new(if locked { "yes" } else { "no" }))
+                            .ok();
+                    }
+                    for funding in &channel_details.funding {
+                        writer
+                            .create_element("podcast:funding")
+                            .with_attribute(("url", funding.url.as_str()))
+                            .write_text_content(BytesText::new(&funding.title))
+                            .ok();
+                    }
+                    for trailer in &channel_details.trailers {
+                        add_trailer(writer, trailer, timezone);
+                    }
+
+                    for related in &related_feeds {
+                        writer
+                            .create_element("atom:link")
+                            .with_attribute(("href", related.href.as_str()))
+                            .with_attribute(("rel", "alternate"))
+                            .with_attribute(("type", "application/rss+xml"))
+                            .with_attribute(("title", related.title.as_str()))
+                            .write_empty()
+                            .ok();
+                    }
+                    for link in &archive_links {
+                        writer
+                            .create_element("atom:link")
+                            .with_attribute(("href", link.href.as_str()))
+                            .with_attribute(("rel", link.rel))
+                            .with_attribute(("type", "application/rss+xml"))
+                            .write_empty()
+                            .ok();
+                    }
+                    if let Some(websub) = &options.websub {
+                        writer
+                            .create_element("atom:link")
+                            .with_attribute(("href", websub.hub.as_str()))
+                            .with_attribute(("rel", "hub"))
+                            .write_empty()
+                            .ok();
+                        writer
+                            .create_element("atom:link")
+                            .with_attribute(("href", websub.self_url.as_str()))
+                            .with_attribute(("rel", "self"))
+                            .with_attribute(("type", "application/rss+xml"))
+                            .write_empty()
+                            .ok();
+                    }
+                    if options.archive {
+                        writer.create_element("fh:archive").write_empty().ok();
+                    }
+
+                    for extra in &extras.channel {
+                        add_extra_element(writer, extra);
+                    }
 
                     for episode in &episodes {
-                        episode.add_object(writer);
+                        let item_extras = extras
+                            .items
+                            .get(&episode.id)
+                            .map(|e| e.as_slice())
+                            .unwrap_or_default();
+                        let assets = item_assets.get(&episode.id);
+                        episode.add_object(writer, assets, item_extras, timezone);
                     }
 
                     Ok(())
@@ -99,6 +342,13 @@ pub fn generate_podcast_xml(
     Ok(String::from_utf8(writer.into_inner().into_inner())?)
 }
 
+/// Render a single `<item>`, for comparing episodes.
+pub fn generate_item_xml(episode: &Episode) -> Result<String, crate::CliError> {
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 4);
+    episode.add_object(&mut writer, None, &[], Tz::UTC);
+    Ok(String::from_utf8(writer.into_inner().into_inner())?)
+}
+
 fn add_text_element<W>(writer: &mut Writer<W>, key: &str, value: &str)
 where
     W: std::io::Write,
@@ -109,14 +359,154 @@ where
         .ok();
 }
 
+/// An element holding HTML, as CDATA so it isn't escaped a second time.
+fn add_cdata_element<W>(writer: &mut Writer<W>, key: &str, html: &str)
+where
+    W: std::io::Write,
+{
+    // `]]>` would end the section early, so split it across two
+    let html = html.replace("]]>", "]]]]><![CDATA[>");
+    writer
+        .create_element(key)
+        .write_inner_content(|writer| {
+            writer.write_event(Event::CData(BytesCData::new(html.as_str())))?;
+            Ok(())
+        })
+        .ok();
+}
+
+/// `itunes:keywords`, when there are any.
+fn add_keywords<W>(writer: &mut Writer<W>, keywords: &[String])
+where
+    W: std::io::Write,
+{
+    let (keywords, _) = crate::keywords::feed_keywords(keywords);
+    if !keywords.is_empty() {
+        add_text_element(writer, "itunes:keywords", &keywords);
+    }
+}
+
+/// Nested `itunes:category` elements, with the subcategories of a category
+/// grouped under it.
+fn add_categories<W>(writer: &mut Writer<W>, categories: &[String])
+where
+    W: std::io::Write,
+{
+    let mut grouped: Vec<(&str, Vec<&str>)> = Vec::new();
+    for category in categories {
+        let (parent, child) = crate::categories::split(category);
+        let index = match grouped.iter().position(|(name, _)| *name == parent) {
+            Some(index) => index,
+            None => {
+                grouped.push((parent, Vec::new()));
+                grouped.len() - 1
+            }
+        };
+        if let Some(child) = child {
+            grouped[index].1.push(child);
+        }
+    }
+
+    for (parent, children) in grouped {
+        let element = writer
+            .create_element("itunes:category")
+            .with_attribute(("text", parent));
+        if children.is_empty() {
+            element.write_empty().ok();
+        } else {
+            element
+                .write_inner_content(|writer| {
+                    for child in &children {
+                        writer
+                            .create_element("itunes:category")
+                            .with_attribute(("text", *child))
+                            .write_empty()
+                            .ok();
+                    }
+                    Ok(())
+                })
+                .ok();
+        }
+    }
+}
+
+fn add_people<W>(writer: &mut Writer<W>, hosts: &[Person], guests: &[Person])
+where
+    W: std::io::Write,
+{
+    let people = hosts
+        .iter()
+        .map(|person| (person, "host"))
+        .chain(guests.iter().map(|person| (person, "guest")));
+    for (person, default_role) in people {
+        let mut element = writer
+            .create_element("podcast:person")
+            .with_attribute(("role", person.role.as_deref().unwrap_or(default_role)));
+        if let Some(href) = &person.href {
+            element = element.with_attribute(("href", href.as_str()));
+        }
+        if let Some(img) = &person.img {
+            element = element.with_attribute(("img", img.as_str()));
+        }
+        element.write_text_content(BytesText::new(&person.name)).ok();
+    }
+}
+
+fn add_trailer<W>(writer: &mut Writer<W>, trailer: &ChannelTrailer, timezone: Tz)
+where
+    W: std::io::Write,
+{
+    let pubdate = crate::dates::feed_date(&trailer.released_at, timezone);
+    let length = trailer.bytes.0.to_string();
+    let mut element = writer
+        .create_element("podcast:trailer")
+        .with_attribute(("pubdate", pubdate.as_str()))
+        .with_attribute(("url", trailer.url.as_str()))
+        .with_attribute(("length", length.as_str()))
+        .with_attribute(("type", trailer.enclosure_type()));
+    let season = trailer.season.map(|season| season.to_string());
+    if let Some(season) = &season {
+        element = element.with_attribute(("season", season.as_str()));
+    }
+    element.write_text_content(BytesText::new(&trailer.title)).ok();
+}
+
+fn add_extra_element<W>(writer: &mut Writer<W>, extra: &ExtraElement)
+where
+    W: std::io::Write,
+{
+    let element = writer.create_element(&extra.name).with_attributes(
+        extra
+            .attributes
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str())),
+    );
+    match &extra.value {
+        Some(value) => element.write_text_content(BytesText::new(value)).ok(),
+        None => element.write_empty().ok(),
+    };
+}
+
 trait XmlOutput {
-    fn add_object<W>(&self, writer: &mut Writer<W>)
+    fn add_object<W>(
+        &self,
+        writer: &mut Writer<W>,
+        assets: Option<&ItemAssets>,
+        extras: &[ExtraElement],
+        timezone: Tz,
+    )
     where
         W: std::io::Write;
 }
 
 impl XmlOutput for Episode {
-    fn add_object<W>(&self, writer: &mut Writer<W>)
+    fn add_object<W>(
+        &self,
+        writer: &mut Writer<W>,
+        assets: Option<&ItemAssets>,
+        extras: &[ExtraElement],
+        timezone: Tz,
+    )
     where
         W: std::io::Write,
     {
@@ -124,39 +514,54 @@ impl XmlOutput for Episode {
             .create_element("item")
             .write_inner_content(|writer| {
                 add_text_element(writer, "title", &self.title);
-                add_text_element(writer, "itunes:subtitle", &self.summary);
+                add_text_element(
+                    writer,
+                    "itunes:subtitle",
+                    &crate::notes::markdown_to_plain_text(&self.summary),
+                );
                 if let Some(link) = &self.link {
-                    add_text_element(writer, "link", &link);
+                    add_text_element(writer, "link", link);
                 }
-                add_text_element(writer, "guid", &self.id);
+                let guid = crate::guids::guid(self);
+                let permalink = if crate::guids::is_permalink(guid) {
+                    "true"
+                } else {
+                    "false"
+                };
+                writer
+                    .create_element("guid")
+                    .with_attribute(("isPermaLink", permalink))
+                    .write_text_content(BytesText::new(guid))
+                    .ok();
                 let url: &str = &self.media.url;
-                let length: &str = &format!("{}", self.media.bytes);
+                let length: &str = &format!("{}", self.media.bytes.0);
                 writer
                     .create_element("enclosure")
                     .with_attribute(("url", url))
                     .with_attribute(("length", length))
-                    .with_attribute(("type", "audio/mpeg"))
+                    .with_attribute(("type", self.media.enclosure_type()))
                     .write_empty()
                     .ok();
                 add_text_element(
                     writer,
                     "pubDate",
-                    &format!("{}", self.released_at.format("%a, %d %b %Y %T %z")),
-                );
-                add_text_element(
-                    writer,
-                    "description",
-                    &comrak::markdown_to_html(&self.description, &Default::default()),
-                );
-                add_text_element(
-                    writer,
-                    "itunes:summary",
-                    &comrak::markdown_to_html(&self.description, &Default::default()),
+                    &crate::dates::feed_date(&self.released_at, timezone),
                 );
+                let no_assets = ItemAssets::default();
+                let assets = assets.unwrap_or(&no_assets);
+                let description = comrak::markdown_to_html(&self.description, &Default::default());
+                match &assets.show_notes {
+                    Some(notes) => {
+                        add_text_element(writer, "description", &crate::notes::plain_text(notes));
+                        add_cdata_element(writer, "content:encoded", notes);
+                    }
+                    None => add_cdata_element(writer, "description", &description),
+                }
+                add_cdata_element(writer, "itunes:summary", &description);
                 add_text_element(
                     writer,
                     "itunes:duration",
-                    &format!("{}", self.media.duration),
+                    &format!("{}", self.media.duration.0),
                 );
 
                 add_text_element(
@@ -164,18 +569,280 @@ impl XmlOutput for Episode {
                     "itunes:season",
                     &format!("{}", self.season),
                 );
-                add_text_element(
-                    writer,
-                    "itunes:episode",
-                    &format!("{}", self.episode_number),
-                );
+                // Episodes numbered 0 aren't numbered, see `Numbering::None`
+                if self.episode_number > 0 {
+                    add_text_element(
+                        writer,
+                        "itunes:episode",
+                        &format!("{}", self.episode_number),
+                    );
+                }
 
                 let image: &str = &self.image;
                 writer
                     .create_element("itunes:image").with_attribute(("href", image)).write_empty().ok();
                 add_text_element(writer, "itunes:title", &self.title);
+                add_text_element(writer, "itunes:episodeType", self.episode_type.as_str());
+                if let Some(explicit) = self.explicit {
+                    add_text_element(writer, "itunes:explicit", if explicit { "Yes" } else { "No" });
+                }
+                add_keywords(writer, &self.keywords);
+
+                add_people(writer, &self.hosts, &self.guests);
+
+                for transcript in &assets.transcripts {
+                    let mut element = writer
+                        .create_element("podcast:transcript")
+                        .with_attribute(("url", transcript.url.as_str()))
+                        .with_attribute(("type", transcript.mime_type.as_str()));
+                    if let Some(language) = &transcript.language {
+                        element = element.with_attribute(("language", language.as_str()));
+                    }
+                    element.write_empty().ok();
+                }
+                if let Some(url) = &assets.chapters_url {
+                    writer
+                        .create_element("podcast:chapters")
+                        .with_attribute(("url", url.as_str()))
+                        .with_attribute(("type", crate::chapters::MIME_TYPE))
+                        .write_empty()
+                        .ok();
+                }
+                for soundbite in &assets.soundbites {
+                    writer
+                        .create_element("podcast:soundbite")
+                        .with_attribute(("startTime", soundbite.start.to_string().as_str()))
+                        .with_attribute(("duration", soundbite.duration.to_string().as_str()))
+                        .write_text_content(BytesText::new(&soundbite.title))
+                        .ok();
+                }
+
+                for extra in extras {
+                    add_extra_element(writer, extra);
+                }
                 Ok(())
             })
             .ok();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(channel: &ChannelDetails, options: &FeedOptions) -> String {
+        generate_podcast_xml(channel, &[Episode::make_test("first")], options).unwrap()
+    }
+
+    /// The `<item>` of the episode titled `title`.
+    fn item<'a>(xml: &'a str, title: &str) -> &'a str {
+        let title = format!("<title>{}</title>", title);
+        xml.split("<item>")
+            .skip(1)
+            .find(|item| item.contains(&title))
+            .expect("no item with that title")
+    }
+
+    #[test]
+    fn podcast_guid_follows_the_specification() {
+        assert_eq!(
+            podcast_guid("https://mp3s.nashownotes.com/pc20rss.xml"),
+            "917393e3-1b1e-5cef-ace4-edaa54e1f810"
+        );
+        assert_eq!(
+            podcast_guid("http://mp3s.nashownotes.com/pc20rss.xml/"),
+            podcast_guid("https://mp3s.nashownotes.com/pc20rss.xml")
+        );
+    }
+
+    #[test]
+    fn podcast_guid_is_derived_from_the_feed_url() {
+        let options = FeedOptions {
+            podcast_guid: Some(podcast_guid("https://mp3s.nashownotes.com/pc20rss.xml")),
+            ..Default::default()
+        };
+        let xml = render(&ChannelDetails::make_test(), &options);
+        assert!(xml.contains("<podcast:guid>917393e3-1b1e-5cef-ace4-edaa54e1f810</podcast:guid>"));
+        assert!(xml.contains(r#"xmlns:podcast="https://podcastindex.org/namespace/1.0""#));
+    }
+
+    #[test]
+    fn configured_podcast_guid_wins() {
+        let mut channel = ChannelDetails::make_test();
+        channel.podcast_guid = Some("moved-guid".to_owned());
+        let options = FeedOptions {
+            podcast_guid: Some(podcast_guid("https://example.com/podcast.xml")),
+            ..Default::default()
+        };
+        let xml = render(&channel, &options);
+        assert!(xml.contains("<podcast:guid>moved-guid</podcast:guid>"));
+        assert_eq!(xml.matches("<podcast:guid>").count(), 1);
+    }
+
+    #[test]
+    fn locked_and_funding_are_emitted_when_set() {
+        let xml = render(&ChannelDetails::make_test(), &FeedOptions::default());
+        assert!(!xml.contains("podcast:guid"));
+        assert!(!xml.contains("podcast:locked"));
+        assert!(!xml.contains("podcast:funding"));
+
+        let mut channel = ChannelDetails::make_test();
+        channel.locked = Some(true);
+        channel.funding = vec![FundingLink {
+            url: "https://example.com/support".to_owned(),
+            title: "Support the show".to_owned(),
+        }];
+        let xml = render(&channel, &FeedOptions::default());
+        assert!(xml.contains(r#"<podcast:locked owner="email">yes</podcast:locked>"#));
+        assert!(xml.contains(
+            r#"<podcast:funding url="https://example.com/support">Support the show</podcast:funding>"#
+        ));
+    }
+
+    #[test]
+    fn transcripts_are_linked_from_their_episode() {
+        let assets = ItemAssets {
+            transcripts: vec![
+                TranscriptLink {
+                    url: "https://example.com/first.vtt".to_owned(),
+                    mime_type: "text/vtt".to_owned(),
+                    language: Some("en".to_owned()),
+                },
+                TranscriptLink {
+                    url: "https://example.com/first.srt".to_owned(),
+                    mime_type: "application/srt".to_owned(),
+                    language: None,
+                },
+            ],
+            ..Default::default()
+        };
+        let mut options = FeedOptions::default();
+        options.item_assets.insert("first".to_owned(), assets);
+        let episodes = [Episode::make_test("first"), Episode::make_test("second")];
+        let xml = generate_podcast_xml(&ChannelDetails::make_test(), &episodes, &options).unwrap();
+        let first = item(&xml, "first");
+        assert!(first.contains(
+            r#"<podcast:transcript url="https://example.com/first.vtt" type="text/vtt" language="en"/>"#
+        ));
+        assert!(first.contains(
+            r#"<podcast:transcript url="https://example.com/first.srt" type="application/srt"/>"#
+        ));
+        assert!(!item(&xml, "second").contains("podcast:transcript"));
+    }
+
+    #[test]
+    fn chapters_are_linked_from_their_episode() {
+        let assets = ItemAssets {
+            chapters_url: Some("https://example.com/first.chapters.json".to_owned()),
+            ..Default::default()
+        };
+        let mut options = FeedOptions::default();
+        options.item_assets.insert("first".to_owned(), assets);
+        let episodes = [Episode::make_test("first"), Episode::make_test("second")];
+        let xml = generate_podcast_xml(&ChannelDetails::make_test(), &episodes, &options).unwrap();
+        assert!(item(&xml, "first").contains(
+            r#"<podcast:chapters url="https://example.com/first.chapters.json" type="application/json+chapters"/>"#
+        ));
+        assert!(!item(&xml, "second").contains("podcast:chapters"));
+    }
+
+    #[test]
+    fn hosts_and_guests_are_credited() {
+        let mut channel = ChannelDetails::make_test();
+        channel.hosts = vec![Person {
+            name: "Ann Host".to_owned(),
+            role: None,
+            href: Some("https://example.com/ann".to_owned()),
+            img: Some("https://example.com/ann.jpg".to_owned()),
+        }];
+        let mut episode = Episode::make_test("first");
+        episode.guests = vec![Person {
+            name: "Bo Guest".to_owned(),
+            role: Some("author".to_owned()),
+            href: None,
+            img: None,
+        }];
+        let options = FeedOptions::default();
+        let xml = generate_podcast_xml(&channel, &[episode], &options).unwrap();
+        let (channel, first) = xml.split_once("<item>").unwrap();
+        assert!(channel.contains(
+            r#"<podcast:person role="host" href="https://example.com/ann" img="https://example.com/ann.jpg">Ann Host</podcast:person>"#
+        ));
+        assert!(first.contains(r#"<podcast:person role="author">Bo Guest</podcast:person>"#));
+        assert!(!first.contains("Ann Host"));
+    }
+
+    #[test]
+    fn episodes_set_their_type_and_explicit_flag() {
+        let mut trailer = Episode::make_test("trailer");
+        trailer.episode_type = EpisodeType::Trailer;
+        trailer.explicit = Some(false);
+        let episodes = [Episode::make_test("full"), trailer];
+        let options = FeedOptions::default();
+        let xml = generate_podcast_xml(&ChannelDetails::make_test(), &episodes, &options).unwrap();
+
+        let full = item(&xml, "full");
+        assert!(full.contains("<itunes:episodeType>full</itunes:episodeType>"));
+        assert!(!full.contains("itunes:explicit"));
+        let trailer = item(&xml, "trailer");
+        assert!(trailer.contains("<itunes:episodeType>trailer</itunes:episodeType>"));
+        assert!(trailer.contains("<itunes:explicit>No</itunes:explicit>"));
+    }
+
+    #[test]
+    fn subcategories_are_nested_under_their_category() {
+        let mut channel = ChannelDetails::make_test();
+        channel.categories = vec![
+            "Society & Culture > Documentary".to_owned(),
+            "Technology".to_owned(),
+            "Society & Culture > Philosophy".to_owned(),
+        ];
+        let xml = render(&channel, &FeedOptions::default());
+        let compact: String = xml.lines().map(str::trim).collect();
+        assert!(compact.contains(concat!(
+            r#"<itunes:category text="Society &amp; Culture">"#,
+            r#"<itunes:category text="Documentary"/>"#,
+            r#"<itunes:category text="Philosophy"/>"#,
+            "</itunes:category>",
+            r#"<itunes:category text="Technology"/>"#,
+        )));
+        assert!(!xml.contains(r#"text="Fiction""#));
+    }
+
+    #[test]
+    fn language_copyright_and_type_come_from_the_channel() {
+        let xml = render(&ChannelDetails::make_test(), &FeedOptions::default());
+        assert!(xml.contains("<language>en-us</language>"));
+        assert!(xml.contains("<itunes:type>Serial</itunes:type>"));
+
+        let mut channel = ChannelDetails::make_test();
+        channel.language = "de".to_owned();
+        channel.copyright = "© 2023 Example".to_owned();
+        channel.podcast_type = PodcastType::Episodic;
+        let xml = render(&channel, &FeedOptions::default());
+        assert!(xml.contains("<language>de</language>"));
+        assert!(xml.contains("<copyright>© 2023 Example</copyright>"));
+        assert!(xml.contains("<itunes:type>Episodic</itunes:type>"));
+    }
+
+    #[test]
+    fn trailers_are_published_with_the_channel() {
+        let mut channel = ChannelDetails::make_test();
+        channel.trailers = vec![ChannelTrailer {
+            title: "Season two is coming".to_owned(),
+            url: "https://example.com/trailer-2.m4a".to_owned(),
+            released_at: "2022-09-01T12:00:00Z".parse().unwrap(),
+            bytes: ByteSize(5000),
+            season: Some(2),
+            mime_type: None,
+        }];
+        let xml = render(&channel, &FeedOptions::default());
+        let (channel, first) = xml.split_once("<item>").unwrap();
+        assert!(channel.contains(concat!(
+            r#"<podcast:trailer pubdate="Thu, 01 Sep 2022 12:00:00 +0000" "#,
+            r#"url="https://example.com/trailer-2.m4a" length="5000" type="audio/x-m4a" "#,
+            r#"season="2">Season two is coming</podcast:trailer>"#,
+        )));
+        assert!(!first.contains("podcast:trailer"));
+    }
+}
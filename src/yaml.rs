@@ -0,0 +1,405 @@
+//! Edit YAML files in place. Only the entries whose values changed are
+//! rewritten, so comments, key order, and formatting everywhere else
+//! survive. When the layout is more than this line editor understands, the
+//! whole document is serialized again instead, with a warning.
+
+use crate::i18n::t;
+use crate::CliError;
+use serde::Serialize;
+use serde_yaml::{Mapping, Value};
+use std::path::Path;
+
+/// The text the YAML file at `path` should have once it holds `value`. A
+/// file that doesn't exist yet is serialized from scratch.
+pub fn updated_text<T: Serialize>(path: &Path, value: &T) -> Result<String, CliError> {
+    let new = serde_yaml::to_value(value)?;
+    if !path.exists() {
+        return Ok(serde_yaml::to_string(&new)?);
+    }
+
+    let original = String::from_utf8_lossy(&crate::crypto::read(path)?).into_owned();
+    update(&original, &new)
+}
+
+/// Edit `original` so it parses to `new`.
+pub fn update(original: &str, new: &Value) -> Result<String, CliError> {
+    let old: Value = match serde_yaml::from_str(original) {
+        Ok(old) => old,
+        Err(_) => return Ok(serde_yaml::to_string(new)?),
+    };
+    if without_nulls(old.clone()) == without_nulls(new.clone()) {
+        return Ok(original.to_owned());
+    }
+
+    if let (Value::Mapping(old), Value::Mapping(new_mapping)) = (&old, new) {
+        let mut lines: Vec<String> = original.lines().map(|line| line.to_owned()).collect();
+        let end = lines.len();
+        if edit_mapping(&mut lines, 0, end, 0, old, new_mapping) {
+            let mut edited = lines.join("\n");
+            if original.ends_with('\n') {
+                edited.push('\n');
+            }
+
+            // Anything the editor got wrong shows up as a different document
+            let reparsed = serde_yaml::from_str::<Value>(&edited)
+                .ok()
+                .map(without_nulls);
+            if reparsed == Some(without_nulls(new.clone())) {
+                return Ok(edited);
+            }
+        }
+    }
+
+    eprintln!("{}", t!("yaml-rewritten"));
+    Ok(serde_yaml::to_string(new)?)
+}
+
+/// Drop `null` entries from mappings, since a missing key reads the same.
+pub fn without_nulls(value: Value) -> Value {
+    match value {
+        Value::Mapping(mapping) => Value::Mapping(
+            mapping
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key, without_nulls(value)))
+                .collect::<Mapping>(),
+        ),
+        Value::Sequence(items) => Value::Sequence(items.into_iter().map(without_nulls).collect()),
+        other => other,
+    }
+}
+
+/// A `key: value` line of a block mapping, and the lines of its value.
+struct Entry {
+    key: String,
+    line: usize,
+    /// One past the last line of the value. Comments and blank lines after
+    /// the value belong to the next entry.
+    end: usize,
+    /// Everything up to the inline value
+    prefix: String,
+    /// The inline value, empty when it is on the following lines
+    value: String,
+    /// A trailing comment, with the whitespace before it
+    comment: String,
+}
+
+/// Edit the block mapping on lines `start..end`, indented by `indent`.
+/// Returns false, without touching `lines`, when they aren't a mapping.
+fn edit_mapping(
+    lines: &mut Vec<String>,
+    start: usize,
+    end: usize,
+    indent: usize,
+    old: &Mapping,
+    new: &Mapping,
+) -> bool {
+    let entries = match entries(lines, start, end, indent) {
+        Some(entries) => entries,
+        None => return false,
+    };
+
+    // New keys go after the last entry, so add them before any line numbers
+    // above them move
+    let insert_at = entries.last().map(|entry| entry.end).unwrap_or(end);
+    let mut added = Vec::new();
+    for (key, value) in new {
+        let exists = entries.iter().any(|entry| key_matches(key, &entry.key));
+        if !exists && !value.is_null() {
+            added.extend(entry_lines(key, value, indent));
+        }
+    }
+    lines.splice(insert_at..insert_at, added);
+
+    for entry in entries.iter().rev() {
+        let old_value = lookup(old, &entry.key).map(|(_, value)| value);
+        match lookup(new, &entry.key) {
+            None => {
+                lines.drain(entry.line..entry.end);
+            }
+            Some((_, new_value)) if Some(new_value) == old_value => {}
+            Some((key, new_value)) => {
+                if !edit_entry(lines, entry, indent, old_value, new_value) {
+                    lines.splice(entry.line..entry.end, entry_lines(key, new_value, indent));
+                }
+            }
+        }
+    }
+
+    true
+}
+
+/// Change one entry's value in place, when it is a scalar replaced by a
+/// scalar or a nested mapping.
+fn edit_entry(
+    lines: &mut Vec<String>,
+    entry: &Entry,
+    indent: usize,
+    old: Option<&Value>,
+    new: &Value,
+) -> bool {
+    match (old, new) {
+        (Some(Value::Mapping(old)), Value::Mapping(new)) if entry.value.is_empty() => {
+            let child_indent = (entry.line + 1..entry.end)
+                .filter(|index| !is_blank_or_comment(&lines[*index]))
+                .map(|index| indentation(&lines[index]))
+                .next();
+            match child_indent {
+                Some(child_indent) if child_indent > indent => {
+                    edit_mapping(lines, entry.line + 1, entry.end, child_indent, old, new)
+                }
+                _ => false,
+            }
+        }
+        (Some(old), new) if is_scalar(old) && is_scalar(new) => {
+            let inline = entry.end == entry.line + 1
+                && !entry.value.is_empty()
+                && !entry.value.starts_with(['|', '>', '&', '*', '!', '[', '{']);
+            let text = match serde_yaml::to_string(new) {
+                Ok(text) => text.trim_end_matches('\n').to_owned(),
+                Err(_) => return false,
+            };
+            if !inline || text.contains('\n') {
+                return false;
+            }
+
+            lines[entry.line] = format!("{}{}{}", entry.prefix, text, entry.comment);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// The entries of the block mapping on lines `start..end`, or `None` when
+/// those lines are something else, like a sequence.
+fn entries(lines: &[String], start: usize, end: usize, indent: usize) -> Option<Vec<Entry>> {
+    let mut entries: Vec<Entry> = Vec::new();
+    for (offset, line) in lines[start..end].iter().enumerate() {
+        let index = start + offset;
+        if is_blank_or_comment(line) {
+            continue;
+        }
+
+        let line_indent = indentation(line);
+        let text = &line[line_indent..];
+        if line_indent < indent {
+            return None;
+        }
+        if line_indent > indent {
+            // Part of the previous entry's value
+            if entries.is_empty() {
+                return None;
+            }
+            continue;
+        }
+        if text == "---" && entries.is_empty() {
+            continue;
+        }
+        if text == "-" || text.starts_with("- ") {
+            // serde_yaml writes sequences at the same indent as their key
+            if entries.is_empty() {
+                return None;
+            }
+            continue;
+        }
+
+        let (key, after_colon) = parse_key(text)?;
+        let rest = &text[after_colon..];
+        let value_start = line_indent + after_colon + (rest.len() - rest.trim_start().len());
+        let (value, comment) = split_comment(&line[value_start..]);
+        entries.push(Entry {
+            key,
+            line: index,
+            end,
+            prefix: line[..value_start].to_owned(),
+            value: value.to_owned(),
+            comment: comment.to_owned(),
+        });
+    }
+
+    for index in 0..entries.len() {
+        let mut end = entries.get(index + 1).map(|next| next.line).unwrap_or(end);
+        while end > entries[index].line + 1 && is_blank_or_comment(&lines[end - 1]) {
+            end -= 1;
+        }
+        entries[index].end = end;
+    }
+
+    Some(entries)
+}
+
+/// The key at the start of `text` and the offset just past its colon.
+fn parse_key(text: &str) -> Option<(String, usize)> {
+    let (key, key_end) = match text.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let close = text[1..].find(quote)? + 2;
+            let key: String = serde_yaml::from_str(&text[..close]).ok()?;
+            (key, close)
+        }
+        '#' | '{' | '[' | '?' | '&' | '*' | '!' | '|' | '>' => return None,
+        _ => {
+            let colon = text
+                .match_indices(':')
+                .map(|(index, _)| index)
+                .find(|index| {
+                    text[index + 1..].is_empty() || text[index + 1..].starts_with([' ', '\t'])
+                })?;
+            (text[..colon].trim_end().to_owned(), colon)
+        }
+    };
+
+    if !text[key_end..].starts_with(':') {
+        return None;
+    }
+    Some((key, key_end + 1))
+}
+
+/// Splits `value # comment` into the value and the comment, keeping the
+/// whitespace before the comment with it.
+fn split_comment(text: &str) -> (&str, &str) {
+    let search_from = match text.chars().next() {
+        Some(quote @ ('"' | '\'')) => text[1..].find(quote).map(|i| i + 2).unwrap_or(text.len()),
+        _ => 0,
+    };
+    let comment = text[search_from..]
+        .match_indices('#')
+        .map(|(index, _)| index + search_from)
+        .find(|index| *index == 0 || text[..*index].ends_with([' ', '\t']));
+
+    match comment {
+        Some(index) => {
+            let value = text[..index].trim_end();
+            (value, &text[value.len()..])
+        }
+        None => (text.trim_end(), ""),
+    }
+}
+
+/// `key: value` serialized at `indent`.
+fn entry_lines(key: &Value, value: &Value, indent: usize) -> Vec<String> {
+    let mut mapping = Mapping::new();
+    mapping.insert(key.clone(), value.clone());
+    serde_yaml::to_string(&Value::Mapping(mapping))
+        .unwrap_or_default()
+        .lines()
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", " ".repeat(indent), line)
+            }
+        })
+        .collect()
+}
+
+fn lookup<'a>(mapping: &'a Mapping, key: &str) -> Option<(&'a Value, &'a Value)> {
+    mapping
+        .iter()
+        .find(|(candidate, _)| key_matches(candidate, key))
+}
+
+fn key_matches(key: &Value, text: &str) -> bool {
+    match key {
+        Value::String(key) => key == text,
+        other => crate::compare::scalar(other) == text,
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(
+        value,
+        Value::Mapping(_) | Value::Sequence(_) | Value::Tagged(_)
+    )
+}
+
+fn is_blank_or_comment(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edited(original: &str, new: &str) -> String {
+        let new: Value = serde_yaml::from_str(new).unwrap();
+        let edited = update(original, &new).unwrap();
+        let reparsed: Value = serde_yaml::from_str(&edited).unwrap();
+        assert_eq!(without_nulls(reparsed), without_nulls(new));
+        edited
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let original = "# Show settings\ntitle: Old title # shown in apps\nexplicit: false\n";
+        assert_eq!(
+            edited(original, "title: New title\nexplicit: false"),
+            "# Show settings\ntitle: New title # shown in apps\nexplicit: false\n"
+        );
+    }
+
+    #[test]
+    fn unchanged_is_untouched() {
+        let original = "title:   Show # spaced out\n";
+        assert_eq!(edited(original, "title: Show"), original);
+    }
+
+    #[test]
+    fn edits_nested_mappings() {
+        let original = "publishing:\n  # where feeds go\n  bucket: old # the bucket\n  prefix: podcast\ntitle: Show\n";
+        assert_eq!(
+            edited(
+                original,
+                "publishing:\n  bucket: new\n  prefix: podcast\ntitle: Show"
+            ),
+            "publishing:\n  # where feeds go\n  bucket: new # the bucket\n  prefix: podcast\ntitle: Show\n"
+        );
+    }
+
+    #[test]
+    fn replaces_sequences() {
+        let original = "# Keywords\nkeywords:\n- news\n- tech\ntitle: Show # name\n";
+        assert_eq!(
+            edited(original, "keywords: [news, science]\ntitle: Show"),
+            "# Keywords\nkeywords:\n- news\n- science\ntitle: Show # name\n"
+        );
+    }
+
+    #[test]
+    fn edits_quoted_keys() {
+        let original = "\"title\": Old # c\n'link': https://example.com\n";
+        assert_eq!(
+            edited(original, "title: New\nlink: https://example.com"),
+            "\"title\": New # c\n'link': https://example.com\n"
+        );
+    }
+
+    #[test]
+    fn replaces_block_scalars() {
+        let original = "description: |\n  First line\n  Second line\n# Artwork\nimage: cover.jpg\n";
+        let edited = edited(original, "description: Only line\nimage: cover.jpg");
+        assert!(edited.ends_with("# Artwork\nimage: cover.jpg\n"));
+        assert!(!edited.contains("Second line"));
+    }
+
+    #[test]
+    fn adds_and_removes_keys() {
+        let original = "title: Show\nlink: https://example.com # old link\n# trailing\n";
+        assert_eq!(
+            edited(original, "title: Show\nexplicit: true"),
+            "title: Show\nexplicit: true\n# trailing\n"
+        );
+    }
+
+    #[test]
+    fn serializes_what_it_cannot_edit() {
+        let original = "{title: Old, explicit: false} # flow style\n";
+        let new = "title: New\nexplicit: false";
+        let expected = serde_yaml::to_string(&serde_yaml::from_str::<Value>(new).unwrap()).unwrap();
+        assert_eq!(edited(original, new), expected);
+    }
+}
@@ -0,0 +1,59 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<!-- Shows podcast.xml as a page in browsers, with how to subscribe and the
+     latest episodes. Podcast apps ignore it. -->
+<xsl:stylesheet version="1.0"
+    xmlns:xsl="http://www.w3.org/1999/XSL/Transform"
+    xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+    <xsl:output method="html" encoding="UTF-8" indent="yes"/>
+
+    <xsl:template match="/rss/channel">
+        <html lang="{language}">
+            <head>
+                <meta charset="utf-8"/>
+                <meta name="viewport" content="width=device-width, initial-scale=1"/>
+                <title><xsl:value-of select="title"/></title>
+                <style>
+                    body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Helvetica, Arial, sans-serif; max-width: 48rem; margin: 2rem auto; padding: 0 1rem; color: #222; line-height: 1.5; }
+                    header { display: flex; gap: 1.5rem; align-items: flex-start; margin-bottom: 2rem; }
+                    header img { width: 10rem; height: 10rem; border-radius: 0.5rem; object-fit: cover; }
+                    h1 { margin: 0 0 0.25rem 0; }
+                    .meta { color: #777; font-size: 0.9rem; }
+                    .subscribe { background: #f4f4f4; padding: 0.75rem; border-radius: 0.25rem; }
+                    .subscribe code { word-break: break-all; }
+                    article { border-top: 1px solid #ddd; padding: 1.25rem 0; }
+                    article h2 { margin: 0 0 0.25rem 0; font-size: 1.2rem; }
+                    audio { width: 100%; margin: 0.5rem 0; }
+                </style>
+            </head>
+            <body>
+                <header>
+                    <img src="{itunes:image/@href}" alt="{title} artwork"/>
+                    <div>
+                        <h1><xsl:value-of select="title"/></h1>
+                        <p class="meta">By <xsl:value-of select="itunes:owner/itunes:name"/> &#183; <xsl:value-of select="count(item)"/> episode(s)</p>
+                        <xsl:if test="link">
+                            <p><a href="{link}"><xsl:value-of select="link"/></a></p>
+                        </xsl:if>
+                    </div>
+                </header>
+                <p><xsl:value-of select="itunes:subtitle"/></p>
+                <div class="subscribe">
+                    <p><strong>This is a podcast feed.</strong> To subscribe, copy this page's address into your podcast app.</p>
+                </div>
+                <main>
+                    <xsl:for-each select="item">
+                        <article>
+                            <h2><xsl:value-of select="title"/></h2>
+                            <p class="meta">
+                                <xsl:value-of select="pubDate"/>
+                                <xsl:if test="itunes:duration"> &#183; <xsl:value-of select="itunes:duration"/></xsl:if>
+                            </p>
+                            <audio controls="controls" preload="none" src="{enclosure/@url}"></audio>
+                            <p><xsl:value-of select="itunes:subtitle"/></p>
+                        </article>
+                    </xsl:for-each>
+                </main>
+            </body>
+        </html>
+    </xsl:template>
+</xsl:stylesheet>